@@ -4,10 +4,12 @@ import { type PropsWithChildren, Suspense } from "react"
 import { ErrorBoundary } from "react-error-boundary"
 import { useGetMeSuspense } from "../../api/user/user.ts"
 import { UserContext } from "../context/user.ts"
+import { usePresenceHeartbeat } from "../hooks/usePresenceHeartbeat.ts"
 import { LoginScreen } from "./LoginScreen.tsx"
 
 const AuthUserContextProvider = ({ children }: PropsWithChildren) => {
   const { data: { data } } = useGetMeSuspense()
+  usePresenceHeartbeat()
 
   return (
     <UserContext value={data}>
@@ -0,0 +1,28 @@
+import { useEffect } from "react"
+
+const HEARTBEAT_INTERVAL_MS = 60_000
+
+const sendHeartbeat = () => {
+  if (document.visibilityState !== "visible") {
+    return
+  }
+
+  // Presence is best-effort, so failures are ignored until the next heartbeat
+  fetch("/api/v1/presence/heartbeat", { method: "POST" }).catch(() => {})
+}
+
+/**
+ * Tells the server that the user is online every minute while the page is visible.
+ */
+export const usePresenceHeartbeat = () => {
+  useEffect(() => {
+    sendHeartbeat()
+    const timer = setInterval(sendHeartbeat, HEARTBEAT_INTERVAL_MS)
+    document.addEventListener("visibilitychange", sendHeartbeat)
+
+    return () => {
+      clearInterval(timer)
+      document.removeEventListener("visibilitychange", sendHeartbeat)
+    }
+  }, [])
+}
@@ -8,7 +8,7 @@ interface UserAvatarProps {
 
 export const UserAvatar = ({ userId, username }: UserAvatarProps) => {
   const [isLoading, setIsLoading] = useState(true)
-  const src = `/api/v1/users/${userId}/icon`
+  const src = `/api/v1/users/${userId}/icon?size=thumb`
 
   return (
     <>
@@ -0,0 +1,54 @@
+//! Embeds build information served by the `/version` endpoint.
+
+use std::{
+    env,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // Builds without a git checkout (e.g. Docker) pass the commit explicitly
+    let git_commit = env::var("GIT_COMMIT")
+        .ok()
+        .filter(|commit| !commit.is_empty())
+        .or_else(git_head);
+    if let Some(commit) = git_commit {
+        println!("cargo:rustc-env=TWITTRA_GIT_COMMIT={commit}");
+    }
+
+    // Reproducible builds pin the timestamp
+    let timestamp = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=TWITTRA_BUILD_TIMESTAMP={timestamp}");
+}
+
+fn git_head() -> Option<String> {
+    let git_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").ok()?).join("../../.git");
+    if !git_dir.exists() {
+        return None;
+    }
+    // Rebuild when the checked out commit changes
+    println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+    println!("cargo:rerun-if-changed={}", git_dir.join("refs").display());
+
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|commit| commit.trim().to_string())
+}
@@ -0,0 +1,197 @@
+//! Versions of the HTTP API, each served under its own root.
+//!
+//! Breaking changes ship under the next version while clients migrate. Once deprecated in the
+//! config, the older version keeps working but announces its deprecation with the `Deprecation`
+//! (RFC 9745) and `Sunset` (RFC 8594) headers, along with a link to its successor.
+
+use crate::config::ApiConfig;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use http::{HeaderName, HeaderValue, header::LINK};
+use std::sync::Arc;
+use time::{OffsetDateTime, UtcOffset, format_description};
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    pub fn root(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/api/v1",
+            ApiVersion::V2 => "/api/v2",
+        }
+    }
+
+    /// Where the OpenAPI document of this version is served. v1 keeps the path it had before
+    /// versions were introduced.
+    pub fn openapi_path(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/docs/openapi.json",
+            ApiVersion::V2 => "/docs/v2/openapi.json",
+        }
+    }
+
+    /// The headers to announce that this version is deprecated with, or `None` if it isn't
+    /// deprecated in `config`.
+    pub fn deprecation(self, config: &ApiConfig) -> Option<Deprecation> {
+        match self {
+            ApiVersion::V1 => config
+                .v1_deprecated_at
+                .map(|at| Deprecation::new(at, config.v1_sunset_at, ApiVersion::V2)),
+            ApiVersion::V2 => None,
+        }
+    }
+}
+
+/// The headers added to every response of a deprecated version.
+#[derive(Clone, Debug)]
+pub struct Deprecation {
+    deprecation: HeaderValue,
+    /// Omitted until a date to remove the version is decided.
+    sunset: Option<HeaderValue>,
+    link: HeaderValue,
+}
+
+impl Deprecation {
+    pub fn new(
+        deprecated_at: OffsetDateTime,
+        sunset_at: Option<OffsetDateTime>,
+        successor: ApiVersion,
+    ) -> Self {
+        Self {
+            deprecation: HeaderValue::from_str(&format!("@{}", deprecated_at.unix_timestamp()))
+                .expect("a timestamp should be a valid header value"),
+            sunset: sunset_at.map(|at| {
+                HeaderValue::from_str(&http_date(at))
+                    .expect("an HTTP date should be a valid header value")
+            }),
+            link: HeaderValue::from_str(&format!(
+                "<{}>; rel=\"successor-version\"",
+                successor.root()
+            ))
+            .expect("a link should be a valid header value"),
+        }
+    }
+}
+
+/// Formats a date as an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn http_date(at: OffsetDateTime) -> String {
+    let format = format_description::parse(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT",
+    )
+    .expect("the HTTP date format should be valid");
+
+    at.to_offset(UtcOffset::UTC)
+        .format(&format)
+        .expect("a date should be formattable as an HTTP date")
+}
+
+/// Middleware adding the deprecation headers to responses.
+pub async fn deprecate(
+    State(deprecation): State<Arc<Deprecation>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert(DEPRECATION, deprecation.deprecation.clone());
+    if let Some(sunset) = &deprecation.sunset {
+        headers.insert(SUNSET, sunset.clone());
+    }
+    headers.append(LINK, deprecation.link.clone());
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::TestAppBuilder;
+    use axum::{Router, body::Body, middleware, routing::get};
+    use http::{HeaderMap, StatusCode};
+    use tower::ServiceExt;
+
+    async fn headers(deprecation: Deprecation) -> HeaderMap {
+        let app =
+            Router::new()
+                .route("/", get(|| async { "ok" }))
+                .layer(middleware::from_fn_with_state(
+                    Arc::new(deprecation),
+                    deprecate,
+                ));
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+
+        app.oneshot(req).await.unwrap().headers().clone()
+    }
+
+    #[tokio::test]
+    async fn deprecated_versions_announce_their_sunset_and_successor() {
+        let deprecated_at = OffsetDateTime::from_unix_timestamp(784_111_777).unwrap();
+        let sunset_at = OffsetDateTime::from_unix_timestamp(784_111_777 + 24 * 60 * 60).unwrap();
+        let headers = headers(Deprecation::new(
+            deprecated_at,
+            Some(sunset_at),
+            ApiVersion::V2,
+        ))
+        .await;
+
+        assert_eq!(headers[DEPRECATION], "@784111777");
+        assert_eq!(headers[SUNSET], "Mon, 07 Nov 1994 08:49:37 GMT");
+        assert_eq!(headers[LINK], "</api/v2>; rel=\"successor-version\"");
+    }
+
+    #[test]
+    fn v1_is_deprecated_once_configured() {
+        assert!(ApiVersion::V1.deprecation(&ApiConfig::default()).is_none());
+
+        let config = ApiConfig {
+            v1_deprecated_at: Some(OffsetDateTime::UNIX_EPOCH),
+            v1_sunset_at: None,
+        };
+        assert!(ApiVersion::V1.deprecation(&config).is_some());
+        assert!(ApiVersion::V2.deprecation(&config).is_none());
+    }
+
+    #[tokio::test]
+    async fn sunset_is_omitted_until_decided() {
+        let config = ApiConfig {
+            v1_deprecated_at: Some(OffsetDateTime::UNIX_EPOCH),
+            v1_sunset_at: None,
+        };
+        let headers = headers(ApiVersion::V1.deprecation(&config).unwrap()).await;
+
+        assert!(headers.contains_key(DEPRECATION));
+        assert!(!headers.contains_key(SUNSET));
+    }
+
+    #[tokio::test]
+    async fn every_version_is_served() {
+        let app = TestAppBuilder::new().build();
+
+        for version in ApiVersion::ALL {
+            let req = Request::builder()
+                .uri(format!("{}/version", version.root()))
+                .body(Body::empty())
+                .unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK, "{version:?}");
+            assert_eq!(
+                res.headers().contains_key(DEPRECATION),
+                version.deprecation(&ApiConfig::default()).is_some(),
+                "{version:?}"
+            );
+        }
+    }
+}
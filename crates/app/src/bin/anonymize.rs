@@ -0,0 +1,46 @@
+//! Copies production data into a staging database with handles hashed, message contents
+//! scrambled and tokens dropped. Everything in the staging database is replaced.
+//!
+//! ```sh
+//! cargo run -p app --bin anonymize -- \
+//!     --source mysql://.../twittra --target mysql://.../twittra_staging --salt "$SALT"
+//! ```
+//!
+//! The URLs can also be given in `SOURCE_DATABASE_URL` and `STAGING_DATABASE_URL`, and the salt
+//! in `ANONYMIZE_SALT`. Without a salt, a random one is used and pseudonyms change on every run.
+
+use infra::anonymize;
+use sqlx::MySqlPool;
+use std::{env, error::Error, iter};
+
+const USAGE: &str =
+    "usage: anonymize --source <database URL> --target <database URL> [--salt <salt>]";
+
+fn arg(args: &[String], name: &str, env_name: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1).cloned())
+        .or_else(|| env::var(env_name).ok())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let (Some(source_url), Some(target_url)) = (
+        arg(&args, "--source", "SOURCE_DATABASE_URL"),
+        arg(&args, "--target", "STAGING_DATABASE_URL"),
+    ) else {
+        return Err(USAGE.into());
+    };
+    let salt = arg(&args, "--salt", "ANONYMIZE_SALT")
+        .unwrap_or_else(|| iter::repeat_with(fastrand::alphanumeric).take(32).collect());
+
+    let source = MySqlPool::connect(&source_url).await?;
+    let target = MySqlPool::connect(&target_url).await?;
+
+    for (table, rows) in anonymize::anonymize(&source, &target, &salt).await? {
+        println!("{table}: {rows} rows");
+    }
+
+    Ok(())
+}
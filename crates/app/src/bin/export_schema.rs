@@ -1,7 +1,9 @@
+use app::{ApiVersion, DEFAULT_SESSION_COOKIE_NAME};
 use std::{error::Error, fs};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let (_, openapi) = app::setup_openapi_routes();
+    // The frontend is generated from the version it calls
+    let (_, openapi) = app::setup_openapi_routes(ApiVersion::V1, DEFAULT_SESSION_COOKIE_NAME);
 
     fs::write("api/openapi.json", openapi.to_pretty_json()?)?;
 
@@ -0,0 +1,332 @@
+//! Generates fake messages and reactions over time, for performance testing the crawler,
+//! recommendation scoring and socket fanout. For development only.
+//!
+//! ```sh
+//! # Writes directly into the database, bypassing traQ and the crawler
+//! cargo run -p app --bin loadgen -- db --database-url mysql://... --rate 50
+//! # Posts to a (local!) traQ instance, so that the running server crawls and broadcasts them
+//! cargo run -p app --bin loadgen -- traq --traq-url http://localhost:3000/api/v3 \
+//!     --token <access token> --channel-id <channel ID> --rate 2
+//! ```
+//!
+//! In `db` mode, new messages reach clients only through `/timeline/updates`, since sockets are
+//! notified by the crawler. Use `traq` mode to exercise the whole pipeline.
+
+use domain::{
+    model::{Message, MessageEventKind, Reaction, Stamp, User},
+    repository::Repository,
+};
+use infra::repository::mariadb;
+use sqlx::MySqlPool;
+use std::{
+    collections::VecDeque,
+    env,
+    error::Error,
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use tokio::time::{self as tokio_time, MissedTickBehavior};
+use traq::{
+    apis::{configuration::Configuration, message_api, stamp_api},
+    models::{PostMessageRequest, PostMessageStampRequest},
+};
+use uuid::{Builder, Uuid};
+
+const USAGE: &str = "usage:
+  loadgen db --database-url <URL> [--users 50] [--channels 20] [--stamps 30] [options]
+  loadgen traq --traq-url <URL> --token <token> --channel-id <ID>... [options]
+
+options:
+  --rate <messages per second>    (default: 10)
+  --duration <seconds>            (default: 60)
+  --max-reactions <per message>   (default: 5)";
+
+/// Reactions are added to messages among the latest ones, like on a real instance.
+const RECENT_MESSAGES: usize = 1000;
+const WORDS: &[&str] = &[
+    "rust",
+    "deploy",
+    "review",
+    "lunch",
+    "meeting",
+    "bug",
+    "release",
+    "coffee",
+    "server",
+    "timeline",
+    "stamp",
+    "channel",
+    "weekend",
+    "contest",
+    "design",
+    "music",
+    "game",
+    "train",
+    "homework",
+    "camp",
+    "今日",
+    "明日",
+    "進捗",
+    "ありがとう",
+    "お疲れさまです",
+    "なるほど",
+];
+
+struct Args(Vec<String>);
+
+impl Args {
+    fn values(&self, name: &str) -> Vec<&str> {
+        self.0
+            .windows(2)
+            .filter(|w| w[0] == name)
+            .map(|w| w[1].as_str())
+            .collect()
+    }
+
+    fn value(&self, name: &str) -> Result<&str, Box<dyn Error>> {
+        self.values(name)
+            .first()
+            .copied()
+            .ok_or_else(|| format!("missing {name}\n\n{USAGE}").into())
+    }
+
+    fn number(&self, name: &str, default: usize) -> Result<usize, Box<dyn Error>> {
+        match self.values(name).first() {
+            Some(value) => Ok(value.parse().map_err(|e| format!("invalid {name}: {e}"))?),
+            None => Ok(default),
+        }
+    }
+}
+
+struct Generator {
+    rng: fastrand::Rng,
+    max_reactions: usize,
+}
+
+impl Generator {
+    fn uuid(&mut self) -> Uuid {
+        let mut bytes = [0; 16];
+        self.rng.fill(&mut bytes);
+        Builder::from_random_bytes(bytes).into_uuid()
+    }
+
+    fn content(&mut self) -> String {
+        let len = self.rng.usize(1..30);
+        (0..len)
+            .map(|_| WORDS[self.rng.usize(..WORDS.len())])
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The number of reactions to add per tick, so that every message gets about
+    /// `max_reactions / 2` reactions on average.
+    fn reactions_per_tick(&mut self, rate: usize) -> usize {
+        rate * self.max_reactions / 2
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.rng.usize(..items.len())]
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args(env::args().skip(1).collect());
+    let mut generator = Generator {
+        rng: fastrand::Rng::new(),
+        max_reactions: args.number("--max-reactions", 5)?,
+    };
+    let rate = args.number("--rate", 10)?;
+    let duration = Duration::from_secs(args.number("--duration", 60)? as u64);
+
+    match args.0.first().map(String::as_str) {
+        Some("db") => run_db(&args, &mut generator, rate, duration).await,
+        Some("traq") => run_traq(&args, &mut generator, rate, duration).await,
+        _ => Err(USAGE.into()),
+    }
+}
+
+async fn run_db(
+    args: &Args,
+    generator: &mut Generator,
+    rate: usize,
+    duration: Duration,
+) -> Result<(), Box<dyn Error>> {
+    let pool = MySqlPool::connect(args.value("--database-url")?).await?;
+    let repo = mariadb::new_repository(pool).await?;
+
+    let users: Vec<User> = (0..args.number("--users", 50)?)
+        .map(|i| User {
+            id: generator.uuid(),
+            handle: format!("loadgen-{i}"),
+            display_name: format!("Load Generator {i}"),
+        })
+        .collect();
+    for user in &users {
+        repo.user.save(user).await?;
+    }
+    let channels: Vec<Uuid> = (0..args.number("--channels", 20)?)
+        .map(|_| generator.uuid())
+        .collect();
+    let stamps: Vec<Stamp> = (0..args.number("--stamps", 30)?)
+        .map(|i| Stamp {
+            id: generator.uuid(),
+            name: format!("loadgen-{i}"),
+        })
+        .collect();
+    repo.stamp.save_batch(&stamps).await?;
+
+    let mut recent = VecDeque::with_capacity(RECENT_MESSAGES);
+    run_ticks(duration, async |stats| {
+        let now = OffsetDateTime::now_utc();
+        let messages: Vec<Message> = (0..rate)
+            .map(|_| Message {
+                id: generator.uuid(),
+                user_id: generator.pick(&users).id,
+                channel_id: *generator.pick(&channels),
+                content: generator.content(),
+                created_at: now,
+                updated_at: now,
+                reactions: vec![],
+            })
+            .collect();
+        save(&repo, &messages, MessageEventKind::Added).await?;
+        stats.messages += messages.len();
+        for message in messages {
+            if recent.len() == RECENT_MESSAGES {
+                recent.pop_front();
+            }
+            recent.push_back(message);
+        }
+
+        // Saving a message replaces its reactions, like a refresh by the crawler
+        let mut updated = Vec::new();
+        for _ in 0..generator.reactions_per_tick(rate) {
+            let i = generator.rng.usize(..recent.len());
+            let reaction = Reaction {
+                stamp_id: generator.pick(&stamps).id,
+                stamp_name: None,
+                user_id: generator.pick(&users).id,
+                stamp_count: 1,
+            };
+            let message: &mut Message = &mut recent[i];
+            message.reactions.push(reaction);
+            message.updated_at = now;
+            updated.push(message.clone());
+        }
+        save(&repo, &updated, MessageEventKind::Updated).await?;
+        stats.reactions += updated.len();
+
+        Ok(())
+    })
+    .await
+}
+
+async fn save(
+    repo: &Repository,
+    messages: &[Message],
+    kind: MessageEventKind,
+) -> Result<(), Box<dyn Error>> {
+    repo.message_writer.save_batch(messages).await?;
+    let ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+    repo.message_event.append(&ids, kind).await?;
+
+    Ok(())
+}
+
+async fn run_traq(
+    args: &Args,
+    generator: &mut Generator,
+    rate: usize,
+    duration: Duration,
+) -> Result<(), Box<dyn Error>> {
+    let config = Configuration {
+        base_path: args.value("--traq-url")?.to_string(),
+        oauth_access_token: Some(args.value("--token")?.to_string()),
+        ..Default::default()
+    };
+    let channels = args.values("--channel-id");
+    if channels.is_empty() {
+        return Err(format!("missing --channel-id\n\n{USAGE}").into());
+    }
+    let stamps: Vec<String> = stamp_api::get_stamps(&config, None, None)
+        .await?
+        .into_iter()
+        .take(30)
+        .map(|stamp| stamp.id.to_string())
+        .collect();
+
+    let mut recent = VecDeque::with_capacity(RECENT_MESSAGES);
+    run_ticks(duration, async |stats| {
+        for _ in 0..rate {
+            let request = PostMessageRequest::new(generator.content());
+            let channel_id = *generator.pick(&channels);
+            let message = message_api::post_message(&config, channel_id, Some(request)).await?;
+            stats.messages += 1;
+            if recent.len() == RECENT_MESSAGES {
+                recent.pop_front();
+            }
+            recent.push_back(message.id.to_string());
+        }
+
+        // The token belongs to a single user, so reactions come from that user only
+        if stamps.is_empty() {
+            return Ok(());
+        }
+        for _ in 0..generator.reactions_per_tick(rate) {
+            let message_id = &recent[generator.rng.usize(..recent.len())];
+            let stamp_id = generator.pick(&stamps);
+            message_api::add_message_stamp(
+                &config,
+                message_id,
+                stamp_id,
+                Some(PostMessageStampRequest { count: 1 }),
+            )
+            .await?;
+            stats.reactions += 1;
+        }
+
+        Ok(())
+    })
+    .await
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    messages: usize,
+    reactions: usize,
+}
+
+/// Runs `tick` every second until `duration` has passed, reporting progress every 10 seconds.
+/// Ticks that take longer than a second are reported instead of being made up for.
+async fn run_ticks(
+    duration: Duration,
+    mut tick: impl AsyncFnMut(&mut Stats) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let started_at = Instant::now();
+    let mut interval = tokio_time::interval(Duration::from_secs(1));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut stats = Stats::default();
+    let mut slowest = Duration::ZERO;
+
+    for second in 1..=duration.as_secs() {
+        interval.tick().await;
+
+        let tick_started_at = Instant::now();
+        tick(&mut stats).await?;
+        slowest = slowest.max(tick_started_at.elapsed());
+
+        if second % 10 == 0 || second == duration.as_secs() {
+            println!(
+                "{:>5}s: {} messages, {} reactions, slowest tick {:?}",
+                started_at.elapsed().as_secs(),
+                stats.messages,
+                stats.reactions,
+                slowest
+            );
+            slowest = Duration::ZERO;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,283 @@
+//! Checks that a running instance works end to end, as a verification gate after deploying.
+//!
+//! ```sh
+//! cargo run -p app --features smoke --bin smoke -- --url https://twittra.example.com \
+//!     --token <traQ access token> --message-id <message ID> --stamp-id <stamp ID>
+//! ```
+//!
+//! Signs in with the token, fetches the timeline, then adds and removes the stamp on the message
+//! while waiting for both changes to be delivered over Socket.IO. Use a message in a sandbox
+//! channel, since the stamp is visible to everyone while the check runs. Exits with an error as
+//! soon as a step fails.
+
+use domain::{
+    event::{ConnectPayload, DELTA_PROTOCOL_VERSION, MessageDelta, SocketEvent, SubscribePayload},
+    model::User,
+};
+use futures_util::FutureExt;
+use reqwest::{
+    Client, Method, Response, StatusCode,
+    header::{CONTENT_TYPE, COOKIE, SET_COOKIE},
+};
+use rust_socketio::{
+    Payload,
+    asynchronous::{Client as SocketClient, ClientBuilder},
+};
+use serde_json::Value;
+use std::{env, error::Error, process, time::Duration};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver},
+    time,
+};
+use uuid::Uuid;
+
+const USAGE: &str = "usage:
+  smoke --url <URL> --token <traQ access token> --message-id <ID> --stamp-id <ID> [options]
+
+options:
+  --socket-timeout <seconds>    (default: 90)";
+
+const API_ROOT: &str = "/api/v1";
+const MESSAGE_DELTA_EVENT: &str = "messageDelta";
+/// The crawler notices stamps within its interval of 30 seconds, so this leaves room for a slow
+/// crawl.
+const DEFAULT_SOCKET_TIMEOUT_SECS: u64 = 90;
+
+struct Args(Vec<String>);
+
+impl Args {
+    fn value(&self, name: &str) -> Option<&str> {
+        self.0
+            .windows(2)
+            .find(|w| w[0] == name)
+            .map(|w| w[1].as_str())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, Box<dyn Error>> {
+        self.value(name)
+            .ok_or_else(|| format!("missing {name}\n\n{USAGE}").into())
+    }
+
+    fn uuid(&self, name: &str) -> Result<Uuid, Box<dyn Error>> {
+        Ok(self
+            .required(name)?
+            .parse()
+            .map_err(|e| format!("invalid {name}: {e}"))?)
+    }
+
+    fn number(&self, name: &str, default: u64) -> Result<u64, Box<dyn Error>> {
+        match self.value(name) {
+            Some(value) => Ok(value.parse().map_err(|e| format!("invalid {name}: {e}"))?),
+            None => Ok(default),
+        }
+    }
+}
+
+/// An API client holding the session cookie of the signed in user.
+struct Api {
+    client: Client,
+    base_url: String,
+    cookie: String,
+}
+
+impl Api {
+    async fn login(base_url: &str, token: &str) -> Result<Self, Box<dyn Error>> {
+        let client = Client::new();
+        let body = serde_json::to_vec(&serde_json::json!({ "accessToken": token }))?;
+        let res = client
+            .post(format!("{base_url}{API_ROOT}/auth/token"))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?;
+        expect_status(res.status(), StatusCode::NO_CONTENT)?;
+
+        // Only the name and value are sent back, not attributes such as the path
+        let cookie = res
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok()?.split(';').next())
+            .collect::<Vec<_>>()
+            .join("; ");
+        if cookie.is_empty() {
+            return Err("signed in, but no session cookie was set".into());
+        }
+
+        Ok(Self {
+            client,
+            base_url: base_url.to_string(),
+            cookie,
+        })
+    }
+
+    async fn request(&self, method: Method, path: &str) -> Result<Response, Box<dyn Error>> {
+        Ok(self
+            .client
+            .request(method, format!("{}{API_ROOT}{path}", self.base_url))
+            .header(COOKIE, &self.cookie)
+            .send()
+            .await?)
+    }
+}
+
+fn expect_status(actual: StatusCode, expected: StatusCode) -> Result<(), Box<dyn Error>> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!("expected {expected}, got {actual}").into())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args(env::args().skip(1).collect());
+    if let Err(e) = run(&args).await {
+        eprintln!("smoke test failed: {e}");
+        process::exit(1);
+    }
+    println!("smoke test passed");
+}
+
+async fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let base_url = args.required("--url")?.trim_end_matches('/');
+    let token = args.required("--token")?;
+    let message_id = args.uuid("--message-id")?;
+    let stamp_id = args.uuid("--stamp-id")?;
+    let socket_timeout =
+        Duration::from_secs(args.number("--socket-timeout", DEFAULT_SOCKET_TIMEOUT_SECS)?);
+
+    let api = step("sign in", Api::login(base_url, token)).await?;
+    let user: User = step("fetch the signed in user", async {
+        let res = api.request(Method::GET, "/me").await?;
+        expect_status(res.status(), StatusCode::OK)?;
+        Ok(serde_json::from_slice(&res.bytes().await?)?)
+    })
+    .await?;
+
+    step("fetch the timeline", async {
+        let res = api.request(Method::GET, "/timeline").await?;
+        expect_status(res.status(), StatusCode::OK)?;
+        let timeline: Value = serde_json::from_slice(&res.bytes().await?)?;
+        if !timeline.is_array() {
+            return Err(format!("expected a list of messages, got {timeline}").into());
+        }
+        Ok(())
+    })
+    .await?;
+
+    let (socket, mut deltas) =
+        step("subscribe to the message", subscribe(base_url, message_id)).await?;
+
+    let stamp_path = format!("/messages/{message_id}/stamps/{stamp_id}");
+    step("add the stamp", async {
+        let res = api.request(Method::POST, &stamp_path).await?;
+        expect_status(res.status(), StatusCode::NO_CONTENT)
+    })
+    .await?;
+    step(
+        "receive the added stamp",
+        wait_for_stamp(&mut deltas, socket_timeout, &user, stamp_id, true),
+    )
+    .await?;
+
+    step("remove the stamp", async {
+        let res = api.request(Method::DELETE, &stamp_path).await?;
+        expect_status(res.status(), StatusCode::NO_CONTENT)
+    })
+    .await?;
+    step(
+        "receive the removed stamp",
+        wait_for_stamp(&mut deltas, socket_timeout, &user, stamp_id, false),
+    )
+    .await?;
+
+    socket.disconnect().await?;
+
+    Ok(())
+}
+
+/// Runs a step, reporting its outcome.
+async fn step<T>(
+    name: &str,
+    step: impl Future<Output = Result<T, Box<dyn Error>>>,
+) -> Result<T, Box<dyn Error>> {
+    match step.await {
+        Ok(value) => {
+            println!("ok    {name}");
+            Ok(value)
+        }
+        Err(e) => {
+            println!("FAIL  {name}");
+            Err(format!("{name}: {e}").into())
+        }
+    }
+}
+
+async fn subscribe(
+    base_url: &str,
+    message_id: Uuid,
+) -> Result<(SocketClient, UnboundedReceiver<MessageDelta>), Box<dyn Error>> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let socket = ClientBuilder::new(base_url)
+        .namespace("/")
+        .auth(serde_json::to_value(ConnectPayload {
+            protocol_version: DELTA_PROTOCOL_VERSION,
+        })?)
+        .on(
+            MESSAGE_DELTA_EVENT,
+            move |payload: Payload, _socket: SocketClient| {
+                let tx = tx.clone();
+                async move {
+                    if let Payload::Text(values) = payload
+                        && let Some(value) = values.into_iter().next()
+                        && let Ok(delta) = serde_json::from_value(value)
+                    {
+                        let _ = tx.send(delta);
+                    }
+                }
+                .boxed()
+            },
+        )
+        .connect()
+        .await?;
+
+    socket
+        .emit(
+            SubscribePayload::event_name(),
+            serde_json::to_value(SubscribePayload {
+                message_ids: vec![message_id],
+            })?,
+        )
+        .await?;
+
+    Ok((socket, rx))
+}
+
+/// Waits for a delta in which `user` has added `stamp_id`, or has not if `added` is false.
+async fn wait_for_stamp(
+    deltas: &mut UnboundedReceiver<MessageDelta>,
+    timeout: Duration,
+    user: &User,
+    stamp_id: Uuid,
+    added: bool,
+) -> Result<(), Box<dyn Error>> {
+    let wait = async {
+        while let Some(delta) = deltas.recv().await {
+            let Some(reactions) = delta.reactions else {
+                continue;
+            };
+            let found = reactions
+                .iter()
+                .any(|r| r.stamp_id == stamp_id && r.user_id == user.id);
+            if found == added {
+                return Ok(());
+            }
+        }
+        Err("the socket was closed".into())
+    };
+
+    time::timeout(timeout, wait)
+        .await
+        .map_err(|_| format!("no update within {timeout:?}"))?
+}
@@ -0,0 +1,561 @@
+//! Assembly of the application from its config. The server, the tests and command line tools all
+//! build their components here, so that they are wired the same way everywhere.
+
+#[cfg(feature = "embeddings")]
+use crate::job::embedding::EmbeddingJob;
+use crate::{
+    config::{AppConfig, DailySummaryConfig, EmbeddingsConfig, TraqBudgetConfig},
+    handler::{
+        AppState,
+        meta::{InstanceFeatures, InstanceLimits, InstanceMeta},
+    },
+    job::{
+        JobHandle, JobScheduler, Schedule, engagement_metrics::EngagementMetricsJob,
+        history_cleanup::JobHistoryCleanupJob, notification_flush::NotificationFlushJob,
+        presence_cleanup::PresenceCleanupJob, quota_cleanup::QuotaCleanupJob,
+        read_roll_up::ReadRollUpJob,
+    },
+    rate_limit::RateLimiter,
+    self_test::{SelfTest, Severity},
+};
+use domain::{
+    channel_sync::ChannelSync,
+    chaos::{ChaosTraqClient, FaultInjector, chaotic},
+    crawler::MessageCrawler,
+    daily_summary::DailySummary,
+    dual_write::dual_write,
+    federation::{HighlightsFetcher, Peers},
+    image_cache::ImageCache,
+    link_preview::{LinkPreviewFetcher, LinkPreviewResolver},
+    model::QuotaOperation,
+    notifier::{CoalescingNotifier, FanOutNotifier, MessageNotifier},
+    quote::QuoteResolver,
+    ranking::RankingWeights,
+    recent_messages::RecentMessages,
+    replay_buffer::BufferedMessageEventRepository,
+    repository::{MessageEventRepository, Repository},
+    search::SearchIndex,
+    service::{
+        BookmarkService, BookmarkServiceImpl, FederationService, FederationServiceImpl,
+        MAX_CHANNEL_INTERESTS, OnboardingService, OnboardingServiceImpl, PresenceService,
+        PresenceServiceImpl, QuotaService, QuotaServiceImpl, ReportService, ReportServiceImpl,
+        TimelineService, TimelineServiceImpl, TraqService, TraqServiceImpl, WebhookService,
+        WebhookServiceImpl,
+    },
+    thumbnail::{ImageResizer, Thumbnailer},
+    traq_budget::{BudgetedTraqClient, LocalTokenBucket, TokenBucket, TraqBudget, TraqPriority},
+    traq_cache::CachedTraqClient,
+    traq_client::TraqClient,
+    webhook::{WebhookNotifier, WebhookSender},
+};
+#[cfg(feature = "embeddings")]
+use infra::embedding_client::EmbeddingClientImpl;
+use infra::{
+    highlights_fetcher::HighlightsFetcherImpl, image_resizer::ImageResizerImpl,
+    link_preview_fetcher::LinkPreviewFetcherImpl, meilisearch::MeilisearchIndex,
+    repository::mariadb, traq_budget::RedisTokenBucket, traq_client::TraqClientImpl,
+    webhook_sender::WebhookSenderImpl,
+};
+use sqlx::MySqlPool;
+use std::{collections::HashMap, error::Error, sync::Arc, time::Duration};
+use uuid::Uuid;
+
+/// The number of recently crawled messages kept in memory for degraded timelines.
+const RECENT_MESSAGES_CAPACITY: usize = 500;
+/// The number of recent message events kept in memory for timeline catch-up.
+const EVENT_REPLAY_CAPACITY: usize = 10_000;
+/// How long reads are kept one by one before they are rolled up into read horizons.
+const READ_RETENTION_DAYS: i64 = 31;
+
+/// The services handlers are served by.
+#[derive(Clone, Debug)]
+pub struct Services {
+    pub traq: Arc<dyn TraqService>,
+    pub timeline: Arc<dyn TimelineService>,
+    pub bookmark: Arc<dyn BookmarkService>,
+    pub onboarding: Arc<dyn OnboardingService>,
+    pub report: Arc<dyn ReportService>,
+    pub webhook: Arc<dyn WebhookService>,
+    pub presence: Arc<dyn PresenceService>,
+    pub quota: Arc<dyn QuotaService>,
+    pub federation: Arc<dyn FederationService>,
+}
+
+/// Builds the services and background jobs on top of a repository and a traQ client.
+#[derive(Debug)]
+pub struct AppBuilder {
+    repository: Repository,
+    traq_client: Arc<dyn TraqClient>,
+    background_traq_client: Arc<dyn TraqClient>,
+    search_index: Option<Arc<dyn SearchIndex>>,
+    link_preview_fetcher: Option<Arc<dyn LinkPreviewFetcher>>,
+    image_resizer: Option<Arc<dyn ImageResizer>>,
+    webhook_sender: Option<Arc<dyn WebhookSender>>,
+    highlights_fetcher: Option<Arc<dyn HighlightsFetcher>>,
+    recent_messages: Arc<RecentMessages>,
+    ranking: Option<RankingWeights>,
+    affinity_half_life_days: Option<f64>,
+    report_hide_threshold: Option<i64>,
+    embeddings: Option<EmbeddingsConfig>,
+    daily_summary: Option<DailySummaryConfig>,
+    heartbeat_urls: HashMap<String, String>,
+    notification_flush_interval: Option<Duration>,
+    admin_user_ids: Vec<Uuid>,
+    meta: InstanceMeta,
+    rate_limit_per_minute: Option<u32>,
+    search_quota: Option<i64>,
+    public_channel_ids: Vec<Uuid>,
+    publish_highlights: bool,
+    peer_urls: Vec<String>,
+    fault_injector: Option<Arc<FaultInjector>>,
+    token_login: bool,
+}
+
+impl AppBuilder {
+    /// Starts with the services' defaults, without search, link previews, image resizing, webhooks,
+    /// embeddings or peer instances.
+    pub fn new(repository: Repository, traq_client: Arc<dyn TraqClient>) -> Self {
+        Self {
+            repository,
+            background_traq_client: traq_client.clone(),
+            traq_client,
+            search_index: None,
+            link_preview_fetcher: None,
+            image_resizer: None,
+            webhook_sender: None,
+            highlights_fetcher: None,
+            recent_messages: Arc::new(RecentMessages::new(RECENT_MESSAGES_CAPACITY)),
+            ranking: None,
+            affinity_half_life_days: None,
+            report_hide_threshold: None,
+            embeddings: None,
+            daily_summary: None,
+            heartbeat_urls: HashMap::new(),
+            notification_flush_interval: None,
+            admin_user_ids: vec![],
+            meta: InstanceMeta::default(),
+            rate_limit_per_minute: None,
+            search_quota: None,
+            public_channel_ids: vec![],
+            publish_highlights: false,
+            peer_urls: vec![],
+            fault_injector: None,
+            token_login: false,
+        }
+    }
+
+    /// Builds on the database behind `pool`, and connects to the secondary database, traQ, the
+    /// search index, the web for link previews and peer instances as configured. Each connection is recorded in
+    /// `self_test`.
+    pub async fn connect(
+        config: &AppConfig,
+        pool: MySqlPool,
+        self_test: &mut SelfTest,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut repository =
+            self_test.require("migrations", mariadb::new_repository(pool).await)?;
+        if let Some(url) = &config.secondary_database_url {
+            let pool = self_test.require("secondary_database", MySqlPool::connect(url).await)?;
+            let secondary =
+                self_test.require("secondary_migrations", mariadb::new_repository(pool).await)?;
+            repository = dual_write(repository, secondary);
+            tracing::info!("Dual-writing to the secondary database");
+        }
+        let message_events = BufferedMessageEventRepository::new(
+            repository.message_event.clone(),
+            EVENT_REPLAY_CAPACITY,
+        );
+        // Events appended from now on are replayed from memory
+        message_events.find_latest_cursor().await?;
+        repository.message_event = Arc::new(message_events);
+
+        let traq_client = TraqClientImpl::new(config.traq.api_base_url.clone());
+        if let Some(version) = self_test.check(
+            "traq",
+            Severity::Hard,
+            traq_client.get_server_version().await,
+        ) {
+            tracing::info!("Connected to traQ {version}");
+        }
+        let token = match repository.user.find_random_valid_token().await {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(
+                "no user has a valid token, so messages are not crawled until someone logs in"
+                    .to_string(),
+            ),
+            Err(e) => Err(e.to_string()),
+        };
+        self_test.check("token", Severity::Soft, token);
+
+        let mut traq_client: Arc<dyn TraqClient> = Arc::new(traq_client);
+        let fault_injector = config.chaos.map(|settings| {
+            tracing::warn!(
+                "Injecting faults into repository and traQ calls: {:?}",
+                settings
+            );
+            Arc::new(FaultInjector::new(settings))
+        });
+        if let Some(fault_injector) = &fault_injector {
+            // Wrapped after the checks above, so that the app still starts
+            repository = chaotic(repository, fault_injector.clone());
+            traq_client = Arc::new(ChaosTraqClient::new(traq_client, fault_injector.clone()));
+        }
+        let (traq_client, background_traq_client) = match &config.traq.budget {
+            Some(budget) => {
+                let budget = Arc::new(traq_budget(budget, self_test).await);
+                (
+                    Arc::new(BudgetedTraqClient::new(
+                        traq_client.clone(),
+                        budget.clone(),
+                        TraqPriority::Interactive,
+                    )) as Arc<dyn TraqClient>,
+                    Arc::new(BudgetedTraqClient::new(
+                        traq_client,
+                        budget,
+                        TraqPriority::Background,
+                    )) as Arc<dyn TraqClient>,
+                )
+            }
+            None => (traq_client.clone(), traq_client),
+        };
+
+        let mut builder = Self::new(repository, Arc::new(CachedTraqClient::new(traq_client)))
+            .with_background_traq_client(background_traq_client)
+            .with_config(config);
+        if let Some(search) = &config.search {
+            let index = MeilisearchIndex::new(
+                search.meilisearch_url.clone(),
+                search.meilisearch_api_key.clone(),
+                search.index.clone(),
+            );
+            // Searches fail until Meilisearch is reachable, but indexing is retried on every crawl
+            self_test.check("search", Severity::Soft, index.configure().await);
+            builder = builder.with_search_index(Arc::new(index));
+        }
+        if config.link_previews.enabled {
+            builder = builder.with_link_preview_fetcher(Arc::new(LinkPreviewFetcherImpl::new()));
+        }
+        builder = builder
+            .with_image_resizer(Arc::new(ImageResizerImpl))
+            .with_webhook_sender(Arc::new(WebhookSenderImpl::new()));
+        if !config.federation.peer_urls.is_empty() {
+            builder = builder.with_highlights_fetcher(Arc::new(HighlightsFetcherImpl::new()));
+        }
+        if let Some(fault_injector) = fault_injector {
+            builder = builder.with_fault_injector(fault_injector);
+        }
+
+        Ok(builder)
+    }
+
+    /// Applies the tuning, admins, sign-in options and instance metadata in the config.
+    pub fn with_config(mut self, config: &AppConfig) -> Self {
+        self.ranking = Some(config.ranking);
+        self.affinity_half_life_days = Some(config.affinity.half_life_days);
+        self.report_hide_threshold = config.reports.auto_hide_threshold;
+        self.embeddings = config.embeddings.clone();
+        self.daily_summary = config.daily_summary.clone();
+        self.admin_user_ids = config.admin_user_ids.clone();
+        self.heartbeat_urls = config.jobs.heartbeat_urls.clone();
+        self.notification_flush_interval = config
+            .notifications
+            .flush_interval_secs
+            .map(|secs| Duration::from_secs(secs as u64));
+        self.rate_limit_per_minute = config
+            .rate_limit
+            .requests_per_minute
+            .map(|limit| limit.try_into().unwrap_or(u32::MAX));
+        self.search_quota = config.quotas.search_per_day;
+        self.public_channel_ids = config.public_timeline.channel_ids.clone();
+        self.publish_highlights = config.federation.publish_highlights;
+        self.peer_urls = config.federation.peer_urls.clone();
+        self.token_login = config.auth.token_login;
+        self.meta = InstanceMeta {
+            name: config.instance.name.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            traq_base_url: config.traq.web_base_url.clone(),
+            features: InstanceFeatures {
+                report_auto_hide: config.reports.auto_hide_threshold.is_some(),
+                public_timeline: !config.public_timeline.channel_ids.is_empty(),
+            },
+            limits: InstanceLimits {
+                max_channel_interests: MAX_CHANNEL_INTERESTS,
+            },
+        };
+        self
+    }
+
+    /// Makes background jobs call traQ with `client` instead of the one users' requests are served
+    /// with, e.g. to give them a lower priority.
+    pub fn with_background_traq_client(mut self, client: Arc<dyn TraqClient>) -> Self {
+        self.background_traq_client = client;
+        self
+    }
+
+    pub fn with_search_index(mut self, search_index: Arc<dyn SearchIndex>) -> Self {
+        self.search_index = Some(search_index);
+        self
+    }
+
+    /// Previews the pages linked from messages with `fetcher`, except links to traQ.
+    pub fn with_link_preview_fetcher(mut self, fetcher: Arc<dyn LinkPreviewFetcher>) -> Self {
+        self.link_preview_fetcher = Some(fetcher);
+        self
+    }
+
+    /// Serves smaller user icons and stamp images resized by `resizer` when asked.
+    pub fn with_image_resizer(mut self, resizer: Arc<dyn ImageResizer>) -> Self {
+        self.image_resizer = Some(resizer);
+        self
+    }
+
+    /// Sends changes to the webhooks registered by admins with `sender`.
+    pub fn with_webhook_sender(mut self, sender: Arc<dyn WebhookSender>) -> Self {
+        self.webhook_sender = Some(sender);
+        self
+    }
+
+    /// Fetches the highlights of the configured peer instances with `fetcher`.
+    pub fn with_highlights_fetcher(mut self, fetcher: Arc<dyn HighlightsFetcher>) -> Self {
+        self.highlights_fetcher = Some(fetcher);
+        self
+    }
+
+    /// Lets admins change the faults injected by `fault_injector` through `/admin/chaos`. The
+    /// repository and traQ client must already be wrapped with it.
+    pub fn with_fault_injector(mut self, fault_injector: Arc<FaultInjector>) -> Self {
+        self.fault_injector = Some(fault_injector);
+        self
+    }
+
+    pub fn repository(&self) -> &Repository {
+        &self.repository
+    }
+
+    pub fn services(&self) -> Services {
+        let mut timeline = TimelineServiceImpl::new(self.repository.clone())
+            .with_recent_messages(self.recent_messages.clone())
+            .with_quote_resolver(Arc::new(QuoteResolver::new(
+                self.traq_client.clone(),
+                self.repository.clone(),
+            )));
+        if let Some(ranking) = self.ranking {
+            timeline = timeline.with_ranking_weights(ranking);
+        }
+        if let Some(days) = self.affinity_half_life_days {
+            timeline = timeline.with_affinity_half_life_days(days);
+        }
+        if let Some(threshold) = self.report_hide_threshold {
+            timeline = timeline.with_report_hide_threshold(threshold);
+        }
+        if cfg!(feature = "embeddings") && self.embeddings.is_some() {
+            timeline = timeline.with_similar_content();
+        }
+        if let Some(search_index) = &self.search_index {
+            timeline = timeline.with_search_index(search_index.clone());
+        }
+        if !self.public_channel_ids.is_empty() {
+            timeline = timeline.with_public_channels(self.public_channel_ids.clone());
+        }
+        if let Some(fetcher) = &self.link_preview_fetcher {
+            let mut resolver =
+                LinkPreviewResolver::new(fetcher.clone(), self.repository.link_preview.clone());
+            if !self.meta.traq_base_url.is_empty() {
+                resolver = resolver.ignoring(self.meta.traq_base_url.clone());
+            }
+            timeline = timeline.with_link_preview_resolver(Arc::new(resolver));
+        }
+
+        let mut traq = TraqServiceImpl::new(self.repository.clone(), self.traq_client.clone())
+            .with_image_cache(Arc::new(ImageCache::new(
+                self.repository.image_cache.clone(),
+            )));
+        if let Some(resizer) = &self.image_resizer {
+            traq = traq.with_thumbnailer(Arc::new(Thumbnailer::new(
+                resizer.clone(),
+                self.repository.image_cache.clone(),
+            )));
+        }
+
+        let mut quota = QuotaServiceImpl::new(self.repository.clone());
+        if let Some(limit) = self.search_quota {
+            quota = quota.with_limit(QuotaOperation::Search, limit);
+        }
+
+        let mut federation =
+            FederationServiceImpl::new(self.repository.clone(), self.meta.name.clone());
+        if self.publish_highlights {
+            federation = federation.publishing();
+        }
+        if let Some(fetcher) = &self.highlights_fetcher
+            && !self.peer_urls.is_empty()
+        {
+            federation = federation.with_peers(Arc::new(Peers::new(
+                fetcher.clone(),
+                self.peer_urls.clone(),
+            )));
+        }
+
+        Services {
+            traq: Arc::new(traq),
+            timeline: Arc::new(timeline),
+            bookmark: Arc::new(BookmarkServiceImpl::new(self.repository.clone())),
+            onboarding: Arc::new(OnboardingServiceImpl::new(self.repository.clone())),
+            report: Arc::new(ReportServiceImpl::new(self.repository.clone())),
+            webhook: Arc::new(WebhookServiceImpl::new(self.repository.clone())),
+            presence: Arc::new(PresenceServiceImpl::new(self.repository.clone())),
+            quota: Arc::new(quota),
+            federation: Arc::new(federation),
+        }
+    }
+
+    /// Schedules the jobs that keep the cache up to date, with their history recorded in the
+    /// repository and heartbeats sent as configured. Crawled messages are announced through
+    /// `notifier` and the registered webhooks, combined per flush interval if one is configured.
+    pub fn scheduler(&self, notifier: Arc<dyn MessageNotifier>) -> JobScheduler {
+        let notifier: Arc<dyn MessageNotifier> = match &self.webhook_sender {
+            Some(sender) => Arc::new(FanOutNotifier::new(vec![
+                notifier,
+                Arc::new(WebhookNotifier::new(
+                    sender.clone(),
+                    self.repository.webhook.clone(),
+                )),
+            ])),
+            None => notifier,
+        };
+        let coalescing = self.notification_flush_interval.map(|interval| {
+            (
+                Arc::new(CoalescingNotifier::new(notifier.clone())),
+                interval,
+            )
+        });
+        let notifier: Arc<dyn MessageNotifier> = match &coalescing {
+            Some((coalescing, _)) => coalescing.clone(),
+            None => notifier,
+        };
+        let mut crawler = MessageCrawler::new(
+            self.background_traq_client.clone(),
+            self.repository.clone(),
+            notifier,
+        )
+        .with_recent_messages(self.recent_messages.clone());
+        if let Some(search_index) = &self.search_index {
+            crawler = crawler.with_search_index(search_index.clone());
+        }
+
+        let scheduler = JobScheduler::new()
+            .register(
+                crawler,
+                Schedule::every(Duration::from_secs(30)).with_jitter(Duration::from_secs(5)),
+            )
+            .register(
+                ChannelSync::new(self.background_traq_client.clone(), self.repository.clone()),
+                Schedule::every(Duration::from_hours(1)).with_jitter(Duration::from_mins(5)),
+            )
+            .register(
+                JobHistoryCleanupJob::new(self.repository.job_run.clone(), time::Duration::days(7)),
+                Schedule::every(Duration::from_hours(1)),
+            )
+            .register(
+                // Kept for longer than the daily active users are counted over
+                PresenceCleanupJob::new(self.repository.presence.clone(), time::Duration::days(2)),
+                Schedule::every(Duration::from_hours(1)),
+            )
+            .register(
+                QuotaCleanupJob::new(self.repository.quota.clone()),
+                Schedule::every(Duration::from_hours(6)),
+            )
+            .register(
+                // Older than any message recommended, so rolling them up changes no timeline
+                ReadRollUpJob::new(
+                    self.repository.message_writer.clone(),
+                    time::Duration::days(READ_RETENTION_DAYS),
+                ),
+                Schedule::every(Duration::from_hours(24)),
+            )
+            .register(
+                EngagementMetricsJob::new(self.repository.impression.clone()),
+                Schedule::every(Duration::from_hours(24)),
+            )
+            .with_history(self.repository.job_run.clone())
+            .with_heartbeat_urls(self.heartbeat_urls.clone());
+        let scheduler = match &self.daily_summary {
+            // Checked hourly, but posted once a day
+            Some(daily_summary) => scheduler.register(
+                DailySummary::new(
+                    self.background_traq_client.clone(),
+                    self.repository.clone(),
+                    daily_summary.channel_id,
+                    daily_summary.bot_token.clone(),
+                    self.meta.traq_base_url.clone(),
+                ),
+                Schedule::every(Duration::from_hours(1)),
+            ),
+            None => scheduler,
+        };
+        let scheduler = match coalescing {
+            Some((notifier, interval)) => scheduler.register(
+                NotificationFlushJob::new(notifier),
+                Schedule::every(interval),
+            ),
+            None => scheduler,
+        };
+        #[cfg(feature = "embeddings")]
+        let scheduler = match self.embeddings.clone() {
+            Some(embeddings) => scheduler.register(
+                EmbeddingJob::new(
+                    Arc::new(EmbeddingClientImpl::new(
+                        embeddings.api_base_url,
+                        embeddings.api_key,
+                        embeddings.model,
+                    )),
+                    self.repository.embedding.clone(),
+                ),
+                Schedule::every(Duration::from_mins(1)),
+            ),
+            None => scheduler,
+        };
+        #[cfg(not(feature = "embeddings"))]
+        if self.embeddings.is_some() {
+            tracing::warn!(
+                "Embeddings are configured, but this build does not include the `embeddings` feature"
+            );
+        }
+
+        scheduler
+    }
+
+    /// Builds the state handlers are served with, reporting on the jobs behind `jobs`.
+    pub fn state(&self, jobs: JobHandle) -> AppState {
+        let state = AppState::new(self.services(), jobs, self.admin_user_ids.clone())
+            .with_meta(self.meta.clone())
+            .with_rate_limiter(RateLimiter::new(self.rate_limit_per_minute))
+            .with_token_login(self.token_login);
+        match &self.fault_injector {
+            Some(fault_injector) => state.with_fault_injector(fault_injector.clone()),
+            None => state,
+        }
+    }
+}
+
+/// Builds the budget for calls to traQ, kept in Redis if configured and reachable.
+async fn traq_budget(config: &TraqBudgetConfig, self_test: &mut SelfTest) -> TraqBudget {
+    let capacity = config.burst as f64;
+    let local = || Arc::new(LocalTokenBucket::new(config.requests_per_second, capacity));
+    let bucket: Arc<dyn TokenBucket> = match &config.redis_url {
+        // Each process keeps its own budget until Redis is back
+        Some(url) => match self_test.check(
+            "traq_budget",
+            Severity::Soft,
+            RedisTokenBucket::connect(url, config.requests_per_second, capacity).await,
+        ) {
+            Some(bucket) => Arc::new(bucket),
+            None => local(),
+        },
+        None => local(),
+    };
+    // At least one call is left for background jobs
+    let reserve = (capacity * config.interactive_share).min(capacity - 1.0);
+
+    TraqBudget::new(bucket, reserve)
+}
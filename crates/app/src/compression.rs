@@ -0,0 +1,79 @@
+//! Compression of responses with gzip or Brotli, as accepted by the client.
+//!
+//! Images proxied from traQ are already compressed, so they are recognized by their content type
+//! and sent as they are. So are small responses, which compression would barely shrink.
+
+use tower_http::compression::{
+    CompressionLayer,
+    predicate::{NotForContentType, Predicate, SizeAbove},
+};
+
+/// Compresses responses of more than `min_size` bytes, except images and event streams.
+pub fn layer(min_size: u16) -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(min_size)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::SSE)
+        // Unlike `NotForContentType::IMAGES`, this also covers SVG, so that no image proxy is
+        // compressed
+        .and(NotForContentType::const_new("image/"));
+
+    CompressionLayer::new()
+        .gzip(true)
+        .br(true)
+        .compress_when(predicate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, extract::Request, routing::get};
+    use http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE};
+    use tower::ServiceExt;
+
+    const MIN_SIZE: u16 = 1024;
+
+    fn app() -> Router {
+        let large = "a".repeat(MIN_SIZE as usize * 2);
+        let image = large.clone();
+        Router::new()
+            .route("/small", get(|| async { "small" }))
+            .route("/large", get(|| async move { large }))
+            .route(
+                "/image",
+                get(|| async move { ([(CONTENT_TYPE, "image/svg+xml")], image) }),
+            )
+            .layer(layer(MIN_SIZE))
+    }
+
+    async fn content_encoding(path: &str, accept_encoding: &str) -> Option<String> {
+        let req = Request::builder()
+            .uri(path)
+            .header(ACCEPT_ENCODING, accept_encoding)
+            .body(Body::empty())
+            .unwrap();
+        let res = app().oneshot(req).await.unwrap();
+
+        res.headers()
+            .get(CONTENT_ENCODING)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn large_responses_are_compressed_as_accepted() {
+        assert_eq!(
+            content_encoding("/large", "gzip").await.as_deref(),
+            Some("gzip")
+        );
+        assert_eq!(
+            content_encoding("/large", "br").await.as_deref(),
+            Some("br")
+        );
+        assert_eq!(content_encoding("/large", "identity").await, None);
+    }
+
+    #[tokio::test]
+    async fn small_responses_and_images_are_not_compressed() {
+        assert_eq!(content_encoding("/small", "gzip, br").await, None);
+        assert_eq!(content_encoding("/image", "gzip, br").await, None);
+    }
+}
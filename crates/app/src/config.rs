@@ -0,0 +1,2094 @@
+//! Application configuration.
+//!
+//! Values are read from an optional TOML or YAML file and then overridden by environment
+//! variables, so a config file can hold the deployment defaults while individual values can still
+//! be tweaked per environment (e.g. secrets injected by the container runtime).
+//! Every environment variable can also be given as a path in its `_FILE` variant, whose contents
+//! are used as the value.
+
+use domain::{
+    chaos::{FaultSettings, Faults},
+    ranking::{RankingWeights, SourceWeights},
+    service::DEFAULT_AFFINITY_HALF_LIFE_DAYS,
+};
+use infra::signing::{KeyRing, SigningKey};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tower_sessions::cookie::SameSite;
+use uuid::Uuid;
+
+const DEFAULT_LISTEN_ADDRESS: &str = "0.0.0.0:8080";
+const DEFAULT_INSTANCE_NAME: &str = "Twittra";
+const DEFAULT_SEARCH_INDEX: &str = "messages";
+pub const DEFAULT_SESSION_COOKIE_NAME: &str = "id";
+const DEFAULT_COMPRESSION_MIN_SIZE_BYTES: u16 = 1024;
+/// The traQ budget allows bursts of this many seconds' worth of calls by default.
+const DEFAULT_TRAQ_BURST_SECS: f64 = 10.0;
+const DEFAULT_TRAQ_INTERACTIVE_SHARE: f64 = 0.5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("failed to read {env} from the file in {env}_FILE ({path}): {source}")]
+    ReadFile {
+        env: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+
+    #[error("failed to parse config file {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+
+    #[error("unsupported config file format: {0} (expected .toml, .yaml or .yml)")]
+    UnsupportedFormat(PathBuf),
+
+    #[error("invalid config value for `{key}`: {message}")]
+    Invalid { key: &'static str, message: String },
+
+    #[error("missing required config value (set `{key}` in the config file or {env})")]
+    Missing {
+        key: &'static str,
+        env: &'static str,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(OsStr::to_str) {
+            Some("toml") => Some(Self::Toml),
+            Some("yaml" | "yml") => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub listen_address: String,
+    pub database_url: String,
+    /// A database that every write is copied to and every read is compared with, for verifying a
+    /// migration to it. Disabled if unset.
+    pub secondary_database_url: Option<String>,
+    /// Users allowed to access the `/admin` endpoints.
+    pub admin_user_ids: Vec<Uuid>,
+    pub affinity: AffinityConfig,
+    pub api: ApiConfig,
+    pub auth: AuthConfig,
+    /// Faults injected into repository and traQ calls, for testing resilience. Only allowed in
+    /// debug builds. Disabled if unset.
+    pub chaos: Option<FaultSettings>,
+    pub compression: CompressionConfig,
+    /// Disabled if unset.
+    pub daily_summary: Option<DailySummaryConfig>,
+    /// Disabled if unset.
+    pub embeddings: Option<EmbeddingsConfig>,
+    pub error_reporting: ErrorReportingConfig,
+    pub federation: FederationConfig,
+    pub instance: InstanceConfig,
+    pub jobs: JobsConfig,
+    pub link_previews: LinkPreviewsConfig,
+    pub notifications: NotificationsConfig,
+    pub public_timeline: PublicTimelineConfig,
+    pub quotas: QuotasConfig,
+    pub ranking: RankingWeights,
+    pub rate_limit: RateLimitConfig,
+    pub reports: ReportsConfig,
+    /// Full-text message search with Meilisearch. The database is searched if unset.
+    pub search: Option<SearchConfig>,
+    pub session: SessionConfig,
+    /// Keys for signing share links, feed tokens and webhook payloads.
+    /// Features that need signatures are disabled if unset.
+    pub signing: Option<KeyRing>,
+    pub startup: StartupConfig,
+    pub traq: TraqConfig,
+}
+
+#[derive(Clone, Debug)]
+pub struct AffinityConfig {
+    /// Stamps count half as much toward the users and channels a user likes every this many days.
+    pub half_life_days: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ApiConfig {
+    /// When `/api/v1` was deprecated, announced to its clients in the `Deprecation` header.
+    /// Not deprecated if unset.
+    pub v1_deprecated_at: Option<OffsetDateTime>,
+    /// When `/api/v1` is going to be removed, announced to its clients in the `Sunset` header.
+    /// Not announced if unset.
+    pub v1_sunset_at: Option<OffsetDateTime>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AuthConfig {
+    /// Allow signing in with a traQ access token at `POST /auth/token`, e.g. for smoke tests.
+    pub token_login: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompressionConfig {
+    /// Responses of at most this many bytes are sent uncompressed.
+    pub min_size_bytes: u16,
+}
+
+/// A daily post of the messages trending on Twittra to a traQ channel.
+#[derive(Clone, Debug)]
+pub struct DailySummaryConfig {
+    pub channel_id: Uuid,
+    /// The access token of a traQ bot that has joined the channel.
+    pub bot_token: String,
+}
+
+/// An OpenAI-compatible embeddings API, used to recommend messages with similar content.
+#[derive(Clone, Debug)]
+pub struct EmbeddingsConfig {
+    pub api_base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ErrorReportingConfig {
+    /// Errors and panics are posted to this URL as JSON, with user IDs scrubbed.
+    /// Disabled if unset.
+    pub webhook_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FederationConfig {
+    /// Publish the highlights of the past day at `/federation/highlights` for peer instances.
+    pub publish_highlights: bool,
+    /// The base URLs of the peer instances whose highlights are shown.
+    pub peer_urls: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct InstanceConfig {
+    /// The name shown by the frontend, so that deployments can be told apart.
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct JobsConfig {
+    /// Heartbeat URLs keyed by job name, pinged after every successful run.
+    /// Monitoring services such as healthchecks.io alert when the pings stop.
+    pub heartbeat_urls: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LinkPreviewsConfig {
+    /// Fetch the pages linked from messages to show previews of them.
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NotificationsConfig {
+    /// Updates of a message are combined into one notification sent every this many seconds.
+    /// Sent as soon as messages are crawled if unset.
+    pub flush_interval_secs: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PublicTimelineConfig {
+    /// The channels whose messages are shown to anyone at `/public/timeline`, e.g. for embedding
+    /// in a community site. Disabled if empty.
+    pub channel_ids: Vec<Uuid>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct QuotasConfig {
+    /// Searches allowed per user per day. Unlimited if unset.
+    pub search_per_day: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RateLimitConfig {
+    /// API calls beyond this many per user per minute are rejected. Calls are only counted if
+    /// unset.
+    pub requests_per_minute: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReportsConfig {
+    /// Messages with at least this many unresolved reports are hidden from timelines until an
+    /// admin resolves them. Disabled if unset.
+    pub auto_hide_threshold: Option<i64>,
+}
+
+/// A Meilisearch instance that crawled messages are indexed into.
+#[derive(Clone, Debug)]
+pub struct SearchConfig {
+    pub meilisearch_url: String,
+    pub meilisearch_api_key: Option<String>,
+    pub index: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    pub table_schema: String,
+    pub table_name: String,
+    pub cookie: SessionCookieConfig,
+}
+
+/// Attributes of the session cookie. The defaults suit a single HTTPS origin, while deployments
+/// behind a reverse proxy may need to share the cookie across subdomains or serve plain HTTP
+/// internally.
+#[derive(Clone, Debug)]
+pub struct SessionCookieConfig {
+    pub name: String,
+    pub secure: bool,
+    /// The cookie is sent only to the host that set it if unset.
+    pub domain: Option<String>,
+    pub same_site: SameSite,
+    /// Sessions expire after this many seconds of inactivity. The cookie lasts until the browser
+    /// is closed if unset.
+    pub max_age_secs: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StartupConfig {
+    /// Refuse to start if a hard check of the startup self-test fails, instead of only logging it.
+    pub strict: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct TraqConfig {
+    pub api_base_url: String,
+    /// The URL of the traQ web client, used to link to messages and users.
+    pub web_base_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Calls to traQ are not limited if unset.
+    pub budget: Option<TraqBudgetConfig>,
+}
+
+/// A token bucket limiting the calls made to traQ by users' requests and background jobs alike.
+#[derive(Clone, Debug)]
+pub struct TraqBudgetConfig {
+    pub requests_per_second: f64,
+    /// Up to this many calls are made at once after a quiet period.
+    pub burst: i64,
+    /// The share of the burst that background jobs leave for calls made on behalf of users.
+    pub interactive_share: f64,
+    /// Shares the budget between processes through Redis. Each process has its own if unset.
+    pub redis_url: Option<String>,
+}
+
+/// The config file representation.
+/// Every value is optional because it may be provided by an environment variable instead.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    listen_address: Option<String>,
+    database_url: Option<String>,
+    secondary_database_url: Option<String>,
+    admin_user_ids: Option<Vec<String>>,
+    affinity: FileAffinityConfig,
+    api: FileApiConfig,
+    auth: FileAuthConfig,
+    chaos: FileChaosConfig,
+    compression: FileCompressionConfig,
+    daily_summary: FileDailySummaryConfig,
+    embeddings: FileEmbeddingsConfig,
+    error_reporting: FileErrorReportingConfig,
+    federation: FileFederationConfig,
+    instance: FileInstanceConfig,
+    jobs: FileJobsConfig,
+    link_previews: FileLinkPreviewsConfig,
+    notifications: FileNotificationsConfig,
+    public_timeline: FilePublicTimelineConfig,
+    quotas: FileQuotasConfig,
+    ranking: FileRankingConfig,
+    rate_limit: FileRateLimitConfig,
+    reports: FileReportsConfig,
+    search: FileSearchConfig,
+    session: FileSessionConfig,
+    signing: FileSigningConfig,
+    startup: FileStartupConfig,
+    traq: FileTraqConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileAffinityConfig {
+    half_life_days: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileApiConfig {
+    v1_deprecated_at: Option<String>,
+    v1_sunset_at: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileAuthConfig {
+    token_login: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileChaosConfig {
+    enabled: Option<bool>,
+    repository_latency_ms: Option<i64>,
+    repository_error_rate: Option<f64>,
+    traq_latency_ms: Option<i64>,
+    traq_error_rate: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileCompressionConfig {
+    min_size_bytes: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileDailySummaryConfig {
+    channel_id: Option<String>,
+    bot_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileEmbeddingsConfig {
+    api_base_url: Option<String>,
+    api_key: Option<String>,
+    model: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileErrorReportingConfig {
+    webhook_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileFederationConfig {
+    publish_highlights: Option<bool>,
+    peer_urls: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileInstanceConfig {
+    name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileJobsConfig {
+    heartbeat_urls: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileLinkPreviewsConfig {
+    enabled: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileNotificationsConfig {
+    flush_interval_secs: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FilePublicTimelineConfig {
+    channel_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileQuotasConfig {
+    search_per_day: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileRankingConfig {
+    top_reacted: FileSourceWeights,
+    affinity_author: FileSourceWeights,
+    affinity_channel: FileSourceWeights,
+    similar_user: FileSourceWeights,
+    recent: FileSourceWeights,
+    similar_content: FileSourceWeights,
+    hidden_author_penalty: Option<f64>,
+    hidden_channel_penalty: Option<f64>,
+    ignored_author_penalty: Option<f64>,
+    ignored_channel_penalty: Option<f64>,
+    max_messages_per_author: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSourceWeights {
+    base: Option<f64>,
+    rank_multiplier: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileRateLimitConfig {
+    requests_per_minute: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileReportsConfig {
+    auto_hide_threshold: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSearchConfig {
+    meilisearch_url: Option<String>,
+    meilisearch_api_key: Option<String>,
+    index: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSessionConfig {
+    table_schema: Option<String>,
+    table_name: Option<String>,
+    cookie_name: Option<String>,
+    cookie_secure: Option<bool>,
+    cookie_domain: Option<String>,
+    cookie_same_site: Option<String>,
+    cookie_max_age_secs: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSigningConfig {
+    active_key: Option<String>,
+    keys: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileStartupConfig {
+    strict: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileTraqConfig {
+    api_base_url: Option<String>,
+    web_base_url: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    requests_per_second: Option<f64>,
+    burst: Option<i64>,
+    interactive_share: Option<f64>,
+    budget_redis_url: Option<String>,
+}
+
+impl FileConfig {
+    fn parse(contents: &str, format: ConfigFormat) -> Result<Self, String> {
+        match format {
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_norway::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        let format = ConfigFormat::from_path(path)
+            .ok_or_else(|| ConfigError::UnsupportedFormat(path.to_path_buf()))?;
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Self::parse(&contents, format).map_err(|message| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+}
+
+/// Resolves each config value from the environment first and falls back to the config file.
+struct Resolver<F> {
+    lookup: F,
+}
+
+impl<F: Fn(&str) -> Option<String>> Resolver<F> {
+    /// Looks up an environment variable. If it is unset, the contents of the file named by the
+    /// `_FILE` variant (e.g. `TRAQ_CLIENT_SECRET_FILE`) are used instead, so that secrets can be
+    /// mounted as files such as Docker secrets.
+    fn env(&self, env: &'static str) -> Result<Option<String>, ConfigError> {
+        if let Some(value) = (self.lookup)(env) {
+            return Ok(Some(value));
+        }
+        let Some(path) = (self.lookup)(&format!("{env}_FILE")) else {
+            return Ok(None);
+        };
+
+        let contents = fs::read_to_string(&path).map_err(|source| ConfigError::ReadFile {
+            env,
+            path: PathBuf::from(&path),
+            source,
+        })?;
+        // Files usually end with a newline that is not part of the secret
+        Ok(Some(contents.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    fn string(
+        &self,
+        env: &'static str,
+        file: Option<String>,
+    ) -> Result<Option<String>, ConfigError> {
+        Ok(self.env(env)?.or(file))
+    }
+
+    fn required(
+        &self,
+        key: &'static str,
+        env: &'static str,
+        file: Option<String>,
+    ) -> Result<String, ConfigError> {
+        self.string(env, file)?
+            .ok_or(ConfigError::Missing { key, env })
+    }
+
+    /// Resolves a list of strings. The environment variable holds comma-separated values.
+    fn strings(
+        &self,
+        env: &'static str,
+        file: Option<Vec<String>>,
+    ) -> Result<Vec<String>, ConfigError> {
+        Ok(match self.env(env)? {
+            Some(value) => value
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => file.unwrap_or_default(),
+        })
+    }
+
+    /// Resolves a list of UUIDs. The environment variable holds comma-separated values.
+    fn uuids(
+        &self,
+        key: &'static str,
+        env: &'static str,
+        file: Option<Vec<String>>,
+    ) -> Result<Vec<Uuid>, ConfigError> {
+        self.strings(env, file)?
+            .iter()
+            .map(|v| {
+                Uuid::parse_str(v).map_err(|e| ConfigError::Invalid {
+                    key,
+                    message: format!("{v}: {e}"),
+                })
+            })
+            .collect()
+    }
+
+    fn float(
+        &self,
+        key: &'static str,
+        env: &'static str,
+        file: Option<f64>,
+        default: f64,
+    ) -> Result<f64, ConfigError> {
+        let Some(value) = self.env(env)? else {
+            return Ok(file.unwrap_or(default));
+        };
+
+        value.trim().parse().map_err(|e| ConfigError::Invalid {
+            key,
+            message: format!("{value}: {e}"),
+        })
+    }
+
+    /// Resolves a boolean. The environment variable holds `true` or `false`.
+    fn boolean(
+        &self,
+        key: &'static str,
+        env: &'static str,
+        file: Option<bool>,
+    ) -> Result<Option<bool>, ConfigError> {
+        let Some(value) = self.env(env)? else {
+            return Ok(file);
+        };
+
+        value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| ConfigError::Invalid {
+                key,
+                message: format!("{value}: {e}"),
+            })
+    }
+
+    /// Resolves an optional integer that must be at least 1.
+    fn positive_integer(
+        &self,
+        key: &'static str,
+        env: &'static str,
+        file: Option<i64>,
+    ) -> Result<Option<i64>, ConfigError> {
+        let value = match self.env(env)? {
+            Some(value) => Some(value.trim().parse().map_err(|e| ConfigError::Invalid {
+                key,
+                message: format!("{value}: {e}"),
+            })?),
+            None => file,
+        };
+
+        match value {
+            Some(value) if value < 1 => Err(ConfigError::Invalid {
+                key,
+                message: format!("{value}: must be at least 1"),
+            }),
+            _ => Ok(value),
+        }
+    }
+
+    fn affinity(&self, file: FileAffinityConfig) -> Result<AffinityConfig, ConfigError> {
+        let key = "affinity.half_life_days";
+        let half_life_days = self.float(
+            key,
+            "AFFINITY_HALF_LIFE_DAYS",
+            file.half_life_days,
+            DEFAULT_AFFINITY_HALF_LIFE_DAYS,
+        )?;
+        if !(half_life_days.is_finite() && half_life_days > 0.0) {
+            return Err(ConfigError::Invalid {
+                key,
+                message: format!("{half_life_days}: must be positive"),
+            });
+        }
+
+        Ok(AffinityConfig { half_life_days })
+    }
+
+    fn api(&self, file: FileApiConfig) -> Result<ApiConfig, ConfigError> {
+        let v1_deprecated_at = self.date(
+            "api.v1_deprecated_at",
+            "API_V1_DEPRECATED_AT",
+            file.v1_deprecated_at,
+        )?;
+        let key = "api.v1_sunset_at";
+        let v1_sunset_at = self.date(key, "API_V1_SUNSET_AT", file.v1_sunset_at)?;
+        if v1_sunset_at.is_some() && v1_deprecated_at.is_none() {
+            return Err(ConfigError::Invalid {
+                key,
+                message: "requires api.v1_deprecated_at to be set".to_string(),
+            });
+        }
+
+        Ok(ApiConfig {
+            v1_deprecated_at,
+            v1_sunset_at,
+        })
+    }
+
+    /// Resolves an RFC 3339 date.
+    fn date(
+        &self,
+        key: &'static str,
+        env: &'static str,
+        file: Option<String>,
+    ) -> Result<Option<OffsetDateTime>, ConfigError> {
+        self.string(env, file)?
+            .map(|value| {
+                OffsetDateTime::parse(&value, &Rfc3339).map_err(|e| ConfigError::Invalid {
+                    key,
+                    message: format!("{value}: {e}"),
+                })
+            })
+            .transpose()
+    }
+
+    fn chaos(&self, file: FileChaosConfig) -> Result<Option<FaultSettings>, ConfigError> {
+        let key = "chaos.enabled";
+        if !self
+            .boolean(key, "CHAOS_ENABLED", file.enabled)?
+            .unwrap_or_default()
+        {
+            return Ok(None);
+        }
+        if !cfg!(debug_assertions) {
+            return Err(ConfigError::Invalid {
+                key,
+                message: "fault injection is only available in debug builds".to_string(),
+            });
+        }
+
+        Ok(Some(FaultSettings {
+            repository: self.faults(
+                ("chaos.repository_latency_ms", "chaos.repository_error_rate"),
+                ("CHAOS_REPOSITORY_LATENCY_MS", "CHAOS_REPOSITORY_ERROR_RATE"),
+                file.repository_latency_ms,
+                file.repository_error_rate,
+            )?,
+            traq: self.faults(
+                ("chaos.traq_latency_ms", "chaos.traq_error_rate"),
+                ("CHAOS_TRAQ_LATENCY_MS", "CHAOS_TRAQ_ERROR_RATE"),
+                file.traq_latency_ms,
+                file.traq_error_rate,
+            )?,
+        }))
+    }
+
+    /// Resolves the latency and error rate injected into the calls of a backend.
+    fn faults(
+        &self,
+        keys: (&'static str, &'static str),
+        envs: (&'static str, &'static str),
+        latency_ms: Option<i64>,
+        error_rate: Option<f64>,
+    ) -> Result<Faults, ConfigError> {
+        let faults = Faults {
+            latency_ms: self
+                .positive_integer(keys.0, envs.0, latency_ms)?
+                .unwrap_or_default() as u64,
+            error_rate: self.float(keys.1, envs.1, error_rate, 0.0)?,
+        };
+        if !faults.is_valid() {
+            return Err(ConfigError::Invalid {
+                key: keys.1,
+                message: format!("{}: must be from 0 to 1", faults.error_rate),
+            });
+        }
+
+        Ok(faults)
+    }
+
+    fn compression(&self, file: FileCompressionConfig) -> Result<CompressionConfig, ConfigError> {
+        let key = "compression.min_size_bytes";
+        let min_size_bytes =
+            match self.positive_integer(key, "COMPRESSION_MIN_SIZE_BYTES", file.min_size_bytes)? {
+                Some(value) => value.try_into().map_err(|_| ConfigError::Invalid {
+                    key,
+                    message: format!("{value}: must be at most {}", u16::MAX),
+                })?,
+                None => DEFAULT_COMPRESSION_MIN_SIZE_BYTES,
+            };
+
+        Ok(CompressionConfig { min_size_bytes })
+    }
+
+    /// Resolves the base score and rank multiplier of a ranking source.
+    fn source_weights(
+        &self,
+        keys: (&'static str, &'static str),
+        envs: (&'static str, &'static str),
+        file: FileSourceWeights,
+        default: SourceWeights,
+    ) -> Result<SourceWeights, ConfigError> {
+        Ok(SourceWeights {
+            base: self.float(keys.0, envs.0, file.base, default.base)?,
+            rank_multiplier: self.float(
+                keys.1,
+                envs.1,
+                file.rank_multiplier,
+                default.rank_multiplier,
+            )?,
+        })
+    }
+
+    fn ranking(&self, file: FileRankingConfig) -> Result<RankingWeights, ConfigError> {
+        let default = RankingWeights::default();
+        let weights = RankingWeights {
+            top_reacted: self.source_weights(
+                (
+                    "ranking.top_reacted.base",
+                    "ranking.top_reacted.rank_multiplier",
+                ),
+                (
+                    "RANKING_TOP_REACTED_BASE",
+                    "RANKING_TOP_REACTED_RANK_MULTIPLIER",
+                ),
+                file.top_reacted,
+                default.top_reacted,
+            )?,
+            affinity_author: self.source_weights(
+                (
+                    "ranking.affinity_author.base",
+                    "ranking.affinity_author.rank_multiplier",
+                ),
+                (
+                    "RANKING_AFFINITY_AUTHOR_BASE",
+                    "RANKING_AFFINITY_AUTHOR_RANK_MULTIPLIER",
+                ),
+                file.affinity_author,
+                default.affinity_author,
+            )?,
+            affinity_channel: self.source_weights(
+                (
+                    "ranking.affinity_channel.base",
+                    "ranking.affinity_channel.rank_multiplier",
+                ),
+                (
+                    "RANKING_AFFINITY_CHANNEL_BASE",
+                    "RANKING_AFFINITY_CHANNEL_RANK_MULTIPLIER",
+                ),
+                file.affinity_channel,
+                default.affinity_channel,
+            )?,
+            similar_user: self.source_weights(
+                (
+                    "ranking.similar_user.base",
+                    "ranking.similar_user.rank_multiplier",
+                ),
+                (
+                    "RANKING_SIMILAR_USER_BASE",
+                    "RANKING_SIMILAR_USER_RANK_MULTIPLIER",
+                ),
+                file.similar_user,
+                default.similar_user,
+            )?,
+            recent: self.source_weights(
+                ("ranking.recent.base", "ranking.recent.rank_multiplier"),
+                ("RANKING_RECENT_BASE", "RANKING_RECENT_RANK_MULTIPLIER"),
+                file.recent,
+                default.recent,
+            )?,
+            similar_content: self.source_weights(
+                (
+                    "ranking.similar_content.base",
+                    "ranking.similar_content.rank_multiplier",
+                ),
+                (
+                    "RANKING_SIMILAR_CONTENT_BASE",
+                    "RANKING_SIMILAR_CONTENT_RANK_MULTIPLIER",
+                ),
+                file.similar_content,
+                default.similar_content,
+            )?,
+            hidden_author_penalty: self.float(
+                "ranking.hidden_author_penalty",
+                "RANKING_HIDDEN_AUTHOR_PENALTY",
+                file.hidden_author_penalty,
+                default.hidden_author_penalty,
+            )?,
+            hidden_channel_penalty: self.float(
+                "ranking.hidden_channel_penalty",
+                "RANKING_HIDDEN_CHANNEL_PENALTY",
+                file.hidden_channel_penalty,
+                default.hidden_channel_penalty,
+            )?,
+            ignored_author_penalty: self.float(
+                "ranking.ignored_author_penalty",
+                "RANKING_IGNORED_AUTHOR_PENALTY",
+                file.ignored_author_penalty,
+                default.ignored_author_penalty,
+            )?,
+            ignored_channel_penalty: self.float(
+                "ranking.ignored_channel_penalty",
+                "RANKING_IGNORED_CHANNEL_PENALTY",
+                file.ignored_channel_penalty,
+                default.ignored_channel_penalty,
+            )?,
+            max_messages_per_author: self
+                .positive_integer(
+                    "ranking.max_messages_per_author",
+                    "RANKING_MAX_MESSAGES_PER_AUTHOR",
+                    file.max_messages_per_author,
+                )?
+                .map_or(default.max_messages_per_author, |v| v as usize),
+        };
+
+        weights.validate().map_err(|message| ConfigError::Invalid {
+            key: "ranking",
+            message,
+        })?;
+
+        Ok(weights)
+    }
+
+    /// Resolves the daily summary. The bot token is required once the channel is configured.
+    fn daily_summary(
+        &self,
+        file: FileDailySummaryConfig,
+    ) -> Result<Option<DailySummaryConfig>, ConfigError> {
+        let Some(channel_id) = self.string("DAILY_SUMMARY_CHANNEL_ID", file.channel_id)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(DailySummaryConfig {
+            channel_id: Uuid::parse_str(&channel_id).map_err(|e| ConfigError::Invalid {
+                key: "daily_summary.channel_id",
+                message: format!("{channel_id}: {e}"),
+            })?,
+            bot_token: self.required(
+                "daily_summary.bot_token",
+                "DAILY_SUMMARY_BOT_TOKEN",
+                file.bot_token,
+            )?,
+        }))
+    }
+
+    /// Resolves the embeddings API. The model is required once the API is configured.
+    fn embeddings(
+        &self,
+        file: FileEmbeddingsConfig,
+    ) -> Result<Option<EmbeddingsConfig>, ConfigError> {
+        let Some(api_base_url) = self.string("EMBEDDINGS_API_BASE_URL", file.api_base_url)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(EmbeddingsConfig {
+            api_base_url,
+            api_key: self.string("EMBEDDINGS_API_KEY", file.api_key)?,
+            model: self.required("embeddings.model", "EMBEDDINGS_MODEL", file.model)?,
+        }))
+    }
+
+    fn search(&self, file: FileSearchConfig) -> Result<Option<SearchConfig>, ConfigError> {
+        let Some(meilisearch_url) = self.string("MEILISEARCH_URL", file.meilisearch_url)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(SearchConfig {
+            meilisearch_url,
+            meilisearch_api_key: self.string("MEILISEARCH_API_KEY", file.meilisearch_api_key)?,
+            index: self
+                .string("MEILISEARCH_INDEX", file.index)?
+                .unwrap_or_else(|| DEFAULT_SEARCH_INDEX.to_string()),
+        }))
+    }
+
+    fn session(&self, file: FileSessionConfig) -> Result<SessionConfig, ConfigError> {
+        let same_site = match self
+            .string("SESSION_COOKIE_SAME_SITE", file.cookie_same_site)?
+            .as_deref()
+        {
+            None => SameSite::Lax,
+            Some(value) if value.eq_ignore_ascii_case("strict") => SameSite::Strict,
+            Some(value) if value.eq_ignore_ascii_case("lax") => SameSite::Lax,
+            Some(value) if value.eq_ignore_ascii_case("none") => SameSite::None,
+            Some(value) => {
+                return Err(ConfigError::Invalid {
+                    key: "session.cookie_same_site",
+                    message: format!("{value}: expected `strict`, `lax` or `none`"),
+                });
+            }
+        };
+        let secure = self
+            .boolean(
+                "session.cookie_secure",
+                "SESSION_COOKIE_SECURE",
+                file.cookie_secure,
+            )?
+            .unwrap_or(true);
+        // Browsers reject `SameSite=None` cookies without `Secure`
+        if same_site == SameSite::None && !secure {
+            return Err(ConfigError::Invalid {
+                key: "session.cookie_same_site",
+                message: "`none` requires `session.cookie_secure`".to_string(),
+            });
+        }
+
+        Ok(SessionConfig {
+            table_schema: self.required(
+                "session.table_schema",
+                "SESSION_TABLE_SCHEMA",
+                file.table_schema,
+            )?,
+            table_name: self.required(
+                "session.table_name",
+                "SESSION_TABLE_NAME",
+                file.table_name,
+            )?,
+            cookie: SessionCookieConfig {
+                name: self
+                    .string("SESSION_COOKIE_NAME", file.cookie_name)?
+                    .unwrap_or_else(|| DEFAULT_SESSION_COOKIE_NAME.to_string()),
+                secure,
+                domain: self.string("SESSION_COOKIE_DOMAIN", file.cookie_domain)?,
+                same_site,
+                max_age_secs: self.positive_integer(
+                    "session.cookie_max_age_secs",
+                    "SESSION_COOKIE_MAX_AGE_SECS",
+                    file.cookie_max_age_secs,
+                )?,
+            },
+        })
+    }
+
+    /// Resolves the signing keys. The active key may be omitted if there is only one key.
+    fn signing(&self, file: FileSigningConfig) -> Result<Option<KeyRing>, ConfigError> {
+        let keys = self.map("signing.keys", "SIGNING_KEYS", file.keys)?;
+        if keys.is_empty() {
+            return Ok(None);
+        }
+
+        let active_key = match self.string("SIGNING_ACTIVE_KEY", file.active_key)? {
+            Some(active_key) => active_key,
+            None if keys.len() == 1 => keys.keys().next().cloned().unwrap_or_default(),
+            None => {
+                return Err(ConfigError::Missing {
+                    key: "signing.active_key",
+                    env: "SIGNING_ACTIVE_KEY",
+                });
+            }
+        };
+        let keys = keys
+            .into_iter()
+            .map(|(id, secret)| SigningKey::new(id, secret))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|keys| KeyRing::new(active_key, keys))
+            .map_err(|e| ConfigError::Invalid {
+                key: "signing",
+                message: e.to_string(),
+            })?;
+
+        Ok(Some(keys))
+    }
+
+    /// Resolves the traQ budget. The rest of it is ignored unless a rate is set.
+    fn traq_budget(&self, file: &FileTraqConfig) -> Result<Option<TraqBudgetConfig>, ConfigError> {
+        let key = "traq.requests_per_second";
+        let env = "TRAQ_REQUESTS_PER_SECOND";
+        if file.requests_per_second.is_none() && self.env(env)?.is_none() {
+            return Ok(None);
+        }
+        let requests_per_second = self.float(key, env, file.requests_per_second, 0.0)?;
+        if !(requests_per_second.is_finite() && requests_per_second > 0.0) {
+            return Err(ConfigError::Invalid {
+                key,
+                message: format!("{requests_per_second}: must be positive"),
+            });
+        }
+
+        let key = "traq.interactive_share";
+        let interactive_share = self.float(
+            key,
+            "TRAQ_INTERACTIVE_SHARE",
+            file.interactive_share,
+            DEFAULT_TRAQ_INTERACTIVE_SHARE,
+        )?;
+        if !(0.0..1.0).contains(&interactive_share) {
+            return Err(ConfigError::Invalid {
+                key,
+                message: format!("{interactive_share}: must be at least 0 and less than 1"),
+            });
+        }
+
+        Ok(Some(TraqBudgetConfig {
+            requests_per_second,
+            burst: self
+                .positive_integer("traq.burst", "TRAQ_BURST", file.burst)?
+                .unwrap_or_else(|| (requests_per_second * DEFAULT_TRAQ_BURST_SECS).ceil() as i64),
+            interactive_share,
+            redis_url: self.string("TRAQ_BUDGET_REDIS_URL", file.budget_redis_url.clone())?,
+        }))
+    }
+
+    fn traq(&self, file: FileTraqConfig) -> Result<TraqConfig, ConfigError> {
+        let budget = self.traq_budget(&file)?;
+        let api_base_url =
+            self.required("traq.api_base_url", "TRAQ_API_BASE_URL", file.api_base_url)?;
+        // The web client is served from the same origin as the API by default
+        let web_base_url = self
+            .string("TRAQ_WEB_BASE_URL", file.web_base_url)?
+            .unwrap_or_else(|| {
+                api_base_url
+                    .trim_end_matches('/')
+                    .trim_end_matches("/api/v3")
+                    .to_string()
+            });
+
+        Ok(TraqConfig {
+            api_base_url,
+            web_base_url,
+            client_id: self.required("traq.client_id", "TRAQ_CLIENT_ID", file.client_id)?,
+            client_secret: self.required(
+                "traq.client_secret",
+                "TRAQ_CLIENT_SECRET",
+                file.client_secret,
+            )?,
+            budget,
+        })
+    }
+
+    /// Resolves a map. The environment variable holds comma-separated `key=value` pairs.
+    fn map(
+        &self,
+        key: &'static str,
+        env: &'static str,
+        file: Option<HashMap<String, String>>,
+    ) -> Result<HashMap<String, String>, ConfigError> {
+        let Some(value) = self.env(env)? else {
+            return Ok(file.unwrap_or_default());
+        };
+
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((k, v)) => Ok((k.trim().to_string(), v.trim().to_string())),
+                None => Err(ConfigError::Invalid {
+                    key,
+                    message: format!("expected `key=value`, got `{pair}`"),
+                }),
+            })
+            .collect()
+    }
+}
+
+impl AppConfig {
+    /// Loads the configuration from the given file (if any), overridden by environment variables.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let file = match path {
+            Some(path) => FileConfig::read(path)?,
+            None => FileConfig::default(),
+        };
+
+        Self::resolve(file, |key| env::var(key).ok())
+    }
+
+    fn resolve(
+        file: FileConfig,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let r = Resolver { lookup };
+
+        Ok(Self {
+            listen_address: r
+                .string("LISTEN_ADDRESS", file.listen_address)?
+                .unwrap_or_else(|| DEFAULT_LISTEN_ADDRESS.to_string()),
+            database_url: r.required("database_url", "DATABASE_URL", file.database_url)?,
+            secondary_database_url: r
+                .string("SECONDARY_DATABASE_URL", file.secondary_database_url)?,
+            admin_user_ids: r.uuids("admin_user_ids", "ADMIN_USER_IDS", file.admin_user_ids)?,
+            affinity: r.affinity(file.affinity)?,
+            api: r.api(file.api)?,
+            auth: AuthConfig {
+                token_login: r
+                    .boolean(
+                        "auth.token_login",
+                        "AUTH_TOKEN_LOGIN",
+                        file.auth.token_login,
+                    )?
+                    .unwrap_or_default(),
+            },
+            chaos: r.chaos(file.chaos)?,
+            compression: r.compression(file.compression)?,
+            daily_summary: r.daily_summary(file.daily_summary)?,
+            embeddings: r.embeddings(file.embeddings)?,
+            error_reporting: ErrorReportingConfig {
+                webhook_url: r.string(
+                    "ERROR_REPORTING_WEBHOOK_URL",
+                    file.error_reporting.webhook_url,
+                )?,
+            },
+            federation: FederationConfig {
+                publish_highlights: r
+                    .boolean(
+                        "federation.publish_highlights",
+                        "FEDERATION_PUBLISH_HIGHLIGHTS",
+                        file.federation.publish_highlights,
+                    )?
+                    .unwrap_or_default(),
+                peer_urls: r.strings("FEDERATION_PEER_URLS", file.federation.peer_urls)?,
+            },
+            instance: InstanceConfig {
+                name: r
+                    .string("INSTANCE_NAME", file.instance.name)?
+                    .unwrap_or_else(|| DEFAULT_INSTANCE_NAME.to_string()),
+            },
+            jobs: JobsConfig {
+                heartbeat_urls: r.map(
+                    "jobs.heartbeat_urls",
+                    "JOB_HEARTBEAT_URLS",
+                    file.jobs.heartbeat_urls,
+                )?,
+            },
+            link_previews: LinkPreviewsConfig {
+                enabled: r
+                    .boolean(
+                        "link_previews.enabled",
+                        "LINK_PREVIEWS_ENABLED",
+                        file.link_previews.enabled,
+                    )?
+                    .unwrap_or_default(),
+            },
+            notifications: NotificationsConfig {
+                flush_interval_secs: r.positive_integer(
+                    "notifications.flush_interval_secs",
+                    "NOTIFICATION_FLUSH_INTERVAL_SECS",
+                    file.notifications.flush_interval_secs,
+                )?,
+            },
+            public_timeline: PublicTimelineConfig {
+                channel_ids: r.uuids(
+                    "public_timeline.channel_ids",
+                    "PUBLIC_TIMELINE_CHANNEL_IDS",
+                    file.public_timeline.channel_ids,
+                )?,
+            },
+            quotas: QuotasConfig {
+                search_per_day: r.positive_integer(
+                    "quotas.search_per_day",
+                    "QUOTA_SEARCH_PER_DAY",
+                    file.quotas.search_per_day,
+                )?,
+            },
+            ranking: r.ranking(file.ranking)?,
+            rate_limit: RateLimitConfig {
+                requests_per_minute: r.positive_integer(
+                    "rate_limit.requests_per_minute",
+                    "RATE_LIMIT_REQUESTS_PER_MINUTE",
+                    file.rate_limit.requests_per_minute,
+                )?,
+            },
+            reports: ReportsConfig {
+                auto_hide_threshold: r.positive_integer(
+                    "reports.auto_hide_threshold",
+                    "REPORT_AUTO_HIDE_THRESHOLD",
+                    file.reports.auto_hide_threshold,
+                )?,
+            },
+            search: r.search(file.search)?,
+            session: r.session(file.session)?,
+            signing: r.signing(file.signing)?,
+            startup: StartupConfig {
+                strict: r
+                    .boolean("startup.strict", "STARTUP_STRICT", file.startup.strict)?
+                    .unwrap_or_default(),
+            },
+            traq: r.traq(file.traq)?,
+        })
+    }
+}
+
+/// Extracts the value of `--config <path>` (or `--config=<path>`) from command line arguments.
+pub fn config_path_from_args(args: impl IntoIterator<Item = String>) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, uuid::UUIDv4};
+    use std::collections::HashMap;
+
+    const TOML: &str = r#"
+        database_url = "mysql://file/twittra"
+
+        [session]
+        table_schema = "twittra"
+        table_name = "sessions"
+
+        [traq]
+        api_base_url = "https://q.example.com/api/v3"
+        client_id = "file_client_id"
+        client_secret = "file_client_secret"
+    "#;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn resolve_from_toml_file() {
+        let file = FileConfig::parse(TOML, ConfigFormat::Toml).unwrap();
+        let config = AppConfig::resolve(file, env(&[])).unwrap();
+
+        assert_eq!(config.listen_address, DEFAULT_LISTEN_ADDRESS);
+        assert_eq!(config.database_url, "mysql://file/twittra");
+        assert_eq!(config.secondary_database_url, None);
+        assert_eq!(config.session.table_name, "sessions");
+        assert_eq!(config.traq.client_id, "file_client_id");
+    }
+
+    #[test]
+    fn resolve_from_yaml_file() {
+        let yaml = r#"
+database_url: mysql://file/twittra
+session:
+  table_schema: twittra
+  table_name: sessions
+traq:
+  api_base_url: https://q.example.com/api/v3
+  client_id: file_client_id
+  client_secret: file_client_secret
+"#;
+        let file = FileConfig::parse(yaml, ConfigFormat::Yaml).unwrap();
+        let config = AppConfig::resolve(file, env(&[])).unwrap();
+
+        assert_eq!(config.session.table_schema, "twittra");
+        assert_eq!(config.traq.client_secret, "file_client_secret");
+    }
+
+    #[test]
+    fn env_overrides_file_values() {
+        let file = FileConfig::parse(TOML, ConfigFormat::Toml).unwrap();
+        let config = AppConfig::resolve(
+            file,
+            env(&[
+                ("DATABASE_URL", "mysql://env/twittra"),
+                ("TRAQ_CLIENT_SECRET", "env_client_secret"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.database_url, "mysql://env/twittra");
+        assert_eq!(config.traq.client_secret, "env_client_secret");
+        assert_eq!(config.traq.client_id, "file_client_id");
+    }
+
+    #[test]
+    fn secrets_are_read_from_files() {
+        let path = env::temp_dir().join(format!("twittra-secret-{}", UUIDv4.fake::<Uuid>()));
+        fs::write(&path, "file_secret\n").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let file = FileConfig::parse(TOML, ConfigFormat::Toml).unwrap();
+        let config =
+            AppConfig::resolve(file, env(&[("TRAQ_CLIENT_SECRET_FILE", path_str)])).unwrap();
+        assert_eq!(config.traq.client_secret, "file_secret");
+
+        // The variable itself takes precedence over the file
+        let file = FileConfig::parse(TOML, ConfigFormat::Toml).unwrap();
+        let config = AppConfig::resolve(
+            file,
+            env(&[
+                ("TRAQ_CLIENT_SECRET", "env_secret"),
+                ("TRAQ_CLIENT_SECRET_FILE", path_str),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.traq.client_secret, "env_secret");
+
+        fs::remove_file(&path).unwrap();
+        let file = FileConfig::parse(TOML, ConfigFormat::Toml).unwrap();
+        let err = AppConfig::resolve(file, env(&[("DATABASE_URL_FILE", path_str)])).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::ReadFile {
+                env: "DATABASE_URL",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn missing_required_value_is_reported() {
+        let err = AppConfig::resolve(FileConfig::default(), env(&[])).unwrap_err();
+
+        assert!(matches!(
+            err,
+            ConfigError::Missing {
+                env: "DATABASE_URL",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn instance_values_are_resolved() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(config.instance.name, DEFAULT_INSTANCE_NAME);
+        assert_eq!(config.traq.web_base_url, "https://q.example.com");
+
+        let toml = format!("{TOML}\n[instance]\nname = \"Example\"\n");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[("TRAQ_WEB_BASE_URL", "https://web.example.com")]),
+        )
+        .unwrap();
+        assert_eq!(config.instance.name, "Example");
+        assert_eq!(config.traq.web_base_url, "https://web.example.com");
+    }
+
+    #[test]
+    fn federation_values_are_resolved() {
+        let toml = format!(
+            "{TOML}\n[federation]\npublish_highlights = true\npeer_urls = [\"https://a.example.com\"]\n"
+        );
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(config.federation.publish_highlights);
+        assert_eq!(config.federation.peer_urls, vec!["https://a.example.com"]);
+
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[(
+                "FEDERATION_PEER_URLS",
+                "https://b.example.com, https://c.example.com",
+            )]),
+        )
+        .unwrap();
+        assert_eq!(
+            config.federation.peer_urls,
+            vec!["https://b.example.com", "https://c.example.com"]
+        );
+    }
+
+    #[test]
+    fn error_reporting_webhook_url_is_resolved() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(config.error_reporting.webhook_url, None);
+
+        let toml =
+            format!("{TOML}\n[error_reporting]\nwebhook_url = \"https://hooks.example.com\"\n");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(
+            config.error_reporting.webhook_url.as_deref(),
+            Some("https://hooks.example.com")
+        );
+    }
+
+    #[test]
+    fn session_cookie_values_are_resolved() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        let cookie = config.session.cookie;
+        assert_eq!(cookie.name, "id");
+        assert!(cookie.secure);
+        assert_eq!(cookie.domain, None);
+        assert_eq!(cookie.same_site, SameSite::Lax);
+        assert_eq!(cookie.max_age_secs, None);
+
+        let toml = TOML.replace(
+            "[session]",
+            "[session]\ncookie_name = \"twittra_session\"\ncookie_domain = \"example.com\"",
+        );
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[
+                ("SESSION_COOKIE_SECURE", "false"),
+                ("SESSION_COOKIE_SAME_SITE", "Strict"),
+                ("SESSION_COOKIE_MAX_AGE_SECS", "86400"),
+            ]),
+        )
+        .unwrap();
+        let cookie = config.session.cookie;
+        assert_eq!(cookie.name, "twittra_session");
+        assert!(!cookie.secure);
+        assert_eq!(cookie.domain.as_deref(), Some("example.com"));
+        assert_eq!(cookie.same_site, SameSite::Strict);
+        assert_eq!(cookie.max_age_secs, Some(86400));
+    }
+
+    #[test]
+    fn insecure_same_site_none_cookie_is_rejected() {
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[
+                ("SESSION_COOKIE_SECURE", "false"),
+                ("SESSION_COOKIE_SAME_SITE", "none"),
+            ]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "session.cookie_same_site",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn compression_min_size_fits_in_u16() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("COMPRESSION_MIN_SIZE_BYTES", "65535")]),
+        )
+        .unwrap();
+        assert_eq!(config.compression.min_size_bytes, u16::MAX);
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("COMPRESSION_MIN_SIZE_BYTES", "65536")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "compression.min_size_bytes",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn api_v1_sunset_is_an_rfc3339_date() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[
+                ("API_V1_DEPRECATED_AT", "2026-10-17T00:00:00Z"),
+                ("API_V1_SUNSET_AT", "2027-04-01T09:00:00+09:00"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            config.api.v1_sunset_at.map(OffsetDateTime::unix_timestamp),
+            Some(1_806_537_600)
+        );
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[
+                ("API_V1_DEPRECATED_AT", "2026-10-17T00:00:00Z"),
+                ("API_V1_SUNSET_AT", "2027-04-01"),
+            ]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "api.v1_sunset_at",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn api_v1_sunset_requires_deprecation() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(config.api.v1_deprecated_at.is_none());
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("API_V1_SUNSET_AT", "2027-04-01T00:00:00Z")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "api.v1_sunset_at",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn chaos_is_resolved_once_enabled() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("CHAOS_TRAQ_ERROR_RATE", "0.5")]),
+        )
+        .unwrap();
+        assert!(config.chaos.is_none());
+
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[
+                ("CHAOS_ENABLED", "true"),
+                ("CHAOS_REPOSITORY_LATENCY_MS", "200"),
+                ("CHAOS_TRAQ_ERROR_RATE", "0.5"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            config.chaos,
+            Some(FaultSettings {
+                repository: Faults {
+                    latency_ms: 200,
+                    error_rate: 0.0,
+                },
+                traq: Faults {
+                    latency_ms: 0,
+                    error_rate: 0.5,
+                },
+            })
+        );
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("CHAOS_ENABLED", "true"), ("CHAOS_TRAQ_ERROR_RATE", "1.5")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "chaos.traq_error_rate",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn traq_budget_is_resolved_once_a_rate_is_set() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(config.traq.budget.is_none());
+
+        let toml = TOML.replace("[traq]", "[traq]\nrequests_per_second = 2.5");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[("TRAQ_BUDGET_REDIS_URL", "redis://localhost")]),
+        )
+        .unwrap();
+        let budget = config.traq.budget.unwrap();
+        assert_eq!(budget.requests_per_second, 2.5);
+        assert_eq!(budget.burst, 25);
+        assert_eq!(budget.interactive_share, DEFAULT_TRAQ_INTERACTIVE_SHARE);
+        assert_eq!(budget.redis_url.as_deref(), Some("redis://localhost"));
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[("TRAQ_INTERACTIVE_SHARE", "1")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "traq.interactive_share",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn search_uses_the_default_index() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(config.search.is_none());
+
+        let toml = format!("{TOML}\n[search]\nmeilisearch_url = \"http://localhost:7700\"\n");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[("MEILISEARCH_API_KEY", "secret")]),
+        )
+        .unwrap();
+        let search = config.search.unwrap();
+        assert_eq!(search.meilisearch_url, "http://localhost:7700");
+        assert_eq!(search.meilisearch_api_key.as_deref(), Some("secret"));
+        assert_eq!(search.index, "messages");
+    }
+
+    #[test]
+    fn embeddings_require_a_model() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(config.embeddings.is_none());
+
+        let toml = format!(
+            "{TOML}\n[embeddings]\napi_base_url = \"https://api.example.com/v1\"\nmodel = \"text-embedding-3-small\"\n"
+        );
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[("EMBEDDINGS_API_KEY", "secret")]),
+        )
+        .unwrap();
+        let embeddings = config.embeddings.unwrap();
+        assert_eq!(embeddings.model, "text-embedding-3-small");
+        assert_eq!(embeddings.api_key.as_deref(), Some("secret"));
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("EMBEDDINGS_API_BASE_URL", "https://api.example.com/v1")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Missing {
+                env: "EMBEDDINGS_MODEL",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn daily_summary_requires_a_bot_token() {
+        const CHANNEL_ID: &str = "01234567-89ab-cdef-0123-456789abcdef";
+
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(config.daily_summary.is_none());
+
+        let toml = format!("{TOML}\n[daily_summary]\nchannel_id = \"{CHANNEL_ID}\"\n");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[("DAILY_SUMMARY_BOT_TOKEN", "secret")]),
+        )
+        .unwrap();
+        let daily_summary = config.daily_summary.unwrap();
+        assert_eq!(daily_summary.channel_id.to_string(), CHANNEL_ID);
+        assert_eq!(daily_summary.bot_token, "secret");
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("DAILY_SUMMARY_CHANNEL_ID", CHANNEL_ID)]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Missing {
+                env: "DAILY_SUMMARY_BOT_TOKEN",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn signing_keys_are_resolved() {
+        const SECRET: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(config.signing.is_none());
+
+        // A single key is active without being named
+        let toml = format!("{TOML}\n[signing.keys]\nk1 = \"{SECRET}\"\n");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(config.signing.unwrap().active_key_id(), "k1");
+
+        let keys = format!("k1={SECRET},k2={SECRET}");
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("SIGNING_KEYS", &keys)]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Missing {
+                env: "SIGNING_ACTIVE_KEY",
+                ..
+            }
+        ));
+
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("SIGNING_KEYS", &keys), ("SIGNING_ACTIVE_KEY", "k2")]),
+        )
+        .unwrap();
+        let signing = config.signing.unwrap();
+        assert_eq!(signing.active_key_id(), "k2");
+        assert_eq!(signing.len(), 2);
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("SIGNING_KEYS", "k1=short")]),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { key: "signing", .. }));
+    }
+
+    #[test]
+    fn startup_strict_is_resolved() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(!config.startup.strict);
+
+        let toml = format!("{TOML}\n[startup]\nstrict = true\n");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(config.startup.strict);
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("STARTUP_STRICT", "yes")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "startup.strict",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn token_login_is_off_unless_enabled() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert!(!config.auth.token_login);
+
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("AUTH_TOKEN_LOGIN", "true")]),
+        )
+        .unwrap();
+        assert!(config.auth.token_login);
+    }
+
+    #[test]
+    fn admin_user_ids_are_parsed() {
+        let id = Uuid::from_u128(1);
+        let other = Uuid::from_u128(2);
+        let toml = format!("admin_user_ids = [\"{id}\"]\n{TOML}");
+
+        let file = FileConfig::parse(&toml, ConfigFormat::Toml).unwrap();
+        let config = AppConfig::resolve(file, env(&[])).unwrap();
+        assert_eq!(config.admin_user_ids, vec![id]);
+
+        let file = FileConfig::parse(&toml, ConfigFormat::Toml).unwrap();
+        let ids = format!("{id}, {other}");
+        let config = AppConfig::resolve(file, env(&[("ADMIN_USER_IDS", &ids)])).unwrap();
+        assert_eq!(config.admin_user_ids, vec![id, other]);
+    }
+
+    #[test]
+    fn invalid_admin_user_id_is_reported() {
+        let file = FileConfig::parse(TOML, ConfigFormat::Toml).unwrap();
+        let err = AppConfig::resolve(file, env(&[("ADMIN_USER_IDS", "not-a-uuid")])).unwrap_err();
+
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "admin_user_ids",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn heartbeat_urls_are_resolved() {
+        let toml = format!(
+            "{TOML}\n[jobs.heartbeat_urls]\nmessage_crawler = \"https://hc.example.com/file\"\n"
+        );
+        let file = FileConfig::parse(&toml, ConfigFormat::Toml).unwrap();
+        let config = AppConfig::resolve(file, env(&[])).unwrap();
+        assert_eq!(
+            config.jobs.heartbeat_urls.get("message_crawler").unwrap(),
+            "https://hc.example.com/file"
+        );
+
+        let file = FileConfig::parse(&toml, ConfigFormat::Toml).unwrap();
+        let config = AppConfig::resolve(
+            file,
+            env(&[(
+                "JOB_HEARTBEAT_URLS",
+                "session_cleanup=https://hc.example.com/env?a=b",
+            )]),
+        )
+        .unwrap();
+        assert_eq!(config.jobs.heartbeat_urls.len(), 1);
+        assert_eq!(
+            config.jobs.heartbeat_urls.get("session_cleanup").unwrap(),
+            "https://hc.example.com/env?a=b"
+        );
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("JOB_HEARTBEAT_URLS", "no_url")]),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { .. }));
+    }
+
+    #[test]
+    fn ranking_weights_are_resolved() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(config.ranking, RankingWeights::default());
+
+        let toml = format!(
+            "{TOML}\n[ranking]\nhidden_author_penalty = 0.25\n[ranking.affinity_channel]\nbase = 4.0\n"
+        );
+        let file = FileConfig::parse(&toml, ConfigFormat::Toml).unwrap();
+        let config = AppConfig::resolve(
+            file,
+            env(&[("RANKING_AFFINITY_CHANNEL_RANK_MULTIPLIER", "0.2")]),
+        )
+        .unwrap();
+        assert_eq!(config.ranking.hidden_author_penalty, 0.25);
+        assert_eq!(config.ranking.max_messages_per_author, 3);
+        assert_eq!(
+            config.ranking.affinity_channel,
+            SourceWeights::new(4.0, 0.2)
+        );
+        assert_eq!(
+            config.ranking.top_reacted,
+            RankingWeights::default().top_reacted
+        );
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("RANKING_HIDDEN_CHANNEL_PENALTY", "2")]),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { key: "ranking", .. }));
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("RANKING_TOP_REACTED_BASE", "high")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "ranking.top_reacted.base",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn affinity_half_life_is_resolved() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(
+            config.affinity.half_life_days,
+            DEFAULT_AFFINITY_HALF_LIFE_DAYS
+        );
+
+        let toml = format!("{TOML}\n[affinity]\nhalf_life_days = 7.5\n");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(config.affinity.half_life_days, 7.5);
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("AFFINITY_HALF_LIFE_DAYS", "0")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "affinity.half_life_days",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn report_auto_hide_threshold_is_resolved() {
+        let config = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(config.reports.auto_hide_threshold, None);
+
+        let toml = format!("{TOML}\n[reports]\nauto_hide_threshold = 3\n");
+        let config = AppConfig::resolve(
+            FileConfig::parse(&toml, ConfigFormat::Toml).unwrap(),
+            env(&[]),
+        )
+        .unwrap();
+        assert_eq!(config.reports.auto_hide_threshold, Some(3));
+
+        let err = AppConfig::resolve(
+            FileConfig::parse(TOML, ConfigFormat::Toml).unwrap(),
+            env(&[("REPORT_AUTO_HIDE_THRESHOLD", "0")]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                key: "reports.auto_hide_threshold",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let result = FileConfig::parse("databse_url = \"typo\"", ConfigFormat::Toml);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn config_path_is_read_from_args() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            config_path_from_args(args(&["app", "--config", "config.toml"])),
+            Some(PathBuf::from("config.toml"))
+        );
+        assert_eq!(
+            config_path_from_args(args(&["app", "--config=config.yaml"])),
+            Some(PathBuf::from("config.yaml"))
+        );
+        assert_eq!(config_path_from_args(args(&["app"])), None);
+    }
+}
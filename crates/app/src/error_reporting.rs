@@ -0,0 +1,239 @@
+//! Reports errors and panics to an external webhook, e.g. an alerting service or chat channel.
+//!
+//! Every `ERROR` level tracing event is forwarded. User IDs are scrubbed before reports leave the
+//! server, since errors are often logged with the ID of the user who hit them. If signing keys are
+//! configured, reports are signed so that the receiver can verify them with
+//! [`infra::signing::webhook::WebhookVerifier`].
+
+use http::header::CONTENT_TYPE;
+use infra::signing::{
+    KeyRing,
+    webhook::{self, SIGNATURE_HEADER},
+};
+use reqwest::Client;
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt, panic, time::Duration};
+use time::OffsetDateTime;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{Layer, layer::Context};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const REDACTED: &str = "[redacted]";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorReport {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    /// `error` for tracing events and `panic` for panics.
+    pub kind: &'static str,
+    /// The module path or source location the error occurred in.
+    pub target: String,
+    pub message: String,
+    pub fields: BTreeMap<String, String>,
+    pub version: &'static str,
+}
+
+impl ErrorReport {
+    fn new(kind: &'static str, target: String, message: String) -> Self {
+        Self {
+            timestamp: OffsetDateTime::now_utc(),
+            kind,
+            target,
+            message: scrub(&message),
+            fields: BTreeMap::new(),
+            version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
+
+/// Sends reports to the webhook in the background, so that logging never waits for the network.
+#[derive(Clone, Debug)]
+pub struct ErrorReporter {
+    tx: UnboundedSender<ErrorReport>,
+}
+
+impl ErrorReporter {
+    /// Starts sending reports to the webhook, signed with `keys` if any. Must be called within a
+    /// Tokio runtime.
+    pub fn start(webhook_url: String, keys: Option<KeyRing>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(send_reports(Client::new(), webhook_url, keys, rx));
+
+        Self { tx }
+    }
+
+    fn report(&self, report: ErrorReport) {
+        // The receiver only stops when the runtime shuts down
+        let _ = self.tx.send(report);
+    }
+
+    /// A tracing layer that reports `ERROR` events.
+    pub fn layer(&self) -> ErrorReportingLayer {
+        ErrorReportingLayer {
+            reporter: self.clone(),
+        }
+    }
+
+    /// Reports panics, then runs the previously installed panic hook.
+    pub fn install_panic_hook(&self) {
+        let reporter = self.clone();
+        let previous = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            let target = info
+                .location()
+                .map(|l| format!("{}:{}", l.file(), l.line()))
+                .unwrap_or_default();
+            let message = match info.payload().downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => match info.payload().downcast_ref::<String>() {
+                    Some(message) => message.clone(),
+                    None => "Box<dyn Any>".to_string(),
+                },
+            };
+            reporter.report(ErrorReport::new("panic", target, message));
+
+            previous(info);
+        }));
+    }
+}
+
+async fn send_reports(
+    client: Client,
+    webhook_url: String,
+    keys: Option<KeyRing>,
+    mut rx: UnboundedReceiver<ErrorReport>,
+) {
+    while let Some(report) = rx.recv().await {
+        let body = match serde_json::to_vec(&report) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Failed to serialize error report: {:?}", e);
+                continue;
+            }
+        };
+        let mut request = client
+            .post(&webhook_url)
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(keys) = &keys {
+            let signature = webhook::sign(keys, &body, OffsetDateTime::now_utc());
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        let result = request
+            .body(body)
+            .timeout(WEBHOOK_TIMEOUT)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status());
+
+        // Logged as a warning so that the failure is not reported again
+        if let Err(e) = result {
+            tracing::warn!("Failed to send error report: {:?}", e);
+        }
+    }
+}
+
+pub struct ErrorReportingLayer {
+    reporter: ErrorReporter,
+}
+
+impl<S: Subscriber> Layer<S> for ErrorReportingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+
+        let mut visitor = ReportVisitor::default();
+        event.record(&mut visitor);
+
+        let mut report = ErrorReport::new(
+            "error",
+            event.metadata().target().to_string(),
+            visitor.message,
+        );
+        report.fields = visitor.fields;
+        self.reporter.report(report);
+    }
+}
+
+#[derive(Default)]
+struct ReportVisitor {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for ReportVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{value:?}");
+
+        if field.name() == "message" {
+            self.message = value;
+        } else if is_user_field(field.name()) {
+            self.fields
+                .insert(field.name().to_string(), REDACTED.to_string());
+        } else {
+            self.fields.insert(field.name().to_string(), scrub(&value));
+        }
+    }
+}
+
+fn is_user_field(name: &str) -> bool {
+    name == "user" || name.ends_with("user_id")
+}
+
+/// Replaces UUIDs, since user IDs cannot be told apart from other IDs in free text.
+fn scrub(text: &str) -> String {
+    const UUID_LEN: usize = 36;
+
+    let mut scrubbed = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if rest.len() >= UUID_LEN && is_uuid(&rest.as_bytes()[..UUID_LEN]) {
+            scrubbed.push_str(REDACTED);
+            rest = &rest[UUID_LEN..];
+            continue;
+        }
+
+        let mut chars = rest.chars();
+        if let Some(c) = chars.next() {
+            scrubbed.push(c);
+        }
+        rest = chars.as_str();
+    }
+
+    scrubbed
+}
+
+fn is_uuid(bytes: &[u8]) -> bool {
+    bytes.iter().enumerate().all(|(i, b)| match i {
+        8 | 13 | 18 | 23 => *b == b'-',
+        _ => b.is_ascii_hexdigit(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrub_replaces_uuids() {
+        let text = "user 0193e9a4-7b2c-7def-8a12-3456789abcde not found (retry 2)";
+
+        assert_eq!(scrub(text), "user [redacted] not found (retry 2)");
+        assert_eq!(scrub("no ids here: 日本語"), "no ids here: 日本語");
+    }
+
+    #[test]
+    fn user_fields_are_redacted() {
+        assert!(is_user_field("user_id"));
+        assert!(is_user_field("muted_user_id"));
+        assert!(is_user_field("user"));
+        assert!(!is_user_field("message_id"));
+    }
+}
@@ -0,0 +1,177 @@
+//! Entity tags, so that clients can revalidate the responses they cached with `If-None-Match`.
+
+use axum::{
+    body::{self, Body, HttpBody},
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, IntoResponseParts, Response},
+};
+use http::{
+    HeaderMap, HeaderValue, StatusCode,
+    header::{CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+/// The length of the hash prefix in an entity tag. It only has to tell versions of the same
+/// resource apart.
+const TAG_BYTES: usize = 16;
+
+/// Responses larger than this are served untagged rather than buffered.
+const MAX_TAGGED_BODY: usize = 4 * 1024 * 1024;
+
+/// Returns a strong entity tag of `body`, e.g. `"0123abcd..."`.
+pub fn of(body: &[u8]) -> HeaderValue {
+    tag(body, "")
+}
+
+/// Returns a weak entity tag of `body`, e.g. `W/"0123abcd..."`. Unlike strong tags, weak tags
+/// stay valid if the body is encoded differently on the way, e.g. compressed.
+pub fn weak(body: &[u8]) -> HeaderValue {
+    tag(body, "W/")
+}
+
+fn tag(body: &[u8], prefix: &str) -> HeaderValue {
+    let hash = Sha256::digest(body);
+    let mut tag = String::with_capacity(prefix.len() + TAG_BYTES * 2 + 2);
+    tag.push_str(prefix);
+    tag.push('"');
+    for byte in &hash[..TAG_BYTES] {
+        write!(tag, "{byte:02x}").expect("writing to a String doesn't fail");
+    }
+    tag.push('"');
+    HeaderValue::from_str(&tag).expect("the tag consists of hex digits")
+}
+
+/// Whether `If-None-Match` in `headers` matches `etag`, comparing weakly as `If-None-Match`
+/// requires.
+pub fn matches(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let etag = opaque(etag.as_bytes());
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .flat_map(|value| value.as_bytes().split(|&b| b == b','))
+        .map(|tag| tag.trim_ascii())
+        .any(|tag| tag == b"*" || opaque(tag) == etag)
+}
+
+fn opaque(tag: &[u8]) -> &[u8] {
+    tag.strip_prefix(b"W/").unwrap_or(tag)
+}
+
+/// Responds with `304 Not Modified` if the client already has `body`, and with `parts` and `body`
+/// otherwise, tagging either with the entity tag of `body`.
+pub fn respond(headers: &HeaderMap, parts: impl IntoResponseParts, body: Vec<u8>) -> Response {
+    let etag = of(&body);
+    if matches(headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+    }
+    ([(ETAG, etag)], parts, body).into_response()
+}
+
+/// Middleware tagging successful responses with a weak entity tag of their body, and responding
+/// with `304 Not Modified` without the body if the tag matches `If-None-Match`. Handlers still
+/// run, so this saves bandwidth for polling clients rather than work.
+pub async fn conditional(request: Request, next: Next) -> Response {
+    let if_none_match: HeaderMap = request
+        .headers()
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .map(|value| (IF_NONE_MATCH, value.clone()))
+        .collect();
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK
+        || response.headers().contains_key(ETAG)
+        || response
+            .body()
+            .size_hint()
+            .upper()
+            .is_none_or(|size| size > MAX_TAGGED_BODY as u64)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match body::to_bytes(body, MAX_TAGGED_BODY).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to buffer the response: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = weak(&body);
+    let not_modified = matches(&if_none_match, &etag);
+    parts.headers.insert(ETAG, etag);
+    if not_modified {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(CONTENT_TYPE);
+        parts.headers.remove(CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, middleware, routing::get};
+    use tower::ServiceExt;
+
+    fn if_none_match(value: &'static str) -> HeaderMap {
+        HeaderMap::from_iter([(IF_NONE_MATCH, HeaderValue::from_static(value))])
+    }
+
+    #[test]
+    fn tags_depend_on_the_body() {
+        assert_eq!(of(b"a"), of(b"a"));
+        assert_ne!(of(b"a"), of(b"b"));
+        assert_eq!(of(b"a").len(), TAG_BYTES * 2 + 2);
+    }
+
+    #[test]
+    fn if_none_match_is_compared_weakly() {
+        let etag = of(b"a");
+        let tag = etag.to_str().unwrap();
+
+        assert!(matches(
+            &HeaderMap::from_iter([(
+                IF_NONE_MATCH,
+                HeaderValue::from_str(&format!("\"other\", W/{tag}")).unwrap()
+            )]),
+            &etag
+        ));
+        assert!(matches(&if_none_match("*"), &etag));
+        assert!(!matches(&if_none_match("\"other\""), &etag));
+        assert!(!matches(&HeaderMap::new(), &etag));
+    }
+
+    #[tokio::test]
+    async fn conditional_responds_not_modified_to_matching_requests() {
+        let app = Router::new()
+            .route("/", get(|| async { Json(vec![1, 2, 3]) }))
+            .layer(middleware::from_fn(conditional));
+
+        let res = app
+            .clone()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers()[ETAG].clone();
+        assert_eq!(etag, weak(b"[1,2,3]"));
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"[1,2,3]");
+
+        let req = Request::builder()
+            .uri("/")
+            .header(IF_NONE_MATCH, etag.clone())
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()[ETAG], etag);
+        assert!(!res.headers().contains_key(CONTENT_TYPE));
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+    }
+}
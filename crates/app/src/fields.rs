@@ -0,0 +1,128 @@
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// The field that is always kept so that clients can identify items.
+const ID_FIELD: &str = "id";
+
+/// Query parameters for selecting the fields of list items.
+#[derive(Debug, Default, Deserialize)]
+pub struct FieldsQuery {
+    /// Comma-separated JSON field names, e.g. `id,userId,createdAt`.
+    pub fields: Option<String>,
+}
+
+impl FieldsQuery {
+    fn field_set(&self) -> Option<HashSet<String>> {
+        let fields = self.fields.as_deref()?;
+        let mut set: HashSet<String> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+            .collect();
+        if set.is_empty() {
+            return None;
+        }
+        set.insert(ID_FIELD.to_string());
+
+        Some(set)
+    }
+}
+
+/// A JSON response that only keeps the requested top-level fields of each item.
+/// All fields are kept if no fields are requested.
+pub struct SparseJson<T> {
+    value: T,
+    fields: Option<HashSet<String>>,
+}
+
+impl<T> SparseJson<T> {
+    pub fn new(value: T, query: &FieldsQuery) -> Self {
+        Self {
+            value,
+            fields: query.field_set(),
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for SparseJson<T> {
+    fn into_response(self) -> Response {
+        let Some(fields) = self.fields else {
+            return Json(self.value).into_response();
+        };
+
+        let mut value = match serde_json::to_value(self.value) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::error!("{:?}", e);
+
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+        retain_fields(&mut value, &fields);
+
+        Json(value).into_response()
+    }
+}
+
+fn retain_fields(value: &mut Value, fields: &HashSet<String>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                retain_fields(item, fields);
+            }
+        }
+        Value::Object(map) => map.retain(|key, _| fields.contains(key)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(fields: &str) -> FieldsQuery {
+        FieldsQuery {
+            fields: Some(fields.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_retain_fields_in_list() {
+        let mut value = json!([
+            { "id": 1, "content": "hello", "reactions": [] },
+            { "id": 2, "content": "world", "reactions": [] },
+        ]);
+        let fields = query("content").field_set().unwrap();
+
+        retain_fields(&mut value, &fields);
+
+        assert_eq!(
+            value,
+            json!([{ "id": 1, "content": "hello" }, { "id": 2, "content": "world" }])
+        );
+    }
+
+    #[test]
+    fn test_field_set_ignores_blank_names() {
+        let fields = query(" userId , ,createdAt").field_set().unwrap();
+
+        assert_eq!(fields.len(), 3);
+        assert!(fields.contains("userId"));
+        assert!(fields.contains("createdAt"));
+        assert!(fields.contains(ID_FIELD));
+    }
+
+    #[test]
+    fn test_empty_fields_keep_everything() {
+        assert!(query("").field_set().is_none());
+        assert!(FieldsQuery::default().field_set().is_none());
+    }
+}
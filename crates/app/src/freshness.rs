@@ -0,0 +1,135 @@
+//! How fresh the recommended timelines are when they are served, which shows whether the crawler
+//! and scoring keep up: old newest messages mean crawling lags behind, and read messages mean read
+//! messages slip through the candidate filters.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::Mutex};
+use time::Duration;
+use utoipa::ToSchema;
+
+/// The number of recent timelines the report is computed over.
+const SAMPLES: usize = 1000;
+
+/// Statistics of the most recently served recommended timelines.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FreshnessReport {
+    /// The timelines the statistics are computed over, at most the last 1000 served.
+    pub timelines: usize,
+    /// The median age of the newest message of each timeline in seconds, or null if no timeline
+    /// had messages.
+    pub newest_message_age_p50_secs: Option<i64>,
+    pub newest_message_age_p90_secs: Option<i64>,
+    pub newest_message_age_max_secs: Option<i64>,
+    /// The percentage of the messages served that the user had already read, or null if no
+    /// message was served.
+    pub read_percentage: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    newest_message_age: Option<Duration>,
+    messages: usize,
+    read: usize,
+}
+
+/// Keeps the freshness of the last served timelines in memory, per process.
+#[derive(Debug, Default)]
+pub struct TimelineFreshness {
+    samples: Mutex<VecDeque<Sample>>,
+}
+
+impl TimelineFreshness {
+    /// Records a timeline of `messages` messages, of which `read` had already been read.
+    pub fn record(&self, newest_message_age: Option<Duration>, messages: usize, read: usize) {
+        tracing::debug!(
+            newest_message_age_secs = newest_message_age.map(|age| age.whole_seconds()),
+            messages,
+            read,
+            "Timeline freshness"
+        );
+
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        if samples.len() == SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(Sample {
+            newest_message_age,
+            messages,
+            read,
+        });
+    }
+
+    pub fn report(&self) -> FreshnessReport {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let mut ages: Vec<i64> = samples
+            .iter()
+            .filter_map(|s| s.newest_message_age)
+            .map(|age| age.whole_seconds())
+            .collect();
+        ages.sort_unstable();
+        let percentile = |p: usize| (!ages.is_empty()).then(|| ages[(ages.len() - 1) * p / 100]);
+
+        let messages: usize = samples.iter().map(|s| s.messages).sum();
+        let read: usize = samples.iter().map(|s| s.read).sum();
+
+        FreshnessReport {
+            timelines: samples.len(),
+            newest_message_age_p50_secs: percentile(50),
+            newest_message_age_p90_secs: percentile(90),
+            newest_message_age_max_secs: ages.last().copied(),
+            read_percentage: (messages > 0).then(|| read as f64 * 100.0 / messages as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_summarizes_recorded_timelines() {
+        let freshness = TimelineFreshness::default();
+        assert_eq!(
+            freshness.report(),
+            FreshnessReport {
+                timelines: 0,
+                newest_message_age_p50_secs: None,
+                newest_message_age_p90_secs: None,
+                newest_message_age_max_secs: None,
+                read_percentage: None,
+            }
+        );
+
+        for secs in 1..=10 {
+            freshness.record(Some(Duration::seconds(secs)), 10, 1);
+        }
+        // Empty timelines have no newest message
+        freshness.record(None, 0, 0);
+
+        assert_eq!(
+            freshness.report(),
+            FreshnessReport {
+                timelines: 11,
+                newest_message_age_p50_secs: Some(5),
+                newest_message_age_p90_secs: Some(9),
+                newest_message_age_max_secs: Some(10),
+                read_percentage: Some(10.0),
+            }
+        );
+    }
+
+    #[test]
+    fn only_recent_timelines_are_kept() {
+        let freshness = TimelineFreshness::default();
+        freshness.record(Some(Duration::hours(1)), 1, 1);
+        for _ in 0..SAMPLES {
+            freshness.record(Some(Duration::seconds(1)), 1, 0);
+        }
+
+        let report = freshness.report();
+        assert_eq!(report.timelines, SAMPLES);
+        assert_eq!(report.newest_message_age_max_secs, Some(1));
+        assert_eq!(report.read_percentage, Some(0.0));
+    }
+}
@@ -0,0 +1,512 @@
+//! A GraphQL endpoint over the timeline and traQ services, for clients that want exactly the
+//! fields they show, such as a message with its author, channel and reactions, in one request.
+//!
+//! Only reads are served; changes go through the REST API. Authors, channels and stamps are taken
+//! from the message when the server has them cached, and resolved through the traQ service
+//! otherwise. Users are loaded together, once per request, however many messages and reactions
+//! refer to them. Unlike `GET /timeline`, the timeline query doesn't record impressions.
+
+use crate::{
+    handler::{AppState, onboarding},
+    problem::{ApiError, AppError},
+    rate_limit,
+    session::CurrentUser,
+};
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Error, ErrorExtensions, Object,
+    dataloader::{DataLoader, Loader},
+};
+use axum::{
+    Extension, Json,
+    extract::State,
+    middleware,
+    routing::{self, MethodRouter},
+};
+use domain::{error::DomainError, id::UserId, model, service::TraqService};
+use http::StatusCode;
+use std::{collections::HashMap, sync::Arc};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub const PATH: &str = "/api/graphql";
+/// Enough for a message's reactions with their stamps and users, but not for queries crafted to
+/// be expensive.
+const MAX_DEPTH: usize = 8;
+/// Each field counts as one, so this allows a timeline with every field of its messages, but not
+/// the same fields repeated under many aliases.
+const MAX_COMPLEXITY: usize = 100;
+
+type Schema = async_graphql::Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// The GraphQL endpoint, answering like the REST API to users who haven't signed in, completed
+/// onboarding, or stayed within their rate limit.
+pub fn route(state: &AppState) -> MethodRouter<AppState> {
+    let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(state.clone())
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish();
+
+    routing::post(execute)
+        .layer(Extension(schema))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            onboarding::require_onboarding,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit,
+        ))
+}
+
+async fn execute(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Extension(schema): Extension<Schema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let users = DataLoader::new(UserLoader(state.traq_service), tokio::spawn);
+    Json(
+        schema
+            .execute(request.data(UserId::from(user.id)).data(users))
+            .await,
+    )
+}
+
+fn state<'a>(ctx: &Context<'a>) -> &'a AppState {
+    ctx.data_unchecked()
+}
+
+/// Converts a service error like the REST API does, describing only errors caused by the client.
+fn error(e: DomainError) -> Error {
+    let e = AppError(e);
+    e.log();
+    let problem = ApiError::from(e.0);
+
+    Error::new(problem.detail.unwrap_or(problem.title))
+        .extend_with(|_, extensions| extensions.set("status", problem.status))
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// The recommended timeline of the signed-in user.
+    async fn timeline(&self, ctx: &Context<'_>) -> Result<Vec<Message>, Error> {
+        let user_id = ctx.data_unchecked::<UserId>();
+        let messages = state(ctx)
+            .timeline_service
+            .get_recommended_messages(user_id.as_uuid())
+            .await
+            .map_err(error)?;
+
+        Ok(messages.into_iter().map(Message).collect())
+    }
+
+    /// The conversation the message belongs to, oldest first.
+    async fn thread(&self, ctx: &Context<'_>, message_id: Uuid) -> Result<Vec<Message>, Error> {
+        let user_id = ctx.data_unchecked::<UserId>();
+        let messages = state(ctx)
+            .timeline_service
+            .get_thread(user_id.as_uuid(), &message_id)
+            .await
+            .map_err(error)?;
+
+        Ok(messages.into_iter().map(Message).collect())
+    }
+
+    async fn user(&self, ctx: &Context<'_>, id: Uuid) -> Result<User, Error> {
+        fetch_user(ctx, &id).await
+    }
+
+    async fn channel(&self, ctx: &Context<'_>, id: Uuid) -> Result<Channel, Error> {
+        fetch_channel(ctx, &id).await
+    }
+
+    /// The channels synced from traQ, ordered by path.
+    async fn channels(&self, ctx: &Context<'_>) -> Result<Vec<Channel>, Error> {
+        let channels = state(ctx)
+            .traq_service
+            .get_channels()
+            .await
+            .map_err(error)?;
+
+        Ok(channels.into_iter().map(Channel).collect())
+    }
+
+    async fn stamp(&self, ctx: &Context<'_>, id: Uuid) -> Result<Stamp, Error> {
+        fetch_stamp(ctx, &id).await
+    }
+}
+
+/// Loads the users requested while resolving a query in one call to the traQ service.
+struct UserLoader(Arc<dyn TraqService>);
+
+impl Loader<Uuid> for UserLoader {
+    type Value = model::User;
+    type Error = Error;
+
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, model::User>, Error> {
+        self.0.get_users_by_ids(keys).await.map_err(error)
+    }
+}
+
+async fn fetch_user(ctx: &Context<'_>, id: &Uuid) -> Result<User, Error> {
+    let user = ctx
+        .data_unchecked::<DataLoader<UserLoader>>()
+        .load_one(*id)
+        .await?
+        .ok_or_else(|| {
+            Error::new("Not Found").extend_with(|_, extensions| {
+                extensions.set("status", StatusCode::NOT_FOUND.as_u16())
+            })
+        })?;
+
+    Ok(User(user))
+}
+
+async fn fetch_channel(ctx: &Context<'_>, id: &Uuid) -> Result<Channel, Error> {
+    state(ctx)
+        .traq_service
+        .get_channel_by_id(id)
+        .await
+        .map(Channel)
+        .map_err(error)
+}
+
+async fn fetch_stamp(ctx: &Context<'_>, id: &Uuid) -> Result<Stamp, Error> {
+    state(ctx)
+        .traq_service
+        .get_stamp_by_id(id)
+        .await
+        .map(Stamp)
+        .map_err(error)
+}
+
+pub struct Message(model::MessageListItem);
+
+#[Object]
+impl Message {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn content(&self) -> &str {
+        &self.0.content
+    }
+
+    /// The user who posted the message.
+    async fn author(&self, ctx: &Context<'_>) -> Result<User, Error> {
+        match &self.0.user {
+            Some(user) => Ok(User(user.clone())),
+            None => fetch_user(ctx, &self.0.user_id).await,
+        }
+    }
+
+    /// The channel the message was posted in.
+    async fn channel(&self, ctx: &Context<'_>) -> Result<Channel, Error> {
+        match &self.0.channel {
+            Some(channel) => Ok(Channel(channel.clone())),
+            None => fetch_channel(ctx, &self.0.channel_id).await,
+        }
+    }
+
+    /// The ID of the message this message replies to, i.e. the first message it cites.
+    async fn reply_to_message_id(&self) -> Option<Uuid> {
+        self.0.reply_to_message_id
+    }
+
+    async fn reactions(&self) -> Vec<Reaction> {
+        self.0.reactions.iter().cloned().map(Reaction).collect()
+    }
+
+    async fn created_at(&self) -> OffsetDateTime {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> OffsetDateTime {
+        self.0.updated_at
+    }
+}
+
+pub struct Reaction(model::Reaction);
+
+#[Object]
+impl Reaction {
+    async fn stamp(&self, ctx: &Context<'_>) -> Result<Stamp, Error> {
+        match &self.0.stamp_name {
+            Some(name) => Ok(Stamp(model::Stamp {
+                id: self.0.stamp_id,
+                name: name.clone(),
+            })),
+            None => fetch_stamp(ctx, &self.0.stamp_id).await,
+        }
+    }
+
+    async fn user(&self, ctx: &Context<'_>) -> Result<User, Error> {
+        fetch_user(ctx, &self.0.user_id).await
+    }
+
+    /// How many times the user added the stamp.
+    async fn count(&self) -> i32 {
+        self.0.stamp_count
+    }
+}
+
+pub struct User(model::User);
+
+#[Object]
+impl User {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn handle(&self) -> &str {
+        &self.0.handle
+    }
+
+    async fn display_name(&self) -> &str {
+        &self.0.display_name
+    }
+}
+
+pub struct Channel(model::Channel);
+
+#[Object]
+impl Channel {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    /// The name of the channel, without its parents.
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// The names of the channel and its parents joined with `/`, like `general/random`.
+    async fn path(&self) -> &str {
+        &self.0.path
+    }
+
+    async fn archived(&self) -> bool {
+        self.0.archived
+    }
+}
+
+pub struct Stamp(model::Stamp);
+
+#[Object]
+impl Stamp {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use domain::{
+        error::RepositoryError,
+        service::{MockTimelineService, MockTraqService},
+        test_factories::{MessageListItemBuilder, ReactionBuilder, StampBuilder, UserBuilder},
+    };
+    use http::header;
+    use mockall::predicate;
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    fn request(query: &str) -> Request<Body> {
+        Request::builder()
+            .uri(PATH)
+            .method("POST")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "query": query }).to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn timeline_resolves_authors_and_stamps_not_cached() {
+        let user = UserBuilder::new().build();
+        let author = UserBuilder::new().build();
+        let stamp = StampBuilder::new().build();
+        let message = MessageListItemBuilder::new()
+            .user_id(author.id)
+            .reactions(vec![
+                ReactionBuilder::new()
+                    .stamp_id(stamp.id)
+                    .user_id(user.id)
+                    .stamp_count(2)
+                    .build(),
+            ])
+            .build();
+
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_recommended_messages()
+            .with(predicate::eq(user.id))
+            .times(1)
+            .returning(move |_| Ok(vec![message.clone()]));
+        let mut mock_traq_service = MockTraqService::new();
+        let author_id = author.id;
+        let author_clone = author.clone();
+        mock_traq_service
+            .expect_get_users_by_ids()
+            .withf(move |ids| ids == [author_id])
+            .times(1)
+            .returning(move |_| Ok(HashMap::from([(author_clone.id, author_clone.clone())])));
+        let stamp_clone = stamp.clone();
+        mock_traq_service
+            .expect_get_stamp_by_id()
+            .with(predicate::eq(stamp.id))
+            .times(1)
+            .returning(move |_| Ok(stamp_clone.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let mut req =
+            request("{ timeline { author { handle } reactions { stamp { name } count } } }");
+        req.headers_mut().insert(header::COOKIE, cookie);
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            response,
+            json!({
+                "data": {
+                    "timeline": [{
+                        "author": { "handle": author.handle },
+                        "reactions": [{ "stamp": { "name": stamp.name }, "count": 2 }],
+                    }],
+                },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn users_are_loaded_at_once() {
+        let user = UserBuilder::new().build();
+        let authors = [UserBuilder::new().build(), UserBuilder::new().build()];
+        let messages: Vec<_> = authors
+            .iter()
+            .map(|author| {
+                MessageListItemBuilder::new()
+                    .user_id(author.id)
+                    .reactions(vec![ReactionBuilder::new().user_id(authors[0].id).build()])
+                    .build()
+            })
+            .collect();
+
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_recommended_messages()
+            .times(1)
+            .returning(move |_| Ok(messages.clone()));
+        let mut mock_traq_service = MockTraqService::new();
+        let authors_clone = authors.clone();
+        mock_traq_service
+            .expect_get_users_by_ids()
+            .withf(|ids| ids.len() == 2)
+            .times(1)
+            .returning(move |_| {
+                Ok(authors_clone
+                    .iter()
+                    .map(|author| (author.id, author.clone()))
+                    .collect())
+            });
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let mut req = request("{ timeline { author { handle } reactions { user { handle } } } }");
+        req.headers_mut().insert(header::COOKIE, cookie);
+        let res = app.oneshot(req).await.unwrap();
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            response["data"]["timeline"][1]["author"]["handle"],
+            authors[1].handle
+        );
+        assert_eq!(
+            response["data"]["timeline"][1]["reactions"][0]["user"]["handle"],
+            authors[0].handle
+        );
+    }
+
+    #[tokio::test]
+    async fn complex_queries_are_rejected() {
+        let user = UserBuilder::new().build();
+        let app = TestAppBuilder::new().with_user(user).build();
+        let cookie = login(&app).await;
+
+        let fields: Vec<String> = (0..=MAX_COMPLEXITY / 2)
+            .map(|i| format!("c{i}: channels {{ path }}"))
+            .collect();
+        let mut req = request(&format!("{{ {} }}", fields.join(" ")));
+        req.headers_mut().insert(header::COOKIE, cookie);
+        let res = app.oneshot(req).await.unwrap();
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Value = serde_json::from_slice(&body).unwrap();
+        assert!(response["data"].is_null());
+        assert_eq!(response["errors"][0]["message"], "Query is too complex.");
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_not_described() {
+        let user = UserBuilder::new().build();
+        let mut mock_traq_service = MockTraqService::new();
+        mock_traq_service
+            .expect_get_channels()
+            .times(1)
+            .returning(|| {
+                Err(DomainError::Repository(RepositoryError::Database(
+                    "connection refused".to_string(),
+                )))
+            });
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let mut req = request("{ channels { path } }");
+        req.headers_mut().insert(header::COOKIE, cookie);
+        let res = app.oneshot(req).await.unwrap();
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Value = serde_json::from_slice(&body).unwrap();
+        let error = &response["errors"][0];
+        assert_eq!(error["message"], "Internal Server Error");
+        assert_eq!(error["extensions"]["status"], 500);
+    }
+
+    #[tokio::test]
+    async fn requires_login() {
+        let app = TestAppBuilder::new().build();
+
+        let res = app.oneshot(request("{ channels { path } }")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,26 +1,111 @@
-use domain::service::{TimelineService, TraqService};
+use crate::{
+    builder::Services, freshness::TimelineFreshness, handler::meta::InstanceMeta, job::JobHandle,
+    rate_limit::RateLimiter,
+};
+use domain::{
+    chaos::FaultInjector,
+    model::ImageSize,
+    service::{
+        BookmarkService, FederationService, OnboardingService, PresenceService, QuotaService,
+        ReportService, TimelineService, TraqService, WebhookService,
+    },
+};
+use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::IntoParams;
+use uuid::Uuid;
 
+pub mod admin;
 pub mod auth;
+pub mod bookmark;
+pub mod channel;
+pub mod federation;
 pub mod message;
+pub mod meta;
+pub mod onboarding;
+pub mod presence;
+pub mod quota;
+pub mod search;
 pub mod stamp;
+pub mod tag;
 pub mod timeline;
 pub mod user;
 
+/// The query of the endpoints proxying images from traQ.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ImageQuery {
+    /// The size to resize the image to (default: original).
+    pub size: Option<ImageSize>,
+}
+
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub traq_service: Arc<dyn TraqService>,
     pub timeline_service: Arc<dyn TimelineService>,
+    pub bookmark_service: Arc<dyn BookmarkService>,
+    pub onboarding_service: Arc<dyn OnboardingService>,
+    pub report_service: Arc<dyn ReportService>,
+    pub webhook_service: Arc<dyn WebhookService>,
+    pub presence_service: Arc<dyn PresenceService>,
+    pub quota_service: Arc<dyn QuotaService>,
+    pub federation_service: Arc<dyn FederationService>,
+    pub jobs: JobHandle,
+    pub meta: Arc<InstanceMeta>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub freshness: Arc<TimelineFreshness>,
+    /// Set only while fault injection is enabled.
+    pub fault_injector: Option<Arc<FaultInjector>>,
+    /// Whether users may sign in with a traQ access token at `POST /auth/token`.
+    pub token_login: bool,
+    admin_user_ids: Arc<[Uuid]>,
 }
 
 impl AppState {
-    pub fn new(
-        traq_service: Arc<dyn TraqService>,
-        timeline_service: Arc<dyn TimelineService>,
-    ) -> Self {
+    pub fn new(services: Services, jobs: JobHandle, admin_user_ids: Vec<Uuid>) -> Self {
         Self {
-            traq_service,
-            timeline_service,
+            traq_service: services.traq,
+            timeline_service: services.timeline,
+            bookmark_service: services.bookmark,
+            onboarding_service: services.onboarding,
+            report_service: services.report,
+            webhook_service: services.webhook,
+            presence_service: services.presence,
+            quota_service: services.quota,
+            federation_service: services.federation,
+            jobs,
+            meta: Arc::default(),
+            rate_limiter: Arc::default(),
+            freshness: Arc::default(),
+            fault_injector: None,
+            token_login: false,
+            admin_user_ids: admin_user_ids.into(),
         }
     }
+
+    /// Sets the instance metadata served by `/meta`.
+    pub fn with_meta(mut self, meta: InstanceMeta) -> Self {
+        self.meta = Arc::new(meta);
+        self
+    }
+
+    /// Sets the limiter the API calls of users are counted by.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Arc::new(rate_limiter);
+        self
+    }
+
+    /// Lets admins change the faults `fault_injector` injects.
+    pub fn with_fault_injector(mut self, fault_injector: Arc<FaultInjector>) -> Self {
+        self.fault_injector = Some(fault_injector);
+        self
+    }
+
+    pub fn with_token_login(mut self, enabled: bool) -> Self {
+        self.token_login = enabled;
+        self
+    }
+
+    pub fn is_admin(&self, user_id: &Uuid) -> bool {
+        self.admin_user_ids.contains(user_id)
+    }
 }
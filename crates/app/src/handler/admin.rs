@@ -0,0 +1,1210 @@
+use crate::{
+    freshness::FreshnessReport, handler::AppState, problem::AppError, session::CurrentUser,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use domain::{
+    chaos::FaultSettings,
+    error::DomainError,
+    id::MessageId,
+    model::{
+        ActiveUsers, Announcement, JobRun, MessageListItem, ReportedMessage, VisibilityReport,
+        Webhook, WebhookEvent,
+    },
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const DEFAULT_JOB_RUNS_LIMIT: i64 = 50;
+const MAX_JOB_RUNS_LIMIT: i64 = 500;
+const DEFAULT_REPORTS_LIMIT: i64 = 50;
+const MAX_REPORTS_LIMIT: i64 = 200;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct JobRunsQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ReportsQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishAnnouncementRequest {
+    #[schema(max_length = 2000)]
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobsResponse {
+    /// Names of the registered jobs.
+    pub jobs: Vec<String>,
+    /// Recent runs of all jobs, newest first.
+    pub runs: Vec<JobRun>,
+}
+
+/// List the registered background jobs and their recent runs.
+#[utoipa::path(
+    get,
+    path = "/admin/jobs",
+    params(
+        ("limit" = Option<i64>, Query, description = "The maximum number of runs to return (default: 50, max: 500)"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = JobsResponse),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_jobs(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Query(query): Query<JobRunsQuery>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_JOB_RUNS_LIMIT)
+        .clamp(1, MAX_JOB_RUNS_LIMIT);
+    let runs = state
+        .jobs
+        .recent_runs(limit)
+        .await
+        .map_err(DomainError::from)?;
+
+    Ok(Json(JobsResponse {
+        jobs: state
+            .jobs
+            .job_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        runs,
+    })
+    .into_response())
+}
+
+/// Run a background job now instead of waiting for its schedule.
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/{jobName}/run",
+    params(
+        ("jobName" = String, Path, description = "The name of the job to run"),
+    ),
+    responses(
+        (status = StatusCode::ACCEPTED),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::NOT_FOUND),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn run_job(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(job_name): Path<String>,
+) -> impl IntoResponse {
+    if !state.is_admin(&user.id) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    if !state.jobs.trigger(&job_name) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    tracing::info!("Job {} triggered by {}", job_name, user.id);
+
+    StatusCode::ACCEPTED.into_response()
+}
+
+/// List messages with unresolved abuse reports, most reported first.
+#[utoipa::path(
+    get,
+    path = "/admin/reports",
+    params(
+        ("limit" = Option<i64>, Query, description = "The maximum number of messages to return (default: 50, max: 200)"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = Vec<ReportedMessage>),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_reports(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Query(query): Query<ReportsQuery>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_REPORTS_LIMIT)
+        .clamp(1, MAX_REPORTS_LIMIT);
+    let reports = state.report_service.get_unresolved_reports(limit).await?;
+
+    Ok(Json(reports).into_response())
+}
+
+/// Mark the reports of a message as reviewed.
+/// The message is no longer hidden because of them.
+#[utoipa::path(
+    post,
+    path = "/admin/reports/{messageId}/resolve",
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of the reported message"),
+    ),
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn resolve_reports(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(message_id): Path<MessageId>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    state.report_service.resolve_reports(&message_id).await?;
+
+    tracing::info!("Reports of message {} resolved by {}", message_id, user.id);
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Replace the announcement pinned at the top of every timeline.
+/// Users who dismissed the previous announcement see the new one.
+#[utoipa::path(
+    put,
+    path = "/admin/announcement",
+    request_body = PublishAnnouncementRequest,
+    responses(
+        (status = StatusCode::OK, body = Announcement),
+        (status = StatusCode::BAD_REQUEST, description = "The content is empty or too long"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn publish_announcement(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Json(payload): Json<PublishAnnouncementRequest>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let announcement = state
+        .timeline_service
+        .publish_announcement(&payload.content)
+        .await?;
+    tracing::info!("Announcement {} published by {}", announcement.id, user.id);
+
+    Ok(Json(announcement).into_response())
+}
+
+/// Withdraw the current announcement from every timeline.
+#[utoipa::path(
+    delete,
+    path = "/admin/announcement",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::NOT_FOUND, description = "There is no current announcement"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn withdraw_announcement(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    state.timeline_service.withdraw_announcement().await?;
+    tracing::info!("Announcement withdrawn by {}", user.id);
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Check on traQ that a user can access the channels of the messages in their timelines.
+///
+/// The recommended and following timelines are built for the user, and one message of each
+/// channel in them is fetched from traQ with the user's token. Messages from channels that
+/// traQ refuses are reported as leaks.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{userId}/visibility-check",
+    params(
+        ("userId" = Uuid, Path, description = "The ID of the user to check the timelines of"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = VisibilityReport),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::NOT_FOUND, description = "The user has no traQ token"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn check_visibility(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let (recommended, following) = tokio::try_join!(
+        state.timeline_service.get_recommended_messages(&user_id),
+        state.timeline_service.get_following_messages(&user_id),
+    )?;
+    let mut seen = HashSet::new();
+    let messages: Vec<MessageListItem> = recommended
+        .into_iter()
+        .chain(following)
+        .filter(|m| seen.insert(m.id))
+        .collect();
+
+    match state
+        .traq_service
+        .check_message_visibility(&user_id, &messages)
+        .await
+    {
+        Ok(report) => {
+            if !report.leaks.is_empty() {
+                tracing::warn!(
+                    "{} of {} messages served to {} are not accessible on traQ",
+                    report.leaks.len(),
+                    report.checked_messages,
+                    user_id
+                );
+            }
+            Ok(Json(report).into_response())
+        }
+        // The user has to sign in to be checked, rather than the admin signing in again
+        Err(DomainError::NoTokenForUser(_)) => Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterWebhookRequest {
+    /// The HTTP(S) URL the events are posted to.
+    #[schema(max_length = 2048)]
+    pub url: String,
+    /// The secret requests are signed with, in the `X-Twittra-Signature` header.
+    #[schema(min_length = 32)]
+    pub secret: String,
+    /// The events to send.
+    pub events: Vec<WebhookEvent>,
+}
+
+/// List the outbound webhooks, oldest first.
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks",
+    responses(
+        (status = StatusCode::OK, body = Vec<Webhook>),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_webhooks(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let webhooks = state.webhook_service.get_webhooks().await?;
+
+    Ok(Json(webhooks).into_response())
+}
+
+/// Register an outbound webhook.
+/// Changes to messages found while crawling are posted to it as JSON, signed with its secret.
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks",
+    request_body = RegisterWebhookRequest,
+    responses(
+        (status = StatusCode::CREATED, body = Webhook),
+        (status = StatusCode::BAD_REQUEST, description = "The URL, secret or events are invalid, or too many webhooks are registered"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn register_webhook(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Json(payload): Json<RegisterWebhookRequest>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let webhook = state
+        .webhook_service
+        .register_webhook(&user.id, &payload.url, &payload.secret, &payload.events)
+        .await?;
+    tracing::info!("Webhook {} registered by {}", webhook.id, user.id);
+
+    Ok((StatusCode::CREATED, Json(webhook)).into_response())
+}
+
+/// Delete an outbound webhook. No more events are sent to it.
+#[utoipa::path(
+    delete,
+    path = "/admin/webhooks/{webhookId}",
+    params(
+        ("webhookId" = i64, Path, description = "The ID of the webhook"),
+    ),
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::NOT_FOUND),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn delete_webhook(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(webhook_id): Path<i64>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    state.webhook_service.delete_webhook(webhook_id).await?;
+    tracing::info!("Webhook {} deleted by {}", webhook_id, user.id);
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Count the users online now and in the past day, from the heartbeats sent by clients.
+#[utoipa::path(
+    get,
+    path = "/admin/active-users",
+    responses(
+        (status = StatusCode::OK, body = ActiveUsers),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_active_users(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let active_users = state.presence_service.get_active_users().await?;
+
+    Ok(Json(active_users).into_response())
+}
+
+/// Report how fresh the recommended timelines served recently by this process were, to tell
+/// whether crawling and scoring keep up.
+#[utoipa::path(
+    get,
+    path = "/admin/timeline-freshness",
+    responses(
+        (status = StatusCode::OK, body = FreshnessReport),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_timeline_freshness(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !state.is_admin(&user.id) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    Json(state.freshness.report()).into_response()
+}
+
+/// Get the faults injected into repository and traQ calls.
+/// Not found unless fault injection is enabled in the config, which only debug builds allow.
+#[utoipa::path(
+    get,
+    path = "/admin/chaos",
+    responses(
+        (status = StatusCode::OK, body = FaultSettings),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::NOT_FOUND),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_chaos(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let Some(fault_injector) = &state.fault_injector else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    Ok(Json(fault_injector.settings()).into_response())
+}
+
+/// Change the faults injected into repository and traQ calls, taking effect immediately.
+#[utoipa::path(
+    put,
+    path = "/admin/chaos",
+    request_body = FaultSettings,
+    responses(
+        (status = StatusCode::OK, body = FaultSettings),
+        (status = StatusCode::BAD_REQUEST, description = "An error rate is not from 0 to 1"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::FORBIDDEN),
+        (status = StatusCode::NOT_FOUND),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn set_chaos(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Json(settings): Json<FaultSettings>,
+) -> Result<Response, AppError> {
+    if !state.is_admin(&user.id) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let Some(fault_injector) = &state.fault_injector else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    if !settings.repository.is_valid() {
+        return Err(
+            DomainError::InvalidFaults("the repository error rate must be from 0 to 1").into(),
+        );
+    }
+    if !settings.traq.is_valid() {
+        return Err(DomainError::InvalidFaults("the traQ error rate must be from 0 to 1").into());
+    }
+
+    fault_injector.set(settings);
+    tracing::warn!("Injected faults changed by {}: {:?}", user.id, settings);
+
+    Ok(Json(settings).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        job::{Job, JobError, JobScheduler, Schedule},
+        problem::ApiError,
+        test_helpers::{TestAppBuilder, login},
+    };
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use domain::{
+        chaos::{FaultInjector, Faults},
+        model::{ReportReason, VisibilityLeak},
+        repository::MockJobRunRepository,
+        service::{
+            MockPresenceService, MockReportService, MockTimelineService, MockTraqService,
+            MockWebhookService,
+        },
+        test_factories::{MessageListItemBuilder, UserBuilder},
+    };
+    use fake::{Fake, uuid::UUIDv4};
+    use http::header;
+    use mockall::predicate;
+    use std::{sync::Arc, time::Duration};
+    use time::OffsetDateTime;
+    use tower::ServiceExt;
+
+    struct NoopJob;
+
+    #[async_trait::async_trait]
+    impl Job for NoopJob {
+        fn name(&self) -> &'static str {
+            "noop"
+        }
+
+        async fn run(&self) -> Result<(), JobError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_jobs_success() {
+        let user = UserBuilder::new().build();
+        let run = JobRun {
+            job_name: "noop".to_string(),
+            started_at: OffsetDateTime::now_utc(),
+            duration_ms: 1,
+            succeeded: true,
+            error: None,
+        };
+        let run_clone = run.clone();
+
+        let mut mock_history = MockJobRunRepository::new();
+        // The job may run once on start
+        mock_history.expect_save().returning(|_| Ok(1));
+        mock_history
+            .expect_find_recent()
+            .withf(|limit| *limit == DEFAULT_JOB_RUNS_LIMIT)
+            .times(1)
+            .returning(move |_| Ok(vec![run_clone.clone()]));
+
+        let jobs = JobScheduler::new()
+            .register(NoopJob, Schedule::every(Duration::from_secs(60)))
+            .with_history(Arc::new(mock_history))
+            .start();
+
+        let app = TestAppBuilder::new()
+            .with_jobs(jobs.handle())
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/jobs")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: JobsResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.jobs, vec!["noop".to_string()]);
+        assert_eq!(response.runs.len(), 1);
+        assert_eq!(response.runs[0].job_name, run.job_name);
+
+        jobs.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_jobs_forbidden_for_non_admin() {
+        let user = UserBuilder::new().build();
+        let app = TestAppBuilder::new().with_user(user).build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/jobs")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_run_job() {
+        let user = UserBuilder::new().build();
+        let jobs = JobScheduler::new()
+            .register(NoopJob, Schedule::every(Duration::from_secs(60)))
+            .start();
+
+        let app = TestAppBuilder::new()
+            .with_jobs(jobs.handle())
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/jobs/noop/run")
+            .method("POST")
+            .header(header::COOKIE, cookie.clone())
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/jobs/unknown/run")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        jobs.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_reports() {
+        let user = UserBuilder::new().build();
+        let report = ReportedMessage {
+            message_id: UUIDv4.fake(),
+            message: None,
+            report_count: 2,
+            reasons: vec![ReportReason::Spam],
+            last_reported_at: OffsetDateTime::now_utc(),
+        };
+        let message_id = report.message_id;
+
+        let mut mock_report_service = MockReportService::new();
+        mock_report_service
+            .expect_get_unresolved_reports()
+            .with(predicate::eq(DEFAULT_REPORTS_LIMIT))
+            .times(1)
+            .returning(move |_| Ok(vec![report.clone()]));
+
+        let app = TestAppBuilder::new()
+            .with_report_service(mock_report_service)
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/reports")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let reports: Vec<ReportedMessage> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].message_id, message_id);
+        assert_eq!(reports[0].report_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_reports() {
+        let user = UserBuilder::new().build();
+        let message_id: MessageId = UUIDv4.fake();
+
+        let mut mock_report_service = MockReportService::new();
+        mock_report_service
+            .expect_resolve_reports()
+            .with(predicate::eq(message_id))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_report_service(mock_report_service)
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/admin/reports/{}/resolve", message_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_reports_forbidden_for_non_admin() {
+        let user = UserBuilder::new().build();
+        let message_id: Uuid = UUIDv4.fake();
+        let app = TestAppBuilder::new().with_user(user).build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/admin/reports/{}/resolve", message_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_publish_announcement() {
+        let user = UserBuilder::new().build();
+
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_publish_announcement()
+            .withf(|content| content == "Maintenance tonight")
+            .times(1)
+            .returning(|content| {
+                Ok(Announcement {
+                    id: 1,
+                    content: content.to_string(),
+                    created_at: OffsetDateTime::now_utc(),
+                })
+            });
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/announcement")
+            .method("PUT")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"content":"Maintenance tonight"}"#))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let announcement: Announcement = serde_json::from_slice(&body).unwrap();
+        assert_eq!(announcement.id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_announcement_without_one() {
+        let user = UserBuilder::new().build();
+
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_withdraw_announcement()
+            .times(1)
+            .returning(|| Err(DomainError::NoAnnouncement));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/announcement")
+            .method("DELETE")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_check_visibility_checks_both_timelines() {
+        let user = UserBuilder::new().build();
+        let target_user_id: Uuid = UUIDv4.fake();
+        let shared = MessageListItemBuilder::new().build();
+        let recommended = vec![shared.clone(), MessageListItemBuilder::new().build()];
+        let following = vec![shared.clone()];
+        let leak = VisibilityLeak {
+            message_id: shared.id,
+            channel_id: shared.channel_id,
+            status: 403,
+        };
+
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_recommended_messages()
+            .with(predicate::eq(target_user_id))
+            .returning(move |_| Ok(recommended.clone()));
+        mock_timeline_service
+            .expect_get_following_messages()
+            .with(predicate::eq(target_user_id))
+            .returning(move |_| Ok(following.clone()));
+        let mut mock_traq_service = MockTraqService::new();
+        // Messages in both timelines are checked once
+        mock_traq_service
+            .expect_check_message_visibility()
+            .withf(move |uid, messages| *uid == target_user_id && messages.len() == 2)
+            .times(1)
+            .returning(move |_, messages| {
+                Ok(VisibilityReport {
+                    checked_messages: messages.len(),
+                    checked_channels: 2,
+                    leaks: vec![leak],
+                })
+            });
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_traq_service(mock_traq_service)
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!(
+                "/api/v1/admin/users/{}/visibility-check",
+                target_user_id
+            ))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let report: VisibilityReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report.checked_messages, 2);
+        assert_eq!(report.leaks, vec![leak]);
+    }
+
+    #[tokio::test]
+    async fn test_register_webhook() {
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let secret = "s".repeat(32);
+        let request = serde_json::json!({
+            "url": "https://hooks.example.com",
+            "secret": secret,
+            "events": ["reactionsChanged"],
+        });
+
+        let mut mock_webhook_service = MockWebhookService::new();
+        mock_webhook_service
+            .expect_register_webhook()
+            .withf(move |created_by, url, s, events| {
+                *created_by == user_id
+                    && url == "https://hooks.example.com"
+                    && *s == secret
+                    && events == [WebhookEvent::ReactionsChanged]
+            })
+            .times(1)
+            .returning(|created_by, url, _, events| {
+                Ok(Webhook {
+                    id: 1,
+                    url: url.to_string(),
+                    events: events.to_vec(),
+                    created_by: *created_by,
+                    created_at: OffsetDateTime::now_utc(),
+                })
+            });
+
+        let app = TestAppBuilder::new()
+            .with_webhook_service(mock_webhook_service)
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/webhooks")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(request.to_string()))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let webhook: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(webhook["id"], 1);
+        // Secrets are never returned
+        assert!(webhook.get("secret").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_webhook_not_found() {
+        let user = UserBuilder::new().build();
+
+        let mut mock_webhook_service = MockWebhookService::new();
+        mock_webhook_service
+            .expect_delete_webhook()
+            .with(predicate::eq(1))
+            .times(1)
+            .returning(|id| Err(DomainError::NoWebhookForId(id)));
+
+        let app = TestAppBuilder::new()
+            .with_webhook_service(mock_webhook_service)
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/webhooks/1")
+            .method("DELETE")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_webhooks_forbidden_for_non_admin() {
+        let user = UserBuilder::new().build();
+        let app = TestAppBuilder::new().with_user(user).build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/webhooks")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_get_active_users() {
+        let user = UserBuilder::new().build();
+        let active_users = ActiveUsers {
+            online: 3,
+            daily: 42,
+        };
+        let expected = active_users.clone();
+
+        let mut mock_presence_service = MockPresenceService::new();
+        mock_presence_service
+            .expect_get_active_users()
+            .times(1)
+            .returning(move || Ok(active_users.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_presence_service(mock_presence_service)
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/active-users")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: ActiveUsers = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, expected);
+    }
+
+    #[tokio::test]
+    async fn test_get_timeline_freshness_requires_admin() {
+        let user = UserBuilder::new().build();
+        let app = TestAppBuilder::new().with_user(user).build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/timeline-freshness")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_get_timeline_freshness() {
+        let user = UserBuilder::new().build();
+        let app = TestAppBuilder::new()
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/timeline-freshness")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: FreshnessReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.timelines, 0);
+    }
+
+    #[tokio::test]
+    async fn test_chaos_not_found_unless_enabled() {
+        let user = UserBuilder::new().build();
+        let app = TestAppBuilder::new()
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/admin/chaos")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_set_chaos() {
+        let user = UserBuilder::new().build();
+        let fault_injector = Arc::new(FaultInjector::default());
+        let app = TestAppBuilder::new()
+            .with_fault_injector(fault_injector.clone())
+            .with_admin(user.id)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let put = |body: &'static str| {
+            Request::builder()
+                .uri("/api/v1/admin/chaos")
+                .method("PUT")
+                .header(header::COOKIE, cookie.clone())
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let res = app
+            .clone()
+            .oneshot(put(
+                r#"{"repository":{"latencyMs":0,"errorRate":2.0},"traq":{"latencyMs":0,"errorRate":0.0}}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let problem: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            problem.detail.as_deref(),
+            Some("invalid faults: the repository error rate must be from 0 to 1")
+        );
+        assert_eq!(fault_injector.settings(), FaultSettings::default());
+
+        let res = app
+            .oneshot(put(
+                r#"{"repository":{"latencyMs":100,"errorRate":0.0},"traq":{"latencyMs":0,"errorRate":0.5}}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            fault_injector.settings(),
+            FaultSettings {
+                repository: Faults {
+                    latency_ms: 100,
+                    error_rate: 0.0,
+                },
+                traq: Faults {
+                    latency_ms: 0,
+                    error_rate: 0.5,
+                },
+            }
+        );
+    }
+}
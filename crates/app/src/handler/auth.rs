@@ -1,10 +1,16 @@
 use axum::{
-    extract::Query,
+    Json,
+    extract::{Query, State},
     response::{IntoResponse, Redirect},
 };
 use http::StatusCode;
+use serde::Deserialize;
+use utoipa::ToSchema;
 
-use crate::session::AuthSession;
+use crate::{
+    handler::AppState,
+    session::{AuthSession, Credentials},
+};
 
 const CSRF_STATE_KEY: &str = "oauth.csrf_state";
 
@@ -75,7 +81,10 @@ pub async fn oauth_callback(
         return StatusCode::BAD_REQUEST.into_response();
     }
 
-    let user = match auth_session.authenticate(code).await {
+    let user = match auth_session
+        .authenticate(Credentials::AuthorizationCode(code))
+        .await
+    {
         Ok(Some(user)) => user,
         Ok(None) => return StatusCode::UNAUTHORIZED.into_response(),
         Err(e) => {
@@ -93,3 +102,58 @@ pub async fn oauth_callback(
 
     Redirect::to("/").into_response()
 }
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenLoginRequest {
+    /// A traQ access token of the user to sign in as.
+    pub access_token: String,
+}
+
+/// Sign in with a traQ access token instead of the OAuth2 flow, e.g. from command line tools.
+///
+/// Only available if enabled in the config. The token is not stored, so messages are still crawled
+/// with the one obtained through the OAuth2 flow, if any.
+#[utoipa::path(
+    post,
+    path = "/auth/token",
+    request_body = TokenLoginRequest,
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    tag = "auth",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn login_with_token(
+    State(state): State<AppState>,
+    mut auth_session: AuthSession,
+    Json(request): Json<TokenLoginRequest>,
+) -> impl IntoResponse {
+    if !state.token_login {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let user = match auth_session
+        .authenticate(Credentials::AccessToken(request.access_token))
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return StatusCode::UNAUTHORIZED.into_response(),
+        Err(e) => {
+            tracing::error!("{:?}", e);
+
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if let Err(e) = auth_session.login(&user).await {
+        tracing::error!("{:?}", e);
+
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
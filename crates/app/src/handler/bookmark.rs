@@ -0,0 +1,232 @@
+use crate::{
+    fields::{FieldsQuery, SparseJson},
+    handler::AppState,
+    problem::AppError,
+    session::CurrentUser,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use domain::{id::MessageId, model::MessageListItem};
+use http::StatusCode;
+
+/// Bookmark a message.
+#[utoipa::path(
+    post,
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of the message to bookmark"),
+    ),
+    path = "/messages/{messageId}/bookmark",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "bookmark",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn add_bookmark(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(message_id): Path<MessageId>,
+) -> Result<Response, AppError> {
+    state
+        .bookmark_service
+        .add_bookmark(&user.id.into(), &message_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Remove a bookmark from a message.
+#[utoipa::path(
+    delete,
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of the message to remove the bookmark from"),
+    ),
+    path = "/messages/{messageId}/bookmark",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "bookmark",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn remove_bookmark(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(message_id): Path<MessageId>,
+) -> Result<Response, AppError> {
+    state
+        .bookmark_service
+        .remove_bookmark(&user.id.into(), &message_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Get the messages bookmarked by the current user, most recently bookmarked first.
+#[utoipa::path(
+    get,
+    path = "/bookmarks",
+    params(
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = [MessageListItem]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "bookmark",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_bookmarks(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
+    let messages = state
+        .bookmark_service
+        .get_bookmarks(&user.id.into())
+        .await?;
+
+    Ok(SparseJson::new(messages, &fields).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use domain::{
+        error::DomainError,
+        id::UserId,
+        service::MockBookmarkService,
+        test_factories::{MessageListItemBuilder, UserBuilder},
+    };
+    use fake::{Fake, uuid::UUIDv4};
+    use http::header;
+    use mockall::predicate;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_add_bookmark_success() {
+        let mut mock_bookmark_service = MockBookmarkService::new();
+        let user = UserBuilder::new().build();
+        let message_id: MessageId = UUIDv4.fake();
+
+        mock_bookmark_service
+            .expect_add_bookmark()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(message_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_bookmark_service(mock_bookmark_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/bookmark", message_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_add_bookmark_unknown_message() {
+        let mut mock_bookmark_service = MockBookmarkService::new();
+        let user = UserBuilder::new().build();
+        let message_id: MessageId = UUIDv4.fake();
+
+        mock_bookmark_service
+            .expect_add_bookmark()
+            .times(1)
+            .returning(|_, message_id| Err(DomainError::NoMessageForId(*message_id.as_uuid())));
+
+        let app = TestAppBuilder::new()
+            .with_bookmark_service(mock_bookmark_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/bookmark", message_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_bookmarks_success() {
+        let mut mock_bookmark_service = MockBookmarkService::new();
+        let user = UserBuilder::new().build();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![message.clone()];
+
+        mock_bookmark_service
+            .expect_get_bookmarks()
+            .with(predicate::eq(UserId::from(user.id)))
+            .times(1)
+            .returning(move |_| Ok(messages.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_bookmark_service(mock_bookmark_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/bookmarks")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response_messages: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response_messages.len(), 1);
+        assert_eq!(response_messages[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_get_bookmarks_unauthorized() {
+        let app = TestAppBuilder::new().build();
+        let req = Request::builder()
+            .uri("/api/v1/bookmarks")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
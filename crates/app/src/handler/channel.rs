@@ -0,0 +1,541 @@
+use crate::{
+    handler::{AppState, user::MessagePageQuery},
+    problem::AppError,
+    session::CurrentUser,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use domain::{
+    id::ChannelId,
+    model::{Channel, ChannelScoreOverride, MessagePage},
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Get the public channels, ordered by path.
+#[utoipa::path(
+    get,
+    path = "/channels",
+    responses(
+        (status = StatusCode::OK, body = [Channel]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "channel",
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_channels(
+    _: CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let channels = state.traq_service.get_channels().await?;
+
+    Ok(Json(channels).into_response())
+}
+
+/// Get a page of messages posted in a channel, newest first.
+///
+/// Messages are served from the crawled cache. Older messages not crawled yet are fetched from
+/// traQ with the user's token and cached.
+#[utoipa::path(
+    get,
+    path = "/channels/{channelId}/messages",
+    params(
+        ("channelId" = Uuid, Path, description = "The ID of the channel"),
+        MessagePageQuery,
+    ),
+    responses(
+        (status = StatusCode::OK, body = MessagePage),
+        (status = StatusCode::BAD_REQUEST, description = "The cursor is invalid"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "channel",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_channel_messages(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(channel_id): Path<Uuid>,
+    Query(query): Query<MessagePageQuery>,
+) -> Result<Response, AppError> {
+    let page = state
+        .traq_service
+        .get_channel_messages(&user.id, &channel_id, query.before)
+        .await?;
+
+    Ok(Json(page).into_response())
+}
+
+/// Get a channel.
+#[utoipa::path(
+    get,
+    params(
+        ("channelId" = Uuid, Path, description = "The ID of the channel to retrieve"),
+    ),
+    path = "/channels/{channelId}",
+    responses(
+        (status = StatusCode::OK, body = Channel),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND, description = "The channel has not been synced from traQ"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "channel",
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_channel_by_id(
+    _: CurrentUser,
+    State(state): State<AppState>,
+    Path(channel_id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let channel = state.traq_service.get_channel_by_id(&channel_id).await?;
+
+    Ok(Json(channel).into_response())
+}
+
+/// Mute a channel. Messages in muted channels no longer appear in the timeline.
+#[utoipa::path(
+    post,
+    params(
+        ("channelId" = Uuid, Path, description = "The ID of the channel to mute"),
+    ),
+    path = "/channels/{channelId}/mute",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "channel",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn mute_channel(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(channel_id): Path<ChannelId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .mute_channel(&user.id.into(), &channel_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Unmute a channel.
+#[utoipa::path(
+    delete,
+    params(
+        ("channelId" = Uuid, Path, description = "The ID of the channel to unmute"),
+    ),
+    path = "/channels/{channelId}/mute",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "channel",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn unmute_channel(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(channel_id): Path<ChannelId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .unmute_channel(&user.id.into(), &channel_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Get the score multipliers the user set for channels.
+#[utoipa::path(
+    get,
+    path = "/me/channel-score-overrides",
+    responses(
+        (status = StatusCode::OK, body = [ChannelScoreOverride]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "channel",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_channel_score_overrides(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let overrides = state
+        .timeline_service
+        .get_channel_score_overrides(&user.id)
+        .await?;
+
+    Ok(Json(overrides).into_response())
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct SetChannelScoreOverrideRequest {
+    /// Greater than 1 to prioritize the channel, and less than 1 to de-prioritize it.
+    #[schema(minimum = 0.1, maximum = 10.0)]
+    pub multiplier: f64,
+}
+
+/// Multiply the scores of messages recommended from a channel.
+#[utoipa::path(
+    put,
+    params(
+        ("channelId" = Uuid, Path, description = "The ID of the channel to prioritize or de-prioritize"),
+    ),
+    path = "/channels/{channelId}/score-override",
+    request_body = SetChannelScoreOverrideRequest,
+    responses(
+        (status = StatusCode::OK, body = ChannelScoreOverride),
+        (status = StatusCode::BAD_REQUEST, description = "The multiplier is out of range, or scores are overridden for too many channels"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "channel",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn set_channel_score_override(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(channel_id): Path<ChannelId>,
+    Json(payload): Json<SetChannelScoreOverrideRequest>,
+) -> Result<Response, AppError> {
+    let score_override = state
+        .timeline_service
+        .set_channel_score_override(&user.id.into(), &channel_id, payload.multiplier)
+        .await?;
+
+    Ok(Json(score_override).into_response())
+}
+
+/// Reset the scores of messages recommended from a channel.
+#[utoipa::path(
+    delete,
+    params(
+        ("channelId" = Uuid, Path, description = "The ID of the channel to reset"),
+    ),
+    path = "/channels/{channelId}/score-override",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "channel",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn remove_channel_score_override(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(channel_id): Path<ChannelId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .remove_channel_score_override(&user.id.into(), &channel_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use domain::{
+        error::DomainError,
+        id::UserId,
+        service::{MockTimelineService, MockTraqService},
+        test_factories::{MessageListItemBuilder, UserBuilder},
+    };
+    use fake::{Fake, uuid::UUIDv4};
+    use http::header;
+    use mockall::predicate;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_get_channel_messages_success() {
+        let mut mock_traq_service = MockTraqService::new();
+        let user = UserBuilder::new().build();
+        let channel_id: Uuid = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().channel_id(channel_id).build();
+        let page = MessagePage {
+            messages: vec![message.clone()],
+            next_cursor: None,
+        };
+
+        mock_traq_service
+            .expect_get_channel_messages()
+            .with(
+                predicate::eq(user.id),
+                predicate::eq(channel_id),
+                predicate::eq(None),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(page.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/channels/{}/messages", channel_id))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: MessagePage = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(response.messages[0].id, message.id);
+        assert_eq!(response.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_channels_success() {
+        let mut mock_traq_service = MockTraqService::new();
+        let channels = vec![Channel {
+            id: UUIDv4.fake(),
+            name: "general".to_string(),
+            path: "general".to_string(),
+            archived: false,
+        }];
+        let channels_clone = channels.clone();
+
+        mock_traq_service
+            .expect_get_channels()
+            .times(1)
+            .returning(move || Ok(channels_clone.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/channels")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<Channel> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, channels);
+    }
+
+    #[tokio::test]
+    async fn test_get_channel_by_id_not_found() {
+        let mut mock_traq_service = MockTraqService::new();
+        let channel_id: Uuid = UUIDv4.fake();
+
+        mock_traq_service
+            .expect_get_channel_by_id()
+            .with(predicate::eq(channel_id))
+            .times(1)
+            .returning(move |_| Err(DomainError::NoChannelForId(channel_id)));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/channels/{}", channel_id))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_mute_channel_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let channel_id: ChannelId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_mute_channel()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(channel_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/channels/{}/mute", channel_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_mute_channel_unauthorized() {
+        let app = TestAppBuilder::new().build();
+        let channel_id: Uuid = UUIDv4.fake();
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/channels/{}/mute", channel_id))
+            .method("POST")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_unmute_channel_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let channel_id: ChannelId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_unmute_channel()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(channel_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/channels/{}/mute", channel_id))
+            .method("DELETE")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_set_channel_score_override_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let channel_id: Uuid = UUIDv4.fake();
+        let score_override = ChannelScoreOverride {
+            channel_id,
+            multiplier: 2.0,
+        };
+
+        mock_timeline_service
+            .expect_set_channel_score_override()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(ChannelId::from(channel_id)),
+                predicate::eq(2.0),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(score_override));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/channels/{}/score-override", channel_id))
+            .method("PUT")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"multiplier":2.0}"#))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: ChannelScoreOverride = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, score_override);
+    }
+
+    #[tokio::test]
+    async fn test_set_channel_score_override_invalid() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_set_channel_score_override()
+            .returning(|_, _, _| Err(DomainError::InvalidChannelScoreMultiplier));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let channel_id: Uuid = UUIDv4.fake();
+        let req = Request::builder()
+            .uri(format!("/api/v1/channels/{}/score-override", channel_id))
+            .method("PUT")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"multiplier":100.0}"#))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{handler::AppState, problem::AppError, session::CurrentUser};
+use axum::{
+    Json,
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use domain::model::{InstanceHighlights, PeerHighlights};
+use http::StatusCode;
+
+/// Get the most reacted messages of the past day, for peer instances to show.
+///
+/// Authors are left out, and content is included only for users who consented to it.
+/// This endpoint does not require authentication.
+#[utoipa::path(
+    get,
+    path = "/federation/highlights",
+    responses(
+        (status = StatusCode::OK, body = InstanceHighlights),
+        (status = StatusCode::NOT_FOUND, description = "This instance doesn't publish highlights"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    tag = "federation",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_highlights(State(state): State<AppState>) -> Result<Response, AppError> {
+    let highlights = state.federation_service.get_highlights().await?;
+
+    Ok(Json(highlights).into_response())
+}
+
+/// Get the highlights published by the peer instances of this instance.
+///
+/// Peers that can't be reached are left out.
+#[utoipa::path(
+    get,
+    path = "/federation/peers/highlights",
+    responses(
+        (status = StatusCode::OK, body = Vec<PeerHighlights>),
+        (status = StatusCode::UNAUTHORIZED),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "federation",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_peer_highlights(
+    _: CurrentUser,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    Json(state.federation_service.get_peer_highlights().await).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use domain::{
+        error::DomainError, federation::HIGHLIGHTS_VERSION, model::Highlight,
+        service::MockFederationService, test_factories::UserBuilder,
+    };
+    use http::header;
+    use time::OffsetDateTime;
+    use tower::ServiceExt;
+
+    fn highlights(instance: &str) -> InstanceHighlights {
+        InstanceHighlights {
+            version: HIGHLIGHTS_VERSION,
+            instance: instance.to_string(),
+            generated_at: OffsetDateTime::from_unix_timestamp(1_735_732_800).unwrap(),
+            highlights: vec![Highlight {
+                reaction_count: 3,
+                stamp_count: 2,
+                created_at: OffsetDateTime::from_unix_timestamp(1_735_700_000).unwrap(),
+                content: None,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_highlights_without_login() {
+        let mut mock_federation_service = MockFederationService::new();
+        mock_federation_service
+            .expect_get_highlights()
+            .times(1)
+            .returning(|| Ok(highlights("Example")));
+
+        let app = TestAppBuilder::new()
+            .with_federation_service(mock_federation_service)
+            .build();
+        let req = Request::builder()
+            .uri("/api/v1/federation/highlights")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: InstanceHighlights = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, highlights("Example"));
+    }
+
+    #[tokio::test]
+    async fn test_get_highlights_not_published() {
+        let mut mock_federation_service = MockFederationService::new();
+        mock_federation_service
+            .expect_get_highlights()
+            .returning(|| Err(DomainError::HighlightsNotPublished));
+
+        let app = TestAppBuilder::new()
+            .with_federation_service(mock_federation_service)
+            .build();
+        let req = Request::builder()
+            .uri("/api/v1/federation/highlights")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_peer_highlights() {
+        let mut mock_federation_service = MockFederationService::new();
+        mock_federation_service
+            .expect_get_peer_highlights()
+            .times(1)
+            .returning(|| {
+                vec![PeerHighlights {
+                    url: "https://peer.example.com".to_string(),
+                    highlights: highlights("Peer"),
+                }]
+            });
+
+        let app = TestAppBuilder::new()
+            .with_federation_service(mock_federation_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+        let req = Request::builder()
+            .uri("/api/v1/federation/peers/highlights")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<PeerHighlights> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0].highlights.instance, "Peer");
+    }
+}
@@ -1,8 +1,17 @@
-use crate::{handler::AppState, session::AuthSession};
+use crate::{
+    fields::{FieldsQuery, SparseJson},
+    handler::AppState,
+    problem::AppError,
+    session::CurrentUser,
+};
 use axum::{
     Json,
-    extract::{Path, State},
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use domain::{
+    id::{MessageId, StampId},
+    model::{MessageListItem, ReportReason, Stamp},
 };
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
@@ -26,27 +35,18 @@ use uuid::Uuid;
     ),
     tag = "message",
 )]
-#[tracing::instrument(skip(auth_session, state))]
+#[tracing::instrument(skip(user, state))]
 pub async fn add_message_stamp(
-    auth_session: AuthSession,
+    CurrentUser(user): CurrentUser,
     State(state): State<AppState>,
-    Path((message_id, stamp_id)): Path<(Uuid, Uuid)>,
-) -> impl IntoResponse {
-    let user = match auth_session.user {
-        Some(user) => user,
-        None => return StatusCode::UNAUTHORIZED.into_response(),
-    };
-
-    if let Err(e) = state
+    Path((message_id, stamp_id)): Path<(MessageId, StampId)>,
+) -> Result<Response, AppError> {
+    state
         .traq_service
-        .add_message_stamp(&user.id, &message_id, &stamp_id, 1)
-        .await
-    {
-        tracing::error!("{:?}", e);
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-    }
+        .add_message_stamp(&user.id.into(), &message_id, &stamp_id, 1)
+        .await?;
 
-    StatusCode::NO_CONTENT.into_response()
+    Ok(StatusCode::NO_CONTENT.into_response())
 }
 
 #[utoipa::path(
@@ -66,27 +66,89 @@ pub async fn add_message_stamp(
     ),
     tag = "message",
 )]
-#[tracing::instrument(skip(auth_session, state))]
+#[tracing::instrument(skip(user, state))]
 pub async fn remove_message_stamp(
-    auth_session: AuthSession,
+    CurrentUser(user): CurrentUser,
     State(state): State<AppState>,
-    Path((message_id, stamp_id)): Path<(Uuid, Uuid)>,
-) -> impl IntoResponse {
-    let user = match auth_session.user {
-        Some(user) => user,
-        None => return StatusCode::UNAUTHORIZED.into_response(),
-    };
+    Path((message_id, stamp_id)): Path<(MessageId, StampId)>,
+) -> Result<Response, AppError> {
+    state
+        .traq_service
+        .remove_message_stamp(&user.id.into(), &message_id, &stamp_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ToggleMessageStampResponse {
+    /// Whether the user has reacted with the stamp after toggling it.
+    pub reacted: bool,
+}
 
-    if let Err(e) = state
+/// Add the stamp if the user hasn't reacted with it yet, and remove it otherwise.
+#[utoipa::path(
+    put,
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of the message to toggle the reaction on"),
+        ("stampId" = Uuid, Path, description = "The ID of the stamp to toggle"),
+    ),
+    path = "/messages/{messageId}/stamps/{stampId}/toggle",
+    responses(
+        (status = StatusCode::OK, body = ToggleMessageStampResponse),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "message",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn toggle_message_stamp(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path((message_id, stamp_id)): Path<(MessageId, StampId)>,
+) -> Result<Response, AppError> {
+    let reacted = state
         .traq_service
-        .remove_message_stamp(&user.id, &message_id, &stamp_id)
-        .await
-    {
-        tracing::error!("{:?}", e);
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-    }
+        .toggle_message_stamp(&user.id.into(), &message_id, &stamp_id)
+        .await?;
+
+    Ok(Json(ToggleMessageStampResponse { reacted }).into_response())
+}
+
+/// Suggest stamps to add to a message, from the stamps that messages by the same author or in
+/// the same channel usually receive and the current user's recent stamps.
+#[utoipa::path(
+    get,
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of the message to suggest stamps for"),
+    ),
+    path = "/messages/{messageId}/stamps/suggestions",
+    responses(
+        (status = StatusCode::OK, body = Vec<Stamp>),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "message",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_stamp_suggestions(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let stamps = state
+        .traq_service
+        .get_stamp_suggestions(&user.id, &message_id)
+        .await?;
 
-    StatusCode::NO_CONTENT.into_response()
+    Ok(Json(stamps).into_response())
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
@@ -108,36 +170,181 @@ pub struct ReadMessagesRequest {
     ),
     tag = "message",
 )]
-#[tracing::instrument(skip(auth_session, state, payload))]
+#[tracing::instrument(skip(user, state, payload))]
 pub async fn mark_messages_as_read(
-    auth_session: AuthSession,
+    CurrentUser(user): CurrentUser,
     State(state): State<AppState>,
     Json(payload): Json<ReadMessagesRequest>,
-) -> impl IntoResponse {
-    let user = match auth_session.user {
-        Some(user) => user,
-        None => return StatusCode::UNAUTHORIZED.into_response(),
-    };
+) -> Result<Response, AppError> {
+    let message_ids: Vec<MessageId> = payload
+        .message_ids
+        .into_iter()
+        .map(MessageId::from)
+        .collect();
+    state
+        .timeline_service
+        .mark_messages_as_read(&user.id.into(), &message_ids)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Mark a message as not interesting.
+/// The message is no longer recommended, and similar messages are recommended less.
+#[utoipa::path(
+    post,
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of the message to hide"),
+    ),
+    path = "/messages/{messageId}/hide",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "message",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn hide_message(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(message_id): Path<MessageId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .hide_message(&user.id.into(), &message_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
 
-    if let Err(e) = state
+/// Get the conversation a message belongs to, oldest first.
+/// Messages reply to the first message they cite, and the thread starts from the earliest cached
+/// message the given one replies to.
+#[utoipa::path(
+    get,
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of any message in the thread"),
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
+    path = "/messages/{messageId}/thread",
+    responses(
+        (status = StatusCode::OK, body = [MessageListItem]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND, description = "The message is not cached"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "message",
+)]
+#[tracing::instrument(skip(user, state, fields))]
+pub async fn get_message_thread(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
+    let messages = state
         .timeline_service
-        .mark_messages_as_read(&user.id, &payload.message_ids)
-        .await
-    {
-        tracing::error!("{:?}", e);
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-    }
+        .get_thread(&user.id, &message_id)
+        .await?;
 
-    StatusCode::NO_CONTENT.into_response()
+    Ok(SparseJson::new(messages, &fields).into_response())
 }
+
+/// Get messages reacted to by the users who reacted to a message, for a "people who liked this
+/// also liked" panel. Messages shared by the most users come first.
+#[utoipa::path(
+    get,
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of the message to find related messages for"),
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
+    path = "/messages/{messageId}/related",
+    responses(
+        (status = StatusCode::OK, body = [MessageListItem]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "message",
+)]
+#[tracing::instrument(skip(user, state, fields))]
+pub async fn get_related_messages(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
+    let messages = state
+        .timeline_service
+        .get_related_messages(&user.id, &message_id)
+        .await?;
+
+    Ok(SparseJson::new(messages, &fields).into_response())
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ReportMessageRequest {
+    pub reason: ReportReason,
+}
+
+/// Report a message as abusive.
+/// Reporting the same message again replaces the previous reason.
+#[utoipa::path(
+    post,
+    params(
+        ("messageId" = Uuid, Path, description = "The ID of the message to report"),
+    ),
+    path = "/messages/{messageId}/report",
+    request_body = ReportMessageRequest,
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "message",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn report_message(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(message_id): Path<MessageId>,
+    Json(payload): Json<ReportMessageRequest>,
+) -> Result<Response, AppError> {
+    state
+        .report_service
+        .report_message(&user.id.into(), &message_id, payload.reason)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_helpers::TestAppBuilder;
-    use axum::{body::Body, http::Request};
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
     use domain::{
-        service::{MockTimelineService, MockTraqService},
-        test_factories::UserBuilder,
+        error::DomainError,
+        id::UserId,
+        service::{MockReportService, MockTimelineService, MockTraqService},
+        test_factories::{MessageListItemBuilder, StampBuilder, UserBuilder},
     };
     use fake::{Fake, uuid::UUIDv4};
     use http::header;
@@ -148,9 +355,10 @@ mod tests {
     async fn test_mark_messages_as_read_success() {
         let mut mock_timeline_service = MockTimelineService::new();
         let user = UserBuilder::new().build();
-        let user_id = user.id;
-        let message_ids = vec![UUIDv4.fake(), UUIDv4.fake()];
-        let message_ids_clone = message_ids.clone();
+        let user_id = UserId::from(user.id);
+        let message_ids: Vec<Uuid> = vec![UUIDv4.fake(), UUIDv4.fake()];
+        let message_ids_clone: Vec<MessageId> =
+            message_ids.iter().copied().map(MessageId::from).collect();
 
         mock_timeline_service
             .expect_mark_messages_as_read()
@@ -189,9 +397,9 @@ mod tests {
         let mut mock_traq_service = MockTraqService::new();
 
         let user = UserBuilder::new().build();
-        let user_id = user.id;
-        let message_id: Uuid = UUIDv4.fake();
-        let stamp_id: Uuid = UUIDv4.fake();
+        let user_id = UserId::from(user.id);
+        let message_id: MessageId = UUIDv4.fake();
+        let stamp_id: StampId = UUIDv4.fake();
 
         mock_traq_service
             .expect_add_message_stamp()
@@ -232,4 +440,307 @@ mod tests {
         let res = app.oneshot(req).await.unwrap();
         assert_eq!(res.status(), StatusCode::NO_CONTENT);
     }
+
+    #[tokio::test]
+    async fn test_toggle_message_stamp_success() {
+        let mut mock_traq_service = MockTraqService::new();
+
+        let user = UserBuilder::new().build();
+        let user_id = UserId::from(user.id);
+        let message_id: MessageId = UUIDv4.fake();
+        let stamp_id: StampId = UUIDv4.fake();
+
+        mock_traq_service
+            .expect_toggle_message_stamp()
+            .with(
+                predicate::eq(user_id),
+                predicate::eq(message_id),
+                predicate::eq(stamp_id),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(true));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!(
+                "/api/v1/messages/{}/stamps/{}/toggle",
+                message_id, stamp_id
+            ))
+            .method("PUT")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let response: ToggleMessageStampResponse = serde_json::from_slice(&body).unwrap();
+        assert!(response.reacted);
+    }
+
+    #[tokio::test]
+    async fn test_hide_message_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let message_id: MessageId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_hide_message()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(message_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/hide", message_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_hide_unknown_message() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let message_id: Uuid = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_hide_message()
+            .returning(|_, message_id| Err(DomainError::NoMessageForId(*message_id.as_uuid())));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/hide", message_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_report_message_success() {
+        let mut mock_report_service = MockReportService::new();
+        let user = UserBuilder::new().build();
+        let message_id: MessageId = UUIDv4.fake();
+
+        mock_report_service
+            .expect_report_message()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(message_id),
+                predicate::eq(ReportReason::Spam),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_report_service(mock_report_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/report", message_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"reason":"spam"}"#))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_report_unknown_message() {
+        let mut mock_report_service = MockReportService::new();
+        let user = UserBuilder::new().build();
+        let message_id: MessageId = UUIDv4.fake();
+
+        mock_report_service
+            .expect_report_message()
+            .returning(|_, message_id, _| Err(DomainError::NoMessageForId(*message_id.as_uuid())));
+
+        let app = TestAppBuilder::new()
+            .with_report_service(mock_report_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/report", message_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"reason":"harassment"}"#))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_related_messages() {
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let message_id: Uuid = UUIDv4.fake();
+        let related = MessageListItemBuilder::new().build();
+        let messages = vec![related.clone()];
+
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_related_messages()
+            .with(predicate::eq(user_id), predicate::eq(message_id))
+            .times(1)
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/related", message_id))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0].id, related.id);
+    }
+
+    #[tokio::test]
+    async fn test_get_message_thread() {
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let root = MessageListItemBuilder::new().build();
+        let reply = MessageListItemBuilder::new()
+            .content(format!("https://q.trap.jp/messages/{}", root.id))
+            .build();
+        let reply_id = reply.id;
+        let messages = vec![root.clone(), reply];
+
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_thread()
+            .with(predicate::eq(user_id), predicate::eq(reply_id))
+            .times(1)
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/thread", reply_id))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 2);
+        assert_eq!(response[1].reply_to_message_id, Some(root.id));
+    }
+
+    #[tokio::test]
+    async fn test_get_message_thread_not_found() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_thread()
+            .returning(|_, message_id| Err(DomainError::NoMessageForId(*message_id)));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/messages/{}/thread", UUIDv4.fake::<Uuid>()))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_stamp_suggestions() {
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let message_id: Uuid = UUIDv4.fake();
+        let stamp = StampBuilder::new().build();
+        let stamps = vec![stamp.clone()];
+
+        let mut mock_traq_service = MockTraqService::new();
+        mock_traq_service
+            .expect_get_stamp_suggestions()
+            .with(predicate::eq(user_id), predicate::eq(message_id))
+            .times(1)
+            .returning(move |_, _| Ok(stamps.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!(
+                "/api/v1/messages/{}/stamps/suggestions",
+                message_id
+            ))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<Stamp> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0].id, stamp.id);
+    }
 }
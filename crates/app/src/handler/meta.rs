@@ -0,0 +1,148 @@
+use crate::handler::AppState;
+use axum::{Json, extract::State, response::IntoResponse};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use utoipa::ToSchema;
+
+/// Deployment-specific values the frontend needs to render the instance.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceMeta {
+    pub name: String,
+    pub version: String,
+    /// The URL of the traQ web client, used to link to messages and users.
+    pub traq_base_url: String,
+    pub features: InstanceFeatures,
+    pub limits: InstanceLimits,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceFeatures {
+    /// Whether heavily reported messages are hidden until an admin reviews them.
+    pub report_auto_hide: bool,
+    /// Whether the public timeline can be seen without signing in.
+    pub public_timeline: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceLimits {
+    /// The maximum number of channels that can be picked during onboarding.
+    pub max_channel_interests: usize,
+}
+
+/// Information about the running build, embedded by the build script.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    pub version: String,
+    /// The git commit the server was built from, if known.
+    pub git_commit: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub build_timestamp: Option<OffsetDateTime>,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: option_env!("TWITTRA_GIT_COMMIT").map(str::to_string),
+            build_timestamp: option_env!("TWITTRA_BUILD_TIMESTAMP")
+                .and_then(|timestamp| timestamp.parse().ok())
+                .and_then(|timestamp| OffsetDateTime::from_unix_timestamp(timestamp).ok()),
+        }
+    }
+}
+
+/// Get the name, version, features and limits of this instance.
+///
+/// This endpoint does not require authentication.
+#[utoipa::path(
+    get,
+    path = "/meta",
+    responses(
+        (status = StatusCode::OK, body = InstanceMeta),
+    ),
+    tag = "meta",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_meta(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.meta.as_ref().clone())
+}
+
+/// Get the version, git commit and build time of the server.
+///
+/// This endpoint does not require authentication.
+#[utoipa::path(
+    get,
+    path = "/version",
+    responses(
+        (status = StatusCode::OK, body = VersionInfo),
+    ),
+    tag = "meta",
+)]
+#[tracing::instrument]
+pub async fn get_version() -> impl IntoResponse {
+    Json(VersionInfo::current())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::TestAppBuilder;
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_get_meta_without_login() {
+        let meta = InstanceMeta {
+            name: "Example".to_string(),
+            version: "1.0.0".to_string(),
+            traq_base_url: "https://q.example.com".to_string(),
+            features: InstanceFeatures {
+                report_auto_hide: true,
+                public_timeline: false,
+            },
+            limits: InstanceLimits {
+                max_channel_interests: 20,
+            },
+        };
+
+        let app = TestAppBuilder::new().with_meta(meta.clone()).build();
+
+        let req = Request::builder()
+            .uri("/api/v1/meta")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: InstanceMeta = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, meta);
+    }
+
+    #[tokio::test]
+    async fn test_get_version() {
+        let app = TestAppBuilder::new().build();
+
+        let req = Request::builder()
+            .uri("/api/v1/version")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: VersionInfo = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.version, env!("CARGO_PKG_VERSION"));
+        assert!(response.build_timestamp.is_some());
+    }
+}
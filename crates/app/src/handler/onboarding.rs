@@ -0,0 +1,317 @@
+use crate::{
+    handler::AppState,
+    problem::AppError,
+    session::{AuthSession, CurrentUser},
+};
+use axum::{
+    Json,
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use domain::{
+    id::ChannelId,
+    model::{ChannelActivity, OnboardingState, OnboardingStep},
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelInterestsRequest {
+    pub channel_ids: Vec<Uuid>,
+}
+
+/// Get the onboarding steps the current user has completed.
+#[utoipa::path(
+    get,
+    path = "/onboarding",
+    responses(
+        (status = StatusCode::OK, body = OnboardingState),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "onboarding",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_onboarding_state(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let onboarding = state
+        .onboarding_service
+        .get_onboarding_state(&user.id)
+        .await?;
+
+    Ok(Json(onboarding).into_response())
+}
+
+/// Complete an onboarding step, such as accepting the privacy notice.
+#[utoipa::path(
+    post,
+    params(
+        ("step" = OnboardingStep, Path, description = "The step to complete"),
+    ),
+    path = "/onboarding/steps/{step}",
+    responses(
+        (status = StatusCode::OK, body = OnboardingState),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "onboarding",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn complete_onboarding_step(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(step): Path<OnboardingStep>,
+) -> Result<Response, AppError> {
+    let onboarding = state
+        .onboarding_service
+        .complete_onboarding_step(&user.id, step)
+        .await?;
+
+    Ok(Json(onboarding).into_response())
+}
+
+/// Get the most active channels to pick interests from.
+#[utoipa::path(
+    get,
+    path = "/onboarding/suggested-channels",
+    responses(
+        (status = StatusCode::OK, body = Vec<ChannelActivity>),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "onboarding",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_suggested_channels(
+    _: CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let channels = state.onboarding_service.get_suggested_channels().await?;
+
+    Ok(Json(channels).into_response())
+}
+
+/// Save the channels the current user is interested in.
+/// They are recommended until the user stamps messages, and the initial channels step is
+/// completed.
+#[utoipa::path(
+    post,
+    path = "/onboarding/channel-interests",
+    request_body = ChannelInterestsRequest,
+    responses(
+        (status = StatusCode::OK, body = OnboardingState),
+        (status = StatusCode::BAD_REQUEST),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "onboarding",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn save_channel_interests(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Json(payload): Json<ChannelInterestsRequest>,
+) -> Result<Response, AppError> {
+    let channel_ids: Vec<ChannelId> = payload
+        .channel_ids
+        .into_iter()
+        .map(ChannelId::from)
+        .collect();
+    let onboarding = state
+        .onboarding_service
+        .save_channel_interests(&user.id.into(), &channel_ids)
+        .await?;
+
+    Ok(Json(onboarding).into_response())
+}
+
+/// Whether the endpoint is needed to complete onboarding.
+fn is_available_before_onboarding(path: &str) -> bool {
+    path == "/me"
+        || path == "/meta"
+        || path == "/version"
+        || path.starts_with("/auth/")
+        || path.starts_with("/onboarding")
+        || path.starts_with("/presence/")
+}
+
+/// Responds with 428 Precondition Required and the onboarding state until the user completes
+/// onboarding.
+pub async fn require_onboarding(
+    auth_session: AuthSession,
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Unauthenticated requests are rejected by `require_login`, or served by public routes
+    let Some(user) = auth_session.user else {
+        return next.run(request).await;
+    };
+    if is_available_before_onboarding(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    match state
+        .onboarding_service
+        .get_onboarding_state(&user.id)
+        .await
+    {
+        Ok(onboarding) if onboarding.is_complete() => next.run(request).await,
+        Ok(onboarding) => (StatusCode::PRECONDITION_REQUIRED, Json(onboarding)).into_response(),
+        Err(e) => AppError::from(e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use domain::{id::UserId, service::MockOnboardingService, test_factories::UserBuilder};
+    use fake::{Fake, uuid::UUIDv4};
+    use http::header;
+    use mockall::predicate;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_complete_onboarding_step() {
+        let user = UserBuilder::new().build();
+        let onboarding = OnboardingState {
+            crawl_consent_granted: true,
+            ..Default::default()
+        };
+
+        let mut mock_onboarding_service = MockOnboardingService::new();
+        mock_onboarding_service
+            .expect_complete_onboarding_step()
+            .with(
+                predicate::eq(user.id),
+                predicate::eq(OnboardingStep::CrawlConsent),
+            )
+            .times(1)
+            .returning(move |_, _| Ok(onboarding));
+
+        let app = TestAppBuilder::new()
+            .with_onboarding_service(mock_onboarding_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/onboarding/steps/crawlConsent")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: OnboardingState = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, onboarding);
+    }
+
+    #[tokio::test]
+    async fn test_save_channel_interests() {
+        let user = UserBuilder::new().build();
+        let channel_ids: Vec<Uuid> = vec![UUIDv4.fake(), UUIDv4.fake()];
+        let onboarding = OnboardingState {
+            initial_channels_picked: true,
+            ..Default::default()
+        };
+
+        let mut mock_onboarding_service = MockOnboardingService::new();
+        mock_onboarding_service
+            .expect_save_channel_interests()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(
+                    channel_ids
+                        .iter()
+                        .copied()
+                        .map(ChannelId::from)
+                        .collect::<Vec<_>>(),
+                ),
+            )
+            .times(1)
+            .returning(move |_, _| Ok(onboarding));
+
+        let app = TestAppBuilder::new()
+            .with_onboarding_service(mock_onboarding_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/onboarding/channel-interests")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::to_string(&ChannelInterestsRequest { channel_ids }).unwrap(),
+            ))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: OnboardingState = serde_json::from_slice(&body).unwrap();
+        assert!(response.initial_channels_picked);
+    }
+
+    #[tokio::test]
+    async fn test_incomplete_onboarding_is_required() {
+        let user = UserBuilder::new().build();
+
+        let mut mock_onboarding_service = MockOnboardingService::new();
+        mock_onboarding_service
+            .expect_get_onboarding_state()
+            .returning(|_| Ok(OnboardingState::default()));
+
+        let app = TestAppBuilder::new()
+            .with_onboarding_service(mock_onboarding_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline")
+            .header(header::COOKIE, cookie.clone())
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PRECONDITION_REQUIRED);
+
+        // The onboarding endpoints stay available
+        let req = Request::builder()
+            .uri("/api/v1/onboarding")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
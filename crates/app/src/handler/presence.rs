@@ -0,0 +1,79 @@
+use crate::{handler::AppState, problem::AppError, session::AuthSession};
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+
+/// Tell the server that the client is open.
+///
+/// Clients send this every minute while they are open, so that the user is known to be online.
+#[utoipa::path(
+    post,
+    path = "/presence/heartbeat",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "presence",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn heartbeat(
+    auth_session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let (Some(user), Some(session_id)) = (auth_session.user, auth_session.session.id()) else {
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    };
+
+    state
+        .presence_service
+        .heartbeat(&session_id.to_string(), &user.id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::{body::Body, http::Request};
+    use domain::{service::MockPresenceService, test_factories::UserBuilder};
+    use http::header;
+    use mockall::predicate;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_heartbeat_is_recorded_per_session() {
+        let user = UserBuilder::new().build();
+        let mut mock_presence_service = MockPresenceService::new();
+        mock_presence_service
+            .expect_heartbeat()
+            .with(
+                predicate::function(|id: &str| !id.is_empty()),
+                predicate::eq(user.id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_presence_service(mock_presence_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/presence/heartbeat")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    handler::AppState,
+    problem::AppError,
+    session::{AuthSession, CurrentUser},
+};
+use axum::{
+    Json,
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use domain::{
+    error::DomainError,
+    model::{QuotaOperation, QuotaUsage},
+};
+use http::{Method, StatusCode, header::RETRY_AFTER};
+use time::OffsetDateTime;
+
+/// Get how much of each daily quota the current user has used.
+#[utoipa::path(
+    get,
+    path = "/me/quotas",
+    responses(
+        (status = StatusCode::OK, body = [QuotaUsage]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_my_quotas(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let quotas = state.quota_service.get_quotas(&user.id).await?;
+
+    Ok(Json(quotas).into_response())
+}
+
+/// The operation with a daily quota that a request performs, if any.
+fn operation_of(method: &Method, path: &str) -> Option<QuotaOperation> {
+    if method != Method::GET {
+        return None;
+    }
+    let runs_saved_search = path
+        .strip_prefix("/searches/")
+        .and_then(|rest| rest.strip_suffix("/messages"))
+        .is_some_and(|id| !id.is_empty() && !id.contains('/'));
+
+    (path == "/search/messages" || runs_saved_search).then_some(QuotaOperation::Search)
+}
+
+/// Uses the quota of the operation a request performs before it is handled, responding with
+/// 429 Too Many Requests and the used-up quota once the quota is used up.
+///
+/// Quotas are soft: requests are still handled if the uses can't be counted.
+pub async fn enforce_quotas(
+    auth_session: AuthSession,
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Unauthenticated requests are rejected by the handlers
+    let (Some(user), Some(operation)) = (
+        auth_session.user,
+        operation_of(request.method(), request.uri().path()),
+    ) else {
+        return next.run(request).await;
+    };
+
+    match state.quota_service.consume(&user.id, operation).await {
+        Ok(()) => next.run(request).await,
+        Err(DomainError::QuotaExceeded(usage)) => {
+            let retry_after = (usage.resets_at - OffsetDateTime::now_utc())
+                .whole_seconds()
+                .max(0);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, retry_after.to_string())],
+                Json(usage),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::warn!("Failed to use the {:?} quota: {:?}", operation, e);
+            next.run(request).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::body::{self, Body};
+    use domain::{
+        service::{MockQuotaService, MockTimelineService},
+        test_factories::UserBuilder,
+    };
+    use http::header;
+    use mockall::predicate;
+    use time::Duration;
+    use tower::ServiceExt;
+
+    #[test]
+    fn searches_are_counted_toward_the_search_quota() {
+        assert_eq!(
+            operation_of(&Method::GET, "/search/messages"),
+            Some(QuotaOperation::Search)
+        );
+        assert_eq!(
+            operation_of(&Method::GET, "/searches/1/messages"),
+            Some(QuotaOperation::Search)
+        );
+        assert_eq!(operation_of(&Method::GET, "/searches"), None);
+        assert_eq!(operation_of(&Method::DELETE, "/searches/1"), None);
+    }
+
+    #[tokio::test]
+    async fn test_searches_are_rejected_once_the_quota_is_used_up() {
+        let user = UserBuilder::new().build();
+        let usage = QuotaUsage {
+            operation: QuotaOperation::Search,
+            used: 10,
+            limit: 10,
+            resets_at: OffsetDateTime::now_utc() + Duration::HOUR,
+        };
+        let mut mock_quota_service = MockQuotaService::new();
+        let returned = usage.clone();
+        mock_quota_service
+            .expect_consume()
+            .with(
+                predicate::eq(user.id),
+                predicate::eq(QuotaOperation::Search),
+            )
+            .times(1)
+            .returning(move |_, _| Err(DomainError::QuotaExceeded(returned.clone())));
+
+        // The search is not run
+        let app = TestAppBuilder::new()
+            .with_quota_service(mock_quota_service)
+            .with_timeline_service(MockTimelineService::new())
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/search/messages?q=hello")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(res.headers().contains_key(RETRY_AFTER));
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: QuotaUsage = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, usage);
+    }
+}
@@ -0,0 +1,417 @@
+use crate::{
+    fields::{FieldsQuery, SparseJson},
+    handler::AppState,
+    problem::AppError,
+    session::CurrentUser,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use domain::model::{MessageListItem, QuotaUsage, SavedSearch};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SearchQuery {
+    /// The text to search for.
+    pub q: String,
+}
+
+/// Search crawled messages, best match first.
+///
+/// Messages are searched locally, in Meilisearch if configured or the database otherwise, so
+/// messages not crawled yet are not found.
+#[utoipa::path(
+    get,
+    path = "/search/messages",
+    params(
+        SearchQuery,
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = [MessageListItem]),
+        (status = StatusCode::BAD_REQUEST, description = "The query is empty"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::TOO_MANY_REQUESTS, body = QuotaUsage, description = "The daily search quota is used up"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+        (status = StatusCode::SERVICE_UNAVAILABLE, description = "The search engine is unavailable"),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "search",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn search_messages(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Ok(StatusCode::BAD_REQUEST.into_response());
+    }
+
+    let messages = state.timeline_service.search_messages(&user.id, q).await?;
+
+    Ok(SparseJson::new(messages, &fields).into_response())
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct SaveSearchRequest {
+    #[schema(max_length = 64)]
+    pub name: String,
+    #[schema(max_length = 256)]
+    pub query: String,
+}
+
+/// Save a search query under a name.
+#[utoipa::path(
+    post,
+    path = "/searches",
+    request_body = SaveSearchRequest,
+    responses(
+        (status = StatusCode::CREATED, body = SavedSearch),
+        (status = StatusCode::BAD_REQUEST, description = "The name or query is empty or too long, or too many searches are saved"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "search",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn save_search(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Json(payload): Json<SaveSearchRequest>,
+) -> Result<Response, AppError> {
+    let search = state
+        .timeline_service
+        .save_search(&user.id, &payload.name, &payload.query)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(search)).into_response())
+}
+
+/// Get the searches saved by the user, newest first.
+#[utoipa::path(
+    get,
+    path = "/searches",
+    responses(
+        (status = StatusCode::OK, body = [SavedSearch]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "search",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_saved_searches(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let searches = state.timeline_service.get_saved_searches(&user.id).await?;
+
+    Ok(Json(searches).into_response())
+}
+
+/// Delete a saved search.
+#[utoipa::path(
+    delete,
+    path = "/searches/{searchId}",
+    params(
+        ("searchId" = i64, Path, description = "The ID of the saved search"),
+    ),
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "search",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn delete_saved_search(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(search_id): Path<i64>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .delete_saved_search(&user.id, search_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Run a saved search, best match first.
+#[utoipa::path(
+    get,
+    path = "/searches/{searchId}/messages",
+    params(
+        ("searchId" = i64, Path, description = "The ID of the saved search"),
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = [MessageListItem]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::NOT_FOUND),
+        (status = StatusCode::TOO_MANY_REQUESTS, body = QuotaUsage, description = "The daily search quota is used up"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+        (status = StatusCode::SERVICE_UNAVAILABLE, description = "The search engine is unavailable"),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "search",
+)]
+#[tracing::instrument(skip(user, state, fields))]
+pub async fn get_saved_search_messages(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(search_id): Path<i64>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
+    let messages = state
+        .timeline_service
+        .run_saved_search(&user.id, search_id)
+        .await?;
+
+    Ok(SparseJson::new(messages, &fields).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{TestAppBuilder, login};
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use domain::{
+        error::DomainError,
+        error::SearchError,
+        service::MockTimelineService,
+        test_factories::{MessageListItemBuilder, UserBuilder},
+    };
+    use http::header;
+    use mockall::predicate;
+    use time::OffsetDateTime;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_search_messages_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let message = MessageListItemBuilder::new().content("hello world").build();
+        let messages = vec![message.clone()];
+
+        mock_timeline_service
+            .expect_search_messages()
+            .withf(move |uid, q| *uid == user_id && q == "hello")
+            .times(1)
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/search/messages?q=%20hello%20")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_empty_query() {
+        let app = TestAppBuilder::new()
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/search/messages?q=%20")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_search_messages_unavailable() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_search_messages()
+            .returning(|_, _| {
+                Err(DomainError::Search(SearchError::Request(
+                    "connection refused".to_string(),
+                )))
+            });
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/search/messages?q=hello")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_save_search_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let search = SavedSearch {
+            id: 1,
+            name: "Rust".to_string(),
+            query: "rust".to_string(),
+            created_at: OffsetDateTime::now_utc().replace_nanosecond(0).unwrap(),
+        };
+        let search_clone = search.clone();
+
+        mock_timeline_service
+            .expect_save_search()
+            .with(
+                predicate::eq(user.id),
+                predicate::eq("Rust"),
+                predicate::eq("rust"),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(search_clone.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/searches")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"name":"Rust","query":"rust"}"#))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: SavedSearch = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, search);
+    }
+
+    #[tokio::test]
+    async fn test_save_search_invalid() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_save_search()
+            .returning(|_, _, _| Err(DomainError::TooManySavedSearches(20)));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/searches")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"name":"Rust","query":"rust"}"#))
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_delete_unknown_saved_search() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_delete_saved_search()
+            .with(predicate::always(), predicate::eq(7))
+            .returning(|_, id| Err(DomainError::NoSavedSearchForId(id)));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/searches/7")
+            .method("DELETE")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_saved_search_messages() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![message.clone()];
+        mock_timeline_service
+            .expect_run_saved_search()
+            .with(predicate::always(), predicate::eq(1))
+            .times(1)
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/searches/1/messages")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0].id, message.id);
+    }
+}
@@ -1,15 +1,26 @@
-use crate::{handler::AppState, session::AuthSession};
+use crate::{
+    etag,
+    fields::{FieldsQuery, SparseJson},
+    handler::{AppState, ImageQuery},
+    problem::AppError,
+    session::CurrentUser,
+};
 use axum::{
     Json,
     extract::{Path, Query, State},
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
-use domain::model::Stamp;
-use http::{StatusCode, header};
+use domain::model::{ImageSize, Stamp};
+use http::{HeaderMap, HeaderValue, StatusCode, header};
 use serde::Deserialize;
-use utoipa::IntoParams;
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+/// Stamp images are rarely replaced, so browsers reuse them for a day before revalidating.
+/// They are private, as they are only served to logged-in users.
+const STAMP_IMAGE_CACHE_CONTROL: HeaderValue = HeaderValue::from_static("private, max-age=86400");
+
 #[derive(Debug, Deserialize, IntoParams)]
 pub struct StampSearchQuery {
     pub name: Option<String>,
@@ -31,32 +42,82 @@ pub struct StampSearchQuery {
     ),
     tag = "stamp",
 )]
-#[tracing::instrument(skip(auth_session, state))]
+#[tracing::instrument(skip(state))]
 pub async fn get_stamp_by_id(
-    auth_session: AuthSession,
+    _: CurrentUser,
     State(state): State<AppState>,
     stamp_id: Path<Uuid>,
-) -> impl IntoResponse {
-    if auth_session.user.is_none() {
-        return StatusCode::UNAUTHORIZED.into_response();
-    }
+) -> Result<Response, AppError> {
+    let stamp = state.traq_service.get_stamp_by_id(&stamp_id).await?;
 
-    let stamp = match state.traq_service.get_stamp_by_id(&stamp_id).await {
-        Ok(stamp) => stamp,
-        Err(e) => {
-            tracing::error!("{:?}", e);
+    Ok(Json(stamp).into_response())
+}
 
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+/// Get the stamps the current user added most recently, for a personalized stamp palette.
+#[utoipa::path(
+    get,
+    path = "/me/stamps/recent",
+    responses(
+        (status = StatusCode::OK, body = Vec<Stamp>),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "stamp",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_recent_stamps(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let stamps = state.traq_service.get_recent_stamps(&user.id).await?;
+
+    Ok(Json(stamps).into_response())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetStampsByIdsRequest {
+    pub stamp_ids: Vec<Uuid>,
+}
+
+/// Look up many stamps at once, keyed by ID. Stamps unknown to traQ are left out.
+#[utoipa::path(
+    post,
+    path = "/stamps/batch",
+    request_body = GetStampsByIdsRequest,
+    responses(
+        (status = StatusCode::OK, body = HashMap<Uuid, Stamp>),
+        (status = StatusCode::BAD_REQUEST, description = "Too many stamp IDs"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "stamp",
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_stamps_by_ids(
+    _: CurrentUser,
+    State(state): State<AppState>,
+    Json(payload): Json<GetStampsByIdsRequest>,
+) -> Result<Response, AppError> {
+    let stamps = state
+        .traq_service
+        .get_stamps_by_ids(&payload.stamp_ids)
+        .await?;
 
-    Json(stamp).into_response()
+    Ok(Json(stamps).into_response())
 }
 
 #[utoipa::path(
     get,
     params(
         ("stampId" = Uuid, Path, description = "The ID of the stamp to retrieve"),
+        ("size" = Option<ImageSize>, Query, description = "`thumb` or `medium` to get the image shrunk to fit in 128 or 512 pixels square (default: original size)"),
     ),
     path = "/stamps/{stampId}/image",
     responses(
@@ -70,6 +131,7 @@ pub async fn get_stamp_by_id(
                 ("image/svg+xml"),
             )
         ),
+        (status = StatusCode::NOT_MODIFIED, description = "The image matches `If-None-Match`"),
         (status = StatusCode::UNAUTHORIZED),
         (status = StatusCode::INTERNAL_SERVER_ERROR),
     ),
@@ -78,26 +140,22 @@ pub async fn get_stamp_by_id(
     ),
     tag = "stamp",
 )]
-#[tracing::instrument(skip(auth_session, state))]
+#[tracing::instrument(skip(state))]
 pub async fn get_stamp_image(
-    auth_session: AuthSession,
+    _: CurrentUser,
     State(state): State<AppState>,
     stamp_id: Path<Uuid>,
-) -> impl IntoResponse {
-    if auth_session.user.is_none() {
-        return StatusCode::UNAUTHORIZED.into_response();
-    }
-
-    let (image, content_type) = match state.traq_service.get_stamp_image(&stamp_id).await {
-        Ok(image) => image,
-        Err(e) => {
-            tracing::error!("{:?}", e);
-
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let size = query.size.unwrap_or_default();
+    let (image, content_type) = state.traq_service.get_stamp_image(&stamp_id, size).await?;
 
-    ([(header::CONTENT_TYPE, content_type)], image).into_response()
+    Ok((
+        [(header::CACHE_CONTROL, STAMP_IMAGE_CACHE_CONTROL)],
+        etag::respond(&headers, [(header::CONTENT_TYPE, content_type)], image),
+    )
+        .into_response())
 }
 
 #[utoipa::path(
@@ -105,9 +163,13 @@ pub async fn get_stamp_image(
     path = "/stamps",
     params(
         ("name" = Option<String>, Query, description = "Filter stamps by name"),
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
     ),
     responses(
-        (status = StatusCode::OK, body = Vec<Stamp>),
+        (status = StatusCode::OK, body = Vec<Stamp>, headers(
+            ("ETag" = String, description = "A weak entity tag of the stamps"),
+        )),
+        (status = StatusCode::NOT_MODIFIED, description = "The stamps match `If-None-Match`"),
         (status = StatusCode::UNAUTHORIZED),
         (status = StatusCode::INTERNAL_SERVER_ERROR),
     ),
@@ -116,48 +178,32 @@ pub async fn get_stamp_image(
     ),
     tag = "stamp",
 )]
-#[tracing::instrument(skip(auth_session, state))]
+#[tracing::instrument(skip(state))]
 pub async fn get_stamps(
-    auth_session: AuthSession,
+    _: CurrentUser,
     State(state): State<AppState>,
     Query(query): Query<StampSearchQuery>,
-) -> impl IntoResponse {
-    if auth_session.user.is_none() {
-        return StatusCode::UNAUTHORIZED.into_response();
-    }
-
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
     let stamps = if let Some(name) = query.name {
-        match state.traq_service.search_stamps(&name).await {
-            Ok(stamps) => stamps,
-            Err(e) => {
-                tracing::error!("{:?}", e);
-
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-            }
-        }
+        state.traq_service.search_stamps(&name).await?
     } else {
-        match state.traq_service.get_stamps().await {
-            Ok(stamps) => stamps,
-            Err(e) => {
-                tracing::error!("{:?}", e);
-
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-            }
-        }
+        state.traq_service.get_stamps().await?
     };
 
-    Json(stamps).into_response()
+    Ok(SparseJson::new(stamps, &fields).into_response())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_helpers::TestAppBuilder;
+    use crate::test_helpers::{TestAppBuilder, login};
     use axum::{
         body::{self, Body},
         http::Request,
     };
     use domain::{
+        error::DomainError,
         service::MockTraqService,
         test_factories::{StampBuilder, UserBuilder},
     };
@@ -206,4 +252,142 @@ mod tests {
         assert_eq!(response_stamps[0].id, stamp.id);
         assert_eq!(response_stamps[0].name, stamp.name);
     }
+
+    #[tokio::test]
+    async fn test_get_recent_stamps() {
+        let mut mock_traq_service = MockTraqService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let stamp = StampBuilder::new().build();
+        let stamps = vec![stamp.clone()];
+
+        mock_traq_service
+            .expect_get_recent_stamps()
+            .withf(move |uid| *uid == user_id)
+            .times(1)
+            .returning(move |_| Ok(stamps.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/me/stamps/recent")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<Stamp> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0].id, stamp.id);
+    }
+
+    #[tokio::test]
+    async fn test_get_stamp_image_is_cacheable() {
+        let mut mock_traq_service = MockTraqService::new();
+        let stamp_id = Uuid::new_v4();
+
+        mock_traq_service
+            .expect_get_stamp_image()
+            .times(2)
+            .returning(|_, _| Ok((vec![1, 2, 3], "image/png".to_string())));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/stamps/{stamp_id}/image"))
+            .header(header::COOKIE, cookie.clone())
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()[header::CACHE_CONTROL],
+            STAMP_IMAGE_CACHE_CONTROL
+        );
+        let etag = res.headers()[header::ETAG].clone();
+
+        // Revalidated without the body
+        let req = Request::builder()
+            .uri(format!("/api/v1/stamps/{stamp_id}/image"))
+            .header(header::COOKIE, cookie)
+            .header(header::IF_NONE_MATCH, etag)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            res.headers()[header::CACHE_CONTROL],
+            STAMP_IMAGE_CACHE_CONTROL
+        );
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_stamps_by_ids_success() {
+        let mut mock_traq_service = MockTraqService::new();
+        let stamp = StampBuilder::new().build();
+        let stamp_id = stamp.id;
+
+        mock_traq_service
+            .expect_get_stamps_by_ids()
+            .withf(move |ids| ids == [stamp_id])
+            .times(1)
+            .returning(move |_| Ok(HashMap::from([(stamp.id, stamp.clone())])));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/stamps/batch")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(format!(r#"{{"stampIds":["{stamp_id}"]}}"#)))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: HashMap<Uuid, Stamp> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[&stamp_id].id, stamp_id);
+    }
+
+    #[tokio::test]
+    async fn test_get_stamps_by_ids_too_many() {
+        let mut mock_traq_service = MockTraqService::new();
+        mock_traq_service
+            .expect_get_stamps_by_ids()
+            .returning(|_| Err(DomainError::TooManyStampIds(100)));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/stamps/batch")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"stampIds":[]}"#))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -0,0 +1,201 @@
+use crate::{
+    fields::{FieldsQuery, SparseJson},
+    handler::{AppState, timeline::ExploreQuery},
+    problem::AppError,
+    session::CurrentUser,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use domain::model::{MessageListItem, TrendingTag, TrendingWindow};
+use http::StatusCode;
+
+/// Get crawled messages tagged with a hashtag, newest first.
+#[utoipa::path(
+    get,
+    path = "/tags/{tag}/messages",
+    params(
+        ("tag" = String, Path, description = "The tag, with or without `#`. Tags are case-insensitive"),
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = [MessageListItem]),
+        (status = StatusCode::BAD_REQUEST, description = "The tag is invalid"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "tag",
+)]
+#[tracing::instrument(skip(user, state, fields))]
+pub async fn get_tag_messages(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(tag): Path<String>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
+    let messages = state
+        .timeline_service
+        .get_tag_messages(&user.id, &tag)
+        .await?;
+
+    Ok(SparseJson::new(messages, &fields).into_response())
+}
+
+/// Get the hashtags used by the most users recently.
+#[utoipa::path(
+    get,
+    path = "/tags/trending",
+    params(
+        ("window" = Option<TrendingWindow>, Query, description = "The period to count tags over (default: `24h`)"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = [TrendingTag]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "tag",
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_trending_tags(
+    _: CurrentUser,
+    State(state): State<AppState>,
+    Query(query): Query<ExploreQuery>,
+) -> Result<Response, AppError> {
+    let tags = state
+        .timeline_service
+        .get_trending_tags(query.window.unwrap_or_default())
+        .await?;
+
+    Ok(Json(tags).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::ApiError;
+    use crate::{
+        request_id::REQUEST_ID_HEADER,
+        test_helpers::{TestAppBuilder, login},
+    };
+    use axum::{
+        body::{self, Body},
+        http::Request,
+    };
+    use domain::{
+        error::DomainError,
+        service::MockTimelineService,
+        test_factories::{MessageListItemBuilder, UserBuilder},
+    };
+    use http::header;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_get_tag_messages_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let message = MessageListItemBuilder::new().content("#rust").build();
+        let messages = vec![message.clone()];
+
+        mock_timeline_service
+            .expect_get_tag_messages()
+            .withf(move |uid, tag| *uid == user_id && tag == "#Rust")
+            .times(1)
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/tags/%23Rust/messages")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_get_tag_messages_invalid_tag() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_tag_messages()
+            .returning(|_, _| Err(DomainError::InvalidTag));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/tags/123/messages")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let request_id = res.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let problem: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.detail.as_deref(), Some("invalid tag"));
+        assert_eq!(problem.request_id, Some(request_id));
+    }
+
+    #[tokio::test]
+    async fn test_get_trending_tags_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let tags = vec![TrendingTag {
+            tag: "rust".to_string(),
+            user_count: 2,
+            message_count: 3,
+        }];
+        let tags_clone = tags.clone();
+
+        mock_timeline_service
+            .expect_get_trending_tags()
+            .withf(|window| *window == TrendingWindow::ThreeDays)
+            .times(1)
+            .returning(move |_| Ok(tags_clone.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/tags/trending?window=72h")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Vec<TrendingTag> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, tags);
+    }
+}
@@ -1,14 +1,109 @@
-use crate::{handler::AppState, session::AuthSession};
-use axum::{Json, extract::State, response::IntoResponse};
-use domain::model::MessageListItem;
-use http::StatusCode;
+use crate::{
+    api_version::ApiVersion,
+    fields::{FieldsQuery, SparseJson},
+    handler::{AppState, user::MessagePageQuery},
+    problem::AppError,
+    session::CurrentUser,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use domain::{
+    error::DomainError,
+    model::{
+        Announcement, Impression, MessageListItem, MessagePage, PageAssets, TimelineUpdates,
+        TrendingWindow,
+    },
+};
+use http::{
+    HeaderMap, HeaderName, HeaderValue, StatusCode,
+    header::{ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, LINK},
+};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+/// Set on timeline responses served from memory while the database is unavailable.
+pub const DEGRADED_HEADER: HeaderName = HeaderName::from_static("x-twittra-degraded");
+
+/// The public timeline is the same for everyone, so shared caches may serve it for a while.
+const PUBLIC_TIMELINE_CACHE_CONTROL: HeaderValue = HeaderValue::from_static("public, max-age=60");
+
+/// The images listed in the `Link` header at most, so that the header stays small.
+const MAX_PREFETCH_LINKS: usize = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TimelineUpdatesQuery {
+    pub since: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ExploreQuery {
+    pub window: Option<TrendingWindow>,
+}
+
+/// Lists the images needed to render `messages` in a `Link` header, so that clients can prefetch
+/// them in bulk, and caches them in the background before clients request them. The images are
+/// linked under the root of `version`.
+fn prefetch_images(
+    state: &AppState,
+    version: ApiVersion,
+    messages: &[MessageListItem],
+) -> HeaderMap {
+    let root = version.root();
+    let assets = PageAssets::of(messages);
+    let icons = assets
+        .user_ids
+        .iter()
+        .map(|id| format!("<{root}/users/{id}/icon?size=thumb>"));
+    let stamps = assets
+        .stamp_ids
+        .iter()
+        .map(|id| format!("<{root}/stamps/{id}/image>"));
+    let links: Vec<String> = icons
+        .chain(stamps)
+        .take(MAX_PREFETCH_LINKS)
+        .map(|url| format!("{url}; rel=prefetch; as=image"))
+        .collect();
+
+    let mut headers = HeaderMap::new();
+    if links.is_empty() {
+        return headers;
+    }
+    headers.insert(
+        LINK,
+        HeaderValue::from_str(&links.join(", ")).expect("the links consist of URL-safe characters"),
+    );
+
+    let traq_service = state.traq_service.clone();
+    tokio::spawn(async move { traq_service.prefetch_images(&assets).await });
+
+    headers
+}
 
 /// Get messages for the timeline.
+///
+/// If the database is briefly unavailable, a reduced chronological timeline of recently crawled
+/// messages is returned instead, with the `X-Twittra-Degraded` header set.
+///
+/// The icons and stamp images needed to render the messages are listed in the `Link` header for
+/// clients to prefetch.
 #[utoipa::path(
     get,
     path = "/timeline",
+    params(
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
     responses(
-        (status = StatusCode::OK, body = [MessageListItem]),
+        (status = StatusCode::OK, body = [MessageListItem], headers(
+            ("X-Twittra-Degraded" = String, description = "Set to `true` if the timeline is degraded"),
+            ("Link" = String, description = "The images needed to render the messages, with `rel=prefetch`"),
+            ("ETag" = String, description = "A weak entity tag of the messages"),
+        )),
+        (status = StatusCode::NOT_MODIFIED, description = "The messages match `If-None-Match`"),
         (status = StatusCode::UNAUTHORIZED),
         (status = StatusCode::INTERNAL_SERVER_ERROR),
     ),
@@ -19,49 +114,293 @@ use http::StatusCode;
 )]
 #[tracing::instrument(skip_all)]
 pub async fn get_timeline(
-    auth_session: AuthSession,
+    CurrentUser(user): CurrentUser,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    let user = match auth_session.user {
-        Some(user) => user,
-        None => return StatusCode::UNAUTHORIZED.into_response(),
-    };
+    Extension(version): Extension<ApiVersion>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
     let messages = match state
         .timeline_service
         .get_recommended_messages(&user.id)
         .await
     {
         Ok(messages) => messages,
-        Err(e) => {
-            tracing::error!("{:?}", e);
+        Err(DomainError::Repository(e)) => {
+            let messages = state.timeline_service.get_degraded_messages(&user.id);
+            if messages.is_empty() {
+                return Err(DomainError::Repository(e).into());
+            }
+
+            tracing::warn!("Serving degraded timeline: {:?}", e);
 
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            return Ok((
+                [(DEGRADED_HEADER, HeaderValue::from_static("true"))],
+                SparseJson::new(messages, &fields),
+            )
+                .into_response());
         }
+        Err(e) => return Err(e.into()),
     };
 
-    Json(messages).into_response()
+    // Recorded in the background so that the response is not delayed
+    let impressions: Vec<Impression> = messages.iter().map(Impression::from).collect();
+    let newest_message_age = messages
+        .iter()
+        .map(|m| m.created_at)
+        .max()
+        .map(|created_at| OffsetDateTime::now_utc() - created_at);
+    let timeline_service = state.timeline_service.clone();
+    let freshness = state.freshness.clone();
+    tokio::spawn(async move {
+        if let Err(e) = timeline_service
+            .record_impressions(&user.id, &impressions)
+            .await
+        {
+            tracing::warn!("Failed to record impressions: {:?}", e);
+        }
+
+        let message_ids: Vec<Uuid> = impressions.iter().map(|i| i.message_id).collect();
+        match timeline_service
+            .find_read_message_ids(&user.id, &message_ids)
+            .await
+        {
+            Ok(read) => freshness.record(newest_message_age, message_ids.len(), read.len()),
+            Err(e) => tracing::warn!("Failed to measure timeline freshness: {:?}", e),
+        }
+    });
+
+    let links = prefetch_images(&state, version, &messages);
+    Ok((links, SparseJson::new(messages, &fields)).into_response())
+}
+
+/// Get messages from followed users in chronological order, newest first.
+///
+/// The icons and stamp images needed to render the messages are listed in the `Link` header for
+/// clients to prefetch.
+#[utoipa::path(
+    get,
+    path = "/timeline/following",
+    params(
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = [MessageListItem], headers(
+            ("Link" = String, description = "The images needed to render the messages, with `rel=prefetch`"),
+        )),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "timeline",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_following_timeline(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Extension(version): Extension<ApiVersion>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
+    let messages = state
+        .timeline_service
+        .get_following_messages(&user.id)
+        .await?;
+
+    let links = prefetch_images(&state, version, &messages);
+    Ok((links, SparseJson::new(messages, &fields)).into_response())
+}
+
+/// Get changes to timeline messages since a cursor.
+///
+/// Without `since`, no changes are returned and only the current cursor is set, so clients can
+/// start syncing from now.
+#[utoipa::path(
+    get,
+    path = "/timeline/updates",
+    params(
+        ("since" = Option<i64>, Query, description = "The cursor returned by the previous request"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = TimelineUpdates),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "timeline",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_timeline_updates(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Query(query): Query<TimelineUpdatesQuery>,
+) -> Result<Response, AppError> {
+    let updates = state
+        .timeline_service
+        .get_timeline_updates(&user.id, query.since)
+        .await?;
+
+    Ok(Json(updates).into_response())
+}
+
+/// Get the announcement to pin at the top of the timeline.
+/// No content is returned if there is none or the current user has dismissed it.
+#[utoipa::path(
+    get,
+    path = "/timeline/announcement",
+    responses(
+        (status = StatusCode::OK, body = Announcement),
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "timeline",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_announcement(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    match state.timeline_service.get_announcement(&user.id).await? {
+        Some(announcement) => Ok(Json(announcement).into_response()),
+        None => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+/// Dismiss an announcement, so that it is no longer pinned to the current user's timeline.
+#[utoipa::path(
+    post,
+    path = "/timeline/announcement/{announcementId}/dismiss",
+    params(
+        ("announcementId" = i64, Path, description = "The ID of the announcement to dismiss"),
+    ),
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "timeline",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn dismiss_announcement(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(announcement_id): Path<i64>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .dismiss_announcement(&user.id, announcement_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Get messages trending across the instance, ranked by reactions per hour regardless of the
+/// current user's affinity.
+#[utoipa::path(
+    get,
+    path = "/explore",
+    params(
+        ("window" = Option<TrendingWindow>, Query, description = "The period to rank messages over (default: `24h`)"),
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to include in each item (default: all). `id` is always included"),
+    ),
+    responses(
+        (status = StatusCode::OK, body = [MessageListItem]),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "timeline",
+)]
+#[tracing::instrument(skip(user, state, fields))]
+pub async fn get_explore(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Query(query): Query<ExploreQuery>,
+    Query(fields): Query<FieldsQuery>,
+) -> Result<Response, AppError> {
+    let messages = state
+        .timeline_service
+        .get_explore_messages(&user.id, query.window.unwrap_or_default())
+        .await?;
+
+    Ok(SparseJson::new(messages, &fields).into_response())
+}
+
+/// Get the latest messages of the channels the instance makes public, newest first.
+///
+/// This endpoint does not require authentication, and can be requested from any origin so that
+/// it can be embedded in other sites.
+#[utoipa::path(
+    get,
+    path = "/public/timeline",
+    params(MessagePageQuery),
+    responses(
+        (status = StatusCode::OK, body = MessagePage),
+        (status = StatusCode::BAD_REQUEST, description = "The cursor is invalid"),
+        (status = StatusCode::NOT_FOUND, description = "The instance has no public timeline"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    tag = "timeline",
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_public_timeline(
+    State(state): State<AppState>,
+    Query(query): Query<MessagePageQuery>,
+) -> Result<Response, AppError> {
+    let page = state
+        .timeline_service
+        .get_public_timeline(query.before)
+        .await?;
+
+    Ok((
+        [
+            (ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*")),
+            (CACHE_CONTROL, PUBLIC_TIMELINE_CACHE_CONTROL),
+        ],
+        Json(page),
+    )
+        .into_response())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_helpers::TestAppBuilder;
+    use crate::test_helpers::{TestAppBuilder, login};
     use axum::{
         body::{self, Body},
         http::Request,
     };
     use domain::{
-        service::MockTimelineService,
-        test_factories::{MessageListItemBuilder, UserBuilder},
+        error::RepositoryError,
+        service::{MockTimelineService, MockTraqService},
+        test_factories::{MessageListItemBuilder, ReactionBuilder, UserBuilder},
     };
     use http::header;
+    use mockall::predicate;
+    use std::time::Duration;
+    use tokio::{sync::mpsc, time};
     use tower::ServiceExt;
+    use uuid::Uuid;
 
     #[tokio::test]
     async fn test_get_timeline_success() {
         let mut mock_timeline_service = MockTimelineService::new();
 
-        let message = MessageListItemBuilder::new().build();
+        let stamp_id = Uuid::new_v4();
+        let message = MessageListItemBuilder::new()
+            .reactions(vec![ReactionBuilder::new().stamp_id(stamp_id).build()])
+            .build();
         let user_id_clone = message.user_id; // Will be overwritten by UserBuilder if not careful, but let's align them.
         let messages = vec![message.clone()];
         let messages_clone = messages.clone();
@@ -71,11 +410,29 @@ mod tests {
             .withf(move |uid| *uid == user_id_clone)
             .times(1)
             .returning(move |_| Ok(messages_clone.clone()));
+        let (impressions_tx, mut impressions_rx) = mpsc::unbounded_channel();
+        mock_timeline_service
+            .expect_record_impressions()
+            .times(1)
+            .returning(move |_, impressions| {
+                impressions_tx.send(impressions.to_vec()).unwrap();
+                Ok(())
+            });
+        mock_timeline_service
+            .expect_find_read_message_ids()
+            .returning(|_, _| Ok(vec![]));
+        let mut mock_traq_service = MockTraqService::new();
+        let (prefetch_tx, mut prefetch_rx) = mpsc::unbounded_channel();
+        mock_traq_service
+            .expect_prefetch_images()
+            .times(1)
+            .returning(move |assets| prefetch_tx.send(assets.clone()).unwrap());
 
         let user = UserBuilder::new().id(message.user_id).build();
 
         let app = TestAppBuilder::new()
             .with_timeline_service(mock_timeline_service)
+            .with_traq_service(mock_traq_service)
             .with_user(user.clone())
             .build();
 
@@ -100,6 +457,14 @@ mod tests {
 
         let res = app.oneshot(req).await.unwrap();
         assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()[header::LINK],
+            format!(
+                "</api/v1/users/{}/icon?size=thumb>; rel=prefetch; as=image, \
+                 </api/v1/stamps/{stamp_id}/image>; rel=prefetch; as=image",
+                message.user_id
+            )
+        );
 
         // Validate response body
         let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
@@ -108,6 +473,326 @@ mod tests {
         assert_eq!(response_messages[0].id, message.id);
         assert_eq!(response_messages[0].content, message.content);
         assert_eq!(response_messages[0].user_id, message.user_id);
+
+        // Impressions are recorded in the background
+        let impressions = time::timeout(Duration::from_secs(1), impressions_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(impressions, vec![Impression::from(&message)]);
+        // The images are cached in the background
+        let assets = time::timeout(Duration::from_secs(1), prefetch_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(assets, PageAssets::of(&[message]));
+    }
+
+    #[tokio::test]
+    async fn test_get_timeline_with_fields() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let message = MessageListItemBuilder::new().build();
+        let message_clone = message.clone();
+
+        mock_timeline_service
+            .expect_get_recommended_messages()
+            .times(1)
+            .returning(move |_| Ok(vec![message_clone.clone()]));
+        mock_timeline_service
+            .expect_record_impressions()
+            .returning(|_, _| Ok(()));
+        mock_timeline_service
+            .expect_find_read_message_ids()
+            .returning(|_, _| Ok(vec![]));
+        let mut mock_traq_service = MockTraqService::new();
+        mock_traq_service.expect_prefetch_images().returning(|_| ());
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline?fields=userId,createdAt")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let item = response[0].as_object().unwrap();
+        let mut keys: Vec<&str> = item.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["createdAt", "id", "userId"]);
+        assert_eq!(item["id"], message.id.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_get_timeline_degraded() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let message = MessageListItemBuilder::new().build();
+        let message_clone = message.clone();
+
+        mock_timeline_service
+            .expect_get_recommended_messages()
+            .times(1)
+            .returning(|_| {
+                Err(DomainError::Repository(RepositoryError::Database(
+                    "connection refused".to_string(),
+                )))
+            });
+        mock_timeline_service
+            .expect_get_degraded_messages()
+            .times(1)
+            .returning(move |_| vec![message_clone.clone()]);
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(DEGRADED_HEADER).unwrap(), "true");
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response_messages: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response_messages.len(), 1);
+        assert_eq!(response_messages[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_get_timeline_error_without_degraded_messages() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+
+        mock_timeline_service
+            .expect_get_recommended_messages()
+            .returning(|_| {
+                Err(DomainError::Repository(RepositoryError::Database(
+                    "connection refused".to_string(),
+                )))
+            });
+        mock_timeline_service
+            .expect_get_degraded_messages()
+            .returning(|_| vec![]);
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_get_following_timeline_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let message = MessageListItemBuilder::new().build();
+        let message_clone = message.clone();
+
+        mock_timeline_service
+            .expect_get_following_messages()
+            .withf(move |uid| *uid == user_id)
+            .times(1)
+            .returning(move |_| Ok(vec![message_clone.clone()]));
+        let mut mock_traq_service = MockTraqService::new();
+        mock_traq_service.expect_prefetch_images().returning(|_| ());
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline/following")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response_messages: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response_messages.len(), 1);
+        assert_eq!(response_messages[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_images_are_linked_under_the_requested_version() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let message = MessageListItemBuilder::new().build();
+        let message_clone = message.clone();
+
+        mock_timeline_service
+            .expect_get_following_messages()
+            .times(1)
+            .returning(move |_| Ok(vec![message_clone.clone()]));
+        let mut mock_traq_service = MockTraqService::new();
+        mock_traq_service.expect_prefetch_images().returning(|_| ());
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_traq_service(mock_traq_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v2/timeline/following")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()[header::LINK],
+            format!(
+                "</api/v2/users/{}/icon?size=thumb>; rel=prefetch; as=image",
+                message.user_id
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_explore_with_window() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let message = MessageListItemBuilder::new().build();
+        let message_clone = message.clone();
+
+        mock_timeline_service
+            .expect_get_explore_messages()
+            .withf(move |uid, window| *uid == user_id && *window == TrendingWindow::ThreeDays)
+            .times(1)
+            .returning(move |_, _| Ok(vec![message_clone.clone()]));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/explore?window=72h")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response_messages: Vec<MessageListItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response_messages.len(), 1);
+        assert_eq!(response_messages[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_get_timeline_updates_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let message = MessageListItemBuilder::new().build();
+        let updates = TimelineUpdates {
+            cursor: 20,
+            added: vec![message.clone()],
+            ..Default::default()
+        };
+
+        mock_timeline_service
+            .expect_get_timeline_updates()
+            .withf(move |uid, since| *uid == user_id && *since == Some(10))
+            .times(1)
+            .returning(move |_, _| Ok(updates.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline/updates?since=10")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: TimelineUpdates = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.cursor, 20);
+        assert_eq!(response.added.len(), 1);
+        assert_eq!(response.added[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_get_timeline_updates_without_cursor() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+
+        mock_timeline_service
+            .expect_get_timeline_updates()
+            .withf(|_, since| since.is_none())
+            .times(1)
+            .returning(|_, _| {
+                Ok(TimelineUpdates {
+                    cursor: 42,
+                    ..Default::default()
+                })
+            });
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline/updates")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: TimelineUpdates = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.cursor, 42);
+        assert!(response.added.is_empty());
     }
 
     #[tokio::test]
@@ -121,4 +806,109 @@ mod tests {
         let res = app.oneshot(req).await.unwrap();
         assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
     }
+
+    #[tokio::test]
+    async fn test_get_announcement_dismissed() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_announcement()
+            .times(1)
+            .returning(|_| Ok(None));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline/announcement")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_dismiss_announcement() {
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_dismiss_announcement()
+            .withf(move |uid, id| *uid == user_id && *id == 3)
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/timeline/announcement/3/dismiss")
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_get_public_timeline_without_login() {
+        let message = MessageListItemBuilder::new().build();
+        let expected_id = message.id;
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_public_timeline()
+            .with(predicate::eq(None))
+            .times(1)
+            .returning(move |_| {
+                Ok(MessagePage {
+                    messages: vec![message.clone()],
+                    next_cursor: None,
+                })
+            });
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .build();
+        let req = Request::builder()
+            .uri("/api/v1/public/timeline")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let page: MessagePage = serde_json::from_slice(&body).unwrap();
+        assert_eq!(page.messages.len(), 1);
+        assert_eq!(page.messages[0].id, expected_id);
+    }
+
+    #[tokio::test]
+    async fn test_get_public_timeline_disabled() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_public_timeline()
+            .returning(|_| Err(DomainError::PublicTimelineDisabled));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .build();
+        let req = Request::builder()
+            .uri("/api/v1/public/timeline")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
 }
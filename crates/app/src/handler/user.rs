@@ -1,13 +1,31 @@
-use crate::{handler::AppState, session::AuthSession};
+use crate::{
+    etag,
+    handler::{AppState, ImageQuery},
+    problem::AppError,
+    rate_limit::ApiUsage,
+    session::CurrentUser,
+};
 use axum::{
     Json,
-    extract::{Path, State},
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use domain::{
+    id::UserId,
+    model::{Affinity, ImageSize, MessagePage, PrivacySettings, User, UserProfile},
 };
-use domain::model::User;
-use http::{StatusCode, header};
+use http::{HeaderMap, StatusCode, header};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use utoipa::IntoParams;
 use uuid::Uuid;
 
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MessagePageQuery {
+    /// The `nextCursor` of the previous page. The first page is returned if omitted.
+    pub before: Option<String>,
+}
+
 /// Get the current authenticated user's information.
 #[utoipa::path(
     get,
@@ -23,24 +41,126 @@ use uuid::Uuid;
     tag = "user",
 )]
 #[tracing::instrument(skip_all)]
-pub async fn get_me(auth_session: AuthSession, State(state): State<AppState>) -> impl IntoResponse {
-    let user_id = match auth_session.user {
-        Some(user) => user.id,
-        None => return StatusCode::UNAUTHORIZED.into_response(),
-    };
-    let user = match state.traq_service.get_user_by_id(&user_id).await {
-        Ok(user) => user,
-        Err(e) => {
-            tracing::error!("{:?}", e);
+pub async fn get_me(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let user = state.traq_service.get_user_by_id(&user.id).await?;
 
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+    Ok(Json(user).into_response())
+}
+
+/// Get the authors and channels whose messages are recommended to the current user for their
+/// affinity, with their scores.
+#[utoipa::path(
+    get,
+    path = "/users/me/affinity",
+    responses(
+        (status = StatusCode::OK, body = Affinity),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_my_affinity(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let affinity = state.timeline_service.get_affinity(&user.id).await?;
+
+    Ok(Json(affinity).into_response())
+}
+
+/// Get the current user's API calls in the last hour and how many are left before being
+/// throttled.
+///
+/// This endpoint is not rate limited itself.
+#[utoipa::path(
+    get,
+    path = "/users/me/usage",
+    responses(
+        (status = StatusCode::OK, body = ApiUsage),
+        (status = StatusCode::UNAUTHORIZED),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_my_usage(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    Json(
+        state
+            .rate_limiter
+            .usage(&user.id, OffsetDateTime::now_utc()),
+    )
+    .into_response()
+}
+
+/// Get the current user's privacy settings.
+#[utoipa::path(
+    get,
+    path = "/me/privacy-settings",
+    responses(
+        (status = StatusCode::OK, body = PrivacySettings),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_privacy_settings(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let settings = state
+        .timeline_service
+        .get_privacy_settings(&user.id)
+        .await?;
+
+    Ok(Json(settings).into_response())
+}
+
+/// Replace the current user's privacy settings.
+#[utoipa::path(
+    put,
+    path = "/me/privacy-settings",
+    request_body = PrivacySettings,
+    responses(
+        (status = StatusCode::OK, body = PrivacySettings),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn set_privacy_settings(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Json(settings): Json<PrivacySettings>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .set_privacy_settings(&user.id, &settings)
+        .await?;
 
-    Json(user).into_response()
+    Ok(Json(settings).into_response())
 }
 
-/// Get a user's information by user ID.
+/// Get a user's information by user ID, with activity stats for their profile page.
 #[utoipa::path(
     get,
     params(
@@ -48,7 +168,7 @@ pub async fn get_me(auth_session: AuthSession, State(state): State<AppState>) ->
     ),
     path = "/users/{userId}",
     responses(
-        (status = StatusCode::OK, body = User),
+        (status = StatusCode::OK, body = UserProfile),
         (status = StatusCode::UNAUTHORIZED),
         (status = StatusCode::INTERNAL_SERVER_ERROR),
     ),
@@ -57,26 +177,49 @@ pub async fn get_me(auth_session: AuthSession, State(state): State<AppState>) ->
     ),
     tag = "user",
 )]
-#[tracing::instrument(skip(auth_session, state))]
+#[tracing::instrument(skip(state))]
 pub async fn get_user_by_id(
-    auth_session: AuthSession,
+    _: CurrentUser,
     State(state): State<AppState>,
     user_id: Path<Uuid>,
-) -> impl IntoResponse {
-    if auth_session.user.is_none() {
-        return StatusCode::UNAUTHORIZED.into_response();
-    }
+) -> Result<Response, AppError> {
+    let profile = state.traq_service.get_user_profile(&user_id).await?;
 
-    let user = match state.traq_service.get_user_by_id(&user_id).await {
-        Ok(user) => user,
-        Err(e) => {
-            tracing::error!("{:?}", e);
+    Ok(Json(profile).into_response())
+}
 
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+/// Get a page of crawled messages posted by a user, newest first.
+#[utoipa::path(
+    get,
+    path = "/users/{userId}/messages",
+    params(
+        ("userId" = Uuid, Path, description = "The ID of the author"),
+        MessagePageQuery,
+    ),
+    responses(
+        (status = StatusCode::OK, body = MessagePage),
+        (status = StatusCode::BAD_REQUEST, description = "The cursor is invalid"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn get_user_messages(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(author_id): Path<Uuid>,
+    Query(query): Query<MessagePageQuery>,
+) -> Result<Response, AppError> {
+    let page = state
+        .timeline_service
+        .get_user_messages(&user.id, &author_id, query.before)
+        .await?;
 
-    Json(user).into_response()
+    Ok(Json(page).into_response())
 }
 
 /// Get a user's icon by user ID.
@@ -84,6 +227,7 @@ pub async fn get_user_by_id(
     get,
     params(
         ("userId" = Uuid, Path, description = "The ID of the user to retrieve"),
+        ("size" = Option<ImageSize>, Query, description = "`thumb` or `medium` to get the image shrunk to fit in 128 or 512 pixels square (default: original size)"),
     ),
     path = "/users/{userId}/icon",
     responses(
@@ -96,6 +240,7 @@ pub async fn get_user_by_id(
                 ("image/png"),
             )
         ),
+        (status = StatusCode::NOT_MODIFIED, description = "The icon matches `If-None-Match`"),
         (status = StatusCode::UNAUTHORIZED),
         (status = StatusCode::INTERNAL_SERVER_ERROR),
     ),
@@ -106,35 +251,222 @@ pub async fn get_user_by_id(
 )]
 #[tracing::instrument]
 pub async fn get_user_icon(
-    auth_session: AuthSession,
+    _: CurrentUser,
     State(state): State<AppState>,
     user_id: Path<Uuid>,
-) -> impl IntoResponse {
-    if auth_session.user.is_none() {
-        return StatusCode::UNAUTHORIZED.into_response();
-    }
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let size = query.size.unwrap_or_default();
+    let (icon, content_type) = state.traq_service.get_user_icon(&user_id, size).await?;
 
-    let (icon, content_type) = match state.traq_service.get_user_icon(&user_id).await {
-        Ok(icon) => icon,
-        Err(e) => {
-            tracing::error!("{:?}", e);
+    Ok(etag::respond(&headers, [(header::CONTENT_TYPE, content_type)], icon).into_response())
+}
 
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+/// Mute a user. Messages from muted users no longer appear in the timeline.
+#[utoipa::path(
+    post,
+    params(
+        ("userId" = Uuid, Path, description = "The ID of the user to mute"),
+    ),
+    path = "/users/{userId}/mute",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::BAD_REQUEST, description = "Tried to mute yourself"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn mute_user(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<UserId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .mute_user(&user.id.into(), &user_id)
+        .await?;
 
-    ([(header::CONTENT_TYPE, content_type)], icon).into_response()
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Unmute a user.
+#[utoipa::path(
+    delete,
+    params(
+        ("userId" = Uuid, Path, description = "The ID of the user to unmute"),
+    ),
+    path = "/users/{userId}/mute",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn unmute_user(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<UserId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .unmute_user(&user.id.into(), &user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Block a user. Neither user sees the other's messages in their timeline.
+#[utoipa::path(
+    post,
+    params(
+        ("userId" = Uuid, Path, description = "The ID of the user to block"),
+    ),
+    path = "/users/{userId}/block",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::BAD_REQUEST, description = "Tried to block yourself"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn block_user(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<UserId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .block_user(&user.id.into(), &user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Unblock a user.
+#[utoipa::path(
+    delete,
+    params(
+        ("userId" = Uuid, Path, description = "The ID of the user to unblock"),
+    ),
+    path = "/users/{userId}/block",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn unblock_user(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<UserId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .unblock_user(&user.id.into(), &user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Follow a user. Messages from followed users appear in the following timeline.
+#[utoipa::path(
+    post,
+    params(
+        ("userId" = Uuid, Path, description = "The ID of the user to follow"),
+    ),
+    path = "/users/{userId}/follow",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::BAD_REQUEST, description = "Tried to follow yourself"),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn follow_user(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<UserId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .follow_user(&user.id.into(), &user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Unfollow a user.
+#[utoipa::path(
+    delete,
+    params(
+        ("userId" = Uuid, Path, description = "The ID of the user to unfollow"),
+    ),
+    path = "/users/{userId}/follow",
+    responses(
+        (status = StatusCode::NO_CONTENT),
+        (status = StatusCode::UNAUTHORIZED),
+        (status = StatusCode::INTERNAL_SERVER_ERROR),
+    ),
+    security(
+        ("cookieAuth" = []),
+    ),
+    tag = "user",
+)]
+#[tracing::instrument(skip(user, state))]
+pub async fn unfollow_user(
+    CurrentUser(user): CurrentUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<UserId>,
+) -> Result<Response, AppError> {
+    state
+        .timeline_service
+        .unfollow_user(&user.id.into(), &user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_helpers::TestAppBuilder;
+    use crate::test_helpers::{TestAppBuilder, login};
     use axum::{
         body::{self, Body},
         http::Request,
     };
-    use domain::{service::MockTraqService, test_factories::UserBuilder};
+    use domain::{
+        error::DomainError,
+        model::{AffinityScore, UserStats},
+        service::{MockTimelineService, MockTraqService},
+        test_factories::{MessageListItemBuilder, UserBuilder},
+    };
+    use fake::{Fake, uuid::UUIDv4};
     use mockall::predicate;
     use tower::ServiceExt;
 
@@ -181,4 +513,551 @@ mod tests {
         assert_eq!(response_user.handle, user.handle);
         assert_eq!(response_user.display_name, user.display_name);
     }
+
+    #[tokio::test]
+    async fn test_get_user_by_id_includes_stats() {
+        let mut mock_traq_service = MockTraqService::new();
+        let user = UserBuilder::new().build();
+        let profile = UserProfile {
+            user: user.clone(),
+            stats: UserStats {
+                message_count: 12,
+                reactions_received: 34,
+            },
+        };
+
+        mock_traq_service
+            .expect_get_user_profile()
+            .with(predicate::eq(user.id))
+            .times(1)
+            .returning(move |_| Ok(profile.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}", user.id))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response["id"], user.id.to_string());
+        assert_eq!(response["handle"], user.handle);
+        assert_eq!(response["stats"]["messageCount"], 12);
+        assert_eq!(response["stats"]["reactionsReceived"], 34);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_icon_not_modified() {
+        let mut mock_traq_service = MockTraqService::new();
+        let user_id: Uuid = UUIDv4.fake();
+
+        mock_traq_service
+            .expect_get_user_icon()
+            .with(predicate::eq(user_id), predicate::eq(ImageSize::Original))
+            .times(2)
+            .returning(|_, _| Ok((vec![1, 2, 3], "image/png".to_string())));
+
+        let app = TestAppBuilder::new()
+            .with_traq_service(mock_traq_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{user_id}/icon"))
+            .header(header::COOKIE, cookie.clone())
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{user_id}/icon"))
+            .header(header::COOKIE, cookie)
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG), Some(&etag));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_messages_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let author_id: Uuid = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().user_id(author_id).build();
+        let page = MessagePage {
+            messages: vec![message.clone()],
+            next_cursor: Some("next".to_string()),
+        };
+
+        mock_timeline_service
+            .expect_get_user_messages()
+            .with(
+                predicate::eq(user.id),
+                predicate::eq(author_id),
+                predicate::eq(Some("cursor".to_string())),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(page.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!(
+                "/api/v1/users/{}/messages?before=cursor",
+                author_id
+            ))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: MessagePage = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(response.messages[0].id, message.id);
+        assert_eq!(response.next_cursor.as_deref(), Some("next"));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_messages_invalid_cursor() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service
+            .expect_get_user_messages()
+            .returning(|_, _, _| Err(DomainError::InvalidCursor));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        let author_id: Uuid = UUIDv4.fake();
+        let req = Request::builder()
+            .uri(format!(
+                "/api/v1/users/{}/messages?before=invalid",
+                author_id
+            ))
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_get_my_affinity_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let affinity = Affinity {
+            authors: vec![AffinityScore {
+                id: UUIDv4.fake(),
+                score: 2.5,
+            }],
+            channels: vec![AffinityScore {
+                id: UUIDv4.fake(),
+                score: 1.0,
+            }],
+        };
+        let affinity_clone = affinity.clone();
+
+        mock_timeline_service
+            .expect_get_affinity()
+            .with(predicate::eq(user.id))
+            .times(1)
+            .returning(move |_| Ok(affinity_clone.clone()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/v1/users/me/affinity")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: Affinity = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, affinity);
+    }
+
+    #[tokio::test]
+    async fn test_get_my_usage_counts_other_calls() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        mock_timeline_service.expect_get_affinity().returning(|_| {
+            Ok(Affinity {
+                authors: vec![],
+                channels: vec![],
+            })
+        });
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(UserBuilder::new().build())
+            .build();
+        let cookie = login(&app).await;
+
+        // The usage endpoint doesn't count itself
+        for _ in 0..2 {
+            let req = Request::builder()
+                .uri("/api/v1/users/me/affinity")
+                .header(header::COOKIE, &cookie)
+                .body(Body::empty())
+                .unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+
+            let req = Request::builder()
+                .uri("/api/v1/users/me/usage")
+                .header(header::COOKIE, &cookie)
+                .body(Body::empty())
+                .unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        let req = Request::builder()
+            .uri("/api/v1/users/me/usage")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let usage: ApiUsage = serde_json::from_slice(&body).unwrap();
+        assert_eq!((usage.total_calls, usage.total_throttled), (2, 0));
+        assert_eq!(usage.remaining, None);
+    }
+
+    #[tokio::test]
+    async fn test_set_privacy_settings_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let settings = PrivacySettings {
+            recommend_only_to_acquaintances: true,
+            share_content_in_highlights: false,
+        };
+
+        mock_timeline_service
+            .expect_set_privacy_settings()
+            .with(predicate::eq(user.id), predicate::eq(settings))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .method("PUT")
+            .uri("/api/v1/me/privacy-settings")
+            .header(header::COOKIE, cookie)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"recommendOnlyToAcquaintances":true}"#))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let response: PrivacySettings = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, settings);
+    }
+
+    #[tokio::test]
+    async fn test_mute_user_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let muted_user_id: UserId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_mute_user()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(muted_user_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/mute", muted_user_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_mute_self_is_bad_request() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+
+        mock_timeline_service
+            .expect_mute_user()
+            .times(1)
+            .returning(|_, _| Err(DomainError::CannotMuteSelf));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/mute", user_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_unmute_user_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let muted_user_id: UserId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_unmute_user()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(muted_user_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/mute", muted_user_id))
+            .method("DELETE")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_block_user_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let blocked_user_id: UserId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_block_user()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(blocked_user_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/block", blocked_user_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_block_self_is_bad_request() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+
+        mock_timeline_service
+            .expect_block_user()
+            .times(1)
+            .returning(|_, _| Err(DomainError::CannotBlockSelf));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/block", user_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_unblock_user_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let blocked_user_id: UserId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_unblock_user()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(blocked_user_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/block", blocked_user_id))
+            .method("DELETE")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_follow_user_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let followed_user_id: UserId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_follow_user()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(followed_user_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/follow", followed_user_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_follow_self_is_bad_request() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+
+        mock_timeline_service
+            .expect_follow_user()
+            .times(1)
+            .returning(|_, _| Err(DomainError::CannotFollowSelf));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/follow", user_id))
+            .method("POST")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_unfollow_user_success() {
+        let mut mock_timeline_service = MockTimelineService::new();
+        let user = UserBuilder::new().build();
+        let followed_user_id: UserId = UUIDv4.fake();
+
+        mock_timeline_service
+            .expect_unfollow_user()
+            .with(
+                predicate::eq(UserId::from(user.id)),
+                predicate::eq(followed_user_id),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let app = TestAppBuilder::new()
+            .with_timeline_service(mock_timeline_service)
+            .with_user(user)
+            .build();
+        let cookie = login(&app).await;
+
+        let req = Request::builder()
+            .uri(format!("/api/v1/users/{}/follow", followed_user_id))
+            .method("DELETE")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
 }
@@ -0,0 +1,547 @@
+//! A small scheduler for periodic background jobs.
+//!
+//! Every periodic task (crawling, session cleanup, ...) implements [`Job`] and is registered to a
+//! [`JobScheduler`] with a [`Schedule`], instead of spawning its own ad-hoc loop. This gives all
+//! jobs the same jitter handling, metrics, and graceful shutdown behavior.
+
+use crate::request_id;
+use domain::{error::RepositoryError, model::JobRun, repository::JobRunRepository};
+use reqwest::Client;
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use tokio::{
+    sync::{Notify, watch},
+    task::{self, JoinHandle},
+    time::sleep,
+};
+
+pub mod channel_sync;
+pub mod crawler;
+pub mod daily_summary;
+#[cfg(feature = "embeddings")]
+pub mod embedding;
+pub mod engagement_metrics;
+pub mod history_cleanup;
+pub mod notification_flush;
+pub mod presence_cleanup;
+pub mod quota_cleanup;
+pub mod read_roll_up;
+pub mod session_cleanup;
+
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub type JobError = Box<dyn Error + Send + Sync>;
+
+#[async_trait::async_trait]
+pub trait Job: Send + Sync {
+    /// A unique name used to identify the job in logs and metrics.
+    fn name(&self) -> &'static str;
+    async fn run(&self) -> Result<(), JobError>;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Schedule {
+    interval: Duration,
+    jitter: Duration,
+}
+
+impl Schedule {
+    pub fn every(interval: Duration) -> Self {
+        Self {
+            interval,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    /// Adds a random delay of up to `jitter` to every interval.
+    /// This prevents jobs with the same interval from hitting traQ or the DB at the same moment.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    fn next_delay(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return self.interval;
+        }
+
+        let jitter_ms = fastrand::u64(0..=self.jitter.as_millis() as u64);
+        self.interval + Duration::from_millis(jitter_ms)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct JobMetrics {
+    pub runs: u64,
+    pub failures: u64,
+    pub last_duration: Option<Duration>,
+    pub last_error: Option<String>,
+}
+
+struct RegisteredJob {
+    job: Arc<dyn Job>,
+    schedule: Schedule,
+    metrics: Arc<Mutex<JobMetrics>>,
+    trigger: Arc<Notify>,
+}
+
+/// A dead-man switch URL (healthchecks.io style) pinged after every successful run.
+/// Monitoring services alert when the pings stop, which catches jobs that silently stopped.
+#[derive(Clone, Debug)]
+struct Heartbeat {
+    client: Client,
+    url: String,
+}
+
+impl Heartbeat {
+    async fn ping(&self) {
+        let result = self
+            .client
+            .get(&self.url)
+            .timeout(HEARTBEAT_TIMEOUT)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status());
+
+        // A failed ping looks like a missed heartbeat to the monitoring service, so just log it
+        if let Err(e) = result {
+            tracing::warn!("Failed to ping heartbeat URL: {:?}", e);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct JobScheduler {
+    jobs: Vec<RegisteredJob>,
+    history: Option<Arc<dyn JobRunRepository>>,
+    heartbeat_urls: HashMap<String, String>,
+}
+
+impl JobScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<J: Job + 'static>(mut self, job: J, schedule: Schedule) -> Self {
+        self.jobs.push(RegisteredJob {
+            job: Arc::new(job),
+            schedule,
+            metrics: Arc::default(),
+            trigger: Arc::default(),
+        });
+        self
+    }
+
+    /// Records every run of every job to `history`.
+    pub fn with_history(mut self, history: Arc<dyn JobRunRepository>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Sets heartbeat URLs keyed by job name. Jobs without a URL don't send heartbeats.
+    pub fn with_heartbeat_urls(mut self, heartbeat_urls: HashMap<String, String>) -> Self {
+        self.heartbeat_urls = heartbeat_urls;
+        self
+    }
+
+    /// Spawns all registered jobs. Each job runs once immediately and then on its schedule.
+    pub fn start(mut self) -> RunningJobs {
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut tasks = Vec::with_capacity(self.jobs.len());
+        let mut metrics = Vec::with_capacity(self.jobs.len());
+        let mut triggers = Vec::with_capacity(self.jobs.len());
+        let client = Client::new();
+
+        for registered in self.jobs {
+            let name = registered.job.name();
+            let heartbeat = self.heartbeat_urls.remove(name).map(|url| Heartbeat {
+                client: client.clone(),
+                url,
+            });
+
+            metrics.push((name, Arc::clone(&registered.metrics)));
+            triggers.push((name, Arc::clone(&registered.trigger)));
+            tasks.push(task::spawn(run_loop(
+                registered,
+                heartbeat,
+                self.history.clone(),
+                shutdown_rx.clone(),
+            )));
+        }
+
+        for name in self.heartbeat_urls.keys() {
+            tracing::warn!("Heartbeat URL is configured for unknown job {}", name);
+        }
+
+        RunningJobs {
+            shutdown: shutdown_tx,
+            tasks,
+            metrics,
+            handle: JobHandle {
+                triggers: Arc::new(triggers),
+                history: self.history,
+            },
+        }
+    }
+}
+
+async fn run_loop(
+    registered: RegisteredJob,
+    heartbeat: Option<Heartbeat>,
+    history: Option<Arc<dyn JobRunRepository>>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        let succeeded = run_once(
+            registered.job.as_ref(),
+            &registered.metrics,
+            history.as_deref(),
+        )
+        .await;
+
+        if succeeded && let Some(heartbeat) = &heartbeat {
+            heartbeat.ping().await;
+        }
+
+        // A job that is already running is allowed to finish; shutdown only interrupts the wait.
+        tokio::select! {
+            _ = sleep(registered.schedule.next_delay()) => {}
+            _ = registered.trigger.notified() => {}
+            _ = shutdown.changed() => break,
+        }
+    }
+
+    tracing::info!("Job {} stopped", registered.job.name());
+}
+
+#[tracing::instrument(skip_all, fields(job = job.name(), request_id = request_id::generate()))]
+async fn run_once(
+    job: &dyn Job,
+    metrics: &Mutex<JobMetrics>,
+    history: Option<&dyn JobRunRepository>,
+) -> bool {
+    let started_at = OffsetDateTime::now_utc();
+    let timer = Instant::now();
+    let result = job.run().await;
+    let duration = timer.elapsed();
+
+    if let Err(e) = &result {
+        tracing::error!("Job failed: {:?}", e);
+    }
+
+    if let Some(history) = history {
+        let run = JobRun {
+            job_name: job.name().to_string(),
+            started_at,
+            duration_ms: duration.as_millis() as i64,
+            succeeded: result.is_ok(),
+            error: result.as_ref().err().map(ToString::to_string),
+        };
+        // Failing to record a run must not affect the job itself
+        if let Err(e) = history.save(&run).await {
+            tracing::error!("Failed to record job run: {:?}", e);
+        }
+    }
+
+    let mut metrics = metrics.lock().unwrap();
+    metrics.runs += 1;
+    metrics.last_duration = Some(duration);
+    match result {
+        Ok(()) => {
+            metrics.last_error = None;
+            true
+        }
+        Err(e) => {
+            metrics.failures += 1;
+            metrics.last_error = Some(e.to_string());
+            false
+        }
+    }
+}
+
+/// Handle to the spawned jobs, used to inspect metrics and to stop them.
+pub struct RunningJobs {
+    shutdown: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+    metrics: Vec<(&'static str, Arc<Mutex<JobMetrics>>)>,
+    handle: JobHandle,
+}
+
+impl RunningJobs {
+    pub fn handle(&self) -> JobHandle {
+        self.handle.clone()
+    }
+
+    /// Returns a snapshot of the metrics of every job, in registration order.
+    pub fn metrics(&self) -> Vec<(&'static str, JobMetrics)> {
+        self.metrics
+            .iter()
+            .map(|(name, metrics)| (*name, metrics.lock().unwrap().clone()))
+            .collect()
+    }
+
+    /// Signals all jobs to stop and waits for in-flight runs to finish.
+    pub async fn shutdown(mut self) {
+        let _ = self.shutdown.send(true);
+
+        for task in &mut self.tasks {
+            if let Err(e) = task.await {
+                tracing::error!("Job task panicked: {:?}", e);
+            }
+        }
+
+        for (name, metrics) in self.metrics() {
+            tracing::info!(
+                job = name,
+                runs = metrics.runs,
+                failures = metrics.failures,
+                last_duration = ?metrics.last_duration,
+                last_error = ?metrics.last_error,
+                "Job summary"
+            );
+        }
+    }
+}
+
+/// A cloneable handle to the running jobs, used by the admin API.
+#[derive(Clone, Debug, Default)]
+pub struct JobHandle {
+    triggers: Arc<Vec<(&'static str, Arc<Notify>)>>,
+    history: Option<Arc<dyn JobRunRepository>>,
+}
+
+impl JobHandle {
+    pub fn job_names(&self) -> Vec<&'static str> {
+        self.triggers.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Runs the job as soon as possible instead of waiting for its schedule.
+    /// If the job is running now, it runs once more right after the current run.
+    /// Returns `false` if there is no job with the given name.
+    pub fn trigger(&self, name: &str) -> bool {
+        match self.triggers.iter().find(|(n, _)| *n == name) {
+            Some((_, trigger)) => {
+                trigger.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the most recent runs of all jobs, newest first.
+    pub async fn recent_runs(&self, limit: i64) -> Result<Vec<JobRun>, RepositoryError> {
+        match &self.history {
+            Some(history) => history.find_recent(limit).await,
+            None => Ok(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::repository::MockJobRunRepository;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::atomic::{AtomicU64, Ordering},
+        thread,
+    };
+
+    struct CountingJob {
+        count: Arc<AtomicU64>,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl Job for CountingJob {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        async fn run(&self) -> Result<(), JobError> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                return Err("failed on purpose".into());
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn jobs_run_repeatedly_until_shutdown() {
+        let count = Arc::new(AtomicU64::new(0));
+        let jobs = JobScheduler::new()
+            .register(
+                CountingJob {
+                    count: Arc::clone(&count),
+                    fail: false,
+                },
+                Schedule::every(Duration::from_millis(10)),
+            )
+            .start();
+
+        sleep(Duration::from_millis(100)).await;
+        jobs.shutdown().await;
+
+        let count_at_shutdown = count.load(Ordering::SeqCst);
+        assert!(count_at_shutdown >= 2);
+
+        // No more runs after shutdown
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(count.load(Ordering::SeqCst), count_at_shutdown);
+    }
+
+    #[tokio::test]
+    async fn failures_are_recorded_in_metrics() {
+        let jobs = JobScheduler::new()
+            .register(
+                CountingJob {
+                    count: Arc::default(),
+                    fail: true,
+                },
+                Schedule::every(Duration::from_secs(60)),
+            )
+            .start();
+
+        sleep(Duration::from_millis(50)).await;
+
+        let metrics = jobs.metrics();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].0, "counting");
+        assert_eq!(metrics[0].1.runs, 1);
+        assert_eq!(metrics[0].1.failures, 1);
+        assert_eq!(
+            metrics[0].1.last_error.as_deref(),
+            Some("failed on purpose")
+        );
+
+        jobs.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn runs_are_recorded_to_history() {
+        let mut mock_history = MockJobRunRepository::new();
+        mock_history
+            .expect_save()
+            .withf(|run| run.job_name == "counting" && !run.succeeded)
+            .times(1)
+            .returning(|_| Ok(1));
+
+        let jobs = JobScheduler::new()
+            .register(
+                CountingJob {
+                    count: Arc::default(),
+                    fail: true,
+                },
+                Schedule::every(Duration::from_secs(60)),
+            )
+            .with_history(Arc::new(mock_history))
+            .start();
+
+        sleep(Duration::from_millis(50)).await;
+        jobs.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn triggered_job_runs_without_waiting_for_schedule() {
+        let count = Arc::new(AtomicU64::new(0));
+        let jobs = JobScheduler::new()
+            .register(
+                CountingJob {
+                    count: Arc::clone(&count),
+                    fail: false,
+                },
+                Schedule::every(Duration::from_secs(60)),
+            )
+            .start();
+        let handle = jobs.handle();
+
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        assert_eq!(handle.job_names(), vec!["counting"]);
+        assert!(handle.trigger("counting"));
+        assert!(!handle.trigger("unknown"));
+
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        jobs.shutdown().await;
+    }
+
+    /// Starts a minimal HTTP server that counts the requests it receives.
+    fn start_heartbeat_server() -> (String, Arc<AtomicU64>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ping", listener.local_addr().unwrap());
+        let pings = Arc::new(AtomicU64::new(0));
+        let pings_clone = Arc::clone(&pings);
+
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf);
+                pings_clone.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n");
+            }
+        });
+
+        (url, pings)
+    }
+
+    #[tokio::test]
+    async fn heartbeat_is_pinged_only_on_success() {
+        let (ok_url, ok_pings) = start_heartbeat_server();
+        let (failing_url, failing_pings) = start_heartbeat_server();
+
+        struct FailingJob;
+
+        #[async_trait::async_trait]
+        impl Job for FailingJob {
+            fn name(&self) -> &'static str {
+                "failing"
+            }
+
+            async fn run(&self) -> Result<(), JobError> {
+                Err("failed on purpose".into())
+            }
+        }
+
+        let jobs = JobScheduler::new()
+            .register(
+                CountingJob {
+                    count: Arc::default(),
+                    fail: false,
+                },
+                Schedule::every(Duration::from_secs(60)),
+            )
+            .register(FailingJob, Schedule::every(Duration::from_secs(60)))
+            .with_heartbeat_urls(HashMap::from([
+                ("counting".to_string(), ok_url),
+                ("failing".to_string(), failing_url),
+            ]))
+            .start();
+
+        sleep(Duration::from_millis(500)).await;
+        jobs.shutdown().await;
+
+        assert_eq!(ok_pings.load(Ordering::SeqCst), 1);
+        assert_eq!(failing_pings.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn next_delay_stays_within_jitter() {
+        let schedule = Schedule::every(Duration::from_secs(30)).with_jitter(Duration::from_secs(5));
+
+        for _ in 0..100 {
+            let delay = schedule.next_delay();
+            assert!(delay >= Duration::from_secs(30));
+            assert!(delay <= Duration::from_secs(35));
+        }
+    }
+}
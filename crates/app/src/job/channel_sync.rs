@@ -0,0 +1,16 @@
+use crate::job::{Job, JobError};
+use domain::channel_sync::ChannelSync;
+
+#[async_trait::async_trait]
+impl Job for ChannelSync {
+    fn name(&self) -> &'static str {
+        "channel_sync"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        let count = self.sync().await?;
+        tracing::debug!("Synced {count} channels");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+use crate::job::{Job, JobError};
+use domain::crawler::MessageCrawler;
+
+#[async_trait::async_trait]
+impl Job for MessageCrawler {
+    fn name(&self) -> &'static str {
+        "message_crawler"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        self.crawl().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+use crate::job::{Job, JobError};
+use domain::daily_summary::DailySummary;
+
+#[async_trait::async_trait]
+impl Job for DailySummary {
+    fn name(&self) -> &'static str {
+        "daily_summary"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        if self.post().await? {
+            tracing::info!("Posted the daily summary");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use crate::job::{Job, JobError};
+use domain::{
+    embedding::EmbeddingService, model::MessageEmbedding, repository::EmbeddingRepository,
+};
+use std::sync::Arc;
+
+/// The number of messages embedded per run.
+const BATCH_SIZE: i64 = 64;
+/// Longer messages are truncated to stay within the input limit of embedding models.
+const MAX_INPUT_CHARS: usize = 4000;
+
+/// Computes embeddings of messages that do not have one yet, newest first.
+pub struct EmbeddingJob {
+    service: Arc<dyn EmbeddingService>,
+    repo: Arc<dyn EmbeddingRepository>,
+}
+
+impl EmbeddingJob {
+    pub fn new(service: Arc<dyn EmbeddingService>, repo: Arc<dyn EmbeddingRepository>) -> Self {
+        Self { service, repo }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for EmbeddingJob {
+    fn name(&self) -> &'static str {
+        "message_embedding"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        let messages = self
+            .repo
+            .find_messages_without_embedding(BATCH_SIZE)
+            .await?;
+
+        // Embedding APIs reject empty inputs, so blank messages get an empty embedding instead,
+        // which is never similar to anything
+        let (blank, texts): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .map(|(id, content)| {
+                (
+                    id,
+                    content.chars().take(MAX_INPUT_CHARS).collect::<String>(),
+                )
+            })
+            .partition(|(_, content)| content.trim().is_empty());
+        let mut embeddings: Vec<MessageEmbedding> = blank
+            .into_iter()
+            .map(|(message_id, _)| MessageEmbedding {
+                message_id,
+                embedding: vec![],
+            })
+            .collect();
+
+        if !texts.is_empty() {
+            let (ids, contents): (Vec<_>, Vec<_>) = texts.into_iter().unzip();
+            let vectors = self.service.embed(&contents).await?;
+            embeddings.extend(ids.into_iter().zip(vectors).map(|(message_id, embedding)| {
+                MessageEmbedding {
+                    message_id,
+                    embedding,
+                }
+            }));
+        }
+
+        self.repo.save_embeddings(&embeddings).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::{embedding::MockEmbeddingService, repository::MockEmbeddingRepository};
+    use fake::{Fake, uuid::UUIDv4};
+    use mockall::predicate;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn run_saves_embeddings() {
+        let message_id: Uuid = UUIDv4.fake();
+        let blank_id: Uuid = UUIDv4.fake();
+
+        let mut repo = MockEmbeddingRepository::new();
+        repo.expect_find_messages_without_embedding()
+            .with(predicate::eq(BATCH_SIZE))
+            .times(1)
+            .returning(move |_| {
+                Ok(vec![
+                    (message_id, "hello".to_string()),
+                    (blank_id, " ".to_string()),
+                ])
+            });
+        repo.expect_save_embeddings()
+            .with(predicate::eq(vec![
+                MessageEmbedding {
+                    message_id: blank_id,
+                    embedding: vec![],
+                },
+                MessageEmbedding {
+                    message_id,
+                    embedding: vec![1.0, 0.0],
+                },
+            ]))
+            .times(1)
+            .returning(|_| Ok(()));
+        let mut service = MockEmbeddingService::new();
+        service
+            .expect_embed()
+            .with(predicate::eq(vec!["hello".to_string()]))
+            .times(1)
+            .returning(|_| Ok(vec![vec![1.0, 0.0]]));
+
+        EmbeddingJob::new(Arc::new(service), Arc::new(repo))
+            .run()
+            .await
+            .unwrap();
+    }
+}
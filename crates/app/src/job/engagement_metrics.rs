@@ -0,0 +1,89 @@
+use crate::job::{Job, JobError};
+use domain::repository::ImpressionRepository;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime, Time};
+
+/// Computes the click-through rate of each recommendation reason for the previous day (UTC), so
+/// that the ranking weights can be tuned against how users actually engage.
+pub struct EngagementMetricsJob {
+    repo: Arc<dyn ImpressionRepository>,
+}
+
+impl EngagementMetricsJob {
+    pub fn new(repo: Arc<dyn ImpressionRepository>) -> Self {
+        Self { repo }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for EngagementMetricsJob {
+    fn name(&self) -> &'static str {
+        "engagement_metrics"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        let until = OffsetDateTime::now_utc().replace_time(Time::MIDNIGHT);
+        let since = until - Duration::days(1);
+        let metrics = self.repo.compute_engagement_metrics(since, until).await?;
+
+        for m in &metrics {
+            tracing::info!(
+                date = %since.date(),
+                reason = ?m.reason,
+                impressions = m.impressions,
+                reads = m.reads,
+                reactions = m.reactions,
+                "CTR {:.3}, reaction rate {:.3}",
+                m.click_through_rate(),
+                m.reaction_rate()
+            );
+        }
+        self.repo
+            .save_engagement_metrics(since.date(), &metrics)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::{
+        model::{EngagementMetrics, RecommendationReason},
+        repository::MockImpressionRepository,
+    };
+    use mockall::predicate;
+
+    #[tokio::test]
+    async fn run_saves_metrics_of_previous_day() {
+        let today = OffsetDateTime::now_utc().date();
+        let metrics = vec![EngagementMetrics {
+            reason: RecommendationReason::Popular,
+            impressions: 10,
+            reads: 3,
+            reactions: 1,
+        }];
+        let expected = metrics.clone();
+
+        let mut repo = MockImpressionRepository::new();
+        repo.expect_compute_engagement_metrics()
+            .withf(move |since, until| {
+                since.date() == today.previous_day().unwrap() && until.date() == today
+            })
+            .times(1)
+            .returning(move |_, _| Ok(metrics.clone()));
+        repo.expect_save_engagement_metrics()
+            .with(
+                predicate::eq(today.previous_day().unwrap()),
+                predicate::eq(expected),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        EngagementMetricsJob::new(Arc::new(repo))
+            .run()
+            .await
+            .unwrap();
+    }
+}
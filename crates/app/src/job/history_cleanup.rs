@@ -0,0 +1,31 @@
+use crate::job::{Job, JobError};
+use domain::repository::JobRunRepository;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+/// Deletes job runs older than the retention period so that the history does not grow forever.
+pub struct JobHistoryCleanupJob {
+    repo: Arc<dyn JobRunRepository>,
+    retention: Duration,
+}
+
+impl JobHistoryCleanupJob {
+    pub fn new(repo: Arc<dyn JobRunRepository>, retention: Duration) -> Self {
+        Self { repo, retention }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for JobHistoryCleanupJob {
+    fn name(&self) -> &'static str {
+        "job_history_cleanup"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        self.repo
+            .delete_started_before(OffsetDateTime::now_utc() - self.retention)
+            .await?;
+
+        Ok(())
+    }
+}
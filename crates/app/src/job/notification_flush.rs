@@ -0,0 +1,27 @@
+use crate::job::{Job, JobError};
+use domain::notifier::CoalescingNotifier;
+use std::sync::Arc;
+
+/// Sends the notifications held back by a [`CoalescingNotifier`].
+pub struct NotificationFlushJob {
+    notifier: Arc<CoalescingNotifier>,
+}
+
+impl NotificationFlushJob {
+    pub fn new(notifier: Arc<CoalescingNotifier>) -> Self {
+        Self { notifier }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for NotificationFlushJob {
+    fn name(&self) -> &'static str {
+        "notification_flush"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        self.notifier.flush().await;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use crate::job::{Job, JobError};
+use domain::repository::PresenceRepository;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+/// Deletes the sessions that haven't sent a heartbeat within the retention period, so that closed
+/// sessions don't pile up.
+pub struct PresenceCleanupJob {
+    repo: Arc<dyn PresenceRepository>,
+    retention: Duration,
+}
+
+impl PresenceCleanupJob {
+    pub fn new(repo: Arc<dyn PresenceRepository>, retention: Duration) -> Self {
+        Self { repo, retention }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for PresenceCleanupJob {
+    fn name(&self) -> &'static str {
+        "presence_cleanup"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        self.repo
+            .delete_seen_before(OffsetDateTime::now_utc() - self.retention)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+use crate::job::{Job, JobError};
+use domain::repository::QuotaRepository;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+/// Deletes the uses of quotas on past days, which no longer count toward any quota.
+pub struct QuotaCleanupJob {
+    repo: Arc<dyn QuotaRepository>,
+}
+
+impl QuotaCleanupJob {
+    pub fn new(repo: Arc<dyn QuotaRepository>) -> Self {
+        Self { repo }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for QuotaCleanupJob {
+    fn name(&self) -> &'static str {
+        "quota_cleanup"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        // Yesterday is kept, in case a request around midnight is still using it
+        let yesterday = OffsetDateTime::now_utc().date() - Duration::DAY;
+        self.repo.delete_before(yesterday).await?;
+
+        Ok(())
+    }
+}
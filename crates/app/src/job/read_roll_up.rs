@@ -0,0 +1,35 @@
+use crate::job::{Job, JobError};
+use domain::repository::MessageWriter;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+/// Rolls the reads of messages older than the retention period up into per-user read horizons, so
+/// that `read_messages` doesn't grow forever for long-time users. A horizon stops at the oldest
+/// message the user hasn't read, so the reads after it are kept until that message is read.
+pub struct ReadRollUpJob {
+    repo: Arc<dyn MessageWriter>,
+    retention: Duration,
+}
+
+impl ReadRollUpJob {
+    pub fn new(repo: Arc<dyn MessageWriter>, retention: Duration) -> Self {
+        Self { repo, retention }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for ReadRollUpJob {
+    fn name(&self) -> &'static str {
+        "read_roll_up"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        let deleted = self
+            .repo
+            .roll_up_read_messages(OffsetDateTime::now_utc() - self.retention)
+            .await?;
+        tracing::debug!("Rolled up {} reads into read horizons", deleted);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use crate::job::{Job, JobError};
+use tower_sessions::session_store::ExpiredDeletion;
+use tower_sessions_sqlx_store::MySqlStore;
+
+/// Deletes expired sessions from the session store.
+pub struct SessionCleanupJob {
+    store: MySqlStore,
+}
+
+impl SessionCleanupJob {
+    pub fn new(store: MySqlStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for SessionCleanupJob {
+    fn name(&self) -> &'static str {
+        "session_cleanup"
+    }
+
+    async fn run(&self) -> Result<(), JobError> {
+        self.store.delete_expired().await?;
+
+        Ok(())
+    }
+}
@@ -1,78 +1,209 @@
 use crate::{
+    builder::AppBuilder,
+    config::AppConfig,
+    error_reporting::ErrorReporter,
     handler::{
-        AppState,
+        AppState, admin,
         auth::{self},
-        message, stamp, timeline, user,
+        bookmark, channel, federation, message, meta, onboarding, presence, quota, search, stamp,
+        tag, timeline, user,
     },
+    job::{Schedule, session_cleanup::SessionCleanupJob},
+    problem::ApiError,
+    self_test::{SelfTest, Severity},
     session::Backend,
 };
-use axum::Router;
+use axum::{Extension, Router, middleware};
 use axum_login::AuthManagerLayerBuilder;
 use domain::{
-    crawler::MessageCrawler,
-    event::{ClientEvent, ServerEvent, SubscribePayload, UnsubscribePayload},
-    model::Message,
-    service::{TimelineServiceImpl, TraqServiceImpl},
+    event::{
+        ClientEvent, ConnectPayload, MessageDelta, ServerEvent, SubscribePayload,
+        UnsubscribePayload,
+    },
+    model::{Message, OnboardingStep, TrendingWindow},
 };
-use infra::{repository::mariadb, traq_client::TraqClientImpl};
 use oauth2::{AuthUrl, ClientId, ClientSecret, TokenUrl, basic::BasicClient};
 use sqlx::MySqlPool;
+#[cfg(not(unix))]
+use std::future;
 use std::{env, error::Error, sync::Arc, time::Duration};
-use tokio::{net::TcpListener, task};
-use tower_sessions::{SessionManagerLayer, cookie::SameSite, session_store::ExpiredDeletion};
+use tokio::{net::TcpListener, signal};
+use tower_sessions::{Expiry, SessionManagerLayer};
 use tower_sessions_sqlx_store::MySqlStore;
-use tracing_subscriber::fmt;
+use tracing_subscriber::{filter::LevelFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 use utoipa::openapi::{
     ComponentsBuilder, Info, OpenApi, OpenApiBuilder, Server,
     security::{ApiKey, ApiKeyValue, SecurityScheme},
 };
-use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouterExt};
 use utoipa_swagger_ui::SwaggerUi;
 
+mod api_version;
+pub mod builder;
+mod compression;
+mod config;
+mod error_reporting;
+mod etag;
+mod fields;
+mod freshness;
+#[cfg(feature = "graphql")]
+mod graphql;
 mod handler;
+mod job;
+mod problem;
+mod rate_limit;
+mod request_id;
+mod self_test;
 mod session;
 mod socket;
 #[cfg(test)]
 pub mod test_helpers;
 
-const API_ROOT: &str = "/api/v1";
+pub use api_version::ApiVersion;
+pub use config::DEFAULT_SESSION_COOKIE_NAME;
 
-pub fn setup_openapi_routes() -> (Router<AppState>, OpenApi) {
+/// Sets up the routes of `version` and their OpenAPI document, which describes sessions as
+/// carried in the `session_cookie_name` cookie.
+pub fn setup_openapi_routes(
+    version: ApiVersion,
+    session_cookie_name: &str,
+) -> (Router<AppState>, OpenApi) {
     // Include Socket.IO event schemas
     let components = ComponentsBuilder::new()
+        .schema_from::<ApiError>()
         .schema_from::<ClientEvent>()
+        .schema_from::<ConnectPayload>()
         .schema_from::<Message>()
+        .schema_from::<MessageDelta>()
+        .schema_from::<OnboardingStep>()
         .schema_from::<ServerEvent>()
         .schema_from::<SubscribePayload>()
+        .schema_from::<TrendingWindow>()
         .schema_from::<UnsubscribePayload>()
         .security_scheme(
             "cookieAuth",
-            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("id".to_string()))),
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(session_cookie_name))),
         )
         .build();
 
     let openapi = OpenApiBuilder::new()
         .info(Info::new("Twittra", env!("CARGO_PKG_VERSION")))
-        .servers(Some([Server::new(API_ROOT)]))
+        .servers(Some([Server::new(version.root())]))
         .components(Some(components))
         .build();
 
-    OpenApiRouter::with_openapi(openapi)
+    let router = OpenApiRouter::with_openapi(openapi)
+        .routes(utoipa_axum::routes!(admin::get_jobs))
+        .routes(utoipa_axum::routes!(admin::run_job))
+        .routes(utoipa_axum::routes!(admin::get_reports))
+        .routes(utoipa_axum::routes!(admin::resolve_reports))
+        .routes(utoipa_axum::routes!(admin::check_visibility))
+        .routes(utoipa_axum::routes!(
+            admin::publish_announcement,
+            admin::withdraw_announcement
+        ))
+        .routes(utoipa_axum::routes!(
+            admin::get_webhooks,
+            admin::register_webhook
+        ))
+        .routes(utoipa_axum::routes!(admin::delete_webhook))
+        .routes(utoipa_axum::routes!(admin::get_active_users))
+        .routes(utoipa_axum::routes!(admin::get_timeline_freshness))
+        .routes(utoipa_axum::routes!(admin::get_chaos, admin::set_chaos))
         .routes(utoipa_axum::routes!(auth::login))
         .routes(utoipa_axum::routes!(auth::oauth_callback))
+        .routes(utoipa_axum::routes!(auth::login_with_token))
+        .routes(utoipa_axum::routes!(bookmark::get_bookmarks))
+        .routes(utoipa_axum::routes!(
+            bookmark::add_bookmark,
+            bookmark::remove_bookmark
+        ))
+        .routes(utoipa_axum::routes!(channel::get_channels))
+        .routes(utoipa_axum::routes!(channel::get_channel_by_id))
+        .routes(utoipa_axum::routes!(channel::get_channel_messages))
+        .routes(utoipa_axum::routes!(
+            channel::mute_channel,
+            channel::unmute_channel
+        ))
+        .routes(utoipa_axum::routes!(channel::get_channel_score_overrides))
+        .routes(utoipa_axum::routes!(
+            channel::set_channel_score_override,
+            channel::remove_channel_score_override
+        ))
         .routes(utoipa_axum::routes!(
             message::add_message_stamp,
             message::remove_message_stamp
         ))
+        .routes(utoipa_axum::routes!(federation::get_highlights))
+        .routes(utoipa_axum::routes!(federation::get_peer_highlights))
+        .routes(utoipa_axum::routes!(message::toggle_message_stamp))
+        .routes(utoipa_axum::routes!(message::get_stamp_suggestions))
+        .routes(utoipa_axum::routes!(message::hide_message))
         .routes(utoipa_axum::routes!(message::mark_messages_as_read))
+        .routes(utoipa_axum::routes!(message::report_message))
+        .routes(utoipa_axum::routes!(message::get_related_messages))
+        .routes(utoipa_axum::routes!(message::get_message_thread))
+        .routes(utoipa_axum::routes!(meta::get_meta))
+        .routes(utoipa_axum::routes!(meta::get_version))
+        .routes(utoipa_axum::routes!(onboarding::get_onboarding_state))
+        .routes(utoipa_axum::routes!(onboarding::complete_onboarding_step))
+        .routes(utoipa_axum::routes!(onboarding::get_suggested_channels))
+        .routes(utoipa_axum::routes!(onboarding::save_channel_interests))
+        .routes(utoipa_axum::routes!(presence::heartbeat))
+        .routes(utoipa_axum::routes!(search::search_messages))
+        .routes(utoipa_axum::routes!(
+            search::save_search,
+            search::get_saved_searches
+        ))
+        .routes(utoipa_axum::routes!(search::delete_saved_search))
+        .routes(utoipa_axum::routes!(search::get_saved_search_messages))
         .routes(utoipa_axum::routes!(stamp::get_stamp_by_id))
-        .routes(utoipa_axum::routes!(stamp::get_stamps))
+        // Polled by clients, which can skip downloading unchanged responses
+        .routes(
+            utoipa_axum::routes!(stamp::get_stamps).layer(middleware::from_fn(etag::conditional)),
+        )
+        .routes(utoipa_axum::routes!(stamp::get_stamps_by_ids))
         .routes(utoipa_axum::routes!(stamp::get_stamp_image))
-        .routes(utoipa_axum::routes!(timeline::get_timeline))
+        .routes(utoipa_axum::routes!(stamp::get_recent_stamps))
+        .routes(utoipa_axum::routes!(tag::get_tag_messages))
+        .routes(utoipa_axum::routes!(tag::get_trending_tags))
+        .routes(
+            utoipa_axum::routes!(timeline::get_timeline)
+                .layer(middleware::from_fn(etag::conditional)),
+        )
+        .routes(utoipa_axum::routes!(timeline::get_following_timeline))
+        .routes(utoipa_axum::routes!(timeline::get_timeline_updates))
+        .routes(utoipa_axum::routes!(timeline::get_explore))
+        .routes(
+            utoipa_axum::routes!(timeline::get_public_timeline)
+                .layer(middleware::from_fn(etag::conditional)),
+        )
+        .routes(utoipa_axum::routes!(timeline::get_announcement))
+        .routes(utoipa_axum::routes!(timeline::dismiss_announcement))
         .routes(utoipa_axum::routes!(user::get_me))
+        .routes(utoipa_axum::routes!(user::get_my_affinity))
+        .routes(utoipa_axum::routes!(user::get_my_usage))
+        .routes(utoipa_axum::routes!(quota::get_my_quotas))
+        .routes(utoipa_axum::routes!(
+            user::get_privacy_settings,
+            user::set_privacy_settings
+        ))
         .routes(utoipa_axum::routes!(user::get_user_by_id))
+        .routes(utoipa_axum::routes!(user::get_user_messages))
         .routes(utoipa_axum::routes!(user::get_user_icon))
-        .split_for_parts()
+        .routes(utoipa_axum::routes!(user::block_user, user::unblock_user))
+        .routes(utoipa_axum::routes!(user::follow_user, user::unfollow_user))
+        .routes(utoipa_axum::routes!(user::mute_user, user::unmute_user));
+
+    // Every version serves the routes above. Routes that change incompatibly are removed from
+    // there and registered here for each version instead.
+    let router = match version {
+        ApiVersion::V1 | ApiVersion::V2 => router,
+    };
+
+    // Handlers linking to other endpoints link to the ones of the version they were called by
+    let (router, openapi) = router.split_for_parts();
+    (router.layer(Extension(version)), openapi)
 }
 
 pub async fn serve() -> Result<(), Box<dyn Error>> {
@@ -82,29 +213,76 @@ pub async fn serve() -> Result<(), Box<dyn Error>> {
         dotenvy::dotenv().ok();
     }
 
-    fmt::init();
+    let config_path = config::config_path_from_args(env::args());
+    let config = AppConfig::load(config_path.as_deref())?;
+    let error_reporter = config
+        .error_reporting
+        .webhook_url
+        .map(|url| ErrorReporter::start(url, config.signing.clone()));
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(fmt::layer())
+        .with(error_reporter.as_ref().map(ErrorReporter::layer))
+        .init();
+    if let Some(error_reporter) = &error_reporter {
+        error_reporter.install_panic_hook();
+    }
 
-    let listener = TcpListener::bind("0.0.0.0:8080").await?;
-    let database_url = env::var("DATABASE_URL")?;
-    let pool = MySqlPool::connect(&database_url).await?;
+    match &config.signing {
+        Some(keys) => tracing::info!(
+            "Signing with key {} ({} keys accepted)",
+            keys.active_key_id(),
+            keys.len()
+        ),
+        None => {
+            tracing::info!("No signing keys configured, features that need signatures are disabled")
+        }
+    }
+
+    let mut self_test = SelfTest::new(config.startup.strict);
+    let listener = TcpListener::bind(&config.listen_address).await?;
+    let pool = self_test.require("database", MySqlPool::connect(&config.database_url).await)?;
     let session_store = MySqlStore::new(pool.clone())
-        .with_schema_name(env::var("SESSION_TABLE_SCHEMA")?)?
-        .with_table_name(env::var("SESSION_TABLE_NAME")?)?;
+        .with_schema_name(&config.session.table_schema)?
+        .with_table_name(&config.session.table_name)?;
+
+    self_test.require("session_store", session_store.migrate().await)?;
 
-    session_store.migrate().await?;
+    let cookie = &config.session.cookie;
+    let mut session_layer = SessionManagerLayer::new(session_store.clone())
+        .with_name(cookie.name.clone())
+        .with_secure(cookie.secure)
+        .with_same_site(cookie.same_site);
+    if let Some(domain) = &cookie.domain {
+        session_layer = session_layer.with_domain(domain.clone());
+    }
+    if let Some(max_age_secs) = cookie.max_age_secs {
+        session_layer =
+            session_layer.with_expiry(Expiry::OnInactivity(time::Duration::seconds(max_age_secs)));
+    }
+    let app = AppBuilder::connect(&config, pool, &mut self_test).await?;
 
-    task::spawn(
-        session_store
-            .clone()
-            .continuously_delete_expired(Duration::from_mins(10)),
+    let (socket_layer, io) = socket::create_socket_layer();
+    self_test.check(
+        "socket",
+        Severity::Hard,
+        io.of("/").ok_or("the default namespace is not registered"),
     );
+    self_test.finish()?;
 
-    let session_layer = SessionManagerLayer::new(session_store).with_same_site(SameSite::Lax);
-    let client_id = env::var("TRAQ_CLIENT_ID").map(ClientId::new)?;
-    let client_secret = env::var("TRAQ_CLIENT_SECRET").map(ClientSecret::new)?;
-    let traq_api_base_url = env::var("TRAQ_API_BASE_URL")?;
-    let client = BasicClient::new(client_id)
-        .set_client_secret(client_secret)
+    let jobs = app
+        .scheduler(Arc::new(socket::SocketNotifier::new(io)))
+        .register(
+            SessionCleanupJob::new(session_store),
+            Schedule::every(Duration::from_mins(10)),
+        )
+        .start();
+    let app_state = app.state(jobs.handle());
+
+    let traq_api_base_url = config.traq.api_base_url;
+    let client = BasicClient::new(ClientId::new(config.traq.client_id))
+        .set_client_secret(ClientSecret::new(config.traq.client_secret))
         .set_auth_uri(AuthUrl::new(format!(
             "{}/oauth2/authorize",
             traq_api_base_url
@@ -113,29 +291,85 @@ pub async fn serve() -> Result<(), Box<dyn Error>> {
             "{}/oauth2/token",
             traq_api_base_url
         ))?);
-    let repository = mariadb::new_repository(pool).await?;
-    let traq_client = TraqClientImpl::new(traq_api_base_url.clone());
-
-    let (socket_layer, io) = socket::create_socket_layer();
-    let notifier = Arc::new(socket::SocketNotifier::new(io));
-    let crawler = MessageCrawler::new(Arc::new(traq_client.clone()), repository.clone(), notifier);
-
-    task::spawn(async move {
-        crawler.run().await;
-    });
-
-    let backend = Backend::new(client, traq_api_base_url, repository.user.clone());
-    let traq_service = TraqServiceImpl::new(repository.clone(), Arc::new(traq_client));
-    let timeline_service = TimelineServiceImpl::new(repository);
-    let app_state = AppState::new(Arc::new(traq_service), Arc::new(timeline_service));
+    let backend = Backend::new(client, traq_api_base_url, app.repository().user.clone());
     let auth_layer = AuthManagerLayerBuilder::new(backend, session_layer).build();
-    let (router, openapi) = setup_openapi_routes();
-    let router = axum::Router::new()
-        .nest(API_ROOT, router.layer(auth_layer))
-        .merge(SwaggerUi::new("/docs/swagger-ui").url("/docs/openapi.json", openapi))
+    let mut router = axum::Router::new();
+    let mut swagger_ui = SwaggerUi::new("/docs/swagger-ui");
+    for version in ApiVersion::ALL {
+        let (api, openapi) = setup_openapi_routes(version, &config.session.cookie.name);
+        let mut api = api
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                quota::enforce_quotas,
+            ))
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                onboarding::require_onboarding,
+            ))
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                rate_limit::limit,
+            ))
+            .layer(middleware::from_fn(session::require_login))
+            .layer(auth_layer.clone())
+            .layer(middleware::from_fn(problem::describe_bare_errors))
+            .layer(middleware::from_fn(request_id::propagate));
+        if let Some(deprecation) = version.deprecation(&config.api) {
+            api = api.layer(middleware::from_fn_with_state(
+                Arc::new(deprecation),
+                api_version::deprecate,
+            ));
+        }
+
+        router = router.nest(version.root(), api);
+        swagger_ui = swagger_ui.url(version.openapi_path(), openapi);
+    }
+    #[cfg(feature = "graphql")]
+    {
+        router = router.route(
+            graphql::PATH,
+            graphql::route(&app_state)
+                .layer(auth_layer.clone())
+                .layer(middleware::from_fn(problem::describe_bare_errors))
+                .layer(middleware::from_fn(request_id::propagate)),
+        );
+    }
+    let router = router
+        .merge(swagger_ui)
+        .layer(compression::layer(config.compression.min_size_bytes))
         .layer(socket_layer);
 
-    axum::serve(listener, router.with_state(app_state)).await?;
+    axum::serve(listener, router.with_state(app_state))
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    tracing::info!("Server stopped, waiting for background jobs to finish");
+    jobs.shutdown().await;
 
     Ok(())
 }
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {:?}", e);
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => tracing::error!("Failed to listen for SIGTERM: {:?}", e),
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
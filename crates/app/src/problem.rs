@@ -0,0 +1,292 @@
+//! Error responses in the problem details format of RFC 7807 (`application/problem+json`).
+//!
+//! Handlers return [`AppError`] for failed service calls, so that domain errors are answered the
+//! same everywhere. Errors answered with a bare status code are described by
+//! [`describe_bare_errors`], so that every error carries the request ID clients can report.
+
+use crate::request_id;
+use axum::{
+    Json,
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use domain::error::{DomainError, TraqClientError};
+use http::{HeaderValue, StatusCode, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// The body of every error response.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    /// A URI identifying the kind of problem. `about:blank` means the status code says it all.
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// The reason phrase of the status code.
+    pub title: String,
+    pub status: u16,
+    /// What went wrong with this request, if it was the client's fault.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// The ID in the `X-Request-Id` header, for finding the server's logs of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: None,
+            request_id: request_id::current(),
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// The status code a domain error is answered with.
+pub fn status_of(error: &DomainError) -> StatusCode {
+    match error {
+        DomainError::NoMessageForId(_)
+        | DomainError::NoChannelForId(_)
+        | DomainError::NoSavedSearchForId(_)
+        | DomainError::NoAnnouncement
+        | DomainError::NoWebhookForId(_)
+        | DomainError::HighlightsNotPublished
+        | DomainError::PublicTimelineDisabled => StatusCode::NOT_FOUND,
+        DomainError::TooManyStampIds(_)
+        | DomainError::CannotMuteSelf
+        | DomainError::CannotBlockSelf
+        | DomainError::CannotFollowSelf
+        | DomainError::TooManyChannelInterests(_)
+        | DomainError::InvalidChannelScoreMultiplier
+        | DomainError::TooManyChannelScoreOverrides(_)
+        | DomainError::InvalidSavedSearch(_)
+        | DomainError::TooManySavedSearches(_)
+        | DomainError::InvalidAnnouncement(_)
+        | DomainError::InvalidTag
+        | DomainError::InvalidCursor
+        | DomainError::InvalidWebhook(_)
+        | DomainError::TooManyWebhooks(_)
+        | DomainError::InvalidFaults(_) => StatusCode::BAD_REQUEST,
+        // The user's traQ token expired or was revoked, so they have to sign in again
+        DomainError::NoTokenForUser(_) => StatusCode::UNAUTHORIZED,
+        // Nobody has signed in with a valid token to fetch from traQ with
+        DomainError::NoTokenForUserFetch
+        | DomainError::NoTokenForUserIcon
+        | DomainError::NoTokenForStampFetch
+        | DomainError::NoTokenForStampImage
+        | DomainError::NoTokenForStampsList => StatusCode::CONFLICT,
+        DomainError::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+        DomainError::TraqClient(TraqClientError::ApiError { status, .. })
+            if *status == StatusCode::NOT_FOUND =>
+        {
+            StatusCode::NOT_FOUND
+        }
+        DomainError::TraqClient(_) => StatusCode::BAD_GATEWAY,
+        DomainError::Search(_) => StatusCode::SERVICE_UNAVAILABLE,
+        DomainError::Repository(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl From<DomainError> for ApiError {
+    /// Describes errors caused by the client. Server errors are not described, since their
+    /// messages may reveal internals; they are found in the logs by the request ID instead.
+    fn from(error: DomainError) -> Self {
+        let status = status_of(&error);
+        let problem = Self::new(status);
+        if status.is_client_error() {
+            problem.with_detail(error.to_string())
+        } else {
+            problem
+        }
+    }
+}
+
+/// A failed service call, answered with the status of [`status_of`] and logged if the server is
+/// at fault.
+#[derive(Debug)]
+pub struct AppError(pub DomainError);
+
+impl From<DomainError> for AppError {
+    fn from(error: DomainError) -> Self {
+        Self(error)
+    }
+}
+
+impl AppError {
+    /// Logs the error if the server is at fault.
+    pub fn log(&self) {
+        let status = status_of(&self.0);
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            tracing::warn!("{:?}", self.0);
+        } else if status.is_server_error() {
+            tracing::error!("{:?}", self.0);
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        self.log();
+        ApiError::from(self.0).into_response()
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status(), Json(self)).into_response();
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        response
+    }
+}
+
+/// Middleware giving error responses without a body a problem details body. Their headers, such
+/// as `Retry-After`, are kept.
+pub async fn describe_bare_errors(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error())
+        || response.headers().contains_key(CONTENT_TYPE)
+    {
+        return response;
+    }
+
+    let (parts, _) = response.into_parts();
+    let mut problem = ApiError::new(status).into_response();
+    for (name, value) in &parts.headers {
+        if !problem.headers().contains_key(name) {
+            problem.headers_mut().append(name, value.clone());
+        }
+    }
+    problem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_id::{REQUEST_ID_HEADER, propagate};
+    use axum::{
+        Router,
+        body::{self, Body},
+        middleware,
+        routing::get,
+    };
+    use http::header::RETRY_AFTER;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    async fn problem_of(res: Response) -> ApiError {
+        assert_eq!(res.headers()[CONTENT_TYPE], PROBLEM_JSON);
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn domain_errors_are_described_to_clients_at_fault() {
+        let app = Router::new()
+            .route(
+                "/client",
+                get(|| async { ApiError::from(DomainError::InvalidCursor) }),
+            )
+            .route(
+                "/server",
+                get(|| async {
+                    ApiError::from(DomainError::TraqClient(TraqClientError::HttpRequest(
+                        "10.0.0.1".to_string(),
+                    )))
+                }),
+            )
+            .layer(middleware::from_fn(propagate));
+
+        let req = Request::builder()
+            .uri("/client")
+            .header(REQUEST_ID_HEADER, "abc")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            problem_of(res).await,
+            ApiError {
+                problem_type: "about:blank".to_string(),
+                title: "Bad Request".to_string(),
+                status: 400,
+                detail: Some("invalid cursor".to_string()),
+                request_id: Some("abc".to_string()),
+            }
+        );
+
+        let req = Request::builder()
+            .uri("/server")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+        let problem = problem_of(res).await;
+        assert_eq!(problem.detail, None);
+        assert!(problem.request_id.is_some());
+    }
+
+    #[test]
+    fn app_errors_are_answered_by_cause() {
+        let cases = [
+            (
+                DomainError::NoTokenForUser(Uuid::nil()),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (DomainError::NoTokenForUserIcon, StatusCode::CONFLICT),
+            (
+                DomainError::TraqClient(TraqClientError::ApiError {
+                    status: StatusCode::NOT_FOUND,
+                    message: "not found".to_string(),
+                }),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                DomainError::TraqClient(TraqClientError::ApiError {
+                    status: StatusCode::FORBIDDEN,
+                    message: "forbidden".to_string(),
+                }),
+                StatusCode::BAD_GATEWAY,
+            ),
+        ];
+        for (error, status) in cases {
+            assert_eq!(AppError::from(error).into_response().status(), status);
+        }
+    }
+
+    #[tokio::test]
+    async fn bare_errors_are_described_keeping_their_headers() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "30")]) }),
+            )
+            .layer(middleware::from_fn(describe_bare_errors));
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[RETRY_AFTER], "30");
+        let problem = problem_of(res).await;
+        assert_eq!(problem.title, "Too Many Requests");
+        assert_eq!(problem.status, 429);
+    }
+}
@@ -0,0 +1,244 @@
+//! Per-user limits on API calls, and the record of recent calls that users can see at
+//! `/users/me/usage`.
+
+use crate::{handler::AppState, session::AuthSession};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{StatusCode, header::RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+use time::{Duration, OffsetDateTime};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// How many minutes of calls are kept for `/users/me/usage`.
+pub const USAGE_WINDOW_MINUTES: usize = 60;
+
+/// Not limited, so that throttled users can still see why.
+const UNLIMITED_PATHS: &[&str] = &["/users/me/usage"];
+
+/// The calls a user made in one minute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MinuteUsage {
+    /// The start of the minute.
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    /// The calls that were served.
+    pub calls: u32,
+    /// The calls rejected for exceeding the limit.
+    pub throttled: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiUsage {
+    /// The calls served per minute, or null if calls are not limited.
+    pub limit_per_minute: Option<u32>,
+    /// The calls left in the current minute, or null if calls are not limited.
+    pub remaining: Option<u32>,
+    /// When the current minute ends and the remaining calls are reset.
+    #[serde(with = "time::serde::rfc3339")]
+    pub resets_at: OffsetDateTime,
+    pub total_calls: u32,
+    pub total_throttled: u32,
+    /// The minutes with calls in the last hour, oldest first.
+    pub minutes: Vec<MinuteUsage>,
+}
+
+/// Counts the API calls of each user per minute, rejecting those beyond the limit.
+///
+/// Counts are kept in memory, so they are per process and lost on restart, which is fine for
+/// limits counted per minute. Users are those of one traQ instance, so they are not evicted.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limit_per_minute: Option<u32>,
+    usage: Mutex<HashMap<Uuid, VecDeque<MinuteUsage>>>,
+}
+
+fn start_of_minute(time: OffsetDateTime) -> OffsetDateTime {
+    time.replace_second(0)
+        .and_then(|time| time.replace_nanosecond(0))
+        .expect("0 is a valid second and nanosecond")
+}
+
+fn prune(minutes: &mut VecDeque<MinuteUsage>, minute: OffsetDateTime) {
+    let window_start = minute - Duration::minutes(USAGE_WINDOW_MINUTES as i64 - 1);
+    while minutes
+        .front()
+        .is_some_and(|usage| usage.started_at < window_start)
+    {
+        minutes.pop_front();
+    }
+}
+
+impl RateLimiter {
+    /// Limits each user to `limit_per_minute` calls per minute, or only counts calls if `None`.
+    pub fn new(limit_per_minute: Option<u32>) -> Self {
+        Self {
+            limit_per_minute,
+            usage: Mutex::default(),
+        }
+    }
+
+    /// Counts a call by the user at `now`. Returns how long until the user may call again if the
+    /// call exceeds the limit.
+    pub fn check(&self, user_id: &Uuid, now: OffsetDateTime) -> Result<(), Duration> {
+        let minute = start_of_minute(now);
+        let mut usage = self.usage.lock().unwrap();
+        let minutes = usage.entry(*user_id).or_default();
+        prune(minutes, minute);
+        if minutes
+            .back()
+            .is_none_or(|usage| usage.started_at != minute)
+        {
+            minutes.push_back(MinuteUsage {
+                started_at: minute,
+                calls: 0,
+                throttled: 0,
+            });
+        }
+        let current = minutes
+            .back_mut()
+            .expect("the current minute was just added");
+
+        if self
+            .limit_per_minute
+            .is_some_and(|limit| current.calls >= limit)
+        {
+            current.throttled += 1;
+            return Err(minute + Duration::MINUTE - now);
+        }
+        current.calls += 1;
+        Ok(())
+    }
+
+    /// Summarizes the calls of the user in the last hour as of `now`.
+    pub fn usage(&self, user_id: &Uuid, now: OffsetDateTime) -> ApiUsage {
+        let minute = start_of_minute(now);
+        let minutes: Vec<MinuteUsage> = match self.usage.lock().unwrap().get_mut(user_id) {
+            Some(minutes) => {
+                prune(minutes, minute);
+                minutes.iter().copied().collect()
+            }
+            None => vec![],
+        };
+        let current_calls = minutes
+            .last()
+            .filter(|usage| usage.started_at == minute)
+            .map_or(0, |usage| usage.calls);
+
+        ApiUsage {
+            limit_per_minute: self.limit_per_minute,
+            remaining: self
+                .limit_per_minute
+                .map(|limit| limit.saturating_sub(current_calls)),
+            resets_at: minute + Duration::MINUTE,
+            total_calls: minutes.iter().map(|usage| usage.calls).sum(),
+            total_throttled: minutes.iter().map(|usage| usage.throttled).sum(),
+            minutes,
+        }
+    }
+}
+
+/// Middleware counting the calls of signed-in users, responding with `429 Too Many Requests` and
+/// `Retry-After` to calls beyond the limit.
+pub async fn limit(
+    State(state): State<AppState>,
+    auth_session: AuthSession,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(user) = auth_session.user else {
+        return next.run(request).await;
+    };
+    if UNLIMITED_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    match state
+        .rate_limiter
+        .check(&user.id, OffsetDateTime::now_utc())
+    {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            // Rounded up, so that retrying after it succeeds
+            let seconds = (retry_after.whole_milliseconds() + 999) / 1000;
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, seconds.to_string())],
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, uuid::UUIDv4};
+
+    /// `seconds` after a minute started.
+    fn at(seconds: i64) -> OffsetDateTime {
+        // 2025-01-01T12:00:00Z
+        OffsetDateTime::from_unix_timestamp(1_735_732_800 + seconds).unwrap()
+    }
+
+    #[test]
+    fn calls_beyond_the_limit_are_rejected_until_the_next_minute() {
+        let limiter = RateLimiter::new(Some(2));
+        let user_id: Uuid = UUIDv4.fake();
+        let now = at(15);
+
+        assert_eq!(limiter.check(&user_id, now), Ok(()));
+        assert_eq!(limiter.check(&user_id, now), Ok(()));
+        assert_eq!(limiter.check(&user_id, now), Err(Duration::seconds(45)));
+        // Other users have their own limits
+        assert_eq!(limiter.check(&UUIDv4.fake(), now), Ok(()));
+        assert_eq!(limiter.check(&user_id, now + Duration::seconds(45)), Ok(()));
+
+        let usage = limiter.usage(&user_id, now + Duration::seconds(50));
+        assert_eq!(usage.limit_per_minute, Some(2));
+        assert_eq!(usage.remaining, Some(1));
+        assert_eq!(usage.resets_at, at(120));
+        assert_eq!((usage.total_calls, usage.total_throttled), (3, 1));
+        assert_eq!(
+            usage.minutes,
+            vec![
+                MinuteUsage {
+                    started_at: at(0),
+                    calls: 2,
+                    throttled: 1,
+                },
+                MinuteUsage {
+                    started_at: at(60),
+                    calls: 1,
+                    throttled: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn calls_older_than_the_window_are_forgotten() {
+        let limiter = RateLimiter::new(None);
+        let user_id: Uuid = UUIDv4.fake();
+        let now = at(0);
+
+        for _ in 0..1000 {
+            assert_eq!(limiter.check(&user_id, now), Ok(()));
+        }
+        let usage = limiter.usage(&user_id, now);
+        assert_eq!((usage.total_calls, usage.remaining), (1000, None));
+
+        let usage = limiter.usage(&user_id, now + Duration::HOUR);
+        assert_eq!(usage.total_calls, 0);
+        assert!(usage.minutes.is_empty());
+    }
+}
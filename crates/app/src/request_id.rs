@@ -0,0 +1,108 @@
+//! IDs correlating the logs of one request, in the `X-Request-Id` header.
+//!
+//! An ID sent by the client (or a reverse proxy in front of the server) is kept, so that its logs
+//! can be matched with the server's. Otherwise one is generated. Either way it is recorded on the
+//! span every handler runs in, returned on every response and included in error bodies.
+
+use axum::{extract::Request, middleware::Next, response::Response};
+use http::{HeaderName, HeaderValue};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// IDs sent by clients longer than this are replaced, so that they can't flood the logs.
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// Generates a random ID of 32 hex digits. Also used for job runs, which have no request.
+pub fn generate() -> String {
+    format!("{:016x}{:016x}", fastrand::u64(..), fastrand::u64(..))
+}
+
+/// Returns the ID of the request being handled, if called while handling one.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+fn from_client(value: &HeaderValue) -> Option<&str> {
+    value
+        .to_str()
+        .ok()
+        .filter(|id| !id.is_empty() && id.len() <= MAX_LEN)
+        .filter(|id| id.bytes().all(|b| b.is_ascii_graphic()))
+}
+
+/// Middleware running the request in a span with its ID, and returning the ID in the response.
+pub async fn propagate(request: Request, next: Next) -> Response {
+    let id = match request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(from_client)
+    {
+        Some(id) => id.to_string(),
+        None => generate(),
+    };
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = request.uri().path(),
+    );
+
+    let mut response = CURRENT
+        .scope(id.clone(), next.run(request).instrument(span))
+        .await;
+    response.headers_mut().insert(
+        REQUEST_ID_HEADER,
+        HeaderValue::from_str(&id).expect("the ID consists of visible ASCII characters"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, middleware, routing::get};
+    use http::StatusCode;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .layer(middleware::from_fn(propagate))
+    }
+
+    #[tokio::test]
+    async fn ids_from_clients_are_kept() {
+        let req = Request::builder()
+            .uri("/")
+            .header(REQUEST_ID_HEADER, "proxy-1234")
+            .body(Body::empty())
+            .unwrap();
+        let res = app().oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(res.headers()[REQUEST_ID_HEADER], "proxy-1234");
+    }
+
+    #[tokio::test]
+    async fn invalid_or_missing_ids_are_generated() {
+        for id in [None, Some("with space"), Some(&*"a".repeat(MAX_LEN + 1))] {
+            let mut req = Request::builder().uri("/");
+            if let Some(id) = id {
+                req = req.header(REQUEST_ID_HEADER, id);
+            }
+            let res = app()
+                .oneshot(req.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            let generated = res.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+            assert_eq!(generated.len(), 32);
+            assert!(generated.bytes().all(|b| b.is_ascii_hexdigit()));
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Checks run on startup, so that misconfigurations are reported before serving requests
+//! instead of surfacing later as failing requests or jobs.
+
+use std::fmt::Display;
+
+/// How a failed check affects startup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The server does not work correctly. Startup is refused in strict mode.
+    Hard,
+    /// Some features may not work until the situation resolves itself.
+    Soft,
+}
+
+#[derive(Debug)]
+struct CheckResult {
+    name: &'static str,
+    severity: Severity,
+    error: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("startup self-test failed: {}", .0.join(", "))]
+pub struct SelfTestError(Vec<&'static str>);
+
+/// Records the outcome of each startup check and logs a summary at the end.
+#[derive(Debug)]
+pub struct SelfTest {
+    strict: bool,
+    results: Vec<CheckResult>,
+}
+
+impl SelfTest {
+    pub fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            results: vec![],
+        }
+    }
+
+    /// Records a check the server cannot run without, failing regardless of strict mode.
+    pub fn require<T, E: Display>(
+        &mut self,
+        name: &'static str,
+        result: Result<T, E>,
+    ) -> Result<T, SelfTestError> {
+        match result {
+            Ok(value) => {
+                self.record(name, Severity::Hard, None);
+                Ok(value)
+            }
+            Err(e) => {
+                self.record(name, Severity::Hard, Some(e.to_string()));
+                self.log_summary();
+                Err(SelfTestError(vec![name]))
+            }
+        }
+    }
+
+    /// Records a check. The value is returned if it passed.
+    pub fn check<T, E: Display>(
+        &mut self,
+        name: &'static str,
+        severity: Severity,
+        result: Result<T, E>,
+    ) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.record(name, severity, None);
+                Some(value)
+            }
+            Err(e) => {
+                self.record(name, severity, Some(e.to_string()));
+                None
+            }
+        }
+    }
+
+    /// Logs the summary. In strict mode, an error is returned if any hard check failed.
+    pub fn finish(self) -> Result<(), SelfTestError> {
+        self.log_summary();
+
+        let hard_failures = self.failures(Severity::Hard);
+        if self.strict && !hard_failures.is_empty() {
+            return Err(SelfTestError(hard_failures));
+        }
+
+        Ok(())
+    }
+
+    fn record(&mut self, name: &'static str, severity: Severity, error: Option<String>) {
+        match (&error, severity) {
+            (None, _) => tracing::info!(check = name, "Self-test passed"),
+            (Some(e), Severity::Hard) => tracing::error!(check = name, "Self-test failed: {e}"),
+            (Some(e), Severity::Soft) => tracing::warn!(check = name, "Self-test failed: {e}"),
+        }
+
+        self.results.push(CheckResult {
+            name,
+            severity,
+            error,
+        });
+    }
+
+    fn failures(&self, severity: Severity) -> Vec<&'static str> {
+        self.results
+            .iter()
+            .filter(|r| r.severity == severity && r.error.is_some())
+            .map(|r| r.name)
+            .collect()
+    }
+
+    fn log_summary(&self) {
+        let passed = self.results.iter().filter(|r| r.error.is_none()).count();
+        let hard_failures = self.failures(Severity::Hard);
+        let soft_failures = self.failures(Severity::Soft);
+
+        if hard_failures.is_empty() && soft_failures.is_empty() {
+            tracing::info!("Self-test summary: all {passed} checks passed");
+        } else {
+            tracing::warn!(
+                "Self-test summary: {passed} passed, failed: [{}], warnings: [{}]",
+                hard_failures.join(", "),
+                soft_failures.join(", ")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hard_failures_are_fatal_only_in_strict_mode() {
+        let mut self_test = SelfTest::new(false);
+        self_test.check::<(), _>("traq", Severity::Hard, Err("connection refused"));
+        assert!(self_test.finish().is_ok());
+
+        let mut self_test = SelfTest::new(true);
+        assert_eq!(
+            self_test.check("database", Severity::Hard, Ok::<_, String>(1)),
+            Some(1)
+        );
+        self_test.check::<(), _>("token", Severity::Soft, Err("no valid token"));
+        self_test.check::<(), _>("traq", Severity::Hard, Err("connection refused"));
+        let err = self_test.finish().unwrap_err();
+        assert_eq!(err.0, vec!["traq"]);
+    }
+
+    #[test]
+    fn required_checks_fail_immediately() {
+        let mut self_test = SelfTest::new(false);
+
+        assert_eq!(
+            self_test.require("database", Ok::<_, String>(1)).unwrap(),
+            1
+        );
+        let err = self_test
+            .require::<(), _>("migrations", Err("checksum mismatch"))
+            .unwrap_err();
+        assert_eq!(err.0, vec!["migrations"]);
+    }
+}
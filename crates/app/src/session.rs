@@ -1,5 +1,11 @@
+use axum::{
+    extract::{FromRequestParts, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
 use axum_login::{AuthUser, AuthnBackend};
 use domain::{error::RepositoryError, repository::UserRepository};
+use http::{StatusCode, request::Parts};
 use oauth2::{
     AsyncHttpClient, AuthorizationCode, CsrfToken, EndpointNotSet, EndpointSet, TokenResponse,
     basic::{BasicClient, BasicRequestTokenError},
@@ -74,6 +80,16 @@ impl Backend {
     }
 }
 
+/// How a user proves who they are when signing in.
+#[derive(Clone)]
+pub enum Credentials {
+    /// The code traQ redirects back with in the OAuth2 flow.
+    AuthorizationCode(String),
+    /// A traQ access token obtained elsewhere, e.g. by command line tools that can't follow the
+    /// OAuth2 flow in a browser. It only authenticates the session and is not stored.
+    AccessToken(String),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum BackendError {
     #[error(transparent)]
@@ -86,37 +102,54 @@ pub enum BackendError {
 
 impl AuthnBackend for Backend {
     type User = UserSession;
-    type Credentials = String;
+    type Credentials = Credentials;
     type Error = BackendError;
 
     async fn authenticate(
         &self,
-        code: Self::Credentials,
+        credentials: Self::Credentials,
     ) -> result::Result<Option<Self::User>, Self::Error> {
-        let token_res = self
-            .oauth_client
-            .exchange_code(AuthorizationCode::new(code))
-            .request_async(&self.http_client)
-            .await
-            .map_err(Self::Error::Oauth2)?;
+        // Only tokens granted to Twittra are kept for crawling, since tokens sent by clients may
+        // have a narrower scope or expire sooner
+        let (access_token, keep_token) = match credentials {
+            Credentials::AuthorizationCode(code) => {
+                let token = self
+                    .oauth_client
+                    .exchange_code(AuthorizationCode::new(code))
+                    .request_async(&self.http_client)
+                    .await
+                    .map_err(Self::Error::Oauth2)?
+                    .access_token()
+                    .secret()
+                    .to_string();
+                (token, true)
+            }
+            Credentials::AccessToken(token) => (token, false),
+        };
         let config = Configuration {
             base_path: self.traq_base_url.clone(),
-            oauth_access_token: Some(token_res.access_token().secret().to_string()),
+            oauth_access_token: Some(access_token.clone()),
             ..Default::default()
         };
-        let user = me_api::get_me(&config)
-            .await
-            .map_err(Self::Error::Traq)?
-            .into();
+        let user = match me_api::get_me(&config).await {
+            Ok(me) => me.into(),
+            // Tokens sent by clients may be invalid or revoked
+            Err(apis::Error::ResponseError(e)) if e.status == StatusCode::UNAUTHORIZED => {
+                return Ok(None);
+            }
+            Err(e) => return Err(Self::Error::Traq(e)),
+        };
 
         self.user_repository
             .save(&user)
             .await
             .map_err(Self::Error::UserRepository)?;
-        self.user_repository
-            .save_token(&user.id, token_res.access_token().secret())
-            .await
-            .map_err(Self::Error::UserRepository)?;
+        if keep_token {
+            self.user_repository
+                .save_token(&user.id, &access_token)
+                .await
+                .map_err(Self::Error::UserRepository)?;
+        }
 
         Ok(Some(UserSession { id: user.id }))
     }
@@ -130,3 +163,90 @@ impl AuthnBackend for Backend {
 }
 
 pub type AuthSession = axum_login::AuthSession<Backend>;
+
+/// The signed-in user, for handlers that need their ID. Requests without one are answered with 401
+/// before the handler runs, even on the public routes [`require_login`] lets through.
+#[derive(Clone, Debug)]
+pub struct CurrentUser(pub UserSession);
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> result::Result<Self, Response> {
+        let auth_session = AuthSession::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        auth_session
+            .user
+            .map(Self)
+            .ok_or_else(|| StatusCode::UNAUTHORIZED.into_response())
+    }
+}
+
+/// Whether the endpoint can be used without signing in: signing in itself, the instance metadata
+/// polled by health checks, and the content published for anonymous visitors and peer instances.
+fn is_public(path: &str) -> bool {
+    path == "/meta"
+        || path == "/version"
+        || path == "/federation/highlights"
+        || path == "/public/timeline"
+        || path.starts_with("/auth/")
+}
+
+/// Responds with 401 Unauthorized to requests without a signed-in user, except on public routes.
+///
+/// This guards every route by default, so that a new handler can't be exposed by forgetting to
+/// take [`CurrentUser`].
+pub async fn require_login(auth_session: AuthSession, request: Request, next: Next) -> Response {
+    if auth_session.user.is_none() && !is_public(request.uri().path()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_helpers::TestAppBuilder;
+    use axum::{body::Body, http::Request};
+    use http::{StatusCode, header};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_private_routes_require_login() {
+        // The mocked services have no expectations, so reaching a handler would panic
+        let app = TestAppBuilder::new().build();
+
+        for uri in ["/api/v1/timeline", "/api/v1/stamps", "/api/v1/admin/jobs"] {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_public_routes_do_not_require_login() {
+        let app = TestAppBuilder::new().build();
+
+        let req = Request::builder()
+            .uri("/api/v1/version")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_token_login_is_disabled_by_default() {
+        let app = TestAppBuilder::new().build();
+
+        let req = Request::builder()
+            .uri("/api/v1/auth/token")
+            .method("POST")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"accessToken":"token"}"#))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,5 +1,8 @@
 use domain::{
-    event::{ServerEvent, SocketEvent, SubscribePayload, UnsubscribePayload},
+    event::{
+        ConnectPayload, DEFAULT_PROTOCOL_VERSION, DELTA_PROTOCOL_VERSION, MessageDelta,
+        ServerEvent, SocketEvent, SubscribePayload, UnsubscribePayload,
+    },
     model::Message,
     notifier::MessageNotifier,
 };
@@ -10,6 +13,7 @@ use socketioxide::{
     layer::SocketIoLayer,
 };
 use std::{future::Future, sync::Arc};
+use uuid::Uuid;
 
 /// Extension trait for SocketRef that provides type-safe event handler registration
 trait SocketRefExt {
@@ -50,27 +54,47 @@ pub fn create_socket_layer() -> (SocketIoLayer, SocketIo) {
     let (socket_layer, io) = SocketIo::new_layer();
 
     // Register default namespace handler with subscribe/unsubscribe handlers
-    io.ns("/", |socket: SocketRef| async move {
+    io.ns("/", |socket: SocketRef, Data::<Value>(auth)| async move {
+        // Clients that send no auth data are treated as the default protocol version
+        let protocol_version = serde_json::from_value::<ConnectPayload>(auth)
+            .unwrap_or_default()
+            .negotiated_protocol_version();
+        tracing::debug!(socket_id = %socket.id, protocol_version, "Client connected");
+
         socket
-            .register_handler(handle_subscribe)
-            .register_handler(handle_unsubscribe);
+            .register_handler(move |socket, payload| {
+                handle_subscribe(socket, payload, protocol_version)
+            })
+            .register_handler(move |socket, payload| {
+                handle_unsubscribe(socket, payload, protocol_version)
+            });
     });
 
     (socket_layer, io)
 }
 
+/// Returns the room for updates of a message.
+/// Clients are split by protocol version so that each receives the events it understands.
+fn message_room(message_id: &Uuid, protocol_version: u32) -> String {
+    if protocol_version >= DELTA_PROTOCOL_VERSION {
+        format!("message:{}:delta", message_id)
+    } else {
+        format!("message:{}", message_id)
+    }
+}
+
 #[tracing::instrument(skip(socket, payload), fields(socket_id = %socket.id))]
-async fn handle_subscribe(socket: SocketRef, payload: SubscribePayload) {
+async fn handle_subscribe(socket: SocketRef, payload: SubscribePayload, protocol_version: u32) {
     for message_id in &payload.message_ids {
-        socket.join(format!("message:{}", message_id));
+        socket.join(message_room(message_id, protocol_version));
     }
     tracing::info!("Client subscribed to message updates");
 }
 
 #[tracing::instrument(skip(socket, payload), fields(socket_id = %socket.id))]
-async fn handle_unsubscribe(socket: SocketRef, payload: UnsubscribePayload) {
+async fn handle_unsubscribe(socket: SocketRef, payload: UnsubscribePayload, protocol_version: u32) {
     for message_id in &payload.message_ids {
-        socket.leave(format!("message:{}", message_id));
+        socket.leave(message_room(message_id, protocol_version));
     }
     tracing::info!("Client unsubscribed from message updates");
 }
@@ -85,34 +109,40 @@ impl SocketNotifier {
     pub fn new(io: SocketIo) -> Self {
         Self { io }
     }
-}
 
-#[async_trait::async_trait]
-impl MessageNotifier for SocketNotifier {
-    #[tracing::instrument(skip(self, message), fields(message_id = %message.id))]
-    async fn notify_message_updated(&self, message: &Message) {
-        let room = format!("message:{}", message.id);
-        tracing::info!("Broadcasting messageUpdated");
+    async fn broadcast(&self, room: String, event: &ServerEvent) {
+        let event_name: &'static str = event.into();
+        tracing::info!("Broadcasting {}", event_name);
 
-        let event = ServerEvent::MessageUpdated(message.clone());
-        let event_name: &'static str = (&event).into();
+        let operators = self.io.to(room);
+        let result = match event {
+            ServerEvent::MessageUpdated(message) => operators.emit(event_name, message).await,
+            ServerEvent::MessageDelta(delta) => operators.emit(event_name, delta).await,
+        };
 
-        if let Err(e) = self
-            .io
-            .to(room)
-            .emit(
-                event_name,
-                &match event {
-                    ServerEvent::MessageUpdated(ref m) => m,
-                },
-            )
-            .await
-        {
-            tracing::error!("Failed to broadcast messageUpdated: {:?}", e);
+        if let Err(e) = result {
+            tracing::error!("Failed to broadcast {}: {:?}", event_name, e);
         }
     }
 }
 
+#[async_trait::async_trait]
+impl MessageNotifier for SocketNotifier {
+    #[tracing::instrument(skip(self, message, delta), fields(message_id = %message.id))]
+    async fn notify_message_updated(&self, message: &Message, delta: &MessageDelta) {
+        self.broadcast(
+            message_room(&message.id, DEFAULT_PROTOCOL_VERSION),
+            &ServerEvent::MessageUpdated(message.clone()),
+        )
+        .await;
+        self.broadcast(
+            message_room(&message.id, DELTA_PROTOCOL_VERSION),
+            &ServerEvent::MessageDelta(delta.clone()),
+        )
+        .await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,31 +178,36 @@ mod tests {
         (format!("http://{}", addr), notifier)
     }
 
-    #[tokio::test]
-    async fn test_socket_message_update() {
-        let (server_addr, notifier) = start_test_server().await;
-
+    /// Connects a client that records the payloads of `event`, subscribed to `message_id`
+    async fn connect_subscribed_client(
+        server_addr: &str,
+        auth: Option<Value>,
+        event: &str,
+        message_id: Uuid,
+    ) -> (Client, Arc<Mutex<Vec<Value>>>) {
         // Track received events
         let received_events = Arc::new(Mutex::new(Vec::new()));
         let events_clone = Arc::clone(&received_events);
 
         // Connect Socket.IO client
-        let client = ClientBuilder::new(server_addr)
-            .namespace("/")
-            .on(
-                "messageUpdated",
-                move |payload: Payload, _client: Client| {
-                    let events = Arc::clone(&events_clone);
-                    async move {
-                        if let Payload::Text(values) = payload
-                            && let Some(value) = values.first()
-                        {
-                            events.lock().unwrap().push(value.clone());
-                        }
+        let mut builder = ClientBuilder::new(server_addr).namespace("/").on(
+            event,
+            move |payload: Payload, _client: Client| {
+                let events = Arc::clone(&events_clone);
+                async move {
+                    if let Payload::Text(values) = payload
+                        && let Some(value) = values.first()
+                    {
+                        events.lock().unwrap().push(value.clone());
                     }
-                    .boxed()
-                },
-            )
+                }
+                .boxed()
+            },
+        );
+        if let Some(auth) = auth {
+            builder = builder.auth(auth);
+        }
+        let client = builder
             .connect()
             .await
             .expect("Failed to connect to Socket.IO server");
@@ -180,11 +215,9 @@ mod tests {
         // Wait for connection to establish
         time::sleep(Duration::from_millis(200)).await;
 
-        let message = MessageBuilder::new().build();
-
         // Subscribe to message updates
         let subscribe_payload = SubscribePayload {
-            message_ids: vec![message.id],
+            message_ids: vec![message_id],
         };
         client
             .emit(
@@ -197,8 +230,19 @@ mod tests {
         // Wait for subscription to be processed
         time::sleep(Duration::from_millis(200)).await;
 
+        (client, received_events)
+    }
+
+    #[tokio::test]
+    async fn test_socket_message_update() {
+        let (server_addr, notifier) = start_test_server().await;
+        let message = MessageBuilder::new().build();
+        let (client, received_events) =
+            connect_subscribed_client(&server_addr, None, "messageUpdated", message.id).await;
+
         // Trigger notification
-        notifier.notify_message_updated(&message).await;
+        let delta = MessageDelta::between(&message, &message);
+        notifier.notify_message_updated(&message, &delta).await;
 
         // Wait for event to be received
         time::sleep(Duration::from_millis(300)).await;
@@ -218,4 +262,39 @@ mod tests {
         // Disconnect client
         client.disconnect().await.expect("Failed to disconnect");
     }
+
+    #[tokio::test]
+    async fn test_socket_message_delta_for_new_protocol() {
+        let (server_addr, notifier) = start_test_server().await;
+        let message = MessageBuilder::new().build();
+        let (client, received_events) = connect_subscribed_client(
+            &server_addr,
+            Some(serde_json::json!({ "protocolVersion": DELTA_PROTOCOL_VERSION })),
+            "messageDelta",
+            message.id,
+        )
+        .await;
+
+        let updated_message = Message {
+            content: "edited".to_string(),
+            ..message.clone()
+        };
+        let delta = MessageDelta::between(&message, &updated_message);
+        notifier
+            .notify_message_updated(&updated_message, &delta)
+            .await;
+
+        time::sleep(Duration::from_millis(300)).await;
+
+        {
+            let events = received_events.lock().unwrap();
+            assert_eq!(events.len(), 1, "Should receive exactly one event");
+
+            let received_delta: MessageDelta =
+                serde_json::from_value(events[0].clone()).expect("Failed to deserialize delta");
+            assert_eq!(received_delta, delta);
+        }
+
+        client.disconnect().await.expect("Failed to disconnect");
+    }
 }
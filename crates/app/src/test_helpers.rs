@@ -1,20 +1,38 @@
 //! Shared test utilities for app crate tests
 
 use crate::{
-    handler::AppState,
-    session::{AuthSession, Backend, BasicClientSet, UserSession},
+    api_version::{self, ApiVersion},
+    builder::Services,
+    config::ApiConfig,
+    handler::{AppState, meta::InstanceMeta, onboarding, quota},
+    job::JobHandle,
+    problem, rate_limit, request_id,
+    session::{self, AuthSession, Backend, BasicClientSet, UserSession},
+};
+use axum::{
+    body::Body,
+    http::{HeaderValue, Request, StatusCode, header},
+    middleware, routing,
 };
-use axum::{http::StatusCode, routing};
 use axum_login::AuthManagerLayerBuilder;
 use domain::{
+    chaos::FaultInjector,
     error::RepositoryError,
-    model::User,
+    model::{AffinityScore, OnboardingState, User},
     repository::UserRepository,
-    service::{MockTimelineService, MockTraqService},
-    service::{TimelineService, TraqService},
+    service::{
+        BookmarkService, FederationService, OnboardingService, PresenceService, QuotaService,
+        ReportService, TimelineService, TraqService, WebhookService,
+    },
+    service::{
+        MockBookmarkService, MockFederationService, MockOnboardingService, MockPresenceService,
+        MockQuotaService, MockReportService, MockTimelineService, MockTraqService,
+        MockWebhookService,
+    },
 };
 use oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl, basic::BasicClient};
 use std::sync::Arc;
+use tower::ServiceExt;
 use tower_sessions::{MemoryStore, SessionManagerLayer};
 use uuid::Uuid;
 
@@ -26,12 +44,14 @@ mockall::mock! {
     #[async_trait::async_trait]
     impl UserRepository for UserRepo {
         async fn find_by_id(&self, id: &Uuid) -> Result<Option<User>, RepositoryError>;
+        async fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<User>, RepositoryError>;
         async fn find_random_valid_token(&self) -> Result<Option<String>, RepositoryError>;
         async fn find_token_by_user_id(&self, user_id: &Uuid) -> Result<Option<String>, RepositoryError>;
         async fn save(&self, user: &User) -> Result<(), RepositoryError>;
         async fn save_token(&self, user_id: &Uuid, access_token: &str) -> Result<(), RepositoryError>;
-        async fn find_frequently_stamped_users_by(&self, user_id: &Uuid, limit: i64) -> Result<Vec<Uuid>, RepositoryError>;
+        async fn find_frequently_stamped_users_by(&self, user_id: &Uuid, limit: i64, half_life_days: f64) -> Result<Vec<AffinityScore>, RepositoryError>;
         async fn find_similar_users(&self, user_id: &Uuid, limit: i64) -> Result<Vec<Uuid>, RepositoryError>;
+        async fn find_acquaintances(&self, viewer_ids: &[Uuid], user_ids: &[Uuid]) -> Result<Vec<(Uuid, Uuid)>, RepositoryError>;
     }
 }
 
@@ -66,6 +86,17 @@ fn create_dummy_oauth_client() -> BasicClientSet {
 pub struct TestAppBuilder {
     traq_service: Option<Arc<dyn TraqService>>,
     timeline_service: Option<Arc<dyn TimelineService>>,
+    bookmark_service: Option<Arc<dyn BookmarkService>>,
+    onboarding_service: Option<Arc<dyn OnboardingService>>,
+    report_service: Option<Arc<dyn ReportService>>,
+    webhook_service: Option<Arc<dyn WebhookService>>,
+    presence_service: Option<Arc<dyn PresenceService>>,
+    quota_service: Option<Arc<dyn QuotaService>>,
+    federation_service: Option<Arc<dyn FederationService>>,
+    jobs: JobHandle,
+    admin_user_ids: Vec<Uuid>,
+    meta: InstanceMeta,
+    fault_injector: Option<Arc<FaultInjector>>,
     user: Option<User>,
 }
 
@@ -75,6 +106,17 @@ impl TestAppBuilder {
         Self {
             traq_service: None,
             timeline_service: None,
+            bookmark_service: None,
+            onboarding_service: None,
+            report_service: None,
+            webhook_service: None,
+            presence_service: None,
+            quota_service: None,
+            federation_service: None,
+            jobs: JobHandle::default(),
+            admin_user_ids: vec![],
+            meta: InstanceMeta::default(),
+            fault_injector: None,
             user: None,
         }
     }
@@ -91,6 +133,72 @@ impl TestAppBuilder {
         self
     }
 
+    /// Set a custom BookmarkService (default: MockBookmarkService::new())
+    pub fn with_bookmark_service<T: BookmarkService + 'static>(mut self, service: T) -> Self {
+        self.bookmark_service = Some(Arc::new(service));
+        self
+    }
+
+    /// Set a custom OnboardingService (default: every user has completed onboarding)
+    pub fn with_onboarding_service<T: OnboardingService + 'static>(mut self, service: T) -> Self {
+        self.onboarding_service = Some(Arc::new(service));
+        self
+    }
+
+    /// Set a custom ReportService (default: MockReportService::new())
+    pub fn with_report_service<T: ReportService + 'static>(mut self, service: T) -> Self {
+        self.report_service = Some(Arc::new(service));
+        self
+    }
+
+    /// Set a custom WebhookService (default: MockWebhookService::new())
+    pub fn with_webhook_service<T: WebhookService + 'static>(mut self, service: T) -> Self {
+        self.webhook_service = Some(Arc::new(service));
+        self
+    }
+
+    /// Set a custom PresenceService (default: MockPresenceService::new())
+    pub fn with_presence_service<T: PresenceService + 'static>(mut self, service: T) -> Self {
+        self.presence_service = Some(Arc::new(service));
+        self
+    }
+
+    /// Set a custom QuotaService (default: a MockQuotaService without quotas)
+    pub fn with_quota_service<T: QuotaService + 'static>(mut self, service: T) -> Self {
+        self.quota_service = Some(Arc::new(service));
+        self
+    }
+
+    /// Set a custom FederationService (default: MockFederationService::new())
+    pub fn with_federation_service<T: FederationService + 'static>(mut self, service: T) -> Self {
+        self.federation_service = Some(Arc::new(service));
+        self
+    }
+
+    /// Set the handle of running jobs (default: no jobs)
+    pub fn with_jobs(mut self, jobs: JobHandle) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Allow the given user to access the admin endpoints
+    pub fn with_admin(mut self, user_id: Uuid) -> Self {
+        self.admin_user_ids.push(user_id);
+        self
+    }
+
+    /// Set the instance metadata served by `/meta` (default: InstanceMeta::default())
+    pub fn with_meta(mut self, meta: InstanceMeta) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    /// Enable fault injection, changeable through `/admin/chaos` (default: disabled)
+    pub fn with_fault_injector(mut self, fault_injector: Arc<FaultInjector>) -> Self {
+        self.fault_injector = Some(fault_injector);
+        self
+    }
+
     /// Set the authenticated user for this test app
     pub fn with_user(mut self, user: User) -> Self {
         self.user = Some(user);
@@ -106,11 +214,54 @@ impl TestAppBuilder {
         let timeline_service = self
             .timeline_service
             .unwrap_or_else(|| Arc::new(MockTimelineService::new()));
+        let bookmark_service = self
+            .bookmark_service
+            .unwrap_or_else(|| Arc::new(MockBookmarkService::new()));
+        let onboarding_service = self.onboarding_service.unwrap_or_else(|| {
+            let mut service = MockOnboardingService::new();
+            service.expect_get_onboarding_state().returning(|_| {
+                Ok(OnboardingState {
+                    privacy_notice_accepted: true,
+                    crawl_consent_granted: true,
+                    initial_channels_picked: true,
+                })
+            });
+            Arc::new(service)
+        });
+        let report_service = self
+            .report_service
+            .unwrap_or_else(|| Arc::new(MockReportService::new()));
+        let webhook_service = self
+            .webhook_service
+            .unwrap_or_else(|| Arc::new(MockWebhookService::new()));
+        let presence_service = self
+            .presence_service
+            .unwrap_or_else(|| Arc::new(MockPresenceService::new()));
+        let quota_service = self.quota_service.unwrap_or_else(|| {
+            let mut service = MockQuotaService::new();
+            service.expect_consume().returning(|_, _| Ok(()));
+            Arc::new(service)
+        });
+        let federation_service = self
+            .federation_service
+            .unwrap_or_else(|| Arc::new(MockFederationService::new()));
 
-        let state = AppState::new(traq_service, timeline_service);
-
-        // Use production route setup
-        let (router, _openapi) = crate::setup_openapi_routes();
+        let services = Services {
+            traq: traq_service,
+            timeline: timeline_service,
+            bookmark: bookmark_service,
+            onboarding: onboarding_service,
+            report: report_service,
+            webhook: webhook_service,
+            presence: presence_service,
+            quota: quota_service,
+            federation: federation_service,
+        };
+        let mut state =
+            AppState::new(services, self.jobs, self.admin_user_ids).with_meta(self.meta);
+        if let Some(fault_injector) = self.fault_injector {
+            state = state.with_fault_injector(fault_injector);
+        }
 
         // Create test-specific auth and session layers
         let mock_user_repo = Arc::new(MockUserRepo::new());
@@ -124,9 +275,40 @@ impl TestAppBuilder {
 
         let user = self.user;
 
-        // Nest routes under /api/v1, add test login endpoint, then apply auth layer to everything
-        axum::Router::new()
-            .nest("/api/v1", router)
+        // Nest the production routes of every version, add test login endpoint, then apply auth
+        // layer to everything
+        let mut router = axum::Router::new();
+        for version in ApiVersion::ALL {
+            let (api, _openapi) =
+                crate::setup_openapi_routes(version, crate::DEFAULT_SESSION_COOKIE_NAME);
+            let mut api = api
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    quota::enforce_quotas,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    onboarding::require_onboarding,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit,
+                ))
+                .layer(middleware::from_fn(session::require_login));
+            if let Some(deprecation) = version.deprecation(&ApiConfig::default()) {
+                api = api.layer(middleware::from_fn_with_state(
+                    Arc::new(deprecation),
+                    api_version::deprecate,
+                ));
+            }
+            router = router.nest(version.root(), api);
+        }
+        #[cfg(feature = "graphql")]
+        {
+            router = router.route(crate::graphql::PATH, crate::graphql::route(&state));
+        }
+
+        router
             .route(
                 "/login",
                 routing::post(|mut auth: AuthSession| async move {
@@ -139,6 +321,8 @@ impl TestAppBuilder {
                 }),
             )
             .layer(auth_layer)
+            .layer(middleware::from_fn(problem::describe_bare_errors))
+            .layer(middleware::from_fn(request_id::propagate))
             .with_state(state)
     }
 }
@@ -148,3 +332,16 @@ impl Default for TestAppBuilder {
         Self::new()
     }
 }
+
+/// Logs in as the user set by [`TestAppBuilder::with_user`] and returns the session cookie
+pub async fn login(app: &axum::Router) -> HeaderValue {
+    let req = Request::builder()
+        .uri("/login")
+        .method("POST")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    res.headers().get(header::SET_COOKIE).unwrap().clone()
+}
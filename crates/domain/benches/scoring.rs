@@ -0,0 +1,186 @@
+//! Baselines for merging, scoring and sorting recommendation candidates.
+//!
+//! ```sh
+//! cargo bench -p domain --features test-utils
+//! ```
+//!
+//! Repositories are mocked, so the numbers only cover the work done in the domain layer.
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use domain::{
+    model::{
+        AffinityScore, ChannelScoreOverride, HiddenMessage, MessageListItem, RecommendationReason,
+    },
+    ranking::{HeuristicRanker, Ranker, ScoredCandidate},
+    repository::{
+        MockBlockRepository, MockFeedbackRepository, MockMessageReader, MockMuteRepository,
+        MockStampRepository, MockUserRepository, MockUserSettingsRepository, Repository,
+    },
+    service::{TimelineService, TimelineServiceImpl},
+    test_factories::{RepositoryBuilder, candidate_sources},
+};
+use fake::{Fake, uuid::UUIDv4};
+use std::hint::black_box;
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+fn scored_candidates(
+    sources: &[(RecommendationReason, Vec<MessageListItem>)],
+) -> Vec<ScoredCandidate> {
+    sources
+        .iter()
+        .flat_map(|(source, messages)| {
+            messages
+                .iter()
+                .enumerate()
+                .map(|(rank, message)| ScoredCandidate {
+                    message: message.clone(),
+                    source: *source,
+                    rank,
+                    hidden_author_count: 0,
+                    hidden_channel_count: 0,
+                    ignored_author_count: 0,
+                    ignored_channel_count: 0,
+                    channel_score_multiplier: 1.0,
+                })
+        })
+        .collect()
+}
+
+fn rank(c: &mut Criterion) {
+    let ranker = HeuristicRanker::default();
+    let mut group = c.benchmark_group("rank");
+
+    // Sources find at most 50 messages each in production
+    for per_source in [10, 50, 200] {
+        let candidates = scored_candidates(&candidate_sources(per_source));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(candidates.len()),
+            &candidates,
+            |b, candidates| {
+                b.iter_batched(
+                    || candidates.clone(),
+                    |candidates| ranker.rank(black_box(candidates)),
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Mocks repositories for a user with enough signals to skip the cold start, who has hidden a
+/// few messages and boosted a channel, so that every filter, penalty and multiplier is applied.
+fn repository(sources: &[(RecommendationReason, Vec<MessageListItem>)]) -> Repository {
+    let source = |reason| {
+        sources
+            .iter()
+            .find(|(r, _)| *r == reason)
+            .map(|(_, messages)| messages.clone())
+            .unwrap_or_default()
+    };
+    let top_reacted = source(RecommendationReason::Popular);
+    let by_affinity_authors = source(RecommendationReason::FrequentlyStampedAuthor);
+    let by_affinity_channels = source(RecommendationReason::FrequentlyStampedChannel);
+    let by_similar_users = source(RecommendationReason::SimilarUsers);
+
+    let hidden_messages: Vec<HiddenMessage> = by_affinity_channels
+        .iter()
+        .step_by(10)
+        .map(|m| HiddenMessage {
+            message_id: m.id,
+            author_id: m.user_id,
+            channel_id: m.channel_id,
+        })
+        .collect();
+    let affinity_users: Vec<Uuid> = (0..20).map(|_| UUIDv4.fake()).collect();
+    let affinity_user_scores: Vec<AffinityScore> = affinity_users
+        .iter()
+        .map(|&id| AffinityScore { id, score: 1.0 })
+        .collect();
+    let similar_users: Vec<Uuid> = (0..20).map(|_| UUIDv4.fake()).collect();
+    let affinity_channels: Vec<AffinityScore> = (0..10)
+        .map(|_| AffinityScore {
+            id: UUIDv4.fake(),
+            score: 1.0,
+        })
+        .collect();
+    let channel_score_overrides = vec![ChannelScoreOverride {
+        channel_id: affinity_channels[0].id,
+        multiplier: 2.0,
+    }];
+
+    let mut mute = MockMuteRepository::new();
+    mute.expect_find_muted_user_ids()
+        .returning(|_| Ok(vec![UUIDv4.fake()]));
+    mute.expect_find_muted_channel_ids()
+        .returning(|_| Ok(vec![UUIDv4.fake()]));
+    let mut block = MockBlockRepository::new();
+    block
+        .expect_find_blocked_or_blocking_user_ids()
+        .returning(|_| Ok(vec![UUIDv4.fake()]));
+    let mut feedback = MockFeedbackRepository::new();
+    feedback
+        .expect_find_hidden_messages()
+        .returning(move |_| Ok(hidden_messages.clone()));
+    feedback
+        .expect_find_ignored_recommendations()
+        .returning(|_, _, _| Ok(vec![]));
+
+    let mut user = MockUserRepository::new();
+    user.expect_find_frequently_stamped_users_by()
+        .returning(move |_, _, _| Ok(affinity_user_scores.clone()));
+    let similar_users_clone = similar_users.clone();
+    user.expect_find_similar_users()
+        .returning(move |_, _| Ok(similar_users_clone.clone()));
+    let mut stamp = MockStampRepository::new();
+    stamp
+        .expect_find_frequently_stamped_channels_by()
+        .returning(move |_, _, _| Ok(affinity_channels.clone()));
+
+    let mut user_settings = MockUserSettingsRepository::new();
+    user_settings
+        .expect_find_channel_score_overrides()
+        .returning(move |_| Ok(channel_score_overrides.clone()));
+
+    let mut message = MockMessageReader::new();
+    message
+        .expect_find_top_reacted_messages()
+        .returning(move |_, _| Ok(top_reacted.clone()));
+    message
+        .expect_find_messages_by_author_allowlist()
+        .withf(move |author_ids, _, _| author_ids == affinity_users.as_slice())
+        .returning(move |_, _, _| Ok(by_affinity_authors.clone()));
+    message
+        .expect_find_messages_by_author_allowlist()
+        .withf(move |author_ids, _, _| author_ids == similar_users.as_slice())
+        .returning(move |_, _, _| Ok(by_similar_users.clone()));
+    message
+        .expect_find_messages_by_channel_allowlist()
+        .returning(move |_, _, _| Ok(by_affinity_channels.clone()));
+
+    RepositoryBuilder::new()
+        .block(block)
+        .feedback(feedback)
+        .message_reader(message)
+        .mute(mute)
+        .stamp(stamp)
+        .user(user)
+        .user_settings(user_settings)
+        .build()
+}
+
+fn get_recommended_messages(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let service = TimelineServiceImpl::new(repository(&candidate_sources(50)));
+    let user_id: Uuid = UUIDv4.fake();
+
+    c.bench_function("get_recommended_messages", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { service.get_recommended_messages(&user_id).await.unwrap() })
+    });
+}
+
+criterion_group!(benches, rank, get_recommended_messages);
+criterion_main!(benches);
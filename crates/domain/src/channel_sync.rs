@@ -0,0 +1,96 @@
+use crate::{error::DomainError, repository::Repository, traq_client::TraqClient};
+use std::sync::Arc;
+
+/// The number of channels saved per query, keeping the number of placeholders well within limits.
+const SAVE_CHUNK_SIZE: usize = 1000;
+
+/// Copies the public channels from traQ to the repository, so that they can be shown by name.
+/// It is meant to be run periodically by a job scheduler.
+pub struct ChannelSync {
+    client: Arc<dyn TraqClient>,
+    repo: Repository,
+}
+
+impl ChannelSync {
+    pub fn new(client: Arc<dyn TraqClient>, repo: Repository) -> Self {
+        Self { client, repo }
+    }
+
+    /// Returns the number of channels synced.
+    pub async fn sync(&self) -> Result<usize, DomainError> {
+        let Some(token) = self.repo.user.find_random_valid_token().await? else {
+            tracing::warn!("No valid token found. Skipping channel sync.");
+
+            return Ok(0);
+        };
+        let channels = self.client.get_channels(&token).await?;
+
+        for chunk in channels.chunks(SAVE_CHUNK_SIZE) {
+            self.repo.channel.save_batch(chunk).await?;
+        }
+
+        Ok(channels.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Channel;
+    use crate::repository::{MockChannelRepository, MockUserRepository};
+    use crate::test_factories::RepositoryBuilder;
+    use crate::traq_client::MockTraqClient;
+    use fake::{Fake, uuid::UUIDv4};
+    use mockall::predicate;
+
+    #[tokio::test]
+    async fn sync_saves_channels() {
+        let channels = vec![Channel {
+            id: UUIDv4.fake(),
+            name: "general".to_string(),
+            path: "general".to_string(),
+            archived: false,
+        }];
+        let expected = channels.clone();
+
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_random_valid_token()
+            .returning(|| Ok(Some("test_token".to_string())));
+        let mut mock_client = MockTraqClient::new();
+        mock_client
+            .expect_get_channels()
+            .with(predicate::eq("test_token"))
+            .times(1)
+            .returning(move |_| Ok(channels.clone()));
+        let mut mock_channel_repo = MockChannelRepository::new();
+        mock_channel_repo
+            .expect_save_batch()
+            .withf(move |channels| channels == expected.as_slice())
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let repo = RepositoryBuilder::new()
+            .user(mock_user_repo)
+            .channel(mock_channel_repo)
+            .build();
+        let sync = ChannelSync::new(Arc::new(mock_client), repo);
+
+        assert_eq!(sync.sync().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn sync_skips_when_no_token() {
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_random_valid_token()
+            .returning(|| Ok(None));
+        let mut mock_client = MockTraqClient::new();
+        mock_client.expect_get_channels().never();
+
+        let repo = RepositoryBuilder::new().user(mock_user_repo).build();
+        let sync = ChannelSync::new(Arc::new(mock_client), repo);
+
+        assert_eq!(sync.sync().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,853 @@
+//! Fault injection for resilience testing, for development only.
+//!
+//! Repository and traQ calls can be slowed down and made to fail at random, so that retries,
+//! circuit breakers and degraded timelines can be exercised deliberately instead of waiting for
+//! the database or traQ to misbehave.
+
+use crate::{
+    error::{RepositoryError, TraqClientError},
+    id::{ChannelId, MessageId, StampId, UserId},
+    model::{
+        AffinityScore, Announcement, CachedImage, Channel, ChannelActivity, ChannelScoreOverride,
+        EngagementMetrics, HiddenMessage, Highlight, IgnoredRecommendations, ImageKind, ImageSize,
+        Impression, JobRun, LinkPreview, Message, MessageCursor, MessageEmbedding, MessageEvent,
+        MessageEventKind, MessageListItem, OnboardingState, OnboardingStep, PrivacySettings,
+        QuotaOperation, ReportReason, ReportedMessage, SavedSearch, Stamp, TrendingTag, User,
+        UserStats, Webhook, WebhookEvent,
+    },
+    repository::{
+        AnnouncementRepository, BlockRepository, BookmarkRepository, ChannelRepository,
+        EmbeddingRepository, FeedbackRepository, FollowRepository, ImageCacheRepository,
+        ImpressionRepository, JobRunRepository, LinkPreviewRepository, MessageEventRepository,
+        MessageReader, MessageWriter, MuteRepository, PresenceRepository, QuotaRepository,
+        ReportRepository, Repository, SavedSearchRepository, StampRepository, UserRepository,
+        UserSettingsRepository, WebhookRepository,
+    },
+    traq_client::TraqClient,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use time::{Date, OffsetDateTime};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The faults injected into the calls of one backend.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Faults {
+    /// Every call is delayed by this many milliseconds.
+    pub latency_ms: u64,
+    /// The probability of a call failing, from 0 to 1. Failed calls don't reach the backend.
+    pub error_rate: f64,
+}
+
+impl Faults {
+    pub fn is_valid(&self) -> bool {
+        (0.0..=1.0).contains(&self.error_rate)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultSettings {
+    pub repository: Faults,
+    pub traq: Faults,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultTarget {
+    Repository,
+    Traq,
+}
+
+/// Holds the faults to inject, which can be changed while the app is running.
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    settings: Mutex<FaultSettings>,
+}
+
+impl FaultInjector {
+    pub fn new(settings: FaultSettings) -> Self {
+        Self {
+            settings: Mutex::new(settings),
+        }
+    }
+
+    pub fn settings(&self) -> FaultSettings {
+        *self.settings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set(&self, settings: FaultSettings) {
+        *self.settings.lock().unwrap_or_else(|e| e.into_inner()) = settings;
+    }
+
+    /// Waits for the injected latency of `target`, and returns whether the call should fail.
+    pub async fn inject(&self, target: FaultTarget) -> bool {
+        let settings = self.settings();
+        let faults = match target {
+            FaultTarget::Repository => settings.repository,
+            FaultTarget::Traq => settings.traq,
+        };
+
+        if faults.latency_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(faults.latency_ms)).await;
+        }
+        fastrand::f64() < faults.error_rate
+    }
+}
+
+/// Wraps every repository of `repository` so that faults are injected into its calls.
+pub fn chaotic(repository: Repository, injector: Arc<FaultInjector>) -> Repository {
+    Repository {
+        announcement: Arc::new(Chaotic::new(
+            "announcement",
+            repository.announcement,
+            injector.clone(),
+        )),
+        block: Arc::new(Chaotic::new("block", repository.block, injector.clone())),
+        bookmark: Arc::new(Chaotic::new(
+            "bookmark",
+            repository.bookmark,
+            injector.clone(),
+        )),
+        channel: Arc::new(Chaotic::new(
+            "channel",
+            repository.channel,
+            injector.clone(),
+        )),
+        embedding: Arc::new(Chaotic::new(
+            "embedding",
+            repository.embedding,
+            injector.clone(),
+        )),
+        feedback: Arc::new(Chaotic::new(
+            "feedback",
+            repository.feedback,
+            injector.clone(),
+        )),
+        follow: Arc::new(Chaotic::new("follow", repository.follow, injector.clone())),
+        image_cache: Arc::new(Chaotic::new(
+            "image_cache",
+            repository.image_cache,
+            injector.clone(),
+        )),
+        impression: Arc::new(Chaotic::new(
+            "impression",
+            repository.impression,
+            injector.clone(),
+        )),
+        job_run: Arc::new(Chaotic::new(
+            "job_run",
+            repository.job_run,
+            injector.clone(),
+        )),
+        link_preview: Arc::new(Chaotic::new(
+            "link_preview",
+            repository.link_preview,
+            injector.clone(),
+        )),
+        message_reader: Arc::new(Chaotic::new(
+            "message_reader",
+            repository.message_reader,
+            injector.clone(),
+        )),
+        message_writer: Arc::new(Chaotic::new(
+            "message_writer",
+            repository.message_writer,
+            injector.clone(),
+        )),
+        message_event: Arc::new(Chaotic::new(
+            "message_event",
+            repository.message_event,
+            injector.clone(),
+        )),
+        mute: Arc::new(Chaotic::new("mute", repository.mute, injector.clone())),
+        presence: Arc::new(Chaotic::new(
+            "presence",
+            repository.presence,
+            injector.clone(),
+        )),
+        quota: Arc::new(Chaotic::new("quota", repository.quota, injector.clone())),
+        report: Arc::new(Chaotic::new("report", repository.report, injector.clone())),
+        saved_search: Arc::new(Chaotic::new(
+            "saved_search",
+            repository.saved_search,
+            injector.clone(),
+        )),
+        stamp: Arc::new(Chaotic::new("stamp", repository.stamp, injector.clone())),
+        user: Arc::new(Chaotic::new("user", repository.user, injector.clone())),
+        user_settings: Arc::new(Chaotic::new(
+            "user_settings",
+            repository.user_settings,
+            injector.clone(),
+        )),
+        webhook: Arc::new(Chaotic::new(
+            "webhook",
+            repository.webhook,
+            injector.clone(),
+        )),
+    }
+}
+
+/// A repository whose calls are delayed and failed as set in a [`FaultInjector`].
+#[derive(Debug)]
+pub struct Chaotic<T: ?Sized> {
+    name: &'static str,
+    inner: Arc<T>,
+    injector: Arc<FaultInjector>,
+}
+
+impl<T: ?Sized> Chaotic<T> {
+    pub fn new(name: &'static str, inner: Arc<T>, injector: Arc<FaultInjector>) -> Self {
+        Self {
+            name,
+            inner,
+            injector,
+        }
+    }
+
+    async fn inject<R>(
+        &self,
+        method: &str,
+        call: impl Future<Output = Result<R, RepositoryError>>,
+    ) -> Result<R, RepositoryError> {
+        if self.injector.inject(FaultTarget::Repository).await {
+            return Err(RepositoryError::Database(format!(
+                "injected fault in {}::{}",
+                self.name, method
+            )));
+        }
+
+        call.await
+    }
+}
+
+/// Implements repository traits for [`Chaotic`] and the traQ client for [`ChaosTraqClient`],
+/// passing each listed method to the inner one through `inject`.
+macro_rules! inject_faults {
+    ($(
+        impl $trait:ident for $target:ty {
+            $(
+                fn $method:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) -> $ret:ty;
+            )*
+        }
+    )*) => {
+        $(
+            #[async_trait::async_trait]
+            impl $trait for $target {
+                $(
+                    async fn $method(&self $(, $arg: $ty)*) -> $ret {
+                        self.inject(stringify!($method), self.inner.$method($($arg),*))
+                            .await
+                    }
+                )*
+            }
+        )*
+    };
+}
+
+inject_faults! {
+    impl AnnouncementRepository for Chaotic<dyn AnnouncementRepository> {
+        fn publish(&self, content: &str) -> Result<Announcement, RepositoryError>;
+        fn import(&self, announcement: &Announcement) -> Result<(), RepositoryError>;
+        fn withdraw(&self) -> Result<bool, RepositoryError>;
+        fn find_undismissed(&self, user_id: &Uuid) -> Result<Option<Announcement>, RepositoryError>;
+        fn dismiss(&self, user_id: &Uuid, announcement_id: i64) -> Result<(), RepositoryError>;
+    }
+
+    impl BlockRepository for Chaotic<dyn BlockRepository> {
+        fn block_user(&self, user_id: &UserId, blocked_user_id: &UserId) -> Result<(), RepositoryError>;
+        fn unblock_user(
+            &self,
+            user_id: &UserId,
+            blocked_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        fn find_blocked_or_blocking_user_ids(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+    }
+
+    impl BookmarkRepository for Chaotic<dyn BookmarkRepository> {
+        fn add(&self, user_id: &UserId, message_id: &MessageId) -> Result<(), RepositoryError>;
+        fn remove(&self, user_id: &UserId, message_id: &MessageId) -> Result<(), RepositoryError>;
+        fn find_bookmarked_messages(
+            &self,
+            user_id: &UserId,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+    }
+
+    impl ChannelRepository for Chaotic<dyn ChannelRepository> {
+        fn find_by_id(&self, id: &Uuid) -> Result<Option<Channel>, RepositoryError>;
+        fn find_all(&self) -> Result<Vec<Channel>, RepositoryError>;
+        fn save_batch(&self, channels: &[Channel]) -> Result<(), RepositoryError>;
+    }
+
+    impl EmbeddingRepository for Chaotic<dyn EmbeddingRepository> {
+        fn find_messages_without_embedding(
+            &self,
+            limit: i64,
+        ) -> Result<Vec<(Uuid, String)>, RepositoryError>;
+        fn save_embeddings(&self, embeddings: &[MessageEmbedding]) -> Result<(), RepositoryError>;
+        fn find_stamped_message_embeddings(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageEmbedding>, RepositoryError>;
+        fn find_candidate_embeddings(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageEmbedding>, RepositoryError>;
+    }
+
+    impl FeedbackRepository for Chaotic<dyn FeedbackRepository> {
+        fn hide_message(&self, user_id: &Uuid, message: &Message) -> Result<(), RepositoryError>;
+        fn find_hidden_messages(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<HiddenMessage>, RepositoryError>;
+        fn find_ignored_recommendations(
+            &self,
+            user_id: &Uuid,
+            since: OffsetDateTime,
+            until: OffsetDateTime,
+        ) -> Result<Vec<IgnoredRecommendations>, RepositoryError>;
+    }
+
+    impl FollowRepository for Chaotic<dyn FollowRepository> {
+        fn follow_user(
+            &self,
+            user_id: &UserId,
+            followed_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        fn unfollow_user(
+            &self,
+            user_id: &UserId,
+            followed_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        fn find_followed_messages(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+    }
+
+    impl ImageCacheRepository for Chaotic<dyn ImageCacheRepository> {
+        fn find(
+            &self,
+            kind: ImageKind,
+            id: &Uuid,
+            size: ImageSize,
+        ) -> Result<Option<CachedImage>, RepositoryError>;
+        fn save(
+            &self,
+            kind: ImageKind,
+            id: &Uuid,
+            size: ImageSize,
+            image: &[u8],
+            content_type: &str,
+        ) -> Result<(), RepositoryError>;
+    }
+
+    impl ImpressionRepository for Chaotic<dyn ImpressionRepository> {
+        fn record_impressions(
+            &self,
+            user_id: &Uuid,
+            impressions: &[Impression],
+        ) -> Result<(), RepositoryError>;
+        fn compute_engagement_metrics(
+            &self,
+            since: OffsetDateTime,
+            until: OffsetDateTime,
+        ) -> Result<Vec<EngagementMetrics>, RepositoryError>;
+        fn save_engagement_metrics(
+            &self,
+            date: Date,
+            metrics: &[EngagementMetrics],
+        ) -> Result<(), RepositoryError>;
+    }
+
+    impl JobRunRepository for Chaotic<dyn JobRunRepository> {
+        fn save(&self, run: &JobRun) -> Result<u64, RepositoryError>;
+        fn import(&self, id: u64, run: &JobRun) -> Result<(), RepositoryError>;
+        fn find_recent(&self, limit: i64) -> Result<Vec<JobRun>, RepositoryError>;
+        fn delete_started_before(&self, before: OffsetDateTime) -> Result<(), RepositoryError>;
+    }
+
+    impl LinkPreviewRepository for Chaotic<dyn LinkPreviewRepository> {
+        fn find_fetched_since(
+            &self,
+            urls: &[String],
+            since: OffsetDateTime,
+        ) -> Result<Vec<LinkPreview>, RepositoryError>;
+        fn save(&self, preview: &LinkPreview) -> Result<(), RepositoryError>;
+    }
+
+    impl MessageReader for Chaotic<dyn MessageReader> {
+        fn find_latest_message_time(&self) -> Result<Option<OffsetDateTime>, RepositoryError>;
+        fn find_by_id(&self, id: &Uuid) -> Result<Option<Message>, RepositoryError>;
+        fn find_list_items_by_ids(
+            &self,
+            ids: &[Uuid],
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_sync_candidates(
+            &self,
+        ) -> Result<Vec<(Uuid, OffsetDateTime, OffsetDateTime)>, RepositoryError>;
+        fn find_read_message_ids(
+            &self,
+            user_id: &Uuid,
+            message_ids: &[Uuid],
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+        fn find_read_message_ids_by_users(
+            &self,
+            user_ids: &[Uuid],
+            message_ids: &[Uuid],
+        ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError>;
+        fn find_top_reacted_messages(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_messages_by_author_allowlist(
+            &self,
+            author_ids: &[Uuid],
+            limit: i64,
+            user_id: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_messages_by_channel_allowlist(
+            &self,
+            channel_ids: &[Uuid],
+            limit: i64,
+            user_id: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_trending_messages(
+            &self,
+            user_id: &Uuid,
+            window_hours: i64,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_highlights(
+            &self,
+            since: OffsetDateTime,
+            limit: i64,
+        ) -> Result<Vec<Highlight>, RepositoryError>;
+        fn find_most_active_channels(
+            &self,
+            limit: i64,
+        ) -> Result<Vec<ChannelActivity>, RepositoryError>;
+        fn find_recent_messages_across_channels(
+            &self,
+            user_id: &Uuid,
+            per_channel: i64,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn search(
+            &self,
+            query: &str,
+            limit: i64,
+            viewer: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_by_tag(
+            &self,
+            tag: &str,
+            limit: i64,
+            viewer: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_by_author(
+            &self,
+            author_id: &Uuid,
+            before: Option<MessageCursor>,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_by_channel(
+            &self,
+            channel_id: &Uuid,
+            before: Option<MessageCursor>,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_public_messages(
+            &self,
+            channel_ids: &[Uuid],
+            before: Option<MessageCursor>,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_user_stats(
+            &self,
+            user_id: &Uuid,
+            since: OffsetDateTime,
+        ) -> Result<UserStats, RepositoryError>;
+        fn find_co_reacted_messages(
+            &self,
+            message_id: &Uuid,
+            limit: i64,
+            viewer: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_thread(
+            &self,
+            message_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        fn find_trending_tags(
+            &self,
+            window_hours: i64,
+            limit: i64,
+        ) -> Result<Vec<TrendingTag>, RepositoryError>;
+    }
+
+    impl MessageWriter for Chaotic<dyn MessageWriter> {
+        fn remove_reaction(
+            &self,
+            message_id: &MessageId,
+            stamp_id: &StampId,
+            user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        fn save(&self, message: &Message) -> Result<(), RepositoryError>;
+        fn save_batch(&self, messages: &[Message]) -> Result<(), RepositoryError>;
+        fn mark_messages_as_read(
+            &self,
+            user_id: &UserId,
+            message_ids: &[MessageId],
+        ) -> Result<(), RepositoryError>;
+        fn roll_up_read_messages(&self, before: OffsetDateTime) -> Result<u64, RepositoryError>;
+    }
+
+    impl MessageEventRepository for Chaotic<dyn MessageEventRepository> {
+        fn append(
+            &self,
+            message_ids: &[Uuid],
+            kind: MessageEventKind,
+        ) -> Result<Vec<MessageEvent>, RepositoryError>;
+        fn import(&self, events: &[MessageEvent]) -> Result<(), RepositoryError>;
+        fn find_since(&self, cursor: i64, limit: i64) -> Result<Vec<MessageEvent>, RepositoryError>;
+        fn find_latest_cursor(&self) -> Result<i64, RepositoryError>;
+    }
+
+    impl MuteRepository for Chaotic<dyn MuteRepository> {
+        fn mute_user(&self, user_id: &UserId, muted_user_id: &UserId) -> Result<(), RepositoryError>;
+        fn unmute_user(&self, user_id: &UserId, muted_user_id: &UserId) -> Result<(), RepositoryError>;
+        fn find_muted_user_ids(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError>;
+        fn mute_channel(
+            &self,
+            user_id: &UserId,
+            channel_id: &ChannelId,
+        ) -> Result<(), RepositoryError>;
+        fn unmute_channel(
+            &self,
+            user_id: &UserId,
+            channel_id: &ChannelId,
+        ) -> Result<(), RepositoryError>;
+        fn find_muted_channel_ids(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError>;
+    }
+
+    impl PresenceRepository for Chaotic<dyn PresenceRepository> {
+        fn save_heartbeat(
+            &self,
+            session_id: &str,
+            user_id: &Uuid,
+            seen_at: OffsetDateTime,
+        ) -> Result<(), RepositoryError>;
+        fn is_seen_since(
+            &self,
+            user_id: &Uuid,
+            since: OffsetDateTime,
+        ) -> Result<bool, RepositoryError>;
+        fn count_users_seen_since(&self, since: OffsetDateTime) -> Result<i64, RepositoryError>;
+        fn delete_seen_before(&self, before: OffsetDateTime) -> Result<(), RepositoryError>;
+    }
+
+    impl QuotaRepository for Chaotic<dyn QuotaRepository> {
+        fn try_use(
+            &self,
+            user_id: &Uuid,
+            operation: QuotaOperation,
+            day: Date,
+            limit: i64,
+        ) -> Result<bool, RepositoryError>;
+        fn find_used(
+            &self,
+            user_id: &Uuid,
+            day: Date,
+        ) -> Result<Vec<(QuotaOperation, i64)>, RepositoryError>;
+        fn delete_before(&self, before: Date) -> Result<(), RepositoryError>;
+    }
+
+    impl ReportRepository for Chaotic<dyn ReportRepository> {
+        fn add(
+            &self,
+            user_id: &UserId,
+            message_id: &MessageId,
+            reason: ReportReason,
+        ) -> Result<(), RepositoryError>;
+        fn find_unresolved(&self, limit: i64) -> Result<Vec<ReportedMessage>, RepositoryError>;
+        fn resolve(&self, message_id: &MessageId) -> Result<(), RepositoryError>;
+        fn find_message_ids_reported_at_least(
+            &self,
+            threshold: i64,
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+    }
+
+    impl SavedSearchRepository for Chaotic<dyn SavedSearchRepository> {
+        fn create(
+            &self,
+            user_id: &Uuid,
+            name: &str,
+            query: &str,
+        ) -> Result<SavedSearch, RepositoryError>;
+        fn import(&self, user_id: &Uuid, search: &SavedSearch) -> Result<(), RepositoryError>;
+        fn find_by_user(&self, user_id: &Uuid) -> Result<Vec<SavedSearch>, RepositoryError>;
+        fn find_by_id(
+            &self,
+            user_id: &Uuid,
+            id: i64,
+        ) -> Result<Option<SavedSearch>, RepositoryError>;
+        fn delete(&self, user_id: &Uuid, id: i64) -> Result<bool, RepositoryError>;
+    }
+
+    impl StampRepository for Chaotic<dyn StampRepository> {
+        fn find_by_id(&self, id: &Uuid) -> Result<Option<Stamp>, RepositoryError>;
+        fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Stamp>, RepositoryError>;
+        fn save(&self, stamp: &Stamp) -> Result<(), RepositoryError>;
+        fn save_batch(&self, stamps: &[Stamp]) -> Result<(), RepositoryError>;
+        fn find_frequently_stamped_channels_by(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+            half_life_days: f64,
+        ) -> Result<Vec<AffinityScore>, RepositoryError>;
+        fn find_frequently_received(
+            &self,
+            author_id: &Uuid,
+            channel_id: &Uuid,
+            since: OffsetDateTime,
+            limit: i64,
+        ) -> Result<Vec<Stamp>, RepositoryError>;
+        fn record_usage(&self, user_id: &UserId, stamp_id: &StampId) -> Result<(), RepositoryError>;
+        fn find_recently_used_by(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<Stamp>, RepositoryError>;
+    }
+
+    impl UserRepository for Chaotic<dyn UserRepository> {
+        fn find_by_id(&self, id: &Uuid) -> Result<Option<User>, RepositoryError>;
+        fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<User>, RepositoryError>;
+        fn find_random_valid_token(&self) -> Result<Option<String>, RepositoryError>;
+        fn find_token_by_user_id(&self, user_id: &Uuid) -> Result<Option<String>, RepositoryError>;
+        fn save(&self, user: &User) -> Result<(), RepositoryError>;
+        fn save_token(&self, user_id: &Uuid, access_token: &str) -> Result<(), RepositoryError>;
+        fn find_frequently_stamped_users_by(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+            half_life_days: f64,
+        ) -> Result<Vec<AffinityScore>, RepositoryError>;
+        fn find_similar_users(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+        fn find_acquaintances(
+            &self,
+            viewer_ids: &[Uuid],
+            user_ids: &[Uuid],
+        ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError>;
+    }
+
+    impl UserSettingsRepository for Chaotic<dyn UserSettingsRepository> {
+        fn find_onboarding_state(&self, user_id: &Uuid) -> Result<OnboardingState, RepositoryError>;
+        fn complete_onboarding_step(
+            &self,
+            user_id: &Uuid,
+            step: OnboardingStep,
+        ) -> Result<(), RepositoryError>;
+        fn find_channel_interests(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError>;
+        fn save_channel_interests(
+            &self,
+            user_id: &UserId,
+            channel_ids: &[ChannelId],
+        ) -> Result<(), RepositoryError>;
+        fn find_channel_score_overrides(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<ChannelScoreOverride>, RepositoryError>;
+        fn save_channel_score_override(
+            &self,
+            user_id: &Uuid,
+            score_override: &ChannelScoreOverride,
+        ) -> Result<(), RepositoryError>;
+        fn delete_channel_score_override(
+            &self,
+            user_id: &UserId,
+            channel_id: &ChannelId,
+        ) -> Result<(), RepositoryError>;
+        fn find_privacy_settings(&self, user_id: &Uuid) -> Result<PrivacySettings, RepositoryError>;
+        fn save_privacy_settings(
+            &self,
+            user_id: &Uuid,
+            settings: &PrivacySettings,
+        ) -> Result<(), RepositoryError>;
+        fn find_acquaintance_only_user_ids(
+            &self,
+            user_ids: &[Uuid],
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+    }
+
+    impl WebhookRepository for Chaotic<dyn WebhookRepository> {
+        fn create(
+            &self,
+            url: &str,
+            secret: &str,
+            events: &[WebhookEvent],
+            created_by: &Uuid,
+        ) -> Result<Webhook, RepositoryError>;
+        fn import(&self, webhook: &Webhook, secret: &str) -> Result<(), RepositoryError>;
+        fn find_all(&self) -> Result<Vec<Webhook>, RepositoryError>;
+        fn find_all_with_secrets(&self) -> Result<Vec<(Webhook, String)>, RepositoryError>;
+        fn delete(&self, id: i64) -> Result<bool, RepositoryError>;
+    }
+}
+
+/// Wraps a [`TraqClient`] so that its calls are delayed and failed as set in a [`FaultInjector`].
+#[derive(Debug)]
+pub struct ChaosTraqClient {
+    inner: Arc<dyn TraqClient>,
+    injector: Arc<FaultInjector>,
+}
+
+impl ChaosTraqClient {
+    pub fn new(inner: Arc<dyn TraqClient>, injector: Arc<FaultInjector>) -> Self {
+        Self { inner, injector }
+    }
+
+    async fn inject<R>(
+        &self,
+        method: &str,
+        call: impl Future<Output = Result<R, TraqClientError>>,
+    ) -> Result<R, TraqClientError> {
+        if self.injector.inject(FaultTarget::Traq).await {
+            return Err(TraqClientError::HttpRequest(format!(
+                "injected fault in {method}"
+            )));
+        }
+
+        call.await
+    }
+}
+
+inject_faults! {
+    impl TraqClient for ChaosTraqClient {
+        fn get_server_version(&self) -> Result<String, TraqClientError>;
+        fn fetch_messages_since(
+            &self,
+            token: &str,
+            since: OffsetDateTime,
+        ) -> Result<Vec<Message>, TraqClientError>;
+        fn get_channels(&self, token: &str) -> Result<Vec<Channel>, TraqClientError>;
+        fn get_stamp(&self, token: &str, stamp_id: &Uuid) -> Result<Stamp, TraqClientError>;
+        fn get_stamps(&self, token: &str) -> Result<Vec<Stamp>, TraqClientError>;
+        fn get_stamp_image(
+            &self,
+            token: &str,
+            stamp_id: &Uuid,
+        ) -> Result<(Vec<u8>, String), TraqClientError>;
+        fn get_user(&self, token: &str, user_id: &Uuid) -> Result<User, TraqClientError>;
+        fn get_user_icon(
+            &self,
+            token: &str,
+            user_id: &Uuid,
+        ) -> Result<(Vec<u8>, String), TraqClientError>;
+        fn add_message_stamp(
+            &self,
+            token: &str,
+            message_id: &MessageId,
+            stamp_id: &StampId,
+            count: i32,
+        ) -> Result<(), TraqClientError>;
+        fn remove_message_stamp(
+            &self,
+            token: &str,
+            message_id: &MessageId,
+            stamp_id: &StampId,
+        ) -> Result<(), TraqClientError>;
+        fn get_message(&self, token: &str, message_id: &Uuid) -> Result<Message, TraqClientError>;
+        fn get_channel_messages(
+            &self,
+            token: &str,
+            channel_id: &Uuid,
+            before: Option<OffsetDateTime>,
+            limit: i32,
+        ) -> Result<Vec<Message>, TraqClientError>;
+        fn post_message(
+            &self,
+            token: &str,
+            channel_id: &Uuid,
+            content: &str,
+        ) -> Result<(), TraqClientError>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{repository::MockChannelRepository, traq_client::MockTraqClient};
+    use std::time::Instant;
+
+    fn injector(repository: Faults, traq: Faults) -> Arc<FaultInjector> {
+        Arc::new(FaultInjector::new(FaultSettings { repository, traq }))
+    }
+
+    #[tokio::test]
+    async fn failed_calls_do_not_reach_the_backend() {
+        // The mock panics if it is called
+        let inner = MockChannelRepository::new();
+        let always = Faults {
+            latency_ms: 0,
+            error_rate: 1.0,
+        };
+        let repo: Chaotic<dyn ChannelRepository> = Chaotic::new(
+            "channel",
+            Arc::new(inner),
+            injector(always, Faults::default()),
+        );
+
+        let err = repo.find_all().await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "database error: injected fault in channel::find_all"
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_pass_through_without_faults() {
+        let mut inner = MockTraqClient::new();
+        inner
+            .expect_get_server_version()
+            .times(1)
+            .returning(|| Ok("3.0.0".to_string()));
+        let client = ChaosTraqClient::new(
+            Arc::new(inner),
+            injector(Faults::default(), Faults::default()),
+        );
+
+        assert_eq!(client.get_server_version().await.unwrap(), "3.0.0");
+    }
+
+    #[tokio::test]
+    async fn latency_is_injected_before_calls() {
+        let slow = Faults {
+            latency_ms: 50,
+            error_rate: 0.0,
+        };
+        let injector = injector(Faults::default(), slow);
+
+        let started_at = Instant::now();
+        assert!(!injector.inject(FaultTarget::Traq).await);
+        assert!(started_at.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,92 @@
+//! Citations of other messages, which link messages into threads and are shown as quotes.
+//! traQ cites a message by embedding its URL (`https://q.trap.jp/messages/{id}`) in the content,
+//! or with an embed like `!{"type":"message","raw":"...","id":"{id}"}`.
+
+use crate::markdown;
+use uuid::Uuid;
+
+const MESSAGE_PATH: &str = "/messages/";
+const MESSAGE_EMBED_TYPE: &str = r#""type":"message""#;
+const EMBED_ID_KEY: &str = r#""id":""#;
+const HYPHENATED_UUID_LEN: usize = 36;
+
+/// Finds the message a message replies to, which is the first message cited in its content.
+///
+/// Only links with an `http(s)://` scheme are citations, so paths mentioned in text are not. Links
+/// in code blocks are ignored, like hashtags.
+pub fn extract_reply_to(content: &str) -> Option<Uuid> {
+    markdown::outside_code_blocks(content).find_map(|text| {
+        linked_message_ids(text)
+            .chain(embedded_message_ids(text))
+            .min_by_key(|&(position, _)| position)
+            .map(|(_, id)| id)
+    })
+}
+
+/// Finds the messages linked by URL, with the positions of the links.
+fn linked_message_ids(text: &str) -> impl Iterator<Item = (usize, Uuid)> {
+    text.match_indices(MESSAGE_PATH).filter_map(|(i, _)| {
+        let is_link = text[..i]
+            .rsplit(char::is_whitespace)
+            .next()
+            .is_some_and(|url| url.starts_with("https://") || url.starts_with("http://"));
+        let start = i + MESSAGE_PATH.len();
+        let id = Uuid::try_parse(text.get(start..start + HYPHENATED_UUID_LEN)?).ok()?;
+
+        is_link.then_some((i, id))
+    })
+}
+
+/// Finds the messages embedded with `!{...}`, with the positions of the embeds.
+fn embedded_message_ids(text: &str) -> impl Iterator<Item = (usize, Uuid)> {
+    text.match_indices("!{").filter_map(|(i, _)| {
+        let embed = &text[i..i + text[i..].find('}')?];
+        if !embed.contains(MESSAGE_EMBED_TYPE) {
+            return None;
+        }
+        let start = embed.find(EMBED_ID_KEY)? + EMBED_ID_KEY.len();
+        let id = Uuid::try_parse(embed.get(start..start + HYPHENATED_UUID_LEN)?).ok()?;
+
+        Some((i, id))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "0197a6b2-3c4d-7e8f-9a0b-1c2d3e4f5a6b";
+
+    #[test]
+    fn extracts_the_first_cited_message() {
+        let content = format!(
+            "I agree\nhttps://q.trap.jp/messages/{ID}\nhttps://q.trap.jp/messages/{}",
+            Uuid::nil()
+        );
+
+        assert_eq!(extract_reply_to(&content), Uuid::try_parse(ID).ok());
+    }
+
+    #[test]
+    fn extracts_embedded_messages() {
+        let content = format!(
+            r#"!{{"type":"user","raw":"@a","id":"{}"}} !{{"type":"message","raw":"quote","id":"{ID}"}}"#,
+            Uuid::nil()
+        );
+
+        assert_eq!(extract_reply_to(&content), Uuid::try_parse(ID).ok());
+    }
+
+    #[test]
+    fn ignores_non_links_and_code_blocks() {
+        assert_eq!(extract_reply_to(&format!("see /messages/{ID}")), None);
+        assert_eq!(
+            extract_reply_to(&format!("```\nhttps://q.trap.jp/messages/{ID}\n```")),
+            None
+        );
+        assert_eq!(
+            extract_reply_to("https://q.trap.jp/messages/not-a-message-id"),
+            None
+        );
+    }
+}
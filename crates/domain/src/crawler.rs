@@ -1,16 +1,26 @@
 use crate::{
-    error::DomainError, model::Message, notifier::MessageNotifier, repository::Repository,
+    error::{DomainError, TraqClientError},
+    event::MessageDelta,
+    model::{Message, MessageEventKind},
+    notifier::MessageNotifier,
+    recent_messages::RecentMessages,
+    repository::Repository,
+    search::SearchIndex,
     traq_client::TraqClient,
 };
-use ::time::{Duration, OffsetDateTime};
-use std::{sync::Arc, time::Duration as StdDuration};
-use tokio::time;
+use http::StatusCode;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
 
-/// Fetches new messages from traQ every 30 seconds and saves them to the repository.
+/// Fetches new messages from traQ and saves them to the repository.
+/// It is meant to be run periodically by a job scheduler.
 pub struct MessageCrawler {
     client: Arc<dyn TraqClient>,
     repo: Repository,
     notifier: Arc<dyn MessageNotifier>,
+    recent_messages: Option<Arc<RecentMessages>>,
+    search_index: Option<Arc<dyn SearchIndex>>,
 }
 
 impl MessageCrawler {
@@ -23,23 +33,55 @@ impl MessageCrawler {
             client,
             repo,
             notifier,
+            recent_messages: None,
+            search_index: None,
         }
     }
 
-    pub async fn run(&self) {
-        loop {
-            if let Err(e) = self.crawl().await {
-                tracing::error!("Crawl failed: {:?}", e);
-            }
+    /// Keeps newly fetched messages in `recent_messages` as well as in the repository.
+    pub fn with_recent_messages(mut self, recent_messages: Arc<RecentMessages>) -> Self {
+        self.recent_messages = Some(recent_messages);
+        self
+    }
+
+    /// Keeps `search_index` in sync with fetched, refreshed and removed messages.
+    pub fn with_search_index(mut self, search_index: Arc<dyn SearchIndex>) -> Self {
+        self.search_index = Some(search_index);
+        self
+    }
 
-            time::sleep(StdDuration::from_secs(30)).await;
+    // Failing to index does not fail the crawl, since the repository stays the source of truth
+    async fn index_messages(&self, messages: &[Message]) {
+        let Some(search_index) = &self.search_index else {
+            return;
+        };
+        if messages.is_empty() {
+            return;
+        }
+
+        if let Err(e) = search_index.index_messages(messages).await {
+            tracing::warn!("Failed to index {} messages: {:?}", messages.len(), e);
+        }
+    }
+
+    async fn remove_indexed_message(&self, message_id: &Uuid) {
+        let Some(search_index) = &self.search_index else {
+            return;
+        };
+
+        if let Err(e) = search_index.remove_messages(&[*message_id]).await {
+            tracing::warn!(
+                "Failed to remove message {} from the index: {:?}",
+                message_id,
+                e
+            );
         }
     }
 
     pub async fn crawl(&self) -> Result<(), DomainError> {
         let last_fetched_at = self
             .repo
-            .message
+            .message_reader
             .find_latest_message_time()
             .await?
             .unwrap_or_else(|| OffsetDateTime::now_utc() - Duration::days(1));
@@ -56,19 +98,42 @@ impl MessageCrawler {
             .fetch_messages_since(&token, last_fetched_at)
             .await?;
 
-        self.repo.message.save_batch(&messages).await?;
+        if let Some(recent_messages) = &self.recent_messages {
+            recent_messages.extend(&messages);
+        }
+
+        self.repo.message_writer.save_batch(&messages).await?;
+        self.index_messages(&messages).await;
+
+        let message_ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+        self.repo
+            .message_event
+            .append(&message_ids, MessageEventKind::Added)
+            .await?;
 
         let refreshed_messages = self.refresh_messages(&token).await?;
+        let refreshed_ids: Vec<Uuid> = refreshed_messages.iter().map(|(m, _)| m.id).collect();
+        self.repo
+            .message_event
+            .append(&refreshed_ids, MessageEventKind::Updated)
+            .await?;
 
-        for message in &refreshed_messages {
-            self.notifier.notify_message_updated(message).await;
+        for (message, delta) in &refreshed_messages {
+            self.notifier.notify_message_updated(message, delta).await;
         }
+        let refreshed_messages: Vec<Message> =
+            refreshed_messages.into_iter().map(|(m, _)| m).collect();
+        self.index_messages(&refreshed_messages).await;
 
         Ok(())
     }
 
-    async fn refresh_messages(&self, token: &str) -> Result<Vec<Message>, DomainError> {
-        let candidates = self.repo.message.find_sync_candidates().await?;
+    /// Refreshes recent messages from traQ and returns the ones that changed with their deltas.
+    async fn refresh_messages(
+        &self,
+        token: &str,
+    ) -> Result<Vec<(Message, MessageDelta)>, DomainError> {
+        let candidates = self.repo.message_reader.find_sync_candidates().await?;
         let now = OffsetDateTime::now_utc();
         let mut refreshed_messages = Vec::new();
 
@@ -79,25 +144,37 @@ impl MessageCrawler {
 
             match self.client.get_message(token, &message_id).await {
                 Ok(new_message) => {
-                    let existing_message = match self.repo.message.find_by_id(&message_id).await? {
-                        Some(msg) => msg,
-                        None => {
-                            // Since message_id is from the repo, this should not happen
-                            return Err(DomainError::NoMessageForId(message_id));
-                        }
-                    };
+                    let existing_message =
+                        match self.repo.message_reader.find_by_id(&message_id).await? {
+                            Some(msg) => msg,
+                            None => {
+                                // Since message_id is from the repo, this should not happen
+                                return Err(DomainError::NoMessageForId(message_id));
+                            }
+                        };
 
                     // Always save to update last_crawled_at
-                    self.repo.message.save(&new_message).await?;
+                    self.repo.message_writer.save(&new_message).await?;
 
                     // Only notify if the message actually changed
                     if existing_message != new_message {
                         tracing::debug!("Refreshed message {}", message_id);
-                        refreshed_messages.push(new_message);
+                        let delta = MessageDelta::between(&existing_message, &new_message);
+                        refreshed_messages.push((new_message, delta));
                     } else {
                         tracing::debug!("Message {} unchanged, skipping notification", message_id);
                     }
                 }
+                Err(TraqClientError::ApiError { status, .. })
+                    if status == StatusCode::NOT_FOUND =>
+                {
+                    tracing::debug!("Message {} was removed from traQ", message_id);
+                    self.repo
+                        .message_event
+                        .append(&[message_id], MessageEventKind::Removed)
+                        .await?;
+                    self.remove_indexed_message(&message_id).await;
+                }
                 Err(e) => {
                     tracing::warn!("Failed to refresh message {}: {:?}", message_id, e);
                 }
@@ -128,16 +205,27 @@ fn should_refresh(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::{RepositoryError, SearchError};
     use crate::notifier::MockMessageNotifier;
-    use crate::repository::{MockMessageRepository, MockUserRepository};
+    use crate::repository::{
+        MockMessageEventRepository, MockMessageReader, MockMessageWriter, MockUserRepository,
+    };
+    use crate::search::MockSearchIndex;
     use crate::test_factories::{MessageBuilder, ReactionBuilder, RepositoryBuilder};
     use crate::traq_client::MockTraqClient;
     use fake::{Fake, uuid::UUIDv4};
     use mockall::predicate;
 
+    fn accepting_event_repo() -> MockMessageEventRepository {
+        let mut mock_event_repo = MockMessageEventRepository::new();
+        mock_event_repo.expect_append().returning(|_, _| Ok(vec![]));
+        mock_event_repo
+    }
+
     #[tokio::test]
     async fn crawl_success_with_existing_messages() {
-        let mut mock_message_repo = MockMessageRepository::new();
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
         let mut mock_user_repo = MockUserRepository::new();
         let mut mock_client = MockTraqClient::new();
 
@@ -146,7 +234,7 @@ mod tests {
         let messages = vec![MessageBuilder::new().build()];
 
         // 1. Get latest message time
-        mock_message_repo
+        mock_message_reader
             .expect_find_latest_message_time()
             .times(1)
             .returning(move || Ok(Some(latest_message_time)));
@@ -168,13 +256,13 @@ mod tests {
             .returning(move |_, _| Ok(messages.clone()));
 
         // 4. Save messages to repository
-        mock_message_repo
+        mock_message_writer
             .expect_save_batch()
             .times(1)
             .returning(|_| Ok(()));
 
         // 5. Find sync candidates (for refresh)
-        mock_message_repo
+        mock_message_reader
             .expect_find_sync_candidates()
             .times(1)
             .returning(|| Ok(vec![]));
@@ -182,7 +270,9 @@ mod tests {
         // Notifier should NOT be called since there are no messages to refresh
         let mock_notifier = MockMessageNotifier::new();
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(accepting_event_repo())
             .user(mock_user_repo)
             .build();
 
@@ -192,14 +282,65 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn crawl_indexes_fetched_messages() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_client = MockTraqClient::new();
+        let mut mock_search_index = MockSearchIndex::new();
+        let messages = vec![MessageBuilder::new().build()];
+        let messages_clone = messages.clone();
+
+        mock_message_reader
+            .expect_find_latest_message_time()
+            .returning(|| Ok(None));
+        mock_user_repo
+            .expect_find_random_valid_token()
+            .returning(|| Ok(Some("test_token".to_string())));
+        mock_client
+            .expect_fetch_messages_since()
+            .returning(move |_, _| Ok(messages_clone.clone()));
+        mock_message_writer
+            .expect_save_batch()
+            .times(1)
+            .returning(|_| Ok(()));
+        mock_message_reader
+            .expect_find_sync_candidates()
+            .returning(|| Ok(vec![]));
+        // Indexing failures do not fail the crawl
+        mock_search_index
+            .expect_index_messages()
+            .with(predicate::eq(messages))
+            .times(1)
+            .returning(|_| Err(SearchError::Request("connection refused".to_string())));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(accepting_event_repo())
+            .user(mock_user_repo)
+            .build();
+        let crawler = MessageCrawler::new(
+            Arc::new(mock_client),
+            repo,
+            Arc::new(MockMessageNotifier::new()),
+        )
+        .with_search_index(Arc::new(mock_search_index));
+        let result = crawler.crawl().await;
+
+        assert!(result.is_ok());
+    }
+
     #[tokio::test]
     async fn crawl_success_no_previous_messages_fallback() {
-        let mut mock_message_repo = MockMessageRepository::new();
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
         let mut mock_user_repo = MockUserRepository::new();
         let mut mock_client = MockTraqClient::new();
 
         // 1. No latest message (returns None)
-        mock_message_repo
+        mock_message_reader
             .expect_find_latest_message_time()
             .times(1)
             .returning(move || Ok(None));
@@ -218,19 +359,21 @@ mod tests {
             .returning(move |_, _| Ok(vec![]));
 
         // 4. Save batch (empty)
-        mock_message_repo
+        mock_message_writer
             .expect_save_batch()
             .times(1)
             .returning(|_| Ok(()));
 
         // 5. Find sync candidates
-        mock_message_repo
+        mock_message_reader
             .expect_find_sync_candidates()
             .times(1)
             .returning(|| Ok(vec![]));
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(accepting_event_repo())
             .user(mock_user_repo)
             .build();
 
@@ -244,10 +387,10 @@ mod tests {
 
     #[tokio::test]
     async fn crawl_skips_when_no_token() {
-        let mut mock_message_repo = MockMessageRepository::new();
+        let mut mock_message_reader = MockMessageReader::new();
         let mut mock_user_repo = MockUserRepository::new();
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_latest_message_time()
             .returning(|| Ok(None));
 
@@ -257,7 +400,8 @@ mod tests {
             .returning(|| Ok(None));
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
+            .message_reader(mock_message_reader)
+            .message_event(accepting_event_repo())
             .user(mock_user_repo)
             .build();
 
@@ -276,7 +420,8 @@ mod tests {
 
     #[tokio::test]
     async fn crawl_refreshes_messages_needing_update() {
-        let mut mock_message_repo = MockMessageRepository::new();
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
         let mut mock_user_repo = MockUserRepository::new();
         let mut mock_client = MockTraqClient::new();
 
@@ -285,7 +430,7 @@ mod tests {
         let created_at = now - Duration::minutes(30);
         let last_crawled_at = now - Duration::minutes(2);
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_latest_message_time()
             .returning(move || Ok(Some(now)));
 
@@ -297,9 +442,11 @@ mod tests {
             .expect_fetch_messages_since()
             .returning(|_, _| Ok(vec![]));
 
-        mock_message_repo.expect_save_batch().returning(|_| Ok(()));
+        mock_message_writer
+            .expect_save_batch()
+            .returning(|_| Ok(()));
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_sync_candidates()
             .times(1)
             .returning(move || Ok(vec![(message_id, created_at, last_crawled_at)]));
@@ -308,7 +455,7 @@ mod tests {
         let refreshed_message = existing_message.clone();
 
         // Expect find_by_id to return existing message
-        mock_message_repo
+        mock_message_reader
             .expect_find_by_id()
             .times(1)
             .returning(move |_| Ok(Some(existing_message.clone())));
@@ -318,13 +465,15 @@ mod tests {
             .times(1)
             .returning(move |_, _| Ok(refreshed_message.clone()));
 
-        mock_message_repo
+        mock_message_writer
             .expect_save()
             .times(1)
             .returning(|_| Ok(()));
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(accepting_event_repo())
             .user(mock_user_repo)
             .build();
 
@@ -339,7 +488,8 @@ mod tests {
 
     #[tokio::test]
     async fn crawl_notifies_when_message_content_changed() {
-        let mut mock_message_repo = MockMessageRepository::new();
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
         let mut mock_user_repo = MockUserRepository::new();
         let mut mock_client = MockTraqClient::new();
 
@@ -348,7 +498,7 @@ mod tests {
         let created_at = now - Duration::minutes(30);
         let last_crawled_at = now - Duration::minutes(2);
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_latest_message_time()
             .returning(move || Ok(Some(now)));
 
@@ -360,9 +510,11 @@ mod tests {
             .expect_fetch_messages_since()
             .returning(|_, _| Ok(vec![]));
 
-        mock_message_repo.expect_save_batch().returning(|_| Ok(()));
+        mock_message_writer
+            .expect_save_batch()
+            .returning(|_| Ok(()));
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_sync_candidates()
             .times(1)
             .returning(move || Ok(vec![(message_id, created_at, last_crawled_at)]));
@@ -376,7 +528,7 @@ mod tests {
             .content("new content".to_string())
             .build();
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_by_id()
             .times(1)
             .returning(move |_| Ok(Some(existing_message.clone())));
@@ -386,21 +538,26 @@ mod tests {
             .times(1)
             .returning(move |_, _| Ok(refreshed_message.clone()));
 
-        mock_message_repo
+        mock_message_writer
             .expect_save()
             .times(1)
             .returning(|_| Ok(()));
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(accepting_event_repo())
             .user(mock_user_repo)
             .build();
 
         let mut mock_notifier = MockMessageNotifier::new();
         mock_notifier
             .expect_notify_message_updated()
+            .withf(|_, delta| {
+                delta.content.as_deref() == Some("new content") && delta.reactions.is_none()
+            })
             .times(1)
-            .returning(|_| ());
+            .returning(|_, _| ());
 
         let crawler = MessageCrawler::new(Arc::new(mock_client), repo, Arc::new(mock_notifier));
         let result = crawler.crawl().await;
@@ -410,7 +567,8 @@ mod tests {
 
     #[tokio::test]
     async fn crawl_notifies_when_reactions_changed() {
-        let mut mock_message_repo = MockMessageRepository::new();
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
         let mut mock_user_repo = MockUserRepository::new();
         let mut mock_client = MockTraqClient::new();
 
@@ -419,7 +577,7 @@ mod tests {
         let created_at = now - Duration::minutes(30);
         let last_crawled_at = now - Duration::minutes(2);
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_latest_message_time()
             .returning(move || Ok(Some(now)));
 
@@ -431,9 +589,11 @@ mod tests {
             .expect_fetch_messages_since()
             .returning(|_, _| Ok(vec![]));
 
-        mock_message_repo.expect_save_batch().returning(|_| Ok(()));
+        mock_message_writer
+            .expect_save_batch()
+            .returning(|_| Ok(()));
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_sync_candidates()
             .times(1)
             .returning(move || Ok(vec![(message_id, created_at, last_crawled_at)]));
@@ -450,7 +610,7 @@ mod tests {
             .reactions(vec![reaction1, reaction2])
             .build();
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_by_id()
             .times(1)
             .returning(move |_| Ok(Some(existing_message.clone())));
@@ -460,13 +620,15 @@ mod tests {
             .times(1)
             .returning(move |_, _| Ok(refreshed_message.clone()));
 
-        mock_message_repo
+        mock_message_writer
             .expect_save()
             .times(1)
             .returning(|_| Ok(()));
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(accepting_event_repo())
             .user(mock_user_repo)
             .build();
 
@@ -474,7 +636,7 @@ mod tests {
         mock_notifier
             .expect_notify_message_updated()
             .times(1)
-            .returning(|_| ());
+            .returning(|_, _| ());
 
         let crawler = MessageCrawler::new(Arc::new(mock_client), repo, Arc::new(mock_notifier));
         let result = crawler.crawl().await;
@@ -484,7 +646,8 @@ mod tests {
 
     #[tokio::test]
     async fn crawl_skips_messages_not_needing_refresh() {
-        let mut mock_message_repo = MockMessageRepository::new();
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
         let mut mock_user_repo = MockUserRepository::new();
         let mut mock_client = MockTraqClient::new();
 
@@ -493,7 +656,7 @@ mod tests {
         let created_at = now - Duration::minutes(30);
         let last_crawled_at = now - Duration::seconds(30);
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_latest_message_time()
             .returning(move || Ok(Some(now)));
 
@@ -505,15 +668,19 @@ mod tests {
             .expect_fetch_messages_since()
             .returning(|_, _| Ok(vec![]));
 
-        mock_message_repo.expect_save_batch().returning(|_| Ok(()));
+        mock_message_writer
+            .expect_save_batch()
+            .returning(|_| Ok(()));
 
-        mock_message_repo
+        mock_message_reader
             .expect_find_sync_candidates()
             .times(1)
             .returning(move || Ok(vec![(message_id, created_at, last_crawled_at)]));
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(accepting_event_repo())
             .user(mock_user_repo)
             .build();
 
@@ -525,6 +692,113 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn crawl_records_messages_removed_from_traq() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_client = MockTraqClient::new();
+        let mut mock_event_repo = MockMessageEventRepository::new();
+
+        let now = OffsetDateTime::now_utc();
+        let message_id: Uuid = UUIDv4.fake();
+        let created_at = now - Duration::minutes(30);
+        let last_crawled_at = now - Duration::minutes(2);
+
+        mock_message_reader
+            .expect_find_latest_message_time()
+            .returning(move || Ok(Some(now)));
+        mock_user_repo
+            .expect_find_random_valid_token()
+            .returning(|| Ok(Some("test_token".to_string())));
+        mock_client
+            .expect_fetch_messages_since()
+            .returning(|_, _| Ok(vec![]));
+        mock_message_writer
+            .expect_save_batch()
+            .returning(|_| Ok(()));
+        mock_message_reader
+            .expect_find_sync_candidates()
+            .returning(move || Ok(vec![(message_id, created_at, last_crawled_at)]));
+        mock_client.expect_get_message().returning(|_, _| {
+            Err(TraqClientError::ApiError {
+                status: StatusCode::NOT_FOUND,
+                message: "not found".to_string(),
+            })
+        });
+
+        mock_event_repo
+            .expect_append()
+            .withf(|ids, kind| ids.is_empty() && *kind != MessageEventKind::Removed)
+            .returning(|_, _| Ok(vec![]));
+        mock_event_repo
+            .expect_append()
+            .withf(move |ids, kind| ids == [message_id] && *kind == MessageEventKind::Removed)
+            .times(1)
+            .returning(|_, _| Ok(vec![]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(mock_event_repo)
+            .user(mock_user_repo)
+            .build();
+
+        let crawler = MessageCrawler::new(
+            Arc::new(mock_client),
+            repo,
+            Arc::new(MockMessageNotifier::new()),
+        );
+        let result = crawler.crawl().await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn crawl_keeps_fetched_messages_when_save_fails() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_client = MockTraqClient::new();
+
+        let message = MessageBuilder::new().build();
+        let message_id = message.id;
+
+        mock_message_reader
+            .expect_find_latest_message_time()
+            .returning(|| Ok(None));
+        mock_user_repo
+            .expect_find_random_valid_token()
+            .returning(|| Ok(Some("test_token".to_string())));
+        mock_client
+            .expect_fetch_messages_since()
+            .returning(move |_, _| Ok(vec![message.clone()]));
+        mock_message_writer
+            .expect_save_batch()
+            .returning(|_| Err(RepositoryError::Database("connection lost".to_string())));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(accepting_event_repo())
+            .user(mock_user_repo)
+            .build();
+        let recent_messages = Arc::new(RecentMessages::new(10));
+
+        let crawler = MessageCrawler::new(
+            Arc::new(mock_client),
+            repo,
+            Arc::new(MockMessageNotifier::new()),
+        )
+        .with_recent_messages(recent_messages.clone());
+        let result = crawler.crawl().await;
+
+        assert!(result.is_err());
+        let latest = recent_messages.latest(10);
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].id, message_id);
+    }
+
     #[test]
     fn should_refresh_recent_message_within_interval() {
         let now = OffsetDateTime::now_utc();
@@ -0,0 +1,173 @@
+use crate::{
+    error::DomainError, model::MessageListItem, repository::Repository, traq_client::TraqClient,
+};
+use std::{fmt::Write, sync::Arc};
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+/// The number of messages linked from a summary.
+const SUMMARY_SIZE: i64 = 5;
+
+/// The number of latest messages of the channel searched for a summary already posted today.
+const RECENT_MESSAGES_CHECKED: i32 = 20;
+
+/// Posts the messages trending on Twittra over the past day to a traQ channel, linking them so
+/// that traQ shows them as quotes. It is meant to be run periodically by a job scheduler, and posts
+/// once per day (UTC) however often it runs.
+pub struct DailySummary {
+    client: Arc<dyn TraqClient>,
+    repo: Repository,
+    channel_id: Uuid,
+    token: String,
+    web_base_url: String,
+}
+
+impl DailySummary {
+    /// Posts to `channel_id` with `token`, which should belong to a bot rather than a user.
+    pub fn new(
+        client: Arc<dyn TraqClient>,
+        repo: Repository,
+        channel_id: Uuid,
+        token: String,
+        web_base_url: String,
+    ) -> Self {
+        Self {
+            client,
+            repo,
+            channel_id,
+            token,
+            web_base_url,
+        }
+    }
+
+    /// Returns whether a summary was posted. Nothing is posted if today's summary is among the
+    /// latest messages of the channel, or no message was reacted to.
+    pub async fn post(&self) -> Result<bool, DomainError> {
+        let title = title(OffsetDateTime::now_utc().date());
+        // The summary is looked up on traQ rather than remembered, so that restarts don't post it
+        // again
+        let latest = self
+            .client
+            .get_channel_messages(&self.token, &self.channel_id, None, RECENT_MESSAGES_CHECKED)
+            .await?;
+        if latest.iter().any(|m| m.content.starts_with(&title)) {
+            return Ok(false);
+        }
+
+        // Trending for nobody in particular, so that authors who opted out of being recommended
+        // to strangers are left out
+        let messages = self
+            .repo
+            .message_reader
+            .find_trending_messages(&Uuid::nil(), 24, SUMMARY_SIZE)
+            .await?;
+        if messages.is_empty() {
+            return Ok(false);
+        }
+
+        let content = self.content(&title, &messages);
+        self.client
+            .post_message(&self.token, &self.channel_id, &content)
+            .await?;
+
+        Ok(true)
+    }
+
+    fn content(&self, title: &str, messages: &[MessageListItem]) -> String {
+        let base_url = self.web_base_url.trim_end_matches('/');
+        let mut content = format!("{title}\nThe most reacted messages of the past day\n");
+        for message in messages {
+            let _ = write!(
+                content,
+                "\n{base_url}/messages/{} ({} reactions)",
+                message.id,
+                message.reactions.len()
+            );
+        }
+        content
+    }
+}
+
+fn title(date: Date) -> String {
+    format!("### Twittra highlights {date}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::Message,
+        repository::MockMessageReader,
+        test_factories::{MessageListItemBuilder, RepositoryBuilder},
+        traq_client::MockTraqClient,
+    };
+    use mockall::predicate;
+
+    fn summary(client: MockTraqClient, reader: MockMessageReader) -> DailySummary {
+        DailySummary::new(
+            Arc::new(client),
+            RepositoryBuilder::new().message_reader(reader).build(),
+            Uuid::nil(),
+            "bot_token".to_string(),
+            "https://q.example.com/".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn post_links_trending_messages() {
+        let message = MessageListItemBuilder::new().build();
+        let expected = format!(
+            "{}\nThe most reacted messages of the past day\n\nhttps://q.example.com/messages/{} (0 reactions)",
+            title(OffsetDateTime::now_utc().date()),
+            message.id
+        );
+
+        let mut client = MockTraqClient::new();
+        client
+            .expect_get_channel_messages()
+            .returning(|_, _, _, _| Ok(vec![]));
+        client
+            .expect_post_message()
+            .with(
+                predicate::eq("bot_token"),
+                predicate::eq(Uuid::nil()),
+                predicate::eq(expected),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let mut reader = MockMessageReader::new();
+        reader
+            .expect_find_trending_messages()
+            .with(
+                predicate::eq(Uuid::nil()),
+                predicate::eq(24),
+                predicate::eq(SUMMARY_SIZE),
+            )
+            .returning(move |_, _, _| Ok(vec![message.clone()]));
+
+        assert!(summary(client, reader).post().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn post_skips_when_posted_today() {
+        let posted = Message {
+            id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            channel_id: Uuid::nil(),
+            content: format!("{}\n...", title(OffsetDateTime::now_utc().date())),
+            created_at: OffsetDateTime::now_utc(),
+            updated_at: OffsetDateTime::now_utc(),
+            reactions: vec![],
+        };
+
+        let mut client = MockTraqClient::new();
+        client
+            .expect_get_channel_messages()
+            .returning(move |_, _, _, _| Ok(vec![posted.clone()]));
+        client.expect_post_message().never();
+        let mut reader = MockMessageReader::new();
+        reader.expect_find_trending_messages().never();
+
+        assert!(!summary(client, reader).post().await.unwrap());
+    }
+}
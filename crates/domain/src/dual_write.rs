@@ -0,0 +1,996 @@
+//! Dual-write mode for moving to another database or schema.
+//! Every write goes to both the current (primary) and the new (secondary) backend, and reads are
+//! compared so that the new backend can be verified against real traffic before switching to it.
+
+use crate::{
+    error::RepositoryError,
+    id::{ChannelId, MessageId, StampId, UserId},
+    model::{
+        AffinityScore, Announcement, CachedImage, Channel, ChannelActivity, ChannelScoreOverride,
+        EngagementMetrics, HiddenMessage, Highlight, IgnoredRecommendations, ImageKind, ImageSize,
+        Impression, JobRun, LinkPreview, Message, MessageCursor, MessageEmbedding, MessageEvent,
+        MessageEventKind, MessageListItem, OnboardingState, OnboardingStep, PrivacySettings,
+        QuotaOperation, ReportReason, ReportedMessage, SavedSearch, Stamp, TrendingTag, User,
+        UserStats, Webhook, WebhookEvent,
+    },
+    repository::{
+        AnnouncementRepository, BlockRepository, BookmarkRepository, ChannelRepository,
+        EmbeddingRepository, FeedbackRepository, FollowRepository, ImageCacheRepository,
+        ImpressionRepository, JobRunRepository, LinkPreviewRepository, MessageEventRepository,
+        MessageReader, MessageWriter, MuteRepository, PresenceRepository, QuotaRepository,
+        ReportRepository, Repository, SavedSearchRepository, StampRepository, UserRepository,
+        UserSettingsRepository, WebhookRepository,
+    },
+};
+use std::{future::Future, sync::Arc};
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+/// Wraps every repository of `primary` so that it also writes to and reads from `secondary`.
+pub fn dual_write(primary: Repository, secondary: Repository) -> Repository {
+    Repository {
+        announcement: Arc::new(DualWrite::new(
+            "announcement",
+            primary.announcement,
+            secondary.announcement,
+        )),
+        block: Arc::new(DualWrite::new("block", primary.block, secondary.block)),
+        bookmark: Arc::new(DualWrite::new(
+            "bookmark",
+            primary.bookmark,
+            secondary.bookmark,
+        )),
+        channel: Arc::new(DualWrite::new(
+            "channel",
+            primary.channel,
+            secondary.channel,
+        )),
+        embedding: Arc::new(DualWrite::new(
+            "embedding",
+            primary.embedding,
+            secondary.embedding,
+        )),
+        feedback: Arc::new(DualWrite::new(
+            "feedback",
+            primary.feedback,
+            secondary.feedback,
+        )),
+        follow: Arc::new(DualWrite::new("follow", primary.follow, secondary.follow)),
+        image_cache: Arc::new(DualWrite::new(
+            "image_cache",
+            primary.image_cache,
+            secondary.image_cache,
+        )),
+        impression: Arc::new(DualWrite::new(
+            "impression",
+            primary.impression,
+            secondary.impression,
+        )),
+        job_run: Arc::new(DualWrite::new(
+            "job_run",
+            primary.job_run,
+            secondary.job_run,
+        )),
+        link_preview: Arc::new(DualWrite::new(
+            "link_preview",
+            primary.link_preview,
+            secondary.link_preview,
+        )),
+        message_reader: Arc::new(DualWrite::new(
+            "message_reader",
+            primary.message_reader,
+            secondary.message_reader,
+        )),
+        message_writer: Arc::new(DualWrite::new(
+            "message_writer",
+            primary.message_writer,
+            secondary.message_writer,
+        )),
+        message_event: Arc::new(DualWrite::new(
+            "message_event",
+            primary.message_event,
+            secondary.message_event,
+        )),
+        mute: Arc::new(DualWrite::new("mute", primary.mute, secondary.mute)),
+        presence: Arc::new(DualWrite::new(
+            "presence",
+            primary.presence,
+            secondary.presence,
+        )),
+        quota: Arc::new(DualWrite::new("quota", primary.quota, secondary.quota)),
+        report: Arc::new(DualWrite::new("report", primary.report, secondary.report)),
+        saved_search: Arc::new(DualWrite::new(
+            "saved_search",
+            primary.saved_search,
+            secondary.saved_search,
+        )),
+        stamp: Arc::new(DualWrite::new("stamp", primary.stamp, secondary.stamp)),
+        user: Arc::new(DualWrite::new("user", primary.user, secondary.user)),
+        user_settings: Arc::new(DualWrite::new(
+            "user_settings",
+            primary.user_settings,
+            secondary.user_settings,
+        )),
+        webhook: Arc::new(DualWrite::new(
+            "webhook",
+            primary.webhook,
+            secondary.webhook,
+        )),
+    }
+}
+
+/// A repository forwarding every call to a primary and a secondary backend.
+/// Only the primary serves results. Failures and mismatches of the secondary are logged, so that
+/// the secondary never breaks the app.
+#[derive(Debug)]
+pub struct DualWrite<T: ?Sized> {
+    name: &'static str,
+    primary: Arc<T>,
+    secondary: Arc<T>,
+}
+
+impl<T: ?Sized> DualWrite<T> {
+    pub fn new(name: &'static str, primary: Arc<T>, secondary: Arc<T>) -> Self {
+        Self {
+            name,
+            primary,
+            secondary,
+        }
+    }
+
+    /// Writes to the secondary only if the primary write succeeded, so that the secondary doesn't
+    /// get data the primary doesn't have.
+    async fn write<R>(
+        &self,
+        method: &str,
+        primary: impl Future<Output = Result<R, RepositoryError>>,
+        secondary: impl Future<Output = Result<R, RepositoryError>>,
+    ) -> Result<R, RepositoryError> {
+        let result = primary.await?;
+        self.copy(method, secondary).await;
+
+        Ok(result)
+    }
+
+    /// Makes the secondary write following a successful primary write, e.g. one storing a record
+    /// with the ID the primary generated for it.
+    async fn copy<R>(
+        &self,
+        method: &str,
+        secondary: impl Future<Output = Result<R, RepositoryError>>,
+    ) {
+        if let Err(e) = secondary.await {
+            tracing::warn!("Secondary {}::{} failed: {:?}", self.name, method, e);
+        }
+    }
+
+    /// Reads from both backends concurrently and returns the result of the primary.
+    async fn read<R: PartialEq>(
+        &self,
+        method: &str,
+        primary: impl Future<Output = Result<R, RepositoryError>>,
+        secondary: impl Future<Output = Result<R, RepositoryError>>,
+    ) -> Result<R, RepositoryError> {
+        self.compare(method, primary, secondary, PartialEq::eq)
+            .await
+    }
+
+    /// Like [`Self::read`], for results in no particular order.
+    async fn read_unordered<T: PartialEq>(
+        &self,
+        method: &str,
+        primary: impl Future<Output = Result<Vec<T>, RepositoryError>>,
+        secondary: impl Future<Output = Result<Vec<T>, RepositoryError>>,
+    ) -> Result<Vec<T>, RepositoryError> {
+        self.compare(method, primary, secondary, |expected, actual| {
+            same_elements(expected, actual)
+        })
+        .await
+    }
+
+    /// Mismatches are logged without the results, as they may contain private messages.
+    async fn compare<R>(
+        &self,
+        method: &str,
+        primary: impl Future<Output = Result<R, RepositoryError>>,
+        secondary: impl Future<Output = Result<R, RepositoryError>>,
+        matches: impl Fn(&R, &R) -> bool,
+    ) -> Result<R, RepositoryError> {
+        let (primary, secondary) = tokio::join!(primary, secondary);
+
+        match (&primary, secondary) {
+            (Ok(expected), Ok(actual)) if !matches(expected, &actual) => {
+                tracing::warn!(
+                    "Secondary {}::{} returned a different result",
+                    self.name,
+                    method
+                );
+            }
+            (Ok(_), Err(e)) => {
+                tracing::warn!("Secondary {}::{} failed: {:?}", self.name, method, e);
+            }
+            _ => {}
+        }
+
+        primary
+    }
+}
+
+/// Whether the lists have the same elements, in any order.
+fn same_elements<T: PartialEq>(expected: &[T], actual: &[T]) -> bool {
+    let mut unmatched: Vec<&T> = actual.iter().collect();
+
+    expected.len() == actual.len()
+        && expected
+            .iter()
+            .all(|e| match unmatched.iter().position(|a| *a == e) {
+                Some(i) => {
+                    unmatched.swap_remove(i);
+                    true
+                }
+                None => false,
+            })
+}
+
+/// Implements a repository trait for [`DualWrite`], passing each listed method to both backends
+/// through the [`DualWrite`] method named before it. Methods that can't simply be passed are
+/// written out after the list.
+macro_rules! dual_write {
+    (
+        impl $trait:ident {
+            $(
+                $kind:ident fn $method:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) -> $ret:ty;
+            )*
+        }
+        $($custom:tt)*
+    ) => {
+        #[async_trait::async_trait]
+        impl $trait for DualWrite<dyn $trait> {
+            $(
+                async fn $method(&self $(, $arg: $ty)*) -> $ret {
+                    self.$kind(
+                        stringify!($method),
+                        self.primary.$method($($arg),*),
+                        self.secondary.$method($($arg),*),
+                    )
+                    .await
+                }
+            )*
+
+            $($custom)*
+        }
+    };
+}
+
+dual_write! {
+    impl AnnouncementRepository {
+        write fn import(&self, announcement: &Announcement) -> Result<(), RepositoryError>;
+        write fn withdraw(&self) -> Result<bool, RepositoryError>;
+        read fn find_undismissed(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Option<Announcement>, RepositoryError>;
+        write fn dismiss(
+            &self,
+            user_id: &Uuid,
+            announcement_id: i64,
+        ) -> Result<(), RepositoryError>;
+    }
+
+    async fn publish(&self, content: &str) -> Result<Announcement, RepositoryError> {
+        let announcement = self.primary.publish(content).await?;
+        self.copy("publish", self.secondary.import(&announcement))
+            .await;
+        Ok(announcement)
+    }
+}
+
+dual_write! {
+    impl BlockRepository {
+        write fn block_user(
+            &self,
+            user_id: &UserId,
+            blocked_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        write fn unblock_user(
+            &self,
+            user_id: &UserId,
+            blocked_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        read_unordered fn find_blocked_or_blocking_user_ids(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl BookmarkRepository {
+        write fn add(
+            &self,
+            user_id: &UserId,
+            message_id: &MessageId,
+        ) -> Result<(), RepositoryError>;
+        write fn remove(
+            &self,
+            user_id: &UserId,
+            message_id: &MessageId,
+        ) -> Result<(), RepositoryError>;
+        read fn find_bookmarked_messages(
+            &self,
+            user_id: &UserId,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl ChannelRepository {
+        read fn find_by_id(&self, id: &Uuid) -> Result<Option<Channel>, RepositoryError>;
+        read fn find_all(&self) -> Result<Vec<Channel>, RepositoryError>;
+        write fn save_batch(&self, channels: &[Channel]) -> Result<(), RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl EmbeddingRepository {
+        read fn find_messages_without_embedding(
+            &self,
+            limit: i64,
+        ) -> Result<Vec<(Uuid, String)>, RepositoryError>;
+        write fn save_embeddings(
+            &self,
+            embeddings: &[MessageEmbedding],
+        ) -> Result<(), RepositoryError>;
+        read fn find_stamped_message_embeddings(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageEmbedding>, RepositoryError>;
+        read fn find_candidate_embeddings(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageEmbedding>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl FeedbackRepository {
+        write fn hide_message(
+            &self,
+            user_id: &Uuid,
+            message: &Message,
+        ) -> Result<(), RepositoryError>;
+        read_unordered fn find_hidden_messages(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<HiddenMessage>, RepositoryError>;
+        read_unordered fn find_ignored_recommendations(
+            &self,
+            user_id: &Uuid,
+            since: OffsetDateTime,
+            until: OffsetDateTime,
+        ) -> Result<Vec<IgnoredRecommendations>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl FollowRepository {
+        write fn follow_user(
+            &self,
+            user_id: &UserId,
+            followed_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        write fn unfollow_user(
+            &self,
+            user_id: &UserId,
+            followed_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        read fn find_followed_messages(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl ImageCacheRepository {
+        read fn find(
+            &self,
+            kind: ImageKind,
+            id: &Uuid,
+            size: ImageSize,
+        ) -> Result<Option<CachedImage>, RepositoryError>;
+        write fn save(
+            &self,
+            kind: ImageKind,
+            id: &Uuid,
+            size: ImageSize,
+            image: &[u8],
+            content_type: &str,
+        ) -> Result<(), RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl ImpressionRepository {
+        write fn record_impressions(
+            &self,
+            user_id: &Uuid,
+            impressions: &[Impression],
+        ) -> Result<(), RepositoryError>;
+        read_unordered fn compute_engagement_metrics(
+            &self,
+            since: OffsetDateTime,
+            until: OffsetDateTime,
+        ) -> Result<Vec<EngagementMetrics>, RepositoryError>;
+        write fn save_engagement_metrics(
+            &self,
+            date: Date,
+            metrics: &[EngagementMetrics],
+        ) -> Result<(), RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl JobRunRepository {
+        write fn import(&self, id: u64, run: &JobRun) -> Result<(), RepositoryError>;
+        read fn find_recent(&self, limit: i64) -> Result<Vec<JobRun>, RepositoryError>;
+        write fn delete_started_before(
+            &self,
+            before: OffsetDateTime,
+        ) -> Result<(), RepositoryError>;
+    }
+
+    async fn save(&self, run: &JobRun) -> Result<u64, RepositoryError> {
+        let id = self.primary.save(run).await?;
+        self.copy("save", self.secondary.import(id, run)).await;
+        Ok(id)
+    }
+}
+
+dual_write! {
+    impl LinkPreviewRepository {
+        read_unordered fn find_fetched_since(
+            &self,
+            urls: &[String],
+            since: OffsetDateTime,
+        ) -> Result<Vec<LinkPreview>, RepositoryError>;
+        write fn save(&self, preview: &LinkPreview) -> Result<(), RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl MessageReader {
+        read fn find_latest_message_time(&self) -> Result<Option<OffsetDateTime>, RepositoryError>;
+        read fn find_by_id(&self, id: &Uuid) -> Result<Option<Message>, RepositoryError>;
+        read_unordered fn find_list_items_by_ids(
+            &self,
+            ids: &[Uuid],
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read_unordered fn find_sync_candidates(
+            &self,
+        ) -> Result<Vec<(Uuid, OffsetDateTime, OffsetDateTime)>, RepositoryError>;
+        read_unordered fn find_read_message_ids(
+            &self,
+            user_id: &Uuid,
+            message_ids: &[Uuid],
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+        read_unordered fn find_read_message_ids_by_users(
+            &self,
+            user_ids: &[Uuid],
+            message_ids: &[Uuid],
+        ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError>;
+        read fn find_top_reacted_messages(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_messages_by_author_allowlist(
+            &self,
+            author_ids: &[Uuid],
+            limit: i64,
+            user_id: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_messages_by_channel_allowlist(
+            &self,
+            channel_ids: &[Uuid],
+            limit: i64,
+            user_id: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_trending_messages(
+            &self,
+            user_id: &Uuid,
+            window_hours: i64,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_highlights(
+            &self,
+            since: OffsetDateTime,
+            limit: i64,
+        ) -> Result<Vec<Highlight>, RepositoryError>;
+        read fn find_most_active_channels(
+            &self,
+            limit: i64,
+        ) -> Result<Vec<ChannelActivity>, RepositoryError>;
+        read fn find_recent_messages_across_channels(
+            &self,
+            user_id: &Uuid,
+            per_channel: i64,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn search(
+            &self,
+            query: &str,
+            limit: i64,
+            viewer: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_by_tag(
+            &self,
+            tag: &str,
+            limit: i64,
+            viewer: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_by_author(
+            &self,
+            author_id: &Uuid,
+            before: Option<MessageCursor>,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_by_channel(
+            &self,
+            channel_id: &Uuid,
+            before: Option<MessageCursor>,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_public_messages(
+            &self,
+            channel_ids: &[Uuid],
+            before: Option<MessageCursor>,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_user_stats(
+            &self,
+            user_id: &Uuid,
+            since: OffsetDateTime,
+        ) -> Result<UserStats, RepositoryError>;
+        read fn find_co_reacted_messages(
+            &self,
+            message_id: &Uuid,
+            limit: i64,
+            viewer: &Uuid,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_thread(
+            &self,
+            message_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<MessageListItem>, RepositoryError>;
+        read fn find_trending_tags(
+            &self,
+            window_hours: i64,
+            limit: i64,
+        ) -> Result<Vec<TrendingTag>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl MessageWriter {
+        write fn remove_reaction(
+            &self,
+            message_id: &MessageId,
+            stamp_id: &StampId,
+            user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        write fn save(&self, message: &Message) -> Result<(), RepositoryError>;
+        write fn save_batch(&self, messages: &[Message]) -> Result<(), RepositoryError>;
+        write fn mark_messages_as_read(
+            &self,
+            user_id: &UserId,
+            message_ids: &[MessageId],
+        ) -> Result<(), RepositoryError>;
+        write fn roll_up_read_messages(
+            &self,
+            before: OffsetDateTime,
+        ) -> Result<u64, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl MessageEventRepository {
+        write fn import(&self, events: &[MessageEvent]) -> Result<(), RepositoryError>;
+        read fn find_since(
+            &self,
+            cursor: i64,
+            limit: i64,
+        ) -> Result<Vec<MessageEvent>, RepositoryError>;
+        read fn find_latest_cursor(&self) -> Result<i64, RepositoryError>;
+    }
+
+    async fn append(
+        &self,
+        message_ids: &[Uuid],
+        kind: MessageEventKind,
+    ) -> Result<Vec<MessageEvent>, RepositoryError> {
+        let events = self.primary.append(message_ids, kind).await?;
+        self.copy("append", self.secondary.import(&events)).await;
+        Ok(events)
+    }
+}
+
+dual_write! {
+    impl MuteRepository {
+        write fn mute_user(
+            &self,
+            user_id: &UserId,
+            muted_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        write fn unmute_user(
+            &self,
+            user_id: &UserId,
+            muted_user_id: &UserId,
+        ) -> Result<(), RepositoryError>;
+        read_unordered fn find_muted_user_ids(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+        write fn mute_channel(
+            &self,
+            user_id: &UserId,
+            channel_id: &ChannelId,
+        ) -> Result<(), RepositoryError>;
+        write fn unmute_channel(
+            &self,
+            user_id: &UserId,
+            channel_id: &ChannelId,
+        ) -> Result<(), RepositoryError>;
+        read_unordered fn find_muted_channel_ids(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl PresenceRepository {
+        write fn save_heartbeat(
+            &self,
+            session_id: &str,
+            user_id: &Uuid,
+            seen_at: OffsetDateTime,
+        ) -> Result<(), RepositoryError>;
+        read fn is_seen_since(
+            &self,
+            user_id: &Uuid,
+            since: OffsetDateTime,
+        ) -> Result<bool, RepositoryError>;
+        read fn count_users_seen_since(
+            &self,
+            since: OffsetDateTime,
+        ) -> Result<i64, RepositoryError>;
+        write fn delete_seen_before(&self, before: OffsetDateTime) -> Result<(), RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl QuotaRepository {
+        write fn try_use(
+            &self,
+            user_id: &Uuid,
+            operation: QuotaOperation,
+            day: Date,
+            limit: i64,
+        ) -> Result<bool, RepositoryError>;
+        read_unordered fn find_used(
+            &self,
+            user_id: &Uuid,
+            day: Date,
+        ) -> Result<Vec<(QuotaOperation, i64)>, RepositoryError>;
+        write fn delete_before(&self, before: Date) -> Result<(), RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl ReportRepository {
+        write fn add(
+            &self,
+            user_id: &UserId,
+            message_id: &MessageId,
+            reason: ReportReason,
+        ) -> Result<(), RepositoryError>;
+        read fn find_unresolved(&self, limit: i64) -> Result<Vec<ReportedMessage>, RepositoryError>;
+        write fn resolve(&self, message_id: &MessageId) -> Result<(), RepositoryError>;
+        read_unordered fn find_message_ids_reported_at_least(
+            &self,
+            threshold: i64,
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl SavedSearchRepository {
+        write fn import(&self, user_id: &Uuid, search: &SavedSearch) -> Result<(), RepositoryError>;
+        read fn find_by_user(&self, user_id: &Uuid) -> Result<Vec<SavedSearch>, RepositoryError>;
+        read fn find_by_id(
+            &self,
+            user_id: &Uuid,
+            id: i64,
+        ) -> Result<Option<SavedSearch>, RepositoryError>;
+        write fn delete(&self, user_id: &Uuid, id: i64) -> Result<bool, RepositoryError>;
+    }
+
+    async fn create(
+        &self,
+        user_id: &Uuid,
+        name: &str,
+        query: &str,
+    ) -> Result<SavedSearch, RepositoryError> {
+        let search = self.primary.create(user_id, name, query).await?;
+        self.copy("create", self.secondary.import(user_id, &search))
+            .await;
+        Ok(search)
+    }
+}
+
+dual_write! {
+    impl StampRepository {
+        read fn find_by_id(&self, id: &Uuid) -> Result<Option<Stamp>, RepositoryError>;
+        read_unordered fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Stamp>, RepositoryError>;
+        write fn save(&self, stamp: &Stamp) -> Result<(), RepositoryError>;
+        write fn save_batch(&self, stamps: &[Stamp]) -> Result<(), RepositoryError>;
+        read fn find_frequently_stamped_channels_by(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+            half_life_days: f64,
+        ) -> Result<Vec<AffinityScore>, RepositoryError>;
+        read fn find_frequently_received(
+            &self,
+            author_id: &Uuid,
+            channel_id: &Uuid,
+            since: OffsetDateTime,
+            limit: i64,
+        ) -> Result<Vec<Stamp>, RepositoryError>;
+        write fn record_usage(
+            &self,
+            user_id: &UserId,
+            stamp_id: &StampId,
+        ) -> Result<(), RepositoryError>;
+        read fn find_recently_used_by(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<Stamp>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl UserRepository {
+        read fn find_by_id(&self, id: &Uuid) -> Result<Option<User>, RepositoryError>;
+        read_unordered fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<User>, RepositoryError>;
+        read fn find_token_by_user_id(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Option<String>, RepositoryError>;
+        write fn save(&self, user: &User) -> Result<(), RepositoryError>;
+        write fn save_token(
+            &self,
+            user_id: &Uuid,
+            access_token: &str,
+        ) -> Result<(), RepositoryError>;
+        read fn find_frequently_stamped_users_by(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+            half_life_days: f64,
+        ) -> Result<Vec<AffinityScore>, RepositoryError>;
+        read fn find_similar_users(
+            &self,
+            user_id: &Uuid,
+            limit: i64,
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+        read_unordered fn find_acquaintances(
+            &self,
+            viewer_ids: &[Uuid],
+            user_ids: &[Uuid],
+        ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError>;
+    }
+
+    async fn find_random_valid_token(&self) -> Result<Option<String>, RepositoryError> {
+        // Tokens are picked at random, so the results never match
+        self.primary.find_random_valid_token().await
+    }
+}
+
+dual_write! {
+    impl UserSettingsRepository {
+        read fn find_onboarding_state(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<OnboardingState, RepositoryError>;
+        write fn complete_onboarding_step(
+            &self,
+            user_id: &Uuid,
+            step: OnboardingStep,
+        ) -> Result<(), RepositoryError>;
+        read fn find_channel_interests(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError>;
+        write fn save_channel_interests(
+            &self,
+            user_id: &UserId,
+            channel_ids: &[ChannelId],
+        ) -> Result<(), RepositoryError>;
+        read fn find_channel_score_overrides(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<Vec<ChannelScoreOverride>, RepositoryError>;
+        write fn save_channel_score_override(
+            &self,
+            user_id: &Uuid,
+            score_override: &ChannelScoreOverride,
+        ) -> Result<(), RepositoryError>;
+        write fn delete_channel_score_override(
+            &self,
+            user_id: &UserId,
+            channel_id: &ChannelId,
+        ) -> Result<(), RepositoryError>;
+        read fn find_privacy_settings(
+            &self,
+            user_id: &Uuid,
+        ) -> Result<PrivacySettings, RepositoryError>;
+        write fn save_privacy_settings(
+            &self,
+            user_id: &Uuid,
+            settings: &PrivacySettings,
+        ) -> Result<(), RepositoryError>;
+        read_unordered fn find_acquaintance_only_user_ids(
+            &self,
+            user_ids: &[Uuid],
+        ) -> Result<Vec<Uuid>, RepositoryError>;
+    }
+}
+
+dual_write! {
+    impl WebhookRepository {
+        write fn import(&self, webhook: &Webhook, secret: &str) -> Result<(), RepositoryError>;
+        read fn find_all(&self) -> Result<Vec<Webhook>, RepositoryError>;
+        read fn find_all_with_secrets(&self) -> Result<Vec<(Webhook, String)>, RepositoryError>;
+        write fn delete(&self, id: i64) -> Result<bool, RepositoryError>;
+    }
+
+    async fn create(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        created_by: &Uuid,
+    ) -> Result<Webhook, RepositoryError> {
+        let webhook = self
+            .primary
+            .create(url, secret, events, created_by)
+            .await?;
+        self.copy("create", self.secondary.import(&webhook, secret))
+            .await;
+        Ok(webhook)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        MockAnnouncementRepository, MockChannelRepository, MockMuteRepository,
+    };
+    use fake::{Fake, uuid::UUIDv4};
+
+    fn channel() -> Channel {
+        Channel {
+            id: UUIDv4.fake(),
+            name: "random".to_string(),
+            path: "general/random".to_string(),
+            archived: false,
+        }
+    }
+
+    fn channel_repository(
+        primary: MockChannelRepository,
+        secondary: MockChannelRepository,
+    ) -> DualWrite<dyn ChannelRepository> {
+        DualWrite::new("channel", Arc::new(primary), Arc::new(secondary))
+    }
+
+    #[tokio::test]
+    async fn writes_go_to_both_backends() {
+        let channels = vec![channel()];
+        let mut primary = MockChannelRepository::new();
+        let mut secondary = MockChannelRepository::new();
+        let expected = channels.clone();
+        primary
+            .expect_save_batch()
+            .withf(move |c| c == expected)
+            .times(1)
+            .returning(|_| Ok(()));
+        // Failures of the secondary are only logged
+        secondary
+            .expect_save_batch()
+            .times(1)
+            .returning(|_| Err(RepositoryError::Database("down".to_string())));
+
+        let repo = channel_repository(primary, secondary);
+        repo.save_batch(&channels).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_writes_are_not_copied_to_the_secondary() {
+        let mut primary = MockMuteRepository::new();
+        // The secondary panics if it is called
+        let secondary = MockMuteRepository::new();
+        primary
+            .expect_mute_user()
+            .returning(|_, _| Err(RepositoryError::Database("down".to_string())));
+
+        let repo: DualWrite<dyn MuteRepository> =
+            DualWrite::new("mute", Arc::new(primary), Arc::new(secondary));
+        let result = repo.mute_user(&UUIDv4.fake(), &UUIDv4.fake()).await;
+        assert!(matches!(result, Err(RepositoryError::Database(_))));
+    }
+
+    #[tokio::test]
+    async fn reads_are_served_by_the_primary() {
+        let channel = channel();
+        let mut primary = MockChannelRepository::new();
+        let mut secondary = MockChannelRepository::new();
+        let expected = channel.clone();
+        primary
+            .expect_find_by_id()
+            .times(1)
+            .returning(move |_| Ok(Some(expected.clone())));
+        secondary
+            .expect_find_by_id()
+            .times(1)
+            .returning(|_| Ok(None));
+        primary
+            .expect_find_all()
+            .returning(|| Err(RepositoryError::Database("down".to_string())));
+        secondary.expect_find_all().returning(|| Ok(vec![]));
+
+        let repo = channel_repository(primary, secondary);
+        assert_eq!(repo.find_by_id(&channel.id).await.unwrap(), Some(channel));
+        assert!(repo.find_all().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn created_records_keep_the_ids_of_the_primary() {
+        let announcement = Announcement {
+            id: 42,
+            content: "Maintenance tonight".to_string(),
+            created_at: OffsetDateTime::now_utc(),
+        };
+        let mut primary = MockAnnouncementRepository::new();
+        // The secondary panics if it generates an ID of its own
+        let mut secondary = MockAnnouncementRepository::new();
+        let created = announcement.clone();
+        primary
+            .expect_publish()
+            .times(1)
+            .returning(move |_| Ok(created.clone()));
+        secondary
+            .expect_import()
+            .withf(|a| a.id == 42)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let repo: DualWrite<dyn AnnouncementRepository> =
+            DualWrite::new("announcement", Arc::new(primary), Arc::new(secondary));
+        assert_eq!(
+            repo.publish("Maintenance tonight").await.unwrap(),
+            announcement
+        );
+    }
+
+    #[test]
+    fn unordered_results_match_in_any_order() {
+        assert!(same_elements(&[1, 2, 2, 3], &[2, 3, 1, 2]));
+        assert!(!same_elements(&[1, 2, 2], &[1, 1, 2]));
+        assert!(!same_elements(&[1, 2], &[1, 2, 3]));
+    }
+}
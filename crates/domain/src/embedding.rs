@@ -0,0 +1,74 @@
+use crate::error::EmbeddingError;
+use std::fmt::Debug;
+
+/// Converts texts into vectors whose cosine similarity reflects how similar their contents are.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait EmbeddingService: Debug + Send + Sync {
+    /// Returns one embedding per text, in the same order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError>;
+}
+
+/// Returns the cosine similarity of two vectors, or 0 if either of them is zero.
+/// Vectors of different lengths are compared up to the shorter length.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Returns the element-wise mean of the vectors, or `None` if there are none.
+pub fn centroid<'a>(vectors: impl IntoIterator<Item = &'a [f32]>) -> Option<Vec<f32>> {
+    let mut sum: Vec<f32> = Vec::new();
+    let mut count = 0;
+
+    for vector in vectors {
+        if sum.len() < vector.len() {
+            sum.resize(vector.len(), 0.0);
+        }
+        for (s, v) in sum.iter_mut().zip(vector) {
+            *s += v;
+        }
+        count += 1;
+    }
+
+    if count == 0 {
+        return None;
+    }
+
+    Some(sum.into_iter().map(|s| s / count as f32).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::iter;
+
+    #[test]
+    fn cosine_similarity_ignores_magnitude() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[3.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 2.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn centroid_averages_vectors() {
+        let vectors = [vec![1.0, 0.0], vec![0.0, 2.0]];
+
+        assert_eq!(
+            centroid(vectors.iter().map(Vec::as_slice)),
+            Some(vec![0.5, 1.0])
+        );
+        assert_eq!(centroid(iter::empty()), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::model::QuotaUsage;
 use thiserror::Error;
 use traq::apis::Error as TraqApiError;
 use uuid::Uuid;
@@ -43,6 +44,69 @@ impl<T> From<TraqApiError<T>> for TraqClientError {
     }
 }
 
+/// Errors that can occur when fetching link previews
+#[derive(Error, Debug, PartialEq)]
+pub enum LinkPreviewError {
+    #[error("link preview URL is not allowed: {0}")]
+    DisallowedUrl(String),
+
+    #[error("link preview request failed: {0}")]
+    Request(String),
+
+    #[error("invalid link preview response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Errors that can occur when fetching highlights from peer instances
+#[derive(Error, Debug, PartialEq)]
+pub enum FederationError {
+    #[error("highlights request failed: {0}")]
+    Request(String),
+
+    #[error("invalid highlights response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Errors that can occur when resizing images
+#[derive(Error, Debug, PartialEq)]
+pub enum ImageError {
+    #[error("failed to decode image: {0}")]
+    Decode(String),
+
+    #[error("failed to encode image: {0}")]
+    Encode(String),
+}
+
+/// Errors that can occur when sending webhook requests
+#[derive(Error, Debug, PartialEq)]
+pub enum WebhookError {
+    #[error("invalid webhook secret: {0}")]
+    InvalidSecret(String),
+
+    #[error("webhook request failed: {0}")]
+    Request(String),
+}
+
+/// Errors that can occur when computing embeddings
+#[derive(Error, Debug, PartialEq)]
+pub enum EmbeddingError {
+    #[error("embedding request failed: {0}")]
+    Request(String),
+
+    #[error("invalid embedding response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Errors that can occur when communicating with the search index
+#[derive(Error, Debug, PartialEq)]
+pub enum SearchError {
+    #[error("search request failed: {0}")]
+    Request(String),
+
+    #[error("invalid search response: {0}")]
+    InvalidResponse(String),
+}
+
 /// Domain-level errors for service operations
 #[derive(Error, Debug, PartialEq)]
 pub enum DomainError {
@@ -64,12 +128,81 @@ pub enum DomainError {
     #[error("no valid token found to fetch stamps from traQ")]
     NoTokenForStampsList,
 
+    #[error("at most {0} stamps can be looked up at once")]
+    TooManyStampIds(usize),
+
+    #[error("no channel found for ID {0}")]
+    NoChannelForId(Uuid),
+
     #[error("no valid token found for user {0}")]
     NoTokenForUser(Uuid),
 
+    #[error("users cannot mute themselves")]
+    CannotMuteSelf,
+
+    #[error("users cannot block themselves")]
+    CannotBlockSelf,
+
+    #[error("users cannot follow themselves")]
+    CannotFollowSelf,
+
+    #[error("at most {0} channels can be picked")]
+    TooManyChannelInterests(usize),
+
+    #[error("score multipliers must be between 0.1 and 10")]
+    InvalidChannelScoreMultiplier,
+
+    #[error("scores can be overridden for at most {0} channels")]
+    TooManyChannelScoreOverrides(usize),
+
+    #[error("no saved search found for ID {0}")]
+    NoSavedSearchForId(i64),
+
+    #[error("invalid saved search: {0}")]
+    InvalidSavedSearch(&'static str),
+
+    #[error("at most {0} searches can be saved")]
+    TooManySavedSearches(usize),
+
+    #[error("announcements must be between 1 and {0} characters")]
+    InvalidAnnouncement(usize),
+
+    #[error("no announcement to withdraw")]
+    NoAnnouncement,
+
+    #[error("invalid tag")]
+    InvalidTag,
+
+    #[error("invalid cursor")]
+    InvalidCursor,
+
+    #[error("invalid webhook: {0}")]
+    InvalidWebhook(&'static str),
+
+    #[error("at most {0} webhooks can be registered")]
+    TooManyWebhooks(usize),
+
+    #[error("no webhook found for ID {0}")]
+    NoWebhookForId(i64),
+
+    #[error("invalid faults: {0}")]
+    InvalidFaults(&'static str),
+
+    #[error("highlights are not published")]
+    HighlightsNotPublished,
+
+    #[error("the public timeline is disabled")]
+    PublicTimelineDisabled,
+
+    #[error("the daily quota of {} is used up", .0.limit)]
+    QuotaExceeded(QuotaUsage),
+
     #[error(transparent)]
     Repository(#[from] RepositoryError),
 
+    #[error(transparent)]
+    Search(#[from] SearchError),
+
     #[error(transparent)]
     TraqClient(#[from] TraqClientError),
 }
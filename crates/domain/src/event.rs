@@ -1,9 +1,18 @@
-use crate::model::Message;
+use crate::model::{Message, Reaction};
 use serde::{Deserialize, Serialize};
 use strum::{EnumDiscriminants, IntoStaticStr};
+use time::OffsetDateTime;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+/// The protocol version assumed for clients that do not declare one.
+/// Clients on this version receive full messages in `messageUpdated` events.
+pub const DEFAULT_PROTOCOL_VERSION: u32 = 1;
+/// The first protocol version that receives `messageDelta` events instead of full messages.
+pub const DELTA_PROTOCOL_VERSION: u32 = 2;
+/// The latest protocol version supported by the server.
+pub const LATEST_PROTOCOL_VERSION: u32 = DELTA_PROTOCOL_VERSION;
+
 /// Trait for Socket.io events that provides type-safe event name access
 pub trait SocketEvent: for<'de> Deserialize<'de> + Send + 'static {
     fn event_name() -> &'static str;
@@ -20,6 +29,34 @@ pub enum ClientEvent {
     Unsubscribe(UnsubscribePayload),
 }
 
+/// Auth data sent by clients on connect
+#[derive(Clone, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectPayload {
+    #[serde(default = "default_protocol_version")]
+    pub protocol_version: u32,
+}
+
+impl ConnectPayload {
+    /// Returns the protocol version to use with the client.
+    pub fn negotiated_protocol_version(&self) -> u32 {
+        self.protocol_version
+            .clamp(DEFAULT_PROTOCOL_VERSION, LATEST_PROTOCOL_VERSION)
+    }
+}
+
+impl Default for ConnectPayload {
+    fn default() -> Self {
+        Self {
+            protocol_version: DEFAULT_PROTOCOL_VERSION,
+        }
+    }
+}
+
+fn default_protocol_version() -> u32 {
+    DEFAULT_PROTOCOL_VERSION
+}
+
 /// Payload for the subscribe event
 #[derive(Clone, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -53,11 +90,53 @@ impl SocketEvent for UnsubscribePayload {
 #[strum(serialize_all = "camelCase")]
 pub enum ServerEvent {
     MessageUpdated(Message),
+    MessageDelta(MessageDelta),
+}
+
+/// Changes to a message. Fields that did not change are omitted.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageDelta {
+    pub id: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reactions: Option<Vec<Reaction>>,
+}
+
+impl MessageDelta {
+    pub fn between(previous: &Message, current: &Message) -> Self {
+        let mut previous_reactions = previous.reactions.clone();
+        let mut current_reactions = current.reactions.clone();
+        previous_reactions.sort();
+        current_reactions.sort();
+
+        MessageDelta {
+            id: current.id,
+            updated_at: current.updated_at,
+            content: (previous.content != current.content).then(|| current.content.clone()),
+            reactions: (previous_reactions != current_reactions).then(|| current.reactions.clone()),
+        }
+    }
+
+    /// Combines this delta with a later one of the same message, as if both changes were made at
+    /// once.
+    pub fn followed_by(&self, later: &MessageDelta) -> Self {
+        MessageDelta {
+            id: later.id,
+            updated_at: later.updated_at,
+            content: later.content.clone().or_else(|| self.content.clone()),
+            reactions: later.reactions.clone().or_else(|| self.reactions.clone()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_factories::{MessageBuilder, ReactionBuilder};
 
     #[test]
     fn test_subscribe_payload_event_name() {
@@ -83,4 +162,38 @@ mod tests {
         let event_name: &'static str = (&event).into();
         assert_eq!(event_name, "messageUpdated");
     }
+
+    #[test]
+    fn test_connect_payload_negotiation() {
+        let payload: ConnectPayload = serde_json::from_str("{}").unwrap();
+        assert_eq!(payload.negotiated_protocol_version(), 1);
+
+        let payload: ConnectPayload = serde_json::from_str(r#"{"protocolVersion":2}"#).unwrap();
+        assert_eq!(payload.negotiated_protocol_version(), 2);
+
+        // Versions newer than the server fall back to the latest one
+        let payload: ConnectPayload = serde_json::from_str(r#"{"protocolVersion":99}"#).unwrap();
+        assert_eq!(
+            payload.negotiated_protocol_version(),
+            LATEST_PROTOCOL_VERSION
+        );
+    }
+
+    #[test]
+    fn test_message_delta_includes_only_changes() {
+        let previous = MessageBuilder::new().build();
+        let reaction = ReactionBuilder::new().build();
+        let current = Message {
+            reactions: vec![reaction.clone()],
+            ..previous.clone()
+        };
+
+        let delta = MessageDelta::between(&previous, &current);
+        assert_eq!(delta.id, previous.id);
+        assert_eq!(delta.content, None);
+        assert_eq!(delta.reactions, Some(vec![reaction]));
+
+        let json = serde_json::to_value(&delta).unwrap();
+        assert!(json.get("content").is_none());
+    }
 }
@@ -0,0 +1,140 @@
+//! Highlights shared between Twittra instances.
+//!
+//! Instances that opt in publish the most reacted messages of the past day without their
+//! authors, and can show the highlights published by the peer instances they are configured with.
+
+use crate::{
+    error::FederationError,
+    model::{InstanceHighlights, PeerHighlights},
+};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+use time::{Duration, OffsetDateTime};
+use tokio::task::JoinSet;
+
+/// The version of [`InstanceHighlights`] published by this instance.
+pub const HIGHLIGHTS_VERSION: u32 = 1;
+
+/// Highlights cover a day, so peers are asked again only this often.
+const PEER_CACHE_TTL: Duration = Duration::minutes(10);
+
+/// Fetches the highlights published by peer instances.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait HighlightsFetcher: Debug + Send + Sync {
+    /// Fetches the highlights of the instance at `base_url`.
+    async fn fetch(&self, base_url: &str) -> Result<InstanceHighlights, FederationError>;
+}
+
+/// The peer instances whose highlights are shown, cached so that showing them doesn't send a
+/// request to every peer.
+#[derive(Debug)]
+pub struct Peers {
+    fetcher: Arc<dyn HighlightsFetcher>,
+    urls: Vec<String>,
+    cache: Mutex<HashMap<String, (OffsetDateTime, InstanceHighlights)>>,
+}
+
+impl Peers {
+    pub fn new(fetcher: Arc<dyn HighlightsFetcher>, urls: Vec<String>) -> Self {
+        Self {
+            fetcher,
+            urls,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Returns the highlights of every peer in the configured order, fetching those not cached
+    /// recently. Peers that can't be reached are shown as last fetched, or left out.
+    pub async fn highlights(&self) -> Vec<PeerHighlights> {
+        let now = OffsetDateTime::now_utc();
+        let stale: Vec<String> = {
+            let cache = self.cache.lock().unwrap();
+            self.urls
+                .iter()
+                .filter(|url| {
+                    cache
+                        .get(*url)
+                        .is_none_or(|(fetched_at, _)| now - *fetched_at >= PEER_CACHE_TTL)
+                })
+                .cloned()
+                .collect()
+        };
+
+        let mut fetches = JoinSet::new();
+        for url in stale {
+            let fetcher = self.fetcher.clone();
+            fetches.spawn(async move {
+                let result = fetcher.fetch(&url).await;
+                (url, result)
+            });
+        }
+        let fetched = fetches.join_all().await;
+
+        let mut cache = self.cache.lock().unwrap();
+        for (url, result) in fetched {
+            match result {
+                Ok(highlights) => {
+                    cache.insert(url, (now, highlights));
+                }
+                Err(e) => tracing::warn!("Failed to fetch the highlights of {}: {:?}", url, e),
+            }
+        }
+        self.urls
+            .iter()
+            .filter_map(|url| {
+                cache.get(url).map(|(_, highlights)| PeerHighlights {
+                    url: url.clone(),
+                    highlights: highlights.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockall::predicate;
+
+    fn highlights(instance: &str) -> InstanceHighlights {
+        InstanceHighlights {
+            version: HIGHLIGHTS_VERSION,
+            instance: instance.to_string(),
+            generated_at: OffsetDateTime::now_utc(),
+            highlights: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn peers_are_fetched_once_and_unreachable_ones_left_out() {
+        let mut mock_fetcher = MockHighlightsFetcher::new();
+        mock_fetcher
+            .expect_fetch()
+            .with(predicate::eq("https://a.example.com"))
+            .times(1)
+            .returning(|_| Ok(highlights("A")));
+        mock_fetcher
+            .expect_fetch()
+            .with(predicate::eq("https://b.example.com"))
+            .times(2)
+            .returning(|_| Err(FederationError::Request("timed out".to_string())));
+
+        let peers = Peers::new(
+            Arc::new(mock_fetcher),
+            vec![
+                "https://b.example.com".to_string(),
+                "https://a.example.com".to_string(),
+            ],
+        );
+        for _ in 0..2 {
+            let fetched = peers.highlights().await;
+            assert_eq!(fetched.len(), 1);
+            assert_eq!(fetched[0].url, "https://a.example.com");
+            assert_eq!(fetched[0].highlights.instance, "A");
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Hashtags in message contents, indexed while crawling for tag pages and trending tags.
+
+use crate::markdown;
+
+/// Longer tags are ignored, since they are rarely meant as tags.
+pub const MAX_TAG_LEN: usize = 64;
+/// Further tags in a message are ignored, so that a single message cannot flood the trends.
+pub const MAX_TAGS_PER_MESSAGE: usize = 10;
+
+/// Extracts `#tag` tokens from a message, in order of appearance and without duplicates.
+///
+/// A tag starts with a `#` at the start of the content or after whitespace, and continues with
+/// letters, digits and `_`. A `#` inside a word does not start a tag, so URL fragments and channel
+/// paths in traQ's embeds (`!{"type":"channel","raw":"#general",...}`) are not tags. Neither are
+/// numbers like `#123`, nor anything in code blocks.
+pub fn extract_hashtags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+
+    for text in markdown::outside_code_blocks(content) {
+        let mut previous = None;
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            let starts_tag = c == '#' && previous.is_none_or(char::is_whitespace);
+            previous = Some(c);
+            if !starts_tag {
+                continue;
+            }
+
+            let mut end = start + 1;
+            while let Some(&(i, c)) = chars.peek() {
+                if !is_tag_char(c) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                previous = Some(c);
+                chars.next();
+            }
+
+            if let Some(tag) = normalize_tag(&text[start + 1..end])
+                && !tags.contains(&tag)
+            {
+                tags.push(tag);
+                if tags.len() == MAX_TAGS_PER_MESSAGE {
+                    return tags;
+                }
+            }
+        }
+    }
+
+    tags
+}
+
+/// Normalizes a tag given without or with its `#`, so that `#Rust` and `rust` are the same tag.
+/// Returns `None` if it is not a valid tag.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.strip_prefix('#').unwrap_or(tag);
+
+    let is_valid = !tag.is_empty()
+        && tag.chars().count() <= MAX_TAG_LEN
+        && tag.chars().all(is_tag_char)
+        && !tag.chars().all(|c| c.is_ascii_digit());
+
+    is_valid.then(|| tag.to_lowercase())
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_tags_after_whitespace() {
+        let content = "#Rust is fun #rust #型システム, see https://example.com/#anchor\n#2024 #_x";
+
+        assert_eq!(extract_hashtags(content), vec!["rust", "型システム", "_x"]);
+    }
+
+    #[test]
+    fn ignores_embeds_and_code_blocks() {
+        let content = concat!(
+            r##"!{"type":"channel","raw":"#general","id":"0"} "##,
+            "```\n#include <stdio.h>\n``` #after_code"
+        );
+
+        assert_eq!(extract_hashtags(content), vec!["after_code"]);
+    }
+
+    #[test]
+    fn caps_tags_per_message() {
+        let content = (0..20).map(|i| format!("#tag{i}")).collect::<Vec<_>>();
+
+        assert_eq!(
+            extract_hashtags(&content.join(" ")).len(),
+            MAX_TAGS_PER_MESSAGE
+        );
+    }
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize_tag("#Rust"), Some("rust".to_string()));
+        assert_eq!(normalize_tag("rust"), Some("rust".to_string()));
+        assert_eq!(normalize_tag("123"), None);
+        assert_eq!(normalize_tag("two words"), None);
+        assert_eq!(normalize_tag(&"a".repeat(MAX_TAG_LEN + 1)), None);
+    }
+}
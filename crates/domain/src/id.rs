@@ -0,0 +1,84 @@
+//! IDs of the different kinds of traQ entities, which are all UUIDs.
+//! Methods taking more than one ID take these, so that the IDs can't be passed in the wrong order.
+//! They convert to and from `Uuid` only explicitly, so that one kind can't stand in for another.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+macro_rules! define_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(Uuid);
+
+        impl From<Uuid> for $name {
+            fn from(id: Uuid) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for Uuid {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl $name {
+            pub fn as_uuid(&self) -> &Uuid {
+                &self.0
+            }
+        }
+
+        #[cfg(any(test, feature = "test-utils"))]
+        impl fake::Dummy<fake::uuid::UUIDv4> for $name {
+            fn dummy_with_rng<R: fake::Rng + ?Sized>(config: &fake::uuid::UUIDv4, rng: &mut R) -> Self {
+                Self(fake::Dummy::dummy_with_rng(config, rng))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+define_id!(
+    /// The ID of a message.
+    MessageId
+);
+define_id!(
+    /// The ID of a user.
+    UserId
+);
+define_id!(
+    /// The ID of a channel.
+    ChannelId
+);
+define_id!(
+    /// The ID of a stamp.
+    StampId
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, uuid::UUIDv4};
+
+    #[test]
+    fn serializes_as_a_bare_uuid() {
+        let id: Uuid = UUIDv4.fake();
+        let json = serde_json::to_string(&MessageId::from(id)).unwrap();
+
+        assert_eq!(json, serde_json::to_string(&id).unwrap());
+        assert_eq!(
+            serde_json::from_str::<MessageId>(&json).unwrap(),
+            MessageId::from(id)
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Images proxied from traQ, kept so that traQ isn't asked for the same image on every request.
+
+use crate::{
+    error::DomainError,
+    model::{ImageKind, ImageSize},
+    repository::ImageCacheRepository,
+};
+use std::{
+    collections::HashSet,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+/// Icons and stamps rarely change, so cached images are served for this long before being
+/// fetched again.
+const REFRESH_AFTER: Duration = Duration::hours(24);
+
+/// Caches the original images proxied from traQ. Stale images are still served, while they are
+/// fetched again in the background.
+#[derive(Debug)]
+pub struct ImageCache {
+    repo: Arc<dyn ImageCacheRepository>,
+    /// The images being fetched in the background, so that each is only fetched once at a time.
+    refreshing: Arc<Mutex<HashSet<(ImageKind, Uuid)>>>,
+}
+
+impl ImageCache {
+    pub fn new(repo: Arc<dyn ImageCacheRepository>) -> Self {
+        Self {
+            repo,
+            refreshing: Arc::default(),
+        }
+    }
+
+    /// Returns the cached image and its content type, fetching it with `fetch` if it isn't
+    /// cached yet.
+    pub async fn get<F>(
+        &self,
+        kind: ImageKind,
+        id: &Uuid,
+        fetch: F,
+    ) -> Result<(Vec<u8>, String), DomainError>
+    where
+        F: Future<Output = Result<(Vec<u8>, String), DomainError>> + Send + 'static,
+    {
+        // A broken cache shouldn't stop images from being served
+        let cached = self
+            .repo
+            .find(kind, id, ImageSize::Original)
+            .await
+            .inspect_err(|e| tracing::warn!("Failed to find the cached {:?} {}: {:?}", kind, id, e))
+            .ok()
+            .flatten();
+
+        match cached {
+            Some(image) => {
+                if image.stored_at < OffsetDateTime::now_utc() - REFRESH_AFTER {
+                    self.refresh_in_background(kind, *id, fetch);
+                }
+                Ok((image.data, image.content_type))
+            }
+            None => {
+                let (image, content_type) = fetch.await?;
+                save(self.repo.as_ref(), kind, id, &image, &content_type).await;
+                Ok((image, content_type))
+            }
+        }
+    }
+
+    fn refresh_in_background<F>(&self, kind: ImageKind, id: Uuid, fetch: F)
+    where
+        F: Future<Output = Result<(Vec<u8>, String), DomainError>> + Send + 'static,
+    {
+        let lock = || self.refreshing.lock().unwrap_or_else(|e| e.into_inner());
+        if !lock().insert((kind, id)) {
+            return;
+        }
+
+        let repo = self.repo.clone();
+        let refreshing = self.refreshing.clone();
+        tokio::spawn(async move {
+            match fetch.await {
+                Ok((image, content_type)) => {
+                    save(repo.as_ref(), kind, &id, &image, &content_type).await;
+                }
+                Err(e) => tracing::warn!("Failed to refresh {:?} {}: {:?}", kind, id, e),
+            }
+            refreshing
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&(kind, id));
+        });
+    }
+}
+
+async fn save(
+    repo: &dyn ImageCacheRepository,
+    kind: ImageKind,
+    id: &Uuid,
+    image: &[u8],
+    content_type: &str,
+) {
+    if let Err(e) = repo
+        .save(kind, id, ImageSize::Original, image, content_type)
+        .await
+    {
+        tracing::warn!("Failed to cache {:?} {}: {:?}", kind, id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::CachedImage, repository::MockImageCacheRepository};
+    use fake::{Fake, uuid::UUIDv4};
+    use mockall::predicate;
+    use tokio::task::yield_now;
+
+    fn cached(age: Duration) -> CachedImage {
+        CachedImage {
+            data: vec![1],
+            content_type: "image/png".to_string(),
+            stored_at: OffsetDateTime::now_utc() - age,
+        }
+    }
+
+    async fn fetched() -> Result<(Vec<u8>, String), DomainError> {
+        Ok((vec![2], "image/png".to_string()))
+    }
+
+    #[tokio::test]
+    async fn fresh_images_are_served_from_the_cache() {
+        let mut mock_repo = MockImageCacheRepository::new();
+        mock_repo
+            .expect_find()
+            .returning(|_, _, _| Ok(Some(cached(Duration::hours(1)))));
+        let cache = ImageCache::new(Arc::new(mock_repo));
+
+        let image = cache
+            .get(ImageKind::UserIcon, &UUIDv4.fake(), async {
+                panic!("fresh images are not fetched")
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(image, (vec![1], "image/png".to_string()));
+    }
+
+    #[tokio::test]
+    async fn missing_images_are_fetched_and_cached() {
+        let id: Uuid = UUIDv4.fake();
+        let mut mock_repo = MockImageCacheRepository::new();
+        mock_repo.expect_find().returning(|_, _, _| Ok(None));
+        mock_repo
+            .expect_save()
+            .with(
+                predicate::eq(ImageKind::UserIcon),
+                predicate::eq(id),
+                predicate::eq(ImageSize::Original),
+                predicate::eq(vec![2]),
+                predicate::eq("image/png"),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        let cache = ImageCache::new(Arc::new(mock_repo));
+
+        let image = cache
+            .get(ImageKind::UserIcon, &id, fetched())
+            .await
+            .unwrap();
+
+        assert_eq!(image, (vec![2], "image/png".to_string()));
+    }
+
+    #[tokio::test]
+    async fn stale_images_are_served_while_refreshed() {
+        let mut mock_repo = MockImageCacheRepository::new();
+        mock_repo
+            .expect_find()
+            .returning(|_, _, _| Ok(Some(cached(REFRESH_AFTER + Duration::hours(1)))));
+        mock_repo
+            .expect_save()
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        let cache = ImageCache::new(Arc::new(mock_repo));
+        let id: Uuid = UUIDv4.fake();
+
+        let image = cache
+            .get(ImageKind::UserIcon, &id, fetched())
+            .await
+            .unwrap();
+        assert_eq!(image, (vec![1], "image/png".to_string()));
+
+        // Only one refresh runs at a time
+        let image = cache
+            .get(ImageKind::UserIcon, &id, fetched())
+            .await
+            .unwrap();
+        assert_eq!(image, (vec![1], "image/png".to_string()));
+
+        while !cache.refreshing.lock().unwrap().is_empty() {
+            yield_now().await;
+        }
+    }
+}
@@ -1,11 +1,32 @@
+pub mod channel_sync;
+pub mod chaos;
+pub mod citation;
 pub mod crawler;
+pub mod daily_summary;
+pub mod dual_write;
+pub mod embedding;
 pub mod error;
 pub mod event;
+pub mod federation;
+pub mod hashtag;
+pub mod id;
+pub mod image_cache;
+pub mod link_preview;
+pub mod markdown;
 pub mod model;
 pub mod notifier;
+pub mod quote;
+pub mod ranking;
+pub mod recent_messages;
+pub mod replay_buffer;
 pub mod repository;
+pub mod search;
 pub mod service;
+pub mod thumbnail;
+pub mod traq_budget;
+pub mod traq_cache;
 pub mod traq_client;
+pub mod webhook;
 
 #[cfg(any(test, feature = "test-utils"))]
 pub mod test_factories;
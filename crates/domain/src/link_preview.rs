@@ -0,0 +1,278 @@
+//! Previews of external pages linked from messages, built from their OpenGraph metadata.
+//! Fetched previews are cached, so that each page is fetched at most once per [`CACHE_TTL`].
+
+use crate::{
+    error::{LinkPreviewError, RepositoryError},
+    markdown,
+    model::{LinkPreview, MessageListItem},
+    repository::LinkPreviewRepository,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
+use time::{Duration, OffsetDateTime};
+use tokio::task::JoinSet;
+
+/// Pages change rarely, so previews are refreshed only this often.
+const CACHE_TTL: Duration = Duration::days(1);
+const MAX_PREVIEWS_PER_MESSAGE: usize = 3;
+/// Uncached pages are fetched while the user waits, so only this many are fetched per request.
+const MAX_FETCHES: usize = 10;
+
+/// Fetches the metadata of web pages.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait LinkPreviewFetcher: Debug + Send + Sync {
+    /// Returns the preview of the page at `url`, which is empty if the page has no metadata.
+    /// The preview has `url` as is, even if the page was redirected.
+    async fn fetch(&self, url: &str) -> Result<LinkPreview, LinkPreviewError>;
+}
+
+/// Finds the external pages linked from a message, in order and without duplicates.
+/// Links in code blocks are ignored, like citations.
+pub fn extract_links(content: &str) -> Vec<String> {
+    let mut links: Vec<String> = vec![];
+    for text in markdown::outside_code_blocks(content) {
+        for word in text.split_whitespace() {
+            let Some(start) = word.find("https://").or_else(|| word.find("http://")) else {
+                continue;
+            };
+            // Links are often followed by punctuation or wrapped in brackets, e.g. in Markdown
+            let link = word[start..].trim_end_matches([')', ']', '>', '.', ',', '!', '?', '"']);
+            if !link.ends_with("://") && !links.iter().any(|l| l == link) {
+                links.push(link.to_string());
+            }
+        }
+    }
+    links
+}
+
+#[derive(Debug)]
+pub struct LinkPreviewResolver {
+    fetcher: Arc<dyn LinkPreviewFetcher>,
+    repo: Arc<dyn LinkPreviewRepository>,
+    ignored_prefixes: Vec<String>,
+}
+
+impl LinkPreviewResolver {
+    pub fn new(fetcher: Arc<dyn LinkPreviewFetcher>, repo: Arc<dyn LinkPreviewRepository>) -> Self {
+        Self {
+            fetcher,
+            repo,
+            ignored_prefixes: vec![],
+        }
+    }
+
+    /// Doesn't preview links starting with `prefix`, e.g. links to traQ, which are shown as
+    /// quotes or need the user's token.
+    pub fn ignoring(mut self, prefix: impl Into<String>) -> Self {
+        self.ignored_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Attaches the previews of the pages linked from `messages`.
+    /// Pages that can't be previewed are left out, since messages can be shown without them.
+    pub async fn attach(&self, messages: &mut [MessageListItem]) {
+        let links: Vec<Vec<String>> = messages
+            .iter()
+            .map(|m| {
+                extract_links(&m.content)
+                    .into_iter()
+                    .filter(|link| !self.ignored_prefixes.iter().any(|p| link.starts_with(p)))
+                    .take(MAX_PREVIEWS_PER_MESSAGE)
+                    .collect()
+            })
+            .collect();
+        let urls: HashSet<&String> = links.iter().flatten().collect();
+        if urls.is_empty() {
+            return;
+        }
+
+        let urls: Vec<String> = urls.into_iter().cloned().collect();
+        let previews = match self.resolve(urls).await {
+            Ok(previews) => previews,
+            Err(e) => {
+                tracing::warn!("Failed to resolve link previews: {:?}", e);
+                return;
+            }
+        };
+        for (message, links) in messages.iter_mut().zip(links) {
+            message.link_previews = links
+                .iter()
+                .filter_map(|link| previews.get(link))
+                .filter(|preview| !preview.is_empty())
+                .cloned()
+                .collect();
+        }
+    }
+
+    async fn resolve(
+        &self,
+        urls: Vec<String>,
+    ) -> Result<HashMap<String, LinkPreview>, RepositoryError> {
+        let since = OffsetDateTime::now_utc() - CACHE_TTL;
+        let mut previews: HashMap<String, LinkPreview> = self
+            .repo
+            .find_fetched_since(&urls, since)
+            .await?
+            .into_iter()
+            .map(|p| (p.url.clone(), p))
+            .collect();
+
+        let mut fetches = JoinSet::new();
+        for url in urls
+            .into_iter()
+            .filter(|url| !previews.contains_key(url))
+            .take(MAX_FETCHES)
+        {
+            let fetcher = self.fetcher.clone();
+            fetches.spawn(async move {
+                match fetcher.fetch(&url).await {
+                    Ok(preview) => preview,
+                    // Failures are cached too, so that broken links aren't fetched on every request
+                    Err(e) => {
+                        tracing::debug!("Failed to fetch the link preview of {}: {:?}", url, e);
+                        LinkPreview::empty(url)
+                    }
+                }
+            });
+        }
+        while let Some(preview) = fetches.join_next().await {
+            let preview = match preview {
+                Ok(preview) => preview,
+                Err(e) => {
+                    tracing::error!("Link preview fetch panicked: {:?}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.repo.save(&preview).await {
+                tracing::warn!(
+                    "Failed to cache the link preview of {}: {:?}",
+                    preview.url,
+                    e
+                );
+            }
+            previews.insert(preview.url.clone(), preview);
+        }
+
+        Ok(previews)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{repository::MockLinkPreviewRepository, test_factories::MessageListItemBuilder};
+    use mockall::predicate;
+
+    fn preview(url: &str) -> LinkPreview {
+        LinkPreview {
+            url: url.to_string(),
+            title: Some("Title".to_string()),
+            description: None,
+            image_url: None,
+        }
+    }
+
+    #[test]
+    fn extracts_links_outside_code_blocks() {
+        let content = "see https://example.com/a, (https://example.com/b) and \
+            [c](https://example.com/c)\nhttps://example.com/a\n```\nhttps://example.com/d\n```";
+
+        assert_eq!(
+            extract_links(content),
+            vec![
+                "https://example.com/a",
+                "https://example.com/b",
+                "https://example.com/c"
+            ]
+        );
+        assert!(extract_links("not a link: example.com https://").is_empty());
+    }
+
+    #[tokio::test]
+    async fn attaches_cached_previews_without_fetching() {
+        let mut messages = vec![
+            MessageListItemBuilder::new()
+                .content("https://example.com/a https://q.trap.jp/messages/x")
+                .build(),
+            MessageListItemBuilder::new()
+                .content("https://example.com/empty")
+                .build(),
+        ];
+
+        let mut mock_repo = MockLinkPreviewRepository::new();
+        mock_repo
+            .expect_find_fetched_since()
+            .withf(|urls, _| {
+                let mut urls = urls.to_vec();
+                urls.sort();
+                urls == ["https://example.com/a", "https://example.com/empty"]
+            })
+            .times(1)
+            .returning(|_, _| {
+                Ok(vec![
+                    preview("https://example.com/a"),
+                    LinkPreview::empty("https://example.com/empty".to_string()),
+                ])
+            });
+
+        let resolver =
+            LinkPreviewResolver::new(Arc::new(MockLinkPreviewFetcher::new()), Arc::new(mock_repo))
+                .ignoring("https://q.trap.jp/");
+        resolver.attach(&mut messages).await;
+
+        assert_eq!(
+            messages[0].link_previews,
+            vec![preview("https://example.com/a")]
+        );
+        assert!(messages[1].link_previews.is_empty());
+    }
+
+    #[tokio::test]
+    async fn caches_fetched_previews_and_failures() {
+        let mut messages = vec![
+            MessageListItemBuilder::new()
+                .content("https://example.com/a https://example.com/broken")
+                .build(),
+        ];
+
+        let mut mock_repo = MockLinkPreviewRepository::new();
+        mock_repo
+            .expect_find_fetched_since()
+            .returning(|_, _| Ok(vec![]));
+        mock_repo
+            .expect_save()
+            .with(predicate::eq(preview("https://example.com/a")))
+            .times(1)
+            .returning(|_| Ok(()));
+        mock_repo
+            .expect_save()
+            .with(predicate::eq(LinkPreview::empty(
+                "https://example.com/broken".to_string(),
+            )))
+            .times(1)
+            .returning(|_| Ok(()));
+        let mut mock_fetcher = MockLinkPreviewFetcher::new();
+        mock_fetcher
+            .expect_fetch()
+            .with(predicate::eq("https://example.com/a"))
+            .times(1)
+            .returning(|url| Ok(preview(url)));
+        mock_fetcher
+            .expect_fetch()
+            .with(predicate::eq("https://example.com/broken"))
+            .times(1)
+            .returning(|_| Err(LinkPreviewError::Request("timed out".to_string())));
+
+        let resolver = LinkPreviewResolver::new(Arc::new(mock_fetcher), Arc::new(mock_repo));
+        resolver.attach(&mut messages).await;
+
+        assert_eq!(
+            messages[0].link_previews,
+            vec![preview("https://example.com/a")]
+        );
+    }
+}
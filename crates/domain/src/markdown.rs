@@ -0,0 +1,35 @@
+//! The parts of traQ's Markdown that affect what is extracted from message contents.
+
+const FENCE: &str = "```";
+
+/// Splits the content into the segments outside code blocks, in order. Code blocks are delimited
+/// by fences (```` ``` ````), and a block left open by an odd fence runs to the end.
+pub fn outside_code_blocks(content: &str) -> impl Iterator<Item = &str> {
+    // Every other segment between fences is inside a code block
+    content.split(FENCE).step_by(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_code_blocks() {
+        let content = "before\n```rust\nlet x = 1;\n```\nbetween\n```\ncode\n```after";
+
+        assert_eq!(
+            outside_code_blocks(content).collect::<Vec<_>>(),
+            vec!["before\n", "\nbetween\n", "after"]
+        );
+    }
+
+    #[test]
+    fn unclosed_code_blocks_run_to_the_end() {
+        let content = "before\n```\ncode";
+
+        assert_eq!(
+            outside_code_blocks(content).collect::<Vec<_>>(),
+            vec!["before\n"]
+        );
+    }
+}
@@ -1,4 +1,10 @@
+use crate::citation;
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
+use strum::{EnumString, IntoStaticStr};
 use time::{OffsetDateTime, error::Parse, format_description::well_known::Rfc3339};
 use traq::models::{self, MessageStamp, MyUserDetail, StampWithThumbnail, UserDetail};
 use utoipa::ToSchema;
@@ -57,7 +63,323 @@ impl PartialEq for Message {
 
 impl Eq for Message {}
 
-#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+/// The kind of change recorded in the message event log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "camelCase")]
+pub enum MessageEventKind {
+    Added,
+    Updated,
+    /// The message was deleted on traQ.
+    Removed,
+}
+
+/// An entry of the message event log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageEvent {
+    /// Increases monotonically with each event.
+    pub cursor: i64,
+    pub message_id: Uuid,
+    pub kind: MessageEventKind,
+}
+
+/// A public channel on traQ.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Channel {
+    pub id: Uuid,
+    /// The name of the channel, without its parents.
+    pub name: String,
+    /// The names of the channel and its parents joined with `/`, like `general/random`.
+    pub path: String,
+    pub archived: bool,
+}
+
+impl Channel {
+    /// Converts traQ's channel tree into channels with their full paths.
+    pub fn from_traq_tree(channels: Vec<models::Channel>) -> Vec<Channel> {
+        let by_id: HashMap<Uuid, &models::Channel> = channels.iter().map(|c| (c.id, c)).collect();
+
+        channels
+            .iter()
+            .map(|channel| {
+                let mut names = vec![channel.name.as_str()];
+                let mut parent_id = channel.parent_id;
+                // traQ keeps the tree acyclic, but a bound keeps a broken response from hanging
+                while let Some(parent) = parent_id.and_then(|id| by_id.get(&id))
+                    && names.len() <= by_id.len()
+                {
+                    names.push(&parent.name);
+                    parent_id = parent.parent_id;
+                }
+                names.reverse();
+
+                Channel {
+                    id: channel.id,
+                    name: channel.name.clone(),
+                    path: names.join("/"),
+                    archived: channel.archived,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The number of recent messages in a channel.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelActivity {
+    pub channel_id: Uuid,
+    pub message_count: i64,
+}
+
+/// A hashtag used in recent messages.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendingTag {
+    /// The tag without `#`, lowercased.
+    pub tag: String,
+    /// The number of users who used the tag.
+    pub user_count: i64,
+    pub message_count: i64,
+}
+
+/// The period over which trending messages are ranked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub enum TrendingWindow {
+    #[default]
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "72h")]
+    ThreeDays,
+}
+
+impl TrendingWindow {
+    pub fn hours(self) -> i64 {
+        match self {
+            TrendingWindow::Day => 24,
+            TrendingWindow::ThreeDays => 72,
+        }
+    }
+}
+
+/// A step a user has to complete before using the app.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema, EnumString, IntoStaticStr,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum OnboardingStep {
+    /// The user accepted the privacy notice.
+    PrivacyNotice,
+    /// The user allowed crawling messages with their token.
+    CrawlConsent,
+    /// The user picked channels they are interested in.
+    InitialChannels,
+}
+
+/// The onboarding steps a user has completed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingState {
+    pub privacy_notice_accepted: bool,
+    pub crawl_consent_granted: bool,
+    pub initial_channels_picked: bool,
+}
+
+impl OnboardingState {
+    pub fn is_complete(&self) -> bool {
+        self.privacy_notice_accepted && self.crawl_consent_granted && self.initial_channels_picked
+    }
+}
+
+/// Changes to timeline messages since a cursor.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineUpdates {
+    /// The cursor to pass as `since` on the next request.
+    pub cursor: i64,
+    pub added: Vec<MessageListItem>,
+    pub updated: Vec<MessageListItem>,
+    pub removed: Vec<Uuid>,
+    /// Whether there are more updates after `cursor`.
+    pub has_more: bool,
+}
+
+/// The position after the last message of a page of messages ordered newest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageCursor {
+    pub created_at: OffsetDateTime,
+    pub id: Uuid,
+}
+
+impl MessageCursor {
+    /// Encodes the cursor into an opaque string for clients.
+    pub fn encode(&self) -> String {
+        format!(
+            "{}_{}",
+            self.created_at.unix_timestamp_nanos(),
+            self.id.simple()
+        )
+    }
+
+    /// Decodes a cursor encoded by [`MessageCursor::encode`].
+    pub fn decode(s: &str) -> Option<Self> {
+        let (nanos, id) = s.split_once('_')?;
+        let created_at = OffsetDateTime::from_unix_timestamp_nanos(nanos.parse().ok()?).ok()?;
+        let id = Uuid::try_parse(id).ok()?;
+
+        Some(Self { created_at, id })
+    }
+}
+
+impl From<&MessageListItem> for MessageCursor {
+    fn from(message: &MessageListItem) -> Self {
+        Self {
+            created_at: message.created_at,
+            id: message.id,
+        }
+    }
+}
+
+/// A message served to a user from a channel they cannot access on traQ.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VisibilityLeak {
+    pub message_id: Uuid,
+    pub channel_id: Uuid,
+    /// The status traQ responded with when the user fetched a message of the channel.
+    pub status: u16,
+}
+
+/// The result of checking on traQ that a user can access the channels of messages served to
+/// them.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VisibilityReport {
+    pub checked_messages: usize,
+    pub checked_channels: usize,
+    pub leaks: Vec<VisibilityLeak>,
+}
+
+/// A page of messages, newest first.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessagePage {
+    pub messages: Vec<MessageListItem>,
+    /// The cursor to pass as `before` to get the next page, or `None` if there are no more
+    /// messages.
+    pub next_cursor: Option<String>,
+}
+
+/// The category of an abuse report.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema, EnumString, IntoStaticStr,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    Inappropriate,
+    Other,
+}
+
+/// A message with unresolved abuse reports.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedMessage {
+    pub message_id: Uuid,
+    /// The message itself.
+    /// Omitted if the message is no longer cached.
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<MessageListItem>,
+    pub report_count: i64,
+    pub reasons: Vec<ReportReason>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_reported_at: OffsetDateTime,
+}
+
+/// A multiplier a user applies to the scores of messages recommended from a channel.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelScoreOverride {
+    pub channel_id: Uuid,
+    /// Greater than 1 to prioritize the channel, and less than 1 to de-prioritize it.
+    pub multiplier: f64,
+}
+
+/// Settings controlling who a user's messages are shown to.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacySettings {
+    /// Whether the user's messages are recommended only to users they have followed or stamped
+    /// messages of. They still appear in searches, tags and the timelines of their followers.
+    pub recommend_only_to_acquaintances: bool,
+    /// Whether the content of the user's popular messages may be published in the highlights
+    /// shared with peer Twittra instances. Only their reaction counts are published otherwise.
+    #[serde(default)]
+    pub share_content_in_highlights: bool,
+}
+
+/// News about the instance from the admins, shown at the top of every timeline until the user
+/// dismisses it.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Announcement {
+    pub id: i64,
+    #[schema(max_length = 2000)]
+    pub content: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// A search query saved by a user to run again later.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub id: i64,
+    #[schema(max_length = 64)]
+    pub name: String,
+    #[schema(max_length = 256)]
+    pub query: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// A message that a user marked as not interesting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HiddenMessage {
+    pub message_id: Uuid,
+    pub author_id: Uuid,
+    pub channel_id: Uuid,
+}
+
+/// Recommended messages by an author in a channel that a user never read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IgnoredRecommendations {
+    pub author_id: Uuid,
+    pub channel_id: Uuid,
+    pub count: i64,
+}
+
+/// A single execution of a background job.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRun {
+    #[schema(max_length = 64)]
+    pub job_name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    pub duration_ms: i64,
+    pub succeeded: bool,
+    /// The error message if the job failed.
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageListItem {
     pub id: Uuid,
@@ -68,18 +390,420 @@ pub struct MessageListItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<User>,
     pub channel_id: Uuid,
+    /// The channel the message was posted in.
+    /// Omitted if the server hasn't synced the channel yet.
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<Channel>,
     pub content: String,
+    /// The message this message replies to, i.e. the first message it cites.
+    /// Omitted if it doesn't cite any message.
+    #[schema(nullable = false)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to_message_id: Option<Uuid>,
+    /// The message this message replies to, so that it can be shown as a quote.
+    /// Omitted if it doesn't cite any message, or the message can't be found or seen by the user.
+    #[schema(nullable = false)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quoted_message: Option<QuotedMessage>,
+    /// Previews of the external pages linked from the message, in order of the links.
+    /// Omitted if there are none, or link previews are disabled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub link_previews: Vec<LinkPreview>,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
     pub reactions: Vec<Reaction>,
+    /// Reactions aggregated per stamp for the user viewing the message, in order of the first
+    /// reaction with each stamp.
+    #[serde(default)]
+    pub reaction_summaries: Vec<ReactionSummary>,
+    /// Why the message was recommended.
+    /// Omitted outside of the recommended timeline.
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<RecommendationReason>,
+}
+
+/// A message quoted by another message.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotedMessage {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// The user who posted the message.
+    /// Omitted if the server hasn't cached the user info.
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    pub channel_id: Uuid,
+    pub content: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+impl From<MessageListItem> for QuotedMessage {
+    fn from(message: MessageListItem) -> Self {
+        QuotedMessage {
+            id: message.id,
+            user_id: message.user_id,
+            user: message.user,
+            channel_id: message.channel_id,
+            content: message.content,
+            created_at: message.created_at,
+        }
+    }
+}
+
+/// The sizes images proxied from traQ are served in.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Deserialize,
+    Serialize,
+    ToSchema,
+    EnumString,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum ImageSize {
+    #[default]
+    Original,
+    /// At most 512 pixels wide and high.
+    Medium,
+    /// At most 128 pixels wide and high.
+    Thumb,
+}
+
+impl ImageSize {
+    /// The maximum width and height, or `None` for the original.
+    pub fn max_dimension(self) -> Option<u32> {
+        match self {
+            ImageSize::Original => None,
+            ImageSize::Medium => Some(512),
+            ImageSize::Thumb => Some(128),
+        }
+    }
+}
+
+/// What a proxied image is of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum ImageKind {
+    Stamp,
+    UserIcon,
+}
+
+/// An image proxied from traQ, as stored in the cache.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedImage {
+    pub data: Vec<u8>,
+    pub content_type: String,
+    pub stored_at: OffsetDateTime,
+}
+
+/// The OpenGraph metadata of a page linked from a message, shown as a card.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkPreview {
+    /// The URL as linked from the message, before following redirects.
+    pub url: String,
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<String>,
+}
+
+impl LinkPreview {
+    /// A preview of a page without metadata, cached so that the page isn't fetched again.
+    pub fn empty(url: String) -> Self {
+        LinkPreview {
+            url,
+            title: None,
+            description: None,
+            image_url: None,
+        }
+    }
+
+    /// Whether there is anything to show. Images alone are not shown.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none()
+    }
+}
+
+/// A change to a message that outbound webhooks can subscribe to.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Deserialize,
+    Serialize,
+    ToSchema,
+    EnumString,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum WebhookEvent {
+    /// The content of a message was edited.
+    MessageEdited,
+    /// Stamps were added to or removed from a message.
+    ReactionsChanged,
+}
+
+/// An endpoint registered by an admin to be sent changes to messages.
+/// The secret its requests are signed with is never returned.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    /// The events sent to the webhook.
+    pub events: Vec<WebhookEvent>,
+    /// The admin who registered the webhook.
+    pub created_by: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// An operation expensive enough to be limited per user per day.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Deserialize,
+    Serialize,
+    ToSchema,
+    EnumString,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum QuotaOperation {
+    /// Searching messages, including running saved searches.
+    Search,
+}
+
+/// How much of a daily quota a user has used. Quotas are reset at midnight UTC.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaUsage {
+    pub operation: QuotaOperation,
+    pub used: i64,
+    pub limit: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub resets_at: OffsetDateTime,
+}
+
+/// A message popular on an instance, as published to peer instances. The author is never
+/// included, and the content only if the author allowed it.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Highlight {
+    pub reaction_count: i64,
+    /// The number of distinct stamps reacted with.
+    pub stamp_count: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[schema(nullable = false)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// The highlights of the past day on an instance, in the format published to peer instances.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceHighlights {
+    /// The version of this format, incremented on incompatible changes.
+    pub version: u32,
+    /// The name of the instance.
+    pub instance: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub generated_at: OffsetDateTime,
+    /// Most reacted first.
+    pub highlights: Vec<Highlight>,
+}
+
+/// The highlights of a peer instance, as last fetched from it.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerHighlights {
+    /// The base URL of the peer.
+    pub url: String,
+    pub highlights: InstanceHighlights,
+}
+
+/// The number of users with a session open in Twittra.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveUsers {
+    /// Users with a session open now.
+    pub online: i64,
+    /// Users with a session open in the past day.
+    pub daily: i64,
+}
+
+/// The main reason a message appears in the recommended timeline.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema, EnumString, IntoStaticStr,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum RecommendationReason {
+    /// The message has many reactions.
+    Popular,
+    /// The user frequently stamps the author's messages.
+    FrequentlyStampedAuthor,
+    /// The user frequently stamps messages in the channel.
+    FrequentlyStampedChannel,
+    /// Users with similar reactions posted the message.
+    SimilarUsers,
+    /// The message is one of the latest messages across channels.
+    /// Only recommended while the user has few reactions.
+    Recent,
+    /// The message is similar to messages the user stamped.
+    SimilarContent,
+}
+
+/// A recommended message served to a user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Impression {
+    pub message_id: Uuid,
+    pub reason: Option<RecommendationReason>,
+}
+
+impl From<&MessageListItem> for Impression {
+    fn from(message: &MessageListItem) -> Self {
+        Impression {
+            message_id: message.id,
+            reason: message.reason,
+        }
+    }
+}
+
+/// How users engaged with the messages recommended for a reason.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EngagementMetrics {
+    pub reason: RecommendationReason,
+    /// The number of distinct messages served to each user.
+    pub impressions: i64,
+    /// The number of impressions the user read afterwards.
+    pub reads: i64,
+    /// The number of impressions the user reacted to.
+    pub reactions: i64,
+}
+
+impl EngagementMetrics {
+    /// The click-through rate, counting reads as clicks.
+    pub fn click_through_rate(&self) -> f64 {
+        if self.impressions == 0 {
+            return 0.0;
+        }
+
+        self.reads as f64 / self.impressions as f64
+    }
+
+    pub fn reaction_rate(&self) -> f64 {
+        if self.impressions == 0 {
+            return 0.0;
+        }
+
+        self.reactions as f64 / self.impressions as f64
+    }
+}
+
+/// The embedding of a message's content.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageEmbedding {
+    pub message_id: Uuid,
+    pub embedding: Vec<f32>,
+}
+
+impl From<Message> for MessageListItem {
+    fn from(message: Message) -> Self {
+        MessageListItem {
+            id: message.id,
+            user_id: message.user_id,
+            user: None,
+            channel_id: message.channel_id,
+            channel: None,
+            reply_to_message_id: citation::extract_reply_to(&message.content),
+            quoted_message: None,
+            link_previews: vec![],
+            content: message.content,
+            created_at: message.created_at,
+            updated_at: message.updated_at,
+            reactions: message.reactions,
+            reaction_summaries: vec![],
+            reason: None,
+        }
+    }
+}
+
+impl MessageListItem {
+    /// Aggregates the reactions for the user viewing the message.
+    pub fn summarize_reactions(&mut self, viewer_id: &Uuid) {
+        self.reaction_summaries = ReactionSummary::summarize(&self.reactions, viewer_id);
+    }
+}
+
+/// The images needed to render a page of messages, in order of first appearance.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PageAssets {
+    /// The authors of the messages and the messages they quote.
+    pub user_ids: Vec<Uuid>,
+    /// The stamps the messages are reacted with.
+    pub stamp_ids: Vec<Uuid>,
+}
+
+impl PageAssets {
+    pub fn of(messages: &[MessageListItem]) -> Self {
+        let mut assets = Self::default();
+        let (mut users, mut stamps) = (HashSet::new(), HashSet::new());
+        for message in messages {
+            let quoted = message.quoted_message.as_ref().map(|quoted| quoted.user_id);
+            for user_id in iter::once(message.user_id).chain(quoted) {
+                if users.insert(user_id) {
+                    assets.user_ids.push(user_id);
+                }
+            }
+            for reaction in &message.reactions {
+                if stamps.insert(reaction.stamp_id) {
+                    assets.stamp_ids.push(reaction.stamp_id);
+                }
+            }
+        }
+        assets
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "camelCase")]
 pub struct Reaction {
     pub stamp_id: Uuid,
+    /// The name of the stamp, so that clients don't need to look up every stamp.
+    /// Omitted if the server hasn't cached the stamp.
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stamp_name: Option<String>,
     pub user_id: Uuid,
     pub stamp_count: i32,
 }
@@ -88,13 +812,63 @@ impl From<MessageStamp> for Reaction {
     fn from(value: MessageStamp) -> Self {
         Reaction {
             stamp_id: value.stamp_id,
+            stamp_name: None,
             user_id: value.user_id,
             stamp_count: value.count,
         }
     }
 }
 
-#[derive(Clone, Deserialize, Serialize, ToSchema)]
+/// The reactions to a message with a stamp.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionSummary {
+    pub stamp_id: Uuid,
+    #[schema(nullable = false)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stamp_name: Option<String>,
+    /// The number of times the stamp was added, counting every time a user added it.
+    pub count: i64,
+    /// The number of users who added the stamp.
+    pub user_count: i64,
+    pub reacted_by_me: bool,
+}
+
+impl ReactionSummary {
+    /// Aggregates reactions per stamp, in order of the first reaction with each stamp.
+    pub fn summarize(reactions: &[Reaction], viewer_id: &Uuid) -> Vec<ReactionSummary> {
+        let mut summaries: Vec<ReactionSummary> = vec![];
+
+        for reaction in reactions {
+            let summary = match summaries
+                .iter_mut()
+                .find(|s| s.stamp_id == reaction.stamp_id)
+            {
+                Some(summary) => summary,
+                None => {
+                    summaries.push(ReactionSummary {
+                        stamp_id: reaction.stamp_id,
+                        stamp_name: None,
+                        count: 0,
+                        user_count: 0,
+                        reacted_by_me: false,
+                    });
+                    summaries.last_mut().expect("a summary was just pushed")
+                }
+            };
+            if summary.stamp_name.is_none() {
+                summary.stamp_name.clone_from(&reaction.stamp_name);
+            }
+            summary.count += i64::from(reaction.stamp_count);
+            summary.user_count += 1;
+            summary.reacted_by_me |= reaction.user_id == *viewer_id;
+        }
+
+        summaries
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Stamp {
     pub id: Uuid,
@@ -120,7 +894,7 @@ impl From<StampWithThumbnail> for Stamp {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
     pub id: Uuid,
@@ -129,6 +903,43 @@ pub struct User {
     pub display_name: String,
 }
 
+/// How much a user interacts with an author or a channel.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AffinityScore {
+    /// The ID of the author or channel.
+    pub id: Uuid,
+    /// The number of the user's reactions to them, each counting half as much every half-life.
+    pub score: f64,
+}
+
+/// The authors and channels whose messages are recommended to a user for their affinity,
+/// highest first.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Affinity {
+    pub authors: Vec<AffinityScore>,
+    pub channels: Vec<AffinityScore>,
+}
+
+/// A user with activity stats for their profile page.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserProfile {
+    #[serde(flatten)]
+    pub user: User,
+    pub stats: UserStats,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStats {
+    /// The number of the user's messages crawled by Twittra.
+    pub message_count: i64,
+    /// The number of stamps added to the user's messages posted in the last 30 days.
+    pub reactions_received: i64,
+}
+
 impl From<MyUserDetail> for User {
     fn from(value: MyUserDetail) -> Self {
         User {
@@ -155,3 +966,129 @@ pub struct UserToken {
     pub user_id: Uuid,
     pub access_token: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_factories::{MessageListItemBuilder, ReactionBuilder};
+    use fake::{Fake, uuid::UUIDv4};
+
+    fn traq_channel(name: &str, parent_id: Option<Uuid>) -> models::Channel {
+        models::Channel {
+            id: UUIDv4.fake(),
+            parent_id,
+            archived: false,
+            force: false,
+            topic: String::new(),
+            name: name.to_string(),
+            children: vec![],
+        }
+    }
+
+    #[test]
+    fn channels_from_traq_tree_have_full_paths() {
+        let root = traq_channel("gps", None);
+        let child = traq_channel("times", Some(root.id));
+        let grandchild = models::Channel {
+            archived: true,
+            ..traq_channel("alice", Some(child.id))
+        };
+        let orphan = traq_channel("orphan", Some(UUIDv4.fake()));
+        let ids = [root.id, child.id, grandchild.id, orphan.id];
+
+        let channels = Channel::from_traq_tree(vec![root, child, grandchild, orphan]);
+
+        let paths: Vec<(Uuid, &str, bool)> = channels
+            .iter()
+            .map(|c| (c.id, c.path.as_str(), c.archived))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                (ids[0], "gps", false),
+                (ids[1], "gps/times", false),
+                (ids[2], "gps/times/alice", true),
+                (ids[3], "orphan", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn message_cursors_round_trip() {
+        let cursor = MessageCursor {
+            created_at: OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_000)
+                .unwrap(),
+            id: UUIDv4.fake(),
+        };
+
+        assert_eq!(MessageCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(MessageCursor::decode("not a cursor"), None);
+        assert_eq!(MessageCursor::decode("123_not-a-uuid"), None);
+    }
+
+    #[test]
+    fn reactions_are_summarized_per_stamp() {
+        let (viewer_id, other_id): (Uuid, Uuid) = (UUIDv4.fake(), UUIDv4.fake());
+        let (first_stamp_id, second_stamp_id): (Uuid, Uuid) = (UUIDv4.fake(), UUIDv4.fake());
+        let reaction = |stamp_id, stamp_name: Option<&str>, user_id, stamp_count| Reaction {
+            stamp_id,
+            stamp_name: stamp_name.map(str::to_string),
+            user_id,
+            stamp_count,
+        };
+        let reactions = vec![
+            reaction(first_stamp_id, None, other_id, 3),
+            reaction(second_stamp_id, Some("second"), other_id, 1),
+            reaction(first_stamp_id, Some("first"), viewer_id, 2),
+        ];
+
+        assert_eq!(
+            ReactionSummary::summarize(&reactions, &viewer_id),
+            vec![
+                ReactionSummary {
+                    stamp_id: first_stamp_id,
+                    stamp_name: Some("first".to_string()),
+                    count: 5,
+                    user_count: 2,
+                    reacted_by_me: true,
+                },
+                ReactionSummary {
+                    stamp_id: second_stamp_id,
+                    stamp_name: Some("second".to_string()),
+                    count: 1,
+                    user_count: 1,
+                    reacted_by_me: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn page_assets_are_distinct_in_order_of_appearance() {
+        let (alice, bob): (Uuid, Uuid) = (UUIDv4.fake(), UUIDv4.fake());
+        let (first_stamp, second_stamp): (Uuid, Uuid) = (UUIDv4.fake(), UUIDv4.fake());
+        let quoted = MessageListItemBuilder::new().user_id(alice).build();
+        let reply = MessageListItem {
+            quoted_message: Some(quoted.clone().into()),
+            ..MessageListItemBuilder::new()
+                .user_id(bob)
+                .reactions(vec![
+                    ReactionBuilder::new().stamp_id(first_stamp).build(),
+                    ReactionBuilder::new().stamp_id(second_stamp).build(),
+                ])
+                .build()
+        };
+        let other = MessageListItemBuilder::new()
+            .user_id(alice)
+            .reactions(vec![ReactionBuilder::new().stamp_id(first_stamp).build()])
+            .build();
+
+        assert_eq!(
+            PageAssets::of(&[reply, other, quoted]),
+            PageAssets {
+                user_ids: vec![bob, alice],
+                stamp_ids: vec![first_stamp, second_stamp],
+            }
+        );
+    }
+}
@@ -1,10 +1,126 @@
-use crate::model::Message;
+use crate::{event::MessageDelta, model::Message};
 use async_trait::async_trait;
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    mem,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
 
 /// Trait for notifying external systems about message updates.
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
 pub trait MessageNotifier: Send + Sync {
     /// Notifies that a message has been updated.
-    async fn notify_message_updated(&self, message: &Message);
+    /// `delta` holds the changes from the previously saved message.
+    async fn notify_message_updated(&self, message: &Message, delta: &MessageDelta);
+}
+
+/// Sends every notification to each of the notifiers in turn, e.g. to clients and webhooks.
+pub struct FanOutNotifier {
+    notifiers: Vec<Arc<dyn MessageNotifier>>,
+}
+
+impl FanOutNotifier {
+    pub fn new(notifiers: Vec<Arc<dyn MessageNotifier>>) -> Self {
+        Self { notifiers }
+    }
+}
+
+#[async_trait]
+impl MessageNotifier for FanOutNotifier {
+    async fn notify_message_updated(&self, message: &Message, delta: &MessageDelta) {
+        for notifier in &self.notifiers {
+            notifier.notify_message_updated(message, delta).await;
+        }
+    }
+}
+
+/// Holds notifications back until [`flush`](Self::flush), sending one per message with the
+/// changes since the last flush combined, so that clients aren't notified of every crawl while a
+/// message is getting reactions.
+pub struct CoalescingNotifier {
+    inner: Arc<dyn MessageNotifier>,
+    pending: Mutex<HashMap<Uuid, (Message, MessageDelta)>>,
+}
+
+impl CoalescingNotifier {
+    pub fn new(inner: Arc<dyn MessageNotifier>) -> Self {
+        Self {
+            inner,
+            pending: Mutex::default(),
+        }
+    }
+
+    /// Sends the notifications held since the last flush.
+    pub async fn flush(&self) {
+        let pending = mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()));
+
+        for (message, delta) in pending.values() {
+            self.inner.notify_message_updated(message, delta).await;
+        }
+    }
+}
+
+#[async_trait]
+impl MessageNotifier for CoalescingNotifier {
+    async fn notify_message_updated(&self, message: &Message, delta: &MessageDelta) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+
+        match pending.entry(message.id) {
+            Entry::Occupied(mut entry) => {
+                let (held_message, held_delta) = entry.get_mut();
+                *held_message = message.clone();
+                *held_delta = held_delta.followed_by(delta);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((message.clone(), delta.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_factories::{MessageBuilder, ReactionBuilder};
+    use mockall::predicate;
+
+    #[tokio::test]
+    async fn coalesces_notifications_until_flushed() {
+        let original = MessageBuilder::new().build();
+        let edited = MessageBuilder::new()
+            .id(original.id)
+            .content("edited")
+            .build();
+        let reacted = MessageBuilder::new()
+            .id(original.id)
+            .content("edited")
+            .reactions(vec![ReactionBuilder::new().build()])
+            .build();
+        let expected = MessageDelta {
+            id: original.id,
+            updated_at: reacted.updated_at,
+            content: Some("edited".to_string()),
+            reactions: Some(reacted.reactions.clone()),
+        };
+
+        let mut mock_notifier = MockMessageNotifier::new();
+        mock_notifier
+            .expect_notify_message_updated()
+            .with(predicate::eq(reacted.clone()), predicate::eq(expected))
+            .times(1)
+            .returning(|_, _| ());
+        let notifier = CoalescingNotifier::new(Arc::new(mock_notifier));
+
+        notifier
+            .notify_message_updated(&edited, &MessageDelta::between(&original, &edited))
+            .await;
+        notifier
+            .notify_message_updated(&reacted, &MessageDelta::between(&edited, &reacted))
+            .await;
+        notifier.flush().await;
+        // Nothing is held after a flush
+        notifier.flush().await;
+    }
 }
@@ -0,0 +1,208 @@
+//! Messages quoted by the messages citing them, attached so that clients can render quotes without
+//! fetching every quoted message.
+
+use crate::{
+    error::{DomainError, TraqClientError},
+    model::{MessageListItem, QuotedMessage},
+    repository::Repository,
+    traq_client::TraqClient,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use uuid::Uuid;
+
+/// Quoted messages not in the cache are fetched from traQ one by one, so only this many are
+/// fetched per request.
+const MAX_TRAQ_FETCHES: usize = 5;
+
+#[derive(Debug)]
+pub struct QuoteResolver {
+    client: Arc<dyn TraqClient>,
+    repo: Repository,
+}
+
+impl QuoteResolver {
+    pub fn new(client: Arc<dyn TraqClient>, repo: Repository) -> Self {
+        Self { client, repo }
+    }
+
+    /// Attaches the messages quoted by `messages` as seen by the user.
+    /// Quotes that can't be resolved are left out, since messages can be shown without them.
+    pub async fn attach(&self, user_id: &Uuid, messages: &mut [MessageListItem]) {
+        let quoted_ids: HashSet<Uuid> = messages
+            .iter()
+            .filter_map(|m| m.reply_to_message_id)
+            .collect();
+        if quoted_ids.is_empty() {
+            return;
+        }
+
+        let quotes = match self.resolve(user_id, quoted_ids).await {
+            Ok(quotes) => quotes,
+            Err(e) => {
+                tracing::warn!("Failed to resolve quoted messages: {:?}", e);
+                return;
+            }
+        };
+        for message in messages {
+            message.quoted_message = message
+                .reply_to_message_id
+                .and_then(|id| quotes.get(&id))
+                .cloned();
+        }
+    }
+
+    async fn resolve(
+        &self,
+        user_id: &Uuid,
+        quoted_ids: HashSet<Uuid>,
+    ) -> Result<HashMap<Uuid, QuotedMessage>, DomainError> {
+        let quoted_ids: Vec<Uuid> = quoted_ids.into_iter().collect();
+        let (cached, blocked_users) = tokio::try_join!(
+            self.repo.message_reader.find_list_items_by_ids(&quoted_ids),
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+        )?;
+        let mut quotes: HashMap<Uuid, QuotedMessage> = cached
+            .into_iter()
+            .map(|m| (m.id, QuotedMessage::from(m)))
+            .collect();
+
+        let missing: Vec<Uuid> = quoted_ids
+            .into_iter()
+            .filter(|id| !quotes.contains_key(id))
+            .take(MAX_TRAQ_FETCHES)
+            .collect();
+        // The user's own token is used, so that messages in channels they can't read are not shown
+        if !missing.is_empty()
+            && let Some(token) = self.repo.user.find_token_by_user_id(user_id).await?
+        {
+            for id in missing {
+                match self.client.get_message(&token, &id).await {
+                    Ok(message) => {
+                        let mut quote = QuotedMessage::from(MessageListItem::from(message));
+                        quote.user = self.repo.user.find_by_id(&quote.user_id).await?;
+                        quotes.insert(id, quote);
+                    }
+                    // Deleted messages and messages the user can't read are not quoted
+                    Err(TraqClientError::ApiError { .. }) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+
+        quotes.retain(|_, quote| !blocked_users.contains(&quote.user_id));
+        Ok(quotes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        repository::{MockBlockRepository, MockMessageReader, MockUserRepository},
+        test_factories::{MessageBuilder, MessageListItemBuilder, RepositoryBuilder},
+        traq_client::MockTraqClient,
+    };
+    use fake::{Fake, uuid::UUIDv4};
+    use http::StatusCode;
+    use mockall::predicate;
+
+    fn citing(message_id: Uuid) -> MessageListItem {
+        MessageListItemBuilder::new()
+            .content(format!("https://q.trap.jp/messages/{}", message_id))
+            .build()
+    }
+
+    fn no_blocks() -> MockBlockRepository {
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_block_repo
+    }
+
+    #[tokio::test]
+    async fn attaches_cached_quotes_without_calling_traq() {
+        let quoted = MessageListItemBuilder::new().build();
+        let blocked = MessageListItemBuilder::new().build();
+        let blocked_user_id = blocked.user_id;
+        let cached = vec![quoted.clone(), blocked.clone()];
+        let mut messages = vec![
+            citing(quoted.id),
+            citing(blocked.id),
+            MessageListItemBuilder::new().build(),
+        ];
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_list_items_by_ids()
+            .times(1)
+            .returning(move |_| Ok(cached.clone()));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(move |_| Ok(vec![blocked_user_id]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let resolver = QuoteResolver::new(Arc::new(MockTraqClient::new()), repo);
+        resolver.attach(&UUIDv4.fake(), &mut messages).await;
+
+        assert_eq!(messages[0].quoted_message, Some(quoted.into()));
+        assert_eq!(messages[1].quoted_message, None);
+        assert_eq!(messages[2].quoted_message, None);
+    }
+
+    #[tokio::test]
+    async fn fetches_uncached_quotes_with_the_users_token() {
+        let user_id: Uuid = UUIDv4.fake();
+        let quoted = MessageBuilder::new().build();
+        let inaccessible_id: Uuid = UUIDv4.fake();
+        let mut messages = vec![citing(quoted.id), citing(inaccessible_id)];
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_list_items_by_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_token_by_user_id()
+            .with(predicate::eq(user_id))
+            .times(1)
+            .returning(|_| Ok(Some("token".to_string())));
+        mock_user_repo.expect_find_by_id().returning(|_| Ok(None));
+        let mut mock_client = MockTraqClient::new();
+        let message = quoted.clone();
+        mock_client
+            .expect_get_message()
+            .withf(move |token, id| token == "token" && *id == message.id)
+            .times(1)
+            .returning(move |_, _| Ok(quoted.clone()));
+        mock_client
+            .expect_get_message()
+            .withf(move |_, id| *id == inaccessible_id)
+            .times(1)
+            .returning(|_, _| {
+                Err(TraqClientError::ApiError {
+                    status: StatusCode::FORBIDDEN,
+                    message: "forbidden".to_string(),
+                })
+            });
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(no_blocks())
+            .user(mock_user_repo)
+            .build();
+        let resolver = QuoteResolver::new(Arc::new(mock_client), repo);
+        resolver.attach(&user_id, &mut messages).await;
+
+        let quote = messages[0].quoted_message.as_ref().unwrap();
+        assert_eq!(Some(quote.id), messages[0].reply_to_message_id);
+        assert_eq!(messages[1].quoted_message, None);
+    }
+}
@@ -0,0 +1,474 @@
+use crate::model::{MessageListItem, RecommendationReason};
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fmt::Debug,
+};
+use uuid::Uuid;
+
+/// Ignored recommendations beyond this number don't lower scores any further, so that skipping a
+/// source only ever makes it a little less likely to be recommended.
+const MAX_IGNORED_PENALTIES: i32 = 10;
+
+/// Weights used to score recommendation candidates.
+/// A candidate at rank `i` (0-based) of a source scores `base + (50 - i) * rank_multiplier`,
+/// and scores from multiple sources are added up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RankingWeights {
+    /// Messages with many reactions.
+    pub top_reacted: SourceWeights,
+    /// Messages from authors the user frequently stamps.
+    pub affinity_author: SourceWeights,
+    /// Messages in channels the user frequently stamps in.
+    pub affinity_channel: SourceWeights,
+    /// Messages from users with similar reactions.
+    pub similar_user: SourceWeights,
+    /// The latest messages across channels, used for users with few reactions.
+    pub recent: SourceWeights,
+    /// Messages with content similar to messages the user stamped.
+    pub similar_content: SourceWeights,
+    /// Score multiplier applied per hidden message by the same author.
+    pub hidden_author_penalty: f64,
+    /// Score multiplier applied per hidden message in the same channel.
+    pub hidden_channel_penalty: f64,
+    /// Score multiplier applied per recommended message by the same author the user never read.
+    pub ignored_author_penalty: f64,
+    /// Score multiplier applied per recommended message in the same channel the user never read.
+    pub ignored_channel_penalty: f64,
+    /// The maximum number of messages by the same author before messages by other authors.
+    /// Further messages by the author only fill the timeline once other candidates run out.
+    pub max_messages_per_author: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SourceWeights {
+    pub base: f64,
+    pub rank_multiplier: f64,
+}
+
+impl SourceWeights {
+    pub const fn new(base: f64, rank_multiplier: f64) -> Self {
+        Self {
+            base,
+            rank_multiplier,
+        }
+    }
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            top_reacted: SourceWeights::new(5.0, 0.1),
+            affinity_author: SourceWeights::new(5.0, 0.15),
+            affinity_channel: SourceWeights::new(3.0, 0.1),
+            similar_user: SourceWeights::new(5.0, 0.1),
+            recent: SourceWeights::new(3.0, 0.1),
+            similar_content: SourceWeights::new(4.0, 0.1),
+            hidden_author_penalty: 0.5,
+            hidden_channel_penalty: 0.8,
+            ignored_author_penalty: 0.97,
+            ignored_channel_penalty: 0.98,
+            max_messages_per_author: 3,
+        }
+    }
+}
+
+impl RankingWeights {
+    /// Checks that the weights keep scores finite and non-negative.
+    pub fn validate(&self) -> Result<(), String> {
+        let sources = [
+            ("top_reacted", self.top_reacted),
+            ("affinity_author", self.affinity_author),
+            ("affinity_channel", self.affinity_channel),
+            ("similar_user", self.similar_user),
+            ("recent", self.recent),
+            ("similar_content", self.similar_content),
+        ];
+        for (name, weights) in sources {
+            if !(weights.base.is_finite() && weights.base >= 0.0) {
+                return Err(format!("{name}.base must be a non-negative number"));
+            }
+            if !(weights.rank_multiplier.is_finite() && weights.rank_multiplier >= 0.0) {
+                return Err(format!(
+                    "{name}.rank_multiplier must be a non-negative number"
+                ));
+            }
+        }
+
+        let penalties = [
+            ("hidden_author_penalty", self.hidden_author_penalty),
+            ("hidden_channel_penalty", self.hidden_channel_penalty),
+            ("ignored_author_penalty", self.ignored_author_penalty),
+            ("ignored_channel_penalty", self.ignored_channel_penalty),
+        ];
+        for (name, penalty) in penalties {
+            if !(penalty > 0.0 && penalty <= 1.0) {
+                return Err(format!("{name} must be greater than 0 and at most 1"));
+            }
+        }
+
+        if self.max_messages_per_author == 0 {
+            return Err("max_messages_per_author must be at least 1".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// A recommendation candidate found by one of the candidate sources.
+/// A message found by multiple sources appears once per source.
+#[derive(Clone, Debug)]
+pub struct ScoredCandidate {
+    pub message: MessageListItem,
+    /// The source that found the message.
+    pub source: RecommendationReason,
+    /// The 0-based position of the message in the results of its source.
+    pub rank: usize,
+    /// The number of messages by the same author the user has hidden.
+    pub hidden_author_count: i32,
+    /// The number of messages in the same channel the user has hidden.
+    pub hidden_channel_count: i32,
+    /// The number of recommended messages by the same author the user never read.
+    pub ignored_author_count: i32,
+    /// The number of recommended messages in the same channel the user never read.
+    pub ignored_channel_count: i32,
+    /// The multiplier the user set for the channel, or 1.
+    pub channel_score_multiplier: f64,
+}
+
+/// Orders recommendation candidates into a timeline.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+pub trait Ranker: Debug + Send + Sync {
+    /// Merges candidates of the same message and returns the messages, best first.
+    fn rank(&self, candidates: Vec<ScoredCandidate>) -> Vec<MessageListItem>;
+}
+
+/// The default ranker, which adds up weighted scores of every source that found a message.
+#[derive(Clone, Debug, Default)]
+pub struct HeuristicRanker {
+    weights: RankingWeights,
+}
+
+impl HeuristicRanker {
+    pub fn new(weights: RankingWeights) -> Self {
+        Self { weights }
+    }
+
+    fn source_weights(&self, source: RecommendationReason) -> SourceWeights {
+        match source {
+            RecommendationReason::Popular => self.weights.top_reacted,
+            RecommendationReason::FrequentlyStampedAuthor => self.weights.affinity_author,
+            RecommendationReason::FrequentlyStampedChannel => self.weights.affinity_channel,
+            RecommendationReason::SimilarUsers => self.weights.similar_user,
+            RecommendationReason::Recent => self.weights.recent,
+            RecommendationReason::SimilarContent => self.weights.similar_content,
+        }
+    }
+}
+
+impl Ranker for HeuristicRanker {
+    fn rank(&self, candidates: Vec<ScoredCandidate>) -> Vec<MessageListItem> {
+        // Messages stay in `candidates` until the order is decided, and only small entries
+        // pointing into it are merged and sorted
+        let mut entries = Vec::<MergedEntry>::with_capacity(candidates.len());
+        let mut entry_by_message = HashMap::<Uuid, usize>::with_capacity(candidates.len());
+
+        for (index, candidate) in candidates.iter().enumerate() {
+            let weights = self.source_weights(candidate.source);
+            let rank_score = (50.0 - candidate.rank as f64).max(0.0) * weights.rank_multiplier;
+            let total_score = weights.base + rank_score;
+            let penalty = self
+                .weights
+                .hidden_author_penalty
+                .powi(candidate.hidden_author_count)
+                * self
+                    .weights
+                    .hidden_channel_penalty
+                    .powi(candidate.hidden_channel_count)
+                * self
+                    .weights
+                    .ignored_author_penalty
+                    .powi(candidate.ignored_author_count.min(MAX_IGNORED_PENALTIES))
+                * self
+                    .weights
+                    .ignored_channel_penalty
+                    .powi(candidate.ignored_channel_count.min(MAX_IGNORED_PENALTIES))
+                * candidate.channel_score_multiplier;
+
+            match entry_by_message.entry(candidate.message.id) {
+                Entry::Occupied(entry) => {
+                    let merged = &mut entries[*entry.get()];
+                    merged.score += total_score * penalty;
+                    // The source that contributes the most explains the recommendation
+                    if total_score > merged.best_source_score {
+                        merged.best_source_score = total_score;
+                        merged.reason = candidate.source;
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(entries.len());
+                    entries.push(MergedEntry {
+                        candidate: index,
+                        score: total_score * penalty,
+                        best_source_score: total_score,
+                        reason: candidate.source,
+                    });
+                }
+            }
+        }
+
+        // Stable, so ties keep the order the sources found the messages in
+        entries.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let mut candidates: Vec<Option<ScoredCandidate>> =
+            candidates.into_iter().map(Some).collect();
+        let mut take = |entry: &MergedEntry| {
+            let mut message = candidates[entry.candidate]
+                .take()
+                .expect("every candidate is taken at most once")
+                .message;
+            message.reason = Some(entry.reason);
+            message
+        };
+
+        // Defer messages by authors who already have enough messages, so that the next-highest
+        // scored messages by other authors take their place
+        let mut author_counts = HashMap::<Uuid, usize>::new();
+        let mut ranked = Vec::with_capacity(entries.len());
+        let mut deferred = vec![];
+        for entry in &entries {
+            let message = take(entry);
+            let count = author_counts.entry(message.user_id).or_default();
+            *count += 1;
+            if *count <= self.weights.max_messages_per_author {
+                ranked.push(message);
+            } else {
+                deferred.push(message);
+            }
+        }
+        ranked.append(&mut deferred);
+
+        ranked
+    }
+}
+
+/// Scores of every candidate of a message, merged into the first one.
+struct MergedEntry {
+    /// The index of the first candidate of the message.
+    candidate: usize,
+    score: f64,
+    /// The largest score from a single source.
+    best_source_score: f64,
+    reason: RecommendationReason,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_factories::{MessageListItemBuilder, candidate_sources};
+    use fake::{Fake, uuid::UUIDv4};
+    use std::collections::HashSet;
+
+    fn candidate(
+        message: &MessageListItem,
+        source: RecommendationReason,
+        rank: usize,
+    ) -> ScoredCandidate {
+        ScoredCandidate {
+            message: message.clone(),
+            source,
+            rank,
+            hidden_author_count: 0,
+            hidden_channel_count: 0,
+            ignored_author_count: 0,
+            ignored_channel_count: 0,
+            channel_score_multiplier: 1.0,
+        }
+    }
+
+    #[test]
+    fn heuristic_ranker_adds_up_sources() {
+        let popular = MessageListItemBuilder::new().build();
+        let both = MessageListItemBuilder::new().build();
+        let candidates = vec![
+            candidate(&popular, RecommendationReason::Popular, 0),
+            candidate(&both, RecommendationReason::Popular, 1),
+            candidate(&both, RecommendationReason::FrequentlyStampedChannel, 0),
+        ];
+
+        let ranked = HeuristicRanker::default().rank(candidates);
+
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].id, both.id);
+        // Popular contributes 5 + 49 * 0.1, more than the channel affinity
+        assert_eq!(ranked[0].reason, Some(RecommendationReason::Popular));
+        assert_eq!(ranked[1].id, popular.id);
+    }
+
+    #[test]
+    fn heuristic_ranker_caps_messages_per_author() {
+        let author = UUIDv4.fake();
+        let by_author: Vec<MessageListItem> = (0..3)
+            .map(|_| MessageListItemBuilder::new().user_id(author).build())
+            .collect();
+        let other = MessageListItemBuilder::new().build();
+        let mut candidates: Vec<ScoredCandidate> = by_author
+            .iter()
+            .enumerate()
+            .map(|(i, m)| candidate(m, RecommendationReason::Popular, i))
+            .collect();
+        candidates.push(candidate(&other, RecommendationReason::Popular, 10));
+
+        let ranker = HeuristicRanker::new(RankingWeights {
+            max_messages_per_author: 2,
+            ..Default::default()
+        });
+        let ranked: Vec<Uuid> = ranker.rank(candidates).iter().map(|m| m.id).collect();
+
+        assert_eq!(
+            ranked,
+            vec![by_author[0].id, by_author[1].id, other.id, by_author[2].id]
+        );
+    }
+
+    #[test]
+    fn heuristic_ranker_merges_overlapping_sources() {
+        let sources = candidate_sources(50);
+        let unique_ids: HashSet<Uuid> = sources
+            .iter()
+            .flat_map(|(_, messages)| messages.iter().map(|m| m.id))
+            .collect();
+        let candidates: Vec<ScoredCandidate> = sources
+            .iter()
+            .flat_map(|(source, messages)| {
+                messages
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, m)| candidate(m, *source, rank))
+            })
+            .collect();
+        assert!(unique_ids.len() < candidates.len());
+
+        let ranked = HeuristicRanker::default().rank(candidates);
+
+        assert_eq!(ranked.len(), unique_ids.len());
+        assert!(ranked.iter().all(|m| m.reason.is_some()));
+    }
+
+    #[test]
+    fn heuristic_ranker_keeps_source_order_for_ties() {
+        let messages: Vec<MessageListItem> = (0..5)
+            .map(|_| MessageListItemBuilder::new().build())
+            .collect();
+        // Ranks past 50 all score the base weight
+        let candidates = messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| candidate(m, RecommendationReason::Popular, 50 + i))
+            .collect();
+
+        let ranked: Vec<Uuid> = HeuristicRanker::default()
+            .rank(candidates)
+            .iter()
+            .map(|m| m.id)
+            .collect();
+
+        assert_eq!(ranked, messages.iter().map(|m| m.id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn heuristic_ranker_downranks_hidden_authors() {
+        let hidden_author = MessageListItemBuilder::new().build();
+        let other = MessageListItemBuilder::new().build();
+        let candidates = vec![
+            ScoredCandidate {
+                hidden_author_count: 2,
+                ..candidate(&hidden_author, RecommendationReason::Popular, 0)
+            },
+            candidate(&other, RecommendationReason::Popular, 10),
+        ];
+
+        let ranked = HeuristicRanker::default().rank(candidates);
+
+        assert_eq!(ranked[0].id, other.id);
+        assert_eq!(ranked[1].id, hidden_author.id);
+    }
+
+    #[test]
+    fn heuristic_ranker_applies_channel_score_multipliers() {
+        let boosted = MessageListItemBuilder::new().build();
+        let other = MessageListItemBuilder::new().build();
+        let candidates = vec![
+            candidate(&other, RecommendationReason::Popular, 0),
+            ScoredCandidate {
+                channel_score_multiplier: 2.0,
+                ..candidate(&boosted, RecommendationReason::Popular, 49)
+            },
+        ];
+
+        let ranked = HeuristicRanker::default().rank(candidates);
+
+        assert_eq!(ranked[0].id, boosted.id);
+        assert_eq!(ranked[1].id, other.id);
+    }
+
+    #[test]
+    fn heuristic_ranker_mildly_downranks_ignored_sources() {
+        let ignored = MessageListItemBuilder::new().build();
+        let close = MessageListItemBuilder::new().build();
+        let far = MessageListItemBuilder::new().build();
+        let candidates = vec![
+            ScoredCandidate {
+                ignored_author_count: 100,
+                ignored_channel_count: 100,
+                ..candidate(&ignored, RecommendationReason::Popular, 0)
+            },
+            candidate(&close, RecommendationReason::Popular, 1),
+            candidate(&far, RecommendationReason::Popular, 49),
+        ];
+
+        let ranked: Vec<Uuid> = HeuristicRanker::default()
+            .rank(candidates)
+            .iter()
+            .map(|m| m.id)
+            .collect();
+
+        // The penalty is capped, so the message still beats candidates ranked much lower
+        assert_eq!(ranked, vec![close.id, ignored.id, far.id]);
+    }
+
+    #[test]
+    fn default_weights_are_valid() {
+        assert!(RankingWeights::default().validate().is_ok());
+    }
+
+    #[test]
+    fn invalid_weights_are_rejected() {
+        let weights = RankingWeights {
+            affinity_channel: SourceWeights::new(-1.0, 0.1),
+            ..Default::default()
+        };
+        assert!(
+            weights
+                .validate()
+                .unwrap_err()
+                .contains("affinity_channel.base")
+        );
+
+        let weights = RankingWeights {
+            top_reacted: SourceWeights::new(5.0, f64::NAN),
+            ..Default::default()
+        };
+        assert!(weights.validate().is_err());
+
+        let weights = RankingWeights {
+            hidden_author_penalty: 1.5,
+            ..Default::default()
+        };
+        assert!(
+            weights
+                .validate()
+                .unwrap_err()
+                .contains("hidden_author_penalty")
+        );
+    }
+}
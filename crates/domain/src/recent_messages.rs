@@ -0,0 +1,111 @@
+use crate::model::{Message, MessageListItem};
+use std::{collections::VecDeque, sync::Mutex};
+
+/// Keeps the most recently crawled messages in memory.
+/// It is used to serve a reduced timeline while the repository is briefly unavailable.
+#[derive(Debug)]
+pub struct RecentMessages {
+    capacity: usize,
+    messages: Mutex<VecDeque<Message>>,
+}
+
+impl RecentMessages {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Adds messages to the buffer, replacing ones with the same ID.
+    /// The oldest messages are dropped when the buffer is full.
+    pub fn extend(&self, messages: &[Message]) {
+        let mut buffer = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+
+        for message in messages {
+            buffer.retain(|m| m.id != message.id);
+            buffer.push_back(message.clone());
+        }
+
+        buffer.make_contiguous().sort_by_key(|m| m.created_at);
+        while buffer.len() > self.capacity {
+            buffer.pop_front();
+        }
+    }
+
+    /// Returns up to `limit` buffered messages, newest first.
+    pub fn latest(&self, limit: usize) -> Vec<MessageListItem> {
+        let buffer = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+
+        buffer
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .map(MessageListItem::from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_factories::MessageBuilder;
+    use time::{Duration, OffsetDateTime};
+
+    #[test]
+    fn latest_returns_newest_first() {
+        let buffer = RecentMessages::new(10);
+        let now = OffsetDateTime::now_utc();
+        let older = MessageBuilder::new()
+            .created_at(now - Duration::minutes(2))
+            .build();
+        let newer = MessageBuilder::new()
+            .created_at(now - Duration::minutes(1))
+            .build();
+
+        buffer.extend(&[newer.clone(), older.clone()]);
+
+        let latest = buffer.latest(10);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].id, newer.id);
+        assert_eq!(latest[1].id, older.id);
+    }
+
+    #[test]
+    fn extend_drops_oldest_messages_over_capacity() {
+        let buffer = RecentMessages::new(2);
+        let now = OffsetDateTime::now_utc();
+        let messages: Vec<Message> = (0..3)
+            .map(|i| {
+                MessageBuilder::new()
+                    .created_at(now - Duration::minutes(i))
+                    .build()
+            })
+            .collect();
+
+        buffer.extend(&messages);
+
+        let latest = buffer.latest(10);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].id, messages[0].id);
+        assert_eq!(latest[1].id, messages[1].id);
+    }
+
+    #[test]
+    fn extend_replaces_messages_with_same_id() {
+        let buffer = RecentMessages::new(10);
+        let message = MessageBuilder::new().content("old").build();
+        let updated = Message {
+            content: "new".to_string(),
+            ..message.clone()
+        };
+
+        buffer.extend(&[message]);
+        buffer.extend(&[updated]);
+
+        let latest = buffer.latest(10);
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].content, "new");
+    }
+}
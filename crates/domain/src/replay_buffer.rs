@@ -0,0 +1,212 @@
+use crate::{
+    error::RepositoryError,
+    model::{MessageEvent, MessageEventKind},
+    repository::MessageEventRepository,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+/// Keeps the most recent message events in memory so that catching up after a short gap does not
+/// hit the database.
+/// The event log is shared by all users and filtered per user when read, so a single buffer
+/// serves every user.
+#[derive(Debug)]
+pub struct EventReplayBuffer {
+    capacity: usize,
+    state: Mutex<BufferState>,
+}
+
+#[derive(Debug, Default)]
+struct BufferState {
+    events: VecDeque<MessageEvent>,
+    /// The buffer holds every event after this cursor.
+    /// `None` until the latest cursor is known, as events before startup are not buffered.
+    complete_after: Option<i64>,
+}
+
+impl EventReplayBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(BufferState {
+                events: VecDeque::with_capacity(capacity),
+                complete_after: None,
+            }),
+        }
+    }
+
+    /// Adds newly appended events, keeping them ordered by cursor.
+    /// The oldest events are dropped when the buffer is full.
+    pub fn push(&self, events: &[MessageEvent]) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        for event in events {
+            // Concurrent appends may arrive out of order
+            let index = state.events.partition_point(|e| e.cursor < event.cursor);
+            state.events.insert(index, event.clone());
+        }
+
+        while state.events.len() > self.capacity {
+            if let Some(evicted) = state.events.pop_front()
+                && let Some(complete_after) = &mut state.complete_after
+            {
+                *complete_after = (*complete_after).max(evicted.cursor);
+            }
+        }
+    }
+
+    /// Marks the buffer as complete after `latest_cursor`, the latest cursor in the event log.
+    /// It does nothing once the buffer is complete.
+    pub fn seed(&self, latest_cursor: i64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if state.complete_after.is_none() {
+            state.events.retain(|e| e.cursor > latest_cursor);
+            state.complete_after = Some(latest_cursor);
+        }
+    }
+
+    /// Returns up to `limit` events after `cursor`, oldest first.
+    /// Returns `None` if some of the events may not be in the buffer.
+    pub fn since(&self, cursor: i64, limit: usize) -> Option<Vec<MessageEvent>> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if cursor < state.complete_after? {
+            return None;
+        }
+
+        Some(
+            state
+                .events
+                .iter()
+                .filter(|e| e.cursor > cursor)
+                .take(limit)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// Serves recent events from an [`EventReplayBuffer`] and falls back to the inner repository for
+/// longer gaps.
+#[derive(Debug)]
+pub struct BufferedMessageEventRepository {
+    inner: Arc<dyn MessageEventRepository>,
+    buffer: EventReplayBuffer,
+}
+
+impl BufferedMessageEventRepository {
+    pub fn new(inner: Arc<dyn MessageEventRepository>, capacity: usize) -> Self {
+        Self {
+            inner,
+            buffer: EventReplayBuffer::new(capacity),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageEventRepository for BufferedMessageEventRepository {
+    async fn append(
+        &self,
+        message_ids: &[Uuid],
+        kind: MessageEventKind,
+    ) -> Result<Vec<MessageEvent>, RepositoryError> {
+        let events = self.inner.append(message_ids, kind).await?;
+        self.buffer.push(&events);
+        Ok(events)
+    }
+
+    async fn import(&self, events: &[MessageEvent]) -> Result<(), RepositoryError> {
+        self.inner.import(events).await?;
+        self.buffer.push(events);
+        Ok(())
+    }
+
+    async fn find_since(
+        &self,
+        cursor: i64,
+        limit: i64,
+    ) -> Result<Vec<MessageEvent>, RepositoryError> {
+        if let Some(events) = self.buffer.since(cursor, limit.max(0) as usize) {
+            return Ok(events);
+        }
+
+        self.inner.find_since(cursor, limit).await
+    }
+
+    async fn find_latest_cursor(&self) -> Result<i64, RepositoryError> {
+        let cursor = self.inner.find_latest_cursor().await?;
+        self.buffer.seed(cursor);
+        Ok(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::MockMessageEventRepository;
+
+    fn event(cursor: i64) -> MessageEvent {
+        MessageEvent {
+            cursor,
+            message_id: Uuid::from_u128(cursor as u128),
+            kind: MessageEventKind::Added,
+        }
+    }
+
+    fn cursors(events: &[MessageEvent]) -> Vec<i64> {
+        events.iter().map(|e| e.cursor).collect()
+    }
+
+    #[test]
+    fn since_is_unavailable_until_seeded() {
+        let buffer = EventReplayBuffer::new(10);
+        buffer.push(&[event(5)]);
+        assert!(buffer.since(4, 10).is_none());
+
+        buffer.seed(4);
+        assert_eq!(cursors(&buffer.since(4, 10).unwrap()), vec![5]);
+        // Events before the seed are not buffered
+        assert!(buffer.since(3, 10).is_none());
+    }
+
+    #[test]
+    fn push_keeps_order_and_capacity() {
+        let buffer = EventReplayBuffer::new(3);
+        buffer.seed(0);
+        buffer.push(&[event(1), event(3)]);
+        buffer.push(&[event(2)]);
+        assert_eq!(cursors(&buffer.since(0, 10).unwrap()), vec![1, 2, 3]);
+        assert_eq!(cursors(&buffer.since(1, 1).unwrap()), vec![2]);
+
+        buffer.push(&[event(4)]);
+        assert!(buffer.since(0, 10).is_none());
+        assert_eq!(cursors(&buffer.since(1, 10).unwrap()), vec![2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn find_since_falls_back_to_inner_repository() {
+        let mut inner = MockMessageEventRepository::new();
+        inner.expect_find_latest_cursor().returning(|| Ok(10));
+        inner.expect_append().returning(|_, _| Ok(vec![event(11)]));
+        inner
+            .expect_find_since()
+            .withf(|cursor, _| *cursor == 5)
+            .times(1)
+            .returning(|_, _| Ok(vec![event(6), event(11)]));
+
+        let repo = BufferedMessageEventRepository::new(Arc::new(inner), 10);
+        repo.find_latest_cursor().await.unwrap();
+        repo.append(&[Uuid::from_u128(11)], MessageEventKind::Added)
+            .await
+            .unwrap();
+
+        // Served from memory
+        assert_eq!(cursors(&repo.find_since(10, 10).await.unwrap()), vec![11]);
+        // Served from the inner repository
+        assert_eq!(cursors(&repo.find_since(5, 10).await.unwrap()), vec![6, 11]);
+    }
+}
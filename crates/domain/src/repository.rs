@@ -1,50 +1,308 @@
 use std::{fmt::Debug, sync::Arc};
 
-use crate::error::RepositoryError;
-use time::OffsetDateTime;
+use crate::{
+    error::RepositoryError,
+    id::{ChannelId, MessageId, StampId, UserId},
+};
+use time::{Date, OffsetDateTime};
 use uuid::Uuid;
 
-use crate::model::{Message, MessageListItem, Stamp, User};
+use crate::model::{
+    AffinityScore, Announcement, CachedImage, Channel, ChannelActivity, ChannelScoreOverride,
+    EngagementMetrics, HiddenMessage, Highlight, IgnoredRecommendations, ImageKind, ImageSize,
+    Impression, JobRun, LinkPreview, Message, MessageCursor, MessageEmbedding, MessageEvent,
+    MessageEventKind, MessageListItem, OnboardingState, OnboardingStep, PrivacySettings,
+    QuotaOperation, ReportReason, ReportedMessage, SavedSearch, Stamp, TrendingTag, User,
+    UserStats, Webhook, WebhookEvent,
+};
 
 #[derive(Clone, Debug)]
 pub struct Repository {
-    pub message: Arc<dyn MessageRepository>,
+    pub announcement: Arc<dyn AnnouncementRepository>,
+    pub block: Arc<dyn BlockRepository>,
+    pub bookmark: Arc<dyn BookmarkRepository>,
+    pub channel: Arc<dyn ChannelRepository>,
+    pub embedding: Arc<dyn EmbeddingRepository>,
+    pub feedback: Arc<dyn FeedbackRepository>,
+    pub follow: Arc<dyn FollowRepository>,
+    pub image_cache: Arc<dyn ImageCacheRepository>,
+    pub impression: Arc<dyn ImpressionRepository>,
+    pub job_run: Arc<dyn JobRunRepository>,
+    pub link_preview: Arc<dyn LinkPreviewRepository>,
+    pub message_reader: Arc<dyn MessageReader>,
+    pub message_writer: Arc<dyn MessageWriter>,
+    pub message_event: Arc<dyn MessageEventRepository>,
+    pub mute: Arc<dyn MuteRepository>,
+    pub presence: Arc<dyn PresenceRepository>,
+    pub quota: Arc<dyn QuotaRepository>,
+    pub report: Arc<dyn ReportRepository>,
+    pub saved_search: Arc<dyn SavedSearchRepository>,
     pub stamp: Arc<dyn StampRepository>,
     pub user: Arc<dyn UserRepository>,
+    pub user_settings: Arc<dyn UserSettingsRepository>,
+    pub webhook: Arc<dyn WebhookRepository>,
 }
 
 #[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
 #[async_trait::async_trait]
-pub trait MessageRepository: Debug + Send + Sync {
+pub trait AnnouncementRepository: Debug + Send + Sync {
+    /// Creates an announcement replacing the current one, so that it is shown again even to users
+    /// who dismissed the previous one.
+    async fn publish(&self, content: &str) -> Result<Announcement, RepositoryError>;
+    /// Publishes an announcement created by another repository like [`Self::publish`], keeping
+    /// its ID and creation time.
+    async fn import(&self, announcement: &Announcement) -> Result<(), RepositoryError>;
+    /// Withdraws the current announcement and returns whether there was one.
+    async fn withdraw(&self) -> Result<bool, RepositoryError>;
+    /// Finds the current announcement unless the user has dismissed it.
+    async fn find_undismissed(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Option<Announcement>, RepositoryError>;
+    /// Records that the user dismissed an announcement. Unknown announcements are ignored.
+    async fn dismiss(&self, user_id: &Uuid, announcement_id: i64) -> Result<(), RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait BlockRepository: Debug + Send + Sync {
+    /// Blocks a user for another user.
+    /// It does nothing if the user is already blocked.
+    async fn block_user(
+        &self,
+        user_id: &UserId,
+        blocked_user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+    async fn unblock_user(
+        &self,
+        user_id: &UserId,
+        blocked_user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+    /// Finds the IDs of users who the user has blocked or who have blocked the user.
+    async fn find_blocked_or_blocking_user_ids(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<Uuid>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait BookmarkRepository: Debug + Send + Sync {
+    /// Bookmarks a message for a user.
+    /// It does nothing if the message is already bookmarked.
+    async fn add(&self, user_id: &UserId, message_id: &MessageId) -> Result<(), RepositoryError>;
+    async fn remove(&self, user_id: &UserId, message_id: &MessageId)
+    -> Result<(), RepositoryError>;
+    /// Finds messages bookmarked by a user, most recently bookmarked first.
+    async fn find_bookmarked_messages(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait ChannelRepository: Debug + Send + Sync {
+    async fn find_by_id(&self, id: &Uuid) -> Result<Option<Channel>, RepositoryError>;
+    /// Finds every channel, ordered by path.
+    async fn find_all(&self) -> Result<Vec<Channel>, RepositoryError>;
+    /// Saves channels, replacing the ones with the same IDs.
+    /// It does nothing if `channels` is empty.
+    async fn save_batch(&self, channels: &[Channel]) -> Result<(), RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait EmbeddingRepository: Debug + Send + Sync {
+    /// Finds the most recent messages that have no embedding yet.
+    /// Returns tuples of (message_id, content).
+    async fn find_messages_without_embedding(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>, RepositoryError>;
+    /// Stores embeddings, replacing existing embeddings of the same messages.
+    async fn save_embeddings(&self, embeddings: &[MessageEmbedding])
+    -> Result<(), RepositoryError>;
+    /// Finds embeddings of the latest messages the user stamped.
+    async fn find_stamped_message_embeddings(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageEmbedding>, RepositoryError>;
+    /// Finds embeddings of recent messages the user has not read or authored,
+    /// excluding muted users and channels, and users who opted out of being recommended to the
+    /// user.
+    async fn find_candidate_embeddings(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageEmbedding>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait FeedbackRepository: Debug + Send + Sync {
+    /// Records that a user is not interested in a message.
+    /// It does nothing if the message is already hidden.
+    async fn hide_message(&self, user_id: &Uuid, message: &Message) -> Result<(), RepositoryError>;
+    /// Finds messages hidden by a user.
+    async fn find_hidden_messages(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<HiddenMessage>, RepositoryError>;
+    /// Counts messages first recommended to a user in the given period that the user hasn't
+    /// read, per author and channel.
+    async fn find_ignored_recommendations(
+        &self,
+        user_id: &Uuid,
+        since: OffsetDateTime,
+        until: OffsetDateTime,
+    ) -> Result<Vec<IgnoredRecommendations>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait FollowRepository: Debug + Send + Sync {
+    /// Makes a user follow another user.
+    /// It does nothing if the user is already followed.
+    async fn follow_user(
+        &self,
+        user_id: &UserId,
+        followed_user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+    async fn unfollow_user(
+        &self,
+        user_id: &UserId,
+        followed_user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+    /// Finds messages posted by users followed by the user, newest first.
+    /// Messages from blocked users are excluded.
+    async fn find_followed_messages(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait ImpressionRepository: Debug + Send + Sync {
+    /// Records that messages were served to a user, counting how many times each was served.
+    /// The reason of the first impression is kept.
+    async fn record_impressions(
+        &self,
+        user_id: &Uuid,
+        impressions: &[Impression],
+    ) -> Result<(), RepositoryError>;
+    /// Computes the engagement per recommendation reason for messages first served in the
+    /// given period.
+    async fn compute_engagement_metrics(
+        &self,
+        since: OffsetDateTime,
+        until: OffsetDateTime,
+    ) -> Result<Vec<EngagementMetrics>, RepositoryError>;
+    /// Stores the engagement of a day, replacing the existing metrics of the day.
+    async fn save_engagement_metrics(
+        &self,
+        date: Date,
+        metrics: &[EngagementMetrics],
+    ) -> Result<(), RepositoryError>;
+}
+
+/// Images proxied from traQ, stored so that they aren't fetched or processed on every request.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait ImageCacheRepository: Debug + Send + Sync {
+    async fn find(
+        &self,
+        kind: ImageKind,
+        id: &Uuid,
+        size: ImageSize,
+    ) -> Result<Option<CachedImage>, RepositoryError>;
+    /// Stores an image now, replacing the one of the same kind, ID and size.
+    async fn save(
+        &self,
+        kind: ImageKind,
+        id: &Uuid,
+        size: ImageSize,
+        image: &[u8],
+        content_type: &str,
+    ) -> Result<(), RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait JobRunRepository: Debug + Send + Sync {
+    /// Records a job run and returns its ID.
+    async fn save(&self, run: &JobRun) -> Result<u64, RepositoryError>;
+    /// Records a job run recorded by another repository, keeping its ID.
+    async fn import(&self, id: u64, run: &JobRun) -> Result<(), RepositoryError>;
+    /// Finds the most recent job runs, newest first.
+    async fn find_recent(&self, limit: i64) -> Result<Vec<JobRun>, RepositoryError>;
+    /// Deletes job runs started before `before`.
+    async fn delete_started_before(&self, before: OffsetDateTime) -> Result<(), RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait LinkPreviewRepository: Debug + Send + Sync {
+    /// Finds the previews of the URLs fetched at or after `since`, including empty previews of
+    /// pages without metadata.
+    async fn find_fetched_since(
+        &self,
+        urls: &[String],
+        since: OffsetDateTime,
+    ) -> Result<Vec<LinkPreview>, RepositoryError>;
+    /// Stores a preview fetched now, replacing the one of the same URL.
+    async fn save(&self, preview: &LinkPreview) -> Result<(), RepositoryError>;
+}
+
+/// Stands for no particular user in the queries for recommendation candidates, so that nothing
+/// read, posted or muted by anyone is excluded. Neither are messages from users who opted out of
+/// being recommended to strangers, which callers must filter for each user they recommend to.
+/// The nil UUID is never a traQ user ID.
+pub const NO_VIEWER: Uuid = Uuid::nil();
+
+/// Reads the cached messages.
+/// Split from [`MessageWriter`] so that reads can be decorated (e.g. cached or routed to a replica)
+/// independently of writes.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait MessageReader: Debug + Send + Sync {
     async fn find_latest_message_time(&self) -> Result<Option<OffsetDateTime>, RepositoryError>;
     async fn find_by_id(&self, id: &Uuid) -> Result<Option<Message>, RepositoryError>;
+    /// Finds messages by their IDs in no particular order.
+    /// Unknown IDs are ignored.
+    async fn find_list_items_by_ids(
+        &self,
+        ids: &[Uuid],
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
 
     /// Returns messages that may need refreshing from traQ.
     /// Returns tuples of (message_id, created_at, last_crawled_at) for messages created within the last 24 hours.
     async fn find_sync_candidates(
         &self,
     ) -> Result<Vec<(Uuid, OffsetDateTime, OffsetDateTime)>, RepositoryError>;
-    /// Removes a reaction from a message.
-    /// This is used for optimistic updates when deleting a stamp.
-    async fn remove_reaction(
+
+    /// Finds the messages read by the user among `message_ids`.
+    async fn find_read_message_ids(
         &self,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
         user_id: &Uuid,
-    ) -> Result<(), RepositoryError>;
-    /// Saves a message to the repository.
-    async fn save(&self, message: &Message) -> Result<(), RepositoryError>;
-    /// Saves a batch of messages to the repository.
-    /// It does nothing if `messages` is empty.
-    async fn save_batch(&self, messages: &[Message]) -> Result<(), RepositoryError>;
-    /// Marks messages as read by a user.
-    async fn mark_messages_as_read(
+        message_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, RepositoryError>;
+
+    /// Finds the messages read by each of the users among `message_ids`, as
+    /// `(user_id, message_id)` pairs.
+    async fn find_read_message_ids_by_users(
         &self,
-        user_id: &Uuid,
+        user_ids: &[Uuid],
         message_ids: &[Uuid],
-    ) -> Result<(), RepositoryError>;
+    ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError>;
 
     /// Finds top reacted messages (popularity-based).
+    /// Messages from users and channels muted by `user_id` are excluded here and in the other
+    /// candidate queries, and so are messages from users who opted out of being recommended to
+    /// `user_id` (see [`PrivacySettings`]) unless `user_id` is [`NO_VIEWER`].
     async fn find_top_reacted_messages(
         &self,
         user_id: &Uuid,
@@ -66,26 +324,341 @@ pub trait MessageRepository: Debug + Send + Sync {
         limit: i64,
         user_id: &Uuid,
     ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds messages posted in the last `window_hours` hours with the most reactions per hour
+    /// since they were posted, regardless of the user's affinity.
+    /// Messages from users and channels muted by `user_id`, and from users who opted out of being
+    /// recommended to them, are excluded.
+    async fn find_trending_messages(
+        &self,
+        user_id: &Uuid,
+        window_hours: i64,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds the most reacted messages since `since` for publishing to peer instances, with the
+    /// content of the authors who allowed it. Messages of users who opted out of being
+    /// recommended to strangers are left out.
+    async fn find_highlights(
+        &self,
+        since: OffsetDateTime,
+        limit: i64,
+    ) -> Result<Vec<Highlight>, RepositoryError>;
+
+    /// Finds the channels with the most messages in the last 7 days, most active first.
+    async fn find_most_active_channels(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<ChannelActivity>, RepositoryError>;
+
+    /// Finds the latest messages, newest first, with at most `per_channel` messages from each
+    /// channel so that the results span many channels.
+    async fn find_recent_messages_across_channels(
+        &self,
+        user_id: &Uuid,
+        per_channel: i64,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds messages matching the query with the database's full-text index, best match first.
+    /// Messages read by `viewer` and from users and channels muted by `viewer` are excluded.
+    async fn search(
+        &self,
+        query: &str,
+        limit: i64,
+        viewer: &Uuid,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds messages tagged with the normalized `tag`, newest first.
+    /// Messages from users and channels muted by `viewer` are excluded.
+    async fn find_by_tag(
+        &self,
+        tag: &str,
+        limit: i64,
+        viewer: &Uuid,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds the messages of an author posted before `before`, newest first.
+    async fn find_by_author(
+        &self,
+        author_id: &Uuid,
+        before: Option<MessageCursor>,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds the messages of a channel posted before `before`, newest first.
+    async fn find_by_channel(
+        &self,
+        channel_id: &Uuid,
+        before: Option<MessageCursor>,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds the messages of the channels posted before `before`, newest first, for showing to
+    /// anyone. Messages of users who opted out of being recommended to strangers are left out.
+    async fn find_public_messages(
+        &self,
+        channel_ids: &[Uuid],
+        before: Option<MessageCursor>,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Counts the crawled messages of a user, and the stamps added to those posted since
+    /// `since`.
+    async fn find_user_stats(
+        &self,
+        user_id: &Uuid,
+        since: OffsetDateTime,
+    ) -> Result<UserStats, RepositoryError>;
+
+    /// Finds messages reacted to by the users who reacted to `message_id`, shared by the most
+    /// users first. Reactions by `viewer`, and messages from users and channels muted by
+    /// `viewer` or from users who opted out of being recommended to them, are excluded.
+    async fn find_co_reacted_messages(
+        &self,
+        message_id: &Uuid,
+        limit: i64,
+        viewer: &Uuid,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds the thread containing a message, oldest first: the earliest cached message it
+    /// replies to, directly or indirectly, and every cached reply to that message.
+    /// Returns nothing if the message isn't cached.
+    async fn find_thread(
+        &self,
+        message_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError>;
+
+    /// Finds the tags used by the most users in messages posted in the last `window_hours` hours.
+    async fn find_trending_tags(
+        &self,
+        window_hours: i64,
+        limit: i64,
+    ) -> Result<Vec<TrendingTag>, RepositoryError>;
+}
+
+/// Writes the cached messages.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait MessageWriter: Debug + Send + Sync {
+    /// Removes a reaction from a message.
+    /// This is used for optimistic updates when deleting a stamp.
+    async fn remove_reaction(
+        &self,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+    /// Saves a message to the repository, along with the hashtags in its content.
+    async fn save(&self, message: &Message) -> Result<(), RepositoryError>;
+    /// Saves a batch of messages to the repository, along with the hashtags in their contents.
+    /// It does nothing if `messages` is empty.
+    async fn save_batch(&self, messages: &[Message]) -> Result<(), RepositoryError>;
+    /// Marks messages as read by a user.
+    async fn mark_messages_as_read(
+        &self,
+        user_id: &UserId,
+        message_ids: &[MessageId],
+    ) -> Result<(), RepositoryError>;
+    /// Moves the read horizon of every user who read a message up to `before`, or to the oldest
+    /// message they haven't read if that is earlier, and deletes the individual reads it covers.
+    /// Returns the number of reads deleted.
+    async fn roll_up_read_messages(&self, before: OffsetDateTime) -> Result<u64, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait MessageEventRepository: Debug + Send + Sync {
+    /// Appends an event for each message to the event log and returns the appended events.
+    /// It does nothing if `message_ids` is empty.
+    async fn append(
+        &self,
+        message_ids: &[Uuid],
+        kind: MessageEventKind,
+    ) -> Result<Vec<MessageEvent>, RepositoryError>;
+    /// Appends events appended to another repository, keeping their cursors.
+    /// It does nothing if `events` is empty.
+    async fn import(&self, events: &[MessageEvent]) -> Result<(), RepositoryError>;
+    /// Finds up to `limit` events after `cursor`, oldest first.
+    async fn find_since(
+        &self,
+        cursor: i64,
+        limit: i64,
+    ) -> Result<Vec<MessageEvent>, RepositoryError>;
+    /// Returns the cursor of the latest event, or 0 if there are no events.
+    async fn find_latest_cursor(&self) -> Result<i64, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait MuteRepository: Debug + Send + Sync {
+    /// Mutes a user for another user.
+    /// It does nothing if the user is already muted.
+    async fn mute_user(
+        &self,
+        user_id: &UserId,
+        muted_user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+    async fn unmute_user(
+        &self,
+        user_id: &UserId,
+        muted_user_id: &UserId,
+    ) -> Result<(), RepositoryError>;
+    /// Finds the IDs of users muted by the user.
+    async fn find_muted_user_ids(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError>;
+    /// Mutes a channel for a user.
+    /// It does nothing if the channel is already muted.
+    async fn mute_channel(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError>;
+    async fn unmute_channel(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError>;
+    /// Finds the IDs of channels muted by the user.
+    async fn find_muted_channel_ids(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait PresenceRepository: Debug + Send + Sync {
+    /// Records that the session of the user was active at `seen_at`.
+    async fn save_heartbeat(
+        &self,
+        session_id: &str,
+        user_id: &Uuid,
+        seen_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError>;
+    /// Returns whether any session of the user was active since `since`.
+    async fn is_seen_since(
+        &self,
+        user_id: &Uuid,
+        since: OffsetDateTime,
+    ) -> Result<bool, RepositoryError>;
+    /// Counts the users with any session active since `since`.
+    async fn count_users_seen_since(&self, since: OffsetDateTime) -> Result<i64, RepositoryError>;
+    /// Deletes the sessions not active since `before`.
+    async fn delete_seen_before(&self, before: OffsetDateTime) -> Result<(), RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait QuotaRepository: Debug + Send + Sync {
+    /// Uses one of the user's `limit` uses of the operation on `day`. Returns `false`, without
+    /// using it, if they are all used.
+    async fn try_use(
+        &self,
+        user_id: &Uuid,
+        operation: QuotaOperation,
+        day: Date,
+        limit: i64,
+    ) -> Result<bool, RepositoryError>;
+    /// Finds how many times the user used each operation on `day`.
+    async fn find_used(
+        &self,
+        user_id: &Uuid,
+        day: Date,
+    ) -> Result<Vec<(QuotaOperation, i64)>, RepositoryError>;
+    /// Deletes the uses on days before `before`.
+    async fn delete_before(&self, before: Date) -> Result<(), RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait ReportRepository: Debug + Send + Sync {
+    /// Records a report of a message by a user.
+    /// If the user already reported the message, the reason is updated.
+    async fn add(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        reason: ReportReason,
+    ) -> Result<(), RepositoryError>;
+    /// Finds messages with unresolved reports, most reported first.
+    /// `message` of the returned items is always `None`.
+    async fn find_unresolved(&self, limit: i64) -> Result<Vec<ReportedMessage>, RepositoryError>;
+    /// Marks all reports of a message as resolved.
+    async fn resolve(&self, message_id: &MessageId) -> Result<(), RepositoryError>;
+    /// Finds the IDs of messages with at least `threshold` unresolved reports.
+    async fn find_message_ids_reported_at_least(
+        &self,
+        threshold: i64,
+    ) -> Result<Vec<Uuid>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait SavedSearchRepository: Debug + Send + Sync {
+    async fn create(
+        &self,
+        user_id: &Uuid,
+        name: &str,
+        query: &str,
+    ) -> Result<SavedSearch, RepositoryError>;
+    /// Saves a search created by another repository, keeping its ID and creation time.
+    async fn import(&self, user_id: &Uuid, search: &SavedSearch) -> Result<(), RepositoryError>;
+    /// Finds the searches saved by the user, newest first.
+    async fn find_by_user(&self, user_id: &Uuid) -> Result<Vec<SavedSearch>, RepositoryError>;
+    /// Finds a search saved by the user. Searches saved by other users are not found.
+    async fn find_by_id(
+        &self,
+        user_id: &Uuid,
+        id: i64,
+    ) -> Result<Option<SavedSearch>, RepositoryError>;
+    /// Deletes a search saved by the user and returns whether it existed.
+    async fn delete(&self, user_id: &Uuid, id: i64) -> Result<bool, RepositoryError>;
 }
 
 #[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
 #[async_trait::async_trait]
 pub trait StampRepository: Debug + Send + Sync {
     async fn find_by_id(&self, id: &Uuid) -> Result<Option<Stamp>, RepositoryError>;
+    /// Finds the cached stamps among the IDs, in no particular order.
+    async fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Stamp>, RepositoryError>;
     async fn save(&self, stamp: &Stamp) -> Result<(), RepositoryError>;
     async fn save_batch(&self, stamps: &[Stamp]) -> Result<(), RepositoryError>;
-    /// Finds channels that the user frequently stamps in.
+    /// Finds channels that the user frequently stamps in, highest score first.
+    /// Reactions count half as much every `half_life_days` days, so that recent ones weigh more.
     async fn find_frequently_stamped_channels_by(
         &self,
         user_id: &Uuid,
         limit: i64,
-    ) -> Result<Vec<Uuid>, RepositoryError>;
+        half_life_days: f64,
+    ) -> Result<Vec<AffinityScore>, RepositoryError>;
+    /// Finds the stamps most often added to messages posted by `author_id` or in `channel_id`
+    /// since `since`, counted once per message. Stamps that aren't cached are left out.
+    async fn find_frequently_received(
+        &self,
+        author_id: &Uuid,
+        channel_id: &Uuid,
+        since: OffsetDateTime,
+        limit: i64,
+    ) -> Result<Vec<Stamp>, RepositoryError>;
+    /// Records that the user added a stamp to a message.
+    async fn record_usage(
+        &self,
+        user_id: &UserId,
+        stamp_id: &StampId,
+    ) -> Result<(), RepositoryError>;
+    /// Finds the stamps the user added most recently, most recent first.
+    /// Stamps that aren't cached are left out.
+    async fn find_recently_used_by(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<Stamp>, RepositoryError>;
 }
 
 #[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
 #[async_trait::async_trait]
 pub trait UserRepository: Debug + Send + Sync {
     async fn find_by_id(&self, id: &Uuid) -> Result<Option<User>, RepositoryError>;
+    /// Finds the cached users among the IDs, in no particular order.
+    async fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<User>, RepositoryError>;
     async fn find_random_valid_token(&self) -> Result<Option<String>, RepositoryError>;
     async fn find_token_by_user_id(
         &self,
@@ -93,16 +666,103 @@ pub trait UserRepository: Debug + Send + Sync {
     ) -> Result<Option<String>, RepositoryError>;
     async fn save(&self, user: &User) -> Result<(), RepositoryError>;
     async fn save_token(&self, user_id: &Uuid, access_token: &str) -> Result<(), RepositoryError>;
-    /// Finds users who the target user frequently stamps to.
+    /// Finds users who the target user frequently stamps to, highest score first.
+    /// Reactions count half as much every `half_life_days` days, so that recent ones weigh more.
     async fn find_frequently_stamped_users_by(
         &self,
         user_id: &Uuid,
         limit: i64,
-    ) -> Result<Vec<Uuid>, RepositoryError>;
+        half_life_days: f64,
+    ) -> Result<Vec<AffinityScore>, RepositoryError>;
     /// Finds users who have similar reaction patterns to the target user.
     async fn find_similar_users(
         &self,
         user_id: &Uuid,
         limit: i64,
     ) -> Result<Vec<Uuid>, RepositoryError>;
+    /// Finds which of `user_ids` are acquainted with each of `viewer_ids`, i.e. follow the viewer
+    /// or have stamped a message of theirs, as `(viewer_id, user_id)` pairs.
+    async fn find_acquaintances(
+        &self,
+        viewer_ids: &[Uuid],
+        user_ids: &[Uuid],
+    ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait UserSettingsRepository: Debug + Send + Sync {
+    /// Finds the onboarding steps the user has completed.
+    /// Returns an empty state if the user has no settings yet.
+    async fn find_onboarding_state(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<OnboardingState, RepositoryError>;
+    /// Records that the user completed an onboarding step.
+    async fn complete_onboarding_step(
+        &self,
+        user_id: &Uuid,
+        step: OnboardingStep,
+    ) -> Result<(), RepositoryError>;
+    /// Finds the channels the user picked as interests.
+    async fn find_channel_interests(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError>;
+    /// Replaces the channels the user picked as interests.
+    async fn save_channel_interests(
+        &self,
+        user_id: &UserId,
+        channel_ids: &[ChannelId],
+    ) -> Result<(), RepositoryError>;
+    /// Finds the score multipliers the user set for channels.
+    async fn find_channel_score_overrides(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<ChannelScoreOverride>, RepositoryError>;
+    /// Sets the score multiplier for a channel, replacing the existing one.
+    async fn save_channel_score_override(
+        &self,
+        user_id: &Uuid,
+        score_override: &ChannelScoreOverride,
+    ) -> Result<(), RepositoryError>;
+    /// Removes the score multiplier for a channel. It does nothing if there is none.
+    async fn delete_channel_score_override(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError>;
+    /// Finds the user's privacy settings.
+    /// Returns the defaults if the user has no settings yet.
+    async fn find_privacy_settings(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<PrivacySettings, RepositoryError>;
+    async fn save_privacy_settings(
+        &self,
+        user_id: &Uuid,
+        settings: &PrivacySettings,
+    ) -> Result<(), RepositoryError>;
+    /// Finds the users among `user_ids` whose messages are recommended only to acquaintances.
+    async fn find_acquaintance_only_user_ids(
+        &self,
+        user_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, RepositoryError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait WebhookRepository: Debug + Send + Sync {
+    async fn create(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        created_by: &Uuid,
+    ) -> Result<Webhook, RepositoryError>;
+    /// Registers a webhook created by another repository, keeping its ID and creation time.
+    async fn import(&self, webhook: &Webhook, secret: &str) -> Result<(), RepositoryError>;
+    /// Finds every webhook, oldest first.
+    async fn find_all(&self) -> Result<Vec<Webhook>, RepositoryError>;
+    /// Finds every webhook with the secret its requests are signed with.
+    async fn find_all_with_secrets(&self) -> Result<Vec<(Webhook, String)>, RepositoryError>;
+    /// Deletes a webhook and returns whether it existed.
+    async fn delete(&self, id: i64) -> Result<bool, RepositoryError>;
 }
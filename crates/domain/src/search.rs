@@ -0,0 +1,14 @@
+use crate::{error::SearchError, model::Message};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+/// A full-text index of messages, so that searches do not have to hit traQ.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait SearchIndex: Debug + Send + Sync {
+    /// Adds messages to the index, replacing messages with the same ID.
+    async fn index_messages(&self, messages: &[Message]) -> Result<(), SearchError>;
+    async fn remove_messages(&self, message_ids: &[Uuid]) -> Result<(), SearchError>;
+    /// Returns the IDs of messages matching the query, best match first.
+    async fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<Uuid>, SearchError>;
+}
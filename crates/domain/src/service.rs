@@ -1,12 +1,246 @@
 use crate::{
-    error::DomainError,
-    model::{MessageListItem, Stamp, User},
-    repository::Repository,
+    embedding::{centroid, cosine_similarity},
+    error::{DomainError, RepositoryError, TraqClientError},
+    federation::{HIGHLIGHTS_VERSION, Peers},
+    hashtag,
+    id::{ChannelId, MessageId, StampId, UserId},
+    image_cache::ImageCache,
+    link_preview::LinkPreviewResolver,
+    model::{
+        ActiveUsers, Affinity, Announcement, Channel, ChannelActivity, ChannelScoreOverride,
+        ImageKind, ImageSize, Impression, InstanceHighlights, MessageCursor, MessageEventKind,
+        MessageListItem, MessagePage, OnboardingState, OnboardingStep, PageAssets, PeerHighlights,
+        PrivacySettings, QuotaOperation, QuotaUsage, RecommendationReason, ReportReason,
+        ReportedMessage, SavedSearch, Stamp, TimelineUpdates, TrendingTag, TrendingWindow, User,
+        UserProfile, VisibilityLeak, VisibilityReport, Webhook, WebhookEvent,
+    },
+    quote::QuoteResolver,
+    ranking::{HeuristicRanker, Ranker, RankingWeights, ScoredCandidate},
+    recent_messages::RecentMessages,
+    repository::{NO_VIEWER, Repository},
+    search::SearchIndex,
+    thumbnail::Thumbnailer,
     traq_client::TraqClient,
 };
-use std::{cmp::Ordering, collections::HashMap, fmt::Debug, sync::Arc};
+use futures_util::future::{join_all, try_join_all};
+use http::StatusCode;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    future::Future,
+    mem,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+    },
+};
+use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
+const DEGRADED_TIMELINE_LIMIT: usize = 50;
+const FOLLOWING_TIMELINE_LIMIT: i64 = 50;
+const TIMELINE_UPDATES_LIMIT: i64 = 200;
+/// Users with fewer affinity users, affinity channels and similar users in total are recommended
+/// recent messages across channels as well.
+const COLD_START_MIN_SIGNALS: usize = 5;
+/// The maximum number of recent messages per channel recommended to cold-start users.
+const COLD_START_MESSAGES_PER_CHANNEL: i64 = 3;
+const AFFINITY_USERS_LIMIT: i64 = 20;
+const AFFINITY_CHANNELS_LIMIT: i64 = 10;
+const SUGGESTED_CHANNELS_LIMIT: i64 = 30;
+const EXPLORE_LIMIT: i64 = 50;
+const SEARCH_LIMIT: usize = 50;
+const TAG_MESSAGES_LIMIT: i64 = 50;
+const USER_MESSAGES_PAGE_SIZE: i64 = 50;
+const CHANNEL_MESSAGES_PAGE_SIZE: i64 = 50;
+const PUBLIC_TIMELINE_PAGE_SIZE: i64 = 20;
+const TRENDING_TAGS_LIMIT: i64 = 20;
+const RELATED_MESSAGES_LIMIT: i64 = 20;
+const THREAD_MESSAGES_LIMIT: i64 = 200;
+const RECENT_STAMPS_LIMIT: i64 = 30;
+/// The images prefetched per page at most, so that warming the cache doesn't use up traQ's rate
+/// limit.
+const MAX_PREFETCHED_IMAGES: usize = 50;
+/// Profiles count the reactions received by messages posted within this period.
+const PROFILE_STATS_WINDOW: Duration = Duration::days(30);
+const STAMP_SUGGESTIONS_LIMIT: usize = 12;
+/// Stamps received by similar messages within this period are suggested.
+const STAMP_SUGGESTIONS_WINDOW: Duration = Duration::days(30);
+/// The maximum number of stamps that can be looked up at once.
+pub const MAX_BATCH_STAMP_IDS: usize = 100;
+/// The maximum length of announcements, in characters.
+pub const MAX_ANNOUNCEMENT_LEN: usize = 2000;
+/// The maximum number of searches a user can save.
+pub const MAX_SAVED_SEARCHES: usize = 20;
+const MAX_SAVED_SEARCH_NAME_LEN: usize = 64;
+const MAX_SAVED_SEARCH_QUERY_LEN: usize = 256;
+/// The number of recently stamped messages whose embeddings describe what the user likes.
+const SIMILAR_CONTENT_SEED_LIMIT: i64 = 50;
+/// The number of recent messages compared with the stamped messages.
+const SIMILAR_CONTENT_CANDIDATE_LIMIT: i64 = 1000;
+const SIMILAR_CONTENT_LIMIT: usize = 50;
+/// Reactions count half as much for affinity after this many days, so that old friendships fade.
+pub const DEFAULT_AFFINITY_HALF_LIFE_DAYS: f64 = 30.0;
+/// Unread recommendations first served within this period count as ignored.
+const IGNORED_RECOMMENDATIONS_WINDOW: Duration = Duration::days(30);
+/// Recommendations served more recently may still be read, so they don't count as ignored yet.
+const IGNORED_RECOMMENDATIONS_GRACE_PERIOD: Duration = Duration::days(1);
+/// The maximum number of webhooks admins can register.
+pub const MAX_WEBHOOKS: usize = 20;
+/// Clients send a heartbeat every minute while they are open, so sessions stay online for two
+/// minutes to allow for a late one.
+const ONLINE_WINDOW: Duration = Duration::minutes(2);
+const DAILY_ACTIVE_WINDOW: Duration = Duration::days(1);
+const HIGHLIGHTS_WINDOW: Duration = Duration::days(1);
+const HIGHLIGHTS_LIMIT: i64 = 20;
+const MAX_WEBHOOK_URL_LEN: usize = 2048;
+/// Shorter secrets can be brute-forced offline from any signed request.
+pub const MIN_WEBHOOK_SECRET_LEN: usize = 32;
+/// The maximum number of channels a user can pick as interests during onboarding.
+pub const MAX_CHANNEL_INTERESTS: usize = 20;
+/// The range of multipliers users can apply to the scores of messages in a channel.
+pub const MIN_CHANNEL_SCORE_MULTIPLIER: f64 = 0.1;
+pub const MAX_CHANNEL_SCORE_MULTIPLIER: f64 = 10.0;
+/// The maximum number of channels a user can override scores for.
+pub const MAX_CHANNEL_SCORE_OVERRIDES: usize = 50;
+/// The number of candidates fetched from each source for everyone, when recommending messages
+/// for many users at once.
+const BATCH_CANDIDATE_POOL_LIMIT: i64 = 500;
+/// How many users' signals are fetched and recommendations presented at once, when recommending
+/// messages for many users, so that a large batch doesn't take every database connection.
+const BATCH_CONCURRENCY: usize = 8;
+
+/// Truncates messages fetched with a limit of one more than `page_size` to `page_size` messages.
+/// If any were dropped, returns the cursor of the last message kept, which the next page starts
+/// after; otherwise this is the last page and `None` is returned.
+fn truncate_to_page(messages: &mut Vec<MessageListItem>, page_size: i64) -> Option<String> {
+    if messages.len() <= page_size as usize {
+        return None;
+    }
+    messages.truncate(page_size as usize);
+    messages.last().map(|m| MessageCursor::from(m).encode())
+}
+
+/// Decodes a cursor given by a client.
+fn decode_cursor(cursor: Option<String>) -> Result<Option<MessageCursor>, DomainError> {
+    cursor
+        .map(|cursor| MessageCursor::decode(&cursor).ok_or(DomainError::InvalidCursor))
+        .transpose()
+}
+
+/// Aggregates the reactions to the messages for the user viewing them.
+fn summarize_reactions(
+    mut messages: Vec<MessageListItem>,
+    viewer_id: &Uuid,
+) -> Vec<MessageListItem> {
+    for message in &mut messages {
+        message.summarize_reactions(viewer_id);
+    }
+    messages
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait BookmarkService: Debug + Send + Sync {
+    async fn add_bookmark(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+    ) -> Result<(), DomainError>;
+    async fn remove_bookmark(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+    ) -> Result<(), DomainError>;
+    async fn get_bookmarks(&self, user_id: &UserId) -> Result<Vec<MessageListItem>, DomainError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait OnboardingService: Debug + Send + Sync {
+    async fn get_onboarding_state(&self, user_id: &Uuid) -> Result<OnboardingState, DomainError>;
+    /// Marks an onboarding step as completed and returns the updated state.
+    async fn complete_onboarding_step(
+        &self,
+        user_id: &Uuid,
+        step: OnboardingStep,
+    ) -> Result<OnboardingState, DomainError>;
+    /// Returns the most active channels to pick interests from.
+    async fn get_suggested_channels(&self) -> Result<Vec<ChannelActivity>, DomainError>;
+    /// Saves the channels the user is interested in, which are recommended until the user stamps
+    /// messages, and completes the initial channels step.
+    async fn save_channel_interests(
+        &self,
+        user_id: &UserId,
+        channel_ids: &[ChannelId],
+    ) -> Result<OnboardingState, DomainError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait ReportService: Debug + Send + Sync {
+    /// Reports a message as abusive.
+    async fn report_message(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        reason: ReportReason,
+    ) -> Result<(), DomainError>;
+    /// Returns messages with unresolved reports, most reported first.
+    async fn get_unresolved_reports(&self, limit: i64)
+    -> Result<Vec<ReportedMessage>, DomainError>;
+    /// Marks all reports of a message as reviewed.
+    async fn resolve_reports(&self, message_id: &MessageId) -> Result<(), DomainError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait WebhookService: Debug + Send + Sync {
+    /// Returns every webhook, oldest first.
+    async fn get_webhooks(&self) -> Result<Vec<Webhook>, DomainError>;
+    /// Registers a webhook to be sent the events, signed with `secret`.
+    async fn register_webhook(
+        &self,
+        created_by: &Uuid,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+    ) -> Result<Webhook, DomainError>;
+    async fn delete_webhook(&self, id: i64) -> Result<(), DomainError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait PresenceService: Debug + Send + Sync {
+    /// Records that the session of the user is open now.
+    async fn heartbeat(&self, session_id: &str, user_id: &Uuid) -> Result<(), DomainError>;
+    /// Returns whether the user has a session open now, e.g. so that they aren't notified of
+    /// what they can already see.
+    async fn is_online(&self, user_id: &Uuid) -> Result<bool, DomainError>;
+    async fn get_active_users(&self) -> Result<ActiveUsers, DomainError>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait FederationService: Debug + Send + Sync {
+    /// Returns the highlights this instance publishes to its peers, failing with
+    /// [`DomainError::HighlightsNotPublished`] unless publishing them is enabled.
+    async fn get_highlights(&self) -> Result<InstanceHighlights, DomainError>;
+    /// Returns the highlights of the peer instances that could be reached.
+    async fn get_peer_highlights(&self) -> Vec<PeerHighlights>;
+}
+
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait QuotaService: Debug + Send + Sync {
+    /// Uses one of the user's daily uses of the operation, failing with
+    /// [`DomainError::QuotaExceeded`] if they are all used. Operations without a quota are free.
+    async fn consume(&self, user_id: &Uuid, operation: QuotaOperation) -> Result<(), DomainError>;
+    /// Returns how much of each quota the user has used today.
+    async fn get_quotas(&self, user_id: &Uuid) -> Result<Vec<QuotaUsage>, DomainError>;
+}
+
 #[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
 #[async_trait::async_trait]
 pub trait TimelineService: Debug + Send + Sync {
@@ -14,436 +248,4900 @@ pub trait TimelineService: Debug + Send + Sync {
         &self,
         user_id: &Uuid,
     ) -> Result<Vec<MessageListItem>, DomainError>;
+    /// Recommends messages for many users at once, e.g. for digests, keyed by user ID.
+    /// Candidates are fetched once for all the users, so a user may get fewer candidates from
+    /// sources shared with many active users than from [`Self::get_recommended_messages`].
+    async fn get_recommended_messages_for_users(
+        &self,
+        user_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<MessageListItem>>, DomainError>;
+    /// Returns a reduced chronological timeline from messages kept in memory.
+    /// It is meant to be used while the repository is unavailable, so mutes and blocks are not
+    /// applied.
+    fn get_degraded_messages(&self, user_id: &Uuid) -> Vec<MessageListItem>;
+    /// Returns messages trending across the instance, regardless of the user's affinity.
+    async fn get_explore_messages(
+        &self,
+        user_id: &Uuid,
+        window: TrendingWindow,
+    ) -> Result<Vec<MessageListItem>, DomainError>;
+    /// Returns messages matching the query, best match first.
+    async fn search_messages(
+        &self,
+        user_id: &Uuid,
+        query: &str,
+    ) -> Result<Vec<MessageListItem>, DomainError>;
+    /// Saves a search query under a name, so that it can be run again later.
+    async fn save_search(
+        &self,
+        user_id: &Uuid,
+        name: &str,
+        query: &str,
+    ) -> Result<SavedSearch, DomainError>;
+    /// Returns the searches saved by the user, newest first.
+    async fn get_saved_searches(&self, user_id: &Uuid) -> Result<Vec<SavedSearch>, DomainError>;
+    async fn delete_saved_search(&self, user_id: &Uuid, id: i64) -> Result<(), DomainError>;
+    /// Runs a saved search like [`TimelineService::search_messages`].
+    async fn run_saved_search(
+        &self,
+        user_id: &Uuid,
+        id: i64,
+    ) -> Result<Vec<MessageListItem>, DomainError>;
+    /// Returns messages tagged with `tag`, newest first. The tag may start with `#`.
+    async fn get_tag_messages(
+        &self,
+        user_id: &Uuid,
+        tag: &str,
+    ) -> Result<Vec<MessageListItem>, DomainError>;
+    /// Returns a page of an author's messages, newest first, starting after the `before` cursor.
+    async fn get_user_messages(
+        &self,
+        user_id: &Uuid,
+        author_id: &Uuid,
+        before: Option<String>,
+    ) -> Result<MessagePage, DomainError>;
+    /// Returns messages reacted to by the users who reacted to the message, shared by the most
+    /// users first.
+    async fn get_related_messages(
+        &self,
+        user_id: &Uuid,
+        message_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, DomainError>;
+    /// Returns the conversation the message belongs to, oldest first, following the messages
+    /// they cite.
+    async fn get_thread(
+        &self,
+        user_id: &Uuid,
+        message_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, DomainError>;
+    /// Returns a page of the messages in the public channels, newest first, starting after the
+    /// `before` cursor. It is shown to anyone, so it fails with
+    /// [`DomainError::PublicTimelineDisabled`] unless public channels are set.
+    async fn get_public_timeline(&self, before: Option<String>)
+    -> Result<MessagePage, DomainError>;
+    /// Returns the tags used by the most users over the window.
+    async fn get_trending_tags(
+        &self,
+        window: TrendingWindow,
+    ) -> Result<Vec<TrendingTag>, DomainError>;
+    /// Returns the authors and channels whose messages are recommended to the user for their
+    /// affinity, with their scores.
+    async fn get_affinity(&self, user_id: &Uuid) -> Result<Affinity, DomainError>;
+    /// Returns messages from users followed by the user in chronological order, newest first.
+    async fn get_following_messages(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, DomainError>;
+    /// Returns changes to timeline messages since `since`.
+    /// If `since` is `None`, it returns no changes and the current cursor.
+    async fn get_timeline_updates(
+        &self,
+        user_id: &Uuid,
+        since: Option<i64>,
+    ) -> Result<TimelineUpdates, DomainError>;
     async fn mark_messages_as_read(
+        &self,
+        user_id: &UserId,
+        message_ids: &[MessageId],
+    ) -> Result<(), DomainError>;
+    /// Records that recommended messages were served to the user.
+    async fn record_impressions(
+        &self,
+        user_id: &Uuid,
+        impressions: &[Impression],
+    ) -> Result<(), DomainError>;
+    /// Finds the messages read by the user among `message_ids`.
+    async fn find_read_message_ids(
         &self,
         user_id: &Uuid,
         message_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, DomainError>;
+    /// Records that the user is not interested in a message.
+    /// The message is never recommended again, and its author and channel are downranked.
+    async fn hide_message(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+    ) -> Result<(), DomainError>;
+    /// Mutes a user so that their messages no longer appear in the user's timeline.
+    async fn mute_user(&self, user_id: &UserId, muted_user_id: &UserId) -> Result<(), DomainError>;
+    async fn unmute_user(
+        &self,
+        user_id: &UserId,
+        muted_user_id: &UserId,
+    ) -> Result<(), DomainError>;
+    /// Returns the score multipliers the user set for channels.
+    async fn get_channel_score_overrides(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<ChannelScoreOverride>, DomainError>;
+    /// Sets a multiplier applied to the scores of messages recommended from a channel.
+    async fn set_channel_score_override(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+        multiplier: f64,
+    ) -> Result<ChannelScoreOverride, DomainError>;
+    async fn remove_channel_score_override(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), DomainError>;
+    async fn get_privacy_settings(&self, user_id: &Uuid) -> Result<PrivacySettings, DomainError>;
+    /// Replaces the user's privacy settings. They apply to recommendations built from then on.
+    async fn set_privacy_settings(
+        &self,
+        user_id: &Uuid,
+        settings: &PrivacySettings,
+    ) -> Result<(), DomainError>;
+    /// Mutes a channel so that its messages no longer appear in the user's timeline.
+    async fn mute_channel(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), DomainError>;
+    async fn unmute_channel(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), DomainError>;
+    /// Blocks a user. Neither user sees the other's messages in their timeline.
+    async fn block_user(
+        &self,
+        user_id: &UserId,
+        blocked_user_id: &UserId,
+    ) -> Result<(), DomainError>;
+    async fn unblock_user(
+        &self,
+        user_id: &UserId,
+        blocked_user_id: &UserId,
+    ) -> Result<(), DomainError>;
+    async fn follow_user(
+        &self,
+        user_id: &UserId,
+        followed_user_id: &UserId,
+    ) -> Result<(), DomainError>;
+    async fn unfollow_user(
+        &self,
+        user_id: &UserId,
+        followed_user_id: &UserId,
+    ) -> Result<(), DomainError>;
+    /// Returns the announcement to pin at the top of the user's timeline, unless they dismissed
+    /// it.
+    async fn get_announcement(&self, user_id: &Uuid) -> Result<Option<Announcement>, DomainError>;
+    async fn dismiss_announcement(
+        &self,
+        user_id: &Uuid,
+        announcement_id: i64,
     ) -> Result<(), DomainError>;
+    /// Replaces the current announcement, showing it to every user until they dismiss it.
+    async fn publish_announcement(&self, content: &str) -> Result<Announcement, DomainError>;
+    async fn withdraw_announcement(&self) -> Result<(), DomainError>;
 }
 
 #[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
 #[async_trait::async_trait]
 pub trait TraqService: Debug + Send + Sync {
     async fn get_user_by_id(&self, user_id: &Uuid) -> Result<User, DomainError>;
-    async fn get_user_icon(&self, user_id: &Uuid) -> Result<(Vec<u8>, String), DomainError>;
+    /// Returns the users keyed by ID, serving cached ones and fetching the rest from traQ one at a
+    /// time.
+    async fn get_users_by_ids(&self, user_ids: &[Uuid])
+    -> Result<HashMap<Uuid, User>, DomainError>;
+    /// Returns a user like [`Self::get_user_by_id`], with their activity stats.
+    async fn get_user_profile(&self, user_id: &Uuid) -> Result<UserProfile, DomainError>;
+    /// Returns the user's icon in `size`, and its content type.
+    async fn get_user_icon(
+        &self,
+        user_id: &Uuid,
+        size: ImageSize,
+    ) -> Result<(Vec<u8>, String), DomainError>;
+    /// Caches the images needed to render a page before clients request them. Images are fetched
+    /// one at a time, for one page at a time, and no more are fetched once traQ rejects a request
+    /// for exceeding its rate limit.
+    async fn prefetch_images(&self, assets: &PageAssets);
     async fn get_stamp_by_id(&self, stamp_id: &Uuid) -> Result<Stamp, DomainError>;
-    async fn get_stamp_image(&self, stamp_id: &Uuid) -> Result<(Vec<u8>, String), DomainError>;
+    /// Returns the stamp's image in `size`, and its content type.
+    async fn get_stamp_image(
+        &self,
+        stamp_id: &Uuid,
+        size: ImageSize,
+    ) -> Result<(Vec<u8>, String), DomainError>;
     async fn get_stamps(&self) -> Result<Vec<Stamp>, DomainError>;
+    /// Returns the stamps keyed by ID, serving cached ones and fetching the rest from traQ.
+    /// IDs unknown to traQ are left out.
+    async fn get_stamps_by_ids(
+        &self,
+        stamp_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Stamp>, DomainError>;
     async fn search_stamps(&self, name: &str) -> Result<Vec<Stamp>, DomainError>;
-    async fn add_message_stamp(
+    /// Returns the stamps the user added most recently through Twittra, most recent first.
+    async fn get_recent_stamps(&self, user_id: &Uuid) -> Result<Vec<Stamp>, DomainError>;
+    /// Suggests stamps for the user to add to a message, from the stamps that messages by the
+    /// same author or in the same channel usually receive and the user's recent stamps.
+    /// Stamps the user already added to the message are left out.
+    async fn get_stamp_suggestions(
         &self,
         user_id: &Uuid,
         message_id: &Uuid,
-        stamp_id: &Uuid,
+    ) -> Result<Vec<Stamp>, DomainError>;
+    /// Returns the channels synced from traQ, ordered by path.
+    async fn get_channels(&self) -> Result<Vec<Channel>, DomainError>;
+    async fn get_channel_by_id(&self, channel_id: &Uuid) -> Result<Channel, DomainError>;
+    async fn add_message_stamp(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        stamp_id: &StampId,
         count: i32,
     ) -> Result<(), DomainError>;
     async fn remove_message_stamp(
         &self,
-        user_id: &Uuid,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
+        user_id: &UserId,
+        message_id: &MessageId,
+        stamp_id: &StampId,
     ) -> Result<(), DomainError>;
+    /// Removes the stamp if the user has reacted with it according to the cached message, and
+    /// adds it otherwise. Returns whether the user has reacted with the stamp afterwards.
+    async fn toggle_message_stamp(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+    ) -> Result<bool, DomainError>;
+    /// Returns a page of a channel's messages, newest first, starting after the `before` cursor.
+    /// Messages are served from the cache, and fetched from traQ and cached only if the page
+    /// reaches back before the messages crawled from the channel.
+    async fn get_channel_messages(
+        &self,
+        user_id: &Uuid,
+        channel_id: &Uuid,
+        before: Option<String>,
+    ) -> Result<MessagePage, DomainError>;
+    /// Checks with the user's token that they can access the channels of the messages, by
+    /// fetching one message of each channel from traQ.
+    async fn check_message_visibility(
+        &self,
+        user_id: &Uuid,
+        messages: &[MessageListItem],
+    ) -> Result<VisibilityReport, DomainError>;
 }
 
-/// Service for timeline-related operations.
+/// Service for bookmarking messages.
 #[derive(Clone, Debug)]
-pub struct TimelineServiceImpl {
+pub struct BookmarkServiceImpl {
     repo: Repository,
 }
 
-impl TimelineServiceImpl {
+impl BookmarkServiceImpl {
     pub fn new(repo: Repository) -> Self {
         Self { repo }
     }
 }
 
 #[async_trait::async_trait]
-impl TimelineService for TimelineServiceImpl {
-    async fn get_recommended_messages(
+impl BookmarkService for BookmarkServiceImpl {
+    async fn add_bookmark(
         &self,
-        user_id: &Uuid,
-    ) -> Result<Vec<MessageListItem>, DomainError> {
-        // 1. Get user affinity list (people I stamp)
-        let affinity_users = self
-            .repo
-            .user
-            .find_frequently_stamped_users_by(user_id, 20)
-            .await?;
-
-        // 2. Get channel affinity list (channels I stamp in)
-        let affinity_channels = self
+        user_id: &UserId,
+        message_id: &MessageId,
+    ) -> Result<(), DomainError> {
+        // Only messages cached in the repository can be bookmarked
+        if self
             .repo
-            .stamp
-            .find_frequently_stamped_channels_by(user_id, 10)
-            .await?;
+            .message_reader
+            .find_by_id(message_id.as_uuid())
+            .await?
+            .is_none()
+        {
+            return Err(DomainError::NoMessageForId(*message_id.as_uuid()));
+        }
 
-        // 3. Get similar users (people who stamp same msgs)
-        let similar_users = self.repo.user.find_similar_users(user_id, 20).await?;
+        self.repo.bookmark.add(user_id, message_id).await?;
+        Ok(())
+    }
 
-        // 4. Fetch candidates from all sources concurrently
-        // To avoid finding messages that user already read or self-authored, we pass user_id.
-        let (top_reacts, affinity_author_msgs, affinity_channel_msgs, similar_user_msgs) = tokio::join!(
-            self.repo.message.find_top_reacted_messages(user_id, 50),
-            self.repo
-                .message
-                .find_messages_by_author_allowlist(&affinity_users, 50, user_id),
-            self.repo
-                .message
-                .find_messages_by_channel_allowlist(&affinity_channels, 50, user_id),
-            self.repo
-                .message
-                .find_messages_by_author_allowlist(&similar_users, 50, user_id)
-        );
+    async fn remove_bookmark(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+    ) -> Result<(), DomainError> {
+        self.repo.bookmark.remove(user_id, message_id).await?;
+        Ok(())
+    }
 
-        let top_reacts = top_reacts?;
-        let affinity_author_msgs = affinity_author_msgs?;
-        let affinity_channel_msgs = affinity_channel_msgs?;
-        let similar_user_msgs = similar_user_msgs?;
-
-        // 5. Merge and Score
-        // Map message_id -> (Message, Score)
-        // Scores:
-        // - Top Reacted: 5.0 + (50 - rank) * 0.1
-        // - Affinity Author: 5.0 + (50 - rank) * 0.15
-        // - Affinity Channel: 3.0 + (50 - rank) * 0.1
-        // - Similar User: 5.0 + (50 - rank) * 0.1
-
-        let mut scored_messages = HashMap::<Uuid, (MessageListItem, f64)>::new();
-
-        let mut add_score = |msgs: Vec<MessageListItem>, base_score: f64, rank_multiplier: f64| {
-            for (i, msg) in msgs.into_iter().enumerate() {
-                let rank_score = (50.0 - i as f64).max(0.0) * rank_multiplier;
-                let total_score = base_score + rank_score;
-
-                scored_messages
-                    .entry(msg.id)
-                    .and_modify(|(_, s)| *s += total_score)
-                    .or_insert((msg, total_score));
-            }
-        };
+    async fn get_bookmarks(&self, user_id: &UserId) -> Result<Vec<MessageListItem>, DomainError> {
+        let messages = self.repo.bookmark.find_bookmarked_messages(user_id).await?;
+        Ok(summarize_reactions(messages, user_id.as_uuid()))
+    }
+}
 
-        add_score(top_reacts, 5.0, 0.1);
-        add_score(affinity_author_msgs, 5.0, 0.15);
-        add_score(affinity_channel_msgs, 3.0, 0.1);
-        add_score(similar_user_msgs, 5.0, 0.1);
-        let mut final_list: Vec<(MessageListItem, f64)> = scored_messages.into_values().collect();
-        // Sort by score descending
-        final_list.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+/// Service for tracking the onboarding of new users.
+#[derive(Clone, Debug)]
+pub struct OnboardingServiceImpl {
+    repo: Repository,
+}
 
-        // Return top 50
-        let result = final_list.into_iter().take(50).map(|(m, _)| m).collect();
+impl OnboardingServiceImpl {
+    pub fn new(repo: Repository) -> Self {
+        Self { repo }
+    }
+}
 
-        Ok(result)
+#[async_trait::async_trait]
+impl OnboardingService for OnboardingServiceImpl {
+    async fn get_onboarding_state(&self, user_id: &Uuid) -> Result<OnboardingState, DomainError> {
+        let state = self
+            .repo
+            .user_settings
+            .find_onboarding_state(user_id)
+            .await?;
+        Ok(state)
     }
 
-    async fn mark_messages_as_read(
+    async fn complete_onboarding_step(
         &self,
         user_id: &Uuid,
-        message_ids: &[Uuid],
-    ) -> Result<(), DomainError> {
+        step: OnboardingStep,
+    ) -> Result<OnboardingState, DomainError> {
         self.repo
-            .message
-            .mark_messages_as_read(user_id, message_ids)
+            .user_settings
+            .complete_onboarding_step(user_id, step)
             .await?;
-        Ok(())
+        self.get_onboarding_state(user_id).await
+    }
+
+    async fn get_suggested_channels(&self) -> Result<Vec<ChannelActivity>, DomainError> {
+        let channels = self
+            .repo
+            .message_reader
+            .find_most_active_channels(SUGGESTED_CHANNELS_LIMIT)
+            .await?;
+        Ok(channels)
+    }
+
+    async fn save_channel_interests(
+        &self,
+        user_id: &UserId,
+        channel_ids: &[ChannelId],
+    ) -> Result<OnboardingState, DomainError> {
+        if channel_ids.len() > MAX_CHANNEL_INTERESTS {
+            return Err(DomainError::TooManyChannelInterests(MAX_CHANNEL_INTERESTS));
+        }
+
+        self.repo
+            .user_settings
+            .save_channel_interests(user_id, channel_ids)
+            .await?;
+        self.complete_onboarding_step(user_id.as_uuid(), OnboardingStep::InitialChannels)
+            .await
     }
 }
 
-/// Handles general data fetching from traQ.
-/// It utilizes the repository as a cache and fetches data from traQ only when necessary.
-/// Twittra's unique features such as recommendations are not handled here.
+/// Service for abuse reports.
 #[derive(Clone, Debug)]
-pub struct TraqServiceImpl {
+pub struct ReportServiceImpl {
     repo: Repository,
-    traq_client: Arc<dyn TraqClient>,
 }
 
-impl TraqServiceImpl {
-    pub fn new(repo: Repository, traq_client: Arc<dyn TraqClient>) -> Self {
-        Self { repo, traq_client }
+impl ReportServiceImpl {
+    pub fn new(repo: Repository) -> Self {
+        Self { repo }
     }
 }
 
 #[async_trait::async_trait]
-impl TraqService for TraqServiceImpl {
-    async fn get_user_by_id(&self, user_id: &Uuid) -> Result<User, DomainError> {
-        let user = match self.repo.user.find_by_id(user_id).await? {
-            Some(user) => user,
-            None => {
-                let token = match self.repo.user.find_random_valid_token().await? {
-                    Some(token) => token,
-                    None => {
-                        return Err(DomainError::NoTokenForUserFetch);
-                    }
-                };
-                let user = self.traq_client.get_user(&token, user_id).await?;
-                self.repo.user.save(&user).await?;
-                user
-            }
-        };
-        Ok(user)
-    }
+impl ReportService for ReportServiceImpl {
+    async fn report_message(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        reason: ReportReason,
+    ) -> Result<(), DomainError> {
+        // Only messages cached in the repository can be reported
+        if self
+            .repo
+            .message_reader
+            .find_by_id(message_id.as_uuid())
+            .await?
+            .is_none()
+        {
+            return Err(DomainError::NoMessageForId(*message_id.as_uuid()));
+        }
 
-    async fn get_user_icon(&self, user_id: &Uuid) -> Result<(Vec<u8>, String), DomainError> {
-        let token = match self.repo.user.find_random_valid_token().await? {
-            Some(token) => token,
-            None => {
-                return Err(DomainError::NoTokenForUserIcon);
-            }
-        };
-        let icon = self.traq_client.get_user_icon(&token, user_id).await?;
-        Ok(icon)
+        self.repo.report.add(user_id, message_id, reason).await?;
+        Ok(())
     }
 
-    async fn get_stamp_by_id(&self, stamp_id: &Uuid) -> Result<Stamp, DomainError> {
-        let stamp = match self.repo.stamp.find_by_id(stamp_id).await? {
-            Some(stamp) => stamp,
-            None => {
-                let token = match self.repo.user.find_random_valid_token().await? {
-                    Some(token) => token,
-                    None => {
-                        return Err(DomainError::NoTokenForStampFetch);
-                    }
-                };
-                let stamp = self.traq_client.get_stamp(&token, stamp_id).await?;
-                self.repo.stamp.save(&stamp).await?;
-                stamp
-            }
-        };
-        Ok(stamp)
+    async fn get_unresolved_reports(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<ReportedMessage>, DomainError> {
+        let mut reports = self.repo.report.find_unresolved(limit).await?;
+
+        let message_ids: Vec<Uuid> = reports.iter().map(|r| r.message_id).collect();
+        let mut messages: HashMap<Uuid, MessageListItem> = self
+            .repo
+            .message_reader
+            .find_list_items_by_ids(&message_ids)
+            .await?
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+        for report in &mut reports {
+            report.message = messages.remove(&report.message_id);
+        }
+
+        Ok(reports)
     }
 
-    async fn get_stamp_image(&self, stamp_id: &Uuid) -> Result<(Vec<u8>, String), DomainError> {
-        let token = match self.repo.user.find_random_valid_token().await? {
-            Some(token) => token,
-            None => {
-                return Err(DomainError::NoTokenForStampImage);
-            }
-        };
-        let image = self.traq_client.get_stamp_image(&token, stamp_id).await?;
-        Ok(image)
+    async fn resolve_reports(&self, message_id: &MessageId) -> Result<(), DomainError> {
+        self.repo.report.resolve(message_id).await?;
+        Ok(())
     }
+}
 
-    async fn get_stamps(&self) -> Result<Vec<Stamp>, DomainError> {
-        let token = match self.repo.user.find_random_valid_token().await? {
-            Some(token) => token,
-            None => {
-                return Err(DomainError::NoTokenForStampsList);
+/// Service for outbound webhooks.
+#[derive(Clone, Debug)]
+pub struct WebhookServiceImpl {
+    repo: Repository,
+}
+
+impl WebhookServiceImpl {
+    pub fn new(repo: Repository) -> Self {
+        Self { repo }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookService for WebhookServiceImpl {
+    async fn get_webhooks(&self) -> Result<Vec<Webhook>, DomainError> {
+        let webhooks = self.repo.webhook.find_all().await?;
+        Ok(webhooks)
+    }
+
+    async fn register_webhook(
+        &self,
+        created_by: &Uuid,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+    ) -> Result<Webhook, DomainError> {
+        let url = url.trim();
+        if !(url.starts_with("https://") || url.starts_with("http://"))
+            || url.len() > MAX_WEBHOOK_URL_LEN
+        {
+            return Err(DomainError::InvalidWebhook(
+                "URLs must be HTTP(S) URLs of at most 2048 characters",
+            ));
+        }
+        if secret.len() < MIN_WEBHOOK_SECRET_LEN {
+            return Err(DomainError::InvalidWebhook(
+                "secrets must be at least 32 bytes",
+            ));
+        }
+        let mut unique_events = Vec::with_capacity(events.len());
+        for event in events {
+            if !unique_events.contains(event) {
+                unique_events.push(*event);
+            }
+        }
+        if unique_events.is_empty() {
+            return Err(DomainError::InvalidWebhook(
+                "at least one event must be subscribed to",
+            ));
+        }
+        if self.repo.webhook.find_all().await?.len() >= MAX_WEBHOOKS {
+            return Err(DomainError::TooManyWebhooks(MAX_WEBHOOKS));
+        }
+
+        let webhook = self
+            .repo
+            .webhook
+            .create(url, secret, &unique_events, created_by)
+            .await?;
+        Ok(webhook)
+    }
+
+    async fn delete_webhook(&self, id: i64) -> Result<(), DomainError> {
+        if !self.repo.webhook.delete(id).await? {
+            return Err(DomainError::NoWebhookForId(id));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PresenceServiceImpl {
+    repo: Repository,
+}
+
+impl PresenceServiceImpl {
+    pub fn new(repo: Repository) -> Self {
+        Self { repo }
+    }
+}
+
+#[async_trait::async_trait]
+impl PresenceService for PresenceServiceImpl {
+    async fn heartbeat(&self, session_id: &str, user_id: &Uuid) -> Result<(), DomainError> {
+        self.repo
+            .presence
+            .save_heartbeat(session_id, user_id, OffsetDateTime::now_utc())
+            .await?;
+        Ok(())
+    }
+
+    async fn is_online(&self, user_id: &Uuid) -> Result<bool, DomainError> {
+        let online = self
+            .repo
+            .presence
+            .is_seen_since(user_id, OffsetDateTime::now_utc() - ONLINE_WINDOW)
+            .await?;
+        Ok(online)
+    }
+
+    async fn get_active_users(&self) -> Result<ActiveUsers, DomainError> {
+        let now = OffsetDateTime::now_utc();
+        let (online, daily) = tokio::try_join!(
+            self.repo
+                .presence
+                .count_users_seen_since(now - ONLINE_WINDOW),
+            self.repo
+                .presence
+                .count_users_seen_since(now - DAILY_ACTIVE_WINDOW),
+        )?;
+        Ok(ActiveUsers { online, daily })
+    }
+}
+
+/// Publishes this instance's highlights and shows those of its peers. Both are disabled by default.
+#[derive(Clone, Debug)]
+pub struct FederationServiceImpl {
+    repo: Repository,
+    instance_name: String,
+    publish: bool,
+    peers: Option<Arc<Peers>>,
+}
+
+impl FederationServiceImpl {
+    pub fn new(repo: Repository, instance_name: String) -> Self {
+        Self {
+            repo,
+            instance_name,
+            publish: false,
+            peers: None,
+        }
+    }
+
+    /// Publishes the highlights of this instance.
+    pub fn publishing(mut self) -> Self {
+        self.publish = true;
+        self
+    }
+
+    pub fn with_peers(mut self, peers: Arc<Peers>) -> Self {
+        self.peers = Some(peers);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl FederationService for FederationServiceImpl {
+    async fn get_highlights(&self) -> Result<InstanceHighlights, DomainError> {
+        if !self.publish {
+            return Err(DomainError::HighlightsNotPublished);
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let highlights = self
+            .repo
+            .message_reader
+            .find_highlights(now - HIGHLIGHTS_WINDOW, HIGHLIGHTS_LIMIT)
+            .await?;
+        Ok(InstanceHighlights {
+            version: HIGHLIGHTS_VERSION,
+            instance: self.instance_name.clone(),
+            generated_at: now,
+            highlights,
+        })
+    }
+
+    async fn get_peer_highlights(&self) -> Vec<PeerHighlights> {
+        match &self.peers {
+            Some(peers) => peers.highlights().await,
+            None => vec![],
+        }
+    }
+}
+
+/// Limits expensive operations per user per day, counting the uses in the repository so that the
+/// counts are shared by every instance of the app.
+#[derive(Clone, Debug)]
+pub struct QuotaServiceImpl {
+    repo: Repository,
+    limits: HashMap<QuotaOperation, i64>,
+}
+
+impl QuotaServiceImpl {
+    /// Starts without quotas, so that nothing is limited.
+    pub fn new(repo: Repository) -> Self {
+        Self {
+            repo,
+            limits: HashMap::new(),
+        }
+    }
+
+    /// Limits the operation to `limit` uses per user per day.
+    pub fn with_limit(mut self, operation: QuotaOperation, limit: i64) -> Self {
+        self.limits.insert(operation, limit);
+        self
+    }
+}
+
+/// Quotas are counted per UTC day, so they are reset at the next midnight UTC.
+fn next_quota_reset(now: OffsetDateTime) -> OffsetDateTime {
+    (now.date() + Duration::DAY).midnight().assume_utc()
+}
+
+#[async_trait::async_trait]
+impl QuotaService for QuotaServiceImpl {
+    async fn consume(&self, user_id: &Uuid, operation: QuotaOperation) -> Result<(), DomainError> {
+        let Some(&limit) = self.limits.get(&operation) else {
+            return Ok(());
+        };
+
+        let now = OffsetDateTime::now_utc();
+        if self
+            .repo
+            .quota
+            .try_use(user_id, operation, now.date(), limit)
+            .await?
+        {
+            return Ok(());
+        }
+        Err(DomainError::QuotaExceeded(QuotaUsage {
+            operation,
+            used: limit,
+            limit,
+            resets_at: next_quota_reset(now),
+        }))
+    }
+
+    async fn get_quotas(&self, user_id: &Uuid) -> Result<Vec<QuotaUsage>, DomainError> {
+        if self.limits.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let used: HashMap<QuotaOperation, i64> = self
+            .repo
+            .quota
+            .find_used(user_id, now.date())
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut quotas: Vec<QuotaUsage> = self
+            .limits
+            .iter()
+            .map(|(&operation, &limit)| QuotaUsage {
+                operation,
+                used: used.get(&operation).copied().unwrap_or_default(),
+                limit,
+                resets_at: next_quota_reset(now),
+            })
+            .collect();
+        quotas.sort_by_key(|quota| <&str>::from(quota.operation));
+        Ok(quotas)
+    }
+}
+
+/// What a user's recommendations are based on.
+struct RecommendationSignals {
+    excluded_users: HashSet<Uuid>,
+    muted_channels: HashSet<Uuid>,
+    hidden_message_ids: HashSet<Uuid>,
+    hidden_author_counts: HashMap<Uuid, i32>,
+    hidden_channel_counts: HashMap<Uuid, i32>,
+    ignored_author_counts: HashMap<Uuid, i32>,
+    ignored_channel_counts: HashMap<Uuid, i32>,
+    channel_score_multipliers: HashMap<Uuid, f64>,
+    affinity_users: Vec<Uuid>,
+    affinity_channels: Vec<Uuid>,
+    similar_users: Vec<Uuid>,
+}
+
+impl RecommendationSignals {
+    /// New users have little signal, so recent messages across channels make up for it.
+    fn is_cold_start(&self) -> bool {
+        self.affinity_users.len() + self.affinity_channels.len() + self.similar_users.len()
+            < COLD_START_MIN_SIGNALS
+    }
+}
+
+/// Service for timeline-related operations.
+#[derive(Clone, Debug)]
+pub struct TimelineServiceImpl {
+    repo: Repository,
+    affinity_half_life_days: f64,
+    recent_messages: Option<Arc<RecentMessages>>,
+    ranker: Arc<dyn Ranker>,
+    report_hide_threshold: Option<i64>,
+    quote_resolver: Option<Arc<QuoteResolver>>,
+    link_preview_resolver: Option<Arc<LinkPreviewResolver>>,
+    search_index: Option<Arc<dyn SearchIndex>>,
+    similar_content: bool,
+    public_channel_ids: Vec<Uuid>,
+}
+
+impl TimelineServiceImpl {
+    pub fn new(repo: Repository) -> Self {
+        Self {
+            repo,
+            affinity_half_life_days: DEFAULT_AFFINITY_HALF_LIFE_DAYS,
+            recent_messages: None,
+            ranker: Arc::new(HeuristicRanker::default()),
+            quote_resolver: None,
+            link_preview_resolver: None,
+            report_hide_threshold: None,
+            search_index: None,
+            similar_content: false,
+            public_channel_ids: vec![],
+        }
+    }
+
+    /// Makes reactions count half as much for affinity every `days` days, instead of every
+    /// [`DEFAULT_AFFINITY_HALF_LIFE_DAYS`] days.
+    pub fn with_affinity_half_life_days(mut self, days: f64) -> Self {
+        self.affinity_half_life_days = days;
+        self
+    }
+
+    /// Shows the messages of the channels to anyone in the public timeline.
+    pub fn with_public_channels(mut self, channel_ids: Vec<Uuid>) -> Self {
+        self.public_channel_ids = channel_ids;
+        self
+    }
+
+    /// Hides messages with at least `threshold` unresolved reports until they are reviewed.
+    pub fn with_report_hide_threshold(mut self, threshold: i64) -> Self {
+        self.report_hide_threshold = Some(threshold);
+        self
+    }
+
+    /// Finds messages hidden from every timeline because of unresolved reports.
+    async fn find_heavily_reported_message_ids(&self) -> Result<Vec<Uuid>, RepositoryError> {
+        match self.report_hide_threshold {
+            Some(threshold) => {
+                self.repo
+                    .report
+                    .find_message_ids_reported_at_least(threshold)
+                    .await
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Scores recommendation candidates with `weights` instead of the defaults.
+    pub fn with_ranking_weights(self, weights: RankingWeights) -> Self {
+        self.with_ranker(Arc::new(HeuristicRanker::new(weights)))
+    }
+
+    /// Orders recommendation candidates with `ranker` instead of [`HeuristicRanker`].
+    pub fn with_ranker(mut self, ranker: Arc<dyn Ranker>) -> Self {
+        self.ranker = ranker;
+        self
+    }
+
+    /// Serves degraded timelines from `recent_messages`.
+    pub fn with_recent_messages(mut self, recent_messages: Arc<RecentMessages>) -> Self {
+        self.recent_messages = Some(recent_messages);
+        self
+    }
+
+    /// Attaches the messages quoted by the returned messages.
+    pub fn with_quote_resolver(mut self, quote_resolver: Arc<QuoteResolver>) -> Self {
+        self.quote_resolver = Some(quote_resolver);
+        self
+    }
+
+    /// Attaches previews of the pages linked from the returned messages.
+    pub fn with_link_preview_resolver(
+        mut self,
+        link_preview_resolver: Arc<LinkPreviewResolver>,
+    ) -> Self {
+        self.link_preview_resolver = Some(link_preview_resolver);
+        self
+    }
+
+    /// Summarizes the reactions of messages for the user, and attaches the messages they quote and
+    /// previews of the pages they link.
+    async fn present(
+        &self,
+        messages: Vec<MessageListItem>,
+        user_id: &Uuid,
+    ) -> Vec<MessageListItem> {
+        let mut messages = summarize_reactions(messages, user_id);
+        if let Some(quote_resolver) = &self.quote_resolver {
+            quote_resolver.attach(user_id, &mut messages).await;
+        }
+        if let Some(link_preview_resolver) = &self.link_preview_resolver {
+            link_preview_resolver.attach(&mut messages).await;
+        }
+        messages
+    }
+
+    /// Searches messages in `search_index`. Searches fail with
+    /// [`DomainError::SearchUnavailable`] without an index.
+    pub fn with_search_index(mut self, search_index: Arc<dyn SearchIndex>) -> Self {
+        self.search_index = Some(search_index);
+        self
+    }
+
+    /// Recommends messages similar to the ones the user stamped.
+    /// Message embeddings must be kept up to date separately.
+    pub fn with_similar_content(mut self) -> Self {
+        self.similar_content = true;
+        self
+    }
+
+    /// Finds what the user must never see, and whose messages and which channels the user likes.
+    async fn find_recommendation_signals(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<RecommendationSignals, RepositoryError> {
+        // 0. Get users and channels that must never appear in the timeline
+        let now = OffsetDateTime::now_utc();
+        let (
+            muted_users,
+            muted_channels,
+            blocked_users,
+            hidden_messages,
+            ignored,
+            channel_score_overrides,
+        ) = tokio::try_join!(
+            self.repo.mute.find_muted_user_ids(user_id),
+            self.repo.mute.find_muted_channel_ids(user_id),
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+            self.repo.feedback.find_hidden_messages(user_id),
+            self.repo.feedback.find_ignored_recommendations(
+                user_id,
+                now - IGNORED_RECOMMENDATIONS_WINDOW,
+                now - IGNORED_RECOMMENDATIONS_GRACE_PERIOD,
+            ),
+            self.repo
+                .user_settings
+                .find_channel_score_overrides(user_id),
+        )?;
+        // Blocks work in both directions, so users who blocked the viewer are hidden too
+        let excluded_users: HashSet<Uuid> = muted_users.into_iter().chain(blocked_users).collect();
+        let muted_channels: HashSet<Uuid> = muted_channels.into_iter().collect();
+        // Hidden messages are never shown again, and their authors and channels are downranked
+        let hidden_message_ids: HashSet<Uuid> =
+            hidden_messages.iter().map(|h| h.message_id).collect();
+        let mut hidden_author_counts = HashMap::<Uuid, i32>::new();
+        let mut hidden_channel_counts = HashMap::<Uuid, i32>::new();
+        for hidden in &hidden_messages {
+            *hidden_author_counts.entry(hidden.author_id).or_default() += 1;
+            *hidden_channel_counts.entry(hidden.channel_id).or_default() += 1;
+        }
+        // Sources the user keeps skipping are downranked a little
+        let mut ignored_author_counts = HashMap::<Uuid, i32>::new();
+        let mut ignored_channel_counts = HashMap::<Uuid, i32>::new();
+        for ignored in &ignored {
+            let count = i32::try_from(ignored.count).unwrap_or(i32::MAX);
+            let author_count = ignored_author_counts.entry(ignored.author_id).or_default();
+            *author_count = author_count.saturating_add(count);
+            let channel_count = ignored_channel_counts
+                .entry(ignored.channel_id)
+                .or_default();
+            *channel_count = channel_count.saturating_add(count);
+        }
+        let channel_score_multipliers: HashMap<Uuid, f64> = channel_score_overrides
+            .into_iter()
+            .map(|o| (o.channel_id, o.multiplier))
+            .collect();
+
+        // 1. Get user affinity list (people I stamp)
+        let affinity_users: Vec<Uuid> = self
+            .repo
+            .user
+            .find_frequently_stamped_users_by(
+                user_id,
+                AFFINITY_USERS_LIMIT,
+                self.affinity_half_life_days,
+            )
+            .await?
+            .into_iter()
+            .map(|affinity| affinity.id)
+            .filter(|id| !excluded_users.contains(id))
+            .collect();
+
+        // 2. Get channel affinity list (channels I stamp in)
+        let mut stamped_channels: Vec<Uuid> = self
+            .repo
+            .stamp
+            .find_frequently_stamped_channels_by(
+                user_id,
+                AFFINITY_CHANNELS_LIMIT,
+                self.affinity_half_life_days,
+            )
+            .await?
+            .into_iter()
+            .map(|affinity| affinity.id)
+            .collect();
+        // Channels picked during onboarding stand in until the user stamps anything
+        if stamped_channels.is_empty() {
+            stamped_channels = self
+                .repo
+                .user_settings
+                .find_channel_interests(user_id)
+                .await?;
+        }
+        let affinity_channels: Vec<Uuid> = stamped_channels
+            .into_iter()
+            .filter(|id| !muted_channels.contains(id))
+            .collect();
+
+        // 3. Get similar users (people who stamp same msgs)
+        let similar_users: Vec<Uuid> = self
+            .repo
+            .user
+            .find_similar_users(user_id, 20)
+            .await?
+            .into_iter()
+            .filter(|id| !excluded_users.contains(id))
+            .collect();
+
+        Ok(RecommendationSignals {
+            excluded_users,
+            muted_channels,
+            hidden_message_ids,
+            hidden_author_counts,
+            hidden_channel_counts,
+            ignored_author_counts,
+            ignored_channel_counts,
+            channel_score_multipliers,
+            affinity_users,
+            affinity_channels,
+            similar_users,
+        })
+    }
+
+    /// Filters and scores candidates from each source, and returns the top 50.
+    fn rank_candidates(
+        &self,
+        signals: &RecommendationSignals,
+        reported_message_ids: &HashSet<Uuid>,
+        sources: [(Vec<MessageListItem>, RecommendationReason); 6],
+    ) -> Vec<MessageListItem> {
+        let mut candidates = Vec::with_capacity(sources.iter().map(|(msgs, _)| msgs.len()).sum());
+        for (msgs, source) in sources {
+            for (rank, message) in msgs.into_iter().enumerate() {
+                // The repository already excludes muted users and channels, but not blocked users
+                // Heavily reported messages are hidden like the user's own hidden messages
+                if signals.excluded_users.contains(&message.user_id)
+                    || signals.muted_channels.contains(&message.channel_id)
+                    || signals.hidden_message_ids.contains(&message.id)
+                    || reported_message_ids.contains(&message.id)
+                {
+                    continue;
+                }
+
+                // Authors and channels of hidden messages and ignored recommendations are
+                // downranked
+                candidates.push(ScoredCandidate {
+                    hidden_author_count: signals
+                        .hidden_author_counts
+                        .get(&message.user_id)
+                        .copied()
+                        .unwrap_or(0),
+                    hidden_channel_count: signals
+                        .hidden_channel_counts
+                        .get(&message.channel_id)
+                        .copied()
+                        .unwrap_or(0),
+                    ignored_author_count: signals
+                        .ignored_author_counts
+                        .get(&message.user_id)
+                        .copied()
+                        .unwrap_or(0),
+                    ignored_channel_count: signals
+                        .ignored_channel_counts
+                        .get(&message.channel_id)
+                        .copied()
+                        .unwrap_or(0),
+                    channel_score_multiplier: signals
+                        .channel_score_multipliers
+                        .get(&message.channel_id)
+                        .copied()
+                        .unwrap_or(1.0),
+                    message,
+                    source,
+                    rank,
+                });
+            }
+        }
+
+        let mut result = self.ranker.rank(candidates);
+        result.truncate(50);
+        result
+    }
+
+    /// Finds recent messages closest to the average embedding of the messages the user stamped,
+    /// most similar first.
+    async fn find_similar_content_messages(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        if !self.similar_content {
+            return Ok(vec![]);
+        }
+
+        let stamped = self
+            .repo
+            .embedding
+            .find_stamped_message_embeddings(user_id, SIMILAR_CONTENT_SEED_LIMIT)
+            .await?;
+        let Some(taste) = centroid(stamped.iter().map(|e| e.embedding.as_slice())) else {
+            return Ok(vec![]);
+        };
+
+        let mut scored: Vec<(Uuid, f32)> = self
+            .repo
+            .embedding
+            .find_candidate_embeddings(user_id, SIMILAR_CONTENT_CANDIDATE_LIMIT)
+            .await?
+            .into_iter()
+            .map(|e| (e.message_id, cosine_similarity(&taste, &e.embedding)))
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        scored.truncate(SIMILAR_CONTENT_LIMIT);
+
+        let ids: Vec<Uuid> = scored.iter().map(|(id, _)| *id).collect();
+        let positions: HashMap<Uuid, usize> =
+            ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let mut messages = self
+            .repo
+            .message_reader
+            .find_list_items_by_ids(&ids)
+            .await?;
+        messages.sort_by_key(|m| positions.get(&m.id).copied().unwrap_or(usize::MAX));
+
+        Ok(messages)
+    }
+}
+
+#[async_trait::async_trait]
+impl TimelineService for TimelineServiceImpl {
+    async fn get_recommended_messages(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, DomainError> {
+        // 0-3. Get what the user must never see and what the user likes
+        let (signals, reported_message_ids) = tokio::try_join!(
+            self.find_recommendation_signals(user_id),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        let reported_message_ids: HashSet<Uuid> = reported_message_ids.into_iter().collect();
+
+        let recent = async {
+            if signals.is_cold_start() {
+                self.repo
+                    .message_reader
+                    .find_recent_messages_across_channels(
+                        user_id,
+                        COLD_START_MESSAGES_PER_CHANNEL,
+                        50,
+                    )
+                    .await
+            } else {
+                Ok(vec![])
+            }
+        };
+
+        // 4. Fetch candidates from all sources concurrently
+        // To avoid finding messages that user already read or self-authored, we pass user_id.
+        let (
+            top_reacts,
+            affinity_author_msgs,
+            affinity_channel_msgs,
+            similar_user_msgs,
+            recent_msgs,
+            similar_content_msgs,
+        ) = tokio::join!(
+            self.repo
+                .message_reader
+                .find_top_reacted_messages(user_id, 50),
+            self.repo.message_reader.find_messages_by_author_allowlist(
+                &signals.affinity_users,
+                50,
+                user_id
+            ),
+            self.repo.message_reader.find_messages_by_channel_allowlist(
+                &signals.affinity_channels,
+                50,
+                user_id
+            ),
+            self.repo.message_reader.find_messages_by_author_allowlist(
+                &signals.similar_users,
+                50,
+                user_id
+            ),
+            recent,
+            self.find_similar_content_messages(user_id),
+        );
+
+        // 5-6. Collect candidates from all sources, and merge and score them
+        let sources = [
+            (top_reacts?, RecommendationReason::Popular),
+            (
+                affinity_author_msgs?,
+                RecommendationReason::FrequentlyStampedAuthor,
+            ),
+            (
+                affinity_channel_msgs?,
+                RecommendationReason::FrequentlyStampedChannel,
+            ),
+            (similar_user_msgs?, RecommendationReason::SimilarUsers),
+            (recent_msgs?, RecommendationReason::Recent),
+            (similar_content_msgs?, RecommendationReason::SimilarContent),
+        ];
+
+        let messages = self.rank_candidates(&signals, &reported_message_ids, sources);
+        Ok(self.present(messages, user_id).await)
+    }
+
+    async fn get_recommended_messages_for_users(
+        &self,
+        user_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<MessageListItem>>, DomainError> {
+        if user_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let reported_message_ids: HashSet<Uuid> = self
+            .find_heavily_reported_message_ids()
+            .await?
+            .into_iter()
+            .collect();
+        let mut signals = Vec::with_capacity(user_ids.len());
+        let mut similar_content = Vec::with_capacity(user_ids.len());
+        for chunk in user_ids.chunks(BATCH_CONCURRENCY) {
+            let (chunk_signals, chunk_similar_content) = tokio::try_join!(
+                try_join_all(chunk.iter().map(|id| self.find_recommendation_signals(id))),
+                try_join_all(
+                    chunk
+                        .iter()
+                        .map(|id| self.find_similar_content_messages(id))
+                ),
+            )?;
+            signals.extend(chunk_signals);
+            similar_content.extend(chunk_similar_content);
+        }
+
+        // Candidates are fetched once for everyone and filtered for each user below
+        let authors: Vec<Uuid> = signals
+            .iter()
+            .flat_map(|s| s.affinity_users.iter().chain(&s.similar_users))
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let channels: Vec<Uuid> = signals
+            .iter()
+            .flat_map(|s| &s.affinity_channels)
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let any_cold_start = signals.iter().any(RecommendationSignals::is_cold_start);
+        let recent = async {
+            if any_cold_start {
+                self.repo
+                    .message_reader
+                    .find_recent_messages_across_channels(
+                        &NO_VIEWER,
+                        COLD_START_MESSAGES_PER_CHANNEL,
+                        BATCH_CANDIDATE_POOL_LIMIT,
+                    )
+                    .await
+            } else {
+                Ok(vec![])
+            }
+        };
+        let (popular, by_authors, by_channels, recent) = tokio::try_join!(
+            self.repo
+                .message_reader
+                .find_top_reacted_messages(&NO_VIEWER, BATCH_CANDIDATE_POOL_LIMIT),
+            self.repo.message_reader.find_messages_by_author_allowlist(
+                &authors,
+                BATCH_CANDIDATE_POOL_LIMIT,
+                &NO_VIEWER
+            ),
+            self.repo.message_reader.find_messages_by_channel_allowlist(
+                &channels,
+                BATCH_CANDIDATE_POOL_LIMIT,
+                &NO_VIEWER
+            ),
+            recent,
+        )?;
+        let pool = || {
+            popular
+                .iter()
+                .chain(&by_authors)
+                .chain(&by_channels)
+                .chain(&recent)
+        };
+        let pool_ids: Vec<Uuid> = pool()
+            .map(|m| m.id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let pool_authors: Vec<Uuid> = pool()
+            .map(|m| m.user_id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        // The pool includes the messages of users who are recommended only to acquaintances, so
+        // they are shown only to the users they are acquainted with
+        let (reads, acquaintance_only) = tokio::try_join!(
+            self.repo
+                .message_reader
+                .find_read_message_ids_by_users(user_ids, &pool_ids),
+            self.repo
+                .user_settings
+                .find_acquaintance_only_user_ids(&pool_authors),
+        )?;
+        let acquaintances: HashSet<(Uuid, Uuid)> = if acquaintance_only.is_empty() {
+            HashSet::new()
+        } else {
+            self.repo
+                .user
+                .find_acquaintances(user_ids, &acquaintance_only)
+                .await?
+                .into_iter()
+                .collect()
+        };
+        let reads: HashSet<(Uuid, Uuid)> = reads.into_iter().collect();
+        let acquaintance_only: HashSet<Uuid> = acquaintance_only.into_iter().collect();
+
+        let mut ranked = Vec::with_capacity(user_ids.len());
+        for ((user_id, signals), similar_content) in
+            user_ids.iter().zip(signals).zip(similar_content)
+        {
+            let visible = |m: &MessageListItem| {
+                m.user_id != *user_id
+                    && !reads.contains(&(*user_id, m.id))
+                    && (!acquaintance_only.contains(&m.user_id)
+                        || acquaintances.contains(&(*user_id, m.user_id)))
+            };
+            // Each source keeps its order, so that ranks are the same as for a single user
+            let pick = |messages: &[MessageListItem], keep: &dyn Fn(&MessageListItem) -> bool| {
+                messages
+                    .iter()
+                    .filter(|m| visible(m) && keep(m))
+                    .take(50)
+                    .cloned()
+                    .collect::<Vec<_>>()
+            };
+            let affinity_users: HashSet<Uuid> = signals.affinity_users.iter().copied().collect();
+            let affinity_channels: HashSet<Uuid> =
+                signals.affinity_channels.iter().copied().collect();
+            let similar_users: HashSet<Uuid> = signals.similar_users.iter().copied().collect();
+
+            let sources = [
+                (pick(&popular, &|_| true), RecommendationReason::Popular),
+                (
+                    pick(&by_authors, &|m| affinity_users.contains(&m.user_id)),
+                    RecommendationReason::FrequentlyStampedAuthor,
+                ),
+                (
+                    pick(&by_channels, &|m| affinity_channels.contains(&m.channel_id)),
+                    RecommendationReason::FrequentlyStampedChannel,
+                ),
+                (
+                    pick(&by_authors, &|m| similar_users.contains(&m.user_id)),
+                    RecommendationReason::SimilarUsers,
+                ),
+                (
+                    pick(&recent, &|_| signals.is_cold_start()),
+                    RecommendationReason::Recent,
+                ),
+                (similar_content, RecommendationReason::SimilarContent),
+            ];
+
+            let messages = self.rank_candidates(&signals, &reported_message_ids, sources);
+            ranked.push((*user_id, messages));
+        }
+
+        let mut recommendations = HashMap::with_capacity(user_ids.len());
+        for chunk in ranked.chunks_mut(BATCH_CONCURRENCY) {
+            let presented = join_all(chunk.iter_mut().map(|(user_id, messages)| async {
+                (*user_id, self.present(mem::take(messages), user_id).await)
+            }))
+            .await;
+            recommendations.extend(presented);
+        }
+
+        Ok(recommendations)
+    }
+
+    fn get_degraded_messages(&self, user_id: &Uuid) -> Vec<MessageListItem> {
+        let Some(recent_messages) = &self.recent_messages else {
+            return vec![];
+        };
+
+        let messages = recent_messages
+            .latest(DEGRADED_TIMELINE_LIMIT)
+            .into_iter()
+            .filter(|m| m.user_id != *user_id)
+            .collect();
+        summarize_reactions(messages, user_id)
+    }
+
+    async fn get_explore_messages(
+        &self,
+        user_id: &Uuid,
+        window: TrendingWindow,
+    ) -> Result<Vec<MessageListItem>, DomainError> {
+        let (mut messages, blocked_users, reported_message_ids) = tokio::try_join!(
+            self.repo
+                .message_reader
+                .find_trending_messages(user_id, window.hours(), EXPLORE_LIMIT),
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        messages.retain(|m| {
+            !blocked_users.contains(&m.user_id) && !reported_message_ids.contains(&m.id)
+        });
+        Ok(self.present(messages, user_id).await)
+    }
+
+    async fn search_messages(
+        &self,
+        user_id: &Uuid,
+        query: &str,
+    ) -> Result<Vec<MessageListItem>, DomainError> {
+        let mut messages = match &self.search_index {
+            Some(search_index) => {
+                let ids = search_index.search_messages(query, SEARCH_LIMIT).await?;
+                let mut messages = self
+                    .repo
+                    .message_reader
+                    .find_list_items_by_ids(&ids)
+                    .await?;
+
+                // The repository returns messages in no particular order
+                let positions: HashMap<Uuid, usize> =
+                    ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+                messages.sort_by_key(|m| positions.get(&m.id).copied().unwrap_or(usize::MAX));
+                messages
+            }
+            // Deployments without a search engine use the database's full-text index
+            None => {
+                self.repo
+                    .message_reader
+                    .search(query, SEARCH_LIMIT as i64, user_id)
+                    .await?
+            }
+        };
+        let (blocked_users, reported_message_ids) = tokio::try_join!(
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        messages.retain(|m| {
+            !blocked_users.contains(&m.user_id) && !reported_message_ids.contains(&m.id)
+        });
+
+        Ok(self.present(messages, user_id).await)
+    }
+
+    async fn save_search(
+        &self,
+        user_id: &Uuid,
+        name: &str,
+        query: &str,
+    ) -> Result<SavedSearch, DomainError> {
+        let (name, query) = (name.trim(), query.trim());
+        if name.is_empty() || name.chars().count() > MAX_SAVED_SEARCH_NAME_LEN {
+            return Err(DomainError::InvalidSavedSearch(
+                "names must be between 1 and 64 characters",
+            ));
+        }
+        if query.is_empty() || query.chars().count() > MAX_SAVED_SEARCH_QUERY_LEN {
+            return Err(DomainError::InvalidSavedSearch(
+                "queries must be between 1 and 256 characters",
+            ));
+        }
+        if self.repo.saved_search.find_by_user(user_id).await?.len() >= MAX_SAVED_SEARCHES {
+            return Err(DomainError::TooManySavedSearches(MAX_SAVED_SEARCHES));
+        }
+
+        let search = self.repo.saved_search.create(user_id, name, query).await?;
+        Ok(search)
+    }
+
+    async fn get_saved_searches(&self, user_id: &Uuid) -> Result<Vec<SavedSearch>, DomainError> {
+        let searches = self.repo.saved_search.find_by_user(user_id).await?;
+        Ok(searches)
+    }
+
+    async fn delete_saved_search(&self, user_id: &Uuid, id: i64) -> Result<(), DomainError> {
+        if !self.repo.saved_search.delete(user_id, id).await? {
+            return Err(DomainError::NoSavedSearchForId(id));
+        }
+
+        Ok(())
+    }
+
+    async fn run_saved_search(
+        &self,
+        user_id: &Uuid,
+        id: i64,
+    ) -> Result<Vec<MessageListItem>, DomainError> {
+        let search = self
+            .repo
+            .saved_search
+            .find_by_id(user_id, id)
+            .await?
+            .ok_or(DomainError::NoSavedSearchForId(id))?;
+
+        self.search_messages(user_id, &search.query).await
+    }
+
+    async fn get_tag_messages(
+        &self,
+        user_id: &Uuid,
+        tag: &str,
+    ) -> Result<Vec<MessageListItem>, DomainError> {
+        let tag = hashtag::normalize_tag(tag).ok_or(DomainError::InvalidTag)?;
+        let (mut messages, blocked_users, reported_message_ids) = tokio::try_join!(
+            self.repo
+                .message_reader
+                .find_by_tag(&tag, TAG_MESSAGES_LIMIT, user_id),
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        messages.retain(|m| {
+            !blocked_users.contains(&m.user_id) && !reported_message_ids.contains(&m.id)
+        });
+
+        Ok(self.present(messages, user_id).await)
+    }
+
+    async fn get_user_messages(
+        &self,
+        user_id: &Uuid,
+        author_id: &Uuid,
+        before: Option<String>,
+    ) -> Result<MessagePage, DomainError> {
+        let before = decode_cursor(before)?;
+        let (mut messages, blocked_users, reported_message_ids) = tokio::try_join!(
+            self.repo
+                .message_reader
+                .find_by_author(author_id, before, USER_MESSAGES_PAGE_SIZE + 1),
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        if blocked_users.contains(author_id) {
+            return Ok(MessagePage::default());
+        }
+
+        let next_cursor = truncate_to_page(&mut messages, USER_MESSAGES_PAGE_SIZE);
+        messages.retain(|m| !reported_message_ids.contains(&m.id));
+
+        Ok(MessagePage {
+            messages: self.present(messages, user_id).await,
+            next_cursor,
+        })
+    }
+
+    async fn get_related_messages(
+        &self,
+        user_id: &Uuid,
+        message_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, DomainError> {
+        let (mut messages, blocked_users, reported_message_ids) = tokio::try_join!(
+            self.repo.message_reader.find_co_reacted_messages(
+                message_id,
+                RELATED_MESSAGES_LIMIT,
+                user_id
+            ),
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        messages.retain(|m| {
+            !blocked_users.contains(&m.user_id) && !reported_message_ids.contains(&m.id)
+        });
+
+        Ok(self.present(messages, user_id).await)
+    }
+
+    async fn get_thread(
+        &self,
+        user_id: &Uuid,
+        message_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, DomainError> {
+        let (mut messages, blocked_users, reported_message_ids) = tokio::try_join!(
+            self.repo
+                .message_reader
+                .find_thread(message_id, THREAD_MESSAGES_LIMIT),
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        if messages.is_empty() {
+            return Err(DomainError::NoMessageForId(*message_id));
+        }
+
+        messages.retain(|m| {
+            !blocked_users.contains(&m.user_id) && !reported_message_ids.contains(&m.id)
+        });
+
+        Ok(self.present(messages, user_id).await)
+    }
+
+    async fn get_public_timeline(
+        &self,
+        before: Option<String>,
+    ) -> Result<MessagePage, DomainError> {
+        if self.public_channel_ids.is_empty() {
+            return Err(DomainError::PublicTimelineDisabled);
+        }
+
+        let before = decode_cursor(before)?;
+        let (mut messages, reported_message_ids) = tokio::try_join!(
+            self.repo.message_reader.find_public_messages(
+                &self.public_channel_ids,
+                before,
+                PUBLIC_TIMELINE_PAGE_SIZE + 1
+            ),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        let next_cursor = truncate_to_page(&mut messages, PUBLIC_TIMELINE_PAGE_SIZE);
+        messages.retain(|m| !reported_message_ids.contains(&m.id));
+
+        // Quotes are not attached, since they may be of messages in other channels
+        let mut messages = summarize_reactions(messages, &Uuid::nil());
+        if let Some(link_preview_resolver) = &self.link_preview_resolver {
+            link_preview_resolver.attach(&mut messages).await;
+        }
+        Ok(MessagePage {
+            messages,
+            next_cursor,
+        })
+    }
+
+    async fn get_trending_tags(
+        &self,
+        window: TrendingWindow,
+    ) -> Result<Vec<TrendingTag>, DomainError> {
+        let tags = self
+            .repo
+            .message_reader
+            .find_trending_tags(window.hours(), TRENDING_TAGS_LIMIT)
+            .await?;
+
+        Ok(tags)
+    }
+
+    async fn get_affinity(&self, user_id: &Uuid) -> Result<Affinity, DomainError> {
+        let (authors, channels) = tokio::try_join!(
+            self.repo.user.find_frequently_stamped_users_by(
+                user_id,
+                AFFINITY_USERS_LIMIT,
+                self.affinity_half_life_days,
+            ),
+            self.repo.stamp.find_frequently_stamped_channels_by(
+                user_id,
+                AFFINITY_CHANNELS_LIMIT,
+                self.affinity_half_life_days,
+            ),
+        )?;
+
+        Ok(Affinity { authors, channels })
+    }
+
+    async fn get_following_messages(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, DomainError> {
+        let (mut messages, reported_message_ids) = tokio::try_join!(
+            self.repo
+                .follow
+                .find_followed_messages(user_id, FOLLOWING_TIMELINE_LIMIT),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        messages.retain(|m| !reported_message_ids.contains(&m.id));
+        Ok(self.present(messages, user_id).await)
+    }
+
+    async fn get_timeline_updates(
+        &self,
+        user_id: &Uuid,
+        since: Option<i64>,
+    ) -> Result<TimelineUpdates, DomainError> {
+        let Some(since) = since else {
+            let cursor = self.repo.message_event.find_latest_cursor().await?;
+            return Ok(TimelineUpdates {
+                cursor,
+                ..Default::default()
+            });
+        };
+
+        let mut events = self
+            .repo
+            .message_event
+            .find_since(since, TIMELINE_UPDATES_LIMIT + 1)
+            .await?;
+        let has_more = events.len() > TIMELINE_UPDATES_LIMIT as usize;
+        events.truncate(TIMELINE_UPDATES_LIMIT as usize);
+        let cursor = events.last().map_or(since, |e| e.cursor);
+
+        // Collapse events per message. A message added and then updated is still new to the
+        // client, and a removal overrides everything before it.
+        let mut kinds = HashMap::<Uuid, MessageEventKind>::new();
+        for event in events {
+            kinds
+                .entry(event.message_id)
+                .and_modify(|kind| {
+                    if *kind != MessageEventKind::Added || event.kind == MessageEventKind::Removed {
+                        *kind = event.kind;
+                    }
+                })
+                .or_insert(event.kind);
+        }
+
+        let mut updates = TimelineUpdates {
+            cursor,
+            has_more,
+            ..Default::default()
+        };
+        let changed_ids: Vec<Uuid> = kinds
+            .iter()
+            .filter(|(_, kind)| **kind != MessageEventKind::Removed)
+            .map(|(id, _)| *id)
+            .collect();
+        updates.removed = kinds
+            .iter()
+            .filter(|(_, kind)| **kind == MessageEventKind::Removed)
+            .map(|(id, _)| *id)
+            .collect();
+        if changed_ids.is_empty() {
+            return Ok(updates);
+        }
+
+        let (
+            messages,
+            muted_users,
+            muted_channels,
+            blocked_users,
+            hidden_messages,
+            reported_message_ids,
+        ) = tokio::try_join!(
+            self.repo
+                .message_reader
+                .find_list_items_by_ids(&changed_ids),
+            self.repo.mute.find_muted_user_ids(user_id),
+            self.repo.mute.find_muted_channel_ids(user_id),
+            self.repo.block.find_blocked_or_blocking_user_ids(user_id),
+            self.repo.feedback.find_hidden_messages(user_id),
+            self.find_heavily_reported_message_ids(),
+        )?;
+        let excluded_users: HashSet<Uuid> = muted_users.into_iter().chain(blocked_users).collect();
+        let muted_channels: HashSet<Uuid> = muted_channels.into_iter().collect();
+        let hidden_message_ids: HashSet<Uuid> = hidden_messages
+            .into_iter()
+            .map(|h| h.message_id)
+            .chain(reported_message_ids)
+            .collect();
+
+        for mut message in messages {
+            if message.user_id == *user_id
+                || excluded_users.contains(&message.user_id)
+                || muted_channels.contains(&message.channel_id)
+                || hidden_message_ids.contains(&message.id)
+            {
+                continue;
+            }
+            message.summarize_reactions(user_id);
+            match kinds.get(&message.id) {
+                Some(MessageEventKind::Added) => updates.added.push(message),
+                Some(MessageEventKind::Updated) => updates.updated.push(message),
+                _ => {}
+            }
+        }
+
+        Ok(updates)
+    }
+
+    async fn mark_messages_as_read(
+        &self,
+        user_id: &UserId,
+        message_ids: &[MessageId],
+    ) -> Result<(), DomainError> {
+        self.repo
+            .message_writer
+            .mark_messages_as_read(user_id, message_ids)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_impressions(
+        &self,
+        user_id: &Uuid,
+        impressions: &[Impression],
+    ) -> Result<(), DomainError> {
+        self.repo
+            .impression
+            .record_impressions(user_id, impressions)
+            .await?;
+        Ok(())
+    }
+
+    async fn find_read_message_ids(
+        &self,
+        user_id: &Uuid,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, DomainError> {
+        Ok(self
+            .repo
+            .message_reader
+            .find_read_message_ids(user_id, message_ids)
+            .await?)
+    }
+
+    async fn hide_message(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+    ) -> Result<(), DomainError> {
+        let message_id = message_id.as_uuid();
+        let message = match self.repo.message_reader.find_by_id(message_id).await? {
+            Some(message) => message,
+            None => return Err(DomainError::NoMessageForId(*message_id)),
+        };
+
+        self.repo
+            .feedback
+            .hide_message(user_id.as_uuid(), &message)
+            .await?;
+        Ok(())
+    }
+
+    async fn mute_user(&self, user_id: &UserId, muted_user_id: &UserId) -> Result<(), DomainError> {
+        if user_id == muted_user_id {
+            return Err(DomainError::CannotMuteSelf);
+        }
+
+        self.repo.mute.mute_user(user_id, muted_user_id).await?;
+        Ok(())
+    }
+
+    async fn unmute_user(
+        &self,
+        user_id: &UserId,
+        muted_user_id: &UserId,
+    ) -> Result<(), DomainError> {
+        self.repo.mute.unmute_user(user_id, muted_user_id).await?;
+        Ok(())
+    }
+
+    async fn get_channel_score_overrides(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<ChannelScoreOverride>, DomainError> {
+        let overrides = self
+            .repo
+            .user_settings
+            .find_channel_score_overrides(user_id)
+            .await?;
+        Ok(overrides)
+    }
+
+    async fn set_channel_score_override(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+        multiplier: f64,
+    ) -> Result<ChannelScoreOverride, DomainError> {
+        if !(MIN_CHANNEL_SCORE_MULTIPLIER..=MAX_CHANNEL_SCORE_MULTIPLIER).contains(&multiplier) {
+            return Err(DomainError::InvalidChannelScoreMultiplier);
+        }
+        let (user_id, channel_id) = (user_id.as_uuid(), channel_id.as_uuid());
+        let overrides = self
+            .repo
+            .user_settings
+            .find_channel_score_overrides(user_id)
+            .await?;
+        // Replacing an existing multiplier doesn't add an override
+        if overrides.len() >= MAX_CHANNEL_SCORE_OVERRIDES
+            && !overrides.iter().any(|o| o.channel_id == *channel_id)
+        {
+            return Err(DomainError::TooManyChannelScoreOverrides(
+                MAX_CHANNEL_SCORE_OVERRIDES,
+            ));
+        }
+
+        let score_override = ChannelScoreOverride {
+            channel_id: *channel_id,
+            multiplier,
+        };
+        self.repo
+            .user_settings
+            .save_channel_score_override(user_id, &score_override)
+            .await?;
+        Ok(score_override)
+    }
+
+    async fn remove_channel_score_override(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), DomainError> {
+        self.repo
+            .user_settings
+            .delete_channel_score_override(user_id, channel_id)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_privacy_settings(&self, user_id: &Uuid) -> Result<PrivacySettings, DomainError> {
+        let settings = self
+            .repo
+            .user_settings
+            .find_privacy_settings(user_id)
+            .await?;
+        Ok(settings)
+    }
+
+    async fn set_privacy_settings(
+        &self,
+        user_id: &Uuid,
+        settings: &PrivacySettings,
+    ) -> Result<(), DomainError> {
+        self.repo
+            .user_settings
+            .save_privacy_settings(user_id, settings)
+            .await?;
+        Ok(())
+    }
+
+    async fn mute_channel(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), DomainError> {
+        self.repo.mute.mute_channel(user_id, channel_id).await?;
+        Ok(())
+    }
+
+    async fn unmute_channel(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), DomainError> {
+        self.repo.mute.unmute_channel(user_id, channel_id).await?;
+        Ok(())
+    }
+
+    async fn block_user(
+        &self,
+        user_id: &UserId,
+        blocked_user_id: &UserId,
+    ) -> Result<(), DomainError> {
+        if user_id == blocked_user_id {
+            return Err(DomainError::CannotBlockSelf);
+        }
+
+        self.repo.block.block_user(user_id, blocked_user_id).await?;
+        Ok(())
+    }
+
+    async fn unblock_user(
+        &self,
+        user_id: &UserId,
+        blocked_user_id: &UserId,
+    ) -> Result<(), DomainError> {
+        self.repo
+            .block
+            .unblock_user(user_id, blocked_user_id)
+            .await?;
+        Ok(())
+    }
+
+    async fn follow_user(
+        &self,
+        user_id: &UserId,
+        followed_user_id: &UserId,
+    ) -> Result<(), DomainError> {
+        if user_id == followed_user_id {
+            return Err(DomainError::CannotFollowSelf);
+        }
+
+        self.repo
+            .follow
+            .follow_user(user_id, followed_user_id)
+            .await?;
+        Ok(())
+    }
+
+    async fn unfollow_user(
+        &self,
+        user_id: &UserId,
+        followed_user_id: &UserId,
+    ) -> Result<(), DomainError> {
+        self.repo
+            .follow
+            .unfollow_user(user_id, followed_user_id)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_announcement(&self, user_id: &Uuid) -> Result<Option<Announcement>, DomainError> {
+        let announcement = self.repo.announcement.find_undismissed(user_id).await?;
+        Ok(announcement)
+    }
+
+    async fn dismiss_announcement(
+        &self,
+        user_id: &Uuid,
+        announcement_id: i64,
+    ) -> Result<(), DomainError> {
+        self.repo
+            .announcement
+            .dismiss(user_id, announcement_id)
+            .await?;
+        Ok(())
+    }
+
+    async fn publish_announcement(&self, content: &str) -> Result<Announcement, DomainError> {
+        let content = content.trim();
+        if content.is_empty() || content.chars().count() > MAX_ANNOUNCEMENT_LEN {
+            return Err(DomainError::InvalidAnnouncement(MAX_ANNOUNCEMENT_LEN));
+        }
+
+        let announcement = self.repo.announcement.publish(content).await?;
+        Ok(announcement)
+    }
+
+    async fn withdraw_announcement(&self) -> Result<(), DomainError> {
+        if !self.repo.announcement.withdraw().await? {
+            return Err(DomainError::NoAnnouncement);
+        }
+
+        Ok(())
+    }
+}
+
+/// Handles general data fetching from traQ.
+/// It utilizes the repository as a cache and fetches data from traQ only when necessary.
+/// Twittra's unique features such as recommendations are not handled here.
+#[derive(Clone, Debug)]
+pub struct TraqServiceImpl {
+    repo: Repository,
+    traq_client: Arc<dyn TraqClient>,
+    thumbnailer: Option<Arc<Thumbnailer>>,
+    image_cache: Option<Arc<ImageCache>>,
+    /// Set while images are being prefetched, so that pages requested at once don't prefetch
+    /// concurrently.
+    prefetching: Arc<AtomicBool>,
+}
+
+impl TraqServiceImpl {
+    pub fn new(repo: Repository, traq_client: Arc<dyn TraqClient>) -> Self {
+        Self {
+            repo,
+            traq_client,
+            thumbnailer: None,
+            image_cache: None,
+            prefetching: Arc::default(),
+        }
+    }
+
+    /// Resizes user icons and stamp images with `thumbnailer`. Without one, images are served in
+    /// their original size whatever size is requested.
+    pub fn with_thumbnailer(mut self, thumbnailer: Arc<Thumbnailer>) -> Self {
+        self.thumbnailer = Some(thumbnailer);
+        self
+    }
+
+    /// Caches the original user icons and stamp images with `image_cache`. Without one, traQ is
+    /// asked for the image on every request.
+    pub fn with_image_cache(mut self, image_cache: Arc<ImageCache>) -> Self {
+        self.image_cache = Some(image_cache);
+        self
+    }
+
+    /// Returns the image in `size`, fetching the original with `fetch` unless it's cached. `fetch`
+    /// is owned, so that the cache can refresh the original after the request has been served.
+    async fn get_image(
+        &self,
+        kind: ImageKind,
+        id: &Uuid,
+        size: ImageSize,
+        fetch: impl Future<Output = Result<(Vec<u8>, String), DomainError>> + Send + 'static,
+    ) -> Result<(Vec<u8>, String), DomainError> {
+        let original = async {
+            match &self.image_cache {
+                Some(cache) => cache.get(kind, id, fetch).await,
+                None => fetch.await,
+            }
+        };
+        match &self.thumbnailer {
+            Some(thumbnailer) => thumbnailer.get(kind, id, size, original).await,
+            None => original.await,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TraqService for TraqServiceImpl {
+    async fn get_user_by_id(&self, user_id: &Uuid) -> Result<User, DomainError> {
+        let user = match self.repo.user.find_by_id(user_id).await? {
+            Some(user) => user,
+            None => {
+                let token = match self.repo.user.find_random_valid_token().await? {
+                    Some(token) => token,
+                    None => {
+                        return Err(DomainError::NoTokenForUserFetch);
+                    }
+                };
+                let user = self.traq_client.get_user(&token, user_id).await?;
+                self.repo.user.save(&user).await?;
+                user
+            }
+        };
+        Ok(user)
+    }
+
+    async fn get_users_by_ids(
+        &self,
+        user_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, User>, DomainError> {
+        let mut users: HashMap<Uuid, User> = self
+            .repo
+            .user
+            .find_by_ids(user_ids)
+            .await?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        let missing: Vec<&Uuid> = user_ids
+            .iter()
+            .filter(|id| !users.contains_key(id))
+            .collect();
+        if missing.is_empty() {
+            return Ok(users);
+        }
+
+        let Some(token) = self.repo.user.find_random_valid_token().await? else {
+            return Err(DomainError::NoTokenForUserFetch);
+        };
+        // traQ cannot look up users by IDs
+        for user_id in missing {
+            let user = self.traq_client.get_user(&token, user_id).await?;
+            self.repo.user.save(&user).await?;
+            users.insert(user.id, user);
+        }
+
+        Ok(users)
+    }
+
+    async fn get_user_profile(&self, user_id: &Uuid) -> Result<UserProfile, DomainError> {
+        let (user, stats) = tokio::try_join!(self.get_user_by_id(user_id), async {
+            let stats = self
+                .repo
+                .message_reader
+                .find_user_stats(user_id, OffsetDateTime::now_utc() - PROFILE_STATS_WINDOW)
+                .await?;
+            Ok(stats)
+        },)?;
+
+        Ok(UserProfile { user, stats })
+    }
+
+    async fn get_user_icon(
+        &self,
+        user_id: &Uuid,
+        size: ImageSize,
+    ) -> Result<(Vec<u8>, String), DomainError> {
+        let (repo, traq_client, id) = (self.repo.clone(), self.traq_client.clone(), *user_id);
+        let fetch = async move {
+            let token = match repo.user.find_random_valid_token().await? {
+                Some(token) => token,
+                None => {
+                    return Err(DomainError::NoTokenForUserIcon);
+                }
+            };
+            let icon = traq_client.get_user_icon(&token, &id).await?;
+            Ok(icon)
+        };
+        self.get_image(ImageKind::UserIcon, user_id, size, fetch)
+            .await
+    }
+
+    async fn prefetch_images(&self, assets: &PageAssets) {
+        // Without a cache, the images would be fetched again when requested
+        if self.image_cache.is_none() || self.prefetching.swap(true, AtomicOrdering::AcqRel) {
+            return;
+        }
+
+        let icons = assets.user_ids.iter().map(|id| (ImageKind::UserIcon, id));
+        let stamps = assets.stamp_ids.iter().map(|id| (ImageKind::Stamp, id));
+        for (kind, id) in icons.chain(stamps).take(MAX_PREFETCHED_IMAGES) {
+            // Clients show icons as thumbnails, and stamps as they are
+            let image = match kind {
+                ImageKind::UserIcon => self.get_user_icon(id, ImageSize::Thumb).await,
+                ImageKind::Stamp => self.get_stamp_image(id, ImageSize::Original).await,
+            };
+            match image {
+                Ok(_) => {}
+                Err(DomainError::TraqClient(TraqClientError::ApiError { status, .. }))
+                    if status == StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    tracing::warn!("Stopped prefetching images as traQ is rate limiting");
+                    break;
+                }
+                Err(e) => tracing::warn!("Failed to prefetch {:?} {}: {:?}", kind, id, e),
+            }
+        }
+        self.prefetching.store(false, AtomicOrdering::Release);
+    }
+
+    async fn get_stamp_by_id(&self, stamp_id: &Uuid) -> Result<Stamp, DomainError> {
+        let stamp = match self.repo.stamp.find_by_id(stamp_id).await? {
+            Some(stamp) => stamp,
+            None => {
+                let token = match self.repo.user.find_random_valid_token().await? {
+                    Some(token) => token,
+                    None => {
+                        return Err(DomainError::NoTokenForStampFetch);
+                    }
+                };
+                let stamp = self.traq_client.get_stamp(&token, stamp_id).await?;
+                self.repo.stamp.save(&stamp).await?;
+                stamp
+            }
+        };
+        Ok(stamp)
+    }
+
+    async fn get_stamp_image(
+        &self,
+        stamp_id: &Uuid,
+        size: ImageSize,
+    ) -> Result<(Vec<u8>, String), DomainError> {
+        let (repo, traq_client, id) = (self.repo.clone(), self.traq_client.clone(), *stamp_id);
+        let fetch = async move {
+            let token = match repo.user.find_random_valid_token().await? {
+                Some(token) => token,
+                None => {
+                    return Err(DomainError::NoTokenForStampImage);
+                }
+            };
+            let image = traq_client.get_stamp_image(&token, &id).await?;
+            Ok(image)
+        };
+        self.get_image(ImageKind::Stamp, stamp_id, size, fetch)
+            .await
+    }
+
+    async fn get_stamps(&self) -> Result<Vec<Stamp>, DomainError> {
+        let token = match self.repo.user.find_random_valid_token().await? {
+            Some(token) => token,
+            None => {
+                return Err(DomainError::NoTokenForStampsList);
+            }
+        };
+        let stamps = self.traq_client.get_stamps(&token).await?;
+        self.repo.stamp.save_batch(&stamps).await?;
+        Ok(stamps)
+    }
+
+    async fn get_stamps_by_ids(
+        &self,
+        stamp_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Stamp>, DomainError> {
+        if stamp_ids.len() > MAX_BATCH_STAMP_IDS {
+            return Err(DomainError::TooManyStampIds(MAX_BATCH_STAMP_IDS));
+        }
+
+        let mut stamps: HashMap<Uuid, Stamp> = self
+            .repo
+            .stamp
+            .find_by_ids(stamp_ids)
+            .await?
+            .into_iter()
+            .map(|stamp| (stamp.id, stamp))
+            .collect();
+
+        if stamp_ids.iter().any(|id| !stamps.contains_key(id)) {
+            // traQ cannot look up stamps by IDs, but listing them all is a single request and
+            // caches every stamp for later lookups
+            for stamp in TraqService::get_stamps(self).await? {
+                if stamp_ids.contains(&stamp.id) {
+                    stamps.entry(stamp.id).or_insert(stamp);
+                }
+            }
+        }
+
+        Ok(stamps)
+    }
+
+    async fn search_stamps(&self, name: &str) -> Result<Vec<Stamp>, DomainError> {
+        let stamps = TraqService::get_stamps(self).await?;
+        let filtered = stamps
+            .into_iter()
+            .filter(|s| s.name.contains(name))
+            .collect();
+        Ok(filtered)
+    }
+
+    async fn get_recent_stamps(&self, user_id: &Uuid) -> Result<Vec<Stamp>, DomainError> {
+        let stamps = self
+            .repo
+            .stamp
+            .find_recently_used_by(user_id, RECENT_STAMPS_LIMIT)
+            .await?;
+        Ok(stamps)
+    }
+
+    async fn get_stamp_suggestions(
+        &self,
+        user_id: &Uuid,
+        message_id: &Uuid,
+    ) -> Result<Vec<Stamp>, DomainError> {
+        let message = self
+            .repo
+            .message_reader
+            .find_by_id(message_id)
+            .await?
+            .ok_or(DomainError::NoMessageForId(*message_id))?;
+        let limit = STAMP_SUGGESTIONS_LIMIT as i64;
+        let (received, recent) = tokio::try_join!(
+            self.repo.stamp.find_frequently_received(
+                &message.user_id,
+                &message.channel_id,
+                OffsetDateTime::now_utc() - STAMP_SUGGESTIONS_WINDOW,
+                limit,
+            ),
+            self.repo.stamp.find_recently_used_by(user_id, limit),
+        )?;
+
+        let recent_ids: HashSet<Uuid> = recent.iter().map(|s| s.id).collect();
+        let mut seen: HashSet<Uuid> = message
+            .reactions
+            .iter()
+            .filter(|r| r.user_id == *user_id)
+            .map(|r| r.stamp_id)
+            .collect();
+        // Stamps both usual for the message and familiar to the user are the likeliest picks
+        let (both, received_only): (Vec<Stamp>, Vec<Stamp>) = received
+            .into_iter()
+            .partition(|s| recent_ids.contains(&s.id));
+        let suggestions = both
+            .into_iter()
+            .chain(received_only)
+            .chain(recent)
+            .filter(|s| seen.insert(s.id))
+            .take(STAMP_SUGGESTIONS_LIMIT)
+            .collect();
+
+        Ok(suggestions)
+    }
+
+    async fn get_channels(&self) -> Result<Vec<Channel>, DomainError> {
+        let channels = self.repo.channel.find_all().await?;
+        Ok(channels)
+    }
+
+    async fn get_channel_by_id(&self, channel_id: &Uuid) -> Result<Channel, DomainError> {
+        self.repo
+            .channel
+            .find_by_id(channel_id)
+            .await?
+            .ok_or(DomainError::NoChannelForId(*channel_id))
+    }
+
+    async fn add_message_stamp(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+        count: i32,
+    ) -> Result<(), DomainError> {
+        let token = match self
+            .repo
+            .user
+            .find_token_by_user_id(user_id.as_uuid())
+            .await?
+        {
+            Some(token) => token,
+            None => {
+                return Err(DomainError::NoTokenForUser(*user_id.as_uuid()));
+            }
+        };
+
+        // 1. Add stamp to traQ
+        self.traq_client
+            .add_message_stamp(&token, message_id, stamp_id, count)
+            .await?;
+
+        // 2. Fetch updated message from traQ (to get latest reactions)
+        let message = self
+            .traq_client
+            .get_message(&token, message_id.as_uuid())
+            .await?;
+
+        // 3. Update local DB
+        self.repo.message_writer.save(&message).await?;
+        self.repo
+            .message_event
+            .append(&[*message_id.as_uuid()], MessageEventKind::Updated)
+            .await?;
+        self.repo.stamp.record_usage(user_id, stamp_id).await?;
+
+        Ok(())
+    }
+
+    async fn remove_message_stamp(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+    ) -> Result<(), DomainError> {
+        let token = match self
+            .repo
+            .user
+            .find_token_by_user_id(user_id.as_uuid())
+            .await?
+        {
+            Some(token) => token,
+            None => {
+                return Err(DomainError::NoTokenForUser(*user_id.as_uuid()));
+            }
+        };
+
+        // 1. Remove stamp from traQ
+        self.traq_client
+            .remove_message_stamp(&token, message_id, stamp_id)
+            .await?;
+
+        // 2. Optimistically update local DB by directly removing the reaction
+        //    traQ does not immediately reflect the removal in subsequent fetches,
+        //    so we directly update the local cache here.
+        self.repo
+            .message_writer
+            .remove_reaction(message_id, stamp_id, user_id)
+            .await?;
+        self.repo
+            .message_event
+            .append(&[*message_id.as_uuid()], MessageEventKind::Updated)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn toggle_message_stamp(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+    ) -> Result<bool, DomainError> {
+        // Messages that aren't cached yet are saved when the stamp is added
+        let reacted =
+            self.repo
+                .message_reader
+                .find_by_id(message_id.as_uuid())
+                .await?
+                .is_some_and(|message| {
+                    message.reactions.iter().any(|r| {
+                        r.stamp_id == *stamp_id.as_uuid() && r.user_id == *user_id.as_uuid()
+                    })
+                });
+
+        if reacted {
+            self.remove_message_stamp(user_id, message_id, stamp_id)
+                .await?;
+        } else {
+            self.add_message_stamp(user_id, message_id, stamp_id, 1)
+                .await?;
+        }
+
+        Ok(!reacted)
+    }
+
+    async fn get_channel_messages(
+        &self,
+        user_id: &Uuid,
+        channel_id: &Uuid,
+        before: Option<String>,
+    ) -> Result<MessagePage, DomainError> {
+        let before = decode_cursor(before)?;
+        let mut messages = self
+            .repo
+            .message_reader
+            .find_by_channel(channel_id, before, CHANNEL_MESSAGES_PAGE_SIZE + 1)
+            .await?;
+
+        // Everything after the oldest cached message has been crawled, so a short page means
+        // that the rest may only be on traQ
+        let missing = CHANNEL_MESSAGES_PAGE_SIZE + 1 - messages.len() as i64;
+        if missing > 0 {
+            let token = match self.repo.user.find_token_by_user_id(user_id).await? {
+                Some(token) => token,
+                None => {
+                    return Err(DomainError::NoTokenForUser(*user_id));
+                }
+            };
+            let until = messages
+                .last()
+                .map(|m| m.created_at)
+                .or(before.map(|c| c.created_at));
+            let fetched = self
+                .traq_client
+                .get_channel_messages(&token, channel_id, until, missing as i32)
+                .await?;
+            if !fetched.is_empty() {
+                self.repo.message_writer.save_batch(&fetched).await?;
+                messages = self
+                    .repo
+                    .message_reader
+                    .find_by_channel(channel_id, before, CHANNEL_MESSAGES_PAGE_SIZE + 1)
+                    .await?;
+            }
+        }
+
+        let next_cursor = truncate_to_page(&mut messages, CHANNEL_MESSAGES_PAGE_SIZE);
+        Ok(MessagePage {
+            messages: summarize_reactions(messages, user_id),
+            next_cursor,
+        })
+    }
+
+    async fn check_message_visibility(
+        &self,
+        user_id: &Uuid,
+        messages: &[MessageListItem],
+    ) -> Result<VisibilityReport, DomainError> {
+        let token = match self.repo.user.find_token_by_user_id(user_id).await? {
+            Some(token) => token,
+            None => {
+                return Err(DomainError::NoTokenForUser(*user_id));
+            }
+        };
+
+        let mut channels: Vec<(Uuid, Vec<Uuid>)> = vec![];
+        let mut channel_indices = HashMap::<Uuid, usize>::new();
+        for message in messages {
+            let index = *channel_indices
+                .entry(message.channel_id)
+                .or_insert_with(|| {
+                    channels.push((message.channel_id, vec![]));
+                    channels.len() - 1
+                });
+            channels[index].1.push(message.id);
+        }
+
+        let mut leaks = vec![];
+        for (channel_id, message_ids) in &channels {
+            let status = match self.traq_client.get_message(&token, &message_ids[0]).await {
+                Ok(_) => continue,
+                Err(TraqClientError::ApiError { status, .. })
+                    if status == StatusCode::FORBIDDEN || status == StatusCode::NOT_FOUND =>
+                {
+                    status
+                }
+                Err(e) => return Err(e.into()),
+            };
+            leaks.extend(message_ids.iter().map(|&message_id| VisibilityLeak {
+                message_id,
+                channel_id: *channel_id,
+                status: status.as_u16(),
+            }));
+        }
+
+        Ok(VisibilityReport {
+            checked_messages: messages.len(),
+            checked_channels: channels.len(),
+            leaks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::RepositoryError,
+        model::{
+            AffinityScore, CachedImage, HiddenMessage, Highlight, IgnoredRecommendations,
+            MessageEmbedding, MessageEvent, UserStats,
+        },
+        repository::{
+            MockAnnouncementRepository, MockBlockRepository, MockBookmarkRepository,
+            MockChannelRepository, MockEmbeddingRepository, MockFeedbackRepository,
+            MockFollowRepository, MockImageCacheRepository, MockMessageEventRepository,
+            MockMessageReader, MockMessageWriter, MockMuteRepository, MockPresenceRepository,
+            MockQuotaRepository, MockReportRepository, MockSavedSearchRepository,
+            MockStampRepository, MockUserRepository, MockUserSettingsRepository,
+            MockWebhookRepository,
+        },
+        search::MockSearchIndex,
+        test_factories::{
+            MessageBuilder, MessageListItemBuilder, ReactionBuilder, RepositoryBuilder,
+            StampBuilder, UserBuilder,
+        },
+        traq_client::MockTraqClient,
+    };
+    use fake::{Fake, uuid::UUIDv4};
+    use mockall::{Sequence, predicate};
+    use time::OffsetDateTime;
+
+    #[tokio::test]
+    async fn bookmark_add_bookmark_success() {
+        let user_id = UUIDv4.fake();
+        let message = MessageBuilder::new().build();
+        let message_id = MessageId::from(message.id);
+
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_bookmark_repo = MockBookmarkRepository::new();
+
+        mock_message_reader
+            .expect_find_by_id()
+            .with(predicate::eq(message.id))
+            .times(1)
+            .returning(move |_| Ok(Some(message.clone())));
+        mock_bookmark_repo
+            .expect_add()
+            .with(predicate::eq(user_id), predicate::eq(message_id))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .bookmark(mock_bookmark_repo)
+            .build();
+        let service = BookmarkServiceImpl::new(repo);
+        let result = service.add_bookmark(&user_id, &message_id).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn bookmark_add_bookmark_unknown_message() {
+        let user_id = UUIDv4.fake();
+        let message_id = UUIDv4.fake();
+
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_bookmark_repo = MockBookmarkRepository::new();
+
+        mock_message_reader
+            .expect_find_by_id()
+            .times(1)
+            .returning(|_| Ok(None));
+        mock_bookmark_repo.expect_add().never();
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .bookmark(mock_bookmark_repo)
+            .build();
+        let service = BookmarkServiceImpl::new(repo);
+        let result = service.add_bookmark(&user_id, &message_id).await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            DomainError::NoMessageForId(*message_id.as_uuid())
+        );
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_success() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![message.clone()];
+
+        // 1. Affinity / Similar users setup
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .with(
+                predicate::eq(message.user_id),
+                predicate::eq(20),
+                predicate::always(),
+            )
+            .returning(|_, _, _| Ok(vec![]));
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .with(
+                predicate::eq(message.user_id),
+                predicate::eq(10),
+                predicate::always(),
+            )
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .with(predicate::eq(message.user_id), predicate::eq(20))
+            .returning(|_, _| Ok(vec![]));
+
+        // 2. Mock setup for remaining fetches
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+
+        // 3. Recommendation fetches
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service
+            .get_recommended_messages(&message.user_id)
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+        assert_eq!(result[0].content, message.content);
+        assert_eq!(result[0].reason, Some(RecommendationReason::Popular));
+    }
+
+    /// A user who picked no channels during onboarding.
+    fn no_channel_settings() -> MockUserSettingsRepository {
+        let mut mock_user_settings_repo = MockUserSettingsRepository::new();
+        mock_user_settings_repo
+            .expect_find_channel_interests()
+            .returning(|_| Ok(vec![]));
+        mock_user_settings_repo
+            .expect_find_channel_score_overrides()
+            .returning(|_| Ok(vec![]));
+        mock_user_settings_repo
+    }
+
+    /// Sets up repositories for a user with `similar_user_count` similar users and no other
+    /// signals or candidates except for `recent`.
+    fn cold_start_repository(
+        similar_user_count: usize,
+        recent: Vec<MessageListItem>,
+        recent_times: usize,
+    ) -> Repository {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(move |_, _| Ok((0..similar_user_count).map(|_| UUIDv4.fake()).collect()));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(|_, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .with(
+                predicate::always(),
+                predicate::eq(COLD_START_MESSAGES_PER_CHANNEL),
+                predicate::eq(50),
+            )
+            .times(recent_times)
+            .returning(move |_, _, _| Ok(recent.clone()));
+
+        RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_cold_start() {
+        let user_id = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().build();
+
+        let repo = cold_start_repository(0, vec![message.clone()], 1);
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+        assert_eq!(result[0].reason, Some(RecommendationReason::Recent));
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_skips_cold_start_with_enough_signal() {
+        let user_id = UUIDv4.fake();
+
+        let repo = cold_start_repository(COLD_START_MIN_SIGNALS, vec![], 0);
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn timeline_find_similar_content_messages_orders_by_similarity() {
+        let user_id = UUIDv4.fake();
+        let similar = MessageListItemBuilder::new().build();
+        let unrelated = MessageListItemBuilder::new().build();
+        let (similar_id, unrelated_id) = (similar.id, unrelated.id);
+
+        let mut mock_embedding_repo = MockEmbeddingRepository::new();
+        mock_embedding_repo
+            .expect_find_stamped_message_embeddings()
+            .with(
+                predicate::eq(user_id),
+                predicate::eq(SIMILAR_CONTENT_SEED_LIMIT),
+            )
+            .returning(|_, _| {
+                Ok(vec![
+                    MessageEmbedding {
+                        message_id: UUIDv4.fake(),
+                        embedding: vec![1.0, 0.0],
+                    },
+                    MessageEmbedding {
+                        message_id: UUIDv4.fake(),
+                        embedding: vec![0.8, 0.2],
+                    },
+                ])
+            });
+        mock_embedding_repo
+            .expect_find_candidate_embeddings()
+            .returning(move |_, _| {
+                Ok(vec![
+                    MessageEmbedding {
+                        message_id: unrelated_id,
+                        embedding: vec![0.0, 1.0],
+                    },
+                    MessageEmbedding {
+                        message_id: similar_id,
+                        embedding: vec![0.9, 0.1],
+                    },
+                ])
+            });
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_list_items_by_ids()
+            .with(predicate::eq(vec![similar_id, unrelated_id]))
+            .returning(move |_| Ok(vec![unrelated.clone(), similar.clone()]));
+
+        let repo = RepositoryBuilder::new()
+            .embedding(mock_embedding_repo)
+            .message_reader(mock_message_reader)
+            .build();
+        let service = TimelineServiceImpl::new(repo).with_similar_content();
+        let result = service
+            .find_similar_content_messages(&user_id)
+            .await
+            .unwrap();
+
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![similar_id, unrelated_id]);
+    }
+
+    #[tokio::test]
+    async fn timeline_find_similar_content_messages_is_disabled_by_default() {
+        let service = TimelineServiceImpl::new(RepositoryBuilder::new().build());
+        let result = service
+            .find_similar_content_messages(&UUIDv4.fake())
+            .await
+            .unwrap();
+
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_seeds_channel_affinity_with_interests() {
+        let user_id = UUIDv4.fake();
+        let channel_id: Uuid = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().channel_id(channel_id).build();
+        let messages = vec![message.clone()];
+
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        let mut mock_user_settings_repo = MockUserSettingsRepository::new();
+
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_settings_repo
+            .expect_find_channel_interests()
+            .with(predicate::eq(user_id))
+            .times(1)
+            .returning(move |_| Ok(vec![channel_id]));
+        mock_user_settings_repo
+            .expect_find_channel_score_overrides()
+            .returning(|_| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .with(
+                predicate::eq(vec![channel_id]),
+                predicate::eq(50),
+                predicate::eq(user_id),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(messages.clone()));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(|_, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(mock_user_settings_repo)
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+        assert_eq!(
+            result[0].reason,
+            Some(RecommendationReason::FrequentlyStampedChannel)
+        );
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_empty() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+
+        let user_id = UUIDv4.fake();
+
+        // Mocks returning empty/defaults
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(|_, _| Ok(vec![]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_for_users_filters_shared_candidates() {
+        let user_a: Uuid = UUIDv4.fake();
+        let user_b: Uuid = UUIDv4.fake();
+        let by_a = MessageListItemBuilder::new().user_id(user_a).build();
+        let by_other = MessageListItemBuilder::new().build();
+        let popular = vec![by_a.clone(), by_other.clone()];
+        let by_other_id = by_other.id;
+
+        let mut mock_mute_repo = MockMuteRepository::new();
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .times(2)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .times(2)
+            .returning(|_, _| Ok(vec![]));
+        let mut mock_stamp_repo = MockStampRepository::new();
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+
+        // Candidates are fetched once, and read messages are filtered for each user
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .with(predicate::eq(NO_VIEWER), predicate::always())
+            .times(1)
+            .returning(move |_, _| Ok(popular.clone()));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .times(1)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .times(1)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .times(1)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_read_message_ids_by_users()
+            .times(1)
+            .returning(move |_, _| Ok(vec![(user_b, by_other_id)]));
+        let mut mock_user_settings_repo = no_channel_settings();
+        mock_user_settings_repo
+            .expect_find_acquaintance_only_user_ids()
+            .returning(|_| Ok(vec![]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(mock_user_settings_repo)
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service
+            .get_recommended_messages_for_users(&[user_a, user_b])
+            .await
+            .unwrap();
+
+        let ids = |user_id| result[&user_id].iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(ids(user_a), vec![by_other.id]);
+        assert_eq!(ids(user_b), vec![by_a.id]);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_for_users_keeps_acquaintance_only_authors() {
+        let user_a: Uuid = UUIDv4.fake();
+        let user_b: Uuid = UUIDv4.fake();
+        let author: Uuid = UUIDv4.fake();
+        let by_author = MessageListItemBuilder::new().user_id(author).build();
+        let popular = vec![by_author.clone()];
+
+        let mut mock_mute_repo = MockMuteRepository::new();
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+        let mut mock_stamp_repo = MockStampRepository::new();
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(move |_, _| Ok(popular.clone()));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_read_message_ids_by_users()
+            .returning(|_, _| Ok(vec![]));
+
+        // The author recommends their messages only to acquaintances, and only user A is one
+        let mut mock_user_settings_repo = no_channel_settings();
+        mock_user_settings_repo
+            .expect_find_acquaintance_only_user_ids()
+            .with(predicate::eq(vec![author]))
+            .returning(move |_| Ok(vec![author]));
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_acquaintances()
+            .times(1)
+            .returning(move |_, _| Ok(vec![(user_a, author)]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(mock_user_settings_repo)
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service
+            .get_recommended_messages_for_users(&[user_a, user_b])
+            .await
+            .unwrap();
+
+        let ids = |user_id| result[&user_id].iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(ids(user_a), vec![by_author.id]);
+        assert!(ids(user_b).is_empty());
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_error() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+
+        let user_id = UUIDv4.fake();
+
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(|_, _| Err(RepositoryError::Database("database error".to_string())));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await;
+
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), DomainError::Repository(_)));
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_excludes_muted_users() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+
+        let user_id = UUIDv4.fake();
+        let muted_user_id: Uuid = UUIDv4.fake();
+        let muted_message = MessageListItemBuilder::new().user_id(muted_user_id).build();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![muted_message, message.clone()];
+
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .with(predicate::eq(user_id))
+            .returning(move |_| Ok(vec![muted_user_id]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(move |_, _, _| {
+                Ok(vec![AffinityScore {
+                    id: muted_user_id,
+                    score: 1.0,
+                }])
+            });
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        // Muted users must not be passed as author allowlists
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .withf(move |author_ids, _, _| !author_ids.contains(&muted_user_id))
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_excludes_muted_channels() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+
+        let user_id = UUIDv4.fake();
+        let muted_channel_id: Uuid = UUIDv4.fake();
+        let muted_message = MessageListItemBuilder::new()
+            .channel_id(muted_channel_id)
+            .build();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![muted_message, message.clone()];
+
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .with(predicate::eq(user_id))
+            .returning(move |_| Ok(vec![muted_channel_id]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(move |_, _, _| {
+                Ok(vec![AffinityScore {
+                    id: muted_channel_id,
+                    score: 1.0,
+                }])
+            });
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        // Muted channels must not be passed as channel allowlists
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .withf(move |channel_ids, _, _| !channel_ids.contains(&muted_channel_id))
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn timeline_mute_user_rejects_self() {
+        let user_id = UUIDv4.fake();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        mock_mute_repo.expect_mute_user().never();
+
+        let repo = RepositoryBuilder::new().mute(mock_mute_repo).build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.mute_user(&user_id, &user_id).await;
+
+        assert_eq!(result.unwrap_err(), DomainError::CannotMuteSelf);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_excludes_blocked_users() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+
+        let user_id = UUIDv4.fake();
+        let blocked_user_id: Uuid = UUIDv4.fake();
+        let blocked_message = MessageListItemBuilder::new()
+            .user_id(blocked_user_id)
+            .build();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![blocked_message, message.clone()];
+
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .with(predicate::eq(user_id))
+            .returning(move |_| Ok(vec![blocked_user_id]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(move |_, _, _| {
+                Ok(vec![AffinityScore {
+                    id: blocked_user_id,
+                    score: 1.0,
+                }])
+            });
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        // Blocked users must not be passed as author allowlists
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .withf(move |author_ids, _, _| !author_ids.contains(&blocked_user_id))
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_applies_hide_feedback() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+
+        let user_id = UUIDv4.fake();
+        let hidden_message = MessageListItemBuilder::new().build();
+        // Posted by the author of the hidden message, ranked first before downranking
+        let downranked_message = MessageListItemBuilder::new()
+            .user_id(hidden_message.user_id)
+            .build();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![
+            hidden_message.clone(),
+            downranked_message.clone(),
+            message.clone(),
+        ];
+        let hidden = HiddenMessage {
+            message_id: hidden_message.id,
+            author_id: hidden_message.user_id,
+            channel_id: hidden_message.channel_id,
+        };
+
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .with(predicate::eq(user_id))
+            .returning(move |_| Ok(vec![hidden.clone()]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, message.id);
+        assert_eq!(result[1].id, downranked_message.id);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_recommended_messages_downranks_ignored_sources() {
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_mute_repo = MockMuteRepository::new();
+        let mut mock_block_repo = MockBlockRepository::new();
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+
+        let user_id = UUIDv4.fake();
+        // Posted by an author whose recommendations the user keeps skipping
+        let downranked_message = MessageListItemBuilder::new().build();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![downranked_message.clone(), message.clone()];
+        let ignored = IgnoredRecommendations {
+            author_id: downranked_message.user_id,
+            channel_id: UUIDv4.fake(),
+            count: 10,
+        };
+
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
+        mock_feedback_repo
+            .expect_find_ignored_recommendations()
+            .withf(move |uid, since, until| *uid == user_id && since < until)
+            .returning(move |_, _, _| Ok(vec![ignored.clone()]));
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_user_repo
+            .expect_find_similar_users()
+            .returning(|_, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_author_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_messages_by_channel_allowlist()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_recent_messages_across_channels()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_message_reader
+            .expect_find_top_reacted_messages()
+            .returning(move |_, _| Ok(messages.clone()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .user_settings(no_channel_settings())
+            .mute(mock_mute_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_recommended_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, message.id);
+        assert_eq!(result[1].id, downranked_message.id);
+    }
+
+    #[tokio::test]
+    async fn timeline_set_channel_score_override() {
+        let user_id: Uuid = UUIDv4.fake();
+        let channel_id: Uuid = UUIDv4.fake();
+        let expected = ChannelScoreOverride {
+            channel_id,
+            multiplier: 2.0,
+        };
+
+        let mut mock_user_settings_repo = MockUserSettingsRepository::new();
+        mock_user_settings_repo
+            .expect_find_channel_score_overrides()
+            .returning(|_| Ok(vec![]));
+        mock_user_settings_repo
+            .expect_save_channel_score_override()
+            .withf(move |uid, o| *uid == user_id && *o == expected)
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let repo = RepositoryBuilder::new()
+            .user_settings(mock_user_settings_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service
+            .set_channel_score_override(&user_id.into(), &channel_id.into(), 2.0)
+            .await
+            .unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn timeline_set_channel_score_override_rejects_invalid_multipliers() {
+        let user_id = UUIDv4.fake();
+        let channel_id = UUIDv4.fake();
+        let service = TimelineServiceImpl::new(RepositoryBuilder::new().build());
+
+        for multiplier in [0.0, 0.05, 11.0, f64::NAN] {
+            let result = service
+                .set_channel_score_override(&user_id, &channel_id, multiplier)
+                .await;
+            assert_eq!(
+                result.unwrap_err(),
+                DomainError::InvalidChannelScoreMultiplier
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn timeline_set_channel_score_override_rejects_too_many() {
+        let user_id = UUIDv4.fake();
+        let existing: Vec<ChannelScoreOverride> = (0..MAX_CHANNEL_SCORE_OVERRIDES)
+            .map(|_| ChannelScoreOverride {
+                channel_id: UUIDv4.fake(),
+                multiplier: 0.5,
+            })
+            .collect();
+        let existing_channel_id = existing[0].channel_id;
+
+        let mut mock_user_settings_repo = MockUserSettingsRepository::new();
+        mock_user_settings_repo
+            .expect_find_channel_score_overrides()
+            .returning(move |_| Ok(existing.clone()));
+        // Only the existing override can be replaced
+        mock_user_settings_repo
+            .expect_save_channel_score_override()
+            .withf(move |_, o| o.channel_id == existing_channel_id)
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let repo = RepositoryBuilder::new()
+            .user_settings(mock_user_settings_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service
+            .set_channel_score_override(&user_id, &UUIDv4.fake(), 2.0)
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            DomainError::TooManyChannelScoreOverrides(MAX_CHANNEL_SCORE_OVERRIDES)
+        );
+
+        let result = service
+            .set_channel_score_override(&user_id, &existing_channel_id.into(), 2.0)
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn timeline_hide_message() {
+        let user_id: Uuid = UUIDv4.fake();
+        let message = MessageBuilder::new().build();
+        let message_id = message.id;
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_by_id()
+            .with(predicate::eq(message_id))
+            .returning(move |_| Ok(Some(message.clone())));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_hide_message()
+            .withf(move |uid, m| *uid == user_id && m.id == message_id)
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+
+        assert!(
+            service
+                .hide_message(&user_id.into(), &message_id.into())
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn timeline_hide_unknown_message() {
+        let user_id = UUIDv4.fake();
+        let message_id: Uuid = UUIDv4.fake();
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_by_id()
+            .returning(|_| Ok(None));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo.expect_hide_message().never();
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .feedback(mock_feedback_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.hide_message(&user_id, &message_id.into()).await;
+
+        assert_eq!(result.unwrap_err(), DomainError::NoMessageForId(message_id));
+    }
+
+    #[tokio::test]
+    async fn timeline_block_user_rejects_self() {
+        let user_id = UUIDv4.fake();
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo.expect_block_user().never();
+
+        let repo = RepositoryBuilder::new().block(mock_block_repo).build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.block_user(&user_id, &user_id).await;
+
+        assert_eq!(result.unwrap_err(), DomainError::CannotBlockSelf);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_following_messages() {
+        let user_id = UUIDv4.fake();
+        let message = MessageListItemBuilder::new()
+            .reactions(vec![
+                ReactionBuilder::new().user_id(user_id).build(),
+                ReactionBuilder::new().build(),
+            ])
+            .build();
+        let message_clone = message.clone();
+
+        let mut mock_follow_repo = MockFollowRepository::new();
+        mock_follow_repo
+            .expect_find_followed_messages()
+            .with(
+                predicate::eq(user_id),
+                predicate::eq(FOLLOWING_TIMELINE_LIMIT),
+            )
+            .times(1)
+            .returning(move |_, _| Ok(vec![message_clone.clone()]));
+
+        let repo = RepositoryBuilder::new().follow(mock_follow_repo).build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.get_following_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+        let reacted_by_me: Vec<bool> = result[0]
+            .reaction_summaries
+            .iter()
+            .map(|s| s.reacted_by_me)
+            .collect();
+        assert_eq!(reacted_by_me, vec![true, false]);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_following_messages_hides_reported_messages() {
+        let user_id = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().build();
+        let reported = MessageListItemBuilder::new().build();
+        let reported_id = reported.id;
+        let messages = vec![message.clone(), reported];
+
+        let mut mock_follow_repo = MockFollowRepository::new();
+        mock_follow_repo
+            .expect_find_followed_messages()
+            .times(1)
+            .returning(move |_, _| Ok(messages.clone()));
+        let mut mock_report_repo = MockReportRepository::new();
+        mock_report_repo
+            .expect_find_message_ids_reported_at_least()
+            .with(predicate::eq(3))
+            .times(1)
+            .returning(move |_| Ok(vec![reported_id]));
+
+        let repo = RepositoryBuilder::new()
+            .follow(mock_follow_repo)
+            .report(mock_report_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo).with_report_hide_threshold(3);
+        let result = service.get_following_messages(&user_id).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn onboarding_complete_onboarding_step() {
+        let user_id = UUIDv4.fake();
+        let state = OnboardingState {
+            privacy_notice_accepted: true,
+            ..Default::default()
+        };
+
+        let mut mock_user_settings_repo = MockUserSettingsRepository::new();
+        mock_user_settings_repo
+            .expect_complete_onboarding_step()
+            .with(
+                predicate::eq(user_id),
+                predicate::eq(OnboardingStep::PrivacyNotice),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mock_user_settings_repo
+            .expect_find_onboarding_state()
+            .with(predicate::eq(user_id))
+            .times(1)
+            .returning(move |_| Ok(state));
+
+        let repo = RepositoryBuilder::new()
+            .user_settings(mock_user_settings_repo)
+            .build();
+        let service = OnboardingServiceImpl::new(repo);
+        let result = service
+            .complete_onboarding_step(&user_id, OnboardingStep::PrivacyNotice)
+            .await
+            .unwrap();
+
+        assert_eq!(result, state);
+    }
+
+    #[tokio::test]
+    async fn onboarding_save_channel_interests_rejects_too_many() {
+        let user_id = UUIDv4.fake();
+        let channel_ids: Vec<ChannelId> =
+            (0..=MAX_CHANNEL_INTERESTS).map(|_| UUIDv4.fake()).collect();
+
+        let mut mock_user_settings_repo = MockUserSettingsRepository::new();
+        mock_user_settings_repo
+            .expect_save_channel_interests()
+            .never();
+
+        let repo = RepositoryBuilder::new()
+            .user_settings(mock_user_settings_repo)
+            .build();
+        let service = OnboardingServiceImpl::new(repo);
+        let result = service.save_channel_interests(&user_id, &channel_ids).await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            DomainError::TooManyChannelInterests(MAX_CHANNEL_INTERESTS)
+        );
+    }
+
+    #[tokio::test]
+    async fn report_report_message_unknown_message() {
+        let user_id = UUIDv4.fake();
+        let message_id = UUIDv4.fake();
+
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_report_repo = MockReportRepository::new();
+
+        mock_message_reader
+            .expect_find_by_id()
+            .times(1)
+            .returning(|_| Ok(None));
+        mock_report_repo.expect_add().never();
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .report(mock_report_repo)
+            .build();
+        let service = ReportServiceImpl::new(repo);
+        let result = service
+            .report_message(&user_id, &message_id, ReportReason::Spam)
+            .await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            DomainError::NoMessageForId(*message_id.as_uuid())
+        );
+    }
+
+    #[tokio::test]
+    async fn report_get_unresolved_reports_attaches_messages() {
+        let message = MessageListItemBuilder::new().build();
+        let message_id = message.id;
+        let deleted_id: Uuid = UUIDv4.fake();
+        let reports: Vec<ReportedMessage> = [message_id, deleted_id]
+            .into_iter()
+            .map(|id| ReportedMessage {
+                message_id: id,
+                message: None,
+                report_count: 1,
+                reasons: vec![ReportReason::Spam],
+                last_reported_at: OffsetDateTime::now_utc(),
+            })
+            .collect();
+
+        let mut mock_report_repo = MockReportRepository::new();
+        mock_report_repo
+            .expect_find_unresolved()
+            .with(predicate::eq(20))
+            .times(1)
+            .returning(move |_| Ok(reports.clone()));
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_list_items_by_ids()
+            .with(predicate::eq(vec![message_id, deleted_id]))
+            .times(1)
+            .returning(move |_| Ok(vec![message.clone()]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .report(mock_report_repo)
+            .build();
+        let service = ReportServiceImpl::new(repo);
+        let result = service.get_unresolved_reports(20).await.unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].message.as_ref().unwrap().id, message_id);
+        assert!(result[1].message.is_none());
+    }
+
+    #[tokio::test]
+    async fn webhook_register_webhook_deduplicates_events() {
+        let admin_id: Uuid = UUIDv4.fake();
+        let secret = "s".repeat(MIN_WEBHOOK_SECRET_LEN);
+        let webhook = Webhook {
+            id: 1,
+            url: "https://hooks.example.com".to_string(),
+            events: vec![WebhookEvent::ReactionsChanged],
+            created_by: admin_id,
+            created_at: OffsetDateTime::now_utc(),
+        };
+        let webhook_clone = webhook.clone();
+
+        let mut mock_webhook_repo = MockWebhookRepository::new();
+        mock_webhook_repo
+            .expect_find_all()
+            .times(1)
+            .returning(|| Ok(vec![]));
+        mock_webhook_repo
+            .expect_create()
+            .with(
+                predicate::eq("https://hooks.example.com"),
+                predicate::eq(secret.clone()),
+                predicate::eq(vec![WebhookEvent::ReactionsChanged]),
+                predicate::eq(admin_id),
+            )
+            .times(1)
+            .returning(move |_, _, _, _| Ok(webhook_clone.clone()));
+
+        let repo = RepositoryBuilder::new().webhook(mock_webhook_repo).build();
+        let service = WebhookServiceImpl::new(repo);
+        let result = service
+            .register_webhook(
+                &admin_id,
+                " https://hooks.example.com ",
+                &secret,
+                &[
+                    WebhookEvent::ReactionsChanged,
+                    WebhookEvent::ReactionsChanged,
+                ],
+            )
+            .await;
+
+        assert_eq!(result, Ok(webhook));
+    }
+
+    #[tokio::test]
+    async fn webhook_register_webhook_rejects_invalid_webhooks() {
+        let admin_id: Uuid = UUIDv4.fake();
+        let secret = "s".repeat(MIN_WEBHOOK_SECRET_LEN);
+        let events = [WebhookEvent::MessageEdited];
+
+        // Nothing is stored
+        let service = WebhookServiceImpl::new(RepositoryBuilder::new().build());
+        for (url, secret, events) in [
+            ("ftp://hooks.example.com", secret.as_str(), &events[..]),
+            ("https://hooks.example.com", "short", &events[..]),
+            ("https://hooks.example.com", secret.as_str(), &[][..]),
+        ] {
+            let result = service
+                .register_webhook(&admin_id, url, secret, events)
+                .await;
+            assert!(matches!(result, Err(DomainError::InvalidWebhook(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn webhook_delete_webhook_not_found() {
+        let mut mock_webhook_repo = MockWebhookRepository::new();
+        mock_webhook_repo
+            .expect_delete()
+            .with(predicate::eq(1))
+            .times(1)
+            .returning(|_| Ok(false));
+
+        let repo = RepositoryBuilder::new().webhook(mock_webhook_repo).build();
+        let service = WebhookServiceImpl::new(repo);
+
+        assert_eq!(
+            service.delete_webhook(1).await,
+            Err(DomainError::NoWebhookForId(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn presence_get_active_users_counts_online_and_daily_users() {
+        let mut mock_presence_repo = MockPresenceRepository::new();
+        mock_presence_repo
+            .expect_count_users_seen_since()
+            .times(2)
+            .returning(|since| {
+                // Online users are the ones seen in the past few minutes
+                if OffsetDateTime::now_utc() - since < Duration::hours(1) {
+                    Ok(3)
+                } else {
+                    Ok(42)
+                }
+            });
+
+        let repo = RepositoryBuilder::new()
+            .presence(mock_presence_repo)
+            .build();
+        let service = PresenceServiceImpl::new(repo);
+
+        assert_eq!(
+            service.get_active_users().await,
+            Ok(ActiveUsers {
+                online: 3,
+                daily: 42
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn federation_get_highlights_only_when_published() {
+        let highlight = Highlight {
+            reaction_count: 12,
+            stamp_count: 3,
+            created_at: OffsetDateTime::now_utc(),
+            content: None,
+        };
+        let mut mock_message_reader = MockMessageReader::new();
+        let returned = highlight.clone();
+        mock_message_reader
+            .expect_find_highlights()
+            .with(predicate::always(), predicate::eq(HIGHLIGHTS_LIMIT))
+            .times(1)
+            .returning(move |_, _| Ok(vec![returned.clone()]));
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .build();
+
+        let unpublished = FederationServiceImpl::new(repo.clone(), "Twittra".to_string());
+        assert_eq!(
+            unpublished.get_highlights().await,
+            Err(DomainError::HighlightsNotPublished)
+        );
+
+        let published = unpublished.publishing();
+        let highlights = published.get_highlights().await.unwrap();
+        assert_eq!(highlights.version, HIGHLIGHTS_VERSION);
+        assert_eq!(highlights.instance, "Twittra");
+        assert_eq!(highlights.highlights, vec![highlight]);
+    }
+
+    #[tokio::test]
+    async fn quota_consume_fails_once_the_quota_is_used_up() {
+        let user_id: Uuid = UUIDv4.fake();
+        let mut mock_quota_repo = MockQuotaRepository::new();
+        let mut seq = Sequence::new();
+        mock_quota_repo
+            .expect_try_use()
+            .with(
+                predicate::eq(user_id),
+                predicate::eq(QuotaOperation::Search),
+                predicate::always(),
+                predicate::eq(10),
+            )
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _, _, _| Ok(true));
+        mock_quota_repo
+            .expect_try_use()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _, _, _| Ok(false));
+
+        let repo = RepositoryBuilder::new().quota(mock_quota_repo).build();
+        let service = QuotaServiceImpl::new(repo).with_limit(QuotaOperation::Search, 10);
+
+        assert_eq!(
+            service.consume(&user_id, QuotaOperation::Search).await,
+            Ok(())
+        );
+        let Err(DomainError::QuotaExceeded(usage)) =
+            service.consume(&user_id, QuotaOperation::Search).await
+        else {
+            panic!("the quota should be used up");
+        };
+        assert_eq!((usage.used, usage.limit), (10, 10));
+        assert_eq!(usage.resets_at.time(), time::Time::MIDNIGHT);
+        assert!(usage.resets_at > OffsetDateTime::now_utc());
+    }
+
+    #[tokio::test]
+    async fn quota_operations_without_a_limit_are_free() {
+        // The repository is not even asked
+        let service = QuotaServiceImpl::new(RepositoryBuilder::new().build());
+
+        assert_eq!(
+            service
+                .consume(&UUIDv4.fake(), QuotaOperation::Search)
+                .await,
+            Ok(())
+        );
+        assert_eq!(service.get_quotas(&UUIDv4.fake()).await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn timeline_get_explore_messages_excludes_blocked_users() {
+        let user_id = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().build();
+        let blocked = MessageListItemBuilder::new().build();
+        let blocked_user_id = blocked.user_id;
+        let messages = vec![message.clone(), blocked];
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_trending_messages()
+            .with(
+                predicate::eq(user_id),
+                predicate::eq(72),
+                predicate::eq(EXPLORE_LIMIT),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(messages.clone()));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(move |_| Ok(vec![blocked_user_id]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service
+            .get_explore_messages(&user_id, TrendingWindow::ThreeDays)
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_tag_messages_normalizes_the_tag() {
+        let user_id = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![message.clone()];
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_by_tag()
+            .with(
+                predicate::eq("rust"),
+                predicate::eq(TAG_MESSAGES_LIMIT),
+                predicate::eq(user_id),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(messages.clone()));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+
+        let result = service.get_tag_messages(&user_id, "#Rust").await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+
+        let result = service.get_tag_messages(&user_id, "not a tag").await;
+        assert_eq!(result.unwrap_err(), DomainError::InvalidTag);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_user_messages_paginates() {
+        let user_id = UUIDv4.fake();
+        let author_id = UUIDv4.fake();
+        let messages: Vec<MessageListItem> = (0..=USER_MESSAGES_PAGE_SIZE)
+            .map(|_| MessageListItemBuilder::new().user_id(author_id).build())
+            .collect();
+        let last_in_page = MessageCursor::from(&messages[USER_MESSAGES_PAGE_SIZE as usize - 1]);
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_by_author()
+            .with(
+                predicate::eq(author_id),
+                predicate::eq(None),
+                predicate::eq(USER_MESSAGES_PAGE_SIZE + 1),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(messages.clone()));
+        mock_message_reader
+            .expect_find_by_author()
+            .with(
+                predicate::eq(author_id),
+                predicate::eq(Some(last_in_page)),
+                predicate::eq(USER_MESSAGES_PAGE_SIZE + 1),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(vec![]));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+
+        let page = service
+            .get_user_messages(&user_id, &author_id, None)
+            .await
+            .unwrap();
+        assert_eq!(page.messages.len(), USER_MESSAGES_PAGE_SIZE as usize);
+        assert_eq!(page.next_cursor, Some(last_in_page.encode()));
+
+        let page = service
+            .get_user_messages(&user_id, &author_id, page.next_cursor)
+            .await
+            .unwrap();
+        assert!(page.messages.is_empty());
+        assert_eq!(page.next_cursor, None);
+
+        let result = service
+            .get_user_messages(&user_id, &author_id, Some("invalid".to_string()))
+            .await;
+        assert_eq!(result.unwrap_err(), DomainError::InvalidCursor);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_public_timeline_only_of_public_channels() {
+        let channel_id: Uuid = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().channel_id(channel_id).build();
+        let expected_id = message.id;
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_public_messages()
+            .with(
+                predicate::eq(vec![channel_id]),
+                predicate::eq(None),
+                predicate::eq(PUBLIC_TIMELINE_PAGE_SIZE + 1),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(vec![message.clone()]));
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .build();
+
+        let disabled = TimelineServiceImpl::new(repo.clone());
+        assert_eq!(
+            disabled.get_public_timeline(None).await.unwrap_err(),
+            DomainError::PublicTimelineDisabled
+        );
+
+        let service = TimelineServiceImpl::new(repo).with_public_channels(vec![channel_id]);
+        let page = service.get_public_timeline(None).await.unwrap();
+        assert_eq!(page.messages.len(), 1);
+        assert_eq!(page.messages[0].id, expected_id);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_affinity_uses_the_half_life() {
+        let user_id = UUIDv4.fake();
+        let author = AffinityScore {
+            id: UUIDv4.fake(),
+            score: 2.5,
+        };
+        let channel = AffinityScore {
+            id: UUIDv4.fake(),
+            score: 0.75,
+        };
+
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_frequently_stamped_users_by()
+            .with(
+                predicate::eq(user_id),
+                predicate::eq(AFFINITY_USERS_LIMIT),
+                predicate::eq(7.0),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(vec![author]));
+        let mut mock_stamp_repo = MockStampRepository::new();
+        mock_stamp_repo
+            .expect_find_frequently_stamped_channels_by()
+            .with(
+                predicate::eq(user_id),
+                predicate::eq(AFFINITY_CHANNELS_LIMIT),
+                predicate::eq(7.0),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(vec![channel]));
+
+        let repo = RepositoryBuilder::new()
+            .user(mock_user_repo)
+            .stamp(mock_stamp_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo).with_affinity_half_life_days(7.0);
+
+        let result = service.get_affinity(&user_id).await.unwrap();
+        assert_eq!(
+            result,
+            Affinity {
+                authors: vec![author],
+                channels: vec![channel],
             }
-        };
-        let stamps = self.traq_client.get_stamps(&token).await?;
-        self.repo.stamp.save_batch(&stamps).await?;
-        Ok(stamps)
+        );
     }
 
-    async fn search_stamps(&self, name: &str) -> Result<Vec<Stamp>, DomainError> {
-        let stamps = TraqService::get_stamps(self).await?;
-        let filtered = stamps
-            .into_iter()
-            .filter(|s| s.name.contains(name))
-            .collect();
-        Ok(filtered)
+    #[tokio::test]
+    async fn timeline_get_related_messages_excludes_blocked_users() {
+        let user_id = UUIDv4.fake();
+        let message_id = UUIDv4.fake();
+        let related = MessageListItemBuilder::new().build();
+        let blocked = MessageListItemBuilder::new().build();
+        let blocked_user_id = blocked.user_id;
+        let messages = vec![related.clone(), blocked];
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_co_reacted_messages()
+            .with(
+                predicate::eq(message_id),
+                predicate::eq(RELATED_MESSAGES_LIMIT),
+                predicate::eq(user_id),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(messages.clone()));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(move |_| Ok(vec![blocked_user_id]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+
+        let result = service
+            .get_related_messages(&user_id, &message_id)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, related.id);
     }
 
-    async fn add_message_stamp(
-        &self,
-        user_id: &Uuid,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
-        count: i32,
-    ) -> Result<(), DomainError> {
-        let token = match self.repo.user.find_token_by_user_id(user_id).await? {
-            Some(token) => token,
-            None => {
-                return Err(DomainError::NoTokenForUser(*user_id));
-            }
-        };
+    #[tokio::test]
+    async fn timeline_get_thread_excludes_blocked_users() {
+        let user_id = UUIDv4.fake();
+        let root = MessageListItemBuilder::new().build();
+        let reply = MessageListItemBuilder::new()
+            .content(format!("https://q.trap.jp/messages/{}", root.id))
+            .build();
+        let blocked = MessageListItemBuilder::new()
+            .content(format!("https://q.trap.jp/messages/{}", root.id))
+            .build();
+        let blocked_user_id = blocked.user_id;
+        let messages = vec![root.clone(), reply.clone(), blocked];
 
-        // 1. Add stamp to traQ
-        self.traq_client
-            .add_message_stamp(&token, message_id, stamp_id, count)
-            .await?;
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_thread()
+            .with(
+                predicate::eq(reply.id),
+                predicate::eq(THREAD_MESSAGES_LIMIT),
+            )
+            .times(1)
+            .returning(move |_, _| Ok(messages.clone()));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(move |_| Ok(vec![blocked_user_id]));
 
-        // 2. Fetch updated message from traQ (to get latest reactions)
-        let message = self.traq_client.get_message(&token, message_id).await?;
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
 
-        // 3. Update local DB
-        self.repo.message.save(&message).await?;
+        let result = service.get_thread(&user_id, &reply.id).await.unwrap();
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![root.id, reply.id]);
+        assert_eq!(result[1].reply_to_message_id, Some(root.id));
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn timeline_get_thread_of_unknown_message() {
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_thread()
+            .returning(|_, _| Ok(vec![]));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+
+        let result = service.get_thread(&UUIDv4.fake(), &UUIDv4.fake()).await;
+        assert!(matches!(result, Err(DomainError::NoMessageForId(_))));
     }
 
-    async fn remove_message_stamp(
-        &self,
-        user_id: &Uuid,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
-    ) -> Result<(), DomainError> {
-        let token = match self.repo.user.find_token_by_user_id(user_id).await? {
-            Some(token) => token,
-            None => {
-                return Err(DomainError::NoTokenForUser(*user_id));
-            }
-        };
+    #[tokio::test]
+    async fn timeline_search_messages_keeps_index_order() {
+        let user_id = UUIDv4.fake();
+        let best = MessageListItemBuilder::new().build();
+        let second = MessageListItemBuilder::new().build();
+        let blocked = MessageListItemBuilder::new().build();
+        let blocked_user_id = blocked.user_id;
+        let ids = vec![best.id, second.id, blocked.id];
+        let messages = vec![second.clone(), blocked, best.clone()];
 
-        // 1. Remove stamp from traQ
-        self.traq_client
-            .remove_message_stamp(&token, message_id, stamp_id)
-            .await?;
+        let mut mock_search_index = MockSearchIndex::new();
+        let ids_clone = ids.clone();
+        mock_search_index
+            .expect_search_messages()
+            .with(predicate::eq("rust"), predicate::eq(SEARCH_LIMIT))
+            .times(1)
+            .returning(move |_, _| Ok(ids_clone.clone()));
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_list_items_by_ids()
+            .with(predicate::eq(ids))
+            .times(1)
+            .returning(move |_| Ok(messages.clone()));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(move |_| Ok(vec![blocked_user_id]));
 
-        // 2. Optimistically update local DB by directly removing the reaction
-        //    traQ does not immediately reflect the removal in subsequent fetches,
-        //    so we directly update the local cache here.
-        self.repo
-            .message
-            .remove_reaction(message_id, stamp_id, user_id)
-            .await?;
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo).with_search_index(Arc::new(mock_search_index));
+        let result = service.search_messages(&user_id, "rust").await.unwrap();
 
-        Ok(())
+        let result_ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(result_ids, vec![best.id, second.id]);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        error::RepositoryError,
-        repository::{MockMessageRepository, MockStampRepository, MockUserRepository},
-        test_factories::{MessageListItemBuilder, RepositoryBuilder, StampBuilder, UserBuilder},
-        traq_client::MockTraqClient,
-    };
-    use fake::{Fake, uuid::UUIDv4};
-    use mockall::predicate;
 
     #[tokio::test]
-    async fn timeline_get_recommended_messages_success() {
-        let mut mock_message_repo = MockMessageRepository::new();
-        let mut mock_user_repo = MockUserRepository::new();
-        let mut mock_stamp_repo = MockStampRepository::new();
+    async fn timeline_search_messages_without_index() {
+        let user_id: Uuid = UUIDv4.fake();
         let message = MessageListItemBuilder::new().build();
         let messages = vec![message.clone()];
 
-        // 1. Affinity / Similar users setup
-        mock_user_repo
-            .expect_find_frequently_stamped_users_by()
-            .with(predicate::eq(message.user_id), predicate::eq(20))
-            .returning(|_, _| Ok(vec![]));
-        mock_stamp_repo
-            .expect_find_frequently_stamped_channels_by()
-            .with(predicate::eq(message.user_id), predicate::eq(10))
-            .returning(|_, _| Ok(vec![]));
-        mock_user_repo
-            .expect_find_similar_users()
-            .with(predicate::eq(message.user_id), predicate::eq(20))
-            .returning(|_, _| Ok(vec![]));
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_search()
+            .with(
+                predicate::eq("rust"),
+                predicate::eq(SEARCH_LIMIT as i64),
+                predicate::eq(user_id),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(messages.clone()));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
 
-        // 2. Mock setup for remaining fetches
-        mock_message_repo
-            .expect_find_messages_by_author_allowlist()
-            .returning(|_, _, _| Ok(vec![]));
-        mock_message_repo
-            .expect_find_messages_by_channel_allowlist()
-            .returning(|_, _, _| Ok(vec![]));
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.search_messages(&user_id, "rust").await.unwrap();
 
-        // 3. Recommendation fetches
-        mock_message_repo
-            .expect_find_top_reacted_messages()
-            .returning(move |_, _| Ok(messages.clone()));
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
+
+    #[tokio::test]
+    async fn timeline_save_search_validates_input() {
+        let user_id: Uuid = UUIDv4.fake();
+        let mut mock_saved_search_repo = MockSavedSearchRepository::new();
+        mock_saved_search_repo.expect_create().never();
+        mock_saved_search_repo
+            .expect_find_by_user()
+            .returning(|_| Ok(vec![]));
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
-            .user(mock_user_repo)
-            .stamp(mock_stamp_repo)
+            .saved_search(mock_saved_search_repo)
             .build();
         let service = TimelineServiceImpl::new(repo);
-        let result = service
-            .get_recommended_messages(&message.user_id)
+
+        assert!(matches!(
+            service.save_search(&user_id, " ", "rust").await,
+            Err(DomainError::InvalidSavedSearch(_))
+        ));
+        assert!(matches!(
+            service
+                .save_search(&user_id, "Rust", &"a".repeat(257))
+                .await,
+            Err(DomainError::InvalidSavedSearch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn timeline_save_search_limits_count() {
+        let user_id: Uuid = UUIDv4.fake();
+        let saved: Vec<SavedSearch> = (0..MAX_SAVED_SEARCHES as i64)
+            .map(|id| SavedSearch {
+                id,
+                name: "Rust".to_string(),
+                query: "rust".to_string(),
+                created_at: OffsetDateTime::now_utc(),
+            })
+            .collect();
+        let mut mock_saved_search_repo = MockSavedSearchRepository::new();
+        mock_saved_search_repo
+            .expect_find_by_user()
+            .returning(move |_| Ok(saved.clone()));
+        mock_saved_search_repo.expect_create().never();
+
+        let repo = RepositoryBuilder::new()
+            .saved_search(mock_saved_search_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.save_search(&user_id, "Rust", "rust").await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            DomainError::TooManySavedSearches(MAX_SAVED_SEARCHES)
+        );
+    }
+
+    #[tokio::test]
+    async fn timeline_publish_announcement_trims_content() {
+        let mut mock_announcement_repo = MockAnnouncementRepository::new();
+        mock_announcement_repo
+            .expect_publish()
+            .withf(|content| content == "Maintenance tonight")
+            .times(1)
+            .returning(|content| {
+                Ok(Announcement {
+                    id: 1,
+                    content: content.to_string(),
+                    created_at: OffsetDateTime::now_utc(),
+                })
+            });
+
+        let repo = RepositoryBuilder::new()
+            .announcement(mock_announcement_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+
+        let announcement = service
+            .publish_announcement("  Maintenance tonight\n")
             .await
             .unwrap();
+        assert_eq!(announcement.content, "Maintenance tonight");
+        assert_eq!(
+            service.publish_announcement(" ").await.unwrap_err(),
+            DomainError::InvalidAnnouncement(MAX_ANNOUNCEMENT_LEN)
+        );
+        assert_eq!(
+            service
+                .publish_announcement(&"a".repeat(MAX_ANNOUNCEMENT_LEN + 1))
+                .await
+                .unwrap_err(),
+            DomainError::InvalidAnnouncement(MAX_ANNOUNCEMENT_LEN)
+        );
+    }
+
+    #[tokio::test]
+    async fn timeline_withdraw_announcement_without_one() {
+        let mut mock_announcement_repo = MockAnnouncementRepository::new();
+        mock_announcement_repo
+            .expect_withdraw()
+            .times(1)
+            .returning(|| Ok(false));
+
+        let repo = RepositoryBuilder::new()
+            .announcement(mock_announcement_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+
+        assert_eq!(
+            service.withdraw_announcement().await.unwrap_err(),
+            DomainError::NoAnnouncement
+        );
+    }
 
+    #[tokio::test]
+    async fn timeline_run_saved_search() {
+        let user_id: Uuid = UUIDv4.fake();
+        let message = MessageListItemBuilder::new().build();
+        let messages = vec![message.clone()];
+
+        let mut mock_saved_search_repo = MockSavedSearchRepository::new();
+        mock_saved_search_repo
+            .expect_find_by_id()
+            .with(predicate::eq(user_id), predicate::eq(1))
+            .returning(|_, id| {
+                Ok(Some(SavedSearch {
+                    id,
+                    name: "Rust".to_string(),
+                    query: "rust".to_string(),
+                    created_at: OffsetDateTime::now_utc(),
+                }))
+            });
+        mock_saved_search_repo
+            .expect_find_by_id()
+            .returning(|_, _| Ok(None));
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_search()
+            .with(
+                predicate::eq("rust"),
+                predicate::always(),
+                predicate::eq(user_id),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(messages.clone()));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(|_| Ok(vec![]));
+
+        let repo = RepositoryBuilder::new()
+            .saved_search(mock_saved_search_repo)
+            .message_reader(mock_message_reader)
+            .block(mock_block_repo)
+            .build();
+        let service = TimelineServiceImpl::new(repo);
+
+        let result = service.run_saved_search(&user_id, 1).await.unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].id, message.id);
-        assert_eq!(result[0].content, message.content);
+
+        assert_eq!(
+            service.run_saved_search(&user_id, 2).await.unwrap_err(),
+            DomainError::NoSavedSearchForId(2)
+        );
     }
 
     #[tokio::test]
-    async fn timeline_get_recommended_messages_empty() {
-        let mut mock_message_repo = MockMessageRepository::new();
-        let mut mock_user_repo = MockUserRepository::new();
-        let mut mock_stamp_repo = MockStampRepository::new();
-
+    async fn timeline_follow_user_rejects_self() {
         let user_id = UUIDv4.fake();
+        let mut mock_follow_repo = MockFollowRepository::new();
+        mock_follow_repo.expect_follow_user().never();
 
-        // Mocks returning empty/defaults
-        mock_user_repo
-            .expect_find_frequently_stamped_users_by()
-            .returning(|_, _| Ok(vec![]));
-        mock_stamp_repo
-            .expect_find_frequently_stamped_channels_by()
-            .returning(|_, _| Ok(vec![]));
-        mock_user_repo
-            .expect_find_similar_users()
-            .returning(|_, _| Ok(vec![]));
-        mock_message_repo
-            .expect_find_messages_by_author_allowlist()
-            .returning(|_, _, _| Ok(vec![]));
-        mock_message_repo
-            .expect_find_messages_by_channel_allowlist()
-            .returning(|_, _, _| Ok(vec![]));
+        let repo = RepositoryBuilder::new().follow(mock_follow_repo).build();
+        let service = TimelineServiceImpl::new(repo);
+        let result = service.follow_user(&user_id, &user_id).await;
 
-        mock_message_repo
-            .expect_find_top_reacted_messages()
-            .returning(|_, _| Ok(vec![]));
+        assert_eq!(result.unwrap_err(), DomainError::CannotFollowSelf);
+    }
+
+    #[tokio::test]
+    async fn timeline_get_timeline_updates_without_cursor() {
+        let user_id = UUIDv4.fake();
+        let mut mock_event_repo = MockMessageEventRepository::new();
+        mock_event_repo
+            .expect_find_latest_cursor()
+            .times(1)
+            .returning(|| Ok(42));
+        mock_event_repo.expect_find_since().never();
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
-            .user(mock_user_repo)
-            .stamp(mock_stamp_repo)
+            .message_event(mock_event_repo)
             .build();
         let service = TimelineServiceImpl::new(repo);
-        let result = service.get_recommended_messages(&user_id).await.unwrap();
+        let result = service.get_timeline_updates(&user_id, None).await.unwrap();
 
-        assert!(result.is_empty());
+        assert_eq!(result.cursor, 42);
+        assert!(result.added.is_empty() && result.updated.is_empty() && result.removed.is_empty());
+        assert!(!result.has_more);
     }
 
     #[tokio::test]
-    async fn timeline_get_recommended_messages_error() {
-        let mut mock_message_repo = MockMessageRepository::new();
-        let mut mock_user_repo = MockUserRepository::new();
-        let mut mock_stamp_repo = MockStampRepository::new();
-
+    async fn timeline_get_timeline_updates_collapses_events() {
         let user_id = UUIDv4.fake();
+        let blocked_user_id: Uuid = UUIDv4.fake();
+        let added = MessageListItemBuilder::new().build();
+        let updated = MessageListItemBuilder::new().build();
+        let blocked = MessageListItemBuilder::new()
+            .user_id(blocked_user_id)
+            .build();
+        let removed_id: Uuid = UUIDv4.fake();
+        let events = vec![
+            MessageEvent {
+                cursor: 11,
+                message_id: added.id,
+                kind: MessageEventKind::Added,
+            },
+            MessageEvent {
+                cursor: 12,
+                message_id: updated.id,
+                kind: MessageEventKind::Updated,
+            },
+            MessageEvent {
+                cursor: 13,
+                message_id: added.id,
+                kind: MessageEventKind::Updated,
+            },
+            MessageEvent {
+                cursor: 14,
+                message_id: blocked.id,
+                kind: MessageEventKind::Added,
+            },
+            MessageEvent {
+                cursor: 15,
+                message_id: removed_id,
+                kind: MessageEventKind::Removed,
+            },
+        ];
+        let items = vec![added.clone(), updated.clone(), blocked.clone()];
 
-        mock_user_repo
-            .expect_find_frequently_stamped_users_by()
-            .returning(|_, _| Ok(vec![]));
-        mock_stamp_repo
-            .expect_find_frequently_stamped_channels_by()
-            .returning(|_, _| Ok(vec![]));
-        mock_user_repo
-            .expect_find_similar_users()
-            .returning(|_, _| Ok(vec![]));
-        mock_message_repo
-            .expect_find_messages_by_author_allowlist()
-            .returning(|_, _, _| Ok(vec![]));
-        mock_message_repo
-            .expect_find_messages_by_channel_allowlist()
-            .returning(|_, _, _| Ok(vec![]));
-
-        mock_message_repo
-            .expect_find_top_reacted_messages()
-            .returning(|_, _| Err(RepositoryError::Database("database error".to_string())));
+        let mut mock_event_repo = MockMessageEventRepository::new();
+        mock_event_repo
+            .expect_find_since()
+            .with(predicate::eq(10), predicate::eq(TIMELINE_UPDATES_LIMIT + 1))
+            .times(1)
+            .returning(move |_, _| Ok(events.clone()));
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_list_items_by_ids()
+            .withf(|ids| ids.len() == 3)
+            .times(1)
+            .returning(move |_| Ok(items.clone()));
+        let mut mock_mute_repo = MockMuteRepository::new();
+        mock_mute_repo
+            .expect_find_muted_user_ids()
+            .returning(|_| Ok(vec![]));
+        mock_mute_repo
+            .expect_find_muted_channel_ids()
+            .returning(|_| Ok(vec![]));
+        let mut mock_block_repo = MockBlockRepository::new();
+        mock_block_repo
+            .expect_find_blocked_or_blocking_user_ids()
+            .returning(move |_| Ok(vec![blocked_user_id]));
+        let mut mock_feedback_repo = MockFeedbackRepository::new();
+        mock_feedback_repo
+            .expect_find_hidden_messages()
+            .returning(|_| Ok(vec![]));
 
         let repo = RepositoryBuilder::new()
-            .message(mock_message_repo)
-            .user(mock_user_repo)
-            .stamp(mock_stamp_repo)
+            .block(mock_block_repo)
+            .feedback(mock_feedback_repo)
+            .message_reader(mock_message_reader)
+            .message_event(mock_event_repo)
+            .mute(mock_mute_repo)
             .build();
         let service = TimelineServiceImpl::new(repo);
-        let result = service.get_recommended_messages(&user_id).await;
+        let result = service
+            .get_timeline_updates(&user_id, Some(10))
+            .await
+            .unwrap();
+
+        assert_eq!(result.cursor, 15);
+        assert_eq!(result.added.len(), 1);
+        assert_eq!(result.added[0].id, added.id);
+        assert_eq!(result.updated.len(), 1);
+        assert_eq!(result.updated[0].id, updated.id);
+        assert_eq!(result.removed, vec![removed_id]);
+        assert!(!result.has_more);
+    }
+
+    #[test]
+    fn timeline_get_degraded_messages_excludes_own_messages() {
+        let user_id = UUIDv4.fake();
+        let own_message = MessageBuilder::new().user_id(user_id).build();
+        let message = MessageBuilder::new().build();
+        let recent_messages = Arc::new(RecentMessages::new(10));
+        recent_messages.extend(&[own_message, message.clone()]);
+
+        let service = TimelineServiceImpl::new(RepositoryBuilder::new().build())
+            .with_recent_messages(recent_messages);
+        let result = service.get_degraded_messages(&user_id);
 
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), DomainError::Repository(_)));
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
     }
 
     #[tokio::test]
@@ -473,6 +5171,38 @@ mod tests {
         // TraqClient should NOT have been called (cache hit)
     }
 
+    #[tokio::test]
+    async fn traq_get_user_profile_includes_stats() {
+        let user = UserBuilder::new().build();
+        let user_id = user.id;
+        let stats = UserStats {
+            message_count: 12,
+            reactions_received: 34,
+        };
+
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_by_id()
+            .returning(move |_| Ok(Some(user.clone())));
+        let mut mock_message_reader = MockMessageReader::new();
+        let stats_clone = stats.clone();
+        mock_message_reader
+            .expect_find_user_stats()
+            .withf(move |uid, _| *uid == user_id)
+            .times(1)
+            .returning(move |_, _| Ok(stats_clone.clone()));
+
+        let repo = RepositoryBuilder::new()
+            .user(mock_user_repo)
+            .message_reader(mock_message_reader)
+            .build();
+        let service = TraqServiceImpl::new(repo, Arc::new(MockTraqClient::new()));
+        let profile = service.get_user_profile(&user_id).await.unwrap();
+
+        assert_eq!(profile.user.id, user_id);
+        assert_eq!(profile.stats, stats);
+    }
+
     #[tokio::test]
     async fn traq_get_user_by_id_cache_miss() {
         let user_id = UUIDv4.fake();
@@ -511,6 +5241,116 @@ mod tests {
         assert_eq!(result.id, user_id);
     }
 
+    #[tokio::test]
+    async fn traq_get_users_by_ids_fetches_only_misses() {
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_client = MockTraqClient::new();
+        let cached = UserBuilder::new().build();
+        let missing = UserBuilder::new().build();
+        let ids = vec![cached.id, missing.id];
+
+        let cached_clone = cached.clone();
+        mock_user_repo
+            .expect_find_by_ids()
+            .times(1)
+            .returning(move |_| Ok(vec![cached_clone.clone()]));
+        mock_user_repo
+            .expect_find_random_valid_token()
+            .times(1)
+            .returning(|| Ok(Some("test_token".to_string())));
+        let missing_id = missing.id;
+        let missing_clone = missing.clone();
+        mock_client
+            .expect_get_user()
+            .withf(move |token, id| token == "test_token" && *id == missing_id)
+            .times(1)
+            .returning(move |_, _| Ok(missing_clone.clone()));
+        mock_user_repo.expect_save().times(1).returning(|_| Ok(()));
+
+        let repo = RepositoryBuilder::new().user(mock_user_repo).build();
+        let service = TraqServiceImpl::new(repo, Arc::new(mock_client));
+        let result = service.get_users_by_ids(&ids).await.unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[&cached.id], cached);
+        assert_eq!(result[&missing.id], missing);
+    }
+
+    #[tokio::test]
+    async fn traq_get_stamp_image_serves_cached_images() {
+        let stamp_id: Uuid = UUIDv4.fake();
+        let mut mock_image_cache_repo = MockImageCacheRepository::new();
+        mock_image_cache_repo
+            .expect_find()
+            .with(
+                predicate::eq(ImageKind::Stamp),
+                predicate::eq(stamp_id),
+                predicate::eq(ImageSize::Original),
+            )
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(Some(CachedImage {
+                    data: vec![1, 2, 3],
+                    content_type: "image/png".to_string(),
+                    stored_at: OffsetDateTime::now_utc(),
+                }))
+            });
+
+        let repo = RepositoryBuilder::new()
+            .image_cache(mock_image_cache_repo)
+            .build();
+        let image_cache = Arc::new(ImageCache::new(repo.image_cache.clone()));
+        // traQ isn't asked for the image
+        let service = TraqServiceImpl::new(repo, Arc::new(MockTraqClient::new()))
+            .with_image_cache(image_cache);
+
+        let image = service
+            .get_stamp_image(&stamp_id, ImageSize::Original)
+            .await
+            .unwrap();
+
+        assert_eq!(image, (vec![1, 2, 3], "image/png".to_string()));
+    }
+
+    #[tokio::test]
+    async fn traq_prefetch_images_stops_when_rate_limited() {
+        let user_ids: Vec<Uuid> = vec![UUIDv4.fake(), UUIDv4.fake()];
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_random_valid_token()
+            .returning(|| Ok(Some("test_token".to_string())));
+        let mut mock_image_cache_repo = MockImageCacheRepository::new();
+        mock_image_cache_repo
+            .expect_find()
+            .returning(|_, _, _| Ok(None));
+        let mut mock_client = MockTraqClient::new();
+        mock_client
+            .expect_get_user_icon()
+            .with(predicate::always(), predicate::eq(user_ids[0]))
+            .times(1)
+            .returning(|_, _| {
+                Err(TraqClientError::ApiError {
+                    status: StatusCode::TOO_MANY_REQUESTS,
+                    message: "rate limited".to_string(),
+                })
+            });
+
+        let repo = RepositoryBuilder::new()
+            .user(mock_user_repo)
+            .image_cache(mock_image_cache_repo)
+            .build();
+        let image_cache = Arc::new(ImageCache::new(repo.image_cache.clone()));
+        let service =
+            TraqServiceImpl::new(repo, Arc::new(mock_client)).with_image_cache(image_cache);
+
+        service
+            .prefetch_images(&PageAssets {
+                user_ids,
+                stamp_ids: vec![],
+            })
+            .await;
+    }
+
     #[tokio::test]
     async fn traq_get_user_by_id_no_token_error() {
         let user_id = UUIDv4.fake();
@@ -537,6 +5377,155 @@ mod tests {
         assert_eq!(result.unwrap_err(), DomainError::NoTokenForUserFetch);
     }
 
+    #[tokio::test]
+    async fn traq_get_channel_by_id_not_found() {
+        let channel_id = UUIDv4.fake();
+        let mut mock_channel_repo = MockChannelRepository::new();
+        mock_channel_repo
+            .expect_find_by_id()
+            .with(predicate::eq(channel_id))
+            .times(1)
+            .returning(|_| Ok(None));
+
+        let repo = RepositoryBuilder::new().channel(mock_channel_repo).build();
+        let service = TraqServiceImpl::new(repo, Arc::new(MockTraqClient::new()));
+        let result = service.get_channel_by_id(&channel_id).await;
+
+        assert_eq!(result.unwrap_err(), DomainError::NoChannelForId(channel_id));
+    }
+
+    #[tokio::test]
+    async fn traq_get_stamps_by_ids_serves_cached_stamps() {
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let stamp = StampBuilder::new().build();
+        let stamp_id = stamp.id;
+
+        mock_stamp_repo
+            .expect_find_by_ids()
+            .withf(move |ids| ids == [stamp_id])
+            .times(1)
+            .returning(move |_| Ok(vec![stamp.clone()]));
+
+        let repo = RepositoryBuilder::new().stamp(mock_stamp_repo).build();
+        let service = TraqServiceImpl::new(repo, Arc::new(MockTraqClient::new()));
+        let result = service.get_stamps_by_ids(&[stamp_id]).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[&stamp_id].id, stamp_id);
+    }
+
+    #[tokio::test]
+    async fn traq_get_stamps_by_ids_fetches_misses_at_once() {
+        let mut mock_user_repo = MockUserRepository::new();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let mut mock_client = MockTraqClient::new();
+
+        let cached = StampBuilder::new().name("cached").build();
+        let missing = StampBuilder::new().name("missing").build();
+        let other = StampBuilder::new().name("other").build();
+        let unknown_id = UUIDv4.fake();
+        let ids = vec![cached.id, missing.id, unknown_id];
+
+        let cached_clone = cached.clone();
+        mock_stamp_repo
+            .expect_find_by_ids()
+            .times(1)
+            .returning(move |_| Ok(vec![cached_clone.clone()]));
+        mock_user_repo
+            .expect_find_random_valid_token()
+            .times(1)
+            .returning(|| Ok(Some("test_token".to_string())));
+        let all_stamps = vec![cached.clone(), missing.clone(), other];
+        mock_client
+            .expect_get_stamps()
+            .times(1)
+            .returning(move |_| Ok(all_stamps.clone()));
+        mock_stamp_repo
+            .expect_save_batch()
+            .withf(|stamps| stamps.len() == 3)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let repo = RepositoryBuilder::new()
+            .stamp(mock_stamp_repo)
+            .user(mock_user_repo)
+            .build();
+        let service = TraqServiceImpl::new(repo, Arc::new(mock_client));
+        let result = service.get_stamps_by_ids(&ids).await.unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[&cached.id].name, "cached");
+        assert_eq!(result[&missing.id].name, "missing");
+        assert!(!result.contains_key(&unknown_id));
+    }
+
+    #[tokio::test]
+    async fn traq_get_stamps_by_ids_rejects_too_many_ids() {
+        let service = TraqServiceImpl::new(
+            RepositoryBuilder::new().build(),
+            Arc::new(MockTraqClient::new()),
+        );
+        let ids: Vec<Uuid> = (0..=MAX_BATCH_STAMP_IDS).map(|_| UUIDv4.fake()).collect();
+
+        let result = service.get_stamps_by_ids(&ids).await;
+
+        assert!(matches!(
+            result,
+            Err(DomainError::TooManyStampIds(MAX_BATCH_STAMP_IDS))
+        ));
+    }
+
+    #[tokio::test]
+    async fn traq_get_stamp_suggestions_prefers_familiar_usual_stamps() {
+        let user_id: Uuid = UUIDv4.fake();
+        let usual = StampBuilder::new().name("usual").build();
+        let familiar_usual = StampBuilder::new().name("familiar_usual").build();
+        let recent = StampBuilder::new().name("recent").build();
+        let added = StampBuilder::new().name("added").build();
+        let message = MessageBuilder::new()
+            .reactions(vec![
+                ReactionBuilder::new()
+                    .user_id(user_id)
+                    .stamp_id(added.id)
+                    .build(),
+            ])
+            .build();
+        let message_id = message.id;
+        let (author_id, channel_id) = (message.user_id, message.channel_id);
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_by_id()
+            .with(predicate::eq(message_id))
+            .returning(move |_| Ok(Some(message.clone())));
+        let mut mock_stamp_repo = MockStampRepository::new();
+        let received = vec![usual.clone(), added.clone(), familiar_usual.clone()];
+        mock_stamp_repo
+            .expect_find_frequently_received()
+            .withf(move |aid, cid, _, _| *aid == author_id && *cid == channel_id)
+            .times(1)
+            .returning(move |_, _, _, _| Ok(received.clone()));
+        let recently_used = vec![recent.clone(), familiar_usual.clone()];
+        mock_stamp_repo
+            .expect_find_recently_used_by()
+            .withf(move |uid, _| *uid == user_id)
+            .times(1)
+            .returning(move |_, _| Ok(recently_used.clone()));
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .stamp(mock_stamp_repo)
+            .build();
+        let service = TraqServiceImpl::new(repo, Arc::new(MockTraqClient::new()));
+        let suggestions = service
+            .get_stamp_suggestions(&user_id, &message_id)
+            .await
+            .unwrap();
+
+        let names: Vec<_> = suggestions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["familiar_usual", "usual", "recent"]);
+    }
+
     #[tokio::test]
     async fn traq_search_stamps_filters_correctly() {
         let mut mock_user_repo = MockUserRepository::new();
@@ -582,12 +5571,12 @@ mod tests {
 
     #[tokio::test]
     async fn traq_remove_message_stamp_optimistically_updates_local_db() {
-        let user_id = UUIDv4.fake();
-        let message_id = UUIDv4.fake();
-        let stamp_id = UUIDv4.fake();
+        let user_id: Uuid = UUIDv4.fake();
+        let message_id: Uuid = UUIDv4.fake();
+        let stamp_id: Uuid = UUIDv4.fake();
 
         let mut mock_user_repo = MockUserRepository::new();
-        let mut mock_message_repo = MockMessageRepository::new();
+        let mut mock_message_writer = MockMessageWriter::new();
         let mut mock_client = MockTraqClient::new();
 
         mock_user_repo
@@ -602,24 +5591,305 @@ mod tests {
             .times(1)
             .returning(|_, _, _| Ok(()));
 
-        mock_message_repo
+        mock_message_writer
             .expect_remove_reaction()
             .withf(move |msg_id, stp_id, usr_id| {
-                *msg_id == message_id && *stp_id == stamp_id && *usr_id == user_id
+                *msg_id.as_uuid() == message_id
+                    && *stp_id.as_uuid() == stamp_id
+                    && *usr_id.as_uuid() == user_id
             })
             .times(1)
             .returning(|_, _, _| Ok(()));
 
+        let mut mock_event_repo = MockMessageEventRepository::new();
+        mock_event_repo
+            .expect_append()
+            .withf(move |ids, kind| ids == [message_id] && *kind == MessageEventKind::Updated)
+            .times(1)
+            .returning(|_, _| Ok(vec![]));
+
         let repo = RepositoryBuilder::new()
             .user(mock_user_repo)
-            .message(mock_message_repo)
+            .message_writer(mock_message_writer)
+            .message_event(mock_event_repo)
             .build();
 
         let service = TraqServiceImpl::new(repo, Arc::new(mock_client));
         let result = service
-            .remove_message_stamp(&user_id, &message_id, &stamp_id)
+            .remove_message_stamp(&user_id.into(), &message_id.into(), &stamp_id.into())
             .await;
 
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn traq_toggle_message_stamp_removes_cached_reaction() {
+        let user_id: Uuid = UUIDv4.fake();
+        let stamp_id: Uuid = UUIDv4.fake();
+        let message = MessageBuilder::new()
+            .reactions(vec![
+                ReactionBuilder::new()
+                    .user_id(user_id)
+                    .stamp_id(stamp_id)
+                    .build(),
+            ])
+            .build();
+        let message_id = message.id;
+
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_token_by_user_id()
+            .returning(|_| Ok(Some("test_token".to_string())));
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
+        mock_message_reader
+            .expect_find_by_id()
+            .with(predicate::eq(message_id))
+            .returning(move |_| Ok(Some(message.clone())));
+        mock_message_writer
+            .expect_remove_reaction()
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let mut mock_event_repo = MockMessageEventRepository::new();
+        mock_event_repo.expect_append().returning(|_, _| Ok(vec![]));
+        let mut mock_client = MockTraqClient::new();
+        mock_client
+            .expect_remove_message_stamp()
+            .withf(move |_, mid, sid| *mid.as_uuid() == message_id && *sid.as_uuid() == stamp_id)
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        mock_client.expect_add_message_stamp().never();
+
+        let repo = RepositoryBuilder::new()
+            .user(mock_user_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(mock_event_repo)
+            .build();
+        let service = TraqServiceImpl::new(repo, Arc::new(mock_client));
+        let reacted = service
+            .toggle_message_stamp(&user_id.into(), &message_id.into(), &stamp_id.into())
+            .await
+            .unwrap();
+
+        assert!(!reacted);
+    }
+
+    #[tokio::test]
+    async fn traq_toggle_message_stamp_adds_missing_reaction() {
+        let user_id: Uuid = UUIDv4.fake();
+        let stamp_id: Uuid = UUIDv4.fake();
+        // Someone else reacted with the stamp
+        let message = MessageBuilder::new()
+            .reactions(vec![ReactionBuilder::new().stamp_id(stamp_id).build()])
+            .build();
+        let message_id = message.id;
+        let message_clone = message.clone();
+
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_token_by_user_id()
+            .returning(|_| Ok(Some("test_token".to_string())));
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
+        mock_message_reader
+            .expect_find_by_id()
+            .returning(move |_| Ok(Some(message_clone.clone())));
+        mock_message_writer
+            .expect_save()
+            .times(1)
+            .returning(|_| Ok(()));
+        let mut mock_event_repo = MockMessageEventRepository::new();
+        mock_event_repo.expect_append().returning(|_, _| Ok(vec![]));
+        let mut mock_client = MockTraqClient::new();
+        mock_client
+            .expect_add_message_stamp()
+            .withf(move |_, mid, sid, count| {
+                *mid.as_uuid() == message_id && *sid.as_uuid() == stamp_id && *count == 1
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        mock_client
+            .expect_get_message()
+            .returning(move |_, _| Ok(message.clone()));
+        mock_client.expect_remove_message_stamp().never();
+        let mut mock_stamp_repo = MockStampRepository::new();
+        mock_stamp_repo
+            .expect_record_usage()
+            .withf(move |uid, sid| *uid.as_uuid() == user_id && *sid.as_uuid() == stamp_id)
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let repo = RepositoryBuilder::new()
+            .user(mock_user_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .message_event(mock_event_repo)
+            .stamp(mock_stamp_repo)
+            .build();
+        let service = TraqServiceImpl::new(repo, Arc::new(mock_client));
+        let reacted = service
+            .toggle_message_stamp(&user_id.into(), &message_id.into(), &stamp_id.into())
+            .await
+            .unwrap();
+
+        assert!(reacted);
+    }
+
+    #[tokio::test]
+    async fn traq_get_channel_messages_serves_full_pages_from_cache() {
+        let user_id = UUIDv4.fake();
+        let channel_id = UUIDv4.fake();
+        let messages: Vec<MessageListItem> = (0..=CHANNEL_MESSAGES_PAGE_SIZE)
+            .map(|_| MessageListItemBuilder::new().channel_id(channel_id).build())
+            .collect();
+
+        let mut mock_message_reader = MockMessageReader::new();
+        mock_message_reader
+            .expect_find_by_channel()
+            .with(
+                predicate::eq(channel_id),
+                predicate::eq(None),
+                predicate::eq(CHANNEL_MESSAGES_PAGE_SIZE + 1),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(messages.clone()));
+        let mut mock_client = MockTraqClient::new();
+        mock_client.expect_get_channel_messages().never();
+
+        let repo = RepositoryBuilder::new()
+            .message_reader(mock_message_reader)
+            .build();
+        let service = TraqServiceImpl::new(repo, Arc::new(mock_client));
+        let page = service
+            .get_channel_messages(&user_id, &channel_id, None)
+            .await
+            .unwrap();
+
+        assert_eq!(page.messages.len(), CHANNEL_MESSAGES_PAGE_SIZE as usize);
+        assert!(page.next_cursor.is_some());
+    }
+
+    #[tokio::test]
+    async fn traq_get_channel_messages_falls_back_to_traq_before_the_cache() {
+        let user_id = UUIDv4.fake();
+        let channel_id = UUIDv4.fake();
+        let cached = MessageListItemBuilder::new().channel_id(channel_id).build();
+        let cached_created_at = cached.created_at;
+        let older = MessageBuilder::new().channel_id(channel_id).build();
+        let older_item = MessageListItem::from(older.clone());
+        let older_id = older.id;
+
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_token_by_user_id()
+            .returning(|_| Ok(Some("test_token".to_string())));
+        let mut mock_message_reader = MockMessageReader::new();
+        let mut mock_message_writer = MockMessageWriter::new();
+        let mut seq = Sequence::new();
+        let cached_clone = cached.clone();
+        mock_message_reader
+            .expect_find_by_channel()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |_, _, _| Ok(vec![cached_clone.clone()]));
+        mock_message_writer
+            .expect_save_batch()
+            .withf(move |messages| messages.len() == 1 && messages[0].id == older_id)
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+        // The page is read again once the older messages are cached
+        mock_message_reader
+            .expect_find_by_channel()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |_, _, _| Ok(vec![cached.clone(), older_item.clone()]));
+        let mut mock_client = MockTraqClient::new();
+        mock_client
+            .expect_get_channel_messages()
+            .withf(move |_, cid, before, limit| {
+                *cid == channel_id
+                    && *before == Some(cached_created_at)
+                    && *limit == CHANNEL_MESSAGES_PAGE_SIZE as i32
+            })
+            .times(1)
+            .returning(move |_, _, _, _| Ok(vec![older.clone()]));
+
+        let repo = RepositoryBuilder::new()
+            .user(mock_user_repo)
+            .message_reader(mock_message_reader)
+            .message_writer(mock_message_writer)
+            .build();
+        let service = TraqServiceImpl::new(repo, Arc::new(mock_client));
+        let page = service
+            .get_channel_messages(&user_id, &channel_id, None)
+            .await
+            .unwrap();
+
+        let ids: Vec<Uuid> = page.messages.iter().map(|m| m.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[1], older_id);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn traq_check_message_visibility_reports_inaccessible_channels() {
+        let user_id = UUIDv4.fake();
+        let (public_channel, private_channel): (Uuid, Uuid) = (UUIDv4.fake(), UUIDv4.fake());
+        let public_message = MessageListItemBuilder::new()
+            .channel_id(public_channel)
+            .build();
+        let private_messages: Vec<MessageListItem> = (0..2)
+            .map(|_| {
+                MessageListItemBuilder::new()
+                    .channel_id(private_channel)
+                    .build()
+            })
+            .collect();
+        let private_message_id = private_messages[0].id;
+
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_find_token_by_user_id()
+            .returning(|_| Ok(Some("test_token".to_string())));
+        let mut mock_client = MockTraqClient::new();
+        // Only one message is fetched per channel
+        mock_client
+            .expect_get_message()
+            .times(2)
+            .returning(move |_, message_id| {
+                if *message_id == private_message_id {
+                    Err(TraqClientError::ApiError {
+                        status: StatusCode::FORBIDDEN,
+                        message: "forbidden".to_string(),
+                    })
+                } else {
+                    Ok(MessageBuilder::new().id(*message_id).build())
+                }
+            });
+
+        let repo = RepositoryBuilder::new().user(mock_user_repo).build();
+        let service = TraqServiceImpl::new(repo, Arc::new(mock_client));
+        let mut messages = vec![public_message];
+        messages.extend(private_messages.clone());
+        let report = service
+            .check_message_visibility(&user_id, &messages)
+            .await
+            .unwrap();
+
+        assert_eq!(report.checked_messages, 3);
+        assert_eq!(report.checked_channels, 2);
+        assert_eq!(
+            report.leaks,
+            private_messages
+                .iter()
+                .map(|m| VisibilityLeak {
+                    message_id: m.id,
+                    channel_id: private_channel,
+                    status: 403,
+                })
+                .collect::<Vec<_>>()
+        );
+    }
 }
@@ -1,11 +1,26 @@
 #![cfg(any(test, feature = "test-utils"))]
 
-use crate::model::{Message, MessageListItem, Reaction, Stamp, User};
+use crate::citation;
+use crate::model::{Message, MessageListItem, Reaction, RecommendationReason, Stamp, User};
 use crate::repository::{
-    MessageRepository, MockMessageRepository, MockStampRepository, MockUserRepository, Repository,
-    StampRepository, UserRepository,
+    AnnouncementRepository, BlockRepository, BookmarkRepository, ChannelRepository,
+    EmbeddingRepository, FeedbackRepository, FollowRepository, ImageCacheRepository,
+    ImpressionRepository, JobRunRepository, LinkPreviewRepository, MessageEventRepository,
+    MessageReader, MessageWriter, MockAnnouncementRepository, MockBlockRepository,
+    MockBookmarkRepository, MockChannelRepository, MockEmbeddingRepository, MockFeedbackRepository,
+    MockFollowRepository, MockImageCacheRepository, MockImpressionRepository, MockJobRunRepository,
+    MockLinkPreviewRepository, MockMessageEventRepository, MockMessageReader, MockMessageWriter,
+    MockMuteRepository, MockPresenceRepository, MockQuotaRepository, MockReportRepository,
+    MockSavedSearchRepository, MockStampRepository, MockUserRepository, MockUserSettingsRepository,
+    MockWebhookRepository, MuteRepository, PresenceRepository, QuotaRepository, ReportRepository,
+    Repository, SavedSearchRepository, StampRepository, UserRepository, UserSettingsRepository,
+    WebhookRepository,
+};
+use fake::{
+    Fake, Faker,
+    faker::{lorem::en::Paragraph, time::en::DateTimeBetween},
+    uuid::UUIDv4,
 };
-use fake::{Fake, Faker, faker::time::en::DateTimeBetween, uuid::UUIDv4};
 use std::sync::Arc;
 use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 use uuid::Uuid;
@@ -180,10 +195,16 @@ impl MessageListItemBuilder {
             user_id: self.user_id,
             user: self.user,
             channel_id: self.channel_id,
+            channel: None,
+            reply_to_message_id: citation::extract_reply_to(&self.content),
+            quoted_message: None,
+            link_previews: vec![],
             content: self.content,
             created_at: self.created_at,
             updated_at: self.updated_at,
             reactions: self.reactions,
+            reaction_summaries: vec![],
+            reason: None,
         }
     }
 }
@@ -309,6 +330,7 @@ impl ReactionBuilder {
     pub fn build(self) -> Reaction {
         Reaction {
             stamp_id: self.stamp_id,
+            stamp_name: None,
             user_id: self.user_id,
             stamp_count: self.stamp_count,
         }
@@ -321,6 +343,60 @@ impl Default for ReactionBuilder {
     }
 }
 
+/// Builds candidate lists shaped like the ones recommendation sources return in production, for
+/// benchmarks and tests of the scoring pipeline.
+///
+/// Every source finds `per_source` messages with their author, content and reactions attached.
+/// The messages are posted by 100 authors in 30 channels, and a third of the messages of each
+/// source are also found by the next one, as popular messages tend to come up in several sources.
+pub fn candidate_sources(per_source: usize) -> Vec<(RecommendationReason, Vec<MessageListItem>)> {
+    let authors: Vec<User> = (0..100).map(|_| UserBuilder::new().build()).collect();
+    let channels: Vec<Uuid> = (0..30).map(|_| UUIDv4.fake()).collect();
+    let stamps: Vec<Uuid> = (0..20).map(|_| UUIDv4.fake()).collect();
+    let reasons = [
+        RecommendationReason::Popular,
+        RecommendationReason::FrequentlyStampedAuthor,
+        RecommendationReason::FrequentlyStampedChannel,
+        RecommendationReason::SimilarUsers,
+        RecommendationReason::Recent,
+        RecommendationReason::SimilarContent,
+    ];
+
+    let mut sources: Vec<(RecommendationReason, Vec<MessageListItem>)> = vec![];
+    let mut n = 0;
+    for reason in reasons {
+        let mut messages: Vec<MessageListItem> = sources
+            .last()
+            .map(|(_, previous)| previous.iter().step_by(3).cloned().collect())
+            .unwrap_or_default();
+        while messages.len() < per_source {
+            // A few authors post most of the messages
+            let author = &authors[(n * n) % authors.len()];
+            let reactions = (0..n % 8)
+                .map(|i| {
+                    ReactionBuilder::new()
+                        .stamp_id(stamps[(n + i) % stamps.len()])
+                        .build()
+                })
+                .collect();
+            messages.push(
+                MessageListItemBuilder::new()
+                    .user_id(author.id)
+                    .user(Some(author.clone()))
+                    .channel_id(channels[n % channels.len()])
+                    .content(Paragraph(1..4).fake::<String>())
+                    .reactions(reactions)
+                    .build(),
+            );
+            n += 1;
+        }
+        messages.truncate(per_source);
+        sources.push((reason, messages));
+    }
+
+    sources
+}
+
 /// Builder for creating Repository instances with mock repositories in tests.
 ///
 /// This builder provides a fluent API for configuring Repository with custom
@@ -330,29 +406,177 @@ impl Default for ReactionBuilder {
 ///
 /// ```rust,ignore
 /// let repo = RepositoryBuilder::new()
-///     .message(mock_message_repo)
+///     .message_reader(mock_message_reader)
 ///     .user(mock_user_repo)
 ///     .build();
 /// ```
 pub struct RepositoryBuilder {
-    message: Option<Arc<dyn MessageRepository>>,
+    announcement: Option<Arc<dyn AnnouncementRepository>>,
+    block: Option<Arc<dyn BlockRepository>>,
+    bookmark: Option<Arc<dyn BookmarkRepository>>,
+    channel: Option<Arc<dyn ChannelRepository>>,
+    embedding: Option<Arc<dyn EmbeddingRepository>>,
+    feedback: Option<Arc<dyn FeedbackRepository>>,
+    follow: Option<Arc<dyn FollowRepository>>,
+    image_cache: Option<Arc<dyn ImageCacheRepository>>,
+    impression: Option<Arc<dyn ImpressionRepository>>,
+    job_run: Option<Arc<dyn JobRunRepository>>,
+    link_preview: Option<Arc<dyn LinkPreviewRepository>>,
+    message_reader: Option<Arc<dyn MessageReader>>,
+    message_writer: Option<Arc<dyn MessageWriter>>,
+    message_event: Option<Arc<dyn MessageEventRepository>>,
+    mute: Option<Arc<dyn MuteRepository>>,
+    presence: Option<Arc<dyn PresenceRepository>>,
+    quota: Option<Arc<dyn QuotaRepository>>,
+    report: Option<Arc<dyn ReportRepository>>,
+    saved_search: Option<Arc<dyn SavedSearchRepository>>,
     stamp: Option<Arc<dyn StampRepository>>,
     user: Option<Arc<dyn UserRepository>>,
+    user_settings: Option<Arc<dyn UserSettingsRepository>>,
+    webhook: Option<Arc<dyn WebhookRepository>>,
 }
 
 impl RepositoryBuilder {
     /// Create a new builder with all repositories unset (will use defaults)
     pub fn new() -> Self {
         Self {
-            message: None,
+            announcement: None,
+            block: None,
+            bookmark: None,
+            channel: None,
+            embedding: None,
+            feedback: None,
+            follow: None,
+            image_cache: None,
+            impression: None,
+            job_run: None,
+            link_preview: None,
+            message_reader: None,
+            message_writer: None,
+            message_event: None,
+            mute: None,
+            presence: None,
+            quota: None,
+            report: None,
+            saved_search: None,
             stamp: None,
             user: None,
+            user_settings: None,
+            webhook: None,
         }
     }
 
-    /// Set a custom MessageRepository (default: MockMessageRepository::new())
-    pub fn message<T: MessageRepository + 'static>(mut self, repo: T) -> Self {
-        self.message = Some(Arc::new(repo));
+    /// Set a custom AnnouncementRepository (default: MockAnnouncementRepository::new())
+    pub fn announcement<T: AnnouncementRepository + 'static>(mut self, repo: T) -> Self {
+        self.announcement = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom BlockRepository (default: MockBlockRepository::new())
+    pub fn block<T: BlockRepository + 'static>(mut self, repo: T) -> Self {
+        self.block = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom BookmarkRepository (default: MockBookmarkRepository::new())
+    pub fn bookmark<T: BookmarkRepository + 'static>(mut self, repo: T) -> Self {
+        self.bookmark = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom ChannelRepository (default: MockChannelRepository::new())
+    pub fn channel<T: ChannelRepository + 'static>(mut self, repo: T) -> Self {
+        self.channel = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom EmbeddingRepository (default: MockEmbeddingRepository::new())
+    pub fn embedding<T: EmbeddingRepository + 'static>(mut self, repo: T) -> Self {
+        self.embedding = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom FeedbackRepository (default: MockFeedbackRepository::new())
+    pub fn feedback<T: FeedbackRepository + 'static>(mut self, repo: T) -> Self {
+        self.feedback = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom FollowRepository (default: MockFollowRepository::new())
+    pub fn follow<T: FollowRepository + 'static>(mut self, repo: T) -> Self {
+        self.follow = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom ImageCacheRepository (default: MockImageCacheRepository::new())
+    pub fn image_cache<T: ImageCacheRepository + 'static>(mut self, repo: T) -> Self {
+        self.image_cache = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom ImpressionRepository (default: MockImpressionRepository::new())
+    pub fn impression<T: ImpressionRepository + 'static>(mut self, repo: T) -> Self {
+        self.impression = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom JobRunRepository (default: MockJobRunRepository::new())
+    pub fn job_run<T: JobRunRepository + 'static>(mut self, repo: T) -> Self {
+        self.job_run = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom LinkPreviewRepository (default: MockLinkPreviewRepository::new())
+    pub fn link_preview<T: LinkPreviewRepository + 'static>(mut self, repo: T) -> Self {
+        self.link_preview = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom MessageReader (default: MockMessageReader::new())
+    pub fn message_reader<T: MessageReader + 'static>(mut self, repo: T) -> Self {
+        self.message_reader = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom MessageWriter (default: MockMessageWriter::new())
+    pub fn message_writer<T: MessageWriter + 'static>(mut self, repo: T) -> Self {
+        self.message_writer = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom MessageEventRepository (default: MockMessageEventRepository::new())
+    pub fn message_event<T: MessageEventRepository + 'static>(mut self, repo: T) -> Self {
+        self.message_event = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom MuteRepository (default: MockMuteRepository::new())
+    pub fn mute<T: MuteRepository + 'static>(mut self, repo: T) -> Self {
+        self.mute = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom PresenceRepository (default: MockPresenceRepository::new())
+    pub fn presence<T: PresenceRepository + 'static>(mut self, repo: T) -> Self {
+        self.presence = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom QuotaRepository (default: MockQuotaRepository::new())
+    pub fn quota<T: QuotaRepository + 'static>(mut self, repo: T) -> Self {
+        self.quota = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom ReportRepository (default: MockReportRepository::new())
+    pub fn report<T: ReportRepository + 'static>(mut self, repo: T) -> Self {
+        self.report = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom SavedSearchRepository (default: MockSavedSearchRepository::new())
+    pub fn saved_search<T: SavedSearchRepository + 'static>(mut self, repo: T) -> Self {
+        self.saved_search = Some(Arc::new(repo));
         self
     }
 
@@ -368,18 +592,90 @@ impl RepositoryBuilder {
         self
     }
 
+    /// Set a custom UserSettingsRepository (default: MockUserSettingsRepository::new())
+    pub fn user_settings<T: UserSettingsRepository + 'static>(mut self, repo: T) -> Self {
+        self.user_settings = Some(Arc::new(repo));
+        self
+    }
+
+    /// Set a custom WebhookRepository (default: MockWebhookRepository::new())
+    pub fn webhook<T: WebhookRepository + 'static>(mut self, repo: T) -> Self {
+        self.webhook = Some(Arc::new(repo));
+        self
+    }
+
     /// Build the Repository using provided repositories or default mocks.
     pub fn build(self) -> Repository {
         Repository {
-            message: self
-                .message
-                .unwrap_or_else(|| Arc::new(MockMessageRepository::new())),
+            announcement: self
+                .announcement
+                .unwrap_or_else(|| Arc::new(MockAnnouncementRepository::new())),
+            block: self
+                .block
+                .unwrap_or_else(|| Arc::new(MockBlockRepository::new())),
+            bookmark: self
+                .bookmark
+                .unwrap_or_else(|| Arc::new(MockBookmarkRepository::new())),
+            channel: self
+                .channel
+                .unwrap_or_else(|| Arc::new(MockChannelRepository::new())),
+            embedding: self
+                .embedding
+                .unwrap_or_else(|| Arc::new(MockEmbeddingRepository::new())),
+            feedback: self
+                .feedback
+                .unwrap_or_else(|| Arc::new(MockFeedbackRepository::new())),
+            follow: self
+                .follow
+                .unwrap_or_else(|| Arc::new(MockFollowRepository::new())),
+            image_cache: self
+                .image_cache
+                .unwrap_or_else(|| Arc::new(MockImageCacheRepository::new())),
+            impression: self
+                .impression
+                .unwrap_or_else(|| Arc::new(MockImpressionRepository::new())),
+            job_run: self
+                .job_run
+                .unwrap_or_else(|| Arc::new(MockJobRunRepository::new())),
+            link_preview: self
+                .link_preview
+                .unwrap_or_else(|| Arc::new(MockLinkPreviewRepository::new())),
+            message_reader: self
+                .message_reader
+                .unwrap_or_else(|| Arc::new(MockMessageReader::new())),
+            message_writer: self
+                .message_writer
+                .unwrap_or_else(|| Arc::new(MockMessageWriter::new())),
+            message_event: self
+                .message_event
+                .unwrap_or_else(|| Arc::new(MockMessageEventRepository::new())),
+            mute: self
+                .mute
+                .unwrap_or_else(|| Arc::new(MockMuteRepository::new())),
+            presence: self
+                .presence
+                .unwrap_or_else(|| Arc::new(MockPresenceRepository::new())),
+            quota: self
+                .quota
+                .unwrap_or_else(|| Arc::new(MockQuotaRepository::new())),
+            report: self
+                .report
+                .unwrap_or_else(|| Arc::new(MockReportRepository::new())),
+            saved_search: self
+                .saved_search
+                .unwrap_or_else(|| Arc::new(MockSavedSearchRepository::new())),
             stamp: self
                 .stamp
                 .unwrap_or_else(|| Arc::new(MockStampRepository::new())),
             user: self
                 .user
                 .unwrap_or_else(|| Arc::new(MockUserRepository::new())),
+            user_settings: self
+                .user_settings
+                .unwrap_or_else(|| Arc::new(MockUserSettingsRepository::new())),
+            webhook: self
+                .webhook
+                .unwrap_or_else(|| Arc::new(MockWebhookRepository::new())),
         }
     }
 }
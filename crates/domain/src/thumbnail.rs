@@ -0,0 +1,170 @@
+//! Smaller copies of images proxied from traQ, so that clients showing them small don't download
+//! the originals.
+
+use crate::{
+    error::{DomainError, ImageError},
+    model::{ImageKind, ImageSize},
+    repository::ImageCacheRepository,
+};
+use std::{fmt::Debug, future::Future, sync::Arc};
+use time::{Duration, OffsetDateTime};
+use tokio::task::spawn_blocking;
+use uuid::Uuid;
+
+/// Images can be replaced in traQ, so resized copies are made again after this long.
+const CACHE_TTL: Duration = Duration::days(1);
+
+/// Resizes images. Resizing is CPU-bound, so it must not be called on an async worker thread.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+pub trait ImageResizer: Debug + Send + Sync {
+    /// Shrinks the image to fit in a `max_dimension` pixels square, keeping its aspect ratio.
+    /// Returns the image and its content type. Images that already fit, and images that can't be
+    /// resized without losing something (e.g. SVGs and animated GIFs) are returned as is.
+    fn resize(
+        &self,
+        image: &[u8],
+        content_type: &str,
+        max_dimension: u32,
+    ) -> Result<(Vec<u8>, String), ImageError>;
+}
+
+#[derive(Debug)]
+pub struct Thumbnailer {
+    resizer: Arc<dyn ImageResizer>,
+    repo: Arc<dyn ImageCacheRepository>,
+}
+
+impl Thumbnailer {
+    pub fn new(resizer: Arc<dyn ImageResizer>, repo: Arc<dyn ImageCacheRepository>) -> Self {
+        Self { resizer, repo }
+    }
+
+    /// Returns the image in `size`, resizing the image from `original` and caching the result.
+    /// The original is served if it can't be resized.
+    pub async fn get(
+        &self,
+        kind: ImageKind,
+        id: &Uuid,
+        size: ImageSize,
+        original: impl Future<Output = Result<(Vec<u8>, String), DomainError>>,
+    ) -> Result<(Vec<u8>, String), DomainError> {
+        let Some(max_dimension) = size.max_dimension() else {
+            return original.await;
+        };
+
+        let since = OffsetDateTime::now_utc() - CACHE_TTL;
+        if let Some(image) = self.repo.find(kind, id, size).await?
+            && image.stored_at >= since
+        {
+            return Ok((image.data, image.content_type));
+        }
+
+        let (image, content_type) = original.await?;
+        let resizer = self.resizer.clone();
+        let (data, data_type) = (image.clone(), content_type.clone());
+        let resized =
+            spawn_blocking(move || resizer.resize(&data, &data_type, max_dimension)).await;
+        let (image, content_type) = match resized {
+            Ok(Ok(resized)) => resized,
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to resize {:?} {}: {:?}", kind, id, e);
+                return Ok((image, content_type));
+            }
+            Err(e) => {
+                tracing::error!("Resizing {:?} {} panicked: {:?}", kind, id, e);
+                return Ok((image, content_type));
+            }
+        };
+
+        if let Err(e) = self.repo.save(kind, id, size, &image, &content_type).await {
+            tracing::warn!("Failed to cache the resized {:?} {}: {:?}", kind, id, e);
+        }
+        Ok((image, content_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::CachedImage, repository::MockImageCacheRepository};
+    use fake::{Fake, uuid::UUIDv4};
+    use mockall::predicate;
+
+    fn original() -> (Vec<u8>, String) {
+        (vec![0; 16], "image/png".to_string())
+    }
+
+    fn resized() -> (Vec<u8>, String) {
+        (vec![0; 4], "image/png".to_string())
+    }
+
+    #[tokio::test]
+    async fn resizes_and_caches_images() {
+        let id: Uuid = UUIDv4.fake();
+
+        let mut mock_repo = MockImageCacheRepository::new();
+        mock_repo.expect_find().returning(|_, _, _| Ok(None));
+        mock_repo
+            .expect_save()
+            .with(
+                predicate::eq(ImageKind::Stamp),
+                predicate::eq(id),
+                predicate::eq(ImageSize::Thumb),
+                predicate::eq(resized().0),
+                predicate::eq("image/png"),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        let mut mock_resizer = MockImageResizer::new();
+        mock_resizer
+            .expect_resize()
+            .with(
+                predicate::eq(original().0),
+                predicate::eq("image/png"),
+                predicate::eq(128),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(resized()));
+
+        let thumbnailer = Thumbnailer::new(Arc::new(mock_resizer), Arc::new(mock_repo));
+        let image = thumbnailer
+            .get(ImageKind::Stamp, &id, ImageSize::Thumb, async {
+                Ok(original())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(image, resized());
+    }
+
+    #[tokio::test]
+    async fn serves_cached_and_original_images_without_resizing() {
+        let mut mock_repo = MockImageCacheRepository::new();
+        mock_repo.expect_find().times(1).returning(|_, _, _| {
+            let (data, content_type) = resized();
+            Ok(Some(CachedImage {
+                data,
+                content_type,
+                stored_at: OffsetDateTime::now_utc(),
+            }))
+        });
+        let thumbnailer = Thumbnailer::new(Arc::new(MockImageResizer::new()), Arc::new(mock_repo));
+        let id: Uuid = UUIDv4.fake();
+
+        let cached = thumbnailer
+            .get(ImageKind::UserIcon, &id, ImageSize::Medium, async {
+                Ok(original())
+            })
+            .await
+            .unwrap();
+        let unresized = thumbnailer
+            .get(ImageKind::UserIcon, &id, ImageSize::Original, async {
+                Ok(original())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(cached, resized());
+        assert_eq!(unresized, original());
+    }
+}
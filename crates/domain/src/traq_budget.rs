@@ -0,0 +1,284 @@
+//! A rate budget for calls to traQ, shared by the requests of users and the background jobs.
+//!
+//! Background jobs such as the crawler could otherwise use up traQ's rate limit and leave users
+//! waiting, so they only take tokens while a share of the bucket is left for calls made on behalf
+//! of users.
+
+use crate::{
+    error::TraqClientError,
+    id::{MessageId, StampId},
+    model::{Channel, Message, Stamp, User},
+    traq_client::TraqClient,
+};
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Waits are at least this long, so that callers don't spin while a token is about to be added.
+const MIN_WAIT: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraqPriority {
+    /// Calls a user is waiting for, which may use the whole bucket.
+    Interactive,
+    /// Calls of background jobs, which leave the reserved share of the bucket alone.
+    Background,
+}
+
+/// Where the tokens of a [`TraqBudget`] are kept.
+#[async_trait::async_trait]
+pub trait TokenBucket: Debug + Send + Sync {
+    /// Takes a token if at least `reserve` tokens are left afterwards. Otherwise returns how long
+    /// to wait until there may be enough.
+    async fn try_take(&self, reserve: f64) -> Result<(), Duration>;
+}
+
+/// A token bucket kept in the memory of this process.
+#[derive(Debug)]
+pub struct LocalTokenBucket {
+    rate_per_second: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl LocalTokenBucket {
+    /// Refills `rate_per_second` tokens per second, up to `capacity`. The bucket starts full.
+    pub fn new(rate_per_second: f64, capacity: f64) -> Self {
+        Self {
+            rate_per_second,
+            capacity,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                updated_at: Instant::now(),
+            }),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenBucket for LocalTokenBucket {
+    async fn try_take(&self, reserve: f64) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let refilled = (now - state.updated_at).as_secs_f64() * self.rate_per_second;
+        state.tokens = (state.tokens + refilled).min(self.capacity);
+        state.updated_at = now;
+
+        let needed = reserve + 1.0;
+        if state.tokens >= needed {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (needed - state.tokens) / self.rate_per_second,
+            ))
+        }
+    }
+}
+
+/// Makes callers wait for a token before calling traQ, keeping `reserve` tokens for interactive
+/// calls.
+#[derive(Debug)]
+pub struct TraqBudget {
+    bucket: Arc<dyn TokenBucket>,
+    reserve: f64,
+}
+
+impl TraqBudget {
+    pub fn new(bucket: Arc<dyn TokenBucket>, reserve: f64) -> Self {
+        Self { bucket, reserve }
+    }
+
+    /// Waits until a call with `priority` may be made.
+    pub async fn acquire(&self, priority: TraqPriority) {
+        let reserve = match priority {
+            TraqPriority::Interactive => 0.0,
+            TraqPriority::Background => self.reserve,
+        };
+
+        while let Err(wait) = self.bucket.try_take(reserve).await {
+            tokio::time::sleep(wait.max(MIN_WAIT)).await;
+        }
+    }
+}
+
+/// Wraps a [`TraqClient`] so that every call waits for the budget first.
+#[derive(Debug)]
+pub struct BudgetedTraqClient {
+    inner: Arc<dyn TraqClient>,
+    budget: Arc<TraqBudget>,
+    priority: TraqPriority,
+}
+
+impl BudgetedTraqClient {
+    pub fn new(
+        inner: Arc<dyn TraqClient>,
+        budget: Arc<TraqBudget>,
+        priority: TraqPriority,
+    ) -> Self {
+        Self {
+            inner,
+            budget,
+            priority,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TraqClient for BudgetedTraqClient {
+    async fn get_server_version(&self) -> Result<String, TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.get_server_version().await
+    }
+
+    async fn fetch_messages_since(
+        &self,
+        token: &str,
+        since: OffsetDateTime,
+    ) -> Result<Vec<Message>, TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.fetch_messages_since(token, since).await
+    }
+
+    async fn get_channels(&self, token: &str) -> Result<Vec<Channel>, TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.get_channels(token).await
+    }
+
+    async fn get_stamp(&self, token: &str, stamp_id: &Uuid) -> Result<Stamp, TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.get_stamp(token, stamp_id).await
+    }
+
+    async fn get_stamps(&self, token: &str) -> Result<Vec<Stamp>, TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.get_stamps(token).await
+    }
+
+    async fn get_stamp_image(
+        &self,
+        token: &str,
+        stamp_id: &Uuid,
+    ) -> Result<(Vec<u8>, String), TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.get_stamp_image(token, stamp_id).await
+    }
+
+    async fn get_user(&self, token: &str, user_id: &Uuid) -> Result<User, TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.get_user(token, user_id).await
+    }
+
+    async fn get_user_icon(
+        &self,
+        token: &str,
+        user_id: &Uuid,
+    ) -> Result<(Vec<u8>, String), TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.get_user_icon(token, user_id).await
+    }
+
+    async fn add_message_stamp(
+        &self,
+        token: &str,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+        count: i32,
+    ) -> Result<(), TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner
+            .add_message_stamp(token, message_id, stamp_id, count)
+            .await
+    }
+
+    async fn remove_message_stamp(
+        &self,
+        token: &str,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+    ) -> Result<(), TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner
+            .remove_message_stamp(token, message_id, stamp_id)
+            .await
+    }
+
+    async fn get_message(
+        &self,
+        token: &str,
+        message_id: &Uuid,
+    ) -> Result<Message, TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.get_message(token, message_id).await
+    }
+
+    async fn get_channel_messages(
+        &self,
+        token: &str,
+        channel_id: &Uuid,
+        before: Option<OffsetDateTime>,
+        limit: i32,
+    ) -> Result<Vec<Message>, TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner
+            .get_channel_messages(token, channel_id, before, limit)
+            .await
+    }
+
+    async fn post_message(
+        &self,
+        token: &str,
+        channel_id: &Uuid,
+        content: &str,
+    ) -> Result<(), TraqClientError> {
+        self.budget.acquire(self.priority).await;
+        self.inner.post_message(token, channel_id, content).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn background_calls_leave_the_reserve() {
+        // Practically never refilled during the test
+        let bucket = LocalTokenBucket::new(0.001, 4.0);
+
+        assert!(bucket.try_take(2.0).await.is_ok());
+        assert!(bucket.try_take(2.0).await.is_ok());
+        assert!(bucket.try_take(2.0).await.is_err());
+
+        assert!(bucket.try_take(0.0).await.is_ok());
+        assert!(bucket.try_take(0.0).await.is_ok());
+        assert!(bucket.try_take(0.0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn waits_until_enough_tokens_are_refilled() {
+        let bucket = LocalTokenBucket::new(1.0, 1.0);
+        bucket.try_take(0.0).await.unwrap();
+
+        let wait = bucket.try_take(0.0).await.unwrap_err();
+        assert!(wait <= Duration::from_secs(1), "{wait:?}");
+        assert!(wait > Duration::from_millis(900), "{wait:?}");
+    }
+
+    #[tokio::test]
+    async fn interactive_calls_use_the_reserve() {
+        let budget = TraqBudget::new(Arc::new(LocalTokenBucket::new(0.001, 2.0)), 2.0);
+
+        budget.acquire(TraqPriority::Interactive).await;
+        budget.acquire(TraqPriority::Interactive).await;
+    }
+}
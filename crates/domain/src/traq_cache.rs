@@ -0,0 +1,459 @@
+//! Caching of idempotent traQ reads with stale-while-revalidate semantics.
+//!
+//! Users, stamps and channels change rarely but are looked up on almost every request, so they are
+//! served from memory for a while. Once an entry goes stale it is still served while it is fetched
+//! again in the background, and concurrent misses of the same key wait for a single fetch instead
+//! of all asking traQ at once.
+
+use crate::{
+    error::TraqClientError,
+    id::{MessageId, StampId},
+    model::{Channel, Message, Stamp, User},
+    traq_client::TraqClient,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use tokio::sync::Mutex as AsyncMutex;
+use uuid::Uuid;
+
+/// How long cached traQ reads are served without asking traQ again.
+const FRESH_FOR: Duration = Duration::from_secs(10 * 60);
+/// How long stale traQ reads are still served while they are fetched again. Older entries are
+/// fetched before responding, as they may be outdated enough to confuse users.
+const STALE_FOR: Duration = Duration::from_secs(24 * 60 * 60);
+/// How many keys each cache holds by default. Far more than the users, stamps or channels of a
+/// traQ instance, while bounding the memory taken by lookups of IDs that don't exist.
+const CAPACITY: usize = 100_000;
+
+/// An in-memory cache serving stale values while they are fetched again in the background.
+pub struct SwrCache<K, V> {
+    fresh_for: Duration,
+    stale_for: Duration,
+    capacity: usize,
+    slots: Arc<Mutex<HashMap<K, Slot<V>>>>,
+}
+
+struct Slot<V> {
+    value: Option<(V, Instant)>,
+    /// Held while the value is fetched, so that each key is only fetched once at a time.
+    fetching: Arc<AsyncMutex<()>>,
+}
+
+impl<V> Slot<V> {
+    fn is_fetching(&self) -> bool {
+        Arc::strong_count(&self.fetching) > 1
+    }
+}
+
+impl<V> Default for Slot<V> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            fetching: Arc::default(),
+        }
+    }
+}
+
+enum Lookup<V> {
+    Fresh(V),
+    Stale(V),
+    Missing,
+}
+
+impl<K, V> SwrCache<K, V>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static,
+{
+    /// Serves values without fetching them for `fresh_for`, and then for another `stale_for` while
+    /// they are fetched again.
+    pub fn new(fresh_for: Duration, stale_for: Duration) -> Self {
+        Self {
+            fresh_for,
+            stale_for,
+            capacity: CAPACITY,
+            slots: Arc::default(),
+        }
+    }
+
+    /// Holds at most `capacity` keys, evicting expired values first and then the oldest ones.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Returns the cached value of `key`, calling `fetch` if it is missing or stale.
+    /// Errors are not cached, so the next call fetches again.
+    pub async fn get<F, Fut>(&self, key: K, fetch: F) -> Result<V, TraqClientError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, TraqClientError>> + Send + 'static,
+    {
+        match self.lookup(&key) {
+            Lookup::Fresh(value) => return Ok(value),
+            Lookup::Stale(value) => {
+                self.refresh_in_background(key, fetch());
+                return Ok(value);
+            }
+            Lookup::Missing => {}
+        }
+
+        let fetching = self.fetching(&key);
+        let _guard = fetching.lock().await;
+        // Another request may have fetched it while this one waited
+        if let Lookup::Fresh(value) | Lookup::Stale(value) = self.lookup(&key) {
+            return Ok(value);
+        }
+
+        match fetch().await {
+            Ok(value) => {
+                store(&self.slots, key, value.clone());
+                Ok(value)
+            }
+            Err(e) => {
+                // Nothing can be served from the slot, so don't keep it for keys that may never
+                // exist
+                let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+                slots.remove(&key);
+                Err(e)
+            }
+        }
+    }
+
+    fn lookup(&self, key: &K) -> Lookup<V> {
+        let slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((value, fetched_at)) = slots.get(key).and_then(|slot| slot.value.as_ref()) else {
+            return Lookup::Missing;
+        };
+
+        let age = fetched_at.elapsed();
+        if age < self.fresh_for {
+            Lookup::Fresh(value.clone())
+        } else if age < self.fresh_for + self.stale_for {
+            Lookup::Stale(value.clone())
+        } else {
+            Lookup::Missing
+        }
+    }
+
+    fn fetching(&self, key: &K) -> Arc<AsyncMutex<()>> {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        if !slots.contains_key(key) && slots.len() >= self.capacity {
+            self.evict(&mut slots);
+        }
+        slots.entry(key.clone()).or_default().fetching.clone()
+    }
+
+    /// Makes room for a key by dropping the values too old to be served, or the oldest value if
+    /// there are none. Slots being fetched are kept.
+    fn evict(&self, slots: &mut HashMap<K, Slot<V>>) {
+        let max_age = self.fresh_for + self.stale_for;
+        slots.retain(|_, slot| {
+            slot.is_fetching()
+                || slot
+                    .value
+                    .as_ref()
+                    .is_some_and(|(_, fetched_at)| fetched_at.elapsed() < max_age)
+        });
+        if slots.len() < self.capacity {
+            return;
+        }
+
+        let oldest = slots
+            .iter()
+            .filter(|(_, slot)| !slot.is_fetching())
+            .min_by_key(|(_, slot)| slot.value.as_ref().map(|(_, fetched_at)| *fetched_at))
+            .map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            slots.remove(&oldest);
+        }
+    }
+
+    fn refresh_in_background<Fut>(&self, key: K, fetch: Fut)
+    where
+        Fut: Future<Output = Result<V, TraqClientError>> + Send + 'static,
+    {
+        // Someone is already fetching it
+        let Ok(guard) = self.fetching(&key).try_lock_owned() else {
+            return;
+        };
+
+        let slots = self.slots.clone();
+        tokio::spawn(async move {
+            let _guard = guard;
+            match fetch.await {
+                Ok(value) => store(&slots, key, value),
+                Err(e) => tracing::warn!("Failed to refresh a cached traQ read: {:?}", e),
+            }
+        });
+    }
+}
+
+fn store<K: Eq + Hash, V>(slots: &Mutex<HashMap<K, Slot<V>>>, key: K, value: V) {
+    let mut slots = slots.lock().unwrap_or_else(|e| e.into_inner());
+    slots.entry(key).or_default().value = Some((value, Instant::now()));
+}
+
+impl<K, V> Debug for SwrCache<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("SwrCache")
+            .field("fresh_for", &self.fresh_for)
+            .field("stale_for", &self.stale_for)
+            .field("len", &slots.len())
+            .finish()
+    }
+}
+
+/// Wraps a [`TraqClient`] to cache users, stamps and channels.
+///
+/// Entries are shared by every token, since these are visible to every traQ user alike.
+#[derive(Debug)]
+pub struct CachedTraqClient {
+    inner: Arc<dyn TraqClient>,
+    users: SwrCache<Uuid, User>,
+    stamps: SwrCache<Uuid, Stamp>,
+    channels: SwrCache<(), Vec<Channel>>,
+}
+
+impl CachedTraqClient {
+    pub fn new(inner: Arc<dyn TraqClient>) -> Self {
+        Self {
+            inner,
+            users: SwrCache::new(FRESH_FOR, STALE_FOR),
+            stamps: SwrCache::new(FRESH_FOR, STALE_FOR),
+            channels: SwrCache::new(FRESH_FOR, STALE_FOR),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TraqClient for CachedTraqClient {
+    async fn get_server_version(&self) -> Result<String, TraqClientError> {
+        self.inner.get_server_version().await
+    }
+
+    async fn fetch_messages_since(
+        &self,
+        token: &str,
+        since: OffsetDateTime,
+    ) -> Result<Vec<Message>, TraqClientError> {
+        self.inner.fetch_messages_since(token, since).await
+    }
+
+    async fn get_channels(&self, token: &str) -> Result<Vec<Channel>, TraqClientError> {
+        let inner = self.inner.clone();
+        let token = token.to_string();
+        self.channels
+            .get((), || async move { inner.get_channels(&token).await })
+            .await
+    }
+
+    async fn get_stamp(&self, token: &str, stamp_id: &Uuid) -> Result<Stamp, TraqClientError> {
+        let inner = self.inner.clone();
+        let token = token.to_string();
+        let stamp_id = *stamp_id;
+        self.stamps
+            .get(stamp_id, || async move {
+                inner.get_stamp(&token, &stamp_id).await
+            })
+            .await
+    }
+
+    async fn get_stamps(&self, token: &str) -> Result<Vec<Stamp>, TraqClientError> {
+        self.inner.get_stamps(token).await
+    }
+
+    async fn get_stamp_image(
+        &self,
+        token: &str,
+        stamp_id: &Uuid,
+    ) -> Result<(Vec<u8>, String), TraqClientError> {
+        self.inner.get_stamp_image(token, stamp_id).await
+    }
+
+    async fn get_user(&self, token: &str, user_id: &Uuid) -> Result<User, TraqClientError> {
+        let inner = self.inner.clone();
+        let token = token.to_string();
+        let user_id = *user_id;
+        self.users
+            .get(
+                user_id,
+                || async move { inner.get_user(&token, &user_id).await },
+            )
+            .await
+    }
+
+    async fn get_user_icon(
+        &self,
+        token: &str,
+        user_id: &Uuid,
+    ) -> Result<(Vec<u8>, String), TraqClientError> {
+        self.inner.get_user_icon(token, user_id).await
+    }
+
+    async fn add_message_stamp(
+        &self,
+        token: &str,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+        count: i32,
+    ) -> Result<(), TraqClientError> {
+        self.inner
+            .add_message_stamp(token, message_id, stamp_id, count)
+            .await
+    }
+
+    async fn remove_message_stamp(
+        &self,
+        token: &str,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+    ) -> Result<(), TraqClientError> {
+        self.inner
+            .remove_message_stamp(token, message_id, stamp_id)
+            .await
+    }
+
+    async fn get_message(
+        &self,
+        token: &str,
+        message_id: &Uuid,
+    ) -> Result<Message, TraqClientError> {
+        self.inner.get_message(token, message_id).await
+    }
+
+    async fn get_channel_messages(
+        &self,
+        token: &str,
+        channel_id: &Uuid,
+        before: Option<OffsetDateTime>,
+        limit: i32,
+    ) -> Result<Vec<Message>, TraqClientError> {
+        self.inner
+            .get_channel_messages(token, channel_id, before, limit)
+            .await
+    }
+
+    async fn post_message(
+        &self,
+        token: &str,
+        channel_id: &Uuid,
+        content: &str,
+    ) -> Result<(), TraqClientError> {
+        self.inner.post_message(token, channel_id, content).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_factories::UserBuilder, traq_client::MockTraqClient};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::task::yield_now;
+
+    #[tokio::test]
+    async fn concurrent_misses_are_fetched_once() {
+        let cache = SwrCache::new(FRESH_FOR, STALE_FOR);
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let fetch = || {
+            let fetches = fetches.clone();
+            async move {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                yield_now().await;
+                Ok(1)
+            }
+        };
+
+        let (a, b) = tokio::join!(cache.get("key", fetch), cache.get("key", fetch));
+
+        assert_eq!((a.unwrap(), b.unwrap()), (1, 1));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn stale_values_are_served_while_refreshed() {
+        let cache = SwrCache::new(Duration::ZERO, STALE_FOR);
+        cache.get("key", || async { Ok(1) }).await.unwrap();
+
+        let value = cache.get("key", || async { Ok(2) }).await.unwrap();
+        assert_eq!(value, 1);
+
+        // Let the refresh finish
+        yield_now().await;
+        let value = cache.get("key", || async { Ok(3) }).await.unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[tokio::test]
+    async fn expired_values_are_fetched_before_responding() {
+        let cache = SwrCache::new(Duration::ZERO, Duration::ZERO);
+        cache.get("key", || async { Ok(1) }).await.unwrap();
+
+        let value = cache.get("key", || async { Ok(2) }).await.unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let cache = SwrCache::new(FRESH_FOR, STALE_FOR);
+        let err = cache
+            .get("key", || async {
+                Err(TraqClientError::HttpRequest("unreachable".to_string()))
+            })
+            .await;
+        assert!(err.is_err());
+
+        let value = cache.get("key", || async { Ok(1) }).await.unwrap();
+        assert_eq!(value, 1);
+    }
+
+    #[tokio::test]
+    async fn failed_keys_are_forgotten() {
+        let cache: SwrCache<&str, i32> = SwrCache::new(FRESH_FOR, STALE_FOR);
+        for key in ["a", "b"] {
+            let err = cache
+                .get(key, || async {
+                    Err(TraqClientError::HttpRequest("not found".to_string()))
+                })
+                .await;
+            assert!(err.is_err());
+        }
+
+        assert!(cache.slots.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn oldest_values_are_evicted_beyond_capacity() {
+        let cache = SwrCache::new(FRESH_FOR, STALE_FOR).with_capacity(2);
+        for (key, value) in [("a", 1), ("b", 2), ("c", 3)] {
+            cache.get(key, || async move { Ok(value) }).await.unwrap();
+        }
+
+        let slots = cache.slots.lock().unwrap();
+        assert_eq!(slots.len(), 2);
+        assert!(!slots.contains_key("a"));
+    }
+
+    #[tokio::test]
+    async fn users_are_cached_across_tokens() {
+        let user = UserBuilder::new().build();
+        let mut mock_client = MockTraqClient::new();
+        let returned = user.clone();
+        mock_client
+            .expect_get_user()
+            .times(1)
+            .returning(move |_, _| Ok(returned.clone()));
+        let client = CachedTraqClient::new(Arc::new(mock_client));
+
+        assert_eq!(client.get_user("token_a", &user.id).await.unwrap(), user);
+        assert_eq!(client.get_user("token_b", &user.id).await.unwrap(), user);
+    }
+}
@@ -1,18 +1,25 @@
-use crate::error::TraqClientError;
+use crate::{
+    error::TraqClientError,
+    id::{MessageId, StampId},
+};
 use std::fmt::Debug;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-use crate::model::{Message, Stamp, User};
+use crate::model::{Channel, Message, Stamp, User};
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
 pub trait TraqClient: Debug + Send + Sync {
+    /// Returns the version of the traQ server. No token is required.
+    async fn get_server_version(&self) -> Result<String, TraqClientError>;
     async fn fetch_messages_since(
         &self,
         token: &str,
         since: OffsetDateTime,
     ) -> Result<Vec<Message>, TraqClientError>;
+    /// Returns every public channel, with paths resolved.
+    async fn get_channels(&self, token: &str) -> Result<Vec<Channel>, TraqClientError>;
     async fn get_stamp(&self, token: &str, stamp_id: &Uuid) -> Result<Stamp, TraqClientError>;
     async fn get_stamps(&self, token: &str) -> Result<Vec<Stamp>, TraqClientError>;
     async fn get_stamp_image(
@@ -30,16 +37,30 @@ pub trait TraqClient: Debug + Send + Sync {
     async fn add_message_stamp(
         &self,
         token: &str,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
+        message_id: &MessageId,
+        stamp_id: &StampId,
         count: i32,
     ) -> Result<(), TraqClientError>;
     async fn remove_message_stamp(
         &self,
         token: &str,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
+        message_id: &MessageId,
+        stamp_id: &StampId,
     ) -> Result<(), TraqClientError>;
     async fn get_message(&self, token: &str, message_id: &Uuid)
     -> Result<Message, TraqClientError>;
+    /// Returns up to `limit` messages of a channel posted before `before`, newest first.
+    async fn get_channel_messages(
+        &self,
+        token: &str,
+        channel_id: &Uuid,
+        before: Option<OffsetDateTime>,
+        limit: i32,
+    ) -> Result<Vec<Message>, TraqClientError>;
+    async fn post_message(
+        &self,
+        token: &str,
+        channel_id: &Uuid,
+        content: &str,
+    ) -> Result<(), TraqClientError>;
 }
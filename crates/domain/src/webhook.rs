@@ -0,0 +1,176 @@
+//! Outbound webhooks registered by admins, sent the changes to messages found while crawling.
+
+use crate::{
+    error::WebhookError,
+    event::MessageDelta,
+    model::{Message, Webhook, WebhookEvent},
+    notifier::MessageNotifier,
+    repository::WebhookRepository,
+};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{fmt::Debug, sync::Arc};
+use tokio::task::JoinSet;
+
+/// The body of a webhook request.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    /// The events the change consists of, among those the webhook is subscribed to.
+    pub events: Vec<WebhookEvent>,
+    pub message: Message,
+    pub delta: MessageDelta,
+}
+
+/// Sends signed requests to webhooks.
+#[cfg_attr(any(test, feature = "test-utils"), mockall::automock)]
+#[async_trait]
+pub trait WebhookSender: Debug + Send + Sync {
+    /// Posts the payload to the webhook, signed with `secret`.
+    async fn send(
+        &self,
+        webhook: &Webhook,
+        secret: &str,
+        payload: &WebhookPayload,
+    ) -> Result<(), WebhookError>;
+}
+
+/// Sends every change to the webhooks subscribed to it. Webhooks are looked up on every change,
+/// so that registering or deleting one takes effect immediately.
+#[derive(Debug)]
+pub struct WebhookNotifier {
+    sender: Arc<dyn WebhookSender>,
+    repo: Arc<dyn WebhookRepository>,
+}
+
+impl WebhookNotifier {
+    pub fn new(sender: Arc<dyn WebhookSender>, repo: Arc<dyn WebhookRepository>) -> Self {
+        Self { sender, repo }
+    }
+}
+
+fn events_of(delta: &MessageDelta) -> Vec<WebhookEvent> {
+    let mut events = vec![];
+    if delta.content.is_some() {
+        events.push(WebhookEvent::MessageEdited);
+    }
+    if delta.reactions.is_some() {
+        events.push(WebhookEvent::ReactionsChanged);
+    }
+    events
+}
+
+#[async_trait]
+impl MessageNotifier for WebhookNotifier {
+    async fn notify_message_updated(&self, message: &Message, delta: &MessageDelta) {
+        let events = events_of(delta);
+        if events.is_empty() {
+            return;
+        }
+        let webhooks = match self.repo.find_all_with_secrets().await {
+            Ok(webhooks) => webhooks,
+            Err(e) => {
+                tracing::warn!("Failed to find webhooks: {:?}", e);
+                return;
+            }
+        };
+
+        // Sent concurrently, so that a slow webhook delays the others by at most its timeout
+        let mut deliveries = JoinSet::new();
+        for (webhook, secret) in webhooks {
+            let events: Vec<WebhookEvent> = events
+                .iter()
+                .filter(|event| webhook.events.contains(event))
+                .copied()
+                .collect();
+            if events.is_empty() {
+                continue;
+            }
+
+            let payload = WebhookPayload {
+                events,
+                message: message.clone(),
+                delta: delta.clone(),
+            };
+            let sender = self.sender.clone();
+            deliveries.spawn(async move {
+                if let Err(e) = sender.send(&webhook, &secret, &payload).await {
+                    tracing::warn!("Failed to send webhook {}: {:?}", webhook.id, e);
+                }
+            });
+        }
+        deliveries.join_all().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        repository::MockWebhookRepository,
+        test_factories::{MessageBuilder, ReactionBuilder},
+    };
+    use fake::{Fake, uuid::UUIDv4};
+    use time::OffsetDateTime;
+
+    fn webhook(id: i64, events: Vec<WebhookEvent>) -> (Webhook, String) {
+        let webhook = Webhook {
+            id,
+            url: format!("https://hooks.example.com/{id}"),
+            events,
+            created_by: UUIDv4.fake(),
+            created_at: OffsetDateTime::now_utc(),
+        };
+        (webhook, "secret".to_string())
+    }
+
+    #[tokio::test]
+    async fn changes_are_sent_to_subscribed_webhooks() {
+        let previous = MessageBuilder::new().build();
+        let current = Message {
+            reactions: vec![ReactionBuilder::new().build()],
+            ..previous.clone()
+        };
+        let delta = MessageDelta::between(&previous, &current);
+
+        let mut mock_repo = MockWebhookRepository::new();
+        mock_repo.expect_find_all_with_secrets().returning(|| {
+            Ok(vec![
+                webhook(1, vec![WebhookEvent::MessageEdited]),
+                webhook(
+                    2,
+                    vec![WebhookEvent::MessageEdited, WebhookEvent::ReactionsChanged],
+                ),
+            ])
+        });
+        let mut mock_sender = MockWebhookSender::new();
+        let expected = WebhookPayload {
+            events: vec![WebhookEvent::ReactionsChanged],
+            message: current.clone(),
+            delta: delta.clone(),
+        };
+        mock_sender
+            .expect_send()
+            .withf(move |webhook, secret, payload| {
+                webhook.id == 2 && secret == "secret" && *payload == expected
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let notifier = WebhookNotifier::new(Arc::new(mock_sender), Arc::new(mock_repo));
+        notifier.notify_message_updated(&current, &delta).await;
+    }
+
+    #[tokio::test]
+    async fn unchanged_messages_are_not_sent() {
+        let message = MessageBuilder::new().build();
+        let delta = MessageDelta::between(&message, &message);
+
+        // Webhooks are not even looked up
+        let notifier = WebhookNotifier::new(
+            Arc::new(MockWebhookSender::new()),
+            Arc::new(MockWebhookRepository::new()),
+        );
+        notifier.notify_message_updated(&message, &delta).await;
+    }
+}
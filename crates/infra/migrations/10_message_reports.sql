@@ -0,0 +1,13 @@
+-- message_id has no foreign key so that reports outlive pruned messages.
+CREATE TABLE message_reports (
+  user_id BINARY(16) NOT NULL, -- UUID
+  message_id BINARY(16) NOT NULL, -- UUID
+  reason VARCHAR(32) NOT NULL,
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+  resolved_at TIMESTAMP(6) NULL,
+
+  PRIMARY KEY (user_id, message_id),
+  INDEX idx_message_reports_message_id (message_id),
+  CONSTRAINT fk_message_reports_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
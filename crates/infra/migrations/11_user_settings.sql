@@ -0,0 +1,11 @@
+-- A step is completed once its timestamp is set.
+CREATE TABLE user_settings (
+  user_id BINARY(16) NOT NULL, -- UUID
+  privacy_notice_accepted_at TIMESTAMP(6) NULL,
+  crawl_consent_granted_at TIMESTAMP(6) NULL,
+  initial_channels_picked_at TIMESTAMP(6) NULL,
+
+  PRIMARY KEY (user_id),
+  CONSTRAINT fk_user_settings_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
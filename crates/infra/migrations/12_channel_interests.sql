@@ -0,0 +1,9 @@
+CREATE TABLE channel_interests (
+  user_id BINARY(16) NOT NULL, -- UUID
+  channel_id BINARY(16) NOT NULL, -- UUID
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, channel_id),
+  CONSTRAINT fk_channel_interests_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,15 @@
+-- Recommended messages served to each user, for measuring click-through rates.
+CREATE TABLE impressions (
+  user_id BINARY(16) NOT NULL, -- UUID
+  message_id BINARY(16) NOT NULL, -- UUID
+  impression_count INT NOT NULL DEFAULT 1,
+  first_served_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+  last_served_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, message_id),
+  INDEX idx_last_served_at (last_served_at),
+  CONSTRAINT fk_impressions_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT fk_impressions_message FOREIGN KEY (message_id)
+    REFERENCES messages(id) ON DELETE CASCADE
+);
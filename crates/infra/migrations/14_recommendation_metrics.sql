@@ -0,0 +1,14 @@
+-- The recommendation reason of the first impression, NULL outside of the recommended timeline.
+ALTER TABLE impressions
+  ADD COLUMN reason VARCHAR(32) NULL AFTER message_id;
+
+CREATE TABLE recommendation_metrics (
+  date DATE NOT NULL,
+  reason VARCHAR(32) NOT NULL,
+  impression_count BIGINT NOT NULL,
+  read_count BIGINT NOT NULL,
+  reaction_count BIGINT NOT NULL,
+  computed_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (date, reason)
+);
@@ -0,0 +1,9 @@
+-- Embeddings of message contents, for recommending messages with similar content.
+CREATE TABLE message_embeddings (
+  message_id BINARY(16) NOT NULL PRIMARY KEY, -- UUID
+  embedding BLOB NOT NULL, -- little-endian f32 values
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  CONSTRAINT fk_message_embeddings_message FOREIGN KEY (message_id)
+    REFERENCES messages(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- Used to search messages when no dedicated search engine is configured.
+-- The built-in parser only splits words on whitespace and punctuation, so Japanese text without
+-- spaces is indexed as whole sentences.
+ALTER TABLE messages ADD FULLTEXT INDEX ft_messages_content (content);
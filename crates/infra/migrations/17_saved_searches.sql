@@ -0,0 +1,11 @@
+CREATE TABLE saved_searches (
+  id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  user_id BINARY(16) NOT NULL, -- UUID
+  name VARCHAR(64) NOT NULL,
+  query VARCHAR(256) NOT NULL,
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  INDEX idx_saved_searches_user_id (user_id),
+  CONSTRAINT fk_saved_searches_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
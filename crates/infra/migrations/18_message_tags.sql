@@ -0,0 +1,13 @@
+-- Hashtags extracted from message contents when they are crawled.
+CREATE TABLE message_tags (
+  message_id BINARY(16) NOT NULL, -- UUID
+  tag VARCHAR(64) NOT NULL,
+  -- Copied from the message so that trends can be computed without joining messages
+  created_at TIMESTAMP(6) NOT NULL,
+
+  PRIMARY KEY (message_id, tag),
+  INDEX idx_message_tags_tag (tag, created_at DESC),
+  INDEX idx_message_tags_created_at (created_at),
+  CONSTRAINT fk_message_tags_message FOREIGN KEY (message_id)
+    REFERENCES messages(id) ON DELETE CASCADE
+);
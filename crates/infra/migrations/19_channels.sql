@@ -0,0 +1,9 @@
+-- A copy of traQ's public channels, synced periodically.
+CREATE TABLE channels (
+  id BINARY(16) NOT NULL PRIMARY KEY, -- UUID
+  -- traQ's channel names are at most 20 characters, but paths have no limit
+  name VARCHAR(20) NOT NULL,
+  path TEXT NOT NULL,
+  archived BOOLEAN NOT NULL,
+  synced_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6)
+);
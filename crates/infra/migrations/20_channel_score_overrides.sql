@@ -0,0 +1,11 @@
+-- Multipliers users apply to the scores of messages recommended from a channel.
+CREATE TABLE channel_score_overrides (
+  user_id BINARY(16) NOT NULL, -- UUID
+  channel_id BINARY(16) NOT NULL, -- UUID
+  multiplier DOUBLE NOT NULL,
+  updated_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, channel_id),
+  CONSTRAINT fk_channel_score_overrides_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,18 @@
+CREATE TABLE announcements (
+  id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  content TEXT NOT NULL,
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+  withdrawn_at TIMESTAMP(6) NULL
+);
+
+CREATE TABLE announcement_dismissals (
+  user_id BINARY(16) NOT NULL, -- UUID
+  announcement_id BIGINT NOT NULL,
+  dismissed_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, announcement_id),
+  CONSTRAINT fk_announcement_dismissals_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT fk_announcement_dismissals_announcement FOREIGN KEY (announcement_id)
+    REFERENCES announcements(id) ON DELETE CASCADE
+);
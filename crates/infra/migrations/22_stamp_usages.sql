@@ -0,0 +1,12 @@
+-- stamp_id has no foreign key, since stamps are cached lazily.
+CREATE TABLE stamp_usages (
+  user_id BINARY(16) NOT NULL, -- UUID
+  stamp_id BINARY(16) NOT NULL, -- UUID
+  use_count INT NOT NULL DEFAULT 1,
+  last_used_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, stamp_id),
+  INDEX idx_stamp_usages_user_last_used_at (user_id, last_used_at),
+  CONSTRAINT fk_stamp_usages_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+-- Profiles count the messages of a user and the reactions to their recent messages.
+ALTER TABLE messages ADD INDEX idx_messages_user_id_created_at (user_id, created_at);
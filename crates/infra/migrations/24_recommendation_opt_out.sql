@@ -0,0 +1,5 @@
+-- Users can keep their messages from being recommended to users they have not interacted with,
+-- which is checked through the reactions they added.
+ALTER TABLE user_settings
+  ADD COLUMN recommend_only_to_acquaintances BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE reactions ADD INDEX idx_reactions_user_id (user_id);
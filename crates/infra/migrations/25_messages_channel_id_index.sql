@@ -0,0 +1,2 @@
+-- Channel pages are read from the cache newest first.
+ALTER TABLE messages ADD INDEX idx_messages_channel_id_created_at (channel_id, created_at);
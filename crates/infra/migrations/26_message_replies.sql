@@ -0,0 +1,16 @@
+-- Messages citing another message reply to the first one they cite, which links them into threads.
+ALTER TABLE messages
+  ADD COLUMN reply_to_message_id BINARY(16) NULL, -- UUID
+  ADD INDEX idx_messages_reply_to_message_id (reply_to_message_id);
+
+-- Cached messages are re-crawled only for a day, so older ones are backfilled here.
+-- Unlike the crawler, this doesn't skip links in code blocks.
+UPDATE messages
+SET reply_to_message_id = UNHEX(REPLACE(RIGHT(
+  REGEXP_SUBSTR(
+    content,
+    'https?://[^[:space:]]*/messages/[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}'
+  ),
+  36
+), '-', ''))
+WHERE content LIKE '%/messages/%';
@@ -0,0 +1,10 @@
+-- OpenGraph metadata of pages linked from messages. Pages without metadata are cached with every
+-- field NULL, so that they aren't fetched on every request.
+CREATE TABLE link_previews (
+  url_hash BINARY(32) NOT NULL PRIMARY KEY, -- SHA-256 of url, which is too long for a key
+  url TEXT NOT NULL,
+  title TEXT NULL,
+  description TEXT NULL,
+  image_url TEXT NULL,
+  fetched_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6)
+);
@@ -0,0 +1,10 @@
+-- Resized copies of images proxied from traQ
+CREATE TABLE image_cache (
+  kind VARCHAR(16) NOT NULL,
+  id BINARY(16) NOT NULL,
+  size VARCHAR(16) NOT NULL,
+  content_type VARCHAR(255) NOT NULL,
+  data MEDIUMBLOB NOT NULL,
+  stored_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+  PRIMARY KEY (kind, id, size)
+);
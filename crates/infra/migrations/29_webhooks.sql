@@ -0,0 +1,10 @@
+-- Outbound webhooks registered by admins
+CREATE TABLE webhooks (
+  id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  url VARCHAR(2048) NOT NULL,
+  -- Stored as is, since requests are signed with it
+  secret VARCHAR(255) NOT NULL,
+  events VARCHAR(255) NOT NULL, -- comma-separated
+  created_by BINARY(16) NOT NULL, -- UUID
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6)
+);
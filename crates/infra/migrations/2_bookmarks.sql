@@ -0,0 +1,15 @@
+-- Unlike read_messages, deleting a message does not cascade here.
+-- Bookmarked messages must be kept even when old messages are pruned from the cache,
+-- so any cleanup of the messages table has to skip bookmarked ones.
+CREATE TABLE bookmarks (
+  user_id BINARY(16) NOT NULL, -- UUID
+  message_id BINARY(16) NOT NULL, -- UUID
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, message_id),
+  INDEX idx_user_created_at (user_id, created_at DESC),
+  CONSTRAINT fk_bookmarks_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT fk_bookmarks_message FOREIGN KEY (message_id)
+    REFERENCES messages(id) ON DELETE RESTRICT
+);
@@ -0,0 +1,8 @@
+-- When each session was last active, from the heartbeats sent by clients
+CREATE TABLE session_presence (
+  session_id VARCHAR(128) NOT NULL PRIMARY KEY,
+  user_id BINARY(16) NOT NULL, -- UUID
+  last_seen_at TIMESTAMP(6) NOT NULL,
+  INDEX idx_session_presence_user_id_last_seen_at (user_id, last_seen_at),
+  INDEX idx_session_presence_last_seen_at (last_seen_at)
+);
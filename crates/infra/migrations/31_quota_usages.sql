@@ -0,0 +1,9 @@
+-- How many times each user used each operation with a daily quota, per UTC day
+CREATE TABLE quota_usages (
+  user_id BINARY(16) NOT NULL, -- UUID
+  operation VARCHAR(32) NOT NULL,
+  day DATE NOT NULL,
+  used BIGINT NOT NULL,
+  PRIMARY KEY (user_id, day, operation),
+  INDEX idx_quota_usages_day (day)
+);
@@ -0,0 +1,4 @@
+-- Users can allow the content of their popular messages to be published in the highlights shared
+-- with peer instances
+ALTER TABLE user_settings
+  ADD COLUMN share_content_in_highlights BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,9 @@
+-- Read state rolled up from read_messages: every message created before read_before counts as
+-- read by the user, and read_messages only lists the reads of newer messages
+CREATE TABLE read_horizons (
+  user_id BINARY(16) NOT NULL PRIMARY KEY, -- UUID
+  read_before TIMESTAMP(6) NOT NULL,
+
+  CONSTRAINT fk_read_horizons_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
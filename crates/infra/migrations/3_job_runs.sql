@@ -0,0 +1,10 @@
+CREATE TABLE job_runs (
+  id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  job_name VARCHAR(64) NOT NULL,
+  started_at TIMESTAMP(6) NOT NULL,
+  duration_ms BIGINT NOT NULL,
+  succeeded BOOLEAN NOT NULL,
+  error TEXT,
+
+  INDEX idx_started_at (started_at DESC)
+);
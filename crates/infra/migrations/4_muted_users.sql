@@ -0,0 +1,10 @@
+-- muted_user_id has no foreign key as the muted user may not be cached in the users table.
+CREATE TABLE muted_users (
+  user_id BINARY(16) NOT NULL, -- UUID
+  muted_user_id BINARY(16) NOT NULL, -- UUID
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, muted_user_id),
+  CONSTRAINT fk_muted_users_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
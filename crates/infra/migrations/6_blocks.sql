@@ -0,0 +1,11 @@
+-- blocked_user_id has no foreign key as the blocked user may not be cached in the users table.
+CREATE TABLE blocks (
+  user_id BINARY(16) NOT NULL, -- UUID
+  blocked_user_id BINARY(16) NOT NULL, -- UUID
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, blocked_user_id),
+  INDEX idx_blocks_blocked_user_id (blocked_user_id),
+  CONSTRAINT fk_blocks_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- followed_user_id has no foreign key as the followed user may not be cached in the users table.
+CREATE TABLE follows (
+  user_id BINARY(16) NOT NULL, -- UUID
+  followed_user_id BINARY(16) NOT NULL, -- UUID
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, followed_user_id),
+  CONSTRAINT fk_follows_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
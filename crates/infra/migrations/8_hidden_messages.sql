@@ -0,0 +1,12 @@
+-- The author and channel are copied from the message so that feedback outlives pruned messages.
+CREATE TABLE hidden_messages (
+  user_id BINARY(16) NOT NULL, -- UUID
+  message_id BINARY(16) NOT NULL, -- UUID
+  author_id BINARY(16) NOT NULL, -- UUID
+  channel_id BINARY(16) NOT NULL, -- UUID
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (user_id, message_id),
+  CONSTRAINT fk_hidden_messages_user FOREIGN KEY (user_id)
+    REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,9 @@
+-- An append-only log of message changes used for delta sync.
+CREATE TABLE message_events (
+  id BIGINT NOT NULL AUTO_INCREMENT,
+  message_id BINARY(16) NOT NULL, -- UUID
+  kind VARCHAR(16) NOT NULL,
+  created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+  PRIMARY KEY (id)
+);
@@ -0,0 +1,460 @@
+//! Copies a database into another with personal data removed, so that recommendation changes can
+//! be tested on a staging instance with realistic data volumes.
+//!
+//! - Handles and display names are replaced with pseudonyms derived from the handle and a salt.
+//! - Message contents are scrambled character by character, keeping their length, whitespace and
+//!   punctuation.
+//! - Access tokens and saved search queries are dropped, as are hashtags and embeddings, which
+//!   reveal contents.
+//!
+//! IDs are kept, so that staging users can log in with their traQ accounts and see timelines
+//! built from the same interactions as in production.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::{
+    Encode, FromRow, MySql, MySqlPool, QueryBuilder, Type, migrate::MigrateError, mysql::MySqlRow,
+    query_builder::Separated,
+};
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+/// The number of rows copied per query.
+const BATCH_SIZE: i64 = 1000;
+
+/// Every table cleared in the target before copying, children first. Tokens, tags, embeddings,
+/// message events, saved searches and announcements are cleared but not copied.
+const TARGET_TABLES: &[&str] = &[
+    "announcement_dismissals",
+    "announcements",
+    "saved_searches",
+    "message_tags",
+    "message_embeddings",
+    "message_events",
+    "user_tokens",
+    "recommendation_metrics",
+    "impressions",
+    "stamp_usages",
+    "channel_score_overrides",
+    "channel_interests",
+    "user_settings",
+    "message_reports",
+    "hidden_messages",
+    "muted_channels",
+    "muted_users",
+    "blocks",
+    "follows",
+    "bookmarks",
+    "read_horizons",
+    "read_messages",
+    "reactions",
+    "messages",
+    "stamps",
+    "channels",
+    "users",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum AnonymizeError {
+    #[error("the source and target are the same database")]
+    SameDatabase,
+
+    #[error("failed to migrate the target database: {0}")]
+    Migrate(#[from] MigrateError),
+
+    #[error("failed to copy `{table}`: {source}")]
+    Copy {
+        table: &'static str,
+        source: sqlx::Error,
+    },
+
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// The number of rows copied for each table.
+pub type AnonymizeSummary = Vec<(&'static str, u64)>;
+
+/// Replaces the data in `target` with an anonymized copy of `source`.
+///
+/// The same salt yields the same pseudonyms, so a stable salt keeps handles recognizable across
+/// refreshes of the staging data.
+pub async fn anonymize(
+    source: &MySqlPool,
+    target: &MySqlPool,
+    salt: &str,
+) -> Result<AnonymizeSummary, AnonymizeError> {
+    type Ts = OffsetDateTime;
+
+    if database_name(source).await? == database_name(target).await? {
+        return Err(AnonymizeError::SameDatabase);
+    }
+
+    sqlx::migrate!().run(target).await?;
+
+    for &table in TARGET_TABLES {
+        sqlx::query(&format!("DELETE FROM {table}"))
+            .execute(target)
+            .await
+            .map_err(|source| AnonymizeError::Copy { table, source })?;
+    }
+
+    let copy = TableCopy { source, target };
+    let mut summary = vec![];
+
+    // Parents are copied before their children to satisfy foreign keys
+    summary.push(
+        copy.rows(
+            "users",
+            &["id", "handle", "display_name"],
+            1,
+            |(id, handle, _): (Uuid, String, String)| {
+                let pseudonym = pseudonymize_handle(salt, &handle);
+                (id, pseudonym.clone(), pseudonym)
+            },
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows("stamps", &["id", "name"], 1, |row: (Uuid, String)| row)
+            .await?,
+    );
+    summary.push(
+        copy.rows(
+            "channels",
+            &["id", "name", "path", "archived", "synced_at"],
+            1,
+            |row: (Uuid, String, String, bool, Ts)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "messages",
+            &[
+                "id",
+                "user_id",
+                "channel_id",
+                "content",
+                "reply_to_message_id",
+                "created_at",
+                "updated_at",
+                "last_crawled_at",
+            ],
+            1,
+            |mut row: (Uuid, Uuid, Uuid, String, Option<Uuid>, Ts, Ts, Ts)| {
+                row.3 = scramble(&row.3, message_seed(salt, &row.0));
+                row
+            },
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "reactions",
+            &["message_id", "stamp_id", "user_id", "stamp_count"],
+            3,
+            |row: (Uuid, Uuid, Uuid, i32)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "read_messages",
+            &["user_id", "message_id", "read_at"],
+            2,
+            |row: (Uuid, Uuid, Ts)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "read_horizons",
+            &["user_id", "read_before"],
+            1,
+            |row: (Uuid, Ts)| row,
+        )
+        .await?,
+    );
+    for (table, other) in [
+        ("bookmarks", "message_id"),
+        ("follows", "followed_user_id"),
+        ("blocks", "blocked_user_id"),
+        ("muted_users", "muted_user_id"),
+        ("muted_channels", "channel_id"),
+        ("channel_interests", "channel_id"),
+    ] {
+        summary.push(
+            copy.rows(
+                table,
+                &["user_id", other, "created_at"],
+                2,
+                |row: (Uuid, Uuid, Ts)| row,
+            )
+            .await?,
+        );
+    }
+    summary.push(
+        copy.rows(
+            "channel_score_overrides",
+            &["user_id", "channel_id", "multiplier", "updated_at"],
+            2,
+            |row: (Uuid, Uuid, f64, Ts)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "stamp_usages",
+            &["user_id", "stamp_id", "use_count", "last_used_at"],
+            2,
+            |row: (Uuid, Uuid, i32, Ts)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "hidden_messages",
+            &[
+                "user_id",
+                "message_id",
+                "author_id",
+                "channel_id",
+                "created_at",
+            ],
+            2,
+            |row: (Uuid, Uuid, Uuid, Uuid, Ts)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "message_reports",
+            &[
+                "user_id",
+                "message_id",
+                "reason",
+                "created_at",
+                "resolved_at",
+            ],
+            2,
+            |row: (Uuid, Uuid, String, Ts, Option<Ts>)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "user_settings",
+            &[
+                "user_id",
+                "privacy_notice_accepted_at",
+                "crawl_consent_granted_at",
+                "initial_channels_picked_at",
+                "recommend_only_to_acquaintances",
+                "share_content_in_highlights",
+            ],
+            1,
+            |row: (Uuid, Option<Ts>, Option<Ts>, Option<Ts>, bool, bool)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "impressions",
+            &[
+                "user_id",
+                "message_id",
+                "reason",
+                "impression_count",
+                "first_served_at",
+                "last_served_at",
+            ],
+            2,
+            |row: (Uuid, Uuid, Option<String>, i32, Ts, Ts)| row,
+        )
+        .await?,
+    );
+    summary.push(
+        copy.rows(
+            "recommendation_metrics",
+            &[
+                "date",
+                "reason",
+                "impression_count",
+                "read_count",
+                "reaction_count",
+                "computed_at",
+            ],
+            2,
+            |row: (Date, String, i64, i64, i64, Ts)| row,
+        )
+        .await?,
+    );
+
+    Ok(summary)
+}
+
+async fn database_name(pool: &MySqlPool) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT CONCAT(@@hostname, ':', @@port, '/', DATABASE())")
+        .fetch_one(pool)
+        .await
+}
+
+struct TableCopy<'a> {
+    source: &'a MySqlPool,
+    target: &'a MySqlPool,
+}
+
+impl TableCopy<'_> {
+    /// Copies the columns in batches and returns the number of rows copied.
+    /// The first `key_len` columns must be the primary key, which the batches are ordered by.
+    async fn rows<R: CopyRow>(
+        &self,
+        table: &'static str,
+        columns: &[&str],
+        key_len: usize,
+        mut sanitize: impl FnMut(R) -> R,
+    ) -> Result<(&'static str, u64), AnonymizeError> {
+        let key = columns[..key_len].join(", ");
+        let columns = columns.join(", ");
+        let select = format!("SELECT {columns} FROM {table} ORDER BY {key} LIMIT ? OFFSET ?");
+        let mut copied = 0;
+
+        loop {
+            let rows: Vec<R> = sqlx::query_as(&select)
+                .bind(BATCH_SIZE)
+                .bind(copied)
+                .fetch_all(self.source)
+                .await
+                .map_err(|source| AnonymizeError::Copy { table, source })?;
+            if rows.is_empty() {
+                break;
+            }
+
+            let len = rows.len() as i64;
+            let mut query_builder = QueryBuilder::new(format!("INSERT INTO {table} ({columns}) "));
+            query_builder.push_values(rows, |mut separated, row| {
+                sanitize(row).push_binds(&mut separated);
+            });
+            query_builder
+                .build()
+                .execute(self.target)
+                .await
+                .map_err(|source| AnonymizeError::Copy { table, source })?;
+
+            copied += len;
+            if len < BATCH_SIZE {
+                break;
+            }
+        }
+
+        Ok((table, copied as u64))
+    }
+}
+
+/// A row that can be read from one database and inserted into another.
+trait CopyRow: for<'r> FromRow<'r, MySqlRow> + Send + Unpin + 'static {
+    fn push_binds(self, separated: &mut Separated<'_, 'static, MySql, &'static str>);
+}
+
+macro_rules! impl_copy_row {
+    ($($t:ident => $v:ident),+) => {
+        impl<$($t),+> CopyRow for ($($t,)+)
+        where
+            Self: for<'r> FromRow<'r, MySqlRow> + Send + Unpin + 'static,
+            $($t: for<'q> Encode<'q, MySql> + Type<MySql> + Send + 'static,)+
+        {
+            fn push_binds(self, separated: &mut Separated<'_, 'static, MySql, &'static str>) {
+                let ($($v,)+) = self;
+                $(separated.push_bind($v);)+
+            }
+        }
+    };
+}
+
+impl_copy_row!(A => a, B => b);
+impl_copy_row!(A => a, B => b, C => c);
+impl_copy_row!(A => a, B => b, C => c, D => d);
+impl_copy_row!(A => a, B => b, C => c, D => d, E => e);
+impl_copy_row!(A => a, B => b, C => c, D => d, E => e, F => f);
+impl_copy_row!(A => a, B => b, C => c, D => d, E => e, F => f, G => g);
+
+fn keyed_hash(salt: &str, data: &[u8]) -> [u8; 32] {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(salt.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Handles are hashed with a salt, since plain hashes of public handles are easily reversed.
+fn pseudonymize_handle(salt: &str, handle: &str) -> String {
+    let hash = keyed_hash(salt, handle.as_bytes());
+
+    // 10 hex digits keep collisions unlikely while fitting traQ's 32 character limit
+    let hex: String = hash[..5].iter().map(|b| format!("{b:02x}")).collect();
+
+    format!("user-{hex}")
+}
+
+fn message_seed(salt: &str, message_id: &Uuid) -> u64 {
+    let hash = keyed_hash(salt, message_id.as_bytes());
+
+    u64::from_le_bytes([
+        hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7],
+    ])
+}
+
+/// Replaces letters and digits with random ones of the same kind, so that the text keeps its
+/// shape (length, line breaks, punctuation and script) but not its meaning.
+fn scramble(content: &str, seed: u64) -> String {
+    let mut rng = fastrand::Rng::with_seed(seed);
+
+    content
+        .chars()
+        .map(|c| match c {
+            'a'..='z' => rng.lowercase(),
+            'A'..='Z' => rng.uppercase(),
+            '0'..='9' => rng.digit(10),
+            // Japanese and other scripts become hiragana
+            c if c.is_alphanumeric() => rng.char('ぁ'..='ゖ'),
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_are_pseudonymized_with_the_salt() {
+        let pseudonym = pseudonymize_handle("salt", "alice");
+
+        assert!(pseudonym.starts_with("user-"));
+        assert_eq!(pseudonym.len(), 15);
+        assert_eq!(pseudonymize_handle("salt", "alice"), pseudonym);
+        assert_ne!(pseudonymize_handle("salt", "bob"), pseudonym);
+        assert_ne!(pseudonymize_handle("other salt", "alice"), pseudonym);
+    }
+
+    #[test]
+    fn scrambling_keeps_the_shape_of_the_text() {
+        let content = "Hello, 世界!\n@alice 123";
+        let scrambled = scramble(content, 42);
+
+        assert_ne!(scrambled, content);
+        assert_eq!(scrambled.chars().count(), content.chars().count());
+        for (original, scrambled) in content.chars().zip(scrambled.chars()) {
+            if original.is_alphanumeric() {
+                assert_eq!(original.is_ascii_digit(), scrambled.is_ascii_digit());
+                assert_eq!(
+                    original.is_ascii_uppercase(),
+                    scrambled.is_ascii_uppercase()
+                );
+            } else {
+                assert_eq!(original, scrambled);
+            }
+        }
+        assert_eq!(scramble(content, 42), scrambled);
+    }
+}
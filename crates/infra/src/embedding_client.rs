@@ -0,0 +1,116 @@
+use domain::{embedding::EmbeddingService, error::EmbeddingError};
+use http::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Computes embeddings with an OpenAI-compatible `/embeddings` API.
+#[derive(Clone, Debug)]
+pub struct EmbeddingClientImpl {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl EmbeddingClientImpl {
+    pub fn new(base_url: String, api_key: Option<String>, model: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+            api_key,
+            model,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingService for EmbeddingClientImpl {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let body = serde_json::to_vec(&EmbeddingRequest {
+            model: &self.model,
+            input: texts,
+        })
+        .map_err(|e| EmbeddingError::Request(e.to_string()))?;
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/embeddings",
+                self.base_url.trim_end_matches('/')
+            ))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .timeout(REQUEST_TIMEOUT);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let bytes = request
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| EmbeddingError::Request(e.to_string()))?
+            .bytes()
+            .await
+            .map_err(|e| EmbeddingError::Request(e.to_string()))?;
+        let mut response: EmbeddingResponse = serde_json::from_slice(&bytes)
+            .map_err(|e| EmbeddingError::InvalidResponse(e.to_string()))?;
+
+        if response.data.len() != texts.len() {
+            return Err(EmbeddingError::InvalidResponse(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                response.data.len()
+            )));
+        }
+        // The API does not guarantee that embeddings are in the order of the inputs
+        response.data.sort_by_key(|d| d.index);
+
+        Ok(response.data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_is_parsed() {
+        let json = r#"{
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.5, 0.25]},
+                {"object": "embedding", "index": 0, "embedding": [1.0, 0.0]}
+            ],
+            "model": "text-embedding-3-small"
+        }"#;
+        let mut response: EmbeddingResponse = serde_json::from_str(json).unwrap();
+        response.data.sort_by_key(|d| d.index);
+
+        assert_eq!(response.data[0].embedding, vec![1.0, 0.0]);
+        assert_eq!(response.data[1].embedding, vec![0.5, 0.25]);
+    }
+}
@@ -0,0 +1,64 @@
+use domain::{
+    error::FederationError,
+    federation::{HIGHLIGHTS_VERSION, HighlightsFetcher},
+    model::InstanceHighlights,
+};
+use reqwest::Client;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const USER_AGENT: &str = concat!("Twittra/", env!("CARGO_PKG_VERSION"), " (federation)");
+
+/// Fetches highlights from the endpoint peer instances publish them at.
+#[derive(Clone, Debug)]
+pub struct HighlightsFetcherImpl {
+    client: Client,
+}
+
+impl HighlightsFetcherImpl {
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("the federation client has a valid configuration");
+
+        Self { client }
+    }
+}
+
+impl Default for HighlightsFetcherImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl HighlightsFetcher for HighlightsFetcherImpl {
+    async fn fetch(&self, base_url: &str) -> Result<InstanceHighlights, FederationError> {
+        let url = format!(
+            "{}/api/v1/federation/highlights",
+            base_url.trim_end_matches('/')
+        );
+        let res = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| FederationError::Request(e.to_string()))?;
+
+        let highlights: InstanceHighlights = res
+            .json()
+            .await
+            .map_err(|e| FederationError::InvalidResponse(e.to_string()))?;
+        if highlights.version != HIGHLIGHTS_VERSION {
+            return Err(FederationError::InvalidResponse(format!(
+                "unsupported version {}",
+                highlights.version
+            )));
+        }
+
+        Ok(highlights)
+    }
+}
@@ -0,0 +1,110 @@
+use domain::{error::ImageError, thumbnail::ImageResizer};
+use image::{
+    DynamicImage, ImageFormat, ImageReader, Limits,
+    codecs::{png::PngDecoder, webp::WebPDecoder},
+};
+use std::io::Cursor;
+
+/// Images larger than this are not decoded, so that a huge image can't exhaust the memory.
+const MAX_SOURCE_DIMENSION: u32 = 8192;
+
+/// Resizes PNG, JPEG and WebP images. Images in other formats are returned as is.
+#[derive(Clone, Debug, Default)]
+pub struct ImageResizerImpl;
+
+impl ImageResizer for ImageResizerImpl {
+    fn resize(
+        &self,
+        image: &[u8],
+        content_type: &str,
+        max_dimension: u32,
+    ) -> Result<(Vec<u8>, String), ImageError> {
+        let unchanged = || Ok((image.to_vec(), content_type.to_string()));
+        let format = match image::guess_format(image) {
+            Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)) => format,
+            _ => return unchanged(),
+        };
+        // Only the first frame would be kept
+        if is_animated(image, format)? {
+            return unchanged();
+        }
+
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+        limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+        let mut reader = ImageReader::with_format(Cursor::new(image), format);
+        reader.limits(limits);
+        let decoded = reader
+            .decode()
+            .map_err(|e| ImageError::Decode(e.to_string()))?;
+        if decoded.width() <= max_dimension && decoded.height() <= max_dimension {
+            return unchanged();
+        }
+
+        let resized = decoded.thumbnail(max_dimension, max_dimension);
+        // Photos are much smaller as JPEGs, but other images may have transparency
+        let (resized, format) = match format {
+            ImageFormat::Jpeg => (DynamicImage::ImageRgb8(resized.into_rgb8()), format),
+            _ => (resized, ImageFormat::Png),
+        };
+        let mut encoded = Cursor::new(Vec::new());
+        resized
+            .write_to(&mut encoded, format)
+            .map_err(|e| ImageError::Encode(e.to_string()))?;
+
+        Ok((encoded.into_inner(), format.to_mime_type().to_string()))
+    }
+}
+
+fn is_animated(image: &[u8], format: ImageFormat) -> Result<bool, ImageError> {
+    let animated = match format {
+        ImageFormat::Png => PngDecoder::new(Cursor::new(image)).and_then(|d| d.is_apng()),
+        ImageFormat::WebP => WebPDecoder::new(Cursor::new(image)).map(|d| d.has_animation()),
+        _ => Ok(false),
+    };
+    animated.map_err(|e| ImageError::Decode(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut encoded = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(RgbaImage::new(width, height))
+            .write_to(&mut encoded, ImageFormat::Png)
+            .unwrap();
+        encoded.into_inner()
+    }
+
+    #[test]
+    fn large_images_are_shrunk_keeping_the_aspect_ratio() {
+        let (resized, content_type) = ImageResizerImpl
+            .resize(&png(400, 200), "image/png", 128)
+            .unwrap();
+        let decoded = image::load_from_memory(&resized).unwrap();
+
+        assert_eq!(content_type, "image/png");
+        assert_eq!((decoded.width(), decoded.height()), (128, 64));
+    }
+
+    #[test]
+    fn small_and_unsupported_images_are_unchanged() {
+        let small = png(64, 64);
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg"/>"#;
+
+        assert_eq!(
+            ImageResizerImpl.resize(&small, "image/png", 128).unwrap(),
+            (small.clone(), "image/png".to_string())
+        );
+        assert_eq!(
+            ImageResizerImpl.resize(svg, "image/svg+xml", 128).unwrap(),
+            (svg.to_vec(), "image/svg+xml".to_string())
+        );
+        assert!(matches!(
+            ImageResizerImpl.resize(&small[..32], "image/png", 128),
+            Err(ImageError::Decode(_))
+        ));
+    }
+}
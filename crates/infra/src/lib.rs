@@ -1,2 +1,12 @@
+pub mod anonymize;
+#[cfg(feature = "embeddings")]
+pub mod embedding_client;
+pub mod highlights_fetcher;
+pub mod image_resizer;
+pub mod link_preview_fetcher;
+pub mod meilisearch;
 pub mod repository;
+pub mod signing;
+pub mod traq_budget;
 pub mod traq_client;
+pub mod webhook_sender;
@@ -0,0 +1,387 @@
+use domain::{error::LinkPreviewError, link_preview::LinkPreviewFetcher, model::LinkPreview};
+use http::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::{
+    Client,
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::net;
+use url::{Host, Url};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REDIRECTS: usize = 3;
+/// Metadata is in the head, so only the beginning of large pages is read.
+const MAX_BODY_BYTES: usize = 256 * 1024;
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+const USER_AGENT: &str = concat!("Twittra/", env!("CARGO_PKG_VERSION"), " (link preview)");
+
+/// Fetches the OpenGraph metadata of public web pages.
+#[derive(Clone, Debug)]
+pub struct LinkPreviewFetcherImpl {
+    client: Client,
+}
+
+impl LinkPreviewFetcherImpl {
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            // A proxy would resolve domains itself, bypassing the resolver
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver))
+            .redirect(Policy::custom(|attempt| {
+                if attempt.previous().len() > MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if is_public(attempt.url()) {
+                    attempt.follow()
+                } else {
+                    attempt.error("redirected to a non-public address")
+                }
+            }))
+            .build()
+            .expect("the link preview client has a valid configuration");
+
+        Self { client }
+    }
+}
+
+impl Default for LinkPreviewFetcherImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkPreviewFetcher for LinkPreviewFetcherImpl {
+    async fn fetch(&self, url: &str) -> Result<LinkPreview, LinkPreviewError> {
+        let parsed =
+            Url::parse(url).map_err(|e| LinkPreviewError::DisallowedUrl(format!("{url}: {e}")))?;
+        if !is_public(&parsed) {
+            return Err(LinkPreviewError::DisallowedUrl(url.to_string()));
+        }
+
+        let mut response = self
+            .client
+            .get(parsed)
+            .header(ACCEPT, "text/html")
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| LinkPreviewError::Request(e.to_string()))?;
+        let is_html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/html") || v.starts_with("application/xhtml"));
+        // Images and other files have no metadata
+        if !is_html {
+            return Ok(LinkPreview::empty(url.to_string()));
+        }
+
+        let base = response.url().clone();
+        let mut body = Vec::new();
+        while body.len() < MAX_BODY_BYTES
+            && let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| LinkPreviewError::Request(e.to_string()))?
+        {
+            body.extend_from_slice(&chunk);
+        }
+        body.truncate(MAX_BODY_BYTES);
+
+        Ok(parse(url, &base, &String::from_utf8_lossy(&body)))
+    }
+}
+
+/// Resolves domains to their public addresses only, so that a domain pointing to an internal
+/// address can't make the server request internal services. Every connection is resolved with it,
+/// including those to the targets of redirects.
+#[derive(Debug)]
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether the URL is on the public internet, so that messages can't make the server request
+/// internal services. Domains are only checked by name here, and by their addresses once resolved
+/// by [`PublicResolver`].
+fn is_public(url: &Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    match url.host() {
+        Some(Host::Domain(domain)) => domain != "localhost" && !domain.ends_with(".localhost"),
+        Some(Host::Ipv4(ip)) => is_public_ipv4(ip),
+        Some(Host::Ipv6(ip)) => is_public_ip(ip.into()),
+        None => false,
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    // Shared address space of carrier-grade NAT, 100.64.0.0/10
+    let is_shared = first == 100 && second & 0b1100_0000 == 64;
+
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || is_shared
+        || ip.is_unspecified()
+        || ip.is_broadcast())
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    !(ip.is_loopback() || ip.is_unspecified() || ip.is_unique_local() || ip.is_unicast_link_local())
+}
+
+/// Builds the preview of `url` from the page's OpenGraph tags, falling back to its title and
+/// description. Relative image URLs are resolved against `base`, the URL after redirects.
+fn parse(url: &str, base: &Url, html: &str) -> LinkPreview {
+    // ASCII lowercasing keeps byte offsets, so positions found in `lower` are valid in `html`
+    let lower = html.to_ascii_lowercase();
+    let mut meta: HashMap<String, &str> = HashMap::new();
+    for (i, _) in lower.match_indices("<meta") {
+        let tag = &html[i + "<meta".len()..];
+        let Some(end) = tag.find('>') else {
+            break;
+        };
+        let attributes = attributes(&tag[..end]);
+        let key = attributes
+            .get("property")
+            .or_else(|| attributes.get("name"))
+            .map(|key| key.to_ascii_lowercase());
+        if let (Some(key), Some(content)) = (key, attributes.get("content")) {
+            meta.entry(key).or_insert(content);
+        }
+    }
+
+    let title = meta
+        .get("og:title")
+        .copied()
+        .or_else(|| title_element(html, &lower));
+    let description = meta
+        .get("og:description")
+        .or_else(|| meta.get("description"))
+        .copied();
+    let image_url = meta
+        .get("og:image")
+        .and_then(|image| base.join(&decode_entities(image)).ok())
+        .filter(|image| matches!(image.scheme(), "http" | "https"))
+        .map(String::from);
+
+    LinkPreview {
+        url: url.to_string(),
+        title: title.and_then(|t| clean(t, MAX_TITLE_CHARS)),
+        description: description.and_then(|d| clean(d, MAX_DESCRIPTION_CHARS)),
+        image_url,
+    }
+}
+
+fn title_element<'a>(html: &'a str, lower: &str) -> Option<&'a str> {
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    Some(&html[start..end])
+}
+
+/// Parses the attributes of a tag, e.g. ` property="og:title" content='Title'`.
+/// Names are lowercased, and attributes without values have empty values.
+fn attributes(tag: &str) -> HashMap<String, &str> {
+    let mut attributes = HashMap::new();
+    let mut rest = tag;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        if name_end == 0 {
+            break;
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let Some(value) = rest.strip_prefix('=') else {
+            attributes.entry(name).or_insert("");
+            continue;
+        };
+        let value = value.trim_start();
+        let (value, after) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                let end = value.find(quote).unwrap_or(value.len());
+                (&value[..end], value.get(end + 1..).unwrap_or_default())
+            }
+            _ => {
+                let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                (&value[..end], &value[end..])
+            }
+        };
+        attributes.entry(name).or_insert(value);
+        rest = after;
+    }
+    attributes
+}
+
+/// Decodes entities, collapses whitespace and shortens the text to `max_chars` characters.
+/// Returns `None` if nothing is left.
+fn clean(text: &str, max_chars: usize) -> Option<String> {
+    let text = decode_entities(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= max_chars {
+        return Some(text);
+    }
+
+    let mut truncated: String = text.chars().take(max_chars - 1).collect();
+    truncated.push('…');
+    Some(truncated)
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opengraph_tags_are_parsed() {
+        let html = r#"<html><head>
+            <title>Fallback</title>
+            <META property="og:title" content="Tom &amp; Jerry&#39;s &#x1F600;">
+            <meta content='A   cat
+                and a mouse' property=og:description />
+            <meta property="og:image" content="/images/cover.png">
+            </head>"#;
+        let base = Url::parse("https://example.com/articles/1").unwrap();
+
+        assert_eq!(
+            parse("https://example.com/a", &base, html),
+            LinkPreview {
+                url: "https://example.com/a".to_string(),
+                title: Some("Tom & Jerry's 😀".to_string()),
+                description: Some("A cat and a mouse".to_string()),
+                image_url: Some("https://example.com/images/cover.png".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn title_and_description_are_fallbacks() {
+        let html = r#"<title> Page </title><meta name="Description" content="About the page">"#;
+        let base = Url::parse("https://example.com/").unwrap();
+        let preview = parse("https://example.com/", &base, html);
+
+        assert_eq!(preview.title.as_deref(), Some("Page"));
+        assert_eq!(preview.description.as_deref(), Some("About the page"));
+        assert!(parse("https://example.com/", &base, "<p>no head</p>").is_empty());
+    }
+
+    #[test]
+    fn only_public_urls_are_fetched() {
+        for url in [
+            "https://example.com/",
+            "http://93.184.215.14/",
+            "http://100.128.0.1/",
+            "https://[2606:2800::1]/",
+        ] {
+            assert!(is_public(&Url::parse(url).unwrap()), "{url}");
+        }
+        for url in [
+            "file:///etc/passwd",
+            "http://localhost:8080/",
+            "http://127.0.0.1/",
+            "http://10.0.0.1/",
+            "http://192.168.1.1/",
+            "http://100.64.0.1/",
+            "http://100.127.255.254/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[fd00::1]/",
+        ] {
+            assert!(!is_public(&Url::parse(url).unwrap()), "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn domains_resolving_to_private_addresses_are_rejected() {
+        let name: Name = "localhost".parse().unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use domain::{error::SearchError, model::Message, search::SearchIndex};
+use http::header::CONTENT_TYPE;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A [`SearchIndex`] backed by a Meilisearch index.
+#[derive(Clone, Debug)]
+pub struct MeilisearchIndex {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    index: String,
+}
+
+impl MeilisearchIndex {
+    pub fn new(base_url: String, api_key: Option<String>, index: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            index,
+        }
+    }
+
+    /// Creates the index if needed and makes only message contents searchable.
+    /// Also serves as a connectivity check on startup.
+    pub async fn configure(&self) -> Result<(), SearchError> {
+        let settings = Settings {
+            searchable_attributes: &["content"],
+            filterable_attributes: &["userId", "channelId"],
+            sortable_attributes: &["createdAt"],
+        };
+        let request = self
+            .client
+            .patch(self.url("settings"))
+            .body(to_json(&settings)?);
+
+        self.send(request).await.map(|_| ())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/indexes/{}/{}", self.base_url, self.index, path)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Vec<u8>, SearchError> {
+        let mut request = request
+            .header(CONTENT_TYPE, "application/json")
+            .timeout(REQUEST_TIMEOUT);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let bytes = request
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| SearchError::Request(e.to_string()))?
+            .bytes()
+            .await
+            .map_err(|e| SearchError::Request(e.to_string()))?;
+
+        Ok(bytes.to_vec())
+    }
+}
+
+fn to_json(value: &impl Serialize) -> Result<Vec<u8>, SearchError> {
+    serde_json::to_vec(value).map_err(|e| SearchError::Request(e.to_string()))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Settings {
+    searchable_attributes: &'static [&'static str],
+    filterable_attributes: &'static [&'static str],
+    sortable_attributes: &'static [&'static str],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageDocument<'a> {
+    id: Uuid,
+    user_id: Uuid,
+    channel_id: Uuid,
+    content: &'a str,
+    /// Unix timestamp, since Meilisearch only sorts numbers and strings.
+    created_at: i64,
+}
+
+impl<'a> From<&'a Message> for MessageDocument<'a> {
+    fn from(message: &'a Message) -> Self {
+        Self {
+            id: message.id,
+            user_id: message.user_id,
+            channel_id: message.channel_id,
+            content: &message.content,
+            created_at: message.created_at.unix_timestamp(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchRequest<'a> {
+    q: &'a str,
+    limit: usize,
+    attributes_to_retrieve: &'static [&'static str],
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    hits: Vec<SearchHit>,
+}
+
+#[derive(Deserialize)]
+struct SearchHit {
+    id: Uuid,
+}
+
+#[async_trait::async_trait]
+impl SearchIndex for MeilisearchIndex {
+    async fn index_messages(&self, messages: &[Message]) -> Result<(), SearchError> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let documents: Vec<MessageDocument> = messages.iter().map(Into::into).collect();
+        // Indexing happens asynchronously in Meilisearch, so the task is not waited for
+        let request = self
+            .client
+            .post(self.url("documents?primaryKey=id"))
+            .body(to_json(&documents)?);
+
+        self.send(request).await.map(|_| ())
+    }
+
+    async fn remove_messages(&self, message_ids: &[Uuid]) -> Result<(), SearchError> {
+        if message_ids.is_empty() {
+            return Ok(());
+        }
+
+        let request = self
+            .client
+            .post(self.url("documents/delete-batch"))
+            .body(to_json(&message_ids)?);
+
+        self.send(request).await.map(|_| ())
+    }
+
+    async fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<Uuid>, SearchError> {
+        let request = self
+            .client
+            .post(self.url("search"))
+            .body(to_json(&SearchRequest {
+                q: query,
+                limit,
+                attributes_to_retrieve: &["id"],
+            })?);
+        let bytes = self.send(request).await?;
+        let response: SearchResponse = serde_json::from_slice(&bytes)
+            .map_err(|e| SearchError::InvalidResponse(e.to_string()))?;
+
+        Ok(response.hits.into_iter().map(|hit| hit.id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::test_factories::MessageBuilder;
+
+    #[test]
+    fn documents_use_camel_case_and_unix_timestamps() {
+        let message = MessageBuilder::new().content("hello").build();
+        let document = serde_json::to_value(MessageDocument::from(&message)).unwrap();
+
+        assert_eq!(document["id"], message.id.to_string());
+        assert_eq!(document["channelId"], message.channel_id.to_string());
+        assert_eq!(document["content"], "hello");
+        assert_eq!(document["createdAt"], message.created_at.unix_timestamp());
+    }
+
+    #[test]
+    fn search_response_is_parsed() {
+        let id = Uuid::from_u128(1);
+        let json = format!(
+            r#"{{"hits":[{{"id":"{id}"}}],"query":"hello","processingTimeMs":1,"limit":50,"offset":0,"estimatedTotalHits":1}}"#
+        );
+        let response: SearchResponse = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(response.hits.len(), 1);
+        assert_eq!(response.hits[0].id, id);
+    }
+}
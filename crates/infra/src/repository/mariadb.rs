@@ -3,12 +3,43 @@ use sqlx::MySqlPool;
 use std::sync::Arc;
 
 use crate::repository::mariadb::{
-    message::MariaDbMessageRepository, stamp::MariaDbStampRepository, user::MariaDbUserRepository,
+    announcement::MariaDbAnnouncementRepository, block::MariaDbBlockRepository,
+    bookmark::MariaDbBookmarkRepository, channel::MariaDbChannelRepository,
+    embedding::MariaDbEmbeddingRepository, feedback::MariaDbFeedbackRepository,
+    follow::MariaDbFollowRepository, image_cache::MariaDbImageCacheRepository,
+    impression::MariaDbImpressionRepository, job_run::MariaDbJobRunRepository,
+    link_preview::MariaDbLinkPreviewRepository, message::MariaDbMessageRepository,
+    message_event::MariaDbMessageEventRepository, mute::MariaDbMuteRepository,
+    presence::MariaDbPresenceRepository, quota::MariaDbQuotaRepository,
+    report::MariaDbReportRepository, saved_search::MariaDbSavedSearchRepository,
+    stamp::MariaDbStampRepository, user::MariaDbUserRepository,
+    user_settings::MariaDbUserSettingsRepository, webhook::MariaDbWebhookRepository,
 };
 
+pub mod announcement;
+pub mod block;
+pub mod bookmark;
+pub mod channel;
+pub mod embedding;
+pub mod feedback;
+pub mod follow;
+pub mod image_cache;
+pub mod impression;
+mod in_list;
+pub mod job_run;
+pub mod link_preview;
 pub mod message;
+pub mod message_event;
+pub mod mute;
+pub mod presence;
+pub mod quota;
+mod read_filter;
+pub mod report;
+pub mod saved_search;
 pub mod stamp;
 pub mod user;
+pub mod user_settings;
+pub mod webhook;
 
 pub async fn new_repository(pool: MySqlPool) -> Result<Repository, RepositoryError> {
     sqlx::migrate!()
@@ -16,9 +47,31 @@ pub async fn new_repository(pool: MySqlPool) -> Result<Repository, RepositoryErr
         .await
         .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
+    let message = Arc::new(MariaDbMessageRepository::new(pool.clone()));
+
     Ok(Repository {
-        message: Arc::new(MariaDbMessageRepository::new(pool.clone())),
+        announcement: Arc::new(MariaDbAnnouncementRepository::new(pool.clone())),
+        block: Arc::new(MariaDbBlockRepository::new(pool.clone())),
+        bookmark: Arc::new(MariaDbBookmarkRepository::new(pool.clone())),
+        channel: Arc::new(MariaDbChannelRepository::new(pool.clone())),
+        embedding: Arc::new(MariaDbEmbeddingRepository::new(pool.clone())),
+        feedback: Arc::new(MariaDbFeedbackRepository::new(pool.clone())),
+        follow: Arc::new(MariaDbFollowRepository::new(pool.clone())),
+        image_cache: Arc::new(MariaDbImageCacheRepository::new(pool.clone())),
+        impression: Arc::new(MariaDbImpressionRepository::new(pool.clone())),
+        job_run: Arc::new(MariaDbJobRunRepository::new(pool.clone())),
+        link_preview: Arc::new(MariaDbLinkPreviewRepository::new(pool.clone())),
+        message_reader: message.clone(),
+        message_writer: message,
+        message_event: Arc::new(MariaDbMessageEventRepository::new(pool.clone())),
+        mute: Arc::new(MariaDbMuteRepository::new(pool.clone())),
+        presence: Arc::new(MariaDbPresenceRepository::new(pool.clone())),
+        quota: Arc::new(MariaDbQuotaRepository::new(pool.clone())),
+        report: Arc::new(MariaDbReportRepository::new(pool.clone())),
+        saved_search: Arc::new(MariaDbSavedSearchRepository::new(pool.clone())),
         stamp: Arc::new(MariaDbStampRepository::new(pool.clone())),
-        user: Arc::new(MariaDbUserRepository::new(pool)),
+        user: Arc::new(MariaDbUserRepository::new(pool.clone())),
+        user_settings: Arc::new(MariaDbUserSettingsRepository::new(pool.clone())),
+        webhook: Arc::new(MariaDbWebhookRepository::new(pool)),
     })
 }
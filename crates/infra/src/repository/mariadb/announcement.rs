@@ -0,0 +1,232 @@
+use domain::{error::RepositoryError, model::Announcement, repository::AnnouncementRepository};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbAnnouncementRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbAnnouncementRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AnnouncementRepository for MariaDbAnnouncementRepository {
+    async fn publish(&self, content: &str) -> Result<Announcement, RepositoryError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE announcements
+            SET withdrawn_at = CURRENT_TIMESTAMP(6)
+            WHERE withdrawn_at IS NULL
+            "#
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO announcements (content)
+            VALUES (?)
+            "#,
+            content
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        let announcement = sqlx::query_as!(
+            Announcement,
+            r#"
+            SELECT id, content, created_at
+            FROM announcements
+            WHERE id = ?
+            "#,
+            result.last_insert_id()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(announcement)
+    }
+
+    async fn import(&self, announcement: &Announcement) -> Result<(), RepositoryError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE announcements
+            SET withdrawn_at = CURRENT_TIMESTAMP(6)
+            WHERE withdrawn_at IS NULL
+            "#
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO announcements (id, content, created_at)
+            VALUES (?, ?, ?)
+            "#,
+            announcement.id,
+            announcement.content,
+            announcement.created_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn withdraw(&self) -> Result<bool, RepositoryError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE announcements
+            SET withdrawn_at = CURRENT_TIMESTAMP(6)
+            WHERE withdrawn_at IS NULL
+            "#
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_undismissed(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Option<Announcement>, RepositoryError> {
+        let announcement = sqlx::query_as!(
+            Announcement,
+            r#"
+            SELECT a.id, a.content, a.created_at
+            FROM announcements a
+            WHERE a.withdrawn_at IS NULL
+              AND NOT EXISTS (
+                SELECT 1 FROM announcement_dismissals d
+                WHERE d.announcement_id = a.id AND d.user_id = ?
+              )
+            ORDER BY a.id DESC
+            LIMIT 1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(announcement)
+    }
+
+    async fn dismiss(&self, user_id: &Uuid, announcement_id: i64) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT IGNORE INTO announcement_dismissals (user_id, announcement_id)
+            SELECT ?, id FROM announcements WHERE id = ?
+            "#,
+            user_id,
+            announcement_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::user::MariaDbUserRepository;
+    use domain::{repository::UserRepository, test_factories::UserBuilder};
+
+    #[sqlx::test]
+    async fn test_announcements(pool: sqlx::MySqlPool) {
+        let repo = MariaDbAnnouncementRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+        let user = UserBuilder::new().build();
+        let other = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        user_repo.save(&other).await.unwrap();
+
+        assert_eq!(repo.find_undismissed(&user.id).await.unwrap(), None);
+        assert!(!repo.withdraw().await.unwrap());
+
+        let first = repo.publish("Maintenance tonight").await.unwrap();
+        assert_eq!(first.content, "Maintenance tonight");
+        assert_eq!(
+            repo.find_undismissed(&user.id).await.unwrap(),
+            Some(first.clone())
+        );
+
+        // Dismissals are per user, and unknown announcements are ignored
+        repo.dismiss(&user.id, first.id).await.unwrap();
+        repo.dismiss(&user.id, first.id).await.unwrap();
+        repo.dismiss(&user.id, first.id + 100).await.unwrap();
+        assert_eq!(repo.find_undismissed(&user.id).await.unwrap(), None);
+        assert_eq!(repo.find_undismissed(&other.id).await.unwrap(), Some(first));
+
+        // A new announcement is shown again to everyone
+        let second = repo.publish("Maintenance is over").await.unwrap();
+        assert_eq!(
+            repo.find_undismissed(&user.id).await.unwrap(),
+            Some(second.clone())
+        );
+        assert_eq!(
+            repo.find_undismissed(&other.id).await.unwrap(),
+            Some(second)
+        );
+
+        assert!(repo.withdraw().await.unwrap());
+        assert_eq!(repo.find_undismissed(&other.id).await.unwrap(), None);
+    }
+
+    #[sqlx::test]
+    async fn test_import_keeps_the_id(pool: sqlx::MySqlPool) {
+        let repo = MariaDbAnnouncementRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let current = repo.publish("Maintenance tonight").await.unwrap();
+
+        let imported = Announcement {
+            id: current.id + 10,
+            content: "Maintenance is over".to_string(),
+            created_at: current.created_at,
+        };
+        repo.import(&imported).await.unwrap();
+        assert_eq!(
+            repo.find_undismissed(&user.id).await.unwrap(),
+            Some(imported.clone())
+        );
+
+        repo.dismiss(&user.id, imported.id).await.unwrap();
+        assert_eq!(repo.find_undismissed(&user.id).await.unwrap(), None);
+    }
+}
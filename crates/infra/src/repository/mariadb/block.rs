@@ -0,0 +1,159 @@
+use domain::{error::RepositoryError, id::UserId, repository::BlockRepository};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbBlockRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbBlockRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct BlockedUserRecord {
+    user_id: Uuid,
+}
+
+#[async_trait::async_trait]
+impl BlockRepository for MariaDbBlockRepository {
+    async fn block_user(
+        &self,
+        user_id: &UserId,
+        blocked_user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT IGNORE INTO blocks (user_id, blocked_user_id)
+            VALUES (?, ?)
+            "#,
+            user_id.as_uuid(),
+            blocked_user_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn unblock_user(
+        &self,
+        user_id: &UserId,
+        blocked_user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM blocks
+            WHERE user_id = ? AND blocked_user_id = ?
+            "#,
+            user_id.as_uuid(),
+            blocked_user_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_blocked_or_blocking_user_ids(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let records = sqlx::query_as!(
+            BlockedUserRecord,
+            r#"
+            SELECT blocked_user_id AS `user_id: _`
+            FROM blocks
+            WHERE user_id = ?
+            UNION
+            SELECT user_id
+            FROM blocks
+            WHERE blocked_user_id = ?
+            "#,
+            user_id,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(records.into_iter().map(|r| r.user_id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::user::MariaDbUserRepository;
+    use domain::{repository::UserRepository, test_factories::UserBuilder};
+
+    #[sqlx::test]
+    async fn test_block_and_unblock_user(pool: sqlx::MySqlPool) {
+        let repo = MariaDbBlockRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create users first (FK constraint)
+        let user = UserBuilder::new().build();
+        let other = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        user_repo.save(&other).await.unwrap();
+
+        repo.block_user(&user.id.into(), &other.id.into())
+            .await
+            .unwrap();
+        // Blocking twice is a no-op
+        repo.block_user(&user.id.into(), &other.id.into())
+            .await
+            .unwrap();
+
+        // Blocks are visible from both sides
+        let blocked = repo
+            .find_blocked_or_blocking_user_ids(&user.id)
+            .await
+            .unwrap();
+        assert_eq!(blocked, vec![other.id]);
+        let blocking = repo
+            .find_blocked_or_blocking_user_ids(&other.id)
+            .await
+            .unwrap();
+        assert_eq!(blocking, vec![user.id]);
+
+        repo.unblock_user(&user.id.into(), &other.id.into())
+            .await
+            .unwrap();
+
+        let blocked = repo
+            .find_blocked_or_blocking_user_ids(&user.id)
+            .await
+            .unwrap();
+        assert!(blocked.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_mutual_blocks_are_deduplicated(pool: sqlx::MySqlPool) {
+        let repo = MariaDbBlockRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let user = UserBuilder::new().build();
+        let other = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        user_repo.save(&other).await.unwrap();
+
+        repo.block_user(&user.id.into(), &other.id.into())
+            .await
+            .unwrap();
+        repo.block_user(&other.id.into(), &user.id.into())
+            .await
+            .unwrap();
+
+        let blocked = repo
+            .find_blocked_or_blocking_user_ids(&user.id)
+            .await
+            .unwrap();
+        assert_eq!(blocked, vec![other.id]);
+    }
+}
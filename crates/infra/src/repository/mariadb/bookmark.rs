@@ -0,0 +1,178 @@
+use domain::{
+    error::RepositoryError,
+    id::{MessageId, UserId},
+    model::MessageListItem,
+    repository::BookmarkRepository,
+};
+use sqlx::MySqlPool;
+
+use crate::repository::mariadb::message::{MessageRow, hydrate_messages};
+
+#[derive(Debug)]
+pub struct MariaDbBookmarkRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbBookmarkRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl BookmarkRepository for MariaDbBookmarkRepository {
+    async fn add(&self, user_id: &UserId, message_id: &MessageId) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT IGNORE INTO bookmarks (user_id, message_id)
+            VALUES (?, ?)
+            "#,
+            user_id.as_uuid(),
+            message_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn remove(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM bookmarks
+            WHERE user_id = ? AND message_id = ?
+            "#,
+            user_id.as_uuid(),
+            message_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_bookmarked_messages(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        let messages: Vec<MessageRow> = sqlx::query_as!(
+            MessageRow,
+            r#"
+            SELECT
+                m.id AS `id: _`,
+                m.user_id AS `user_id: _`,
+                m.channel_id AS `channel_id: _`,
+                m.content,
+                m.created_at,
+                m.updated_at,
+                u.handle AS user_handle,
+                u.display_name AS user_display_name
+            FROM bookmarks b
+            JOIN messages m ON b.message_id = m.id
+            LEFT JOIN users u ON m.user_id = u.id
+            WHERE b.user_id = ?
+            ORDER BY b.created_at DESC
+            "#,
+            user_id.as_uuid()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        hydrate_messages(&self.pool, messages).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::{
+        message::MariaDbMessageRepository, user::MariaDbUserRepository,
+    };
+    use domain::{
+        repository::{MessageWriter, UserRepository},
+        test_factories::{MessageBuilder, UserBuilder},
+    };
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    #[sqlx::test]
+    async fn test_add_and_find_bookmarks(pool: sqlx::MySqlPool) {
+        let repo = MariaDbBookmarkRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create user first (FK constraint)
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+
+        let older = MessageBuilder::new().build();
+        let newer = MessageBuilder::new().build();
+        message_repo.save(&older).await.unwrap();
+        message_repo.save(&newer).await.unwrap();
+
+        repo.add(&user.id.into(), &older.id.into()).await.unwrap();
+        sleep(Duration::from_millis(10)).await;
+        repo.add(&user.id.into(), &newer.id.into()).await.unwrap();
+        // Adding twice is a no-op
+        repo.add(&user.id.into(), &older.id.into()).await.unwrap();
+
+        let bookmarks = repo
+            .find_bookmarked_messages(&user.id.into())
+            .await
+            .unwrap();
+
+        // Most recently bookmarked first
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].id, newer.id);
+        assert_eq!(bookmarks[1].id, older.id);
+    }
+
+    #[sqlx::test]
+    async fn test_remove_bookmark(pool: sqlx::MySqlPool) {
+        let repo = MariaDbBookmarkRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let message = MessageBuilder::new().build();
+        message_repo.save(&message).await.unwrap();
+
+        repo.add(&user.id.into(), &message.id.into()).await.unwrap();
+        repo.remove(&user.id.into(), &message.id.into())
+            .await
+            .unwrap();
+
+        let bookmarks = repo
+            .find_bookmarked_messages(&user.id.into())
+            .await
+            .unwrap();
+        assert!(bookmarks.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_bookmarked_message_cannot_be_deleted(pool: sqlx::MySqlPool) {
+        let repo = MariaDbBookmarkRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let message = MessageBuilder::new().build();
+        message_repo.save(&message).await.unwrap();
+        repo.add(&user.id.into(), &message.id.into()).await.unwrap();
+
+        let result = sqlx::query!("DELETE FROM messages WHERE id = ?", message.id)
+            .execute(&pool)
+            .await;
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use domain::{error::RepositoryError, model::Channel, repository::ChannelRepository};
+use sqlx::{MySqlPool, QueryBuilder};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbChannelRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbChannelRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ChannelRepository for MariaDbChannelRepository {
+    async fn find_by_id(&self, id: &Uuid) -> Result<Option<Channel>, RepositoryError> {
+        sqlx::query_as!(
+            Channel,
+            r#"
+            SELECT id AS `id: _`, name, path, archived AS `archived: bool`
+            FROM channels
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))
+    }
+
+    async fn find_all(&self) -> Result<Vec<Channel>, RepositoryError> {
+        sqlx::query_as!(
+            Channel,
+            r#"
+            SELECT id AS `id: _`, name, path, archived AS `archived: bool`
+            FROM channels
+            ORDER BY path
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))
+    }
+
+    async fn save_batch(&self, channels: &[Channel]) -> Result<(), RepositoryError> {
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder =
+            QueryBuilder::new("INSERT INTO channels (id, name, path, archived) ");
+
+        query_builder.push_values(channels, |mut separated, channel| {
+            separated
+                .push_bind(channel.id)
+                .push_bind(&channel.name)
+                .push_bind(&channel.path)
+                .push_bind(channel.archived);
+        });
+
+        query_builder.push(
+            " ON DUPLICATE KEY UPDATE name = VALUE(name), path = VALUE(path), archived = VALUE(archived)",
+        );
+
+        query_builder
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, uuid::UUIDv4};
+
+    fn channel(name: &str, path: &str) -> Channel {
+        Channel {
+            id: UUIDv4.fake(),
+            name: name.to_string(),
+            path: path.to_string(),
+            archived: false,
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_save_batch_and_find(pool: MySqlPool) {
+        let repo = MariaDbChannelRepository::new(pool);
+        let child = channel("times", "gps/times");
+        let mut root = channel("gps", "gps");
+        repo.save_batch(&[child.clone(), root.clone()])
+            .await
+            .unwrap();
+
+        // Syncing again replaces the stored channels
+        root.archived = true;
+        repo.save_batch(&[root.clone()]).await.unwrap();
+
+        assert_eq!(repo.find_all().await.unwrap(), vec![root.clone(), child]);
+        assert_eq!(repo.find_by_id(&root.id).await.unwrap(), Some(root));
+        assert_eq!(repo.find_by_id(&UUIDv4.fake()).await.unwrap(), None);
+    }
+}
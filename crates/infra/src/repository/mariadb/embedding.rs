@@ -0,0 +1,276 @@
+use domain::{error::RepositoryError, model::MessageEmbedding, repository::EmbeddingRepository};
+use sqlx::{MySqlPool, QueryBuilder};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbEmbeddingRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbEmbeddingRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct EmbeddingRow {
+    message_id: Uuid,
+    embedding: Vec<u8>,
+}
+
+impl TryFrom<EmbeddingRow> for MessageEmbedding {
+    type Error = RepositoryError;
+
+    fn try_from(row: EmbeddingRow) -> Result<Self, Self::Error> {
+        Ok(MessageEmbedding {
+            message_id: row.message_id,
+            embedding: decode_embedding(&row.embedding)?,
+        })
+    }
+}
+
+/// Encodes an embedding as little-endian f32 values.
+fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_embedding(bytes: &[u8]) -> Result<Vec<f32>, RepositoryError> {
+    let chunks = bytes.chunks_exact(size_of::<f32>());
+    if !chunks.remainder().is_empty() {
+        return Err(RepositoryError::Serialization(format!(
+            "embedding of {} bytes is not a sequence of f32 values",
+            bytes.len()
+        )));
+    }
+
+    Ok(chunks
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+#[async_trait::async_trait]
+impl EmbeddingRepository for MariaDbEmbeddingRepository {
+    async fn find_messages_without_embedding(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>, RepositoryError> {
+        struct PendingRow {
+            id: Uuid,
+            content: String,
+        }
+
+        let rows = sqlx::query_as!(
+            PendingRow,
+            r#"
+            SELECT m.id AS `id: _`, m.content
+            FROM messages m
+            LEFT JOIN message_embeddings e ON e.message_id = m.id
+            WHERE e.message_id IS NULL
+            ORDER BY m.created_at DESC
+            LIMIT ?
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(|row| (row.id, row.content)).collect())
+    }
+
+    async fn save_embeddings(
+        &self,
+        embeddings: &[MessageEmbedding],
+    ) -> Result<(), RepositoryError> {
+        if embeddings.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder =
+            QueryBuilder::new("INSERT INTO message_embeddings (message_id, embedding) ");
+
+        query_builder.push_values(embeddings, |mut separated, embedding| {
+            separated
+                .push_bind(embedding.message_id)
+                .push_bind(encode_embedding(&embedding.embedding));
+        });
+        query_builder
+            .push(" ON DUPLICATE KEY UPDATE embedding = VALUE(embedding), created_at = NOW(6)");
+
+        query_builder
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_stamped_message_embeddings(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageEmbedding>, RepositoryError> {
+        // Reactions have no timestamp, so the latest stamped messages stand in for the latest
+        // stamps
+        let rows = sqlx::query_as!(
+            EmbeddingRow,
+            r#"
+            SELECT e.message_id AS `message_id: _`, e.embedding
+            FROM message_embeddings e
+            JOIN messages m ON e.message_id = m.id
+            WHERE EXISTS (
+                SELECT 1 FROM reactions r WHERE r.message_id = m.id AND r.user_id = ?
+            )
+            ORDER BY m.created_at DESC
+            LIMIT ?
+            "#,
+            user_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        rows.into_iter().map(TryInto::try_into).collect()
+    }
+
+    async fn find_candidate_embeddings(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageEmbedding>, RepositoryError> {
+        let rows = sqlx::query_as!(
+            EmbeddingRow,
+            r#"
+            SELECT e.message_id AS `message_id: _`, e.embedding
+            FROM message_embeddings e
+            JOIN messages m ON e.message_id = m.id
+            WHERE m.created_at > DATE_SUB(NOW(), INTERVAL 7 DAY)
+              AND m.user_id != ?
+              AND m.id NOT IN (SELECT message_id FROM read_messages WHERE user_id = ?)
+              AND NOT EXISTS (SELECT 1 FROM read_horizons h WHERE h.user_id = ? AND m.created_at < h.read_before)
+              AND m.user_id NOT IN (SELECT muted_user_id FROM muted_users WHERE user_id = ?)
+              AND m.channel_id NOT IN (SELECT channel_id FROM muted_channels WHERE user_id = ?)
+              AND (
+                m.user_id NOT IN (SELECT user_id FROM user_settings WHERE recommend_only_to_acquaintances)
+                OR EXISTS (SELECT 1 FROM follows f WHERE f.user_id = m.user_id AND f.followed_user_id = ?)
+                OR EXISTS (
+                  SELECT 1 FROM reactions ar JOIN messages am ON ar.message_id = am.id
+                  WHERE ar.user_id = m.user_id AND am.user_id = ?
+                )
+              )
+            ORDER BY m.created_at DESC
+            LIMIT ?
+            "#,
+            user_id,
+            user_id,
+            user_id,
+            user_id,
+            user_id,
+            user_id,
+            user_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        rows.into_iter().map(TryInto::try_into).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::{
+        message::MariaDbMessageRepository, user::MariaDbUserRepository,
+    };
+    use domain::{
+        repository::{MessageWriter, UserRepository},
+        test_factories::{MessageBuilder, ReactionBuilder, UserBuilder},
+    };
+    use time::OffsetDateTime;
+
+    #[test]
+    fn embeddings_round_trip() {
+        let embedding = vec![0.25, -1.5, 3.0];
+
+        assert_eq!(
+            decode_embedding(&encode_embedding(&embedding)).unwrap(),
+            embedding
+        );
+        assert!(decode_embedding(&[0, 1, 2]).is_err());
+    }
+
+    #[sqlx::test]
+    async fn test_embeddings(pool: sqlx::MySqlPool) {
+        let repo = MariaDbEmbeddingRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        // Candidates are limited to recent messages
+        let now = OffsetDateTime::now_utc();
+        let stamped = MessageBuilder::new()
+            .created_at(now)
+            .reactions(vec![ReactionBuilder::new().user_id(user.id).build()])
+            .build();
+        let candidate = MessageBuilder::new().created_at(now).build();
+        let own = MessageBuilder::new()
+            .created_at(now)
+            .user_id(user.id)
+            .build();
+        message_repo
+            .save_batch(&[stamped.clone(), candidate.clone(), own.clone()])
+            .await
+            .unwrap();
+
+        let mut pending: Vec<Uuid> = repo
+            .find_messages_without_embedding(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        pending.sort();
+        let mut expected = vec![stamped.id, candidate.id, own.id];
+        expected.sort();
+        assert_eq!(pending, expected);
+
+        let embeddings: Vec<MessageEmbedding> = [&stamped, &candidate, &own]
+            .into_iter()
+            .map(|message| MessageEmbedding {
+                message_id: message.id,
+                embedding: vec![1.0, 0.5],
+            })
+            .collect();
+        repo.save_embeddings(&embeddings).await.unwrap();
+        assert!(
+            repo.find_messages_without_embedding(10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let stamped_embeddings = repo
+            .find_stamped_message_embeddings(&user.id, 10)
+            .await
+            .unwrap();
+        assert_eq!(stamped_embeddings, vec![embeddings[0].clone()]);
+
+        // The user's own messages are excluded
+        let mut candidates: Vec<Uuid> = repo
+            .find_candidate_embeddings(&user.id, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.message_id)
+            .collect();
+        candidates.sort();
+        let mut expected = vec![stamped.id, candidate.id];
+        expected.sort();
+        assert_eq!(candidates, expected);
+    }
+}
@@ -0,0 +1,227 @@
+use domain::{
+    error::RepositoryError,
+    model::{HiddenMessage, IgnoredRecommendations, Message},
+    repository::FeedbackRepository,
+};
+use sqlx::MySqlPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbFeedbackRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbFeedbackRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl FeedbackRepository for MariaDbFeedbackRepository {
+    async fn hide_message(&self, user_id: &Uuid, message: &Message) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT IGNORE INTO hidden_messages (user_id, message_id, author_id, channel_id)
+            VALUES (?, ?, ?, ?)
+            "#,
+            user_id,
+            message.id,
+            message.user_id,
+            message.channel_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_hidden_messages(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<HiddenMessage>, RepositoryError> {
+        let hidden_messages = sqlx::query_as!(
+            HiddenMessage,
+            r#"
+            SELECT
+                message_id AS `message_id: _`,
+                author_id AS `author_id: _`,
+                channel_id AS `channel_id: _`
+            FROM hidden_messages
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(hidden_messages)
+    }
+
+    async fn find_ignored_recommendations(
+        &self,
+        user_id: &Uuid,
+        since: OffsetDateTime,
+        until: OffsetDateTime,
+    ) -> Result<Vec<IgnoredRecommendations>, RepositoryError> {
+        // Impressions without a reason were served outside of the recommended timeline
+        let ignored = sqlx::query_as!(
+            IgnoredRecommendations,
+            r#"
+            SELECT
+                m.user_id AS `author_id: _`,
+                m.channel_id AS `channel_id: _`,
+                COUNT(*) AS `count!: i64`
+            FROM impressions i
+            JOIN messages m ON m.id = i.message_id
+            WHERE i.user_id = ?
+              AND i.reason IS NOT NULL
+              AND i.first_served_at >= ?
+              AND i.first_served_at < ?
+              AND NOT EXISTS (
+                  SELECT 1 FROM read_messages rm
+                  WHERE rm.user_id = i.user_id AND rm.message_id = i.message_id
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM read_horizons h
+                  WHERE h.user_id = i.user_id AND m.created_at < h.read_before
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM reactions r
+                  WHERE r.user_id = i.user_id AND r.message_id = i.message_id
+              )
+            GROUP BY m.user_id, m.channel_id
+            "#,
+            user_id,
+            since,
+            until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(ignored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::{
+        impression::MariaDbImpressionRepository, message::MariaDbMessageRepository,
+        user::MariaDbUserRepository,
+    };
+    use domain::{
+        model::{Impression, RecommendationReason},
+        repository::{ImpressionRepository, MessageWriter, UserRepository},
+        test_factories::{MessageBuilder, UserBuilder},
+    };
+    use time::Duration;
+
+    #[sqlx::test]
+    async fn test_hide_and_find_hidden_messages(pool: sqlx::MySqlPool) {
+        let repo = MariaDbFeedbackRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create user first (FK constraint)
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let message = MessageBuilder::new().build();
+
+        repo.hide_message(&user.id, &message).await.unwrap();
+        // Hiding twice is a no-op
+        repo.hide_message(&user.id, &message).await.unwrap();
+
+        let hidden = repo.find_hidden_messages(&user.id).await.unwrap();
+        assert_eq!(
+            hidden,
+            vec![HiddenMessage {
+                message_id: message.id,
+                author_id: message.user_id,
+                channel_id: message.channel_id,
+            }]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_find_ignored_recommendations(pool: sqlx::MySqlPool) {
+        let repo = MariaDbFeedbackRepository::new(pool.clone());
+        let impression_repo = MariaDbImpressionRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let ignored = MessageBuilder::new().build();
+        let ignored_again = MessageBuilder::new()
+            .user_id(ignored.user_id)
+            .channel_id(ignored.channel_id)
+            .build();
+        let read = MessageBuilder::new()
+            .user_id(ignored.user_id)
+            .channel_id(ignored.channel_id)
+            .build();
+        let not_recommended = MessageBuilder::new().build();
+        message_repo
+            .save_batch(&[
+                ignored.clone(),
+                ignored_again.clone(),
+                read.clone(),
+                not_recommended.clone(),
+            ])
+            .await
+            .unwrap();
+
+        let impressions: Vec<Impression> = [&ignored, &ignored_again, &read]
+            .into_iter()
+            .map(|message| Impression {
+                message_id: message.id,
+                reason: Some(RecommendationReason::Popular),
+            })
+            .chain([Impression {
+                message_id: not_recommended.id,
+                reason: None,
+            }])
+            .collect();
+        impression_repo
+            .record_impressions(&user.id, &impressions)
+            .await
+            .unwrap();
+        message_repo
+            .mark_messages_as_read(&user.id.into(), &[read.id.into()])
+            .await
+            .unwrap();
+
+        let now = OffsetDateTime::now_utc();
+        let result = repo
+            .find_ignored_recommendations(
+                &user.id,
+                now - Duration::hours(1),
+                now + Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            vec![IgnoredRecommendations {
+                author_id: ignored.user_id,
+                channel_id: ignored.channel_id,
+                count: 2,
+            }]
+        );
+
+        // Recommendations served after the period may still be read
+        let result = repo
+            .find_ignored_recommendations(
+                &user.id,
+                now - Duration::hours(2),
+                now - Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use domain::{
+    error::RepositoryError, id::UserId, model::MessageListItem, repository::FollowRepository,
+};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+use crate::repository::mariadb::message::{MessageRow, hydrate_messages};
+
+#[derive(Debug)]
+pub struct MariaDbFollowRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbFollowRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl FollowRepository for MariaDbFollowRepository {
+    async fn follow_user(
+        &self,
+        user_id: &UserId,
+        followed_user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT IGNORE INTO follows (user_id, followed_user_id)
+            VALUES (?, ?)
+            "#,
+            user_id.as_uuid(),
+            followed_user_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn unfollow_user(
+        &self,
+        user_id: &UserId,
+        followed_user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM follows
+            WHERE user_id = ? AND followed_user_id = ?
+            "#,
+            user_id.as_uuid(),
+            followed_user_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_followed_messages(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        let messages: Vec<MessageRow> = sqlx::query_as!(
+            MessageRow,
+            r#"
+            SELECT
+                m.id AS `id: _`,
+                m.user_id AS `user_id: _`,
+                m.channel_id AS `channel_id: _`,
+                m.content,
+                m.created_at,
+                m.updated_at,
+                u.handle AS user_handle,
+                u.display_name AS user_display_name
+            FROM follows f
+            JOIN messages m ON f.followed_user_id = m.user_id
+            LEFT JOIN users u ON m.user_id = u.id
+            WHERE f.user_id = ?
+              AND m.user_id NOT IN (
+                SELECT blocked_user_id FROM blocks WHERE user_id = ?
+                UNION
+                SELECT user_id FROM blocks WHERE blocked_user_id = ?
+              )
+            ORDER BY m.created_at DESC
+            LIMIT ?
+            "#,
+            user_id,
+            user_id,
+            user_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        hydrate_messages(&self.pool, messages).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::{
+        block::MariaDbBlockRepository, message::MariaDbMessageRepository,
+        user::MariaDbUserRepository,
+    };
+    use domain::{
+        repository::{BlockRepository, MessageWriter, UserRepository},
+        test_factories::{MessageBuilder, UserBuilder},
+    };
+    use fake::{Fake, uuid::UUIDv4};
+    use time::{Duration, OffsetDateTime};
+
+    #[sqlx::test]
+    async fn test_find_followed_messages(pool: sqlx::MySqlPool) {
+        let repo = MariaDbFollowRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create user first (FK constraint)
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+
+        let followed_user_id: Uuid = UUIDv4.fake();
+        let now = OffsetDateTime::now_utc();
+        let older = MessageBuilder::new()
+            .user_id(followed_user_id)
+            .created_at(now - Duration::minutes(2))
+            .build();
+        let newer = MessageBuilder::new()
+            .user_id(followed_user_id)
+            .created_at(now - Duration::minutes(1))
+            .build();
+        let unrelated = MessageBuilder::new().build();
+        message_repo
+            .save_batch(&[older.clone(), newer.clone(), unrelated])
+            .await
+            .unwrap();
+
+        repo.follow_user(&user.id.into(), &followed_user_id.into())
+            .await
+            .unwrap();
+        // Following twice is a no-op
+        repo.follow_user(&user.id.into(), &followed_user_id.into())
+            .await
+            .unwrap();
+
+        let messages = repo.find_followed_messages(&user.id, 10).await.unwrap();
+
+        // Newest first
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].id, newer.id);
+        assert_eq!(messages[1].id, older.id);
+
+        repo.unfollow_user(&user.id.into(), &followed_user_id.into())
+            .await
+            .unwrap();
+
+        let messages = repo.find_followed_messages(&user.id, 10).await.unwrap();
+        assert!(messages.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_find_followed_messages_excludes_blocked_users(pool: sqlx::MySqlPool) {
+        let repo = MariaDbFollowRepository::new(pool.clone());
+        let block_repo = MariaDbBlockRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let user = UserBuilder::new().build();
+        let followed = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        user_repo.save(&followed).await.unwrap();
+
+        let message = MessageBuilder::new().user_id(followed.id).build();
+        message_repo.save(&message).await.unwrap();
+        repo.follow_user(&user.id.into(), &followed.id.into())
+            .await
+            .unwrap();
+
+        // The followed user blocks the follower
+        block_repo
+            .block_user(&followed.id.into(), &user.id.into())
+            .await
+            .unwrap();
+
+        let messages = repo.find_followed_messages(&user.id, 10).await.unwrap();
+        assert!(messages.is_empty());
+    }
+}
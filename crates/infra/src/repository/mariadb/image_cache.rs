@@ -0,0 +1,82 @@
+use domain::{
+    error::RepositoryError,
+    model::{CachedImage, ImageKind, ImageSize},
+    repository::ImageCacheRepository,
+};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbImageCacheRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbImageCacheRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageCacheRepository for MariaDbImageCacheRepository {
+    async fn find(
+        &self,
+        kind: ImageKind,
+        id: &Uuid,
+        size: ImageSize,
+    ) -> Result<Option<CachedImage>, RepositoryError> {
+        let kind: &'static str = kind.into();
+        let size: &'static str = size.into();
+        let row = sqlx::query!(
+            r#"
+            SELECT data, content_type, stored_at
+            FROM image_cache
+            WHERE kind = ? AND id = ? AND size = ?
+            "#,
+            kind,
+            id,
+            size
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(row.map(|row| CachedImage {
+            data: row.data,
+            content_type: row.content_type,
+            stored_at: row.stored_at,
+        }))
+    }
+
+    async fn save(
+        &self,
+        kind: ImageKind,
+        id: &Uuid,
+        size: ImageSize,
+        image: &[u8],
+        content_type: &str,
+    ) -> Result<(), RepositoryError> {
+        let kind: &'static str = kind.into();
+        let size: &'static str = size.into();
+        sqlx::query!(
+            r#"
+            INSERT INTO image_cache (kind, id, size, content_type, data)
+            VALUES (?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                content_type = VALUES(content_type),
+                data = VALUES(data),
+                stored_at = CURRENT_TIMESTAMP(6)
+            "#,
+            kind,
+            id,
+            size,
+            content_type,
+            image
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
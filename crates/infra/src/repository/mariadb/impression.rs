@@ -0,0 +1,275 @@
+use std::str::FromStr;
+
+use domain::{
+    error::RepositoryError,
+    model::{EngagementMetrics, Impression, RecommendationReason},
+    repository::ImpressionRepository,
+};
+use sqlx::{MySqlPool, QueryBuilder};
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbImpressionRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbImpressionRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImpressionRepository for MariaDbImpressionRepository {
+    async fn record_impressions(
+        &self,
+        user_id: &Uuid,
+        impressions: &[Impression],
+    ) -> Result<(), RepositoryError> {
+        if impressions.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder =
+            QueryBuilder::new("INSERT INTO impressions (user_id, message_id, reason) ");
+
+        query_builder.push_values(impressions, |mut separated, impression| {
+            let reason: Option<&'static str> = impression.reason.map(Into::into);
+            separated
+                .push_bind(user_id)
+                .push_bind(impression.message_id)
+                .push_bind(reason);
+        });
+        query_builder.push(
+            " ON DUPLICATE KEY UPDATE impression_count = impression_count + 1, last_served_at = NOW(6)",
+        );
+
+        query_builder
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn compute_engagement_metrics(
+        &self,
+        since: OffsetDateTime,
+        until: OffsetDateTime,
+    ) -> Result<Vec<EngagementMetrics>, RepositoryError> {
+        struct EngagementRecord {
+            reason: String,
+            impressions: i64,
+            reads: i64,
+            reactions: i64,
+        }
+
+        // Reactions have no timestamp, so reactions made before the impression are counted too
+        let records = sqlx::query_as!(
+            EngagementRecord,
+            r#"
+            SELECT
+                i.reason AS `reason!: String`,
+                COUNT(*) AS `impressions!: i64`,
+                COUNT(rm.message_id) AS `reads!: i64`,
+                CAST(SUM(EXISTS(
+                    SELECT 1 FROM reactions r
+                    WHERE r.message_id = i.message_id AND r.user_id = i.user_id
+                )) AS SIGNED) AS `reactions!: i64`
+            FROM impressions i
+            LEFT JOIN read_messages rm
+                ON rm.user_id = i.user_id
+               AND rm.message_id = i.message_id
+               AND rm.read_at >= i.first_served_at
+            WHERE i.reason IS NOT NULL
+              AND i.first_served_at >= ?
+              AND i.first_served_at < ?
+            GROUP BY i.reason
+            "#,
+            since,
+            until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                Ok(EngagementMetrics {
+                    reason: RecommendationReason::from_str(&record.reason)
+                        .map_err(|e| RepositoryError::Serialization(e.to_string()))?,
+                    impressions: record.impressions,
+                    reads: record.reads,
+                    reactions: record.reactions,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_engagement_metrics(
+        &self,
+        date: Date,
+        metrics: &[EngagementMetrics],
+    ) -> Result<(), RepositoryError> {
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder = QueryBuilder::new(
+            "INSERT INTO recommendation_metrics (date, reason, impression_count, read_count, reaction_count) ",
+        );
+
+        query_builder.push_values(metrics, |mut separated, metrics| {
+            let reason: &'static str = metrics.reason.into();
+            separated
+                .push_bind(date)
+                .push_bind(reason)
+                .push_bind(metrics.impressions)
+                .push_bind(metrics.reads)
+                .push_bind(metrics.reactions);
+        });
+        query_builder.push(
+            " ON DUPLICATE KEY UPDATE impression_count = VALUE(impression_count), read_count = VALUE(read_count), reaction_count = VALUE(reaction_count), computed_at = NOW(6)",
+        );
+
+        query_builder
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::{
+        message::MariaDbMessageRepository, user::MariaDbUserRepository,
+    };
+    use domain::{
+        repository::{MessageWriter, UserRepository},
+        test_factories::{MessageBuilder, ReactionBuilder, UserBuilder},
+    };
+    use time::Duration;
+
+    #[sqlx::test]
+    async fn test_record_impressions_counts_repeats(pool: sqlx::MySqlPool) {
+        let repo = MariaDbImpressionRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+
+        // Create user and messages first (FK constraints)
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let message = MessageBuilder::new().build();
+        let other_message = MessageBuilder::new().build();
+        message_repo
+            .save_batch(&[message.clone(), other_message.clone()])
+            .await
+            .unwrap();
+
+        let impression = Impression {
+            message_id: message.id,
+            reason: Some(RecommendationReason::Popular),
+        };
+        let other_impression = Impression {
+            message_id: other_message.id,
+            reason: None,
+        };
+        repo.record_impressions(&user.id, &[impression, other_impression])
+            .await
+            .unwrap();
+        repo.record_impressions(&user.id, &[impression])
+            .await
+            .unwrap();
+
+        let count: i32 = sqlx::query_scalar(
+            "SELECT impression_count FROM impressions WHERE user_id = ? AND message_id = ?",
+        )
+        .bind(user.id)
+        .bind(message.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(count, 2);
+
+        let count: i32 = sqlx::query_scalar(
+            "SELECT impression_count FROM impressions WHERE user_id = ? AND message_id = ?",
+        )
+        .bind(user.id)
+        .bind(other_message.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[sqlx::test]
+    async fn test_compute_engagement_metrics(pool: sqlx::MySqlPool) {
+        let repo = MariaDbImpressionRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let read_message = MessageBuilder::new().build();
+        let reacted_message = MessageBuilder::new()
+            .reactions(vec![ReactionBuilder::new().user_id(user.id).build()])
+            .build();
+        let ignored_message = MessageBuilder::new().build();
+        message_repo
+            .save_batch(&[
+                read_message.clone(),
+                reacted_message.clone(),
+                ignored_message.clone(),
+            ])
+            .await
+            .unwrap();
+
+        let impressions: Vec<Impression> = [&read_message, &reacted_message, &ignored_message]
+            .into_iter()
+            .map(|message| Impression {
+                message_id: message.id,
+                reason: Some(RecommendationReason::SimilarUsers),
+            })
+            .collect();
+        repo.record_impressions(&user.id, &impressions)
+            .await
+            .unwrap();
+        message_repo
+            .mark_messages_as_read(&user.id.into(), &[read_message.id.into()])
+            .await
+            .unwrap();
+
+        let now = OffsetDateTime::now_utc();
+        let metrics = repo
+            .compute_engagement_metrics(now - Duration::hours(1), now + Duration::hours(1))
+            .await
+            .unwrap();
+        let expected = EngagementMetrics {
+            reason: RecommendationReason::SimilarUsers,
+            impressions: 3,
+            reads: 1,
+            reactions: 1,
+        };
+        assert_eq!(metrics, vec![expected]);
+
+        // Saving twice replaces the metrics of the day
+        repo.save_engagement_metrics(now.date(), &metrics)
+            .await
+            .unwrap();
+        repo.save_engagement_metrics(now.date(), &metrics)
+            .await
+            .unwrap();
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM recommendation_metrics")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+}
@@ -0,0 +1,64 @@
+//! `IN` lists with a few fixed lengths, so that queries filtering by a list of IDs have only a few
+//! shapes and their prepared statements are reused, instead of being prepared for every length.
+
+use sqlx::{MySql, QueryBuilder};
+use uuid::Uuid;
+
+/// The lengths lists are padded to. Longer lists must be split into chunks of [`MAX_LEN`].
+const LENGTHS: [usize; 4] = [1, 8, 32, 128];
+pub(super) const MAX_LEN: usize = LENGTHS[LENGTHS.len() - 1];
+
+/// Pushes `(?, ?, ...)` binding the IDs, padded to the next fixed length by repeating the last
+/// ID, which doesn't change the result of `IN`.
+///
+/// # Panics
+///
+/// Panics if `ids` is empty or longer than [`MAX_LEN`].
+pub(super) fn push(query_builder: &mut QueryBuilder<'_, MySql>, ids: &[Uuid]) {
+    let last = *ids.last().expect("IN lists must not be empty");
+    let len = LENGTHS
+        .into_iter()
+        .find(|&len| len >= ids.len())
+        .expect("IN lists must be split into chunks of MAX_LEN");
+
+    query_builder.push("(");
+    let mut separated = query_builder.separated(", ");
+    for &id in ids {
+        separated.push_bind(id);
+    }
+    for _ in ids.len()..len {
+        separated.push_bind(last);
+    }
+    query_builder.push(")");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, uuid::UUIDv4};
+
+    fn sql(ids: &[Uuid]) -> String {
+        let mut query_builder = QueryBuilder::new("SELECT 1 WHERE id IN ");
+        push(&mut query_builder, ids);
+        query_builder.into_sql()
+    }
+
+    #[test]
+    fn pads_lists_to_fixed_lengths() {
+        let ids: Vec<Uuid> = (0..MAX_LEN).map(|_| UUIDv4.fake()).collect();
+
+        assert_eq!(sql(&ids[..1]), "SELECT 1 WHERE id IN (?)");
+        assert_eq!(sql(&ids[..2]), sql(&ids[..8]));
+        assert_eq!(sql(&ids[..9]), sql(&ids[..32]));
+        assert_eq!(sql(&ids[..33]), sql(&ids));
+        assert_ne!(sql(&ids[..8]), sql(&ids[..9]));
+    }
+
+    #[test]
+    #[should_panic(expected = "chunks")]
+    fn rejects_long_lists() {
+        let ids: Vec<Uuid> = (0..=MAX_LEN).map(|_| UUIDv4.fake()).collect();
+
+        sql(&ids);
+    }
+}
@@ -0,0 +1,149 @@
+use domain::{error::RepositoryError, model::JobRun, repository::JobRunRepository};
+use sqlx::MySqlPool;
+use time::OffsetDateTime;
+
+#[derive(Debug)]
+pub struct MariaDbJobRunRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbJobRunRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl JobRunRepository for MariaDbJobRunRepository {
+    async fn save(&self, run: &JobRun) -> Result<u64, RepositoryError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO job_runs (job_name, started_at, duration_ms, succeeded, error)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            run.job_name,
+            run.started_at,
+            run.duration_ms,
+            run.succeeded,
+            run.error
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(result.last_insert_id())
+    }
+
+    async fn import(&self, id: u64, run: &JobRun) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO job_runs (id, job_name, started_at, duration_ms, succeeded, error)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            id,
+            run.job_name,
+            run.started_at,
+            run.duration_ms,
+            run.succeeded,
+            run.error
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_recent(&self, limit: i64) -> Result<Vec<JobRun>, RepositoryError> {
+        let runs = sqlx::query_as!(
+            JobRun,
+            r#"
+            SELECT job_name, started_at, duration_ms, succeeded, error
+            FROM job_runs
+            ORDER BY started_at DESC
+            LIMIT ?
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(runs)
+    }
+
+    async fn delete_started_before(&self, before: OffsetDateTime) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM job_runs
+            WHERE started_at < ?
+            "#,
+            before
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::test_factories::fake_recent_datetime;
+    use time::Duration;
+
+    fn job_run(job_name: &str, started_at: OffsetDateTime, error: Option<&str>) -> JobRun {
+        JobRun {
+            job_name: job_name.to_string(),
+            started_at,
+            duration_ms: 42,
+            succeeded: error.is_none(),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_save_and_find_recent(pool: sqlx::MySqlPool) {
+        let repo = MariaDbJobRunRepository::new(pool);
+        let now = fake_recent_datetime();
+
+        let older = job_run("message_crawler", now - Duration::minutes(1), None);
+        let newer = job_run("session_cleanup", now, Some("connection refused"));
+        repo.save(&older).await.unwrap();
+        repo.save(&newer).await.unwrap();
+
+        let runs = repo.find_recent(10).await.unwrap();
+
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].job_name, "session_cleanup");
+        assert!(!runs[0].succeeded);
+        assert_eq!(runs[0].error.as_deref(), Some("connection refused"));
+        assert_eq!(runs[1].job_name, "message_crawler");
+        assert!(runs[1].succeeded);
+
+        let runs = repo.find_recent(1).await.unwrap();
+        assert_eq!(runs.len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn test_delete_started_before(pool: sqlx::MySqlPool) {
+        let repo = MariaDbJobRunRepository::new(pool);
+        let now = fake_recent_datetime();
+
+        repo.save(&job_run("message_crawler", now - Duration::days(8), None))
+            .await
+            .unwrap();
+        repo.save(&job_run("message_crawler", now, None))
+            .await
+            .unwrap();
+
+        repo.delete_started_before(now - Duration::days(7))
+            .await
+            .unwrap();
+
+        let runs = repo.find_recent(10).await.unwrap();
+        assert_eq!(runs.len(), 1);
+    }
+}
@@ -0,0 +1,90 @@
+use domain::{error::RepositoryError, model::LinkPreview, repository::LinkPreviewRepository};
+use sqlx::{MySqlPool, QueryBuilder, prelude::FromRow};
+use time::OffsetDateTime;
+
+#[derive(Debug)]
+pub struct MariaDbLinkPreviewRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbLinkPreviewRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[derive(FromRow)]
+struct LinkPreviewRow {
+    url: String,
+    title: Option<String>,
+    description: Option<String>,
+    image_url: Option<String>,
+}
+
+impl From<LinkPreviewRow> for LinkPreview {
+    fn from(row: LinkPreviewRow) -> Self {
+        LinkPreview {
+            url: row.url,
+            title: row.title,
+            description: row.description,
+            image_url: row.image_url,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkPreviewRepository for MariaDbLinkPreviewRepository {
+    async fn find_fetched_since(
+        &self,
+        urls: &[String],
+        since: OffsetDateTime,
+    ) -> Result<Vec<LinkPreview>, RepositoryError> {
+        if urls.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut query_builder = QueryBuilder::new(
+            "SELECT url, title, description, image_url FROM link_previews WHERE url_hash IN (",
+        );
+        let mut separated = query_builder.separated(", ");
+        for url in urls {
+            separated
+                .push("UNHEX(SHA2(")
+                .push_bind_unseparated(url)
+                .push_unseparated(", 256))");
+        }
+        query_builder.push(") AND fetched_at >= ").push_bind(since);
+
+        let rows = query_builder
+            .build_query_as::<LinkPreviewRow>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(LinkPreview::from).collect())
+    }
+
+    async fn save(&self, preview: &LinkPreview) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO link_previews (url_hash, url, title, description, image_url)
+            VALUES (UNHEX(SHA2(?, 256)), ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                title = VALUES(title),
+                description = VALUES(description),
+                image_url = VALUES(image_url),
+                fetched_at = CURRENT_TIMESTAMP(6)
+            "#,
+            preview.url,
+            preview.url,
+            preview.title,
+            preview.description,
+            preview.image_url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
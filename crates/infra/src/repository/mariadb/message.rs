@@ -1,22 +1,155 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    slice,
+    sync::Arc,
+};
 
+use crate::repository::mariadb::{
+    in_list,
+    read_filter::{BloomFilter, ReadFilter},
+};
 use domain::{
+    citation,
     error::RepositoryError,
-    model::{Message, MessageListItem, Reaction, User},
-    repository::MessageRepository,
+    hashtag,
+    id::{MessageId, StampId, UserId},
+    model::{
+        Channel, ChannelActivity, Highlight, Message, MessageCursor, MessageListItem, Reaction,
+        TrendingTag, User, UserStats,
+    },
+    repository::{MessageReader, MessageWriter, NO_VIEWER},
 };
+use serde::Deserialize;
 use sqlx::{MySql, MySqlPool, QueryBuilder, Transaction, prelude::FromRow};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+/// Threads deeper than this are cut off.
+const MAX_THREAD_DEPTH: i64 = 50;
+/// How many times as many candidates are fetched per page as requested, once read ones are
+/// dropped after the query.
+const READ_OVERFETCH: i64 = 2;
+/// How many reads are loaded at a time while a read filter is built.
+const READ_FILTER_PAGE: i64 = 10_000;
+
 #[derive(Debug)]
 pub struct MariaDbMessageRepository {
     pool: MySqlPool,
+    read_filter: Arc<ReadFilter>,
 }
 
 impl MariaDbMessageRepository {
     pub fn new(pool: MySqlPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            read_filter: Arc::default(),
+        }
+    }
+
+    /// Fetches up to `limit` rows of the query built by `build` that the user hasn't read, in the
+    /// order of the query. `build` pushes the query up to its `ORDER BY`, passing its argument to
+    /// [`push_read_filter`].
+    ///
+    /// Until the user's read filter is ready, reads are excluded in SQL while the filter is built
+    /// in the background. Once it is ready, reads are dropped after the query instead, and further
+    /// pages are fetched until `limit` unread rows are found or the query runs out of rows.
+    async fn fetch_unread<'q>(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+        build: impl Fn(bool) -> QueryBuilder<'q, MySql>,
+    ) -> Result<Vec<MessageRow>, RepositoryError> {
+        let Ok(limit_len) = usize::try_from(limit) else {
+            return Ok(vec![]);
+        };
+        if limit_len == 0 {
+            return Ok(vec![]);
+        }
+
+        if !self.read_filter.is_ready(user_id) {
+            self.build_read_filter(user_id);
+
+            let mut query_builder = build(true);
+            query_builder.push(" LIMIT ");
+            query_builder.push_bind(limit);
+            return query_builder
+                .build_query_as::<MessageRow>()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| RepositoryError::Database(e.to_string()));
+        }
+
+        let page_size = limit * READ_OVERFETCH;
+        let mut unread = vec![];
+        let mut offset = 0;
+        loop {
+            let mut query_builder = build(false);
+            query_builder.push(" LIMIT ");
+            query_builder.push_bind(page_size);
+            query_builder.push(" OFFSET ");
+            query_builder.push_bind(offset);
+            let rows = query_builder
+                .build_query_as::<MessageRow>()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| RepositoryError::Database(e.to_string()))?;
+            let exhausted = (rows.len() as i64) < page_size;
+
+            unread.extend(self.drop_read(user_id, rows).await?);
+            if exhausted || unread.len() >= limit_len {
+                break;
+            }
+            offset += page_size;
+        }
+        unread.truncate(limit_len);
+
+        Ok(unread)
+    }
+
+    /// Drops the rows the user has read one by one, keeping their order.
+    /// Only the hits of the user's read filter are checked in the database.
+    async fn drop_read(
+        &self,
+        user_id: &Uuid,
+        rows: Vec<MessageRow>,
+    ) -> Result<Vec<MessageRow>, RepositoryError> {
+        if rows.is_empty() {
+            return Ok(rows);
+        }
+
+        let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+        // The filter may have expired since the query
+        let possibly_read = self.read_filter.possibly_read(user_id, &ids).unwrap_or(ids);
+        let read_ids: HashSet<Uuid> = self
+            .find_read_message_ids(user_id, &possibly_read)
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(rows
+            .into_iter()
+            .filter(|row| !read_ids.contains(&row.id))
+            .collect())
+    }
+
+    /// Builds the user's read filter in the background, unless it is already being built.
+    fn build_read_filter(&self, user_id: &Uuid) {
+        if !self.read_filter.start_building(user_id) {
+            return;
+        }
+
+        let pool = self.pool.clone();
+        let read_filter = self.read_filter.clone();
+        let user_id = *user_id;
+        tokio::spawn(async move {
+            match load_reads(&pool, &user_id).await {
+                Ok(bloom) => read_filter.finish_building(&user_id, bloom),
+                Err(e) => {
+                    tracing::warn!("Failed to build a read filter: {:?}", e);
+                    read_filter.abandon_building(&user_id);
+                }
+            }
+        });
     }
 
     async fn update_reactions(
@@ -29,18 +162,16 @@ impl MariaDbMessageRepository {
             return Ok(());
         }
 
-        let mut query_builder = QueryBuilder::new("DELETE FROM reactions WHERE message_id IN (");
-        let mut separated = query_builder.separated(", ");
-        for id in message_ids {
-            separated.push_bind(id);
-        }
-        query_builder.push(")");
+        for chunk in message_ids.chunks(in_list::MAX_LEN) {
+            let mut query_builder = QueryBuilder::new("DELETE FROM reactions WHERE message_id IN ");
+            in_list::push(&mut query_builder, chunk);
 
-        query_builder
-            .build()
-            .execute(&mut **tx)
-            .await
-            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+            query_builder
+                .build()
+                .execute(&mut **tx)
+                .await
+                .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        }
 
         if reactions.is_empty() {
             return Ok(());
@@ -66,25 +197,77 @@ impl MariaDbMessageRepository {
 
         Ok(())
     }
+
+    /// Replaces the tags of the messages with the ones in their contents, so that tags removed by
+    /// edits are removed too.
+    async fn update_tags(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        messages: &[Message],
+    ) -> Result<(), RepositoryError> {
+        let message_ids: Vec<Uuid> = messages.iter().map(|message| message.id).collect();
+        for chunk in message_ids.chunks(in_list::MAX_LEN) {
+            let mut query_builder =
+                QueryBuilder::new("DELETE FROM message_tags WHERE message_id IN ");
+            in_list::push(&mut query_builder, chunk);
+
+            query_builder
+                .build()
+                .execute(&mut **tx)
+                .await
+                .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        }
+
+        let tags = messages
+            .iter()
+            .flat_map(|message| {
+                hashtag::extract_hashtags(&message.content)
+                    .into_iter()
+                    .map(move |tag| (message, tag))
+            })
+            .collect::<Vec<_>>();
+        if tags.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder =
+            QueryBuilder::new("INSERT INTO message_tags (message_id, tag, created_at) ");
+
+        query_builder.push_values(tags, |mut separated, (message, tag)| {
+            separated
+                .push_bind(message.id)
+                .push_bind(tag)
+                .push_bind(message.created_at);
+        });
+
+        query_builder
+            .build()
+            .execute(&mut **tx)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
 }
 
 #[derive(FromRow)]
-struct MessageRow {
-    id: Uuid,
-    user_id: Uuid,
-    channel_id: Uuid,
-    content: String,
-    created_at: OffsetDateTime,
-    updated_at: OffsetDateTime,
-
-    user_handle: Option<String>,
-    user_display_name: Option<String>,
+pub(super) struct MessageRow {
+    pub(super) id: Uuid,
+    pub(super) user_id: Uuid,
+    pub(super) channel_id: Uuid,
+    pub(super) content: String,
+    pub(super) created_at: OffsetDateTime,
+    pub(super) updated_at: OffsetDateTime,
+
+    pub(super) user_handle: Option<String>,
+    pub(super) user_display_name: Option<String>,
 }
 
 #[derive(FromRow)]
 struct ReactionRow {
     message_id: Uuid,
     stamp_id: Uuid,
+    stamp_name: Option<String>,
     user_id: Uuid,
     stamp_count: i32,
 }
@@ -93,17 +276,83 @@ impl From<ReactionRow> for Reaction {
     fn from(row: ReactionRow) -> Self {
         Reaction {
             stamp_id: row.stamp_id,
+            stamp_name: row.stamp_name,
             user_id: row.user_id,
             stamp_count: row.stamp_count,
         }
     }
 }
 
-struct MessageRowWithReactions(MessageRow, Vec<ReactionRow>);
+/// Aggregates the reactions of the message `m` into a JSON array, so that messages can be fetched
+/// with their reactions in a single query. The column is `NULL` if there are no reactions.
+pub(super) const REACTIONS_JSON_COLUMN: &str = r#"
+    (
+        SELECT JSON_ARRAYAGG(JSON_OBJECT(
+            'stamp_id', HEX(r.stamp_id),
+            'stamp_name', s.name,
+            'user_id', HEX(r.user_id),
+            'stamp_count', r.stamp_count
+        ))
+        FROM reactions r
+        LEFT JOIN stamps s ON r.stamp_id = s.id
+        WHERE r.message_id = m.id
+    ) AS reactions
+"#;
+
+#[derive(FromRow)]
+pub(super) struct MessageRowWithReactionsJson {
+    #[sqlx(flatten)]
+    message: MessageRow,
+    reactions: Option<String>,
+    channel_name: Option<String>,
+    channel_path: Option<String>,
+    channel_archived: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct ReactionJson {
+    stamp_id: Uuid,
+    stamp_name: Option<String>,
+    user_id: Uuid,
+    stamp_count: i32,
+}
+
+impl TryFrom<MessageRowWithReactionsJson> for MessageListItem {
+    type Error = RepositoryError;
+
+    fn try_from(row: MessageRowWithReactionsJson) -> Result<Self, Self::Error> {
+        let reactions = match row.reactions {
+            Some(json) => serde_json::from_str::<Vec<ReactionJson>>(&json)
+                .map_err(|e| RepositoryError::Database(format!("invalid reactions: {}", e)))?
+                .into_iter()
+                .map(|r| Reaction {
+                    stamp_id: r.stamp_id,
+                    stamp_name: r.stamp_name,
+                    user_id: r.user_id,
+                    stamp_count: r.stamp_count,
+                })
+                .collect(),
+            None => vec![],
+        };
+        let channel = match (row.channel_name, row.channel_path, row.channel_archived) {
+            (Some(name), Some(path), Some(archived)) => Some(Channel {
+                id: row.message.channel_id,
+                name,
+                path,
+                archived,
+            }),
+            _ => None,
+        };
+
+        Ok(MessageRowWithReactions(row.message, reactions, channel).into())
+    }
+}
+
+struct MessageRowWithReactions(MessageRow, Vec<Reaction>, Option<Channel>);
 
 impl From<MessageRowWithReactions> for MessageListItem {
     fn from(value: MessageRowWithReactions) -> Self {
-        let (row, reactions) = (value.0, value.1);
+        let MessageRowWithReactions(row, reactions, channel) = value;
 
         MessageListItem {
             id: row.id,
@@ -117,16 +366,22 @@ impl From<MessageRowWithReactions> for MessageListItem {
                 _ => None,
             },
             channel_id: row.channel_id,
+            channel,
+            reply_to_message_id: citation::extract_reply_to(&row.content),
+            quoted_message: None,
+            link_previews: vec![],
             content: row.content,
             created_at: row.created_at,
             updated_at: row.updated_at,
-            reactions: reactions.into_iter().map(Into::into).collect(),
+            reactions,
+            reaction_summaries: vec![],
+            reason: None,
         }
     }
 }
 
 #[async_trait::async_trait]
-impl MessageRepository for MariaDbMessageRepository {
+impl MessageReader for MariaDbMessageRepository {
     async fn find_latest_message_time(&self) -> Result<Option<OffsetDateTime>, RepositoryError> {
         let result = sqlx::query_scalar!(
             r#"
@@ -172,9 +427,15 @@ impl MessageRepository for MariaDbMessageRepository {
         let reactions = sqlx::query_as!(
             ReactionRow,
             r#"
-            SELECT message_id AS `message_id: _`, stamp_id AS `stamp_id: _`, user_id AS `user_id: _`, stamp_count
-            FROM reactions
-            WHERE message_id = ?
+            SELECT
+                r.message_id AS `message_id: _`,
+                r.stamp_id AS `stamp_id: _`,
+                s.name AS stamp_name,
+                r.user_id AS `user_id: _`,
+                r.stamp_count
+            FROM reactions r
+            LEFT JOIN stamps s ON r.stamp_id = s.id
+            WHERE r.message_id = ?
             "#,
             id
         )
@@ -193,6 +454,55 @@ impl MessageRepository for MariaDbMessageRepository {
         }))
     }
 
+    async fn find_list_items_by_ids(
+        &self,
+        ids: &[Uuid],
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut rows: Vec<MessageRowWithReactionsJson> = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(in_list::MAX_LEN) {
+            let mut query_builder = QueryBuilder::new(
+                r#"
+                SELECT
+                    m.id,
+                    m.user_id,
+                    m.channel_id,
+                    m.content,
+                    m.created_at,
+                    m.updated_at,
+                    u.handle AS user_handle,
+                    u.display_name AS user_display_name,
+                    c.name AS channel_name,
+                    c.path AS channel_path,
+                    c.archived AS channel_archived,
+                "#,
+            );
+            query_builder.push(REACTIONS_JSON_COLUMN);
+            query_builder.push(
+                r#"
+                FROM messages m
+                LEFT JOIN users u ON m.user_id = u.id
+                LEFT JOIN channels c ON m.channel_id = c.id
+                WHERE m.id IN
+                "#,
+            );
+            in_list::push(&mut query_builder, chunk);
+
+            rows.extend(
+                query_builder
+                    .build_query_as::<MessageRowWithReactionsJson>()
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| RepositoryError::Database(e.to_string()))?,
+            );
+        }
+
+        rows.into_iter().map(TryInto::try_into).collect()
+    }
+
     async fn find_sync_candidates(
         &self,
     ) -> Result<Vec<(Uuid, OffsetDateTime, OffsetDateTime)>, RepositoryError> {
@@ -221,149 +531,234 @@ impl MessageRepository for MariaDbMessageRepository {
             .collect())
     }
 
-    async fn remove_reaction(
+    async fn find_read_message_ids(
         &self,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
         user_id: &Uuid,
-    ) -> Result<(), RepositoryError> {
-        sqlx::query!(
-            r#"
-            DELETE FROM reactions
-            WHERE message_id = ? AND stamp_id = ? AND user_id = ?
-            "#,
-            message_id,
-            stamp_id,
-            user_id
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        message_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let mut read_ids = vec![];
+        for chunk in message_ids.chunks(in_list::MAX_LEN) {
+            let mut query_builder = QueryBuilder::new(
+                r#"
+                SELECT m.id
+                FROM messages m
+                WHERE (
+                  EXISTS (SELECT 1 FROM read_messages rm WHERE rm.message_id = m.id AND rm.user_id = "#,
+            );
+            query_builder.push_bind(user_id);
+            query_builder.push(
+                r#")
+                  OR EXISTS (
+                    SELECT 1 FROM read_horizons h
+                    WHERE m.created_at < h.read_before AND h.user_id = "#,
+            );
+            query_builder.push_bind(user_id);
+            query_builder.push(")) AND m.id IN ");
+            in_list::push(&mut query_builder, chunk);
+
+            read_ids.extend(
+                query_builder
+                    .build_query_scalar::<Uuid>()
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| RepositoryError::Database(e.to_string()))?,
+            );
+        }
 
-        Ok(())
+        Ok(read_ids)
     }
 
-    async fn save(&self, message: &Message) -> Result<(), RepositoryError> {
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| RepositoryError::Database(e.to_string()))?;
-
-        sqlx::query!(
-            r#"
-            INSERT INTO messages (id, user_id, channel_id, content, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?)
-            ON DUPLICATE KEY UPDATE content=VALUE(content), updated_at=VALUE(updated_at), last_crawled_at=NOW(6)
-            "#,
-            message.id,
-            message.user_id,
-            message.channel_id,
-            message.content,
-            message.created_at,
-            message.updated_at
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+    async fn find_read_message_ids_by_users(
+        &self,
+        user_ids: &[Uuid],
+        message_ids: &[Uuid],
+    ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError> {
+        let mut reads = vec![];
+        for user_chunk in user_ids.chunks(in_list::MAX_LEN) {
+            for message_chunk in message_ids.chunks(in_list::MAX_LEN) {
+                let mut query_builder = QueryBuilder::new(
+                    r#"
+                    SELECT rm.user_id, rm.message_id
+                    FROM read_messages rm
+                    WHERE rm.user_id IN "#,
+                );
+                in_list::push(&mut query_builder, user_chunk);
+                query_builder.push(" AND rm.message_id IN ");
+                in_list::push(&mut query_builder, message_chunk);
+                query_builder.push(
+                    r#"
+                    UNION
+                    SELECT h.user_id, m.id
+                    FROM read_horizons h
+                    JOIN messages m ON m.created_at < h.read_before
+                    WHERE h.user_id IN "#,
+                );
+                in_list::push(&mut query_builder, user_chunk);
+                query_builder.push(" AND m.id IN ");
+                in_list::push(&mut query_builder, message_chunk);
+
+                reads.extend(
+                    query_builder
+                        .build_query_as::<(Uuid, Uuid)>()
+                        .fetch_all(&self.pool)
+                        .await
+                        .map_err(|e| RepositoryError::Database(e.to_string()))?,
+                );
+            }
+        }
 
-        let reactions_data: Vec<_> = message
-            .reactions
-            .iter()
-            .map(|r| (message.id, r.clone()))
-            .collect();
+        Ok(reads)
+    }
 
-        self.update_reactions(&mut tx, &[message.id], &reactions_data)
+    async fn find_top_reacted_messages(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        let messages = self
+            .fetch_unread(user_id, limit, |check_read_messages| {
+                let mut query_builder = QueryBuilder::new(
+                    r#"
+                    SELECT
+                        m.id,
+                        m.user_id,
+                        m.channel_id,
+                        m.content,
+                        m.created_at,
+                        m.updated_at,
+                        u.handle AS user_handle,
+                        u.display_name AS user_display_name
+                    FROM messages m
+                    LEFT JOIN users u ON m.user_id = u.id
+                    LEFT JOIN reactions r ON m.id = r.message_id
+                    WHERE m.created_at > DATE_SUB(NOW(), INTERVAL 7 DAY)
+                    "#,
+                );
+
+                query_builder.push(" AND m.user_id != ");
+                query_builder.push_bind(*user_id);
+                push_read_filter(&mut query_builder, user_id, check_read_messages);
+                push_mute_filter(&mut query_builder, user_id);
+                push_acquaintance_filter(&mut query_builder, user_id);
+                query_builder.push(
+                    r#"
+                    GROUP BY m.id
+                    ORDER BY (COUNT(r.user_id) / POW((TIMESTAMPDIFF(HOUR, m.created_at, NOW()) + 2), 1.8)) DESC, m.id
+                    "#,
+                );
+                query_builder
+            })
             .await?;
 
-        tx.commit()
-            .await
-            .map_err(|e| RepositoryError::Database(e.to_string()))?;
-
-        Ok(())
+        hydrate_messages(&self.pool, messages).await
     }
 
-    async fn save_batch(&self, messages: &[Message]) -> Result<(), RepositoryError> {
-        if messages.is_empty() {
-            return Ok(());
+    async fn find_messages_by_author_allowlist(
+        &self,
+        author_ids: &[Uuid],
+        limit: i64,
+        user_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        if author_ids.is_empty() {
+            return Ok(vec![]);
         }
 
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| RepositoryError::Database(e.to_string()))?;
-        let mut query_builder = QueryBuilder::new(
-            "INSERT INTO messages (id, user_id, channel_id, content, created_at, updated_at) ",
-        );
-
-        query_builder.push_values(messages, |mut separated, message| {
-            separated
-                .push_bind(message.id)
-                .push_bind(message.user_id)
-                .push_bind(message.channel_id)
-                .push_bind(&message.content)
-                .push_bind(message.created_at)
-                .push_bind(message.updated_at);
-        });
-        query_builder
-            .push(" ON DUPLICATE KEY UPDATE content=VALUE(content), updated_at=VALUE(updated_at), last_crawled_at=NOW(6)");
-        query_builder
-            .build()
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| RepositoryError::Database(e.to_string()))?;
-
-        let reactions_data = messages
-            .iter()
-            .flat_map(|msg| {
-                msg.reactions
-                    .iter()
-                    .map(move |reaction| (msg.id, reaction.clone()))
-            })
-            .collect::<Vec<_>>();
-
-        let message_ids = messages.iter().map(|m| m.id).collect::<Vec<_>>();
-        self.update_reactions(&mut tx, &message_ids, &reactions_data)
-            .await?;
-
-        tx.commit()
-            .await
-            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        // Each chunk is limited on its own, so that the newest messages across chunks are kept
+        let mut messages: Vec<MessageRow> = vec![];
+        for chunk in author_ids.chunks(in_list::MAX_LEN) {
+            let rows = self
+                .fetch_unread(user_id, limit, |check_read_messages| {
+                    let mut query_builder = QueryBuilder::new(
+                        r#"
+                        SELECT
+                            m.id,
+                            m.user_id,
+                            m.channel_id,
+                            m.content,
+                            m.created_at,
+                            m.updated_at,
+                            u.handle AS user_handle,
+                            u.display_name AS user_display_name
+                        FROM messages m
+                        LEFT JOIN users u ON m.user_id = u.id
+                        WHERE m.created_at > DATE_SUB(NOW(), INTERVAL 30 DAY)
+                        "#,
+                    );
+
+                    query_builder.push(" AND m.user_id IN ");
+                    in_list::push(&mut query_builder, chunk);
+                    push_read_filter(&mut query_builder, user_id, check_read_messages);
+                    push_mute_filter(&mut query_builder, user_id);
+                    push_acquaintance_filter(&mut query_builder, user_id);
+                    query_builder.push(" ORDER BY m.created_at DESC, m.id DESC");
+                    query_builder
+                })
+                .await?;
+            messages.extend(rows);
+        }
+        let messages = newest_first(messages, limit);
 
-        Ok(())
+        hydrate_messages(&self.pool, messages).await
     }
 
-    async fn mark_messages_as_read(
+    async fn find_messages_by_channel_allowlist(
         &self,
+        channel_ids: &[Uuid],
+        limit: i64,
         user_id: &Uuid,
-        message_ids: &[Uuid],
-    ) -> Result<(), RepositoryError> {
-        if message_ids.is_empty() {
-            return Ok(());
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        if channel_ids.is_empty() {
+            return Ok(vec![]);
         }
 
-        let mut query_builder =
-            QueryBuilder::new("INSERT IGNORE INTO read_messages (user_id, message_id) ");
-
-        query_builder.push_values(message_ids, |mut separated, message_id| {
-            separated.push_bind(user_id).push_bind(message_id);
-        });
-
-        query_builder
-            .build()
-            .execute(&self.pool)
-            .await
-            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        // Each chunk is limited on its own, so that the newest messages across chunks are kept
+        let mut messages: Vec<MessageRow> = vec![];
+        for chunk in channel_ids.chunks(in_list::MAX_LEN) {
+            let rows = self
+                .fetch_unread(user_id, limit, |check_read_messages| {
+                    let mut query_builder = QueryBuilder::new(
+                        r#"
+                        SELECT
+                            m.id,
+                            m.user_id,
+                            m.channel_id,
+                            m.content,
+                            m.created_at,
+                            m.updated_at,
+                            u.handle AS user_handle,
+                            u.display_name AS user_display_name
+                        FROM messages m
+                        LEFT JOIN users u ON m.user_id = u.id
+                        WHERE m.created_at > DATE_SUB(NOW(), INTERVAL 30 DAY)
+                        "#,
+                    );
+
+                    query_builder.push(" AND m.channel_id IN ");
+                    in_list::push(&mut query_builder, chunk);
+                    push_read_filter(&mut query_builder, user_id, check_read_messages);
+                    push_mute_filter(&mut query_builder, user_id);
+                    push_acquaintance_filter(&mut query_builder, user_id);
+                    query_builder.push(" AND m.user_id != ");
+                    query_builder.push_bind(*user_id);
+                    query_builder.push(" ORDER BY m.created_at DESC, m.id DESC");
+                    query_builder
+                })
+                .await?;
+            messages.extend(rows);
+        }
+        let messages = newest_first(messages, limit);
 
-        Ok(())
+        hydrate_messages(&self.pool, messages).await
     }
 
-    async fn find_top_reacted_messages(
+    async fn find_trending_messages(
         &self,
         user_id: &Uuid,
+        window_hours: i64,
         limit: i64,
     ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        // Unlike the recommendations, read messages are kept so that the list is shared
+        // across users
         let messages: Vec<MessageRow> = sqlx::query_as!(
             MessageRow,
             r#"
@@ -378,14 +773,27 @@ impl MessageRepository for MariaDbMessageRepository {
                 u.display_name AS user_display_name
             FROM messages m
             LEFT JOIN users u ON m.user_id = u.id
-            LEFT JOIN reactions r ON m.id = r.message_id
-            WHERE m.created_at > DATE_SUB(NOW(), INTERVAL 7 DAY)
+            JOIN reactions r ON m.id = r.message_id
+            WHERE m.created_at > DATE_SUB(NOW(), INTERVAL ? HOUR)
               AND m.user_id != ?
-              AND m.id NOT IN (SELECT message_id FROM read_messages WHERE user_id = ?)
+              AND m.user_id NOT IN (SELECT muted_user_id FROM muted_users WHERE user_id = ?)
+              AND m.channel_id NOT IN (SELECT channel_id FROM muted_channels WHERE user_id = ?)
+              AND (
+                m.user_id NOT IN (SELECT user_id FROM user_settings WHERE recommend_only_to_acquaintances)
+                OR EXISTS (SELECT 1 FROM follows f WHERE f.user_id = m.user_id AND f.followed_user_id = ?)
+                OR EXISTS (
+                  SELECT 1 FROM reactions ar JOIN messages am ON ar.message_id = am.id
+                  WHERE ar.user_id = m.user_id AND am.user_id = ?
+                )
+              )
             GROUP BY m.id
-            ORDER BY (COUNT(r.user_id) / POW((TIMESTAMPDIFF(HOUR, m.created_at, NOW()) + 2), 1.8)) DESC
+            ORDER BY (COUNT(r.user_id) / (TIMESTAMPDIFF(MINUTE, m.created_at, NOW()) / 60 + 1)) DESC
             LIMIT ?
             "#,
+            window_hours,
+            user_id,
+            user_id,
+            user_id,
             user_id,
             user_id,
             limit
@@ -394,70 +802,72 @@ impl MessageRepository for MariaDbMessageRepository {
         .await
         .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        self.hydrate_messages(messages).await
+        hydrate_messages(&self.pool, messages).await
     }
 
-    async fn find_messages_by_author_allowlist(
+    async fn find_highlights(
         &self,
-        author_ids: &[Uuid],
+        since: OffsetDateTime,
         limit: i64,
-        user_id: &Uuid,
-    ) -> Result<Vec<MessageListItem>, RepositoryError> {
-        if author_ids.is_empty() {
-            return Ok(vec![]);
-        }
-
-        let mut query_builder = QueryBuilder::new(
+    ) -> Result<Vec<Highlight>, RepositoryError> {
+        let highlights = sqlx::query_as!(
+            Highlight,
             r#"
             SELECT
-                m.id,
-                m.user_id,
-                m.channel_id,
-                m.content,
+                COUNT(r.user_id) AS `reaction_count!: i64`,
+                COUNT(DISTINCT r.stamp_id) AS `stamp_count!: i64`,
                 m.created_at,
-                m.updated_at,
-                u.handle AS user_handle,
-                u.display_name AS user_display_name
+                IF(COALESCE(s.share_content_in_highlights, FALSE), m.content, NULL) AS content
             FROM messages m
-            LEFT JOIN users u ON m.user_id = u.id
-            WHERE m.created_at > DATE_SUB(NOW(), INTERVAL 30 DAY)
+            JOIN reactions r ON m.id = r.message_id
+            LEFT JOIN user_settings s ON m.user_id = s.user_id
+            WHERE m.created_at > ?
+              AND NOT COALESCE(s.recommend_only_to_acquaintances, FALSE)
+            GROUP BY m.id
+            ORDER BY reaction_count DESC, m.created_at DESC
+            LIMIT ?
             "#,
-        );
-
-        query_builder.push(" AND m.user_id IN (");
-        let mut separated = query_builder.separated(", ");
-
-        for id in author_ids {
-            separated.push_bind(id);
-        }
+            since,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(format!("could not fetch highlights: {}", e)))?;
 
-        query_builder.push(") ");
-        query_builder
-            .push(" AND m.id NOT IN (SELECT message_id FROM read_messages WHERE user_id = ");
-        query_builder.push_bind(user_id);
-        query_builder.push(") ");
-        query_builder.push(" ORDER BY m.created_at DESC LIMIT ");
-        query_builder.push_bind(limit);
+        Ok(highlights)
+    }
 
-        let messages = query_builder
-            .build_query_as()
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+    async fn find_most_active_channels(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<ChannelActivity>, RepositoryError> {
+        let channels = sqlx::query_as!(
+            ChannelActivity,
+            r#"
+            SELECT
+                channel_id AS `channel_id: _`,
+                COUNT(*) AS `message_count!: i64`
+            FROM messages
+            WHERE created_at > DATE_SUB(NOW(), INTERVAL 7 DAY)
+            GROUP BY channel_id
+            ORDER BY message_count DESC
+            LIMIT ?
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        self.hydrate_messages(messages).await
+        Ok(channels)
     }
 
-    async fn find_messages_by_channel_allowlist(
+    async fn find_recent_messages_across_channels(
         &self,
-        channel_ids: &[Uuid],
-        limit: i64,
         user_id: &Uuid,
+        per_channel: i64,
+        limit: i64,
     ) -> Result<Vec<MessageListItem>, RepositoryError> {
-        if channel_ids.is_empty() {
-            return Ok(vec![]);
-        }
-
         let mut query_builder = QueryBuilder::new(
             r#"
             SELECT
@@ -469,96 +879,175 @@ impl MessageRepository for MariaDbMessageRepository {
                 m.updated_at,
                 u.handle AS user_handle,
                 u.display_name AS user_display_name
-            FROM messages m
+            FROM (
+                SELECT
+                    m.*,
+                    ROW_NUMBER() OVER (PARTITION BY m.channel_id ORDER BY m.created_at DESC) AS channel_rank
+                FROM messages m
+                WHERE m.created_at > DATE_SUB(NOW(), INTERVAL 1 DAY)
+                  AND m.user_id != "#,
+        );
+        query_builder.push_bind(*user_id);
+        push_read_filter(&mut query_builder, user_id, true);
+        push_mute_filter(&mut query_builder, user_id);
+        push_acquaintance_filter(&mut query_builder, user_id);
+        query_builder.push(
+            r#"
+            ) m
             LEFT JOIN users u ON m.user_id = u.id
-            WHERE m.created_at > DATE_SUB(NOW(), INTERVAL 30 DAY)
-            "#,
+            WHERE m.channel_rank <= "#,
         );
-
-        query_builder.push(" AND m.channel_id IN (");
-        let mut separated = query_builder.separated(", ");
-        for id in channel_ids {
-            separated.push_bind(id);
-        }
-        query_builder.push(") ");
-
-        query_builder
-            .push(" AND m.id NOT IN (SELECT message_id FROM read_messages WHERE user_id = ");
-        query_builder.push_bind(user_id);
-        query_builder.push(") ");
-        query_builder.push(" AND m.user_id != ");
-        query_builder.push_bind(user_id);
-
+        query_builder.push_bind(per_channel);
         query_builder.push(" ORDER BY m.created_at DESC LIMIT ");
         query_builder.push_bind(limit);
 
         let messages = query_builder
-            .build_query_as()
+            .build_query_as::<MessageRow>()
             .fetch_all(&self.pool)
             .await
             .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        self.hydrate_messages(messages).await
+        hydrate_messages(&self.pool, messages).await
     }
-}
 
-impl MariaDbMessageRepository {
-    async fn hydrate_messages(
+    async fn search(
         &self,
-        messages: Vec<MessageRow>,
+        query: &str,
+        limit: i64,
+        viewer: &Uuid,
     ) -> Result<Vec<MessageListItem>, RepositoryError> {
-        if messages.is_empty() {
-            return Ok(vec![]);
-        }
-
-        let mut query_builder = QueryBuilder::new(
+        // Natural language mode, since boolean mode gives operators in user input a meaning
+        let messages: Vec<MessageRow> = sqlx::query_as!(
+            MessageRow,
             r#"
             SELECT
-                message_id,
-                stamp_id,
-                user_id,
-                stamp_count
-            FROM reactions
-            WHERE message_id IN (
+                m.id AS `id: _`,
+                m.user_id AS `user_id: _`,
+                m.channel_id AS `channel_id: _`,
+                m.content,
+                m.created_at,
+                m.updated_at,
+                u.handle AS user_handle,
+                u.display_name AS user_display_name
+            FROM messages m
+            LEFT JOIN users u ON m.user_id = u.id
+            WHERE MATCH(m.content) AGAINST (? IN NATURAL LANGUAGE MODE)
+              AND m.id NOT IN (SELECT message_id FROM read_messages WHERE user_id = ?)
+              AND NOT EXISTS (SELECT 1 FROM read_horizons h WHERE h.user_id = ? AND m.created_at < h.read_before)
+              AND m.user_id NOT IN (SELECT muted_user_id FROM muted_users WHERE user_id = ?)
+              AND m.channel_id NOT IN (SELECT channel_id FROM muted_channels WHERE user_id = ?)
+            ORDER BY MATCH(m.content) AGAINST (? IN NATURAL LANGUAGE MODE) DESC
+            LIMIT ?
             "#,
-        );
-        let mut separated = query_builder.separated(", ");
-
-        for msg in &messages {
-            separated.push_bind(msg.id);
-        }
-
-        query_builder.push(")");
+            query,
+            viewer,
+            viewer,
+            viewer,
+            viewer,
+            query,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        let reactions = query_builder
-            .build_query_as::<ReactionRow>()
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| RepositoryError::Database(format!("could not fetch reactions: {}", e)))?;
+        hydrate_messages(&self.pool, messages).await
+    }
 
-        let mut message_reaction_map = HashMap::<Uuid, Vec<ReactionRow>>::new();
+    async fn find_by_tag(
+        &self,
+        tag: &str,
+        limit: i64,
+        viewer: &Uuid,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        // Like the explore page, read messages are kept
+        let messages: Vec<MessageRow> = sqlx::query_as!(
+            MessageRow,
+            r#"
+            SELECT
+                m.id AS `id: _`,
+                m.user_id AS `user_id: _`,
+                m.channel_id AS `channel_id: _`,
+                m.content,
+                m.created_at,
+                m.updated_at,
+                u.handle AS user_handle,
+                u.display_name AS user_display_name
+            FROM message_tags t
+            JOIN messages m ON t.message_id = m.id
+            LEFT JOIN users u ON m.user_id = u.id
+            WHERE t.tag = ?
+              AND m.user_id NOT IN (SELECT muted_user_id FROM muted_users WHERE user_id = ?)
+              AND m.channel_id NOT IN (SELECT channel_id FROM muted_channels WHERE user_id = ?)
+            ORDER BY t.created_at DESC
+            LIMIT ?
+            "#,
+            tag,
+            viewer,
+            viewer,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        for reaction in reactions {
-            let entry = message_reaction_map.entry(reaction.message_id).or_default();
-            entry.push(reaction);
-        }
+        hydrate_messages(&self.pool, messages).await
+    }
 
-        let messages = messages
-            .into_iter()
-            .map(|msg| {
-                let reactions = message_reaction_map.remove(&msg.id).unwrap_or_default();
-                MessageListItem::from(MessageRowWithReactions(msg, reactions))
-            })
-            .collect();
+    async fn find_by_author(
+        &self,
+        author_id: &Uuid,
+        before: Option<MessageCursor>,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        let before_created_at = before.map(|c| c.created_at);
+        let before_id = before.map(|c| c.id);
+        // Messages posted at the same time are ordered by ID, so that pages never skip them
+        let messages: Vec<MessageRow> = sqlx::query_as!(
+            MessageRow,
+            r#"
+            SELECT
+                m.id AS `id: _`,
+                m.user_id AS `user_id: _`,
+                m.channel_id AS `channel_id: _`,
+                m.content,
+                m.created_at,
+                m.updated_at,
+                u.handle AS user_handle,
+                u.display_name AS user_display_name
+            FROM messages m
+            LEFT JOIN users u ON m.user_id = u.id
+            WHERE m.user_id = ?
+              AND (
+                ? IS NULL
+                OR m.created_at < ?
+                OR (m.created_at = ? AND m.id < ?)
+              )
+            ORDER BY m.created_at DESC, m.id DESC
+            LIMIT ?
+            "#,
+            author_id,
+            before_created_at,
+            before_created_at,
+            before_created_at,
+            before_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        Ok(messages)
+        hydrate_messages(&self.pool, messages).await
     }
 
-    #[cfg(test)]
-    pub async fn find_all_messages_for_test(
+    async fn find_by_channel(
         &self,
-        user_id: &Uuid,
+        channel_id: &Uuid,
+        before: Option<MessageCursor>,
+        limit: i64,
     ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        let before_created_at = before.map(|c| c.created_at);
+        let before_id = before.map(|c| c.id);
         let messages: Vec<MessageRow> = sqlx::query_as!(
             MessageRow,
             r#"
@@ -573,305 +1062,2071 @@ impl MariaDbMessageRepository {
                 u.display_name AS user_display_name
             FROM messages m
             LEFT JOIN users u ON m.user_id = u.id
-            WHERE
-                m.user_id != ?
-                AND m.id NOT IN (
-                    SELECT message_id
-                    FROM read_messages
-                    WHERE user_id = ?
-                )
-            ORDER BY m.created_at DESC
+            WHERE m.channel_id = ?
+              AND (
+                ? IS NULL
+                OR m.created_at < ?
+                OR (m.created_at = ? AND m.id < ?)
+              )
+            ORDER BY m.created_at DESC, m.id DESC
+            LIMIT ?
             "#,
-            user_id,
-            user_id
+            channel_id,
+            before_created_at,
+            before_created_at,
+            before_created_at,
+            before_id,
+            limit
         )
         .fetch_all(&self.pool)
         .await
-        .map_err(|e| RepositoryError::Database(format!("could not fetch messages: {}", e)))?;
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        self.hydrate_messages(messages).await
+        hydrate_messages(&self.pool, messages).await
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use domain::test_factories::{MessageBuilder, ReactionBuilder, fake_recent_datetime};
-    use fake::{Fake, uuid::UUIDv4};
-    use std::time::Duration;
-    use tokio::time::sleep;
+    async fn find_public_messages(
+        &self,
+        channel_ids: &[Uuid],
+        before: Option<MessageCursor>,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        if channel_ids.is_empty() {
+            return Ok(vec![]);
+        }
 
-    #[sqlx::test]
-    async fn test_save_and_find_message(pool: sqlx::MySqlPool) {
-        let repo = MariaDbMessageRepository::new(pool);
+        let mut messages: Vec<MessageRow> = vec![];
+        for chunk in channel_ids.chunks(in_list::MAX_LEN) {
+            let mut query_builder = QueryBuilder::new(
+                r#"
+                SELECT
+                    m.id,
+                    m.user_id,
+                    m.channel_id,
+                    m.content,
+                    m.created_at,
+                    m.updated_at,
+                    u.handle AS user_handle,
+                    u.display_name AS user_display_name
+                FROM messages m
+                LEFT JOIN users u ON m.user_id = u.id
+                WHERE m.user_id NOT IN (
+                    SELECT user_id FROM user_settings WHERE recommend_only_to_acquaintances
+                )
+                "#,
+            );
+            query_builder.push(" AND m.channel_id IN ");
+            in_list::push(&mut query_builder, chunk);
+            if let Some(before) = before {
+                query_builder.push(" AND (m.created_at < ");
+                query_builder.push_bind(before.created_at);
+                query_builder.push(" OR (m.created_at = ");
+                query_builder.push_bind(before.created_at);
+                query_builder.push(" AND m.id < ");
+                query_builder.push_bind(before.id);
+                query_builder.push("))");
+            }
+            query_builder.push(" ORDER BY m.created_at DESC, m.id DESC LIMIT ");
+            query_builder.push_bind(limit);
+
+            messages.extend(
+                query_builder
+                    .build_query_as::<MessageRow>()
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| RepositoryError::Database(e.to_string()))?,
+            );
+        }
+        let messages = newest_first(messages, limit);
 
-        // Create a test message
-        let message = MessageBuilder::new().build();
+        hydrate_messages(&self.pool, messages).await
+    }
 
-        // Save the message
-        repo.save(&message).await.unwrap();
+    async fn find_user_stats(
+        &self,
+        user_id: &Uuid,
+        since: OffsetDateTime,
+    ) -> Result<UserStats, RepositoryError> {
+        sqlx::query_as!(
+            UserStats,
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM messages WHERE user_id = ?) AS `message_count!: i64`,
+                (
+                    SELECT COUNT(*)
+                    FROM messages m
+                    JOIN reactions r ON r.message_id = m.id
+                    WHERE m.user_id = ? AND m.created_at > ?
+                ) AS `reactions_received!: i64`
+            "#,
+            user_id,
+            user_id,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))
+    }
 
-        let user_id = UUIDv4.fake();
-        let messages = repo.find_all_messages_for_test(&user_id).await.unwrap();
+    async fn find_co_reacted_messages(
+        &self,
+        message_id: &Uuid,
+        limit: i64,
+        viewer: &Uuid,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        // Ranked by users rather than reactions, so that a single user stamping a lot cannot
+        // push a message up
+        let messages: Vec<MessageRow> = sqlx::query_as!(
+            MessageRow,
+            r#"
+            SELECT
+                m.id AS `id: _`,
+                m.user_id AS `user_id: _`,
+                m.channel_id AS `channel_id: _`,
+                m.content,
+                m.created_at,
+                m.updated_at,
+                u.handle AS user_handle,
+                u.display_name AS user_display_name
+            FROM (
+                SELECT other.message_id, COUNT(DISTINCT other.user_id) AS user_count
+                FROM reactions r
+                JOIN reactions other
+                  ON other.user_id = r.user_id AND other.message_id <> r.message_id
+                WHERE r.message_id = ? AND r.user_id <> ?
+                GROUP BY other.message_id
+            ) co
+            JOIN messages m ON co.message_id = m.id
+            LEFT JOIN users u ON m.user_id = u.id
+            WHERE m.user_id NOT IN (SELECT muted_user_id FROM muted_users WHERE user_id = ?)
+              AND m.channel_id NOT IN (SELECT channel_id FROM muted_channels WHERE user_id = ?)
+              AND (
+                m.user_id NOT IN (SELECT user_id FROM user_settings WHERE recommend_only_to_acquaintances)
+                OR EXISTS (SELECT 1 FROM follows f WHERE f.user_id = m.user_id AND f.followed_user_id = ?)
+                OR EXISTS (
+                  SELECT 1 FROM reactions ar JOIN messages am ON ar.message_id = am.id
+                  WHERE ar.user_id = m.user_id AND am.user_id = ?
+                )
+              )
+            ORDER BY co.user_count DESC, m.created_at DESC
+            LIMIT ?
+            "#,
+            message_id,
+            viewer,
+            viewer,
+            viewer,
+            viewer,
+            viewer,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        // Verify
-        assert_eq!(messages.len(), 1);
-        assert_eq!(messages[0].id, message.id);
-        assert_eq!(messages[0].content, message.content);
+        hydrate_messages(&self.pool, messages).await
     }
 
-    #[sqlx::test]
-    async fn test_save_message_with_reactions(pool: sqlx::MySqlPool) {
-        let repo = MariaDbMessageRepository::new(pool);
-
-        let reaction = ReactionBuilder::new().build();
+    async fn find_thread(
+        &self,
+        message_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        // Edits can make messages cite each other, so the depth is bounded to stop cycles
+        let messages: Vec<MessageRow> = sqlx::query_as!(
+            MessageRow,
+            r#"
+            WITH RECURSIVE ancestors AS (
+                SELECT id, reply_to_message_id, 0 AS depth
+                FROM messages
+                WHERE id = ?
+                UNION ALL
+                SELECT m.id, m.reply_to_message_id, a.depth + 1
+                FROM messages m
+                JOIN ancestors a ON m.id = a.reply_to_message_id
+                WHERE a.depth < ?
+            ),
+            thread AS (
+                SELECT id, 0 AS depth
+                FROM (SELECT id FROM ancestors ORDER BY depth DESC LIMIT 1) root
+                UNION ALL
+                SELECT m.id, t.depth + 1
+                FROM messages m
+                JOIN thread t ON m.reply_to_message_id = t.id
+                WHERE t.depth < ?
+            )
+            SELECT
+                m.id AS `id: _`,
+                m.user_id AS `user_id: _`,
+                m.channel_id AS `channel_id: _`,
+                m.content,
+                m.created_at,
+                m.updated_at,
+                u.handle AS user_handle,
+                u.display_name AS user_display_name
+            FROM messages m
+            LEFT JOIN users u ON m.user_id = u.id
+            WHERE m.id IN (SELECT id FROM thread)
+            ORDER BY m.created_at, m.id
+            LIMIT ?
+            "#,
+            message_id,
+            MAX_THREAD_DEPTH,
+            MAX_THREAD_DEPTH,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        let message = MessageBuilder::new()
-            .reactions(vec![reaction.clone()])
-            .build();
+        hydrate_messages(&self.pool, messages).await
+    }
 
+    async fn find_trending_tags(
+        &self,
+        window_hours: i64,
+        limit: i64,
+    ) -> Result<Vec<TrendingTag>, RepositoryError> {
+        // Ranked by users rather than messages, so that a single user cannot push a tag up
+        sqlx::query_as!(
+            TrendingTag,
+            r#"
+            SELECT
+                t.tag,
+                COUNT(DISTINCT m.user_id) AS `user_count!: i64`,
+                COUNT(*) AS `message_count!: i64`
+            FROM message_tags t
+            JOIN messages m ON t.message_id = m.id
+            WHERE t.created_at > DATE_SUB(NOW(), INTERVAL ? HOUR)
+            GROUP BY t.tag
+            ORDER BY COUNT(DISTINCT m.user_id) DESC, COUNT(*) DESC, t.tag
+            LIMIT ?
+            "#,
+            window_hours,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageWriter for MariaDbMessageRepository {
+    async fn remove_reaction(
+        &self,
+        message_id: &MessageId,
+        stamp_id: &StampId,
+        user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM reactions
+            WHERE message_id = ? AND stamp_id = ? AND user_id = ?
+            "#,
+            message_id.as_uuid(),
+            stamp_id.as_uuid(),
+            user_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn save(&self, message: &Message) -> Result<(), RepositoryError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO messages (id, user_id, channel_id, content, reply_to_message_id, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE content=VALUE(content), reply_to_message_id=VALUE(reply_to_message_id), updated_at=VALUE(updated_at), last_crawled_at=NOW(6)
+            "#,
+            message.id,
+            message.user_id,
+            message.channel_id,
+            message.content,
+            citation::extract_reply_to(&message.content),
+            message.created_at,
+            message.updated_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        let reactions_data: Vec<_> = message
+            .reactions
+            .iter()
+            .map(|r| (message.id, r.clone()))
+            .collect();
+
+        self.update_reactions(&mut tx, &[message.id], &reactions_data)
+            .await?;
+        self.update_tags(&mut tx, slice::from_ref(message)).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn save_batch(&self, messages: &[Message]) -> Result<(), RepositoryError> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        let mut query_builder = QueryBuilder::new(
+            "INSERT INTO messages (id, user_id, channel_id, content, reply_to_message_id, created_at, updated_at) ",
+        );
+
+        query_builder.push_values(messages, |mut separated, message| {
+            separated
+                .push_bind(message.id)
+                .push_bind(message.user_id)
+                .push_bind(message.channel_id)
+                .push_bind(&message.content)
+                .push_bind(citation::extract_reply_to(&message.content))
+                .push_bind(message.created_at)
+                .push_bind(message.updated_at);
+        });
+        query_builder
+            .push(" ON DUPLICATE KEY UPDATE content=VALUE(content), reply_to_message_id=VALUE(reply_to_message_id), updated_at=VALUE(updated_at), last_crawled_at=NOW(6)");
+        query_builder
+            .build()
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        let reactions_data = messages
+            .iter()
+            .flat_map(|msg| {
+                msg.reactions
+                    .iter()
+                    .map(move |reaction| (msg.id, reaction.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        let message_ids = messages.iter().map(|m| m.id).collect::<Vec<_>>();
+        self.update_reactions(&mut tx, &message_ids, &reactions_data)
+            .await?;
+        self.update_tags(&mut tx, messages).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn mark_messages_as_read(
+        &self,
+        user_id: &UserId,
+        message_ids: &[MessageId],
+    ) -> Result<(), RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(());
+        }
+        let message_ids: Vec<Uuid> = message_ids.iter().copied().map(Uuid::from).collect();
+
+        let mut query_builder =
+            QueryBuilder::new("INSERT IGNORE INTO read_messages (user_id, message_id) ");
+
+        query_builder.push_values(&message_ids, |mut separated, message_id| {
+            separated
+                .push_bind(*user_id.as_uuid())
+                .push_bind(message_id);
+        });
+
+        query_builder
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        self.read_filter.mark_read(user_id.as_uuid(), &message_ids);
+
+        Ok(())
+    }
+
+    async fn roll_up_read_messages(&self, before: OffsetDateTime) -> Result<u64, RepositoryError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        // Each horizon only moves up to the oldest message the user hasn't read, so that rolling
+        // up never marks a message as read. Horizons never move back either, so that a shorter
+        // retention can't make messages unread again
+        sqlx::query!(
+            r#"
+            INSERT INTO read_horizons (user_id, read_before)
+            SELECT u.user_id, COALESCE(
+                (
+                    SELECT MIN(m.created_at)
+                    FROM messages m
+                    WHERE m.created_at < ?
+                      AND m.created_at >= COALESCE(h.read_before, m.created_at)
+                      AND NOT EXISTS (
+                          SELECT 1 FROM read_messages rm
+                          WHERE rm.user_id = u.user_id AND rm.message_id = m.id
+                      )
+                ),
+                ?
+            )
+            FROM (SELECT DISTINCT user_id FROM read_messages) u
+            LEFT JOIN read_horizons h ON u.user_id = h.user_id
+            ON DUPLICATE KEY UPDATE
+                read_before = GREATEST(read_horizons.read_before, VALUES(read_before))
+            "#,
+            before,
+            before
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        let deleted = sqlx::query!(
+            r#"
+            DELETE rm
+            FROM read_messages rm
+            JOIN read_horizons h ON rm.user_id = h.user_id
+            JOIN messages m ON rm.message_id = m.id
+            WHERE m.created_at < h.read_before
+            "#
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?
+        .rows_affected();
+
+        tx.commit()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(deleted)
+    }
+}
+
+/// Pushes a condition excluding messages the user has read: those older than their read horizon,
+/// and those read one by one if `check_read_messages` is set. Otherwise the latter are left to
+/// [`MariaDbMessageRepository::drop_read`].
+fn push_read_filter(
+    query_builder: &mut QueryBuilder<'_, MySql>,
+    user_id: &Uuid,
+    check_read_messages: bool,
+) {
+    query_builder.push(" AND NOT EXISTS (SELECT 1 FROM read_horizons h WHERE h.user_id = ");
+    query_builder.push_bind(*user_id);
+    query_builder.push(" AND m.created_at < h.read_before) ");
+    if check_read_messages {
+        query_builder.push(" AND NOT EXISTS (SELECT 1 FROM read_messages rm WHERE rm.user_id = ");
+        query_builder.push_bind(*user_id);
+        query_builder.push(" AND rm.message_id = m.id) ");
+    }
+}
+
+/// Pushes a condition excluding messages from the users and channels the user muted.
+fn push_mute_filter(query_builder: &mut QueryBuilder<'_, MySql>, user_id: &Uuid) {
+    query_builder
+        .push(" AND m.user_id NOT IN (SELECT muted_user_id FROM muted_users WHERE user_id = ");
+    query_builder.push_bind(*user_id);
+    query_builder.push(") ");
+    query_builder
+        .push(" AND m.channel_id NOT IN (SELECT channel_id FROM muted_channels WHERE user_id = ");
+    query_builder.push_bind(*user_id);
+    query_builder.push(") ");
+}
+
+/// Loads the user's reads into a bloom filter a page at a time, so that the IDs of a user who read
+/// a lot are not all held in memory at once.
+async fn load_reads(pool: &MySqlPool, user_id: &Uuid) -> Result<BloomFilter, RepositoryError> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*)
+        FROM read_messages
+        WHERE user_id = ?
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+    let mut bloom = BloomFilter::with_capacity(count.try_into().unwrap_or(0));
+    let mut after = Uuid::nil();
+    loop {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT message_id AS `message_id: Uuid`
+            FROM read_messages
+            WHERE user_id = ? AND message_id > ?
+            ORDER BY message_id
+            LIMIT ?
+            "#,
+            user_id,
+            after,
+            READ_FILTER_PAGE
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        for id in &ids {
+            bloom.insert(id);
+        }
+
+        match ids.last() {
+            Some(last) if ids.len() as i64 == READ_FILTER_PAGE => after = *last,
+            _ => return Ok(bloom),
+        }
+    }
+}
+
+/// Pushes a condition excluding messages from users who opted out of being recommended to
+/// `viewer`, unless they follow `viewer` or have stamped a message of theirs. Nothing is excluded
+/// for [`NO_VIEWER`].
+fn push_acquaintance_filter(query_builder: &mut QueryBuilder<'_, MySql>, viewer: &Uuid) {
+    if *viewer == NO_VIEWER {
+        return;
+    }
+
+    query_builder.push(
+        r#"
+        AND (
+          m.user_id NOT IN (SELECT user_id FROM user_settings WHERE recommend_only_to_acquaintances)
+          OR EXISTS (SELECT 1 FROM follows f WHERE f.user_id = m.user_id AND f.followed_user_id = "#,
+    );
+    query_builder.push_bind(*viewer);
+    query_builder.push(
+        r#")
+          OR EXISTS (
+            SELECT 1 FROM reactions ar JOIN messages am ON ar.message_id = am.id
+            WHERE ar.user_id = m.user_id AND am.user_id = "#,
+    );
+    query_builder.push_bind(*viewer);
+    query_builder.push("))\n");
+}
+
+/// Attaches reactions and channels to the message rows, keeping the order of the rows.
+///
+/// Queries that can select [`REACTIONS_JSON_COLUMN`] should do so instead, saving a round trip.
+pub(super) async fn hydrate_messages(
+    pool: &MySqlPool,
+    messages: Vec<MessageRow>,
+) -> Result<Vec<MessageListItem>, RepositoryError> {
+    if messages.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let message_ids: Vec<Uuid> = messages.iter().map(|msg| msg.id).collect();
+    let mut message_reaction_map = HashMap::<Uuid, Vec<ReactionRow>>::new();
+
+    for chunk in message_ids.chunks(in_list::MAX_LEN) {
+        let mut query_builder = QueryBuilder::new(
+            r#"
+            SELECT
+                r.message_id,
+                r.stamp_id,
+                s.name AS stamp_name,
+                r.user_id,
+                r.stamp_count
+            FROM reactions r
+            LEFT JOIN stamps s ON r.stamp_id = s.id
+            WHERE r.message_id IN
+            "#,
+        );
+        in_list::push(&mut query_builder, chunk);
+
+        let reactions = query_builder
+            .build_query_as::<ReactionRow>()
+            .fetch_all(pool)
+            .await
+            .map_err(|e| RepositoryError::Database(format!("could not fetch reactions: {}", e)))?;
+
+        for reaction in reactions {
+            let entry = message_reaction_map.entry(reaction.message_id).or_default();
+            entry.push(reaction);
+        }
+    }
+
+    let channels = find_channels(pool, &messages).await?;
+
+    let messages = messages
+        .into_iter()
+        .map(|msg| {
+            let reactions = message_reaction_map
+                .remove(&msg.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect();
+            let channel = channels.get(&msg.channel_id).cloned();
+            MessageListItem::from(MessageRowWithReactions(msg, reactions, channel))
+        })
+        .collect();
+
+    Ok(messages)
+}
+
+/// Sorts the rows fetched in chunks from the newest, keeping at most `limit` rows.
+fn newest_first(mut messages: Vec<MessageRow>, limit: i64) -> Vec<MessageRow> {
+    // Ties are broken by ID like in the queries, so that cursors skip nothing
+    messages.sort_by(|a, b| (b.created_at, b.id).cmp(&(a.created_at, a.id)));
+    messages.truncate(limit.try_into().unwrap_or(0));
+    messages
+}
+
+/// Finds the synced channels of the message rows, keyed by ID.
+async fn find_channels(
+    pool: &MySqlPool,
+    messages: &[MessageRow],
+) -> Result<HashMap<Uuid, Channel>, RepositoryError> {
+    let channel_ids: Vec<Uuid> = messages
+        .iter()
+        .map(|msg| msg.channel_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let mut channels = Vec::with_capacity(channel_ids.len());
+
+    for chunk in channel_ids.chunks(in_list::MAX_LEN) {
+        let mut query_builder =
+            QueryBuilder::new("SELECT id, name, path, archived FROM channels WHERE id IN ");
+        in_list::push(&mut query_builder, chunk);
+
+        channels.extend(
+            query_builder
+                .build_query_as::<(Uuid, String, String, bool)>()
+                .fetch_all(pool)
+                .await
+                .map_err(|e| {
+                    RepositoryError::Database(format!("could not fetch channels: {}", e))
+                })?,
+        );
+    }
+
+    Ok(channels
+        .into_iter()
+        .map(|(id, name, path, archived)| {
+            (
+                id,
+                Channel {
+                    id,
+                    name,
+                    path,
+                    archived,
+                },
+            )
+        })
+        .collect())
+}
+
+impl MariaDbMessageRepository {
+    #[cfg(test)]
+    pub async fn find_all_messages_for_test(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<MessageListItem>, RepositoryError> {
+        let messages: Vec<MessageRow> = sqlx::query_as!(
+            MessageRow,
+            r#"
+            SELECT
+                m.id AS `id: _`,
+                m.user_id AS `user_id: _`,
+                m.channel_id AS `channel_id: _`,
+                m.content,
+                m.created_at,
+                m.updated_at,
+                u.handle AS user_handle,
+                u.display_name AS user_display_name
+            FROM messages m
+            LEFT JOIN users u ON m.user_id = u.id
+            WHERE
+                m.user_id != ?
+                AND m.id NOT IN (
+                    SELECT message_id
+                    FROM read_messages
+                    WHERE user_id = ?
+                )
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM read_horizons h
+                    WHERE h.user_id = ? AND m.created_at < h.read_before
+                )
+            ORDER BY m.created_at DESC
+            "#,
+            user_id,
+            user_id,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(format!("could not fetch messages: {}", e)))?;
+
+        hydrate_messages(&self.pool, messages).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::{
+        channel::MariaDbChannelRepository, mute::MariaDbMuteRepository,
+        stamp::MariaDbStampRepository, user::MariaDbUserRepository,
+    };
+    use domain::{
+        repository::{ChannelRepository, MuteRepository, StampRepository, UserRepository},
+        test_factories::{
+            MessageBuilder, ReactionBuilder, StampBuilder, UserBuilder, fake_recent_datetime,
+        },
+    };
+    use fake::{Fake, uuid::UUIDv4};
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    #[sqlx::test]
+    async fn test_save_and_find_message(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        // Create a test message
+        let message = MessageBuilder::new().build();
+
+        // Save the message
+        repo.save(&message).await.unwrap();
+
+        let user_id = UUIDv4.fake();
+        let messages = repo.find_all_messages_for_test(&user_id).await.unwrap();
+
+        // Verify
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, message.id);
+        assert_eq!(messages[0].content, message.content);
+    }
+
+    #[sqlx::test]
+    async fn test_save_message_with_reactions(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        let reaction = ReactionBuilder::new().build();
+
+        let message = MessageBuilder::new()
+            .reactions(vec![reaction.clone()])
+            .build();
+
+        repo.save(&message).await.unwrap();
+
+        let user_id = UUIDv4.fake();
+
+        let messages = repo.find_all_messages_for_test(&user_id).await.unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].reactions.len(), 1);
+        assert_eq!(messages[0].reactions[0].stamp_id, reaction.stamp_id);
+        assert_eq!(messages[0].reactions[0].stamp_count, reaction.stamp_count);
+    }
+
+    #[sqlx::test]
+    async fn test_remove_reaction(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        let message_id = UUIDv4.fake();
+        let stamp_id = UUIDv4.fake();
+        let user_id = UUIDv4.fake();
+
+        let reaction = ReactionBuilder::new()
+            .stamp_id(stamp_id)
+            .user_id(user_id)
+            .build();
+
+        let message = MessageBuilder::new()
+            .id(message_id)
+            .reactions(vec![reaction])
+            .build();
+
+        // Save with reaction
+        repo.save(&message).await.unwrap();
+
+        let viewer_id = UUIDv4.fake();
+
+        // Verify reaction exists
+        let messages = repo.find_all_messages_for_test(&viewer_id).await.unwrap();
+        assert_eq!(messages[0].reactions.len(), 1);
+
+        // Remove reaction
+        repo.remove_reaction(&message_id.into(), &stamp_id.into(), &user_id.into())
+            .await
+            .unwrap();
+
+        // Verify reaction is removed
+        let messages = repo.find_all_messages_for_test(&viewer_id).await.unwrap();
+        assert_eq!(messages[0].reactions.len(), 0);
+    }
+
+    #[sqlx::test]
+    async fn test_save_batch(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        let channel_id = UUIDv4.fake();
+        let messages = vec![
+            MessageBuilder::new().channel_id(channel_id).build(),
+            MessageBuilder::new().channel_id(channel_id).build(),
+        ];
+
+        repo.save_batch(&messages).await.unwrap();
+
+        let user_id = UUIDv4.fake();
+
+        let saved_messages = repo.find_all_messages_for_test(&user_id).await.unwrap();
+        assert!(saved_messages.len() >= 2); // At least our 2 messages
+    }
+
+    #[sqlx::test]
+    async fn test_find_list_items_by_ids(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        let reaction = ReactionBuilder::new().build();
+        let message = MessageBuilder::new()
+            .reactions(vec![reaction.clone()])
+            .build();
+        let other = MessageBuilder::new().build();
+        repo.save_batch(&[message.clone(), other]).await.unwrap();
+
+        let unknown_id = UUIDv4.fake();
+        let items = repo
+            .find_list_items_by_ids(&[message.id, unknown_id])
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, message.id);
+        assert_eq!(items[0].reactions, vec![reaction]);
+
+        assert!(repo.find_list_items_by_ids(&[]).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_messages_include_synced_channels(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let channel_repo = MariaDbChannelRepository::new(pool);
+
+        let channel = Channel {
+            id: UUIDv4.fake(),
+            name: "general".to_string(),
+            path: "general".to_string(),
+            archived: false,
+        };
+        channel_repo
+            .save_batch(slice::from_ref(&channel))
+            .await
+            .unwrap();
+        let message = MessageBuilder::new().channel_id(channel.id).build();
+        let unsynced = MessageBuilder::new().build();
+        repo.save_batch(&[message.clone(), unsynced.clone()])
+            .await
+            .unwrap();
+
+        let items = repo
+            .find_list_items_by_ids(&[message.id, unsynced.id])
+            .await
+            .unwrap();
+        let channel_of = |id| items.iter().find(|m| m.id == id).unwrap().channel.clone();
+        assert_eq!(channel_of(message.id), Some(channel.clone()));
+        assert_eq!(channel_of(unsynced.id), None);
+
+        let viewer_id = UUIDv4.fake();
+        let items = repo.find_all_messages_for_test(&viewer_id).await.unwrap();
+        let channel_of = |id| items.iter().find(|m| m.id == id).unwrap().channel.clone();
+        assert_eq!(channel_of(message.id), Some(channel));
+        assert_eq!(channel_of(unsynced.id), None);
+    }
+
+    #[sqlx::test]
+    async fn test_reactions_include_cached_stamp_names(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let stamp_repo = MariaDbStampRepository::new(pool);
+
+        let stamp = StampBuilder::new().build();
+        stamp_repo.save(&stamp).await.unwrap();
+        let cached = ReactionBuilder::new().stamp_id(stamp.id).build();
+        let uncached = ReactionBuilder::new().build();
+        let message = MessageBuilder::new()
+            .reactions(vec![cached.clone(), uncached.clone()])
+            .build();
+        repo.save(&message).await.unwrap();
+
+        let stamp_name_of = |reactions: &[Reaction], stamp_id| {
+            reactions
+                .iter()
+                .find(|r| r.stamp_id == stamp_id)
+                .unwrap()
+                .stamp_name
+                .clone()
+        };
+
+        let items = repo.find_list_items_by_ids(&[message.id]).await.unwrap();
+        assert_eq!(
+            stamp_name_of(&items[0].reactions, stamp.id),
+            Some(stamp.name.clone())
+        );
+        assert_eq!(stamp_name_of(&items[0].reactions, uncached.stamp_id), None);
+
+        let viewer_id = UUIDv4.fake();
+        let items = repo.find_all_messages_for_test(&viewer_id).await.unwrap();
+        assert_eq!(
+            stamp_name_of(&items[0].reactions, stamp.id),
+            Some(stamp.name.clone())
+        );
+
+        let found = repo.find_by_id(&message.id).await.unwrap().unwrap();
+        assert_eq!(stamp_name_of(&found.reactions, stamp.id), Some(stamp.name));
+    }
+
+    #[test]
+    fn test_reactions_json_is_parsed() {
+        let reaction = ReactionBuilder::new().build();
+        let now = OffsetDateTime::now_utc();
+        let row = MessageRowWithReactionsJson {
+            message: MessageRow {
+                id: UUIDv4.fake(),
+                user_id: UUIDv4.fake(),
+                channel_id: UUIDv4.fake(),
+                content: "hello".to_string(),
+                created_at: now,
+                updated_at: now,
+                user_handle: None,
+                user_display_name: None,
+            },
+            channel_name: None,
+            channel_path: None,
+            channel_archived: None,
+            // HEX() returns uppercase digits without hyphens
+            reactions: Some(format!(
+                r#"[{{"stamp_id": "{}", "user_id": "{}", "stamp_count": {}}}]"#,
+                reaction.stamp_id.simple().to_string().to_uppercase(),
+                reaction.user_id.simple().to_string().to_uppercase(),
+                reaction.stamp_count
+            )),
+        };
+
+        let item = MessageListItem::try_from(row).unwrap();
+
+        assert_eq!(item.reactions, vec![reaction]);
+    }
+
+    #[sqlx::test]
+    async fn test_find_sync_candidates_returns_recent_messages(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        let recent_time = fake_recent_datetime();
+        let recent_message = MessageBuilder::new()
+            .created_at(recent_time - time::Duration::hours(1))
+            .build();
+        let old_message = MessageBuilder::new()
+            .created_at(recent_time - time::Duration::hours(25))
+            .build();
+
+        repo.save(&recent_message).await.unwrap();
+        repo.save(&old_message).await.unwrap();
+
+        let candidates = repo.find_sync_candidates().await.unwrap();
+
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].0, recent_message.id);
+    }
+
+    #[sqlx::test]
+    async fn test_save_updates_last_crawled_at(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        let recent_time = fake_recent_datetime();
+        let message = MessageBuilder::new().created_at(recent_time).build();
+        repo.save(&message).await.unwrap();
+
+        let first_candidates = repo.find_sync_candidates().await.unwrap();
+        let first_crawled_at = first_candidates[0].2;
+
+        sleep(Duration::from_millis(100)).await;
+
+        repo.save(&message).await.unwrap();
+
+        let second_candidates = repo.find_sync_candidates().await.unwrap();
+        let second_crawled_at = second_candidates[0].2;
+
+        assert!(second_crawled_at > first_crawled_at);
+    }
+    #[sqlx::test]
+    async fn test_save_adds_new_reactions(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        let message_id = UUIDv4.fake();
+        let reaction1 = ReactionBuilder::new().stamp_count(1).build();
+
+        // 1. Initial save with no reactions
+        let message = MessageBuilder::new().id(message_id).build();
+        repo.save(&message).await.unwrap();
+
+        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
+        assert!(saved.reactions.is_empty());
+
+        // 2. Update: Add reaction1
+        let message = MessageBuilder::new()
+            .id(message_id)
+            .reactions(vec![reaction1.clone()])
+            .build();
+        repo.save(&message).await.unwrap();
+
+        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
+        assert_eq!(saved.reactions.len(), 1);
+        assert_eq!(saved.reactions[0].stamp_id, reaction1.stamp_id);
+    }
+
+    #[sqlx::test]
+    async fn test_save_updates_reaction_counts(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+
+        let message_id = UUIDv4.fake();
+        let mut reaction = ReactionBuilder::new().stamp_count(1).build();
+
+        // 1. Initial save with count 1
+        let message = MessageBuilder::new()
+            .id(message_id)
+            .reactions(vec![reaction.clone()])
+            .build();
         repo.save(&message).await.unwrap();
 
-        let user_id = UUIDv4.fake();
-
-        let messages = repo.find_all_messages_for_test(&user_id).await.unwrap();
+        // 2. Update: count 2
+        reaction.stamp_count = 2;
+        let message = MessageBuilder::new()
+            .id(message_id)
+            .reactions(vec![reaction.clone()])
+            .build();
+        repo.save(&message).await.unwrap();
 
-        assert_eq!(messages.len(), 1);
-        assert_eq!(messages[0].reactions.len(), 1);
-        assert_eq!(messages[0].reactions[0].stamp_id, reaction.stamp_id);
-        assert_eq!(messages[0].reactions[0].stamp_count, reaction.stamp_count);
+        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
+        assert_eq!(saved.reactions.len(), 1);
+        assert_eq!(saved.reactions[0].stamp_count, 2);
     }
 
     #[sqlx::test]
-    async fn test_remove_reaction(pool: sqlx::MySqlPool) {
+    async fn test_save_removes_missing_reactions(pool: sqlx::MySqlPool) {
         let repo = MariaDbMessageRepository::new(pool);
 
         let message_id = UUIDv4.fake();
-        let stamp_id = UUIDv4.fake();
+        let reaction1 = ReactionBuilder::new().stamp_count(1).build();
+        let reaction2 = ReactionBuilder::new().stamp_count(1).build();
+
+        // 1. Initial save with 2 reactions
+        let message = MessageBuilder::new()
+            .id(message_id)
+            .reactions(vec![reaction1.clone(), reaction2.clone()])
+            .build();
+        repo.save(&message).await.unwrap();
+
+        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
+        assert_eq!(saved.reactions.len(), 2);
+
+        // 2. Update: Remove reaction2
+        let message = MessageBuilder::new()
+            .id(message_id)
+            .reactions(vec![reaction1.clone()])
+            .build();
+        repo.save(&message).await.unwrap();
+
+        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
+        assert_eq!(saved.reactions.len(), 1);
+        assert_eq!(saved.reactions[0].stamp_id, reaction1.stamp_id);
+    }
+
+    #[sqlx::test]
+    async fn test_find_top_reacted_messages(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+        let message = MessageBuilder::new()
+            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(3600))
+            .build();
+        repo.save(&message).await.unwrap();
+
         let user_id = UUIDv4.fake();
+        let result = repo.find_top_reacted_messages(&user_id, 10).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
 
-        let reaction = ReactionBuilder::new()
-            .stamp_id(stamp_id)
+    #[sqlx::test]
+    async fn test_find_trending_messages(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+        let now = OffsetDateTime::now_utc();
+        let slow = MessageBuilder::new()
+            .reactions(vec![ReactionBuilder::new().build()])
+            .created_at(now - Duration::from_secs(10 * 3600))
+            .build();
+        let fast = MessageBuilder::new()
+            .reactions(vec![
+                ReactionBuilder::new().build(),
+                ReactionBuilder::new().build(),
+            ])
+            .created_at(now - Duration::from_secs(3600))
+            .build();
+        let unreacted = MessageBuilder::new()
+            .created_at(now - Duration::from_secs(60))
+            .build();
+        let old = MessageBuilder::new()
+            .reactions(vec![ReactionBuilder::new().build()])
+            .created_at(now - Duration::from_secs(48 * 3600))
+            .build();
+        repo.save_batch(&[slow.clone(), fast.clone(), unreacted, old.clone()])
+            .await
+            .unwrap();
+
+        let viewer_id = UUIDv4.fake();
+        let result = repo
+            .find_trending_messages(&viewer_id, 24, 10)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![fast.id, slow.id]);
+
+        // A wider window includes older messages
+        let result = repo
+            .find_trending_messages(&viewer_id, 72, 10)
+            .await
+            .unwrap();
+        assert!(result.iter().any(|m| m.id == old.id));
+    }
+
+    #[sqlx::test]
+    async fn test_find_highlights_shares_content_only_with_consent(pool: sqlx::MySqlPool) {
+        use crate::repository::mariadb::user_settings::MariaDbUserSettingsRepository;
+        use domain::{model::PrivacySettings, repository::UserSettingsRepository};
+
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_settings_repo = MariaDbUserSettingsRepository::new(pool);
+        let now = OffsetDateTime::now_utc();
+        let consenting_id = UUIDv4.fake();
+        let opted_out_id = UUIDv4.fake();
+        for (user_id, settings) in [
+            (
+                consenting_id,
+                PrivacySettings {
+                    share_content_in_highlights: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                opted_out_id,
+                PrivacySettings {
+                    recommend_only_to_acquaintances: true,
+                    share_content_in_highlights: true,
+                },
+            ),
+        ] {
+            user_settings_repo
+                .save_privacy_settings(&user_id, &settings)
+                .await
+                .unwrap();
+        }
+
+        let stamp_id = UUIDv4.fake();
+        let shared = MessageBuilder::new()
+            .user_id(consenting_id)
+            .reactions(vec![
+                ReactionBuilder::new().stamp_id(stamp_id).build(),
+                ReactionBuilder::new().stamp_id(stamp_id).build(),
+            ])
+            .created_at(now - Duration::from_secs(3600))
+            .build();
+        let anonymous = MessageBuilder::new()
+            .reactions(vec![ReactionBuilder::new().build()])
+            .created_at(now - Duration::from_secs(60))
+            .build();
+        let opted_out = MessageBuilder::new()
+            .user_id(opted_out_id)
+            .reactions(vec![ReactionBuilder::new().build()])
+            .created_at(now - Duration::from_secs(60))
+            .build();
+        repo.save_batch(&[shared.clone(), anonymous, opted_out])
+            .await
+            .unwrap();
+
+        let since = now - Duration::from_secs(24 * 3600);
+        let result = repo.find_highlights(since, 10).await.unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!((result[0].reaction_count, result[0].stamp_count), (2, 1));
+        assert_eq!(result[0].content, Some(shared.content));
+        assert_eq!(result[1].content, None);
+    }
+
+    #[sqlx::test]
+    async fn test_find_messages_by_author_allowlist(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+        let user_id = UUIDv4.fake();
+        let message = MessageBuilder::new()
             .user_id(user_id)
+            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(60))
+            .build();
+        repo.save(&message).await.unwrap();
+
+        let viewer_id = UUIDv4.fake();
+
+        let result = repo
+            .find_messages_by_author_allowlist(&[user_id], 10, &viewer_id)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
+
+    #[sqlx::test]
+    async fn test_candidate_queries_exclude_read_messages(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+
+        let viewer = UserBuilder::new().build();
+        user_repo.save(&viewer).await.unwrap();
+        let author_id = UUIDv4.fake();
+        let read_before = MessageBuilder::new()
+            .user_id(author_id)
+            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(120))
+            .build();
+        let read_after = MessageBuilder::new()
+            .user_id(author_id)
+            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(60))
+            .build();
+        let unread = MessageBuilder::new()
+            .user_id(author_id)
+            .created_at(OffsetDateTime::now_utc())
+            .build();
+        repo.save_batch(&[read_before.clone(), read_after.clone(), unread.clone()])
+            .await
+            .unwrap();
+        // Read before the viewer's read filter is built, so excluded in SQL
+        repo.mark_messages_as_read(&viewer.id.into(), &[read_before.id.into()])
+            .await
+            .unwrap();
+
+        let result = repo
+            .find_messages_by_author_allowlist(&[author_id], 10, &viewer.id)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![unread.id, read_after.id]);
+
+        // Read after it may have been built, and through another repository that has no filter
+        repo.mark_messages_as_read(&viewer.id.into(), &[read_after.id.into()])
+            .await
+            .unwrap();
+        for repo in [repo, MariaDbMessageRepository::new(pool)] {
+            let result = repo
+                .find_messages_by_author_allowlist(&[author_id], 10, &viewer.id)
+                .await
+                .unwrap();
+            let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+            assert_eq!(ids, vec![unread.id]);
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_candidates_are_paged_past_read_messages(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+
+        let viewer = UserBuilder::new().build();
+        user_repo.save(&viewer).await.unwrap();
+        let author_id = UUIDv4.fake();
+        let messages: Vec<Message> = (0..6)
+            .map(|i| {
+                MessageBuilder::new()
+                    .user_id(author_id)
+                    .created_at(OffsetDateTime::now_utc() - Duration::from_secs(i * 60))
+                    .build()
+            })
+            .collect();
+        repo.save_batch(&messages).await.unwrap();
+        // The first page of candidates is all read
+        let read_ids: Vec<MessageId> = messages[..4].iter().map(|m| m.id.into()).collect();
+        repo.mark_messages_as_read(&viewer.id.into(), &read_ids)
+            .await
+            .unwrap();
+        assert!(repo.read_filter.start_building(&viewer.id));
+        repo.read_filter
+            .finish_building(&viewer.id, load_reads(&pool, &viewer.id).await.unwrap());
+
+        let result = repo
+            .find_messages_by_author_allowlist(&[author_id], 2, &viewer.id)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![messages[4].id, messages[5].id]);
+    }
+
+    #[sqlx::test]
+    async fn test_find_messages_by_author_allowlist_across_chunks(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+        let now = OffsetDateTime::now_utc();
+        let mut author_ids: Vec<Uuid> = (0..in_list::MAX_LEN).map(|_| UUIDv4.fake()).collect();
+        let older = MessageBuilder::new()
+            .user_id(author_ids[0])
+            .created_at(now - Duration::from_secs(120))
+            .build();
+        let newer = MessageBuilder::new()
+            .created_at(now - Duration::from_secs(60))
+            .build();
+        author_ids.push(newer.user_id);
+        repo.save_batch(&[older, newer.clone()]).await.unwrap();
+
+        let viewer_id = UUIDv4.fake();
+
+        let result = repo
+            .find_messages_by_author_allowlist(&author_ids, 1, &viewer_id)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, newer.id);
+    }
+
+    #[sqlx::test]
+    async fn test_find_most_active_channels(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+        let busy_channel_id = UUIDv4.fake();
+        let quiet_channel_id = UUIDv4.fake();
+        let messages: Vec<Message> = [busy_channel_id, busy_channel_id, quiet_channel_id]
+            .into_iter()
+            .map(|channel_id| {
+                MessageBuilder::new()
+                    .channel_id(channel_id)
+                    .created_at(fake_recent_datetime())
+                    .build()
+            })
+            .collect();
+        repo.save_batch(&messages).await.unwrap();
+
+        let result = repo.find_most_active_channels(10).await.unwrap();
+        assert_eq!(
+            result,
+            vec![
+                ChannelActivity {
+                    channel_id: busy_channel_id,
+                    message_count: 2,
+                },
+                ChannelActivity {
+                    channel_id: quiet_channel_id,
+                    message_count: 1,
+                },
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_find_recent_messages_across_channels(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+        let busy_channel_id = UUIDv4.fake();
+        let quiet_channel_id = UUIDv4.fake();
+        let now = OffsetDateTime::now_utc();
+        let busy_messages: Vec<Message> = (1..=3)
+            .map(|i| {
+                MessageBuilder::new()
+                    .channel_id(busy_channel_id)
+                    .created_at(now - Duration::from_secs(60 * i))
+                    .build()
+            })
+            .collect();
+        let quiet_message = MessageBuilder::new()
+            .channel_id(quiet_channel_id)
+            .created_at(now - Duration::from_secs(3600))
             .build();
+        repo.save_batch(&busy_messages).await.unwrap();
+        repo.save(&quiet_message).await.unwrap();
+
+        let viewer_id = UUIDv4.fake();
+        let result = repo
+            .find_recent_messages_across_channels(&viewer_id, 2, 10)
+            .await
+            .unwrap();
+
+        // The oldest message of the busy channel makes room for the quiet channel
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(
+            ids,
+            vec![busy_messages[0].id, busy_messages[1].id, quiet_message.id]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_candidate_queries_exclude_muted_users(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let mute_repo = MariaDbMuteRepository::new(pool);
+
+        let viewer = UserBuilder::new().build();
+        user_repo.save(&viewer).await.unwrap();
 
+        let muted_user_id = UUIDv4.fake();
+        let channel_id = UUIDv4.fake();
         let message = MessageBuilder::new()
-            .id(message_id)
-            .reactions(vec![reaction])
+            .user_id(muted_user_id)
+            .channel_id(channel_id)
+            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(60))
             .build();
+        repo.save(&message).await.unwrap();
+        mute_repo
+            .mute_user(&viewer.id.into(), &muted_user_id.into())
+            .await
+            .unwrap();
 
-        // Save with reaction
+        let top_reacted = repo
+            .find_top_reacted_messages(&viewer.id, 10)
+            .await
+            .unwrap();
+        let by_author = repo
+            .find_messages_by_author_allowlist(&[muted_user_id], 10, &viewer.id)
+            .await
+            .unwrap();
+        let by_channel = repo
+            .find_messages_by_channel_allowlist(&[channel_id], 10, &viewer.id)
+            .await
+            .unwrap();
+
+        assert!(top_reacted.is_empty());
+        assert!(by_author.is_empty());
+        assert!(by_channel.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_candidate_queries_exclude_muted_channels(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let mute_repo = MariaDbMuteRepository::new(pool);
+
+        let viewer = UserBuilder::new().build();
+        user_repo.save(&viewer).await.unwrap();
+
+        let author_id = UUIDv4.fake();
+        let muted_channel_id = UUIDv4.fake();
+        let message = MessageBuilder::new()
+            .user_id(author_id)
+            .channel_id(muted_channel_id)
+            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(60))
+            .build();
+        repo.save(&message).await.unwrap();
+        mute_repo
+            .mute_channel(&viewer.id.into(), &muted_channel_id.into())
+            .await
+            .unwrap();
+
+        let top_reacted = repo
+            .find_top_reacted_messages(&viewer.id, 10)
+            .await
+            .unwrap();
+        let by_author = repo
+            .find_messages_by_author_allowlist(&[author_id], 10, &viewer.id)
+            .await
+            .unwrap();
+        let by_channel = repo
+            .find_messages_by_channel_allowlist(&[muted_channel_id], 10, &viewer.id)
+            .await
+            .unwrap();
+
+        assert!(top_reacted.is_empty());
+        assert!(by_author.is_empty());
+        assert!(by_channel.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_candidate_queries_respect_recommendation_opt_out(pool: sqlx::MySqlPool) {
+        use crate::repository::mariadb::user_settings::MariaDbUserSettingsRepository;
+        use domain::{model::PrivacySettings, repository::UserSettingsRepository};
+
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let user_settings_repo = MariaDbUserSettingsRepository::new(pool);
+
+        let viewer = UserBuilder::new().build();
+        let author = UserBuilder::new().build();
+        user_repo.save(&viewer).await.unwrap();
+        user_repo.save(&author).await.unwrap();
+        user_settings_repo
+            .save_privacy_settings(
+                &author.id,
+                &PrivacySettings {
+                    recommend_only_to_acquaintances: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let channel_id = UUIDv4.fake();
+        let message = MessageBuilder::new()
+            .user_id(author.id)
+            .channel_id(channel_id)
+            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(60))
+            .build();
+        repo.save(&message).await.unwrap();
+
+        let top_reacted = repo
+            .find_top_reacted_messages(&viewer.id, 10)
+            .await
+            .unwrap();
+        let by_author = repo
+            .find_messages_by_author_allowlist(&[author.id], 10, &viewer.id)
+            .await
+            .unwrap();
+        let by_channel = repo
+            .find_messages_by_channel_allowlist(&[channel_id], 10, &viewer.id)
+            .await
+            .unwrap();
+        assert!(top_reacted.is_empty());
+        assert!(by_author.is_empty());
+        assert!(by_channel.is_empty());
+
+        // Once the author stamps a message of the viewer, they are acquainted
+        let viewer_message = MessageBuilder::new()
+            .user_id(viewer.id)
+            .reactions(vec![ReactionBuilder::new().user_id(author.id).build()])
+            .build();
+        repo.save(&viewer_message).await.unwrap();
+
+        let by_author = repo
+            .find_messages_by_author_allowlist(&[author.id], 10, &viewer.id)
+            .await
+            .unwrap();
+        let by_channel = repo
+            .find_messages_by_channel_allowlist(&[channel_id], 10, &viewer.id)
+            .await
+            .unwrap();
+        assert_eq!(by_author.len(), 1);
+        assert_eq!(by_channel.len(), 1);
+        assert_eq!(by_author[0].id, message.id);
+    }
+
+    #[sqlx::test]
+    async fn test_find_messages_by_channel_allowlist(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool);
+        let channel_id = UUIDv4.fake();
+        let message = MessageBuilder::new()
+            .channel_id(channel_id)
+            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(60))
+            .build();
         repo.save(&message).await.unwrap();
 
-        let viewer_id = UUIDv4.fake();
+        let viewer_id = UUIDv4.fake();
+
+        let result = repo
+            .find_messages_by_channel_allowlist(&[channel_id], 10, &viewer_id)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, message.id);
+    }
+
+    #[sqlx::test]
+    async fn test_search(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let mute_repo = MariaDbMuteRepository::new(pool);
+
+        let viewer = UserBuilder::new().build();
+        user_repo.save(&viewer).await.unwrap();
+        let best = MessageBuilder::new()
+            .content("rust borrow checker and rust lifetimes")
+            .build();
+        let other = MessageBuilder::new().content("learning rust").build();
+        let read = MessageBuilder::new().content("rust macros").build();
+        let muted = MessageBuilder::new().content("rust traits").build();
+        let unrelated = MessageBuilder::new().content("lunch menu").build();
+        repo.save_batch(&[
+            best.clone(),
+            other.clone(),
+            read.clone(),
+            muted.clone(),
+            unrelated,
+        ])
+        .await
+        .unwrap();
+        repo.mark_messages_as_read(&viewer.id.into(), &[read.id.into()])
+            .await
+            .unwrap();
+        mute_repo
+            .mute_user(&viewer.id.into(), &muted.user_id.into())
+            .await
+            .unwrap();
 
-        // Verify reaction exists
-        let messages = repo.find_all_messages_for_test(&viewer_id).await.unwrap();
-        assert_eq!(messages[0].reactions.len(), 1);
+        let result = repo.search("rust", 10, &viewer.id).await.unwrap();
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![best.id, other.id]);
+    }
 
-        // Remove reaction
-        repo.remove_reaction(&message_id, &stamp_id, &user_id)
+    #[sqlx::test]
+    async fn test_find_read_message_ids(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let viewer = UserBuilder::new().build();
+        user_repo.save(&viewer).await.unwrap();
+        let read = MessageBuilder::new().build();
+        let unread = MessageBuilder::new().build();
+        repo.save_batch(&[read.clone(), unread.clone()])
+            .await
+            .unwrap();
+        repo.mark_messages_as_read(&viewer.id.into(), &[read.id.into()])
             .await
             .unwrap();
 
-        // Verify reaction is removed
-        let messages = repo.find_all_messages_for_test(&viewer_id).await.unwrap();
-        assert_eq!(messages[0].reactions.len(), 0);
+        let result = repo
+            .find_read_message_ids(&viewer.id, &[read.id, unread.id])
+            .await
+            .unwrap();
+        assert_eq!(result, vec![read.id]);
+        assert!(
+            repo.find_read_message_ids(&viewer.id, &[])
+                .await
+                .unwrap()
+                .is_empty()
+        );
     }
 
     #[sqlx::test]
-    async fn test_save_batch(pool: sqlx::MySqlPool) {
-        let repo = MariaDbMessageRepository::new(pool);
-
-        let channel_id = UUIDv4.fake();
-        let messages = vec![
-            MessageBuilder::new().channel_id(channel_id).build(),
-            MessageBuilder::new().channel_id(channel_id).build(),
-        ];
+    async fn test_find_read_message_ids_by_users(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
 
+        let viewers = [UserBuilder::new().build(), UserBuilder::new().build()];
+        for viewer in &viewers {
+            user_repo.save(viewer).await.unwrap();
+        }
+        let messages = [MessageBuilder::new().build(), MessageBuilder::new().build()];
         repo.save_batch(&messages).await.unwrap();
+        repo.mark_messages_as_read(&viewers[0].id.into(), &[messages[0].id.into()])
+            .await
+            .unwrap();
+        repo.mark_messages_as_read(
+            &viewers[1].id.into(),
+            &[messages[0].id.into(), messages[1].id.into()],
+        )
+        .await
+        .unwrap();
 
-        let user_id = UUIDv4.fake();
-
-        let saved_messages = repo.find_all_messages_for_test(&user_id).await.unwrap();
-        assert!(saved_messages.len() >= 2); // At least our 2 messages
+        let mut result = repo
+            .find_read_message_ids_by_users(
+                &[viewers[0].id, viewers[1].id],
+                &[messages[0].id, messages[1].id],
+            )
+            .await
+            .unwrap();
+        result.sort();
+        let mut expected = vec![
+            (viewers[0].id, messages[0].id),
+            (viewers[1].id, messages[0].id),
+            (viewers[1].id, messages[1].id),
+        ];
+        expected.sort();
+        assert_eq!(result, expected);
     }
 
     #[sqlx::test]
-    async fn test_find_sync_candidates_returns_recent_messages(pool: sqlx::MySqlPool) {
-        let repo = MariaDbMessageRepository::new(pool);
-
-        let recent_time = fake_recent_datetime();
-        let recent_message = MessageBuilder::new()
-            .created_at(recent_time - time::Duration::hours(1))
+    async fn test_roll_up_read_messages(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let viewer = UserBuilder::new().build();
+        user_repo.save(&viewer).await.unwrap();
+        let now = OffsetDateTime::now_utc();
+        let old_read = MessageBuilder::new()
+            .created_at(now - Duration::from_secs(3 * 3600))
             .build();
-        let old_message = MessageBuilder::new()
-            .created_at(recent_time - time::Duration::hours(25))
+        let old_unread = MessageBuilder::new()
+            .created_at(now - Duration::from_secs(2 * 3600))
             .build();
+        let recent_read = MessageBuilder::new().created_at(now).build();
+        let recent_unread = MessageBuilder::new().created_at(now).build();
+        repo.save_batch(&[
+            old_read.clone(),
+            old_unread.clone(),
+            recent_read.clone(),
+            recent_unread.clone(),
+        ])
+        .await
+        .unwrap();
+        repo.mark_messages_as_read(
+            &viewer.id.into(),
+            &[old_read.id.into(), recent_read.id.into()],
+        )
+        .await
+        .unwrap();
 
-        repo.save(&recent_message).await.unwrap();
-        repo.save(&old_message).await.unwrap();
+        let deleted = repo
+            .roll_up_read_messages(now - Duration::from_secs(3600))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        // The horizon stops at the oldest unread message, which stays unread
+        let mut result = repo
+            .find_read_message_ids(
+                &viewer.id,
+                &[old_read.id, old_unread.id, recent_read.id, recent_unread.id],
+            )
+            .await
+            .unwrap();
+        result.sort();
+        let mut expected = vec![old_read.id, recent_read.id];
+        expected.sort();
+        assert_eq!(result, expected);
+        let mut unread: Vec<Uuid> = repo
+            .find_all_messages_for_test(&viewer.id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        unread.sort();
+        let mut expected = vec![old_unread.id, recent_unread.id];
+        expected.sort();
+        assert_eq!(unread, expected);
 
-        let candidates = repo.find_sync_candidates().await.unwrap();
+        // Once it is read, the horizon moves past it
+        repo.mark_messages_as_read(&viewer.id.into(), &[old_unread.id.into()])
+            .await
+            .unwrap();
+        let deleted = repo
+            .roll_up_read_messages(now - Duration::from_secs(3600))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(
+            repo.find_read_message_ids(&viewer.id, &[old_read.id, old_unread.id])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
 
-        assert_eq!(candidates.len(), 1);
-        assert_eq!(candidates[0].0, recent_message.id);
+        // Rolling up with an earlier horizon doesn't make messages unread again
+        repo.roll_up_read_messages(now - Duration::from_secs(4 * 3600))
+            .await
+            .unwrap();
+        assert_eq!(
+            repo.find_read_message_ids(&viewer.id, &[old_unread.id])
+                .await
+                .unwrap(),
+            vec![old_unread.id]
+        );
     }
 
     #[sqlx::test]
-    async fn test_save_updates_last_crawled_at(pool: sqlx::MySqlPool) {
-        let repo = MariaDbMessageRepository::new(pool);
-
-        let recent_time = fake_recent_datetime();
-        let message = MessageBuilder::new().created_at(recent_time).build();
-        repo.save(&message).await.unwrap();
-
-        let first_candidates = repo.find_sync_candidates().await.unwrap();
-        let first_crawled_at = first_candidates[0].2;
-
-        sleep(Duration::from_millis(100)).await;
-
-        repo.save(&message).await.unwrap();
-
-        let second_candidates = repo.find_sync_candidates().await.unwrap();
-        let second_crawled_at = second_candidates[0].2;
+    async fn test_tags(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let mute_repo = MariaDbMuteRepository::new(pool);
+
+        let viewer = UUIDv4.fake();
+        let author = UUIDv4.fake();
+        let now = OffsetDateTime::now_utc();
+        let older = MessageBuilder::new()
+            .user_id(author)
+            .content("#Rust and #traP")
+            .created_at(now - Duration::from_secs(7200))
+            .build();
+        let newer = MessageBuilder::new()
+            .content("more #rust")
+            .created_at(now - Duration::from_secs(3600))
+            .build();
+        let muted = MessageBuilder::new()
+            .content("#rust again")
+            .created_at(now)
+            .build();
+        let mut edited = MessageBuilder::new()
+            .user_id(author)
+            .content("#trap")
+            .created_at(now)
+            .build();
+        repo.save_batch(&[older.clone(), newer.clone(), muted.clone(), edited.clone()])
+            .await
+            .unwrap();
+        // Tags removed by edits are removed too
+        edited.content = "no tags".to_string();
+        repo.save(&edited).await.unwrap();
+        mute_repo
+            .mute_user(&viewer.into(), &muted.user_id.into())
+            .await
+            .unwrap();
 
-        assert!(second_crawled_at > first_crawled_at);
+        let result = repo.find_by_tag("rust", 10, &viewer).await.unwrap();
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![newer.id, older.id]);
+
+        let trending = repo.find_trending_tags(24, 10).await.unwrap();
+        assert_eq!(
+            trending,
+            vec![
+                TrendingTag {
+                    tag: "rust".to_string(),
+                    user_count: 3,
+                    message_count: 3,
+                },
+                TrendingTag {
+                    tag: "trap".to_string(),
+                    user_count: 1,
+                    message_count: 1,
+                },
+            ]
+        );
     }
+
     #[sqlx::test]
-    async fn test_save_adds_new_reactions(pool: sqlx::MySqlPool) {
+    async fn test_find_co_reacted_messages(pool: sqlx::MySqlPool) {
         let repo = MariaDbMessageRepository::new(pool);
 
-        let message_id = UUIDv4.fake();
-        let reaction1 = ReactionBuilder::new().stamp_count(1).build();
-
-        // 1. Initial save with no reactions
-        let message = MessageBuilder::new().id(message_id).build();
-        repo.save(&message).await.unwrap();
-
-        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
-        assert!(saved.reactions.is_empty());
-
-        // 2. Update: Add reaction1
-        let message = MessageBuilder::new()
-            .id(message_id)
-            .reactions(vec![reaction1.clone()])
+        let viewer = UUIDv4.fake();
+        let (alice, bob) = (UUIDv4.fake(), UUIDv4.fake());
+        let reacted_by = |user_ids: &[Uuid]| {
+            user_ids
+                .iter()
+                .map(|&user_id| ReactionBuilder::new().user_id(user_id).build())
+                .collect::<Vec<_>>()
+        };
+        let target = MessageBuilder::new()
+            .reactions(reacted_by(&[alice, bob, viewer]))
             .build();
-        repo.save(&message).await.unwrap();
+        let liked_by_both = MessageBuilder::new()
+            .reactions(reacted_by(&[alice, bob]))
+            .build();
+        let liked_by_alice = MessageBuilder::new()
+            .reactions(reacted_by(&[alice]))
+            .build();
+        let liked_by_viewer = MessageBuilder::new()
+            .reactions(reacted_by(&[viewer]))
+            .build();
+        let unrelated = MessageBuilder::new()
+            .reactions(reacted_by(&[UUIDv4.fake()]))
+            .build();
+        repo.save_batch(&[
+            target.clone(),
+            liked_by_alice.clone(),
+            liked_by_both.clone(),
+            liked_by_viewer,
+            unrelated,
+        ])
+        .await
+        .unwrap();
 
-        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
-        assert_eq!(saved.reactions.len(), 1);
-        assert_eq!(saved.reactions[0].stamp_id, reaction1.stamp_id);
+        let result = repo
+            .find_co_reacted_messages(&target.id, 10, &viewer)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = result.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![liked_by_both.id, liked_by_alice.id]);
     }
 
     #[sqlx::test]
-    async fn test_save_updates_reaction_counts(pool: sqlx::MySqlPool) {
+    async fn test_find_by_author(pool: sqlx::MySqlPool) {
         let repo = MariaDbMessageRepository::new(pool);
+        let author_id = UUIDv4.fake();
+        let created_at = fake_recent_datetime();
 
-        let message_id = UUIDv4.fake();
-        let mut reaction = ReactionBuilder::new().stamp_count(1).build();
-
-        // 1. Initial save with count 1
-        let message = MessageBuilder::new()
-            .id(message_id)
-            .reactions(vec![reaction.clone()])
-            .build();
-        repo.save(&message).await.unwrap();
+        // Two messages share a timestamp to check that pages are split between them
+        let mut messages: Vec<Message> = [0, 0, 1, 2]
+            .into_iter()
+            .map(|minutes| {
+                MessageBuilder::new()
+                    .user_id(author_id)
+                    .created_at(created_at - Duration::from_secs(minutes * 60))
+                    .build()
+            })
+            .collect();
+        repo.save_batch(&messages).await.unwrap();
+        repo.save(&MessageBuilder::new().build()).await.unwrap();
+        messages.sort_by(|a, b| (b.created_at, b.id).cmp(&(a.created_at, a.id)));
 
-        // 2. Update: count 2
-        reaction.stamp_count = 2;
-        let message = MessageBuilder::new()
-            .id(message_id)
-            .reactions(vec![reaction.clone()])
-            .build();
-        repo.save(&message).await.unwrap();
+        let first_page = repo.find_by_author(&author_id, None, 2).await.unwrap();
+        let cursor = MessageCursor::from(first_page.last().unwrap());
+        let second_page = repo
+            .find_by_author(&author_id, Some(cursor), 10)
+            .await
+            .unwrap();
 
-        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
-        assert_eq!(saved.reactions.len(), 1);
-        assert_eq!(saved.reactions[0].stamp_count, 2);
+        let ids: Vec<Uuid> = first_page
+            .iter()
+            .chain(&second_page)
+            .map(|m| m.id)
+            .collect();
+        let expected: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+        assert_eq!(ids, expected);
     }
 
     #[sqlx::test]
-    async fn test_save_removes_missing_reactions(pool: sqlx::MySqlPool) {
+    async fn test_find_by_channel(pool: sqlx::MySqlPool) {
         let repo = MariaDbMessageRepository::new(pool);
+        let channel_id = UUIDv4.fake();
+        let now = OffsetDateTime::now_utc();
+
+        let messages: Vec<Message> = (0..3)
+            .map(|minutes| {
+                MessageBuilder::new()
+                    .channel_id(channel_id)
+                    .created_at(now - Duration::from_secs(minutes * 60))
+                    .build()
+            })
+            .collect();
+        repo.save_batch(&messages).await.unwrap();
+        repo.save(&MessageBuilder::new().build()).await.unwrap();
 
-        let message_id = UUIDv4.fake();
-        let reaction1 = ReactionBuilder::new().stamp_count(1).build();
-        let reaction2 = ReactionBuilder::new().stamp_count(1).build();
+        let first_page = repo.find_by_channel(&channel_id, None, 2).await.unwrap();
+        let cursor = MessageCursor::from(first_page.last().unwrap());
+        let second_page = repo
+            .find_by_channel(&channel_id, Some(cursor), 2)
+            .await
+            .unwrap();
 
-        // 1. Initial save with 2 reactions
-        let message = MessageBuilder::new()
-            .id(message_id)
-            .reactions(vec![reaction1.clone(), reaction2.clone()])
-            .build();
-        repo.save(&message).await.unwrap();
+        let ids: Vec<Uuid> = first_page
+            .iter()
+            .chain(&second_page)
+            .map(|m| m.id)
+            .collect();
+        let expected: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+        assert_eq!(ids, expected);
+    }
 
-        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
-        assert_eq!(saved.reactions.len(), 2);
+    #[sqlx::test]
+    async fn test_find_public_messages(pool: sqlx::MySqlPool) {
+        use crate::repository::mariadb::user_settings::MariaDbUserSettingsRepository;
+        use domain::{model::PrivacySettings, repository::UserSettingsRepository};
+
+        let repo = MariaDbMessageRepository::new(pool.clone());
+        let user_settings_repo = MariaDbUserSettingsRepository::new(pool);
+        let opted_out_id = UUIDv4.fake();
+        user_settings_repo
+            .save_privacy_settings(
+                &opted_out_id,
+                &PrivacySettings {
+                    recommend_only_to_acquaintances: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let channel_ids: Vec<Uuid> = vec![UUIDv4.fake(), UUIDv4.fake()];
+        let now = OffsetDateTime::now_utc();
+
+        let messages: Vec<Message> = (0..3)
+            .map(|minutes| {
+                MessageBuilder::new()
+                    .channel_id(channel_ids[minutes as usize % 2])
+                    .created_at(now - Duration::from_secs(minutes * 60))
+                    .build()
+            })
+            .collect();
+        repo.save_batch(&messages).await.unwrap();
+        repo.save(&MessageBuilder::new().build()).await.unwrap();
+        repo.save(
+            &MessageBuilder::new()
+                .channel_id(channel_ids[0])
+                .user_id(opted_out_id)
+                .build(),
+        )
+        .await
+        .unwrap();
 
-        // 2. Update: Remove reaction2
-        let message = MessageBuilder::new()
-            .id(message_id)
-            .reactions(vec![reaction1.clone()])
-            .build();
-        repo.save(&message).await.unwrap();
+        let first_page = repo
+            .find_public_messages(&channel_ids, None, 2)
+            .await
+            .unwrap();
+        let cursor = MessageCursor::from(first_page.last().unwrap());
+        let second_page = repo
+            .find_public_messages(&channel_ids, Some(cursor), 2)
+            .await
+            .unwrap();
 
-        let saved = repo.find_by_id(&message_id).await.unwrap().unwrap();
-        assert_eq!(saved.reactions.len(), 1);
-        assert_eq!(saved.reactions[0].stamp_id, reaction1.stamp_id);
+        let ids: Vec<Uuid> = first_page
+            .iter()
+            .chain(&second_page)
+            .map(|m| m.id)
+            .collect();
+        let expected: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+        assert_eq!(ids, expected);
     }
 
     #[sqlx::test]
-    async fn test_find_top_reacted_messages(pool: sqlx::MySqlPool) {
+    async fn test_find_thread(pool: sqlx::MySqlPool) {
         let repo = MariaDbMessageRepository::new(pool);
-        let message = MessageBuilder::new()
-            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(3600))
-            .build();
-        repo.save(&message).await.unwrap();
+        let now = OffsetDateTime::now_utc();
+        let cite = |message: &Message, minutes| {
+            MessageBuilder::new()
+                .content(format!("https://q.trap.jp/messages/{}", message.id))
+                .created_at(now + Duration::from_secs(minutes * 60))
+                .build()
+        };
 
-        let user_id = UUIDv4.fake();
-        let result = repo.find_top_reacted_messages(&user_id, 10).await.unwrap();
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].id, message.id);
+        let root = MessageBuilder::new().created_at(now).build();
+        let reply = cite(&root, 1);
+        let nested_reply = cite(&reply, 2);
+        let other_reply = cite(&root, 3);
+        repo.save_batch(&[
+            nested_reply.clone(),
+            root.clone(),
+            other_reply.clone(),
+            reply.clone(),
+            MessageBuilder::new().build(),
+        ])
+        .await
+        .unwrap();
+
+        let thread = repo.find_thread(&nested_reply.id, 10).await.unwrap();
+        let ids: Vec<Uuid> = thread.iter().map(|m| m.id).collect();
+        assert_eq!(
+            ids,
+            vec![root.id, reply.id, nested_reply.id, other_reply.id]
+        );
+        assert_eq!(thread[2].reply_to_message_id, Some(reply.id));
+
+        assert!(
+            repo.find_thread(&UUIDv4.fake(), 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
     }
 
     #[sqlx::test]
-    async fn test_find_messages_by_author_allowlist(pool: sqlx::MySqlPool) {
+    async fn test_find_user_stats(pool: sqlx::MySqlPool) {
         let repo = MariaDbMessageRepository::new(pool);
+
         let user_id = UUIDv4.fake();
-        let message = MessageBuilder::new()
+        let now = OffsetDateTime::now_utc();
+        let since = now - Duration::from_secs(30 * 86400);
+        let recent = MessageBuilder::new()
             .user_id(user_id)
-            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(60))
+            .created_at(now)
+            .reactions(vec![
+                ReactionBuilder::new().build(),
+                ReactionBuilder::new().build(),
+            ])
             .build();
-        repo.save(&message).await.unwrap();
-
-        let viewer_id = UUIDv4.fake();
-
-        let result = repo
-            .find_messages_by_author_allowlist(&[user_id], 10, &viewer_id)
-            .await
-            .unwrap();
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].id, message.id);
-    }
-
-    #[sqlx::test]
-    async fn test_find_messages_by_channel_allowlist(pool: sqlx::MySqlPool) {
-        let repo = MariaDbMessageRepository::new(pool);
-        let channel_id = UUIDv4.fake();
-        let message = MessageBuilder::new()
-            .channel_id(channel_id)
-            .created_at(OffsetDateTime::now_utc() - Duration::from_secs(60))
+        let old = MessageBuilder::new()
+            .user_id(user_id)
+            .created_at(since - Duration::from_secs(3600))
+            .reactions(vec![ReactionBuilder::new().build()])
             .build();
-        repo.save(&message).await.unwrap();
-
-        let viewer_id = UUIDv4.fake();
-
-        let result = repo
-            .find_messages_by_channel_allowlist(&[channel_id], 10, &viewer_id)
-            .await
-            .unwrap();
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].id, message.id);
+        let others = MessageBuilder::new()
+            .created_at(now)
+            .reactions(vec![ReactionBuilder::new().build()])
+            .build();
+        repo.save_batch(&[recent, old, others]).await.unwrap();
+
+        let stats = repo.find_user_stats(&user_id, since).await.unwrap();
+        assert_eq!(
+            stats,
+            UserStats {
+                message_count: 2,
+                reactions_received: 2,
+            }
+        );
     }
 }
@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use domain::{
+    error::RepositoryError,
+    model::{MessageEvent, MessageEventKind},
+    repository::MessageEventRepository,
+};
+use sqlx::{MySqlPool, QueryBuilder};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbMessageEventRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbMessageEventRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageEventRepository for MariaDbMessageEventRepository {
+    async fn append(
+        &self,
+        message_ids: &[Uuid],
+        kind: MessageEventKind,
+    ) -> Result<Vec<MessageEvent>, RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let kind_name: &'static str = kind.into();
+        let mut query_builder = QueryBuilder::new("INSERT INTO message_events (message_id, kind) ");
+        query_builder.push_values(message_ids, |mut b, message_id| {
+            b.push_bind(message_id).push_bind(kind_name);
+        });
+
+        let result = query_builder
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        // A multi-row insert gets consecutive IDs starting from the last insert ID
+        // (with the default `innodb_autoinc_lock_mode`)
+        let first_id = result.last_insert_id() as i64;
+        let events = message_ids
+            .iter()
+            .enumerate()
+            .map(|(i, message_id)| MessageEvent {
+                cursor: first_id + i as i64,
+                message_id: *message_id,
+                kind,
+            })
+            .collect();
+
+        Ok(events)
+    }
+
+    async fn import(&self, events: &[MessageEvent]) -> Result<(), RepositoryError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder =
+            QueryBuilder::new("INSERT INTO message_events (id, message_id, kind) ");
+        query_builder.push_values(events, |mut b, event| {
+            b.push_bind(event.cursor)
+                .push_bind(event.message_id)
+                .push_bind(<&'static str>::from(event.kind));
+        });
+
+        query_builder
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_since(
+        &self,
+        cursor: i64,
+        limit: i64,
+    ) -> Result<Vec<MessageEvent>, RepositoryError> {
+        struct MessageEventRecord {
+            id: i64,
+            message_id: Uuid,
+            kind: String,
+        }
+
+        let records = sqlx::query_as!(
+            MessageEventRecord,
+            r#"
+            SELECT id, message_id AS `message_id: _`, kind
+            FROM message_events
+            WHERE id > ?
+            ORDER BY id
+            LIMIT ?
+            "#,
+            cursor,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                let kind = MessageEventKind::from_str(&record.kind)
+                    .map_err(|e| RepositoryError::Serialization(e.to_string()))?;
+                Ok(MessageEvent {
+                    cursor: record.id,
+                    message_id: record.message_id,
+                    kind,
+                })
+            })
+            .collect()
+    }
+
+    async fn find_latest_cursor(&self) -> Result<i64, RepositoryError> {
+        let cursor = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(MAX(id), 0) AS `cursor!: i64`
+            FROM message_events
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, uuid::UUIDv4};
+
+    #[sqlx::test]
+    async fn test_append_and_find_since(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageEventRepository::new(pool);
+        assert_eq!(repo.find_latest_cursor().await.unwrap(), 0);
+
+        let added: Vec<Uuid> = vec![UUIDv4.fake(), UUIDv4.fake()];
+        let removed: Uuid = UUIDv4.fake();
+        let mut appended = repo.append(&added, MessageEventKind::Added).await.unwrap();
+        appended.extend(
+            repo.append(&[removed], MessageEventKind::Removed)
+                .await
+                .unwrap(),
+        );
+        // Appending nothing is a no-op
+        repo.append(&[], MessageEventKind::Updated).await.unwrap();
+
+        let events = repo.find_since(0, 10).await.unwrap();
+        assert_eq!(events, appended);
+        assert_eq!(events[0].message_id, added[0]);
+        assert_eq!(events[0].kind, MessageEventKind::Added);
+        assert_eq!(events[2].message_id, removed);
+        assert_eq!(events[2].kind, MessageEventKind::Removed);
+        assert_eq!(repo.find_latest_cursor().await.unwrap(), events[2].cursor);
+
+        let events_after = repo.find_since(events[0].cursor, 1).await.unwrap();
+        assert_eq!(events_after, vec![events[1].clone()]);
+    }
+
+    #[sqlx::test]
+    async fn test_import_keeps_the_cursors(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMessageEventRepository::new(pool);
+        let imported = vec![
+            MessageEvent {
+                cursor: 5,
+                message_id: UUIDv4.fake(),
+                kind: MessageEventKind::Added,
+            },
+            MessageEvent {
+                cursor: 8,
+                message_id: UUIDv4.fake(),
+                kind: MessageEventKind::Updated,
+            },
+        ];
+        repo.import(&imported).await.unwrap();
+        repo.import(&[]).await.unwrap();
+
+        assert_eq!(repo.find_since(0, 10).await.unwrap(), imported);
+        // Appended events continue after the imported ones
+        let appended = repo
+            .append(&[UUIDv4.fake()], MessageEventKind::Removed)
+            .await
+            .unwrap();
+        assert!(appended[0].cursor > 8);
+    }
+}
@@ -0,0 +1,207 @@
+use domain::{
+    error::RepositoryError,
+    id::{ChannelId, UserId},
+    repository::MuteRepository,
+};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbMuteRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbMuteRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct MutedUserRecord {
+    muted_user_id: Uuid,
+}
+
+struct MutedChannelRecord {
+    channel_id: Uuid,
+}
+
+#[async_trait::async_trait]
+impl MuteRepository for MariaDbMuteRepository {
+    async fn mute_user(
+        &self,
+        user_id: &UserId,
+        muted_user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT IGNORE INTO muted_users (user_id, muted_user_id)
+            VALUES (?, ?)
+            "#,
+            user_id.as_uuid(),
+            muted_user_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn unmute_user(
+        &self,
+        user_id: &UserId,
+        muted_user_id: &UserId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM muted_users
+            WHERE user_id = ? AND muted_user_id = ?
+            "#,
+            user_id.as_uuid(),
+            muted_user_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_muted_user_ids(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError> {
+        let records = sqlx::query_as!(
+            MutedUserRecord,
+            r#"
+            SELECT muted_user_id AS `muted_user_id: _`
+            FROM muted_users
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(records.into_iter().map(|r| r.muted_user_id).collect())
+    }
+
+    async fn mute_channel(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT IGNORE INTO muted_channels (user_id, channel_id)
+            VALUES (?, ?)
+            "#,
+            user_id.as_uuid(),
+            channel_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn unmute_channel(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM muted_channels
+            WHERE user_id = ? AND channel_id = ?
+            "#,
+            user_id.as_uuid(),
+            channel_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_muted_channel_ids(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError> {
+        let records = sqlx::query_as!(
+            MutedChannelRecord,
+            r#"
+            SELECT channel_id AS `channel_id: _`
+            FROM muted_channels
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(records.into_iter().map(|r| r.channel_id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::user::MariaDbUserRepository;
+    use domain::{repository::UserRepository, test_factories::UserBuilder};
+    use fake::{Fake, uuid::UUIDv4};
+
+    #[sqlx::test]
+    async fn test_mute_and_unmute_user(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMuteRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create user first (FK constraint)
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let muted_user_id: Uuid = UUIDv4.fake();
+
+        repo.mute_user(&user.id.into(), &muted_user_id.into())
+            .await
+            .unwrap();
+        // Muting twice is a no-op
+        repo.mute_user(&user.id.into(), &muted_user_id.into())
+            .await
+            .unwrap();
+
+        let muted = repo.find_muted_user_ids(&user.id).await.unwrap();
+        assert_eq!(muted, vec![muted_user_id]);
+
+        repo.unmute_user(&user.id.into(), &muted_user_id.into())
+            .await
+            .unwrap();
+
+        let muted = repo.find_muted_user_ids(&user.id).await.unwrap();
+        assert!(muted.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_mute_and_unmute_channel(pool: sqlx::MySqlPool) {
+        let repo = MariaDbMuteRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let channel_id: Uuid = UUIDv4.fake();
+
+        repo.mute_channel(&user.id.into(), &channel_id.into())
+            .await
+            .unwrap();
+        repo.mute_channel(&user.id.into(), &channel_id.into())
+            .await
+            .unwrap();
+
+        let muted = repo.find_muted_channel_ids(&user.id).await.unwrap();
+        assert_eq!(muted, vec![channel_id]);
+
+        repo.unmute_channel(&user.id.into(), &channel_id.into())
+            .await
+            .unwrap();
+
+        let muted = repo.find_muted_channel_ids(&user.id).await.unwrap();
+        assert!(muted.is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+use domain::{error::RepositoryError, repository::PresenceRepository};
+use sqlx::MySqlPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbPresenceRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbPresenceRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl PresenceRepository for MariaDbPresenceRepository {
+    async fn save_heartbeat(
+        &self,
+        session_id: &str,
+        user_id: &Uuid,
+        seen_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO session_presence (session_id, user_id, last_seen_at)
+            VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                user_id = VALUES(user_id),
+                last_seen_at = VALUES(last_seen_at)
+            "#,
+            session_id,
+            user_id,
+            seen_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn is_seen_since(
+        &self,
+        user_id: &Uuid,
+        since: OffsetDateTime,
+    ) -> Result<bool, RepositoryError> {
+        let seen = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM session_presence
+                WHERE user_id = ? AND last_seen_at >= ?
+            ) AS `seen!: bool`
+            "#,
+            user_id,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(seen)
+    }
+
+    async fn count_users_seen_since(&self, since: OffsetDateTime) -> Result<i64, RepositoryError> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(DISTINCT user_id) AS `count!: i64`
+            FROM session_presence
+            WHERE last_seen_at >= ?
+            "#,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(count)
+    }
+
+    async fn delete_seen_before(&self, before: OffsetDateTime) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM session_presence
+            WHERE last_seen_at < ?
+            "#,
+            before
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::test_factories::fake_recent_datetime;
+    use fake::{Fake, uuid::UUIDv4};
+    use time::Duration;
+
+    #[sqlx::test]
+    async fn test_heartbeats_are_counted_per_user(pool: sqlx::MySqlPool) {
+        let repo = MariaDbPresenceRepository::new(pool);
+        let now = fake_recent_datetime();
+        let (alice, bob): (Uuid, Uuid) = (UUIDv4.fake(), UUIDv4.fake());
+
+        repo.save_heartbeat("alice-laptop", &alice, now - Duration::hours(2))
+            .await
+            .unwrap();
+        repo.save_heartbeat("alice-phone", &alice, now)
+            .await
+            .unwrap();
+        repo.save_heartbeat("bob-laptop", &bob, now - Duration::days(2))
+            .await
+            .unwrap();
+        // Later heartbeats of a session replace the earlier ones
+        repo.save_heartbeat("alice-laptop", &alice, now)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repo.count_users_seen_since(now - Duration::days(1))
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(
+            repo.is_seen_since(&alice, now - Duration::minutes(1))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !repo
+                .is_seen_since(&bob, now - Duration::minutes(1))
+                .await
+                .unwrap()
+        );
+
+        repo.delete_seen_before(now - Duration::days(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            repo.count_users_seen_since(now - Duration::days(30))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use domain::{error::RepositoryError, model::QuotaOperation, repository::QuotaRepository};
+use sqlx::MySqlPool;
+use std::str::FromStr;
+use time::Date;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbQuotaRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbQuotaRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    async fn increment_below(
+        &self,
+        user_id: &Uuid,
+        operation: &str,
+        day: Date,
+        limit: i64,
+    ) -> Result<bool, RepositoryError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE quota_usages
+            SET used = used + 1
+            WHERE user_id = ? AND day = ? AND operation = ? AND used < ?
+            "#,
+            user_id,
+            day,
+            operation,
+            limit
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait::async_trait]
+impl QuotaRepository for MariaDbQuotaRepository {
+    async fn try_use(
+        &self,
+        user_id: &Uuid,
+        operation: QuotaOperation,
+        day: Date,
+        limit: i64,
+    ) -> Result<bool, RepositoryError> {
+        let operation: &'static str = operation.into();
+        if self.increment_below(user_id, operation, day, limit).await? {
+            return Ok(true);
+        }
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT IGNORE INTO quota_usages (user_id, day, operation, used)
+            VALUES (?, ?, ?, 1)
+            "#,
+            user_id,
+            day,
+            operation
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?
+        .rows_affected()
+            > 0;
+        if inserted {
+            return Ok(true);
+        }
+
+        // Another request inserted the row in between
+        self.increment_below(user_id, operation, day, limit).await
+    }
+
+    async fn find_used(
+        &self,
+        user_id: &Uuid,
+        day: Date,
+    ) -> Result<Vec<(QuotaOperation, i64)>, RepositoryError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT operation, used
+            FROM quota_usages
+            WHERE user_id = ? AND day = ?
+            "#,
+            user_id,
+            day
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            // Operations that no longer exist are left to be deleted with their day
+            .filter_map(|row| {
+                QuotaOperation::from_str(&row.operation)
+                    .ok()
+                    .map(|operation| (operation, row.used))
+            })
+            .collect())
+    }
+
+    async fn delete_before(&self, before: Date) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM quota_usages
+            WHERE day < ?
+            "#,
+            before
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::test_factories::fake_recent_datetime;
+    use fake::{Fake, uuid::UUIDv4};
+    use time::Duration;
+
+    #[sqlx::test]
+    async fn test_uses_are_limited_per_user_and_day(pool: sqlx::MySqlPool) {
+        let repo = MariaDbQuotaRepository::new(pool);
+        let today = fake_recent_datetime().date();
+        let yesterday = today - Duration::DAY;
+        let (alice, bob): (Uuid, Uuid) = (UUIDv4.fake(), UUIDv4.fake());
+
+        for _ in 0..2 {
+            assert!(
+                repo.try_use(&alice, QuotaOperation::Search, today, 2)
+                    .await
+                    .unwrap()
+            );
+        }
+        assert!(
+            !repo
+                .try_use(&alice, QuotaOperation::Search, today, 2)
+                .await
+                .unwrap()
+        );
+        assert!(
+            repo.try_use(&bob, QuotaOperation::Search, today, 2)
+                .await
+                .unwrap()
+        );
+        assert!(
+            repo.try_use(&alice, QuotaOperation::Search, yesterday, 2)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            repo.find_used(&alice, today).await.unwrap(),
+            vec![(QuotaOperation::Search, 2)]
+        );
+
+        repo.delete_before(today).await.unwrap();
+        assert!(repo.find_used(&alice, yesterday).await.unwrap().is_empty());
+        assert_eq!(
+            repo.find_used(&bob, today).await.unwrap(),
+            vec![(QuotaOperation::Search, 1)]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Bloom filters of the messages each user has read, kept in memory so that timeline candidates
+//! can be fetched without checking `read_messages` for every row in SQL.
+//!
+//! A filter never misses a read recorded through this process, but it may report unread messages
+//! as read, so its hits must be confirmed against the database. Filters are rebuilt from the
+//! database after [`REBUILD_INTERVAL`], which picks up reads recorded by other instances. Until a
+//! user's filter is ready, their reads are checked in SQL as before.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// How long a filter is used before it is rebuilt from the database.
+const REBUILD_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How long a filter may take to be built before another request tries again, in case the
+/// request building it was cancelled.
+const BUILD_TIMEOUT: Duration = Duration::from_secs(30);
+/// Filters are sized for at least this many reads, so that users who read little don't get their
+/// filter saturated by their next few reads.
+const MIN_CAPACITY: usize = 1024;
+/// The number of bits per read and hashes per ID, for a false positive rate of about 1% at full
+/// capacity.
+const BITS_PER_ITEM: usize = 10;
+const HASHES: u64 = 7;
+
+pub(super) struct ReadFilter {
+    users: Mutex<HashMap<Uuid, UserFilter>>,
+    rebuild_interval: Duration,
+}
+
+enum UserFilter {
+    Building {
+        started_at: Instant,
+        /// Reads recorded while the filter is built, which the database snapshot may miss.
+        marked: Vec<Uuid>,
+    },
+    Ready {
+        built_at: Instant,
+        bloom: BloomFilter,
+    },
+}
+
+impl Default for ReadFilter {
+    fn default() -> Self {
+        Self {
+            users: Mutex::default(),
+            rebuild_interval: REBUILD_INTERVAL,
+        }
+    }
+}
+
+impl ReadFilter {
+    #[cfg(test)]
+    fn with_rebuild_interval(mut self, rebuild_interval: Duration) -> Self {
+        self.rebuild_interval = rebuild_interval;
+        self
+    }
+
+    /// Returns the user's filter unless it is missing, still being built or out of date.
+    fn ready_bloom<'a>(
+        &self,
+        users: &'a HashMap<Uuid, UserFilter>,
+        user_id: &Uuid,
+    ) -> Option<&'a BloomFilter> {
+        match users.get(user_id) {
+            Some(UserFilter::Ready { built_at, bloom })
+                if built_at.elapsed() < self.rebuild_interval =>
+            {
+                Some(bloom)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether the caller should build the user's filter with [`Self::finish_building`],
+    /// i.e. whether it is missing or out of date and nobody else is building it.
+    pub(super) fn start_building(&self, user_id: &Uuid) -> bool {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        // Filters of users who haven't come back are dropped instead of being rebuilt
+        users.retain(|_, filter| match filter {
+            UserFilter::Building { started_at, .. } => now - *started_at < BUILD_TIMEOUT,
+            UserFilter::Ready { built_at, .. } => now - *built_at < self.rebuild_interval,
+        });
+        if users.contains_key(user_id) {
+            return false;
+        }
+
+        users.insert(
+            *user_id,
+            UserFilter::Building {
+                started_at: now,
+                marked: vec![],
+            },
+        );
+        true
+    }
+
+    /// Installs the user's filter, built from the IDs read so far with [`BloomFilter::insert`].
+    pub(super) fn finish_building(&self, user_id: &Uuid, mut bloom: BloomFilter) {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(UserFilter::Building { marked, .. }) = users.remove(user_id) {
+            for id in &marked {
+                bloom.insert(id);
+            }
+        }
+
+        users.insert(
+            *user_id,
+            UserFilter::Ready {
+                built_at: Instant::now(),
+                bloom,
+            },
+        );
+    }
+
+    /// Forgets the filter being built for the user, so that the next request tries again.
+    pub(super) fn abandon_building(&self, user_id: &Uuid) {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(UserFilter::Building { .. }) = users.get(user_id) {
+            users.remove(user_id);
+        }
+    }
+
+    /// Records reads in the user's filter, if there is one.
+    pub(super) fn mark_read(&self, user_id: &Uuid, message_ids: &[Uuid]) {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        match users.get_mut(user_id) {
+            Some(UserFilter::Building { marked, .. }) => marked.extend_from_slice(message_ids),
+            Some(UserFilter::Ready { bloom, .. }) => {
+                for id in message_ids {
+                    bloom.insert(id);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Returns whether the user's filter can be used. Filters older than [`REBUILD_INTERVAL`] are
+    /// not, so that callers build them again with [`Self::start_building`].
+    pub(super) fn is_ready(&self, user_id: &Uuid) -> bool {
+        let users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        self.ready_bloom(&users, user_id).is_some()
+    }
+
+    /// Returns the messages the user may have read, or `None` if the user's filter isn't ready
+    /// and all of them have to be checked.
+    pub(super) fn possibly_read(&self, user_id: &Uuid, message_ids: &[Uuid]) -> Option<Vec<Uuid>> {
+        let users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        let bloom = self.ready_bloom(&users, user_id)?;
+
+        Some(
+            message_ids
+                .iter()
+                .filter(|id| bloom.contains(id))
+                .copied()
+                .collect(),
+        )
+    }
+}
+
+impl Debug for ReadFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("ReadFilter")
+            .field("users", &users.len())
+            .finish()
+    }
+}
+
+pub(super) struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Sizes a filter for the number of reads it is built from. Reads recorded after it is built
+    /// are added on top, raising its false positive rate until it is rebuilt.
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        let bits = capacity.max(MIN_CAPACITY) * BITS_PER_ITEM;
+        Self {
+            bits: vec![0; bits.div_ceil(64)],
+        }
+    }
+
+    pub(super) fn insert(&mut self, id: &Uuid) {
+        for bit in self.bit_indices(id) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    fn contains(&self, id: &Uuid) -> bool {
+        self.bit_indices(id)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Derives the bits of an ID by double hashing. The halves of the ID are mixed first, since
+    /// the timestamp in UUIDv7 leaves many of their bits shared between IDs.
+    fn bit_indices(&self, id: &Uuid) -> impl Iterator<Item = usize> {
+        let (high, low) = id.as_u64_pair();
+        let h1 = mix(high ^ mix(low));
+        let h2 = mix(low ^ h1) | 1;
+        let len = (self.bits.len() * 64) as u64;
+
+        (0..HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
+
+/// The finalizer of SplitMix64, which spreads every input bit over the whole output.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, uuid::UUIDv4};
+
+    fn bloom(read_ids: &[Uuid]) -> BloomFilter {
+        let mut bloom = BloomFilter::with_capacity(read_ids.len());
+        for id in read_ids {
+            bloom.insert(id);
+        }
+        bloom
+    }
+
+    #[test]
+    fn test_read_messages_are_always_possibly_read() {
+        let filter = ReadFilter::default();
+        let user_id: Uuid = UUIDv4.fake();
+        let read_ids: Vec<Uuid> = (0..2000).map(|_| UUIDv4.fake()).collect();
+
+        assert!(filter.start_building(&user_id));
+        assert!(!filter.start_building(&user_id));
+        assert!(!filter.is_ready(&user_id));
+        assert_eq!(filter.possibly_read(&user_id, &read_ids), None);
+        // Marked while the filter is built
+        filter.mark_read(&user_id, &read_ids[1000..1500]);
+        filter.finish_building(&user_id, bloom(&read_ids[..1000]));
+        filter.mark_read(&user_id, &read_ids[1500..]);
+
+        assert_eq!(filter.possibly_read(&user_id, &read_ids), Some(read_ids));
+    }
+
+    #[test]
+    fn test_unread_messages_are_rarely_possibly_read() {
+        let filter = ReadFilter::default();
+        let user_id: Uuid = UUIDv4.fake();
+        let read_ids: Vec<Uuid> = (0..MIN_CAPACITY).map(|_| UUIDv4.fake()).collect();
+        let unread_ids: Vec<Uuid> = (0..10_000).map(|_| UUIDv4.fake()).collect();
+
+        filter.start_building(&user_id);
+        filter.finish_building(&user_id, bloom(&read_ids));
+
+        let false_positives = filter.possibly_read(&user_id, &unread_ids).unwrap().len();
+        assert!(false_positives < 300, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn test_expired_filters_are_built_again() {
+        let filter = ReadFilter::default().with_rebuild_interval(Duration::ZERO);
+        let user_id: Uuid = UUIDv4.fake();
+        let read_ids: Vec<Uuid> = (0..10).map(|_| UUIDv4.fake()).collect();
+
+        assert!(filter.start_building(&user_id));
+        filter.finish_building(&user_id, bloom(&read_ids));
+
+        assert!(!filter.is_ready(&user_id));
+        assert_eq!(filter.possibly_read(&user_id, &read_ids), None);
+        assert!(filter.start_building(&user_id));
+    }
+
+    #[test]
+    fn test_abandoned_filters_are_built_again() {
+        let filter = ReadFilter::default();
+        let user_id: Uuid = UUIDv4.fake();
+
+        assert!(filter.start_building(&user_id));
+        filter.abandon_building(&user_id);
+        assert!(filter.start_building(&user_id));
+    }
+}
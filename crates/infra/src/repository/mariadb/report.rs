@@ -0,0 +1,211 @@
+use std::str::FromStr;
+
+use domain::{
+    error::RepositoryError,
+    id::{MessageId, UserId},
+    model::{ReportReason, ReportedMessage},
+    repository::ReportRepository,
+};
+use sqlx::MySqlPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbReportRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbReportRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct ReportedMessageRecord {
+    message_id: Uuid,
+    report_count: i64,
+    /// Comma-separated reasons.
+    reasons: String,
+    last_reported_at: OffsetDateTime,
+}
+
+struct MessageIdRecord {
+    message_id: Uuid,
+}
+
+#[async_trait::async_trait]
+impl ReportRepository for MariaDbReportRepository {
+    async fn add(
+        &self,
+        user_id: &UserId,
+        message_id: &MessageId,
+        reason: ReportReason,
+    ) -> Result<(), RepositoryError> {
+        let reason: &'static str = reason.into();
+
+        // Reporting a resolved message again reopens the report
+        sqlx::query!(
+            r#"
+            INSERT INTO message_reports (user_id, message_id, reason)
+            VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE reason = VALUE(reason), created_at = NOW(6), resolved_at = NULL
+            "#,
+            user_id.as_uuid(),
+            message_id.as_uuid(),
+            reason
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_unresolved(&self, limit: i64) -> Result<Vec<ReportedMessage>, RepositoryError> {
+        let records = sqlx::query_as!(
+            ReportedMessageRecord,
+            r#"
+            SELECT
+                message_id AS `message_id: _`,
+                COUNT(*) AS `report_count!: i64`,
+                GROUP_CONCAT(DISTINCT reason) AS `reasons!: String`,
+                MAX(created_at) AS `last_reported_at!: OffsetDateTime`
+            FROM message_reports
+            WHERE resolved_at IS NULL
+            GROUP BY message_id
+            ORDER BY report_count DESC, last_reported_at DESC
+            LIMIT ?
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                let reasons = record
+                    .reasons
+                    .split(',')
+                    .map(ReportReason::from_str)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| RepositoryError::Serialization(e.to_string()))?;
+
+                Ok(ReportedMessage {
+                    message_id: record.message_id,
+                    message: None,
+                    report_count: record.report_count,
+                    reasons,
+                    last_reported_at: record.last_reported_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn resolve(&self, message_id: &MessageId) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            UPDATE message_reports
+            SET resolved_at = NOW(6)
+            WHERE message_id = ? AND resolved_at IS NULL
+            "#,
+            message_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_message_ids_reported_at_least(
+        &self,
+        threshold: i64,
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let records = sqlx::query_as!(
+            MessageIdRecord,
+            r#"
+            SELECT message_id AS `message_id: _`
+            FROM message_reports
+            WHERE resolved_at IS NULL
+            GROUP BY message_id
+            HAVING COUNT(*) >= ?
+            "#,
+            threshold
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(records.into_iter().map(|r| r.message_id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::user::MariaDbUserRepository;
+    use domain::{repository::UserRepository, test_factories::UserBuilder};
+    use fake::{Fake, uuid::UUIDv4};
+
+    #[sqlx::test]
+    async fn test_report_and_resolve(pool: sqlx::MySqlPool) {
+        let repo = MariaDbReportRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create users first (FK constraint)
+        let reporter = UserBuilder::new().build();
+        let other_reporter = UserBuilder::new().build();
+        user_repo.save(&reporter).await.unwrap();
+        user_repo.save(&other_reporter).await.unwrap();
+        let message_id: Uuid = UUIDv4.fake();
+        let other_message_id: Uuid = UUIDv4.fake();
+
+        repo.add(&reporter.id.into(), &message_id.into(), ReportReason::Spam)
+            .await
+            .unwrap();
+        // Reporting twice only updates the reason
+        repo.add(
+            &reporter.id.into(),
+            &message_id.into(),
+            ReportReason::Harassment,
+        )
+        .await
+        .unwrap();
+        repo.add(
+            &other_reporter.id.into(),
+            &message_id.into(),
+            ReportReason::Harassment,
+        )
+        .await
+        .unwrap();
+        repo.add(
+            &reporter.id.into(),
+            &other_message_id.into(),
+            ReportReason::Other,
+        )
+        .await
+        .unwrap();
+
+        let reported = repo.find_unresolved(10).await.unwrap();
+        assert_eq!(reported.len(), 2);
+        assert_eq!(reported[0].message_id, message_id);
+        assert_eq!(reported[0].report_count, 2);
+        assert_eq!(reported[0].reasons, vec![ReportReason::Harassment]);
+
+        let ids = repo.find_message_ids_reported_at_least(2).await.unwrap();
+        assert_eq!(ids, vec![message_id]);
+
+        repo.resolve(&message_id.into()).await.unwrap();
+        let reported = repo.find_unresolved(10).await.unwrap();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].message_id, other_message_id);
+        assert!(
+            repo.find_message_ids_reported_at_least(2)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use domain::{error::RepositoryError, model::SavedSearch, repository::SavedSearchRepository};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbSavedSearchRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbSavedSearchRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SavedSearchRepository for MariaDbSavedSearchRepository {
+    async fn create(
+        &self,
+        user_id: &Uuid,
+        name: &str,
+        query: &str,
+    ) -> Result<SavedSearch, RepositoryError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO saved_searches (user_id, name, query)
+            VALUES (?, ?, ?)
+            "#,
+            user_id,
+            name,
+            query
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        self.find_by_id(user_id, result.last_insert_id() as i64)
+            .await?
+            .ok_or_else(|| RepositoryError::Database("saved search vanished".to_string()))
+    }
+
+    async fn import(&self, user_id: &Uuid, search: &SavedSearch) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO saved_searches (id, user_id, name, query, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            search.id,
+            user_id,
+            search.name,
+            search.query,
+            search.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_by_user(&self, user_id: &Uuid) -> Result<Vec<SavedSearch>, RepositoryError> {
+        let searches = sqlx::query_as!(
+            SavedSearch,
+            r#"
+            SELECT id, name, query, created_at
+            FROM saved_searches
+            WHERE user_id = ?
+            ORDER BY id DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(searches)
+    }
+
+    async fn find_by_id(
+        &self,
+        user_id: &Uuid,
+        id: i64,
+    ) -> Result<Option<SavedSearch>, RepositoryError> {
+        let search = sqlx::query_as!(
+            SavedSearch,
+            r#"
+            SELECT id, name, query, created_at
+            FROM saved_searches
+            WHERE id = ? AND user_id = ?
+            "#,
+            id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(search)
+    }
+
+    async fn delete(&self, user_id: &Uuid, id: i64) -> Result<bool, RepositoryError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM saved_searches
+            WHERE id = ? AND user_id = ?
+            "#,
+            id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::user::MariaDbUserRepository;
+    use domain::{repository::UserRepository, test_factories::UserBuilder};
+
+    #[sqlx::test]
+    async fn test_saved_searches(pool: sqlx::MySqlPool) {
+        let repo = MariaDbSavedSearchRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+        let user = UserBuilder::new().build();
+        let other = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        user_repo.save(&other).await.unwrap();
+
+        let first = repo.create(&user.id, "Rust", "rust").await.unwrap();
+        let second = repo.create(&user.id, "Lunch", "lunch menu").await.unwrap();
+        assert_eq!(second.name, "Lunch");
+        assert_eq!(second.query, "lunch menu");
+
+        assert_eq!(
+            repo.find_by_user(&user.id).await.unwrap(),
+            vec![second.clone(), first.clone()]
+        );
+        assert_eq!(
+            repo.find_by_id(&user.id, first.id).await.unwrap(),
+            Some(first.clone())
+        );
+
+        // Other users can neither see nor delete the search
+        assert_eq!(repo.find_by_id(&other.id, first.id).await.unwrap(), None);
+        assert!(!repo.delete(&other.id, first.id).await.unwrap());
+
+        assert!(repo.delete(&user.id, first.id).await.unwrap());
+        assert_eq!(repo.find_by_user(&user.id).await.unwrap(), vec![second]);
+    }
+}
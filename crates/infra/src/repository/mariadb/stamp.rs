@@ -1,5 +1,12 @@
-use domain::{error::RepositoryError, model::Stamp, repository::StampRepository};
-use sqlx::MySqlPool;
+use crate::repository::mariadb::in_list;
+use domain::{
+    error::RepositoryError,
+    id::{StampId, UserId},
+    model::{AffinityScore, Stamp},
+    repository::StampRepository,
+};
+use sqlx::{MySqlPool, QueryBuilder};
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -36,6 +43,23 @@ impl StampRepository for MariaDbStampRepository {
         Ok(stamp)
     }
 
+    async fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Stamp>, RepositoryError> {
+        let mut stamps = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(in_list::MAX_LEN) {
+            let mut query_builder = QueryBuilder::new("SELECT id, name FROM stamps WHERE id IN ");
+            in_list::push(&mut query_builder, chunk);
+
+            let rows: Vec<(Uuid, String)> = query_builder
+                .build_query_as()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| RepositoryError::Database(e.to_string()))?;
+            stamps.extend(rows.into_iter().map(|(id, name)| Stamp { id, name }));
+        }
+
+        Ok(stamps)
+    }
+
     async fn save(&self, stamp: &Stamp) -> Result<(), RepositoryError> {
         sqlx::query!(
             r#"
@@ -79,20 +103,103 @@ impl StampRepository for MariaDbStampRepository {
         &self,
         user_id: &Uuid,
         limit: i64,
-    ) -> Result<Vec<Uuid>, RepositoryError> {
-        struct ChannelIdRecord {
-            channel_id: Uuid,
-        }
-
-        let records = sqlx::query_as!(
-            ChannelIdRecord,
+        half_life_days: f64,
+    ) -> Result<Vec<AffinityScore>, RepositoryError> {
+        // Reactions have no timestamps, so they are dated by their messages
+        let scores = sqlx::query_as!(
+            AffinityScore,
             r#"
-            SELECT m.channel_id AS `channel_id: _`
+            SELECT
+                m.channel_id AS `id: _`,
+                SUM(POW(0.5, GREATEST(TIMESTAMPDIFF(SECOND, m.created_at, NOW()), 0) / (? * 86400))) AS `score!: f64`
             FROM reactions r
             JOIN messages m ON r.message_id = m.id
             WHERE r.user_id = ?
             GROUP BY m.channel_id
-            ORDER BY COUNT(*) DESC
+            ORDER BY score DESC
+            LIMIT ?
+            "#,
+            half_life_days,
+            user_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(scores)
+    }
+
+    async fn find_frequently_received(
+        &self,
+        author_id: &Uuid,
+        channel_id: &Uuid,
+        since: OffsetDateTime,
+        limit: i64,
+    ) -> Result<Vec<Stamp>, RepositoryError> {
+        // Counted per message, so that many users adding one stamp to a single message don't
+        // make it look usual
+        let stamps = sqlx::query_as!(
+            Stamp,
+            r#"
+            SELECT s.id AS `id: _`, s.name
+            FROM messages m
+            JOIN reactions r ON r.message_id = m.id
+            JOIN stamps s ON r.stamp_id = s.id
+            WHERE (m.user_id = ? OR m.channel_id = ?)
+              AND m.created_at > ?
+            GROUP BY s.id, s.name
+            ORDER BY COUNT(DISTINCT r.message_id) DESC, s.name
+            LIMIT ?
+            "#,
+            author_id,
+            channel_id,
+            since,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(stamps)
+    }
+
+    async fn record_usage(
+        &self,
+        user_id: &UserId,
+        stamp_id: &StampId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO stamp_usages (user_id, stamp_id)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+                use_count = use_count + 1,
+                last_used_at = CURRENT_TIMESTAMP(6)
+            "#,
+            user_id.as_uuid(),
+            stamp_id.as_uuid(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_recently_used_by(
+        &self,
+        user_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<Stamp>, RepositoryError> {
+        let stamps = sqlx::query_as!(
+            Stamp,
+            r#"
+            SELECT s.id AS `id: _`, s.name
+            FROM stamp_usages su
+            JOIN stamps s ON su.stamp_id = s.id
+            WHERE su.user_id = ?
+            ORDER BY su.last_used_at DESC
             LIMIT ?
             "#,
             user_id,
@@ -102,14 +209,16 @@ impl StampRepository for MariaDbStampRepository {
         .await
         .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        Ok(records.into_iter().map(|r| r.channel_id).collect())
+        Ok(stamps)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use domain::test_factories::{MessageBuilder, ReactionBuilder, StampBuilder};
+    use domain::test_factories::{
+        MessageBuilder, ReactionBuilder, StampBuilder, fake_recent_datetime,
+    };
     use fake::{Fake, uuid::UUIDv4};
 
     #[sqlx::test]
@@ -139,6 +248,26 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[sqlx::test]
+    async fn test_find_by_ids(pool: sqlx::MySqlPool) {
+        let repo = MariaDbStampRepository::new(pool);
+
+        let stamps = vec![StampBuilder::new().build(), StampBuilder::new().build()];
+        repo.save_batch(&stamps).await.unwrap();
+
+        let missing_id = UUIDv4.fake();
+        let mut found = repo
+            .find_by_ids(&[stamps[1].id, missing_id, stamps[0].id])
+            .await
+            .unwrap();
+        found.sort_by_key(|s| s.id);
+        let mut expected: Vec<Uuid> = stamps.iter().map(|s| s.id).collect();
+        expected.sort();
+
+        assert_eq!(found.iter().map(|s| s.id).collect::<Vec<_>>(), expected);
+        assert!(repo.find_by_ids(&[]).await.unwrap().is_empty());
+    }
+
     #[sqlx::test]
     async fn test_save_batch(pool: sqlx::MySqlPool) {
         let repo = MariaDbStampRepository::new(pool);
@@ -185,10 +314,109 @@ mod tests {
         assert_eq!(found.name, "updated_name");
     }
 
+    #[sqlx::test]
+    async fn test_find_frequently_received(pool: sqlx::MySqlPool) {
+        use crate::repository::mariadb::message::MariaDbMessageRepository;
+        use domain::repository::MessageWriter;
+        use time::Duration;
+
+        let repo = MariaDbStampRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool);
+
+        let (author, channel) = (UUIDv4.fake(), UUIDv4.fake());
+        let usual = StampBuilder::new().name("usual").build();
+        let rare = StampBuilder::new().name("rare").build();
+        let elsewhere = StampBuilder::new().name("elsewhere").build();
+        repo.save_batch(&[usual.clone(), rare.clone(), elsewhere.clone()])
+            .await
+            .unwrap();
+
+        let now = OffsetDateTime::now_utc();
+        let reaction = |stamp: &Stamp| ReactionBuilder::new().stamp_id(stamp.id).build();
+        let messages = vec![
+            // Many users adding a stamp to one message count once
+            MessageBuilder::new()
+                .user_id(author)
+                .created_at(now)
+                .reactions(vec![reaction(&rare), reaction(&rare), reaction(&rare)])
+                .build(),
+            MessageBuilder::new()
+                .user_id(author)
+                .created_at(now)
+                .reactions(vec![reaction(&usual)])
+                .build(),
+            MessageBuilder::new()
+                .channel_id(channel)
+                .created_at(now)
+                .reactions(vec![reaction(&usual)])
+                .build(),
+            MessageBuilder::new()
+                .created_at(now)
+                .reactions(vec![reaction(&elsewhere)])
+                .build(),
+            MessageBuilder::new()
+                .user_id(author)
+                .created_at(now - Duration::days(60))
+                .reactions(vec![reaction(&elsewhere), reaction(&elsewhere)])
+                .build(),
+        ];
+        message_repo.save_batch(&messages).await.unwrap();
+
+        let stamps = repo
+            .find_frequently_received(&author, &channel, now - Duration::days(30), 10)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = stamps.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![usual.id, rare.id]);
+    }
+
+    #[sqlx::test]
+    async fn test_find_recently_used_by(pool: sqlx::MySqlPool) {
+        use crate::repository::mariadb::user::MariaDbUserRepository;
+        use domain::{repository::UserRepository, test_factories::UserBuilder};
+
+        let repo = MariaDbStampRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+        let user = UserBuilder::new().build();
+        let other = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        user_repo.save(&other).await.unwrap();
+
+        let first = StampBuilder::new().build();
+        let second = StampBuilder::new().build();
+        repo.save_batch(&[first.clone(), second.clone()])
+            .await
+            .unwrap();
+        let uncached_id = UUIDv4.fake();
+
+        repo.record_usage(&user.id.into(), &first.id.into())
+            .await
+            .unwrap();
+        repo.record_usage(&user.id.into(), &second.id.into())
+            .await
+            .unwrap();
+        repo.record_usage(&user.id.into(), &uncached_id)
+            .await
+            .unwrap();
+        repo.record_usage(&user.id.into(), &first.id.into())
+            .await
+            .unwrap();
+        repo.record_usage(&other.id.into(), &second.id.into())
+            .await
+            .unwrap();
+
+        let recent = repo.find_recently_used_by(&user.id, 10).await.unwrap();
+        let ids: Vec<Uuid> = recent.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![first.id, second.id]);
+
+        let recent = repo.find_recently_used_by(&user.id, 1).await.unwrap();
+        assert_eq!(recent.len(), 1);
+    }
+
     #[sqlx::test]
     async fn test_find_frequently_stamped_channels_by(pool: sqlx::MySqlPool) {
         use crate::repository::mariadb::message::MariaDbMessageRepository;
-        use domain::repository::MessageRepository;
+        use domain::repository::MessageWriter;
 
         let stamp_repo = MariaDbStampRepository::new(pool.clone());
         let message_repo = MariaDbMessageRepository::new(pool.clone());
@@ -205,6 +433,7 @@ mod tests {
                 .id(msg.id)
                 .channel_id(msg.channel_id)
                 .reactions(vec![reaction])
+                .created_at(fake_recent_datetime())
                 .build();
             message_repo.save(&msg_with_reaction).await.unwrap();
         }
@@ -217,6 +446,7 @@ mod tests {
                 .id(msg.id)
                 .channel_id(msg.channel_id)
                 .reactions(vec![reaction])
+                .created_at(fake_recent_datetime())
                 .build();
             message_repo.save(&msg_with_reaction).await.unwrap();
         }
@@ -229,16 +459,18 @@ mod tests {
             .id(msg.id)
             .channel_id(msg.channel_id)
             .reactions(vec![reaction])
+            .created_at(fake_recent_datetime())
             .build();
         message_repo.save(&msg_with_reaction).await.unwrap();
 
         let channels = stamp_repo
-            .find_frequently_stamped_channels_by(&user_id, 10)
+            .find_frequently_stamped_channels_by(&user_id, 10, 30.0)
             .await
             .unwrap();
 
         assert_eq!(channels.len(), 2);
-        assert_eq!(channels[0], channel_1); // Most frequent first
-        assert_eq!(channels[1], channel_2);
+        assert_eq!(channels[0].id, channel_1); // Most frequent first
+        assert_eq!(channels[1].id, channel_2);
+        assert!(channels[0].score > channels[1].score);
     }
 }
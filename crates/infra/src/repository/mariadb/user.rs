@@ -1,5 +1,10 @@
-use domain::{error::RepositoryError, model::User, repository::UserRepository};
-use sqlx::MySqlPool;
+use crate::repository::mariadb::in_list;
+use domain::{
+    error::RepositoryError,
+    model::{AffinityScore, User},
+    repository::UserRepository,
+};
+use sqlx::{MySqlPool, QueryBuilder};
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -40,6 +45,28 @@ impl UserRepository for MariaDbUserRepository {
         Ok(user)
     }
 
+    async fn find_by_ids(&self, ids: &[Uuid]) -> Result<Vec<User>, RepositoryError> {
+        let mut users = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(in_list::MAX_LEN) {
+            let mut query_builder =
+                QueryBuilder::new("SELECT id, handle, display_name FROM users WHERE id IN ");
+            in_list::push(&mut query_builder, chunk);
+
+            let rows: Vec<(Uuid, String, String)> = query_builder
+                .build_query_as()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| RepositoryError::Database(e.to_string()))?;
+            users.extend(rows.into_iter().map(|(id, handle, display_name)| User {
+                id,
+                handle,
+                display_name,
+            }));
+        }
+
+        Ok(users)
+    }
+
     async fn find_random_valid_token(&self) -> Result<Option<String>, RepositoryError> {
         let rows_count = sqlx::query_scalar!(
             r#"
@@ -133,18 +160,23 @@ impl UserRepository for MariaDbUserRepository {
         &self,
         user_id: &Uuid,
         limit: i64,
-    ) -> Result<Vec<Uuid>, RepositoryError> {
-        let records = sqlx::query_as!(
-            UserIdRecord,
+        half_life_days: f64,
+    ) -> Result<Vec<AffinityScore>, RepositoryError> {
+        // Reactions have no timestamps, so they are dated by their messages
+        let scores = sqlx::query_as!(
+            AffinityScore,
             r#"
-            SELECT m.user_id AS `user_id: _`
+            SELECT
+                m.user_id AS `id: _`,
+                SUM(POW(0.5, GREATEST(TIMESTAMPDIFF(SECOND, m.created_at, NOW()), 0) / (? * 86400))) AS `score!: f64`
             FROM reactions r
             JOIN messages m ON r.message_id = m.id
             WHERE r.user_id = ? AND m.user_id != ?
             GROUP BY m.user_id
-            ORDER BY COUNT(*) DESC
+            ORDER BY score DESC
             LIMIT ?
             "#,
+            half_life_days,
             user_id,
             user_id,
             limit
@@ -153,7 +185,7 @@ impl UserRepository for MariaDbUserRepository {
         .await
         .map_err(|e| RepositoryError::Database(e.to_string()))?;
 
-        Ok(records.into_iter().map(|r| r.user_id).collect())
+        Ok(scores)
     }
 
     async fn find_similar_users(
@@ -182,12 +214,56 @@ impl UserRepository for MariaDbUserRepository {
 
         Ok(records.into_iter().map(|r| r.user_id).collect())
     }
+
+    async fn find_acquaintances(
+        &self,
+        viewer_ids: &[Uuid],
+        user_ids: &[Uuid],
+    ) -> Result<Vec<(Uuid, Uuid)>, RepositoryError> {
+        let mut acquaintances = vec![];
+        for viewer_chunk in viewer_ids.chunks(in_list::MAX_LEN) {
+            for user_chunk in user_ids.chunks(in_list::MAX_LEN) {
+                let mut query_builder = QueryBuilder::new(
+                    r#"
+                    SELECT f.followed_user_id, f.user_id
+                    FROM follows f
+                    WHERE f.followed_user_id IN "#,
+                );
+                in_list::push(&mut query_builder, viewer_chunk);
+                query_builder.push(" AND f.user_id IN ");
+                in_list::push(&mut query_builder, user_chunk);
+                query_builder.push(
+                    r#"
+                    UNION
+                    SELECT m.user_id, r.user_id
+                    FROM reactions r
+                    JOIN messages m ON r.message_id = m.id
+                    WHERE m.user_id IN "#,
+                );
+                in_list::push(&mut query_builder, viewer_chunk);
+                query_builder.push(" AND r.user_id IN ");
+                in_list::push(&mut query_builder, user_chunk);
+
+                acquaintances.extend(
+                    query_builder
+                        .build_query_as::<(Uuid, Uuid)>()
+                        .fetch_all(&self.pool)
+                        .await
+                        .map_err(|e| RepositoryError::Database(e.to_string()))?,
+                );
+            }
+        }
+
+        Ok(acquaintances)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use domain::test_factories::{MessageBuilder, ReactionBuilder, UserBuilder};
+    use domain::test_factories::{
+        MessageBuilder, ReactionBuilder, UserBuilder, fake_recent_datetime,
+    };
     use fake::{Fake, uuid::UUIDv4};
 
     #[sqlx::test]
@@ -218,6 +294,27 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[sqlx::test]
+    async fn test_find_by_ids(pool: sqlx::MySqlPool) {
+        let repo = MariaDbUserRepository::new(pool);
+
+        let users = vec![UserBuilder::new().build(), UserBuilder::new().build()];
+        for user in &users {
+            repo.save(user).await.unwrap();
+        }
+
+        let mut found = repo
+            .find_by_ids(&[users[1].id, UUIDv4.fake(), users[0].id])
+            .await
+            .unwrap();
+        found.sort_by_key(|u| u.id);
+        let mut expected = users.clone();
+        expected.sort_by_key(|u| u.id);
+
+        assert_eq!(found, expected);
+        assert!(repo.find_by_ids(&[]).await.unwrap().is_empty());
+    }
+
     #[sqlx::test]
     async fn test_save_and_find_token(pool: sqlx::MySqlPool) {
         let repo = MariaDbUserRepository::new(pool);
@@ -298,7 +395,7 @@ mod tests {
     #[sqlx::test]
     async fn test_find_frequently_stamped_users_by(pool: sqlx::MySqlPool) {
         use crate::repository::mariadb::message::MariaDbMessageRepository;
-        use domain::repository::MessageRepository;
+        use domain::repository::MessageWriter;
 
         let user_repo = MariaDbUserRepository::new(pool.clone());
         let message_repo = MariaDbMessageRepository::new(pool.clone());
@@ -315,6 +412,7 @@ mod tests {
                 .id(msg.id)
                 .user_id(msg.user_id)
                 .reactions(vec![reaction])
+                .created_at(fake_recent_datetime())
                 .build();
             message_repo.save(&msg_with_reaction).await.unwrap();
         }
@@ -327,6 +425,7 @@ mod tests {
                 .id(msg.id)
                 .user_id(msg.user_id)
                 .reactions(vec![reaction])
+                .created_at(fake_recent_datetime())
                 .build();
             message_repo.save(&msg_with_reaction).await.unwrap();
         }
@@ -339,23 +438,64 @@ mod tests {
             .id(msg.id)
             .user_id(msg.user_id)
             .reactions(vec![reaction])
+            .created_at(fake_recent_datetime())
             .build();
         message_repo.save(&msg_with_reaction).await.unwrap();
 
         let stamped_users = user_repo
-            .find_frequently_stamped_users_by(&me, 10)
+            .find_frequently_stamped_users_by(&me, 10, 30.0)
             .await
             .unwrap();
 
         assert_eq!(stamped_users.len(), 2);
-        assert_eq!(stamped_users[0], target_user_1);
-        assert_eq!(stamped_users[1], target_user_2);
+        assert_eq!(stamped_users[0].id, target_user_1);
+        assert_eq!(stamped_users[1].id, target_user_2);
+        // Recent reactions count almost fully
+        assert!(stamped_users[0].score > 2.9 && stamped_users[0].score <= 3.0);
+    }
+
+    #[sqlx::test]
+    async fn test_find_frequently_stamped_users_by_decays_old_reactions(pool: sqlx::MySqlPool) {
+        use crate::repository::mariadb::message::MariaDbMessageRepository;
+        use domain::repository::MessageWriter;
+
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool.clone());
+
+        let me = UUIDv4.fake();
+        let old_friend = UUIDv4.fake();
+        let new_friend = UUIDv4.fake();
+        let now = time::OffsetDateTime::now_utc();
+
+        // 3 reactions 10 half-lives ago weigh less than 1 reaction today
+        for _ in 0..3 {
+            let msg = MessageBuilder::new()
+                .user_id(old_friend)
+                .created_at(now - time::Duration::days(300))
+                .reactions(vec![ReactionBuilder::new().user_id(me).build()])
+                .build();
+            message_repo.save(&msg).await.unwrap();
+        }
+        let msg = MessageBuilder::new()
+            .user_id(new_friend)
+            .created_at(now)
+            .reactions(vec![ReactionBuilder::new().user_id(me).build()])
+            .build();
+        message_repo.save(&msg).await.unwrap();
+
+        let stamped_users = user_repo
+            .find_frequently_stamped_users_by(&me, 10, 30.0)
+            .await
+            .unwrap();
+
+        let ids: Vec<Uuid> = stamped_users.iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec![new_friend, old_friend]);
     }
 
     #[sqlx::test]
     async fn test_find_similar_users(pool: sqlx::MySqlPool) {
         use crate::repository::mariadb::message::MariaDbMessageRepository;
-        use domain::repository::MessageRepository;
+        use domain::repository::MessageWriter;
 
         let user_repo = MariaDbUserRepository::new(pool.clone());
         let message_repo = MariaDbMessageRepository::new(pool.clone());
@@ -415,4 +555,43 @@ mod tests {
         assert_eq!(similar_users[0], similar_user_1); // 2 co-occurrences
         assert_eq!(similar_users[1], similar_user_2); // 1 co-occurrence
     }
+
+    #[sqlx::test]
+    async fn test_find_acquaintances(pool: sqlx::MySqlPool) {
+        use crate::repository::mariadb::{
+            follow::MariaDbFollowRepository, message::MariaDbMessageRepository,
+        };
+        use domain::repository::{FollowRepository, MessageWriter};
+
+        let user_repo = MariaDbUserRepository::new(pool.clone());
+        let follow_repo = MariaDbFollowRepository::new(pool.clone());
+        let message_repo = MariaDbMessageRepository::new(pool);
+
+        let [viewer, follower, stamper, stranger] = [(); 4].map(|_| UserBuilder::new().build());
+        for user in [&viewer, &follower, &stamper, &stranger] {
+            user_repo.save(user).await.unwrap();
+        }
+        follow_repo
+            .follow_user(&follower.id.into(), &viewer.id.into())
+            .await
+            .unwrap();
+        message_repo
+            .save(
+                &MessageBuilder::new()
+                    .user_id(viewer.id)
+                    .reactions(vec![ReactionBuilder::new().user_id(stamper.id).build()])
+                    .build(),
+            )
+            .await
+            .unwrap();
+
+        let mut acquaintances = user_repo
+            .find_acquaintances(&[viewer.id], &[follower.id, stamper.id, stranger.id])
+            .await
+            .unwrap();
+        acquaintances.sort();
+        let mut expected = vec![(viewer.id, follower.id), (viewer.id, stamper.id)];
+        expected.sort();
+        assert_eq!(acquaintances, expected);
+    }
 }
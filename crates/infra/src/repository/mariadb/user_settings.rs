@@ -0,0 +1,451 @@
+use crate::repository::mariadb::in_list;
+use domain::{
+    error::RepositoryError,
+    id::{ChannelId, UserId},
+    model::{ChannelScoreOverride, OnboardingState, OnboardingStep, PrivacySettings},
+    repository::UserSettingsRepository,
+};
+use sqlx::{MySqlPool, QueryBuilder};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbUserSettingsRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbUserSettingsRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserSettingsRepository for MariaDbUserSettingsRepository {
+    async fn find_onboarding_state(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<OnboardingState, RepositoryError> {
+        let state = sqlx::query_as!(
+            OnboardingState,
+            r#"
+            SELECT
+                privacy_notice_accepted_at IS NOT NULL AS `privacy_notice_accepted!: bool`,
+                crawl_consent_granted_at IS NOT NULL AS `crawl_consent_granted!: bool`,
+                initial_channels_picked_at IS NOT NULL AS `initial_channels_picked!: bool`
+            FROM user_settings
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(state.unwrap_or_default())
+    }
+
+    async fn complete_onboarding_step(
+        &self,
+        user_id: &Uuid,
+        step: OnboardingStep,
+    ) -> Result<(), RepositoryError> {
+        let privacy_notice = step == OnboardingStep::PrivacyNotice;
+        let crawl_consent = step == OnboardingStep::CrawlConsent;
+        let initial_channels = step == OnboardingStep::InitialChannels;
+
+        // Completing a step again keeps the original timestamp
+        sqlx::query!(
+            r#"
+            INSERT INTO user_settings (
+                user_id,
+                privacy_notice_accepted_at,
+                crawl_consent_granted_at,
+                initial_channels_picked_at
+            )
+            VALUES (?, IF(?, NOW(6), NULL), IF(?, NOW(6), NULL), IF(?, NOW(6), NULL))
+            ON DUPLICATE KEY UPDATE
+                privacy_notice_accepted_at =
+                    COALESCE(privacy_notice_accepted_at, VALUE(privacy_notice_accepted_at)),
+                crawl_consent_granted_at =
+                    COALESCE(crawl_consent_granted_at, VALUE(crawl_consent_granted_at)),
+                initial_channels_picked_at =
+                    COALESCE(initial_channels_picked_at, VALUE(initial_channels_picked_at))
+            "#,
+            user_id,
+            privacy_notice,
+            crawl_consent,
+            initial_channels
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_channel_interests(&self, user_id: &Uuid) -> Result<Vec<Uuid>, RepositoryError> {
+        struct ChannelIdRecord {
+            channel_id: Uuid,
+        }
+
+        let records = sqlx::query_as!(
+            ChannelIdRecord,
+            r#"
+            SELECT channel_id AS `channel_id: _`
+            FROM channel_interests
+            WHERE user_id = ?
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(records.into_iter().map(|r| r.channel_id).collect())
+    }
+
+    async fn save_channel_interests(
+        &self,
+        user_id: &UserId,
+        channel_ids: &[ChannelId],
+    ) -> Result<(), RepositoryError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM channel_interests
+            WHERE user_id = ?
+            "#,
+            user_id.as_uuid()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        if !channel_ids.is_empty() {
+            let mut query_builder =
+                QueryBuilder::new("INSERT IGNORE INTO channel_interests (user_id, channel_id) ");
+            query_builder.push_values(channel_ids, |mut b, channel_id| {
+                b.push_bind(*user_id.as_uuid())
+                    .push_bind(*channel_id.as_uuid());
+            });
+            query_builder
+                .build()
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| RepositoryError::Database(e.to_string()))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_channel_score_overrides(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<ChannelScoreOverride>, RepositoryError> {
+        let overrides = sqlx::query_as!(
+            ChannelScoreOverride,
+            r#"
+            SELECT
+                channel_id AS `channel_id: _`,
+                multiplier
+            FROM channel_score_overrides
+            WHERE user_id = ?
+            ORDER BY updated_at
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(overrides)
+    }
+
+    async fn save_channel_score_override(
+        &self,
+        user_id: &Uuid,
+        score_override: &ChannelScoreOverride,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO channel_score_overrides (user_id, channel_id, multiplier)
+            VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE multiplier = VALUE(multiplier), updated_at = NOW(6)
+            "#,
+            user_id,
+            score_override.channel_id,
+            score_override.multiplier
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn delete_channel_score_override(
+        &self,
+        user_id: &UserId,
+        channel_id: &ChannelId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM channel_score_overrides
+            WHERE user_id = ? AND channel_id = ?
+            "#,
+            user_id.as_uuid(),
+            channel_id.as_uuid()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_privacy_settings(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<PrivacySettings, RepositoryError> {
+        let settings = sqlx::query_as!(
+            PrivacySettings,
+            r#"
+            SELECT
+                recommend_only_to_acquaintances AS `recommend_only_to_acquaintances: bool`,
+                share_content_in_highlights AS `share_content_in_highlights: bool`
+            FROM user_settings
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(settings.unwrap_or_default())
+    }
+
+    async fn save_privacy_settings(
+        &self,
+        user_id: &Uuid,
+        settings: &PrivacySettings,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_settings (
+                user_id, recommend_only_to_acquaintances, share_content_in_highlights
+            )
+            VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                recommend_only_to_acquaintances = VALUE(recommend_only_to_acquaintances),
+                share_content_in_highlights = VALUE(share_content_in_highlights)
+            "#,
+            user_id,
+            settings.recommend_only_to_acquaintances,
+            settings.share_content_in_highlights
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_acquaintance_only_user_ids(
+        &self,
+        user_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, RepositoryError> {
+        let mut opted_out = vec![];
+        for chunk in user_ids.chunks(in_list::MAX_LEN) {
+            let mut query_builder = QueryBuilder::new(
+                "SELECT user_id FROM user_settings WHERE recommend_only_to_acquaintances AND user_id IN ",
+            );
+            in_list::push(&mut query_builder, chunk);
+
+            opted_out.extend(
+                query_builder
+                    .build_query_scalar::<Uuid>()
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| RepositoryError::Database(e.to_string()))?,
+            );
+        }
+
+        Ok(opted_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::mariadb::user::MariaDbUserRepository;
+    use domain::{repository::UserRepository, test_factories::UserBuilder};
+    use fake::{Fake, uuid::UUIDv4};
+
+    #[sqlx::test]
+    async fn test_complete_onboarding_steps(pool: sqlx::MySqlPool) {
+        let repo = MariaDbUserSettingsRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create user first (FK constraint)
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+
+        let state = repo.find_onboarding_state(&user.id).await.unwrap();
+        assert_eq!(state, OnboardingState::default());
+
+        repo.complete_onboarding_step(&user.id, OnboardingStep::PrivacyNotice)
+            .await
+            .unwrap();
+        repo.complete_onboarding_step(&user.id, OnboardingStep::CrawlConsent)
+            .await
+            .unwrap();
+        // Completing a step twice is a no-op
+        repo.complete_onboarding_step(&user.id, OnboardingStep::PrivacyNotice)
+            .await
+            .unwrap();
+
+        let state = repo.find_onboarding_state(&user.id).await.unwrap();
+        assert!(state.privacy_notice_accepted);
+        assert!(state.crawl_consent_granted);
+        assert!(!state.initial_channels_picked);
+        assert!(!state.is_complete());
+
+        repo.complete_onboarding_step(&user.id, OnboardingStep::InitialChannels)
+            .await
+            .unwrap();
+        let state = repo.find_onboarding_state(&user.id).await.unwrap();
+        assert!(state.is_complete());
+    }
+
+    #[sqlx::test]
+    async fn test_save_channel_interests(pool: sqlx::MySqlPool) {
+        let repo = MariaDbUserSettingsRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create user first (FK constraint)
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let channel_ids: Vec<ChannelId> = (0..3).map(|_| UUIDv4.fake()).collect();
+
+        repo.save_channel_interests(&user.id.into(), &channel_ids)
+            .await
+            .unwrap();
+        let mut interests = repo.find_channel_interests(&user.id).await.unwrap();
+        interests.sort();
+        let mut expected: Vec<Uuid> = channel_ids.iter().copied().map(Uuid::from).collect();
+        expected.sort();
+        assert_eq!(interests, expected);
+
+        // Saving again replaces the interests
+        repo.save_channel_interests(&user.id.into(), &channel_ids[..1])
+            .await
+            .unwrap();
+        let interests = repo.find_channel_interests(&user.id).await.unwrap();
+        assert_eq!(interests, vec![Uuid::from(channel_ids[0])]);
+    }
+
+    #[sqlx::test]
+    async fn test_channel_score_overrides(pool: sqlx::MySqlPool) {
+        let repo = MariaDbUserSettingsRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        // Create user first (FK constraint)
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        let boosted = ChannelScoreOverride {
+            channel_id: UUIDv4.fake(),
+            multiplier: 2.0,
+        };
+
+        repo.save_channel_score_override(&user.id, &boosted)
+            .await
+            .unwrap();
+        // Saving again replaces the multiplier
+        let boosted = ChannelScoreOverride {
+            multiplier: 3.0,
+            ..boosted
+        };
+        repo.save_channel_score_override(&user.id, &boosted)
+            .await
+            .unwrap();
+        let overrides = repo.find_channel_score_overrides(&user.id).await.unwrap();
+        assert_eq!(overrides, vec![boosted]);
+
+        repo.delete_channel_score_override(&user.id.into(), &boosted.channel_id.into())
+            .await
+            .unwrap();
+        let overrides = repo.find_channel_score_overrides(&user.id).await.unwrap();
+        assert!(overrides.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_privacy_settings(pool: sqlx::MySqlPool) {
+        let repo = MariaDbUserSettingsRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let user = UserBuilder::new().build();
+        user_repo.save(&user).await.unwrap();
+        repo.complete_onboarding_step(&user.id, OnboardingStep::PrivacyNotice)
+            .await
+            .unwrap();
+
+        let settings = repo.find_privacy_settings(&user.id).await.unwrap();
+        assert_eq!(settings, PrivacySettings::default());
+
+        let opted_out = PrivacySettings {
+            recommend_only_to_acquaintances: true,
+            share_content_in_highlights: true,
+        };
+        repo.save_privacy_settings(&user.id, &opted_out)
+            .await
+            .unwrap();
+        assert_eq!(
+            repo.find_privacy_settings(&user.id).await.unwrap(),
+            opted_out
+        );
+        // Saving the settings keeps the onboarding progress
+        let state = repo.find_onboarding_state(&user.id).await.unwrap();
+        assert!(state.privacy_notice_accepted);
+    }
+
+    #[sqlx::test]
+    async fn test_find_acquaintance_only_user_ids(pool: sqlx::MySqlPool) {
+        let repo = MariaDbUserSettingsRepository::new(pool.clone());
+        let user_repo = MariaDbUserRepository::new(pool);
+
+        let opted_out = UserBuilder::new().build();
+        let opted_in = UserBuilder::new().build();
+        for user in [&opted_out, &opted_in] {
+            user_repo.save(user).await.unwrap();
+        }
+        repo.save_privacy_settings(
+            &opted_out.id,
+            &PrivacySettings {
+                recommend_only_to_acquaintances: true,
+                share_content_in_highlights: false,
+            },
+        )
+        .await
+        .unwrap();
+        repo.save_privacy_settings(&opted_in.id, &PrivacySettings::default())
+            .await
+            .unwrap();
+
+        let result = repo
+            .find_acquaintance_only_user_ids(&[opted_in.id, opted_out.id, UUIDv4.fake()])
+            .await
+            .unwrap();
+        assert_eq!(result, vec![opted_out.id]);
+    }
+}
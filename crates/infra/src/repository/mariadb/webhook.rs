@@ -0,0 +1,188 @@
+use domain::{
+    error::RepositoryError,
+    model::{Webhook, WebhookEvent},
+    repository::WebhookRepository,
+};
+use sqlx::MySqlPool;
+use std::str::FromStr;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct MariaDbWebhookRepository {
+    pool: MySqlPool,
+}
+
+impl MariaDbWebhookRepository {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct WebhookRecord {
+    id: i64,
+    url: String,
+    secret: String,
+    events: String,
+    created_by: Uuid,
+    created_at: OffsetDateTime,
+}
+
+impl WebhookRecord {
+    fn into_webhook(self) -> Result<(Webhook, String), RepositoryError> {
+        let events = self
+            .events
+            .split(',')
+            .map(WebhookEvent::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| RepositoryError::Serialization(e.to_string()))?;
+
+        let webhook = Webhook {
+            id: self.id,
+            url: self.url,
+            events,
+            created_by: self.created_by,
+            created_at: self.created_at,
+        };
+        Ok((webhook, self.secret))
+    }
+}
+
+fn join_events(events: &[WebhookEvent]) -> String {
+    events
+        .iter()
+        .map(|&event| <&'static str>::from(event))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[async_trait::async_trait]
+impl WebhookRepository for MariaDbWebhookRepository {
+    async fn create(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+        created_by: &Uuid,
+    ) -> Result<Webhook, RepositoryError> {
+        let events = join_events(events);
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webhooks (url, secret, events, created_by)
+            VALUES (?, ?, ?, ?)
+            "#,
+            url,
+            secret,
+            events,
+            created_by
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        let id = result.last_insert_id() as i64;
+        self.find_all_with_secrets()
+            .await?
+            .into_iter()
+            .map(|(webhook, _)| webhook)
+            .find(|webhook| webhook.id == id)
+            .ok_or_else(|| RepositoryError::Database("webhook vanished".to_string()))
+    }
+
+    async fn import(&self, webhook: &Webhook, secret: &str) -> Result<(), RepositoryError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhooks (id, url, secret, events, created_by, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            webhook.id,
+            webhook.url,
+            secret,
+            join_events(&webhook.events),
+            webhook.created_by,
+            webhook.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn find_all(&self) -> Result<Vec<Webhook>, RepositoryError> {
+        let webhooks = self.find_all_with_secrets().await?;
+        Ok(webhooks.into_iter().map(|(webhook, _)| webhook).collect())
+    }
+
+    async fn find_all_with_secrets(&self) -> Result<Vec<(Webhook, String)>, RepositoryError> {
+        let records = sqlx::query_as!(
+            WebhookRecord,
+            r#"
+            SELECT id, url, secret, events, created_by AS `created_by: _`, created_at
+            FROM webhooks
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        records
+            .into_iter()
+            .map(WebhookRecord::into_webhook)
+            .collect()
+    }
+
+    async fn delete(&self, id: i64) -> Result<bool, RepositoryError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM webhooks
+            WHERE id = ?
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RepositoryError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, uuid::UUIDv4};
+
+    #[sqlx::test]
+    async fn test_webhooks(pool: sqlx::MySqlPool) {
+        let repo = MariaDbWebhookRepository::new(pool);
+        let admin_id: Uuid = UUIDv4.fake();
+
+        let webhook = repo
+            .create(
+                "https://hooks.example.com",
+                "secret",
+                &[WebhookEvent::MessageEdited, WebhookEvent::ReactionsChanged],
+                &admin_id,
+            )
+            .await
+            .unwrap();
+        assert_eq!(webhook.url, "https://hooks.example.com");
+        assert_eq!(
+            webhook.events,
+            vec![WebhookEvent::MessageEdited, WebhookEvent::ReactionsChanged]
+        );
+        assert_eq!(webhook.created_by, admin_id);
+
+        assert_eq!(repo.find_all().await.unwrap(), vec![webhook.clone()]);
+        assert_eq!(
+            repo.find_all_with_secrets().await.unwrap(),
+            vec![(webhook.clone(), "secret".to_string())]
+        );
+
+        assert!(repo.delete(webhook.id).await.unwrap());
+        assert!(!repo.delete(webhook.id).await.unwrap());
+        assert_eq!(repo.find_all().await.unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,216 @@
+//! HMAC signing keys for artifacts handed out to clients or other services, such as share links,
+//! feed tokens and webhook signatures.
+//!
+//! A [`KeyRing`] signs with one active key but accepts signatures by any key it holds, so keys can
+//! be rotated without invalidating everything at once: add the new key, make it active once every
+//! instance has it, and remove the old key after the artifacts it signed have expired.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{collections::HashMap, fmt};
+
+pub mod webhook;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shorter secrets are rejected, since they can be brute-forced offline from any signature.
+pub const MIN_SECRET_LEN: usize = 32;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum SigningError {
+    #[error("invalid signing key `{id}`: {message}")]
+    InvalidKey { id: String, message: String },
+
+    #[error("the active signing key `{0}` is not in the key ring")]
+    MissingActiveKey(String),
+
+    #[error("malformed signature")]
+    Malformed,
+
+    #[error("signed by unknown key `{0}`")]
+    UnknownKey(String),
+
+    #[error("signature mismatch")]
+    Mismatch,
+}
+
+#[derive(Clone)]
+pub struct SigningKey {
+    id: String,
+    secret: Vec<u8>,
+}
+
+impl SigningKey {
+    /// Creates a key. IDs consist of ASCII letters, digits, `-` and `_`, since they are embedded
+    /// in signatures.
+    pub fn new(id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Result<Self, SigningError> {
+        let id = id.into();
+        let secret = secret.into();
+
+        let invalid = |message: &str| SigningError::InvalidKey {
+            id: id.clone(),
+            message: message.to_string(),
+        };
+        if id.is_empty()
+            || !id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        {
+            return Err(invalid(
+                "IDs may only contain ASCII letters, digits, `-` and `_`",
+            ));
+        }
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(invalid(&format!(
+                "secrets must be at least {MIN_SECRET_LEN} bytes"
+            )));
+        }
+
+        Ok(Self { id, secret })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac
+    }
+}
+
+// Never print secrets
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyRing {
+    active_id: String,
+    keys: HashMap<String, SigningKey>,
+}
+
+impl KeyRing {
+    pub fn new(
+        active_id: impl Into<String>,
+        keys: impl IntoIterator<Item = SigningKey>,
+    ) -> Result<Self, SigningError> {
+        let active_id = active_id.into();
+        let keys: HashMap<String, SigningKey> =
+            keys.into_iter().map(|k| (k.id.clone(), k)).collect();
+
+        if !keys.contains_key(&active_id) {
+            return Err(SigningError::MissingActiveKey(active_id));
+        }
+
+        Ok(Self { active_id, keys })
+    }
+
+    /// The ID of the key new signatures are made with.
+    pub fn active_key_id(&self) -> &str {
+        &self.active_id
+    }
+
+    /// The number of keys whose signatures are accepted.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Signs the payload with the active key.
+    /// The signature has the form `<key ID>.<base64url HMAC-SHA256>`.
+    pub fn sign(&self, payload: &[u8]) -> String {
+        let key = &self.keys[&self.active_id];
+        let tag = key.mac(payload).finalize().into_bytes();
+
+        format!("{}.{}", key.id, URL_SAFE_NO_PAD.encode(tag))
+    }
+
+    /// Verifies a signature made by any key in the ring.
+    pub fn verify(&self, payload: &[u8], signature: &str) -> Result<(), SigningError> {
+        let (id, tag) = signature.split_once('.').ok_or(SigningError::Malformed)?;
+        let tag = URL_SAFE_NO_PAD
+            .decode(tag)
+            .map_err(|_| SigningError::Malformed)?;
+        let key = self
+            .keys
+            .get(id)
+            .ok_or_else(|| SigningError::UnknownKey(id.to_string()))?;
+
+        // Compared in constant time
+        key.mac(payload)
+            .verify_slice(&tag)
+            .map_err(|_| SigningError::Mismatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: &str) -> SigningKey {
+        SigningKey::new(
+            id,
+            format!("{id}-secret-0123456789abcdefghijklmnopqrstuvwxyz"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn signatures_survive_rotation() {
+        let old = KeyRing::new("k1", [key("k1")]).unwrap();
+        let signature = old.sign(b"payload");
+        assert!(signature.starts_with("k1."));
+
+        // The new key is active, but signatures by the old key are still accepted
+        let rotated = KeyRing::new("k2", [key("k1"), key("k2")]).unwrap();
+        assert_eq!(rotated.verify(b"payload", &signature), Ok(()));
+        assert!(rotated.sign(b"payload").starts_with("k2."));
+
+        // Once the old key is removed, its signatures are rejected
+        let retired = KeyRing::new("k2", [key("k2")]).unwrap();
+        assert_eq!(
+            retired.verify(b"payload", &signature),
+            Err(SigningError::UnknownKey("k1".to_string()))
+        );
+    }
+
+    #[test]
+    fn tampered_signatures_are_rejected() {
+        let ring = KeyRing::new("k1", [key("k1")]).unwrap();
+        let signature = ring.sign(b"payload");
+
+        assert_eq!(
+            ring.verify(b"other payload", &signature),
+            Err(SigningError::Mismatch)
+        );
+        assert_eq!(
+            ring.verify(b"payload", "no-separator"),
+            Err(SigningError::Malformed)
+        );
+        assert_eq!(
+            ring.verify(b"payload", "k1.not base64!"),
+            Err(SigningError::Malformed)
+        );
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        assert!(SigningKey::new("k1", "short").is_err());
+        assert!(SigningKey::new("k.1", [0; MIN_SECRET_LEN]).is_err());
+        assert_eq!(
+            KeyRing::new("k2", [key("k1")]).unwrap_err(),
+            SigningError::MissingActiveKey("k2".to_string())
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Signatures of webhook payloads, in the [`SIGNATURE_HEADER`] header of each request.
+//!
+//! The header has the form `t=<unix seconds>,v1=<signature>`, where the signature is made by a
+//! [`KeyRing`] over `<unix seconds>.<body>`. Receivers holding the same keys verify requests with
+//! a [`WebhookVerifier`], which rejects requests signed too long ago and requests already seen, so
+//! that a captured request can't be replayed.
+
+use crate::signing::{KeyRing, SigningError};
+use std::{collections::HashMap, sync::Mutex};
+use time::{Duration, OffsetDateTime};
+
+pub const SIGNATURE_HEADER: &str = "X-Twittra-Signature";
+
+/// How far the signing time may be from the receiver's clock, allowing for clock skew and
+/// retries.
+pub const DEFAULT_TOLERANCE: Duration = Duration::minutes(5);
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum WebhookSignatureError {
+    #[error("malformed signature header")]
+    Malformed,
+
+    #[error("signed at {0}, which is outside the tolerance")]
+    Expired(i64),
+
+    #[error("the request was already received")]
+    Replayed,
+
+    #[error(transparent)]
+    Signature(#[from] SigningError),
+}
+
+/// Signs `body` sent at `timestamp`, returning the value of the [`SIGNATURE_HEADER`] header.
+pub fn sign(keys: &KeyRing, body: &[u8], timestamp: OffsetDateTime) -> String {
+    let timestamp = timestamp.unix_timestamp();
+    format!(
+        "t={timestamp},v1={}",
+        keys.sign(&signed_payload(timestamp, body))
+    )
+}
+
+fn signed_payload(timestamp: i64, body: &[u8]) -> Vec<u8> {
+    let mut payload = format!("{timestamp}.").into_bytes();
+    payload.extend_from_slice(body);
+    payload
+}
+
+/// Parses `t=<unix seconds>,v1=<signature>`. Unknown fields are ignored, so that other
+/// signature versions can be added alongside.
+fn parse_header(header: &str) -> Result<(i64, &str), WebhookSignatureError> {
+    let mut timestamp = None;
+    let mut signature = None;
+    for field in header.split(',') {
+        match field.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse().ok(),
+            Some(("v1", value)) => signature = Some(value),
+            _ => {}
+        }
+    }
+
+    timestamp
+        .zip(signature)
+        .ok_or(WebhookSignatureError::Malformed)
+}
+
+/// Verifies signed webhook requests, remembering the signatures it accepted for as long as they
+/// are within the tolerance.
+#[derive(Debug)]
+pub struct WebhookVerifier {
+    keys: KeyRing,
+    tolerance: Duration,
+    /// Accepted signatures and their timestamps.
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+impl WebhookVerifier {
+    pub fn new(keys: KeyRing) -> Self {
+        Self {
+            keys,
+            tolerance: DEFAULT_TOLERANCE,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Verifies the [`SIGNATURE_HEADER`] header of a request with `body` received now.
+    pub fn verify(&self, header: &str, body: &[u8]) -> Result<(), WebhookSignatureError> {
+        self.verify_at(header, body, OffsetDateTime::now_utc())
+    }
+
+    /// Verifies the [`SIGNATURE_HEADER`] header of a request with `body` received at `now`.
+    pub fn verify_at(
+        &self,
+        header: &str,
+        body: &[u8],
+        now: OffsetDateTime,
+    ) -> Result<(), WebhookSignatureError> {
+        let (timestamp, signature) = parse_header(header)?;
+        let now = now.unix_timestamp();
+        let tolerance = self.tolerance.whole_seconds();
+        if (now - timestamp).abs() > tolerance {
+            return Err(WebhookSignatureError::Expired(timestamp));
+        }
+        self.keys
+            .verify(&signed_payload(timestamp, body), signature)?;
+
+        let mut seen = self.seen.lock().unwrap();
+        // Older signatures are rejected as expired anyway
+        seen.retain(|_, &mut seen_at| now - seen_at <= tolerance);
+        if seen.insert(signature.to_string(), timestamp).is_some() {
+            return Err(WebhookSignatureError::Replayed);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::SigningKey;
+
+    fn keys() -> KeyRing {
+        let key = SigningKey::new("k1", "k1-secret-0123456789abcdefghijklmnopqrstuvwxyz").unwrap();
+        KeyRing::new("k1", [key]).unwrap()
+    }
+
+    #[test]
+    fn signed_requests_are_accepted_once() {
+        let now = OffsetDateTime::now_utc();
+        let header = sign(&keys(), b"{}", now);
+        let verifier = WebhookVerifier::new(keys());
+
+        assert_eq!(
+            verifier.verify_at(&header, b"{}", now + Duration::SECOND),
+            Ok(())
+        );
+        assert_eq!(
+            verifier.verify_at(&header, b"{}", now + Duration::SECOND),
+            Err(WebhookSignatureError::Replayed)
+        );
+    }
+
+    #[test]
+    fn tampered_and_expired_requests_are_rejected() {
+        let now = OffsetDateTime::now_utc();
+        let header = sign(&keys(), b"{}", now);
+        let verifier = WebhookVerifier::new(keys());
+
+        assert_eq!(
+            verifier.verify_at(&header, b"{\"a\":1}", now),
+            Err(WebhookSignatureError::Signature(SigningError::Mismatch))
+        );
+        assert_eq!(
+            verifier.verify_at(&header, b"{}", now + DEFAULT_TOLERANCE + Duration::SECOND),
+            Err(WebhookSignatureError::Expired(now.unix_timestamp()))
+        );
+        // Re-signing with another timestamp requires the key
+        let forged = header.replacen(
+            &format!("t={}", now.unix_timestamp()),
+            &format!("t={}", now.unix_timestamp() + 60),
+            1,
+        );
+        assert_eq!(
+            verifier.verify_at(&forged, b"{}", now),
+            Err(WebhookSignatureError::Signature(SigningError::Mismatch))
+        );
+        assert_eq!(
+            verifier.verify_at("v1=k1.abc", b"{}", now),
+            Err(WebhookSignatureError::Malformed)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use domain::traq_budget::{LocalTokenBucket, TokenBucket};
+use redis::{Client, RedisError, Script, aio::ConnectionManager};
+use std::{
+    fmt::{self, Debug, Formatter},
+    time::Duration,
+};
+
+const KEY: &str = "twittra:traq_budget";
+
+/// Refills and takes a token atomically, returning how many seconds to wait if there aren't
+/// enough. The wait is returned as a string, since Redis truncates Lua numbers to integers.
+const TAKE_SCRIPT: &str = r#"
+local rate = tonumber(ARGV[1])
+local capacity = tonumber(ARGV[2])
+local needed = tonumber(ARGV[3]) + 1
+local time = redis.call('TIME')
+local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(state[1]) or capacity
+local updated_at = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * rate)
+
+local wait = 0
+if tokens >= needed then
+    tokens = tokens - 1
+else
+    wait = (needed - tokens) / rate
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', tostring(now))
+-- A full bucket is the same as a missing one
+redis.call('EXPIRE', KEYS[1], math.ceil(capacity / rate) + 1)
+return tostring(wait)
+"#;
+
+/// A token bucket kept in Redis, so that every process calling traQ shares the same budget.
+///
+/// While Redis is unreachable, tokens are taken from a bucket of this process instead, so that
+/// traQ can still be called at the rate of one process.
+pub struct RedisTokenBucket {
+    connection: ConnectionManager,
+    script: Script,
+    rate_per_second: f64,
+    capacity: f64,
+    fallback: LocalTokenBucket,
+}
+
+impl RedisTokenBucket {
+    pub async fn connect(
+        url: &str,
+        rate_per_second: f64,
+        capacity: f64,
+    ) -> Result<Self, RedisError> {
+        let connection = Client::open(url)?.get_connection_manager().await?;
+
+        Ok(Self {
+            connection,
+            script: Script::new(TAKE_SCRIPT),
+            rate_per_second,
+            capacity,
+            fallback: LocalTokenBucket::new(rate_per_second, capacity),
+        })
+    }
+}
+
+impl Debug for RedisTokenBucket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisTokenBucket")
+            .field("rate_per_second", &self.rate_per_second)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenBucket for RedisTokenBucket {
+    async fn try_take(&self, reserve: f64) -> Result<(), Duration> {
+        let result: Result<String, RedisError> = self
+            .script
+            .key(KEY)
+            .arg(self.rate_per_second)
+            .arg(self.capacity)
+            .arg(reserve)
+            .invoke_async(&mut self.connection.clone())
+            .await;
+
+        match result.map(|wait| wait.parse::<f64>()) {
+            Ok(Ok(wait)) if wait <= 0.0 => Ok(()),
+            Ok(Ok(wait)) => Err(Duration::from_secs_f64(wait)),
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to parse the wait for the traQ budget: {:?}", e);
+                self.fallback.try_take(reserve).await
+            }
+            Err(e) => {
+                tracing::warn!("Failed to take from the traQ budget in Redis: {:?}", e);
+                self.fallback.try_take(reserve).await
+            }
+        }
+    }
+}
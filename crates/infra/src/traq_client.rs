@@ -1,12 +1,15 @@
 use domain::{
     error::TraqClientError,
-    model::{Message, Stamp, User},
+    id::{MessageId, StampId},
+    model::{Channel, Message, Stamp, User},
     traq_client::TraqClient,
 };
 use time::{OffsetDateTime, error::Parse, format_description::well_known::Rfc3339};
 use traq::{
-    apis::{configuration::Configuration, message_api, stamp_api, user_api},
-    models::PostMessageStampRequest,
+    apis::{
+        channel_api, configuration::Configuration, message_api, public_api, stamp_api, user_api,
+    },
+    models::{PostMessageRequest, PostMessageStampRequest},
 };
 use uuid::Uuid;
 
@@ -23,6 +26,16 @@ impl TraqClientImpl {
 
 #[async_trait::async_trait]
 impl TraqClient for TraqClientImpl {
+    async fn get_server_version(&self) -> Result<String, TraqClientError> {
+        let config = Configuration {
+            base_path: self.base_url.clone(),
+            ..Default::default()
+        };
+        let version = public_api::get_server_version(&config).await?;
+
+        Ok(version.version)
+    }
+
     async fn fetch_messages_since(
         &self,
         token: &str,
@@ -67,6 +80,17 @@ impl TraqClient for TraqClientImpl {
         Ok(messages)
     }
 
+    async fn get_channels(&self, token: &str) -> Result<Vec<Channel>, TraqClientError> {
+        let config = Configuration {
+            base_path: self.base_url.clone(),
+            oauth_access_token: Some(token.to_string()),
+            ..Default::default()
+        };
+        let channel_list = channel_api::get_channels(&config, Some(false), None).await?;
+
+        Ok(Channel::from_traq_tree(channel_list.public))
+    }
+
     async fn get_stamp(&self, token: &str, stamp_id: &Uuid) -> Result<Stamp, TraqClientError> {
         let config = Configuration {
             base_path: self.base_url.clone(),
@@ -156,8 +180,8 @@ impl TraqClient for TraqClientImpl {
     async fn add_message_stamp(
         &self,
         token: &str,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
+        message_id: &MessageId,
+        stamp_id: &StampId,
         count: i32,
     ) -> Result<(), TraqClientError> {
         let config = Configuration {
@@ -180,8 +204,8 @@ impl TraqClient for TraqClientImpl {
     async fn remove_message_stamp(
         &self,
         token: &str,
-        message_id: &Uuid,
-        stamp_id: &Uuid,
+        message_id: &MessageId,
+        stamp_id: &StampId,
     ) -> Result<(), TraqClientError> {
         let config = Configuration {
             base_path: self.base_url.clone(),
@@ -211,6 +235,60 @@ impl TraqClient for TraqClientImpl {
 
         Ok(message)
     }
+
+    async fn get_channel_messages(
+        &self,
+        token: &str,
+        channel_id: &Uuid,
+        before: Option<OffsetDateTime>,
+        limit: i32,
+    ) -> Result<Vec<Message>, TraqClientError> {
+        let config = Configuration {
+            base_path: self.base_url.clone(),
+            oauth_access_token: Some(token.to_string()),
+            ..Default::default()
+        };
+        let until = before
+            .map(|before| before.format(&Rfc3339))
+            .transpose()
+            .map_err(|e| TraqClientError::ResponseParse(e.to_string()))?;
+        let messages = message_api::get_messages(
+            &config,
+            &channel_id.to_string(),
+            Some(limit),
+            None,
+            None,
+            until,
+            Some(false),
+            Some("desc"),
+        )
+        .await?;
+        let messages = messages
+            .into_iter()
+            .map(|msg| msg.try_into())
+            .collect::<Result<Vec<Message>, _>>()
+            .map_err(|e: Parse| TraqClientError::ResponseParse(e.to_string()))?;
+
+        Ok(messages)
+    }
+
+    async fn post_message(
+        &self,
+        token: &str,
+        channel_id: &Uuid,
+        content: &str,
+    ) -> Result<(), TraqClientError> {
+        let config = Configuration {
+            base_path: self.base_url.clone(),
+            oauth_access_token: Some(token.to_string()),
+            ..Default::default()
+        };
+        let post_message_request = PostMessageRequest::new(content.to_string());
+        message_api::post_message(&config, &channel_id.to_string(), Some(post_message_request))
+            .await?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -468,6 +546,18 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_get_server_version() {
+        let env = TraqTestEnvironment::start().await;
+
+        let client = TraqClientImpl::new(env.base_url().to_string());
+        let result = client.get_server_version().await;
+
+        assert!(!result.unwrap().is_empty());
+
+        env.cleanup().await;
+    }
+
     #[tokio::test]
     async fn test_get_user_success() {
         let env = TraqTestEnvironment::start().await;
@@ -0,0 +1,70 @@
+use crate::signing::{
+    KeyRing, SigningKey,
+    webhook::{self, SIGNATURE_HEADER},
+};
+use domain::{
+    error::WebhookError,
+    model::Webhook,
+    webhook::{WebhookPayload, WebhookSender},
+};
+use http::header::CONTENT_TYPE;
+use reqwest::Client;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const USER_AGENT: &str = concat!("Twittra/", env!("CARGO_PKG_VERSION"), " (webhook)");
+
+/// Posts payloads as JSON, signed as described in [`crate::signing::webhook`] with the webhook's
+/// secret under a key named after the webhook's ID.
+#[derive(Clone, Debug)]
+pub struct WebhookSenderImpl {
+    client: Client,
+}
+
+impl WebhookSenderImpl {
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("the webhook client has a valid configuration");
+
+        Self { client }
+    }
+}
+
+impl Default for WebhookSenderImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookSender for WebhookSenderImpl {
+    async fn send(
+        &self,
+        webhook: &Webhook,
+        secret: &str,
+        payload: &WebhookPayload,
+    ) -> Result<(), WebhookError> {
+        let key_id = webhook.id.to_string();
+        let keys = SigningKey::new(key_id.clone(), secret)
+            .and_then(|key| KeyRing::new(key_id, [key]))
+            .map_err(|e| WebhookError::InvalidSecret(e.to_string()))?;
+        let body = serde_json::to_vec(payload).map_err(|e| WebhookError::Request(e.to_string()))?;
+        let signature = webhook::sign(&keys, &body, OffsetDateTime::now_utc());
+
+        self.client
+            .post(&webhook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| WebhookError::Request(e.to_string()))?;
+
+        Ok(())
+    }
+}